@@ -14,10 +14,9 @@
 
 use std::rc::Rc;
 
-extern crate arrow;
 extern crate datafusion;
 
-use arrow::datatypes::*;
+use datafusion::datatypes::*;
 use datafusion::exec::*;
 use datafusion::functions::geospatial::st_astext::*;
 use datafusion::functions::geospatial::st_point::*;
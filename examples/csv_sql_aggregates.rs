@@ -14,10 +14,9 @@
 
 use std::fs::File;
 
-extern crate arrow;
 extern crate datafusion;
 
-use arrow::datatypes::*;
+use datafusion::datatypes::*;
 use datafusion::exec::*;
 
 fn main() {
@@ -12,10 +12,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-extern crate arrow;
 extern crate datafusion;
 
-use arrow::datatypes::*;
+use datafusion::datatypes::*;
 use datafusion::exec::*;
 
 fn main() {
@@ -12,7 +12,6 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-extern crate arrow;
 extern crate datafusion;
 
 use datafusion::exec::*;
@@ -0,0 +1,197 @@
+// Copyright 2018 Grove Enterprises LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Integer and floating point arithmetic with defined overflow and division by zero behavior.
+//!
+//! By default integer overflow and integer division or modulo by zero are errors. In wrapping
+//! mode results wrap around and division or modulo by zero is null. Floating point arithmetic
+//! follows IEEE 754 in both modes, so it never fails.
+
+use std::fmt;
+use std::result;
+
+/// How arithmetic operators and `SUM` handle integer overflow and division by zero
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OverflowMode {
+    /// Overflow and division by zero are errors
+    Checked,
+    /// Results wrap around and division by zero is null
+    Wrapping,
+}
+
+impl Default for OverflowMode {
+    fn default() -> Self {
+        OverflowMode::Checked
+    }
+}
+
+/// An arithmetic operation on two integers or two floating point numbers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArithmeticOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulus,
+}
+
+impl fmt::Display for ArithmeticOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let op = match self {
+            ArithmeticOperator::Add => "+",
+            ArithmeticOperator::Subtract => "-",
+            ArithmeticOperator::Multiply => "*",
+            ArithmeticOperator::Divide => "/",
+            ArithmeticOperator::Modulus => "%",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+/// A primitive type that arithmetic operators can be applied to
+pub trait Arithmetic: Copy + Default {
+    /// Apply an operator, returning `Ok(None)` if the result is null or an error message if it
+    /// cannot be computed
+    fn apply(
+        op: ArithmeticOperator,
+        a: Self,
+        b: Self,
+        mode: OverflowMode,
+    ) -> result::Result<Option<Self>, String>;
+}
+
+macro_rules! integer_arithmetic {
+    ($($TY:ty),*) => {
+        $(
+            impl Arithmetic for $TY {
+                fn apply(
+                    op: ArithmeticOperator,
+                    a: Self,
+                    b: Self,
+                    mode: OverflowMode,
+                ) -> result::Result<Option<Self>, String> {
+                    use self::ArithmeticOperator::*;
+                    if b == 0 && (op == Divide || op == Modulus) {
+                        return match mode {
+                            OverflowMode::Checked => Err("Division by zero".to_string()),
+                            OverflowMode::Wrapping => Ok(None),
+                        };
+                    }
+                    let result = match mode {
+                        OverflowMode::Checked => match op {
+                            Add => a.checked_add(b),
+                            Subtract => a.checked_sub(b),
+                            Multiply => a.checked_mul(b),
+                            // MIN / -1 overflows
+                            Divide => a.checked_div(b),
+                            Modulus => a.checked_rem(b),
+                        },
+                        OverflowMode::Wrapping => Some(match op {
+                            Add => a.wrapping_add(b),
+                            Subtract => a.wrapping_sub(b),
+                            Multiply => a.wrapping_mul(b),
+                            Divide => a.wrapping_div(b),
+                            Modulus => a.wrapping_rem(b),
+                        }),
+                    };
+                    match result {
+                        Some(v) => Ok(Some(v)),
+                        None => Err(format!(
+                            "Integer overflow: {} {} {} does not fit in {}",
+                            a,
+                            op,
+                            b,
+                            stringify!($TY)
+                        )),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+integer_arithmetic!(i8, i16, i32, i64, u8, u16, u32, u64);
+
+macro_rules! float_arithmetic {
+    ($($TY:ty),*) => {
+        $(
+            impl Arithmetic for $TY {
+                fn apply(
+                    op: ArithmeticOperator,
+                    a: Self,
+                    b: Self,
+                    _mode: OverflowMode,
+                ) -> result::Result<Option<Self>, String> {
+                    Ok(Some(match op {
+                        ArithmeticOperator::Add => a + b,
+                        ArithmeticOperator::Subtract => a - b,
+                        ArithmeticOperator::Multiply => a * b,
+                        ArithmeticOperator::Divide => a / b,
+                        ArithmeticOperator::Modulus => a % b,
+                    }))
+                }
+            }
+        )*
+    };
+}
+
+float_arithmetic!(f32, f64);
+
+#[cfg(test)]
+mod tests {
+    use super::ArithmeticOperator::*;
+    use super::OverflowMode::*;
+    use super::*;
+
+    #[test]
+    fn checked_arithmetic() {
+        assert_eq!(Ok(Some(255)), u8::apply(Add, 200, 55, Checked));
+        assert_eq!(
+            Err("Integer overflow: 200 + 56 does not fit in u8".to_string()),
+            u8::apply(Add, 200, 56, Checked)
+        );
+        assert!(u32::apply(Subtract, 1, 2, Checked).is_err());
+        assert!(i64::apply(Multiply, i64::max_value(), 2, Checked).is_err());
+        assert!(i32::apply(Divide, i32::min_value(), -1, Checked).is_err());
+        assert_eq!(Ok(Some(-3)), i16::apply(Divide, -7, 2, Checked));
+        assert_eq!(Ok(Some(-1)), i16::apply(Modulus, -7, 2, Checked));
+    }
+
+    #[test]
+    fn wrapping_arithmetic() {
+        assert_eq!(Ok(Some(0)), u8::apply(Add, 200, 56, Wrapping));
+        assert_eq!(Ok(Some(u32::max_value())), u32::apply(Subtract, 1, 2, Wrapping));
+        assert_eq!(Ok(Some(-128)), i8::apply(Add, 127, 1, Wrapping));
+        assert_eq!(
+            Ok(Some(i32::min_value())),
+            i32::apply(Divide, i32::min_value(), -1, Wrapping)
+        );
+    }
+
+    #[test]
+    fn division_by_zero() {
+        assert_eq!(
+            Err("Division by zero".to_string()),
+            i32::apply(Divide, 1, 0, Checked)
+        );
+        assert!(u64::apply(Modulus, 1, 0, Checked).is_err());
+        assert_eq!(Ok(None), i32::apply(Divide, 1, 0, Wrapping));
+        assert_eq!(Ok(None), u8::apply(Modulus, 1, 0, Wrapping));
+        // floating point division follows IEEE 754
+        assert_eq!(
+            Ok(Some(::std::f64::INFINITY)),
+            f64::apply(Divide, 1.0, 0.0, Checked)
+        );
+    }
+}
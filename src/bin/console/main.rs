@@ -16,8 +16,7 @@ extern crate clap;
 extern crate datafusion;
 
 use std::fs::File;
-use std::io::BufRead;
-use std::io::BufReader;
+use std::io::Read;
 use std::rc::Rc;
 use std::str;
 use std::time::Instant;
@@ -27,7 +26,8 @@ use datafusion::exec::*;
 use datafusion::functions::geospatial::st_astext::*;
 use datafusion::functions::geospatial::st_point::*;
 use datafusion::functions::math::*;
-use datafusion::sqlast::ASTNode::SQLCreateTable;
+use datafusion::sqlast::ASTNode;
+use datafusion::sqlast::ASTNode::{SQLAnalyze, SQLCreateTable, SQLExplain};
 use datafusion::sqlparser::*;
 
 mod linereader;
@@ -41,28 +41,13 @@ fn setup_console(cmdline: clap::ArgMatches) {
     let mut console = Console::new(/*etcd_endpoints.to_string()*/);
 
     match cmdline.value_of("SCRIPT") {
-        Some(filename) => match File::open(filename) {
-            Ok(f) => {
-                let mut cmd_buffer = String::new();
-                let mut reader = BufReader::new(&f);
-                for line in reader.lines() {
-                    match line {
-                        Ok(cmd) => {
-                            cmd_buffer.push_str(&cmd);
-                            if cmd_buffer.as_str().ends_with(";") {
-                                console.execute(&cmd_buffer[0..cmd_buffer.len() - 2]);
-                                cmd_buffer = String::new();
-                            }
-                        }
-                        Err(e) => println!("Error: {}", e),
-                    }
-                }
-                if cmd_buffer.as_str().ends_with(";") {
-                    console.execute(&cmd_buffer[0..cmd_buffer.len() - 2]);
-                }
+        Some(filename) => {
+            let mut script = String::new();
+            match File::open(filename).and_then(|mut f| f.read_to_string(&mut script)) {
+                Ok(_) => console.execute_script(&script, !cmdline.is_present("CONTINUE")),
+                Err(e) => println!("Could not open file {}: {}", filename, e),
             }
-            Err(e) => println!("Could not open file {}: {}", filename, e),
-        },
+        }
         _ => {
             let mut reader = linereader::LineReader::new();
             loop {
@@ -109,6 +94,12 @@ fn main() {
                     .required(false)
                     .takes_value(true),
             )
+            .arg(
+                Arg::with_name("CONTINUE")
+                    .help("Keep running the statements in a script after a statement fails")
+                    .long("continue-on-error")
+                    .required(false),
+            )
             .get_matches();
     setup_console(cmdline);
 }
@@ -128,56 +119,126 @@ impl Console {
         Console { ctx }
     }
 
+    /// Execute the statements in a SQL script in order, as if each one was entered at the
+    /// console. A statement with a syntax error is reported and counts as a failed statement.
+    fn execute_script(&mut self, script: &str, stop_on_error: bool) {
+        match Parser::parse_each_statement(String::from(script)) {
+            Ok(statements) => for statement in statements {
+                let succeeded = match statement {
+                    Ok(statement) => {
+                        let sql = statement.to_string();
+                        self.execute_statement(statement, &sql)
+                    }
+                    Err(e) => {
+                        println!("Error: {}", e.render(script));
+                        false
+                    }
+                };
+                if !succeeded && stop_on_error {
+                    break;
+                }
+            },
+            Err(e) => println!("Error: {}", e.render(script)),
+        }
+    }
+
     /// Execute a SQL statement or console command
     fn execute(&mut self, sql: &str) {
+        // parse the SQL
+        match Parser::parse_sql(String::from(sql)) {
+            Ok(ast) => {
+                self.execute_statement(ast, sql);
+            }
+            Err(e) => println!("Error: {}", e.render(sql)),
+        }
+    }
+
+    /// Execute a parsed SQL statement, printing the outcome. Returns whether the statement
+    /// succeeded.
+    fn execute_statement(&mut self, ast: ASTNode, sql: &str) -> bool {
         println!("Executing query ...");
 
         let timer = Instant::now();
 
-        // parse the SQL
-        match Parser::parse_sql(String::from(sql)) {
-            Ok(ast) => match ast {
-                SQLCreateTable { .. } => {
-                    self.ctx.sql(&sql).unwrap();
-                    //println!("Registered schema with execution context");
-                    ()
+        match ast {
+            SQLCreateTable { .. } => match self.ctx.sql(&sql) {
+                //println!("Registered schema with execution context");
+                Ok(_) => true,
+                Err(e) => {
+                    println!("Error: {}", e);
+                    false
+                }
+            },
+            SQLAnalyze { ref name } => {
+                match self
+                    .ctx
+                    .resolve_table_name(name)
+                    .and_then(|name| self.ctx.analyze_table(&name))
+                {
+                    Ok(stats) => {
+                        println!("Table {} has {:?} rows", name, stats.row_count);
+                        true
+                    }
+                    Err(e) => {
+                        println!("Error: {}", e);
+                        false
+                    }
                 }
-                _ => match self.ctx.create_logical_plan(sql) {
-                    Ok(logical_plan) => {
-                        let physical_plan = PhysicalPlan::Interactive {
-                            plan: logical_plan.clone(),
-                        };
-
-                        let result = self.ctx.execute(&physical_plan);
-
-                        match result {
-                            Ok(result) => {
-                                let elapsed = timer.elapsed();
-                                let elapsed_seconds = elapsed.as_secs() as f64
-                                    + elapsed.subsec_nanos() as f64 / 1000000000.0;
-
-                                match result {
-                                    ExecutionResult::Unit => {
-                                        println!("Query executed in {} seconds", elapsed_seconds);
-                                    }
-                                    ExecutionResult::Count(n) => {
-                                        println!(
-                                            "Query executed in {} seconds and updated {} rows",
-                                            elapsed_seconds, n
-                                        );
-                                    }
-                                    ExecutionResult::Str(_) => {
-                                        println!("Query executed in {} seconds", elapsed_seconds);
-                                    }
+            }
+            SQLExplain { .. } => match self.ctx.sql(&sql) {
+                Ok(df) => match self.ctx.show(df.as_ref(), usize::max_value()) {
+                    Ok(_) => true,
+                    Err(e) => {
+                        println!("Error: {}", e);
+                        false
+                    }
+                },
+                Err(e) => {
+                    println!("Error: {}", e);
+                    false
+                }
+            },
+            _ => match self.ctx.create_logical_plan(sql) {
+                Ok(logical_plan) => {
+                    let physical_plan = PhysicalPlan::Interactive {
+                        plan: logical_plan.clone(),
+                    };
+
+                    let result = self.ctx.execute(&physical_plan);
+
+                    match result {
+                        Ok(result) => {
+                            let elapsed = timer.elapsed();
+                            let elapsed_seconds = elapsed.as_secs() as f64
+                                + elapsed.subsec_nanos() as f64 / 1000000000.0;
+
+                            match result {
+                                ExecutionResult::Unit => {
+                                    println!("Query executed in {} seconds", elapsed_seconds);
+                                }
+                                ExecutionResult::Count(n) => {
+                                    println!(
+                                        "Query executed in {} seconds and updated {} rows",
+                                        elapsed_seconds, n
+                                    );
+                                }
+                                ExecutionResult::Str(_) => {
+                                    println!("Query executed in {} seconds", elapsed_seconds);
                                 }
                             }
-                            Err(e) => println!("Error: {:?}", e),
+                            true
+                        }
+                        Err(e) => {
+                            println!("Error: {}", e);
+                            false
                         }
                     }
-                    Err(e) => println!("Error: {:?}", e),
-                },
+                }
+                Err(e) => {
+                    println!("Error: {}", e);
+                    false
+                }
             },
-            Err(e) => println!("Error: {:?}", e),
         }
     }
 }
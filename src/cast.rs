@@ -0,0 +1,636 @@
+// Copyright 2018 Grove Enterprises LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Conversions between data types for `CAST` and `TRY_CAST`.
+//!
+//! A `CAST` fails on the first value that cannot be converted, such as a string that is not a
+//! number or a number that does not fit in the target type. A `TRY_CAST` (a "safe" cast)
+//! returns null for those values instead.
+
+use std::fmt;
+use std::rc::Rc;
+use std::result;
+use std::str;
+
+use arrow::array::{ListArray, PrimitiveArray};
+
+use super::datasources::common::get_value;
+use super::datatypes::*;
+use super::decimal::{self, ToDecimal};
+use super::errors::*;
+use super::temporal;
+use super::types::*;
+
+/// A number read from a boolean, integer, floating point or decimal value. Booleans are read
+/// as 0 or 1.
+#[derive(Debug, Clone, Copy)]
+enum Number {
+    Int(i128),
+    Float(f64),
+    /// A decimal value multiplied by 10^scale, with the scale
+    Decimal(i128, usize),
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Number::Int(v) => write!(f, "{}", v),
+            Number::Float(v) => write!(f, "{}", v),
+            Number::Decimal(v, scale) => write!(f, "{}", decimal::format_decimal(*v, *scale)),
+        }
+    }
+}
+
+/// Read the number at the given row, or `None` if the array does not hold numbers
+fn number_at(data: &ArrayData, i: usize) -> Option<Number> {
+    match data {
+        ArrayData::Boolean(ref a) => Some(Number::Int(if *a.get(i) { 1 } else { 0 })),
+        ArrayData::Int8(ref a) => Some(Number::Int(*a.get(i) as i128)),
+        ArrayData::Int16(ref a) => Some(Number::Int(*a.get(i) as i128)),
+        ArrayData::Int32(ref a) => Some(Number::Int(*a.get(i) as i128)),
+        ArrayData::Int64(ref a) => Some(Number::Int(*a.get(i) as i128)),
+        ArrayData::UInt8(ref a) => Some(Number::Int(*a.get(i) as i128)),
+        ArrayData::UInt16(ref a) => Some(Number::Int(*a.get(i) as i128)),
+        ArrayData::UInt32(ref a) => Some(Number::Int(*a.get(i) as i128)),
+        ArrayData::UInt64(ref a) => Some(Number::Int(*a.get(i) as i128)),
+        ArrayData::Float32(ref a) => Some(Number::Float(*a.get(i) as f64)),
+        ArrayData::Float64(ref a) => Some(Number::Float(*a.get(i))),
+        ArrayData::Decimal(ref v, _, scale) => Some(Number::Decimal(v[i], *scale)),
+        _ => None,
+    }
+}
+
+/// A primitive type that numbers and strings can be cast to
+trait CastTarget: Sized + Default {
+    /// Convert a number, or return `None` if it is out of range
+    fn from_number(n: Number) -> Option<Self>;
+    /// Parse a string, or return `None` if it is not a valid value
+    fn parse(s: &str) -> Option<Self>;
+}
+
+/// Integer casts discard the fractional digits of floating point numbers and decimals
+macro_rules! integer_cast_target {
+    ($($TY:ty),*) => {
+        $(
+            impl CastTarget for $TY {
+                fn from_number(n: Number) -> Option<Self> {
+                    let v = match n {
+                        Number::Int(v) => v,
+                        Number::Float(v) => {
+                            let v = v.trunc();
+                            // NaN fails both comparisons
+                            if !(v >= <$TY>::min_value() as f64
+                                && v < <$TY>::max_value() as f64 + 1.0)
+                            {
+                                return None;
+                            }
+                            v as i128
+                        }
+                        Number::Decimal(v, scale) => decimal::to_i128(v, scale),
+                    };
+                    if v >= <$TY>::min_value() as i128 && v <= <$TY>::max_value() as i128 {
+                        Some(v as $TY)
+                    } else {
+                        None
+                    }
+                }
+
+                fn parse(s: &str) -> Option<Self> {
+                    s.trim().parse().ok()
+                }
+            }
+        )*
+    };
+}
+
+integer_cast_target!(i8, i16, i32, i64, u8, u16, u32, u64);
+
+impl CastTarget for f64 {
+    fn from_number(n: Number) -> Option<Self> {
+        Some(match n {
+            Number::Int(v) => v as f64,
+            Number::Float(v) => v,
+            Number::Decimal(v, scale) => decimal::to_f64(v, scale),
+        })
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        s.trim().parse().ok()
+    }
+}
+
+impl CastTarget for f32 {
+    fn from_number(n: Number) -> Option<Self> {
+        let v = f64::from_number(n)?;
+        // infinity and NaN are kept, but finite values must not become infinite
+        if v.is_finite() && v.abs() > ::std::f32::MAX as f64 {
+            None
+        } else {
+            Some(v as f32)
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        s.trim().parse().ok()
+    }
+}
+
+impl CastTarget for bool {
+    fn from_number(n: Number) -> Option<Self> {
+        Some(match n {
+            Number::Int(v) => v != 0,
+            Number::Float(v) => v != 0.0,
+            Number::Decimal(v, _) => v != 0,
+        })
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_ref() {
+            "true" | "t" | "yes" | "y" | "on" | "1" => Some(true),
+            "false" | "f" | "no" | "n" | "off" | "0" => Some(false),
+            _ => None,
+        }
+    }
+}
+
+fn is_numeric(t: &DataType) -> bool {
+    match t {
+        DataType::Boolean
+        | DataType::Int8
+        | DataType::Int16
+        | DataType::Int32
+        | DataType::Int64
+        | DataType::UInt8
+        | DataType::UInt16
+        | DataType::UInt32
+        | DataType::UInt64
+        | DataType::Float32
+        | DataType::Float64
+        | DataType::Decimal(_, _) => true,
+        _ => false,
+    }
+}
+
+/// Whether values of one type can be cast to another. This does not mean that every value can
+/// be converted, e.g. casting a string to a number fails if the string is not a number.
+pub fn can_cast(from: &DataType, to: &DataType) -> bool {
+    if from == to {
+        return true;
+    }
+    match (from, to) {
+        (DataType::Struct(_), _) | (_, DataType::Struct(_)) => false,
+        (_, DataType::Utf8) => true,
+        (DataType::Utf8, _) => is_numeric(to) || to.is_temporal(),
+        (DataType::Date32, DataType::Timestamp(_))
+        | (DataType::Timestamp(_), DataType::Date32)
+        | (DataType::Time64(_), DataType::Time64(_))
+        | (DataType::Timestamp(_), DataType::Timestamp(_)) => true,
+        _ => is_numeric(from) && is_numeric(to),
+    }
+}
+
+fn unsupported_cast(from: &DataType, to: &DataType) -> ExecutionError {
+    ExecutionError::type_mismatch(format!("CAST from {:?} to {:?} is not supported", from, to))
+}
+
+/// Cast each valid row of a column with `f`, which is given the row index. Rows that cannot be
+/// cast become null if `safe` is true and otherwise fail the cast. Returns the cast values and
+/// the validity of each row.
+fn cast_rows<T, F>(array: &Array, safe: bool, f: F) -> Result<(Vec<T>, Vec<bool>)>
+where
+    T: Default,
+    F: Fn(usize) -> result::Result<T, String>,
+{
+    let mut values: Vec<T> = Vec::with_capacity(array.len());
+    let mut validity: Vec<bool> = Vec::with_capacity(array.len());
+    for i in 0..array.len() {
+        if !array.is_valid(i) {
+            values.push(T::default());
+            validity.push(false);
+            continue;
+        }
+        match f(i) {
+            Ok(v) => {
+                values.push(v);
+                validity.push(true);
+            }
+            Err(_) if safe => {
+                values.push(T::default());
+                validity.push(false);
+            }
+            Err(message) => {
+                return Err(ExecutionError::DataError {
+                    message,
+                    file: None,
+                    row: Some(i),
+                    column: None,
+                })
+            }
+        }
+    }
+    Ok((values, validity))
+}
+
+fn utf8_at(list: &ListArray<u8>, i: usize) -> result::Result<&str, String> {
+    str::from_utf8(list.get(i)).map_err(|e| format!("Invalid Utf8 value: {}", e))
+}
+
+/// Parse the string at the given row with a parser from the `temporal` or `decimal` modules
+fn parse_utf8<T, F>(
+    list: &ListArray<u8>,
+    i: usize,
+    to: &DataType,
+    parse: F,
+) -> result::Result<T, String>
+where
+    F: Fn(&str) -> result::Result<T, String>,
+{
+    let s = utf8_at(list, i)?;
+    parse(s).map_err(|e| format!("Cannot cast Utf8 value '{}' to {:?}: {}", s, to, e))
+}
+
+/// Cast a boolean, numeric or string column to a boolean or numeric type
+fn primitive_cast<T>(array: &Array, to: &DataType, safe: bool) -> Result<Array>
+where
+    T: CastTarget,
+    Array: From<Vec<T>>,
+{
+    let data = array.data();
+    let (values, validity) = match data {
+        ArrayData::Utf8(ref list) => cast_rows(array, safe, |i| {
+            let s = utf8_at(list, i)?;
+            T::parse(s).ok_or_else(|| format!("Cannot cast Utf8 value '{}' to {:?}", s, to))
+        })?,
+        _ => cast_rows(array, safe, |i| match number_at(data, i) {
+            Some(n) => T::from_number(n)
+                .ok_or_else(|| format!("Cannot cast {} to {:?}: value is out of range", n, to)),
+            None => Err(format!("Cannot cast {:?} to {:?}", data.data_type(), to)),
+        })?,
+    };
+    Ok(Array::with_validity(Array::from(values).into_data(), &validity))
+}
+
+/// Cast a boolean, numeric or string column to a decimal type. Digits beyond the scale are
+/// rounded half away from zero.
+fn decimal_cast(array: &Array, precision: usize, scale: usize, safe: bool) -> Result<Array> {
+    let to = DataType::Decimal(precision, scale);
+    let data = array.data();
+    let (values, validity) = match data {
+        ArrayData::Utf8(ref list) => cast_rows(array, safe, |i| {
+            parse_utf8(list, i, &to, |s| decimal::parse_decimal(s, precision, scale))
+        })?,
+        _ => cast_rows(array, safe, |i| {
+            let rescale = |v: i128, from: usize| {
+                decimal::rescale(v, from, scale)
+                    .ok_or_else(|| "value is out of range".to_string())
+                    .and_then(|d| decimal::check_precision(d, precision, scale))
+            };
+            let d = match number_at(data, i) {
+                Some(Number::Int(v)) => rescale(v, 0),
+                Some(Number::Float(v)) => v.to_decimal(precision, scale),
+                Some(Number::Decimal(v, from)) => rescale(v, from),
+                None => return Err(format!("Cannot cast {:?} to {:?}", data.data_type(), to)),
+            };
+            d.map_err(|e| format!("Cannot cast to {:?}: {}", to, e))
+        })?,
+    };
+    Ok(Array::with_validity(
+        ArrayData::Decimal(values, precision, scale),
+        &validity,
+    ))
+}
+
+/// Cast a string, date, time or timestamp column to a date, time or timestamp type
+fn temporal_cast(array: &Array, to: &DataType, safe: bool) -> Result<Array> {
+    let unsupported = || unsupported_cast(&array.data().data_type(), to);
+    let out_of_range =
+        |v: i64| format!("Cannot cast {} to {:?}: value is out of range", v, to);
+    match (array.data(), to) {
+        (ArrayData::Utf8(ref list), DataType::Date32) => {
+            let (values, validity) =
+                cast_rows(array, safe, |i| parse_utf8(list, i, to, temporal::parse_date))?;
+            let data = ArrayData::Date32(PrimitiveArray::from(values));
+            Ok(Array::with_validity(data, &validity))
+        }
+        (ArrayData::Timestamp(ref list, unit), DataType::Date32) => {
+            let (values, validity) = cast_rows(array, safe, |i| {
+                let v = *list.get(i);
+                temporal::timestamp_to_date(v, *unit).ok_or_else(|| out_of_range(v))
+            })?;
+            let data = ArrayData::Date32(PrimitiveArray::from(values));
+            Ok(Array::with_validity(data, &validity))
+        }
+        (_, DataType::Time64(to_unit)) => {
+            let (values, validity) = match array.data() {
+                ArrayData::Utf8(ref list) => cast_rows(array, safe, |i| {
+                    parse_utf8(list, i, to, |s| temporal::parse_time(s, *to_unit))
+                })?,
+                ArrayData::Time64(ref list, from) => cast_rows(array, safe, |i| {
+                    let v = *list.get(i);
+                    temporal::convert_time_unit(v, *from, *to_unit).ok_or_else(|| out_of_range(v))
+                })?,
+                _ => return Err(unsupported()),
+            };
+            let data = ArrayData::Time64(PrimitiveArray::from(values), *to_unit);
+            Ok(Array::with_validity(data, &validity))
+        }
+        (_, DataType::Timestamp(to_unit)) => {
+            let (values, validity) = match array.data() {
+                ArrayData::Utf8(ref list) => cast_rows(array, safe, |i| {
+                    parse_utf8(list, i, to, |s| temporal::parse_timestamp(s, *to_unit))
+                })?,
+                ArrayData::Date32(ref list) => cast_rows(array, safe, |i| {
+                    let days = *list.get(i);
+                    temporal::date_to_timestamp(days, *to_unit)
+                        .ok_or_else(|| out_of_range(days as i64))
+                })?,
+                ArrayData::Timestamp(ref list, from) => cast_rows(array, safe, |i| {
+                    let v = *list.get(i);
+                    temporal::convert_time_unit(v, *from, *to_unit).ok_or_else(|| out_of_range(v))
+                })?,
+                _ => return Err(unsupported()),
+            };
+            let data = ArrayData::Timestamp(PrimitiveArray::from(values), *to_unit);
+            Ok(Array::with_validity(data, &validity))
+        }
+        _ => Err(unsupported()),
+    }
+}
+
+/// Cast a column to a different type. Null rows stay null.
+fn cast_array(array: &Array, to: &DataType, safe: bool) -> Result<Array> {
+    match to {
+        DataType::Boolean => primitive_cast::<bool>(array, to, safe),
+        DataType::Int8 => primitive_cast::<i8>(array, to, safe),
+        DataType::Int16 => primitive_cast::<i16>(array, to, safe),
+        DataType::Int32 => primitive_cast::<i32>(array, to, safe),
+        DataType::Int64 => primitive_cast::<i64>(array, to, safe),
+        DataType::UInt8 => primitive_cast::<u8>(array, to, safe),
+        DataType::UInt16 => primitive_cast::<u16>(array, to, safe),
+        DataType::UInt32 => primitive_cast::<u32>(array, to, safe),
+        DataType::UInt64 => primitive_cast::<u64>(array, to, safe),
+        DataType::Float32 => primitive_cast::<f32>(array, to, safe),
+        DataType::Float64 => primitive_cast::<f64>(array, to, safe),
+        DataType::Decimal(p, s) => decimal_cast(array, *p, *s, safe),
+        DataType::Utf8 => {
+            // strings are formatted the same way as query results
+            let (values, validity) =
+                cast_rows(array, safe, |i| Ok(get_value(array, i).to_string()))?;
+            Ok(Array::with_validity(
+                Array::from(values).into_data(),
+                &validity,
+            ))
+        }
+        DataType::Date32 | DataType::Time64(_) | DataType::Timestamp(_) => {
+            temporal_cast(array, to, safe)
+        }
+        _ => Err(unsupported_cast(&array.data().data_type(), to)),
+    }
+}
+
+/// Create an array holding a single value
+fn scalar_to_array(value: &ScalarValue) -> Result<Array> {
+    Ok(match value {
+        ScalarValue::Boolean(v) => Array::from(vec![*v]),
+        ScalarValue::Int8(v) => Array::from(vec![*v]),
+        ScalarValue::Int16(v) => Array::from(vec![*v]),
+        ScalarValue::Int32(v) => Array::from(vec![*v]),
+        ScalarValue::Int64(v) => Array::from(vec![*v]),
+        ScalarValue::UInt8(v) => Array::from(vec![*v]),
+        ScalarValue::UInt16(v) => Array::from(vec![*v]),
+        ScalarValue::UInt32(v) => Array::from(vec![*v]),
+        ScalarValue::UInt64(v) => Array::from(vec![*v]),
+        ScalarValue::Float32(v) => Array::from(vec![*v]),
+        ScalarValue::Float64(v) => Array::from(vec![*v]),
+        ScalarValue::Utf8(ref v) => Array::from(vec![v.as_ref().clone()]),
+        ScalarValue::Date32(v) => Array::new(1, ArrayData::Date32(PrimitiveArray::from(vec![*v]))),
+        ScalarValue::Time64(v, unit) => Array::new(
+            1,
+            ArrayData::Time64(PrimitiveArray::from(vec![*v]), *unit),
+        ),
+        ScalarValue::Timestamp(v, unit) => Array::new(
+            1,
+            ArrayData::Timestamp(PrimitiveArray::from(vec![*v]), *unit),
+        ),
+        ScalarValue::Decimal(v, p, s) => Array::new(1, ArrayData::Decimal(vec![*v], *p, *s)),
+        other => {
+            return Err(ExecutionError::type_error(
+                "Cannot cast value".to_string(),
+                other,
+            ))
+        }
+    })
+}
+
+/// Cast a single value to a different type. Null stays null.
+pub fn cast_scalar(value: &ScalarValue, to: &DataType, safe: bool) -> Result<ScalarValue> {
+    match value {
+        ScalarValue::Null => return Ok(ScalarValue::Null),
+        ScalarValue::Struct(_) => return Err(unsupported_cast(&DataType::Struct(vec![]), to)),
+        _ => {}
+    }
+    let from = value.get_datatype()?;
+    if from == *to {
+        Ok(value.clone())
+    } else if !can_cast(&from, to) {
+        Err(unsupported_cast(&from, to))
+    } else if *to == DataType::Utf8 {
+        // intervals have no array type, so values are formatted directly
+        Ok(ScalarValue::Utf8(Rc::new(value.to_string())))
+    } else {
+        match cast_array(&scalar_to_array(value)?, to, safe) {
+            Ok(array) => Ok(get_value(&array, 0)),
+            // there is only one row, so the row number would not be useful
+            Err(ExecutionError::DataError { message, .. }) => Err(ExecutionError::DataError {
+                message,
+                file: None,
+                row: None,
+                column: None,
+            }),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Cast a column or scalar to a different type. If `safe` is true then values that cannot be
+/// converted become null, otherwise the first one is an error.
+pub fn cast(value: &Value, to: &DataType, safe: bool) -> Result<Value> {
+    match value {
+        Value::Column(ref array) => {
+            let from = array.data().data_type();
+            if from == *to {
+                Ok(value.clone())
+            } else if !can_cast(&from, to) {
+                Err(unsupported_cast(&from, to))
+            } else {
+                Ok(Value::Column(Rc::new(cast_array(array, to, safe)?)))
+            }
+        }
+        Value::Scalar(ref v) => Ok(Value::Scalar(Rc::new(cast_scalar(v, to, safe)?))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cast_values(array: Array, to: DataType, safe: bool) -> Result<Vec<ScalarValue>> {
+        let array = cast_array(&array, &to, safe)?;
+        Ok((0..array.len()).map(|i| get_value(&array, i)).collect())
+    }
+
+    #[test]
+    fn cast_numbers() {
+        assert_eq!(
+            vec![ScalarValue::Int32(1), ScalarValue::Int32(0)],
+            cast_values(Array::from(vec![true, false]), DataType::Int32, false).unwrap()
+        );
+        assert_eq!(
+            vec![ScalarValue::Boolean(true), ScalarValue::Boolean(false)],
+            cast_values(Array::from(vec![-2.5_f64, 0.0]), DataType::Boolean, false).unwrap()
+        );
+        assert_eq!(
+            vec![ScalarValue::Int8(-2), ScalarValue::Int8(127)],
+            cast_values(Array::from(vec![-2.9_f64, 127.9]), DataType::Int8, false).unwrap()
+        );
+        assert_eq!(
+            vec![ScalarValue::UInt8(255), ScalarValue::Null],
+            cast_values(Array::from(vec![255_i32, 256]), DataType::UInt8, true).unwrap()
+        );
+        assert_eq!(
+            vec![ScalarValue::Decimal(1250, 5, 2)],
+            cast_values(Array::from(vec![12.5_f32]), DataType::Decimal(5, 2), false).unwrap()
+        );
+        assert_eq!(
+            vec![ScalarValue::Int16(12)],
+            cast_values(
+                Array::new(1, ArrayData::Decimal(vec![1299], 5, 2)),
+                DataType::Int16,
+                false
+            ).unwrap()
+        );
+    }
+
+    #[test]
+    fn cast_overflow() {
+        match cast_values(Array::from(vec![1_i64, 300]), DataType::Int8, false) {
+            Err(ExecutionError::DataError { message, row, .. }) => {
+                assert_eq!("Cannot cast 300 to Int8: value is out of range", message);
+                assert_eq!(Some(1), row);
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(cast_values(Array::from(vec![-1_i32]), DataType::UInt32, false).is_err());
+        assert!(cast_values(Array::from(vec![1e300_f64]), DataType::Float32, false).is_err());
+        assert!(cast_values(Array::from(vec![::std::f64::NAN]), DataType::Int64, false).is_err());
+        assert!(cast_values(Array::from(vec![1000_i32]), DataType::Decimal(4, 2), false).is_err());
+    }
+
+    #[test]
+    fn cast_strings() {
+        assert_eq!(
+            vec![
+                ScalarValue::Boolean(true),
+                ScalarValue::Boolean(false),
+                ScalarValue::Null,
+            ],
+            cast_values(Array::from(vec!["TRUE", "no", "maybe"]), DataType::Boolean, true)
+                .unwrap()
+        );
+        assert_eq!(
+            vec![ScalarValue::Int32(42), ScalarValue::Null],
+            cast_values(Array::from(vec![" 42 ", "x"]), DataType::Int32, true).unwrap()
+        );
+        assert!(cast_values(Array::from(vec!["x"]), DataType::Int32, false).is_err());
+        assert_eq!(
+            vec![
+                ScalarValue::Utf8(Rc::new("1.5".to_string())),
+                ScalarValue::Utf8(Rc::new("-3".to_string())),
+            ],
+            cast_values(Array::from(vec![1.5_f64, -3.0]), DataType::Utf8, false).unwrap()
+        );
+        assert_eq!(
+            vec![ScalarValue::Utf8(Rc::new("false".to_string()))],
+            cast_values(Array::from(vec![false]), DataType::Utf8, false).unwrap()
+        );
+    }
+
+    #[test]
+    fn cast_temporal_overflow() {
+        let dates = || {
+            let values = PrimitiveArray::from(vec![i32::max_value()]);
+            Array::new(1, ArrayData::Date32(values))
+        };
+        let seconds = || {
+            let values = PrimitiveArray::from(vec![i64::max_value()]);
+            Array::new(1, ArrayData::Timestamp(values, TimeUnit::Second))
+        };
+
+        // TRY_CAST gives null for values that are out of range, and CAST is an error
+        let check = |array: &Fn() -> Array, to: DataType| {
+            assert_eq!(
+                vec![ScalarValue::Null],
+                cast_values(array(), to.clone(), true).unwrap()
+            );
+            match cast_values(array(), to, false) {
+                Err(ExecutionError::DataError { message, row, .. }) => {
+                    assert!(message.ends_with("value is out of range"));
+                    assert_eq!(Some(0), row);
+                }
+                other => panic!("unexpected result {:?}", other),
+            }
+        };
+        check(&dates, DataType::Timestamp(TimeUnit::Nanosecond));
+        check(&seconds, DataType::Timestamp(TimeUnit::Nanosecond));
+        check(&seconds, DataType::Date32);
+    }
+
+    #[test]
+    fn cast_keeps_nulls() {
+        let array = Array::with_validity(ArrayData::from(vec![1_i32, 2]), &[false, true]);
+        assert_eq!(
+            vec![ScalarValue::Null, ScalarValue::Float64(2.0)],
+            cast_values(array, DataType::Float64, false).unwrap()
+        );
+    }
+
+    #[test]
+    fn cast_scalars() {
+        assert_eq!(
+            ScalarValue::Date32(17532),
+            cast_scalar(
+                &ScalarValue::Utf8(Rc::new("2018-01-01".to_string())),
+                &DataType::Date32,
+                false
+            ).unwrap()
+        );
+        assert_eq!(
+            ScalarValue::Null,
+            cast_scalar(
+                &ScalarValue::Utf8(Rc::new("not a date".to_string())),
+                &DataType::Date32,
+                true
+            ).unwrap()
+        );
+        assert_eq!(
+            ScalarValue::Utf8(Rc::new("1.50".to_string())),
+            cast_scalar(&ScalarValue::Decimal(150, 3, 2), &DataType::Utf8, false).unwrap()
+        );
+        assert_eq!(
+            ScalarValue::Null,
+            cast_scalar(&ScalarValue::Null, &DataType::Int32, false).unwrap()
+        );
+        assert!(cast_scalar(&ScalarValue::Date32(0), &DataType::Int32, true).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+// Copyright 2018 Grove Enterprises LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Cost model that estimates the cardinality of logical plans from table statistics
+
+use std::cmp;
+use std::rc::Rc;
+
+use super::logical::*;
+use super::sqlplanner::SchemaProvider;
+
+/// Fraction of rows assumed to pass a filter when nothing is known about the predicate
+const FILTER_SELECTIVITY: f64 = 0.25;
+
+/// Estimates the number of rows produced by logical plans
+pub struct CostModel {
+    schema_provider: Rc<SchemaProvider>,
+}
+
+impl CostModel {
+    pub fn new(schema_provider: Rc<SchemaProvider>) -> Self {
+        CostModel { schema_provider }
+    }
+
+    /// Estimate the number of rows produced by a plan, or `None` if there are no statistics for
+    /// the tables it reads
+    pub fn estimate_rows(&self, plan: &LogicalPlan) -> Option<usize> {
+        match *plan {
+            LogicalPlan::TableScan { ref table_name, .. } => self
+                .schema_provider
+                .get_table_statistics(table_name)
+                .and_then(|s| s.row_count),
+            LogicalPlan::Selection { ref input, .. } => self
+                .estimate_rows(input)
+                .map(|n| (n as f64 * FILTER_SELECTIVITY).ceil() as usize),
+            LogicalPlan::Projection { ref input, .. } | LogicalPlan::Sort { ref input, .. } => {
+                self.estimate_rows(input)
+            }
+            LogicalPlan::Limit {
+                limit, ref input, ..
+            } => match self.estimate_rows(input) {
+                Some(n) => Some(cmp::min(n, limit)),
+                None => Some(limit),
+            },
+            LogicalPlan::Aggregate {
+                ref input,
+                ref group_expr,
+                ..
+            } => if group_expr.is_empty() {
+                Some(1)
+            } else {
+                self.estimate_rows(input)
+            },
+            LogicalPlan::Join {
+                ref left,
+                ref right,
+                ref on,
+                ..
+            } => {
+                let left_rows = self.estimate_rows(left)?;
+                let right_rows = self.estimate_rows(right)?;
+
+                // each row matches rows with the same key on the other side, assuming values
+                // are uniformly distributed. Without distinct counts assume a foreign key join.
+                let distinct = on
+                    .iter()
+                    .filter_map(|(l, r)| {
+                        match (self.distinct_count(left, l), self.distinct_count(right, r)) {
+                            (Some(a), Some(b)) => Some(cmp::max(a, b)),
+                            _ => None,
+                        }
+                    })
+                    .max();
+                match distinct {
+                    Some(d) if d > 0 => Some(left_rows.saturating_mul(right_rows) / d),
+                    _ => Some(cmp::max(left_rows, right_rows)),
+                }
+            }
+            LogicalPlan::Union { ref inputs, .. } => {
+                let mut rows = 0;
+                for input in inputs {
+                    rows = self.estimate_rows(input)?.saturating_add(rows);
+                }
+                Some(rows)
+            }
+            LogicalPlan::EmptyRelation { .. }
+            | LogicalPlan::Explain { .. }
+            | LogicalPlan::Unnest { .. }
+            | LogicalPlan::CsvFile { .. }
+            | LogicalPlan::NdJsonFile { .. }
+            | LogicalPlan::ParquetFile { .. } => None,
+        }
+    }
+
+    /// Estimate the number of distinct values of a column produced by a plan
+    pub fn distinct_count(&self, plan: &LogicalPlan, column: &Column) -> Option<usize> {
+        match *plan {
+            LogicalPlan::TableScan {
+                ref table_name,
+                ref schema,
+                ..
+            } => {
+                let index = column.index_of(schema).ok()?;
+                let stats = self.schema_provider.get_table_statistics(table_name)?;
+                let column_stats = stats.column(index)?;
+                column_stats.distinct_count
+            }
+            LogicalPlan::Selection { ref input, .. }
+            | LogicalPlan::Sort { ref input, .. }
+            | LogicalPlan::Limit { ref input, .. } => self.distinct_count(input, column),
+            LogicalPlan::Join {
+                ref left,
+                ref right,
+                ..
+            } => self
+                .distinct_count(left, column)
+                .or_else(|| self.distinct_count(right, column)),
+            _ => None,
+        }
+    }
+
+    /// Determine whether the left input of a hash join should be the build side, which is the
+    /// case when it is estimated to be smaller than the right input
+    pub fn build_left(&self, left: &LogicalPlan, right: &LogicalPlan) -> bool {
+        match (self.estimate_rows(left), self.estimate_rows(right)) {
+            (Some(l), Some(r)) => l < r,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::datasources::common::*;
+    use super::super::planbuilder::LogicalPlanBuilder;
+    use super::*;
+
+    use super::super::datatypes::*;
+
+    struct TestSchemaProvider {}
+
+    impl SchemaProvider for TestSchemaProvider {
+        fn get_table_meta(&self, _name: &str) -> Option<Rc<Schema>> {
+            None
+        }
+
+        fn get_function_meta(&self, _name: &str) -> Option<Rc<FunctionMeta>> {
+            None
+        }
+
+        fn get_table_statistics(&self, name: &str) -> Option<Statistics> {
+            let (rows, distinct) = match name {
+                "fact" => (10000, 100),
+                "dim" => (100, 100),
+                _ => return None,
+            };
+            Some(Statistics {
+                row_count: Some(rows),
+                byte_size: None,
+                column_statistics: Some(vec![ColumnStatistics {
+                    distinct_count: Some(distinct),
+                    ..ColumnStatistics::default()
+                }]),
+            })
+        }
+    }
+
+    fn scan(name: &str) -> Rc<LogicalPlan> {
+        let schema = Schema::new(vec![Field::new("id", DataType::Int32, false)]);
+        LogicalPlanBuilder::scan("default", name, &schema, None)
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn estimate_join_rows() {
+        let cost_model = CostModel::new(Rc::new(TestSchemaProvider {}));
+        let fact = scan("fact");
+        let dim = scan("dim");
+
+        assert_eq!(Some(10000), cost_model.estimate_rows(&fact));
+
+        let join = LogicalPlanBuilder::from(&fact)
+            .join(
+                &dim,
+                JoinType::Inner,
+                vec![Column::qualified("fact", "id")],
+                vec![Column::qualified("dim", "id")],
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(Some(10000), cost_model.estimate_rows(&join));
+
+        assert!(!cost_model.build_left(&fact, &dim));
+        assert!(cost_model.build_left(&dim, &fact));
+        assert!(!cost_model.build_left(&dim, &scan("unknown")));
+    }
+}
@@ -18,11 +18,12 @@ use std::clone::Clone;
 use std::rc::Rc;
 use std::str;
 
-use arrow::datatypes::*;
-
+use super::datasources::common::RecordBatch;
+use super::datatypes::*;
 use super::errors::*;
 use super::exec::*;
 use super::logical::*;
+use super::planbuilder::LogicalPlanBuilder;
 
 /// DataFrame is an abstraction of a logical plan and a schema
 pub trait DataFrame {
@@ -32,6 +33,24 @@ pub trait DataFrame {
     /// Selection
     fn filter(&self, expr: Expr) -> Result<Rc<DataFrame>>;
 
+    /// Aggregate, with optional grouping expressions
+    fn aggregate(&self, group_expr: Vec<Expr>, aggr_expr: Vec<Expr>) -> Result<Rc<DataFrame>>;
+
+    /// Limit the number of rows
+    fn limit(&self, n: usize) -> Result<Rc<DataFrame>>;
+
+    /// Join with another DataFrame on pairs of equal columns
+    fn join(
+        &self,
+        right: Rc<DataFrame>,
+        join_type: JoinType,
+        left_keys: &[&str],
+        right_keys: &[&str],
+    ) -> Result<Rc<DataFrame>>;
+
+    /// Append the rows of another DataFrame with the same schema
+    fn union(&self, other: Rc<DataFrame>) -> Result<Rc<DataFrame>>;
+
     /// Return an expression representing the specified column
     fn col(&self, column_name: &str) -> Result<Expr>;
 
@@ -41,6 +60,9 @@ pub trait DataFrame {
 
     /// show N rows (useful for debugging)
     fn show(&self, count: usize);
+
+    /// Execute the plan and return all of the resulting batches
+    fn collect(&self) -> Result<Vec<Rc<RecordBatch>>>;
 }
 
 pub struct DF {
@@ -60,16 +82,8 @@ impl DF {
 
 impl DataFrame for DF {
     fn select(&self, expr: Vec<Expr>) -> Result<Rc<DataFrame>> {
-        use sqlplanner::exprlist_to_fields;
-        let projection_schema = Rc::new(Schema::new(exprlist_to_fields(&expr, self.schema())));
-
-        let plan = LogicalPlan::Projection {
-            expr: expr,
-            input: self.plan.clone(),
-            schema: projection_schema,
-        };
-
-        Ok(Rc::new(self.with_plan(Rc::new(plan))))
+        let plan = LogicalPlanBuilder::from(&self.plan).project(expr)?.build()?;
+        Ok(Rc::new(self.with_plan(plan)))
     }
 
     //    fn sort(&self, expr: Vec<Expr>) -> Result<Rc<DataFrame>> {
@@ -83,19 +97,57 @@ impl DataFrame for DF {
     //    }
 
     fn filter(&self, expr: Expr) -> Result<Rc<DataFrame>> {
-        let plan = LogicalPlan::Selection {
-            expr: expr,
-            input: self.plan.clone(),
-        };
+        let plan = LogicalPlanBuilder::from(&self.plan).filter(expr)?.build()?;
+        Ok(Rc::new(self.with_plan(plan)))
+    }
 
-        Ok(Rc::new(self.with_plan(Rc::new(plan))))
+    fn aggregate(&self, group_expr: Vec<Expr>, aggr_expr: Vec<Expr>) -> Result<Rc<DataFrame>> {
+        let plan = LogicalPlanBuilder::from(&self.plan)
+            .aggregate(group_expr, aggr_expr)?
+            .build()?;
+        Ok(Rc::new(self.with_plan(plan)))
+    }
+
+    fn limit(&self, n: usize) -> Result<Rc<DataFrame>> {
+        let plan = LogicalPlanBuilder::from(&self.plan).limit(n)?.build()?;
+        Ok(Rc::new(self.with_plan(plan)))
+    }
+
+    fn join(
+        &self,
+        right: Rc<DataFrame>,
+        join_type: JoinType,
+        left_keys: &[&str],
+        right_keys: &[&str],
+    ) -> Result<Rc<DataFrame>> {
+        let plan = LogicalPlanBuilder::from(&self.plan)
+            .join(
+                right.plan(),
+                join_type,
+                left_keys
+                    .iter()
+                    .map(|k| Column::from_qualified_name(k))
+                    .collect(),
+                right_keys
+                    .iter()
+                    .map(|k| Column::from_qualified_name(k))
+                    .collect(),
+            )?
+            .build()?;
+        Ok(Rc::new(self.with_plan(plan)))
+    }
+
+    fn union(&self, other: Rc<DataFrame>) -> Result<Rc<DataFrame>> {
+        let plan = LogicalPlanBuilder::from(&self.plan)
+            .union(other.plan())?
+            .build()?;
+        Ok(Rc::new(self.with_plan(plan)))
     }
 
     fn col(&self, column_name: &str) -> Result<Expr> {
-        match self.plan.schema().column_with_name(column_name) {
-            Some((i, _)) => Ok(Expr::Column(i)),
-            _ => Err(ExecutionError::InvalidColumn(column_name.to_string())),
-        }
+        let column = Column::from_qualified_name(column_name);
+        column.index_of(self.plan.schema())?;
+        Ok(Expr::ColumnRef(column))
     }
 
     fn schema(&self) -> &Rc<Schema> {
@@ -109,4 +161,8 @@ impl DataFrame for DF {
     fn show(&self, count: usize) {
         self.ctx.show(self, count).unwrap();
     }
+
+    fn collect(&self) -> Result<Vec<Rc<RecordBatch>>> {
+        self.ctx.collect(self)
+    }
 }
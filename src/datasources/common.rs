@@ -16,11 +16,10 @@
 
 use std::cell::RefCell;
 use std::rc::Rc;
-use std::str;
 
 //use arrow::array::*;
-use arrow::datatypes::*;
 
+use super::super::datatypes::*;
 use super::super::errors::*;
 use super::super::types::*;
 
@@ -45,6 +44,9 @@ pub trait RecordBatch {
 
 pub fn get_value(column: &Array, index: usize) -> ScalarValue {
     ////println!("get_value() index={}", index);
+    if !column.is_valid(index) {
+        return ScalarValue::Null;
+    }
     let v = match column.data() {
         ArrayData::Boolean(ref v) => ScalarValue::Boolean(*v.get(index)),
         ArrayData::Float32(ref v) => ScalarValue::Float32(*v.get(index)),
@@ -57,9 +59,14 @@ pub fn get_value(column: &Array, index: usize) -> ScalarValue {
         ArrayData::UInt16(ref v) => ScalarValue::UInt16(*v.get(index)),
         ArrayData::UInt32(ref v) => ScalarValue::UInt32(*v.get(index)),
         ArrayData::UInt64(ref v) => ScalarValue::UInt64(*v.get(index)),
-        ArrayData::Utf8(ref data) => ScalarValue::Utf8(Rc::new(String::from(
-            str::from_utf8(data.get(index)).unwrap(),
-        ))),
+        ArrayData::Utf8(ref data) => ScalarValue::Utf8(Rc::new(
+            String::from_utf8_lossy(data.get(index)).to_string(),
+        )),
+        ArrayData::Binary(ref data) => ScalarValue::Binary(Rc::new(data.get(index).to_vec())),
+        ArrayData::Date32(ref v) => ScalarValue::Date32(*v.get(index)),
+        ArrayData::Time64(ref v, unit) => ScalarValue::Time64(*v.get(index), *unit),
+        ArrayData::Timestamp(ref v, unit) => ScalarValue::Timestamp(*v.get(index), *unit),
+        ArrayData::Decimal(ref v, p, s) => ScalarValue::Decimal(v[index], *p, *s),
         ArrayData::Struct(ref v) => {
             // v is Vec<ArrayData>
             // each field has its own ArrayData e.g. lat, lon so we want to get a value from each (but it's recursive)
@@ -67,6 +74,12 @@ pub fn get_value(column: &Array, index: usize) -> ScalarValue {
             let fields = v.iter().map(|arr| get_value(&arr, index)).collect();
             ScalarValue::Struct(fields)
         }
+        ArrayData::List(ref offsets, ref values) => ScalarValue::List(
+            (offsets[index]..offsets[index + 1])
+                .map(|i| get_value(values, i))
+                .collect(),
+            values.data().data_type(),
+        ),
     };
     //    //println!("get_value() index={} returned {:?}", index, v);
     v
@@ -101,9 +114,48 @@ impl RecordBatch for DefaultRecordBatch {
     }
 }
 
+/// Statistics for a single column. Each value is optional because data sources may only know
+/// some of them, and distinct counts are estimates.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ColumnStatistics {
+    pub min_value: Option<ScalarValue>,
+    pub max_value: Option<ScalarValue>,
+    pub null_count: Option<usize>,
+    pub distinct_count: Option<usize>,
+}
+
+/// Statistics for a table or data source, used by the cost model
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Statistics {
+    pub row_count: Option<usize>,
+    pub byte_size: Option<usize>,
+    /// Per-column statistics in schema order, if known
+    pub column_statistics: Option<Vec<ColumnStatistics>>,
+}
+
+impl Statistics {
+    /// Get the statistics for the column at the given index, if known
+    pub fn column(&self, index: usize) -> Option<&ColumnStatistics> {
+        match self.column_statistics {
+            Some(ref c) => c.get(index),
+            None => None,
+        }
+    }
+}
+
 pub trait DataSource {
     fn schema(&self) -> &Rc<Schema>;
     fn next(&mut self) -> Option<Result<Rc<RecordBatch>>>;
+
+    /// Statistics that are known without reading the data (e.g. from file metadata)
+    fn statistics(&self) -> Statistics {
+        Statistics::default()
+    }
+
+    /// The number of bytes read from the underlying file so far
+    fn bytes_read(&self) -> usize {
+        0
+    }
 }
 
 pub struct DataSourceIterator {
@@ -18,16 +18,17 @@ use std::fs::File;
 use std::io::{BufReader, BufWriter, Write};
 use std::rc::Rc;
 
-use arrow::array::ListArray;
+use arrow::array::{ListArray, PrimitiveArray};
 use arrow::bitmap::*;
-use arrow::builder::*;
-use arrow::datatypes::*;
 use arrow::list_builder::ListBuilder;
 
 use csv;
 use csv::{StringRecord, StringRecordsIntoIter};
 
+use super::super::datatypes::*;
+use super::super::decimal;
 use super::super::errors::*;
+use super::super::temporal;
 use super::super::types::*;
 use super::common::*;
 
@@ -36,6 +37,7 @@ pub struct CsvFile {
     projection: Option<Vec<usize>>,
     record_iter: StringRecordsIntoIter<BufReader<File>>,
     batch_size: usize,
+    filename: Option<String>,
 }
 
 impl CsvFile {
@@ -55,47 +57,94 @@ impl CsvFile {
             projection,
             record_iter,
             batch_size: 1024,
+            filename: None,
         })
     }
 
     pub fn set_batch_size(&mut self, batch_size: usize) {
         self.batch_size = batch_size
     }
+
+    /// Set the name of the file, which is used to report where invalid values were found
+    pub fn set_filename(&mut self, filename: &str) {
+        self.filename = Some(filename.to_string())
+    }
+}
+
+/// Create an error for a value in a CSV record, located by the line number of the record
+fn value_error(
+    message: String,
+    filename: &Option<String>,
+    row: &StringRecord,
+    column: &str,
+) -> ExecutionError {
+    ExecutionError::data(
+        message,
+        filename.as_ref().map(|f| f.as_str()),
+        row.position().map(|p| p.line() as usize),
+        Some(column),
+    )
 }
 
-/// Built an Arrow array from one column in a batch of CSV records
+/// Built an Arrow array from one column in a batch of CSV records, returning an error from the
+/// enclosing function if a value cannot be parsed. Values are parsed with `FromStr` unless a
+/// parse function and a function to wrap the parsed values in `ArrayData` are given.
 macro_rules! collect_column {
-    ($ROWS:expr, $COL_INDEX:expr, $TY:ty, $LEN:expr, $DEFAULT_VALUE:expr) => {{
-        let mut bitmap = Bitmap::new($LEN);
+    ($ROWS:expr, $COL_INDEX:expr, $TY:ty, $DEFAULT_VALUE:expr, $FILE:expr, $NAME:expr) => {{
+        collect_column!(
+            $ROWS,
+            $COL_INDEX,
+            $TY,
+            $DEFAULT_VALUE,
+            $FILE,
+            $NAME,
+            stringify!($TY),
+            |s: &str| s.parse::<$TY>(),
+            ArrayData::from
+        )
+    }};
+    (
+        $ROWS:expr,
+        $COL_INDEX:expr,
+        $TY:ty,
+        $DEFAULT_VALUE:expr,
+        $FILE:expr,
+        $NAME:expr,
+        $TYPE_NAME:expr,
+        $PARSE:expr,
+        $DATA:expr
+    ) => {{
+        let len = $ROWS.len();
+        let mut bitmap = Bitmap::new(len);
         let mut null_count = 0;
-        let mut b: Builder<$TY> = Builder::with_capacity($LEN);
-        for row_index in 0..$LEN {
+        let mut b: Vec<$TY> = Vec::with_capacity(len);
+        for row_index in 0..len {
             b.push(match $ROWS[row_index].get($COL_INDEX) {
                 Some(s) => if s.len() == 0 {
                     null_count += 1;
                     bitmap.clear(row_index);
                     $DEFAULT_VALUE
                 } else {
-                    match s.parse::<$TY>() {
+                    match $PARSE(s) {
                         Ok(v) => v,
-                        Err(e) => panic!(
-                            "Failed to parse value '{}' as {} at batch row {} column {}: {:?}",
-                            s,
-                            stringify!($TY),
-                            row_index,
-                            $COL_INDEX,
-                            e
-                        ),
+                        Err(e) => return Err(value_error(
+                            format!("Failed to parse '{}' as {}: {}", s, $TYPE_NAME, e),
+                            $FILE,
+                            &$ROWS[row_index],
+                            $NAME,
+                        )),
                     }
                 },
-                None => panic!(
-                    "CSV file missing value at batch  row {}, column {}",
-                    row_index, $COL_INDEX
-                ),
+                None => return Err(value_error(
+                    "Missing value".to_string(),
+                    $FILE,
+                    &$ROWS[row_index],
+                    $NAME,
+                )),
             })
         }
-        let data = ArrayData::from(b.finish());
-        Value::Column(Rc::new(Array::with_nulls($LEN, data, null_count, bitmap)))
+        let data = $DATA(b);
+        Value::Column(Rc::new(Array::with_nulls(len, data, null_count, bitmap)))
     }};
 }
 
@@ -108,7 +157,14 @@ impl DataSource for CsvFile {
                 Some(Ok(r)) => {
                     rows.push(r);
                 }
-                Some(Err(e)) => panic!("{:?}", e),
+                Some(Err(e)) => {
+                    return Some(Err(ExecutionError::data(
+                        e.to_string(),
+                        self.filename.as_ref().map(|f| f.as_str()),
+                        None,
+                        None,
+                    )))
+                }
                 None => break,
             }
         }
@@ -132,22 +188,68 @@ impl DataSource for CsvFile {
                 .collect(),
         };
 
-        let columns: Vec<Value> = column_with_index
+        let filename = &self.filename;
+        let columns: Result<Vec<Value>> = column_with_index
             .map(|(i, c)| {
+                let name = c.name().as_str();
                 if projection.contains(&i) {
-                    match c.data_type() {
-                        DataType::Boolean => collect_column!(rows, i, bool, rows.len(), false),
-                        DataType::Int8 => collect_column!(rows, i, i8, rows.len(), 0),
-                        DataType::Int16 => collect_column!(rows, i, i16, rows.len(), 0),
-                        DataType::Int32 => collect_column!(rows, i, i32, rows.len(), 0),
-                        DataType::Int64 => collect_column!(rows, i, i64, rows.len(), 0),
-                        DataType::UInt8 => collect_column!(rows, i, u8, rows.len(), 0),
-                        DataType::UInt16 => collect_column!(rows, i, u16, rows.len(), 0),
-                        DataType::UInt32 => collect_column!(rows, i, u32, rows.len(), 0),
-                        DataType::UInt64 => collect_column!(rows, i, u64, rows.len(), 0),
-                        DataType::Float16 => collect_column!(rows, i, f32, rows.len(), 0_f32),
-                        DataType::Float32 => collect_column!(rows, i, f32, rows.len(), 0_f32),
-                        DataType::Float64 => collect_column!(rows, i, f64, rows.len(), 0_f64),
+                    Ok(match c.data_type() {
+                        DataType::Boolean => collect_column!(rows, i, bool, false, filename, name),
+                        DataType::Int8 => collect_column!(rows, i, i8, 0, filename, name),
+                        DataType::Int16 => collect_column!(rows, i, i16, 0, filename, name),
+                        DataType::Int32 => collect_column!(rows, i, i32, 0, filename, name),
+                        DataType::Int64 => collect_column!(rows, i, i64, 0, filename, name),
+                        DataType::UInt8 => collect_column!(rows, i, u8, 0, filename, name),
+                        DataType::UInt16 => collect_column!(rows, i, u16, 0, filename, name),
+                        DataType::UInt32 => collect_column!(rows, i, u32, 0, filename, name),
+                        DataType::UInt64 => collect_column!(rows, i, u64, 0, filename, name),
+                        DataType::Float16 => collect_column!(rows, i, f32, 0_f32, filename, name),
+                        DataType::Float32 => collect_column!(rows, i, f32, 0_f32, filename, name),
+                        DataType::Float64 => collect_column!(rows, i, f64, 0_f64, filename, name),
+                        DataType::Date32 => collect_column!(
+                            rows,
+                            i,
+                            i32,
+                            0,
+                            filename,
+                            name,
+                            "Date32",
+                            temporal::parse_date,
+                            |v: Vec<i32>| ArrayData::Date32(PrimitiveArray::from(v))
+                        ),
+                        DataType::Time64(unit) => collect_column!(
+                            rows,
+                            i,
+                            i64,
+                            0,
+                            filename,
+                            name,
+                            format!("{:?}", c.data_type()),
+                            |s: &str| temporal::parse_time(s, *unit),
+                            |v: Vec<i64>| ArrayData::Time64(PrimitiveArray::from(v), *unit)
+                        ),
+                        DataType::Timestamp(unit) => collect_column!(
+                            rows,
+                            i,
+                            i64,
+                            0,
+                            filename,
+                            name,
+                            format!("{:?}", c.data_type()),
+                            |s: &str| temporal::parse_timestamp(s, *unit),
+                            |v: Vec<i64>| ArrayData::Timestamp(PrimitiveArray::from(v), *unit)
+                        ),
+                        DataType::Decimal(p, s) => collect_column!(
+                            rows,
+                            i,
+                            i128,
+                            0,
+                            filename,
+                            name,
+                            format!("{:?}", c.data_type()),
+                            |v: &str| decimal::parse_decimal(v, *p, *s),
+                            |v: Vec<i128>| ArrayData::Decimal(v, *p, *s)
+                        ),
                         DataType::Utf8 => {
                             let mut builder: ListBuilder<u8> =
                                 ListBuilder::with_capacity(rows.len());
@@ -174,26 +276,52 @@ impl DataSource for CsvFile {
                                 bitmap,
                             )))
                         }
-                        _ => unimplemented!("CSV does not support data type {:?}", c.data_type()),
-                    }
+                        other => {
+                            return Err(ExecutionError::type_mismatch(format!(
+                                "CSV does not support data type {:?}",
+                                other
+                            )))
+                        }
+                    })
                 } else {
                     // not in the projection
                     //println!("Not loading column {} at index {}", c.name(), i);
-                    Value::Scalar(Rc::new(ScalarValue::Null))
+                    Ok(Value::Scalar(Rc::new(ScalarValue::Null)))
                 }
             })
             .collect();
 
-        Some(Ok(Rc::new(DefaultRecordBatch {
-            schema: self.schema.clone(),
-            data: columns,
-            row_count: rows.len(),
-        })))
+        Some(columns.map(|columns| {
+            Rc::new(DefaultRecordBatch {
+                schema: self.schema.clone(),
+                data: columns,
+                row_count: rows.len(),
+            }) as Rc<RecordBatch>
+        }))
     }
 
     fn schema(&self) -> &Rc<Schema> {
         &self.schema
     }
+
+    fn bytes_read(&self) -> usize {
+        self.record_iter.reader().position().byte() as usize
+    }
+}
+
+/// Format a value as a CSV field. Null values are written as empty fields, which is how they are
+/// read. Fields that contain a comma, a double quote or a line break, such as strings and lists,
+/// are quoted, with any double quotes doubled.
+pub fn format_field(v: &ScalarValue) -> String {
+    let field = match *v {
+        ScalarValue::Null => return String::new(),
+        _ => v.to_string(),
+    };
+    if field.contains(|c: char| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
 }
 
 pub struct CsvWriter {
@@ -201,8 +329,10 @@ pub struct CsvWriter {
 }
 
 impl CsvWriter {
-    pub fn write_scalar(&mut self, v: &ScalarValue) {
+    pub fn write_scalar(&mut self, v: &ScalarValue) -> Result<()> {
         match *v {
+            // null values are written as empty fields, which is how they are read
+            ScalarValue::Null => Ok(()),
             ScalarValue::Int8(vv) => self.write_bytes(format!("{}", vv).as_bytes()),
             ScalarValue::Int16(vv) => self.write_bytes(format!("{}", vv).as_bytes()),
             ScalarValue::Int32(vv) => self.write_bytes(format!("{}", vv).as_bytes()),
@@ -213,45 +343,64 @@ impl CsvWriter {
             ScalarValue::UInt64(vv) => self.write_bytes(format!("{}", vv).as_bytes()),
             ScalarValue::Float32(vv) => self.write_bytes(format!("{}", vv).as_bytes()),
             ScalarValue::Float64(vv) => self.write_bytes(format!("{}", vv).as_bytes()),
-            _ => self.write_bytes(format!("{:?}", v).as_bytes()),
+            // everything else is formatted the same way as `write_string`, so strings and lists
+            // are quoted when they need to be and binary values are written as hex
+            _ => self.write_bytes(format_field(v).as_bytes()),
         }
     }
-    pub fn write_bool(&mut self, v: &bool) {
-        self.w.write(format!("{}", *v).as_bytes()).unwrap();
+    pub fn write_bool(&mut self, v: &bool) -> Result<()> {
+        self.w.write_all(format!("{}", *v).as_bytes())?;
+        Ok(())
+    }
+    pub fn write_u8(&mut self, v: &u8) -> Result<()> {
+        self.w.write_all(format!("{}", *v).as_bytes())?;
+        Ok(())
     }
-    pub fn write_u8(&mut self, v: &u8) {
-        self.w.write(format!("{}", *v).as_bytes()).unwrap();
+    pub fn write_u16(&mut self, v: &u16) -> Result<()> {
+        self.w.write_all(format!("{}", *v).as_bytes())?;
+        Ok(())
     }
-    pub fn write_u16(&mut self, v: &u16) {
-        self.w.write(format!("{}", *v).as_bytes()).unwrap();
+    pub fn write_u32(&mut self, v: &u32) -> Result<()> {
+        self.w.write_all(format!("{}", *v).as_bytes())?;
+        Ok(())
     }
-    pub fn write_u32(&mut self, v: &u32) {
-        self.w.write(format!("{}", *v).as_bytes()).unwrap();
+    pub fn write_u64(&mut self, v: &u64) -> Result<()> {
+        self.w.write_all(format!("{}", *v).as_bytes())?;
+        Ok(())
     }
-    pub fn write_u64(&mut self, v: &u64) {
-        self.w.write(format!("{}", *v).as_bytes()).unwrap();
+    pub fn write_i8(&mut self, v: &i8) -> Result<()> {
+        self.w.write_all(format!("{}", *v).as_bytes())?;
+        Ok(())
     }
-    pub fn write_i8(&mut self, v: &i8) {
-        self.w.write(format!("{}", *v).as_bytes()).unwrap();
+    pub fn write_i16(&mut self, v: &i16) -> Result<()> {
+        self.w.write_all(format!("{}", *v).as_bytes())?;
+        Ok(())
     }
-    pub fn write_i16(&mut self, v: &i16) {
-        self.w.write(format!("{}", *v).as_bytes()).unwrap();
+    pub fn write_i32(&mut self, v: &i32) -> Result<()> {
+        self.w.write_all(format!("{}", *v).as_bytes())?;
+        Ok(())
     }
-    pub fn write_i32(&mut self, v: &i32) {
-        self.w.write(format!("{}", *v).as_bytes()).unwrap();
+    pub fn write_i64(&mut self, v: &i64) -> Result<()> {
+        self.w.write_all(format!("{}", *v).as_bytes())?;
+        Ok(())
     }
-    pub fn write_i64(&mut self, v: &i64) {
-        self.w.write(format!("{}", *v).as_bytes()).unwrap();
+    pub fn write_f32(&mut self, v: &f32) -> Result<()> {
+        self.w.write_all(format!("{}", *v).as_bytes())?;
+        Ok(())
     }
-    pub fn write_f32(&mut self, v: &f32) {
-        self.w.write(format!("{}", *v).as_bytes()).unwrap();
+    pub fn write_f64(&mut self, v: &f64) -> Result<()> {
+        self.w.write_all(format!("{}", *v).as_bytes())?;
+        Ok(())
     }
-    pub fn write_f64(&mut self, v: &f64) {
-        self.w.write(format!("{}", *v).as_bytes()).unwrap();
+
+    pub fn write_bytes(&mut self, s: &[u8]) -> Result<()> {
+        self.w.write_all(s)?;
+        Ok(())
     }
 
-    pub fn write_bytes(&mut self, s: &[u8]) {
-        self.w.write(s).unwrap();
+    pub fn flush(&mut self) -> Result<()> {
+        self.w.flush()?;
+        Ok(())
     }
 }
 
@@ -277,6 +426,19 @@ mod tests {
         assert_eq!(3, batch.num_columns());
     }
 
+    #[test]
+    fn test_format_field() {
+        let utf8 = |s: &str| ScalarValue::Utf8(Rc::new(s.to_string()));
+        assert_eq!("", format_field(&ScalarValue::Null));
+        assert_eq!("Solihull", format_field(&utf8("Solihull")));
+        assert_eq!("\"Solihull, UK\"", format_field(&utf8("Solihull, UK")));
+        assert_eq!("\"a \"\"b\"\"\"", format_field(&utf8("a \"b\"")));
+        assert_eq!("\"a\nb\"", format_field(&utf8("a\nb")));
+        // structs are quoted like lists, since their fields are separated by commas
+        let point = ScalarValue::Struct(vec![ScalarValue::Float64(1.5), utf8("x")]);
+        assert_eq!("\"{1.5, x}\"", format_field(&point));
+    }
+
     #[test]
     fn test_nulls() {
         let schema = Schema::new(vec![
@@ -304,6 +466,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_invalid_value() {
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("first_name", DataType::Int32, false),
+        ]);
+        let file = File::open("test/data/people.csv").unwrap();
+        let mut csv = CsvFile::open(file, Rc::new(schema), true, None).unwrap();
+        csv.set_filename("test/data/people.csv");
+        match csv.next() {
+            Some(Err(ExecutionError::DataError {
+                file, row, column, ..
+            })) => {
+                assert_eq!(Some("test/data/people.csv".to_string()), file);
+                assert_eq!(Some(2), row);
+                assert_eq!(Some("first_name".to_string()), column);
+            }
+            _ => panic!("expected a data error"),
+        }
+    }
+
     #[test]
     fn test_csv_iterator() {
         let schema = Schema::new(vec![
@@ -14,11 +14,10 @@
 
 use std::rc::Rc;
 
+use super::super::datatypes::*;
 use super::super::errors::*;
 use super::common::*;
 
-use arrow::datatypes::*;
-
 pub struct EmptyRelation {
     first: bool,
     schema: Rc<Schema>,
@@ -19,15 +19,17 @@ use std::io;
 use std::io::{BufRead, BufReader};
 use std::rc::Rc;
 
-use arrow::array::ListArray;
+use arrow::array::{ListArray, PrimitiveArray};
 use arrow::bitmap::*;
+use arrow::buffer::Buffer;
 use arrow::builder::*;
-use arrow::datatypes::{DataType, Schema};
 use arrow::list_builder::ListBuilder;
 
 use json;
 
+use super::super::datatypes::{DataType, Schema};
 use super::super::errors::*;
+use super::super::temporal;
 use super::super::types::*;
 use super::common::*;
 
@@ -36,6 +38,10 @@ pub struct NdJsonFile {
     projection: Option<Vec<usize>>,
     lines: Box<Iterator<Item = io::Result<String>>>,
     batch_size: usize,
+    bytes_read: usize,
+    /// Number of lines read so far, used to report the location of invalid values
+    lines_read: usize,
+    filename: Option<String>,
 }
 
 impl NdJsonFile {
@@ -46,47 +52,265 @@ impl NdJsonFile {
             schema: schema.clone(),
             lines: Box::new(it),
             batch_size: 1024,
+            bytes_read: 0,
+            lines_read: 0,
+            filename: None,
             projection,
         })
     }
+
+    /// Set the name of the file, which is used to report where invalid values were found
+    pub fn set_filename(&mut self, filename: &str) {
+        self.filename = Some(filename.to_string())
+    }
+
+    fn value_error(&self, message: String, line: usize, column: Option<&str>) -> ExecutionError {
+        ExecutionError::data(
+            message,
+            self.filename.as_ref().map(|f| f.as_str()),
+            Some(line),
+            column,
+        )
+    }
 }
 
-/// Built an Arrow array from one column in a batch of JSON records
+/// Build an Arrow array from the JSON values of one column, returning an error from the
+/// enclosing function if a value has the wrong type. Values are read with a `JsonValue` accessor,
+/// or read as strings and parsed if a parse function and a function to wrap the parsed buffer in
+/// `ArrayData` are given.
 macro_rules! collect_column {
-    ($ROWS:expr, $NAME:expr, $TY:ty, $ACCESSOR:ident, $LEN:expr, $DEFAULT_VALUE:expr) => {{
-        let mut bitmap = Bitmap::new($LEN);
+    (
+        $SELF:expr,
+        $VALUES:expr,
+        $LINES:expr,
+        $NAME:expr,
+        $TY:ty,
+        $ACCESSOR:ident,
+        $DEFAULT_VALUE:expr
+    ) => {{
+        let len = $VALUES.len();
+        let mut bitmap = Bitmap::new(len);
         let mut null_count = 0;
-        let mut b: Builder<$TY> = Builder::with_capacity($LEN);
-        for row_index in 0..$LEN {
-            let field_name: &str = $NAME.as_ref();
-            let json_value: &json::JsonValue = &$ROWS[row_index][field_name];
+        let mut b: Builder<$TY> = Builder::with_capacity(len);
+        for row_index in 0..len {
+            let field_name: &str = $NAME;
+            let json_value: &json::JsonValue = $VALUES[row_index];
             if json_value.is_null() {
                 null_count += 1;
                 bitmap.clear(row_index);
                 b.push($DEFAULT_VALUE)
             } else {
-                b.push(json_value.$ACCESSOR().unwrap());
+                match json_value.$ACCESSOR() {
+                    Some(v) => b.push(v),
+                    None => return Err($SELF.value_error(
+                        format!("Expected {} but found {}", stringify!($TY), json_value.dump()),
+                        $LINES[row_index],
+                        Some(field_name),
+                    )),
+                }
             }
         }
         let data = ArrayData::from(b.finish());
-        Value::Column(Rc::new(Array::with_nulls($LEN, data, null_count, bitmap)))
+        Array::with_nulls(len, data, null_count, bitmap)
+    }};
+    (
+        $SELF:expr,
+        $VALUES:expr,
+        $LINES:expr,
+        $NAME:expr,
+        $TY:ty,
+        $TYPE_NAME:expr,
+        $PARSE:expr,
+        $DATA:expr
+    ) => {{
+        let len = $VALUES.len();
+        let mut bitmap = Bitmap::new(len);
+        let mut null_count = 0;
+        let mut b: Builder<$TY> = Builder::with_capacity(len);
+        for row_index in 0..len {
+            let field_name: &str = $NAME;
+            let json_value: &json::JsonValue = $VALUES[row_index];
+            if json_value.is_null() {
+                null_count += 1;
+                bitmap.clear(row_index);
+                b.push(0)
+            } else {
+                let error = |message: String| {
+                    $SELF.value_error(message, $LINES[row_index], Some(field_name))
+                };
+                match json_value.as_str() {
+                    Some(s) => match $PARSE(s) {
+                        Ok(v) => b.push(v),
+                        Err(e) => return Err(error(format!(
+                            "Failed to parse '{}' as {}: {}",
+                            s, $TYPE_NAME, e
+                        ))),
+                    },
+                    None => return Err(error(format!(
+                        "Expected string but found {}",
+                        json_value.dump()
+                    ))),
+                }
+            }
+        }
+        let data = $DATA(b.finish());
+        Array::with_nulls(len, data, null_count, bitmap)
     }};
 }
 
+impl NdJsonFile {
+    /// Build an array of the given type from the JSON values of a column, where `lines` holds
+    /// the line number of each value. The elements of lists are read as a column of their own.
+    fn read_column(
+        &self,
+        values: &[&json::JsonValue],
+        lines: &[usize],
+        name: &str,
+        data_type: &DataType,
+    ) -> Result<Array> {
+        Ok(match data_type {
+            DataType::Boolean => collect_column!(self, values, lines, name, bool, as_bool, false),
+            DataType::Int8 => collect_column!(self, values, lines, name, i8, as_i8, 0),
+            DataType::Int16 => collect_column!(self, values, lines, name, i16, as_i16, 0),
+            DataType::Int32 => collect_column!(self, values, lines, name, i32, as_i32, 0),
+            DataType::Int64 => collect_column!(self, values, lines, name, i64, as_i64, 0),
+            DataType::UInt8 => collect_column!(self, values, lines, name, u8, as_u8, 0),
+            DataType::UInt16 => collect_column!(self, values, lines, name, u16, as_u16, 0),
+            DataType::UInt32 => collect_column!(self, values, lines, name, u32, as_u32, 0),
+            DataType::UInt64 => collect_column!(self, values, lines, name, u64, as_u64, 0),
+            DataType::Float16 => collect_column!(self, values, lines, name, f32, as_f32, 0_f32),
+            DataType::Float32 => collect_column!(self, values, lines, name, f32, as_f32, 0_f32),
+            DataType::Float64 => collect_column!(self, values, lines, name, f64, as_f64, 0_f64),
+            DataType::Date32 => collect_column!(
+                self,
+                values,
+                lines,
+                name,
+                i32,
+                "Date32",
+                temporal::parse_date,
+                |b: Buffer<i32>| ArrayData::Date32(PrimitiveArray::from(b))
+            ),
+            DataType::Time64(unit) => collect_column!(
+                self,
+                values,
+                lines,
+                name,
+                i64,
+                format!("{:?}", data_type),
+                |s: &str| temporal::parse_time(s, *unit),
+                |b: Buffer<i64>| ArrayData::Time64(PrimitiveArray::from(b), *unit)
+            ),
+            DataType::Timestamp(unit) => collect_column!(
+                self,
+                values,
+                lines,
+                name,
+                i64,
+                format!("{:?}", data_type),
+                |s: &str| temporal::parse_timestamp(s, *unit),
+                |b: Buffer<i64>| ArrayData::Timestamp(PrimitiveArray::from(b), *unit)
+            ),
+            DataType::Utf8 => {
+                let mut b: ListBuilder<u8> = ListBuilder::with_capacity(values.len());
+                let mut bitmap = Bitmap::new(values.len());
+                let mut null_count = 0;
+                for row_index in 0..values.len() {
+                    let json_value: &json::JsonValue = values[row_index];
+                    if json_value.is_null() {
+                        null_count += 1;
+                        bitmap.clear(row_index);
+                        b.push(b"");
+                    } else {
+                        match json_value.as_str() {
+                            Some(v) => b.push(v.as_bytes()),
+                            None => {
+                                return Err(self.value_error(
+                                    format!("Expected string but found {}", json_value.dump()),
+                                    lines[row_index],
+                                    Some(name),
+                                ))
+                            }
+                        }
+                    }
+                }
+                let buffer = b.finish();
+                Array::with_nulls(
+                    values.len(),
+                    ArrayData::Utf8(ListArray::from(buffer)),
+                    null_count,
+                    bitmap,
+                )
+            }
+            DataType::List(element_type) => {
+                let mut offsets: Vec<usize> = Vec::with_capacity(values.len() + 1);
+                let mut validity: Vec<bool> = Vec::with_capacity(values.len());
+                let mut elements: Vec<&json::JsonValue> = vec![];
+                let mut element_lines: Vec<usize> = vec![];
+                offsets.push(0);
+                for row_index in 0..values.len() {
+                    let json_value: &json::JsonValue = values[row_index];
+                    if json_value.is_null() {
+                        validity.push(false);
+                    } else if json_value.is_array() {
+                        validity.push(true);
+                        for element in json_value.members() {
+                            elements.push(element);
+                            element_lines.push(lines[row_index]);
+                        }
+                    } else {
+                        return Err(self.value_error(
+                            format!("Expected array but found {}", json_value.dump()),
+                            lines[row_index],
+                            Some(name),
+                        ));
+                    }
+                    offsets.push(elements.len());
+                }
+                let child = self.read_column(&elements, &element_lines, name, element_type)?;
+                Array::with_validity(ArrayData::List(offsets, Rc::new(child)), &validity)
+            }
+            other => {
+                return Err(ExecutionError::type_mismatch(format!(
+                    "ndjson reader does not support data type {:?}",
+                    other
+                )))
+            }
+        })
+    }
+}
+
 impl DataSource for NdJsonFile {
     fn schema(&self) -> &Rc<Schema> {
-        unimplemented!()
+        &self.schema
+    }
+
+    fn bytes_read(&self) -> usize {
+        self.bytes_read
     }
 
     fn next(&mut self) -> Option<Result<Rc<RecordBatch>>> {
         // load a batch of JSON records into memory
+        let first_line = self.lines_read + 1;
         let mut rows: Vec<json::JsonValue> = Vec::with_capacity(self.batch_size);
         for _ in 0..self.batch_size {
             match self.lines.next() {
                 Some(Ok(r)) => {
-                    rows.push(json::parse(&r).unwrap());
+                    // include the line terminator
+                    self.bytes_read += r.len() + 1;
+                    self.lines_read += 1;
+                    match json::parse(&r) {
+                        Ok(v) => rows.push(v),
+                        Err(e) => {
+                            return Some(Err(self.value_error(
+                                format!("Invalid JSON: {}", e),
+                                self.lines_read,
+                                None,
+                            )))
+                        }
+                    }
                 }
-                Some(Err(e)) => panic!("{:?}", e),
+                Some(Err(e)) => return Some(Err(ExecutionError::IoError(e))),
                 None => break,
             }
         }
@@ -112,92 +336,36 @@ impl DataSource for NdJsonFile {
                 .collect(),
         };
 
-        let columns: Vec<Value> = column_with_index
+        let lines: Vec<usize> = (first_line..first_line + rows.len()).collect();
+        let columns: Result<Vec<Value>> = column_with_index
             .map(|(i, c)| {
                 if projection.contains(&i) {
-                    match c.data_type() {
-                        DataType::Boolean => {
-                            collect_column!(rows, c.name(), bool, as_bool, rows.len(), false)
-                        }
-                        DataType::Int8 => collect_column!(rows, c.name(), i8, as_i8, rows.len(), 0),
-                        DataType::Int16 => {
-                            collect_column!(rows, c.name(), i16, as_i16, rows.len(), 0)
-                        }
-                        DataType::Int32 => {
-                            collect_column!(rows, c.name(), i32, as_i32, rows.len(), 0)
-                        }
-                        DataType::Int64 => {
-                            collect_column!(rows, c.name(), i64, as_i64, rows.len(), 0)
-                        }
-                        DataType::UInt8 => {
-                            collect_column!(rows, c.name(), u8, as_u8, rows.len(), 0)
-                        }
-                        DataType::UInt16 => {
-                            collect_column!(rows, c.name(), u16, as_u16, rows.len(), 0)
-                        }
-                        DataType::UInt32 => {
-                            collect_column!(rows, c.name(), u32, as_u32, rows.len(), 0)
-                        }
-                        DataType::UInt64 => {
-                            collect_column!(rows, c.name(), u64, as_u64, rows.len(), 0)
-                        }
-                        DataType::Float16 => {
-                            collect_column!(rows, c.name(), f32, as_f32, rows.len(), 0_f32)
-                        }
-                        DataType::Float32 => {
-                            collect_column!(rows, c.name(), f32, as_f32, rows.len(), 0_f32)
-                        }
-                        DataType::Float64 => {
-                            collect_column!(rows, c.name(), f64, as_f64, rows.len(), 0_f64)
-                        }
-                        DataType::Utf8 => {
-                            let mut b: ListBuilder<u8> = ListBuilder::with_capacity(rows.len());
-                            let mut bitmap = Bitmap::new(rows.len());
-                            let mut null_count = 0;
-                            for row_index in 0..rows.len() {
-                                let field_name: &str = c.name().as_ref();
-                                let json_value: &json::JsonValue = &rows[row_index][field_name];
-                                if json_value.is_null() {
-                                    null_count += 1;
-                                    bitmap.clear(row_index);
-                                    b.push(b"");
-                                } else {
-                                    b.push(json_value.as_str().unwrap().as_bytes());
-                                }
-                            }
-                            let buffer = b.finish();
-                            Value::Column(Rc::new(Array::with_nulls(
-                                rows.len(),
-                                ArrayData::Utf8(ListArray::from(buffer)),
-                                null_count,
-                                bitmap,
-                            )))
-                        }
-                        _ => unimplemented!(
-                            "ndjson reader does not support data type {:?}",
-                            c.data_type()
-                        ),
-                    }
+                    let name: &str = c.name().as_ref();
+                    let values: Vec<&json::JsonValue> = rows.iter().map(|r| &r[name]).collect();
+                    let array = self.read_column(&values, &lines, name, c.data_type())?;
+                    Ok(Value::Column(Rc::new(array)))
                 } else {
                     // not in the projection
                     //println!("Not loading column {} at index {}", c.name(), i);
-                    Value::Scalar(Rc::new(ScalarValue::Null))
+                    Ok(Value::Scalar(Rc::new(ScalarValue::Null)))
                 }
             })
             .collect();
 
-        Some(Ok(Rc::new(DefaultRecordBatch {
-            schema: self.schema.clone(),
-            data: columns,
-            row_count: rows.len(),
-        })))
+        Some(columns.map(|columns| {
+            Rc::new(DefaultRecordBatch {
+                schema: self.schema.clone(),
+                data: columns,
+                row_count: rows.len(),
+            }) as Rc<RecordBatch>
+        }))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use arrow::datatypes::Field;
+    use super::super::super::datatypes::{Field, TimeUnit};
 
     #[test]
     fn test_read_simple_file() {
@@ -214,4 +382,98 @@ mod tests {
         assert_eq!(3, batch.num_rows());
         assert_eq!(3, batch.num_columns());
     }
+
+    #[test]
+    fn test_read_temporal_values() {
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("day", DataType::Date32, true),
+            Field::new("ts", DataType::Timestamp(TimeUnit::Millisecond), false),
+        ]);
+
+        let file = File::open("test/data/events.ndjson").unwrap();
+
+        let mut file = NdJsonFile::open(file, Rc::new(schema), None).unwrap();
+        let batch = file.next().unwrap().unwrap();
+        let row = batch.row_slice(1);
+        assert_eq!("2018-01-31", row[1].to_string());
+        assert_eq!("2018-01-15 12:00:00", row[2].to_string());
+        assert_eq!("2018-02-28 23:59:59.500", batch.row_slice(2)[2].to_string());
+    }
+
+    #[test]
+    fn test_invalid_value() {
+        let schema = Schema::new(vec![
+            Field::new("a", DataType::UInt32, false),
+            Field::new("b", DataType::Int64, false),
+        ]);
+
+        let file = File::open("test/data/example1.ndjson").unwrap();
+
+        let mut file = NdJsonFile::open(file, Rc::new(schema), None).unwrap();
+        file.set_filename("test/data/example1.ndjson");
+        match file.next() {
+            Some(Err(ExecutionError::DataError { row, column, .. })) => {
+                assert_eq!(Some(1), row);
+                assert_eq!(Some("b".to_string()), column);
+            }
+            _ => panic!("expected a data error"),
+        }
+    }
+
+    #[test]
+    fn test_read_lists() {
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("tags", DataType::List(Box::new(DataType::Utf8)), true),
+            Field::new("scores", DataType::List(Box::new(DataType::Int64)), true),
+        ]);
+
+        let file = File::open("test/data/tags.ndjson").unwrap();
+
+        let mut file = NdJsonFile::open(file, Rc::new(schema), None).unwrap();
+        let batch = file.next().unwrap().unwrap();
+        assert_eq!(4, batch.num_rows());
+
+        let rows: Vec<String> = (0..batch.num_rows())
+            .map(|i| {
+                format!(
+                    "{}, {}",
+                    value_at(batch.column(1), i),
+                    value_at(batch.column(2), i)
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                "[rust, arrow], [1, 2, 3]",
+                "[], [4]",
+                "NULL, [NULL, 5]",
+                "[sql], []",
+            ],
+            rows
+        );
+    }
+
+    #[test]
+    fn test_invalid_list() {
+        let schema = Schema::new(vec![Field::new(
+            "id",
+            DataType::List(Box::new(DataType::Int64)),
+            false,
+        )]);
+
+        let file = File::open("test/data/tags.ndjson").unwrap();
+
+        let mut file = NdJsonFile::open(file, Rc::new(schema), None).unwrap();
+        match file.next() {
+            Some(Err(ExecutionError::DataError {
+                message, column, ..
+            })) => {
+                assert_eq!("Expected array but found 1", message);
+                assert_eq!(Some("id".to_string()), column);
+            }
+            _ => panic!("expected a data error"),
+        }
+    }
 }
@@ -14,24 +14,47 @@
 
 //! Parquet support
 
+use std::collections::VecDeque;
 use std::fs::File;
 use std::rc::Rc;
+use std::str;
 
-use arrow::array::ListArray;
+use arrow::array::{ListArray, PrimitiveArray};
+use arrow::buffer::Buffer;
 use arrow::builder::*;
-use arrow::datatypes::*;
 use arrow::list_builder::*;
 
 use parquet::basic;
 use parquet::column::reader::*;
-use parquet::data_type::{ByteArray, Int96};
+use parquet::data_type::{ByteArray, DataType as ParquetDataType, Int96};
+use parquet::errors::ParquetError;
 use parquet::file::reader::*;
-use parquet::schema::types::Type;
+use parquet::file::statistics::Statistics as ParquetStatistics;
+use parquet::schema::types::{Type, TypePtr};
 
+use super::super::cast::cast_scalar;
+use super::super::datatypes::*;
+use super::super::decimal;
 use super::super::errors::*;
+use super::super::logical::Operator;
+use super::super::temporal;
 use super::super::types::*;
 use super::common::*;
 
+/// The Julian day number of 1970-01-01, used to convert INT96 timestamps
+const JULIAN_DAY_OF_EPOCH: i64 = 2_440_588;
+
+/// A comparison of a column, or of a field of a struct column, with a value e.g.
+/// `location.lat > 50.0`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowGroupPredicate {
+    /// The name of the column followed by the names of the struct fields e.g.
+    /// `["location", "lat"]`
+    pub path: Vec<String>,
+    pub op: Operator,
+    pub value: ScalarValue,
+}
+
 pub struct ParquetFile {
     reader: SerializedFileReader,
     row_group_index: usize,
@@ -40,14 +63,28 @@ pub struct ParquetFile {
     batch_size: usize,
     current_row_group: Option<Box<RowGroupReader>>,
     column_readers: Vec<Option<ColumnReader>>,
+    /// The primitive and list columns of the file, in the order of the column chunks
+    leaves: Vec<Leaf>,
+    /// Paths of the struct fields to read, where struct columns without paths are read in full
+    field_projection: Vec<Vec<String>>,
+    /// Comparisons of column chunks, by their index, with values of the same type as the chunk
+    row_group_filter: Vec<(usize, Operator, ScalarValue)>,
+    /// The rows read so far from each list column in the current row group
+    list_buffers: Vec<Option<ListBuffer>>,
+    bytes_read: usize,
+    filename: Option<String>,
 }
 
 impl ParquetFile {
     pub fn open(file: File, projection: Option<Vec<usize>>) -> Result<Self> {
-        let reader = SerializedFileReader::new(file).unwrap();
+        let reader = SerializedFileReader::new(file).map_err(|e| read_error(e, &None, None))?;
 
         let metadata = reader.metadata();
-        let file_type = to_arrow(metadata.file_metadata().schema());
+        let file_type = to_arrow(metadata.file_metadata().schema())?;
+        let mut leaves = vec![];
+        for (i, f) in metadata.file_metadata().schema().get_fields().iter().enumerate() {
+            collect_leaves(f, i, &[], 0, &mut leaves)?;
+        }
 
         match file_type.data_type() {
             DataType::Struct(fields) => {
@@ -61,6 +98,12 @@ impl ParquetFile {
                     batch_size: 64 * 1024,
                     current_row_group: None,
                     column_readers: vec![],
+                    leaves,
+                    field_projection: vec![],
+                    row_group_filter: vec![],
+                    list_buffers: vec![],
+                    bytes_read: 0,
+                    filename: None,
                 })
             }
             _ => Err(ExecutionError::General(
@@ -73,46 +116,157 @@ impl ParquetFile {
         self.batch_size = batch_size
     }
 
-    fn load_next_row_group(&mut self) {
+    /// Set the name of the file, which is used to report where invalid values were found
+    pub fn set_filename(&mut self, filename: &str) {
+        self.filename = Some(filename.to_string())
+    }
+
+    /// Only read the given fields of struct columns, where each path starts with the name of
+    /// the column e.g. `["location", "lat"]`. The other fields of those columns are not read.
+    pub fn set_field_projection(&mut self, fields: &[Vec<String>]) -> Result<()> {
+        for path in fields {
+            if !self.leaves.iter().any(|leaf| leaf.path.starts_with(path)) {
+                return Err(ExecutionError::General(format!(
+                    "Parquet file has no field named '{}'",
+                    path.join(".")
+                )));
+            }
+        }
+        self.field_projection = fields.to_vec();
+        Ok(())
+    }
+
+    /// Skip the row groups in which, according to the statistics of their column chunks, no row
+    /// satisfies all of the predicates. The rows that are read still need to be filtered.
+    pub fn set_row_group_filter(&mut self, predicates: &[RowGroupPredicate]) -> Result<()> {
+        let mut filter = vec![];
+        for predicate in predicates {
+            if !self.leaves.iter().any(|leaf| leaf.path.starts_with(&predicate.path)) {
+                return Err(ExecutionError::General(format!(
+                    "Parquet file has no field named '{}'",
+                    predicate.path.join(".")
+                )));
+            }
+            // only primitive values have statistics, as the statistics of a list column describe
+            // its elements rather than its rows, and a struct has a column chunk for each field
+            let leaf = match self.leaves.iter().position(|leaf| leaf.path == predicate.path) {
+                Some(leaf) if self.leaves[leaf].list_levels.is_none() => leaf,
+                _ => continue,
+            };
+            // a value that cannot be cast to the type of the chunk cannot be compared with its
+            // statistics
+            let data_type = self.leaves[leaf].field.data_type();
+            if let Ok(value) = cast_scalar(&predicate.value, data_type, false) {
+                filter.push((leaf, predicate.op.clone(), value));
+            }
+        }
+        self.row_group_filter = filter;
+        Ok(())
+    }
+
+    /// Determine whether a row group can be skipped because the statistics of one of its column
+    /// chunks show that none of its rows satisfy a predicate of the row group filter
+    fn can_skip_row_group(&self, i: usize) -> bool {
+        let metadata = self.reader.metadata();
+        let row_group = &metadata.row_groups()[i];
+        self.row_group_filter.iter().any(|&(leaf, ref op, ref value)| {
+            let data_type = self.leaves[leaf].field.data_type();
+            let logical_type = metadata.file_metadata().schema_descr().column(leaf).logical_type();
+            let min_max = row_group
+                .column(leaf)
+                .statistics()
+                .and_then(|stats| min_max_values(stats, data_type, logical_type));
+            match min_max {
+                Some((min, max)) => {
+                    let (min, max) = (logical_value(min, data_type), logical_value(max, data_type));
+                    !may_contain_match(&min, &max, op, value)
+                }
+                None => false,
+            }
+        })
+    }
+
+    /// Determine whether a column chunk is read, based on the projection
+    fn is_projected(&self, leaf: &Leaf) -> bool {
+        if let Some(ref projection) = self.projection {
+            if !projection.contains(&leaf.column) {
+                return false;
+            }
+        }
+        let mut paths = self
+            .field_projection
+            .iter()
+            .filter(|path| path[0] == leaf.path[0])
+            .peekable();
+        paths.peek().is_none() || paths.any(|path| leaf.path.starts_with(path))
+    }
+
+    fn load_next_row_group(&mut self) -> Result<()> {
+        while self.row_group_index < self.reader.num_row_groups()
+            && self.can_skip_row_group(self.row_group_index)
+        {
+            self.row_group_index += 1;
+        }
         if self.row_group_index < self.reader.num_row_groups() {
             //println!("Loading row group {} of {}", self.row_group_index, self.reader.num_row_groups());
-            let reader = self.reader.get_row_group(self.row_group_index).unwrap();
+            let reader = self
+                .reader
+                .get_row_group(self.row_group_index)
+                .map_err(|e| read_error(e, &self.filename, None))?;
+            let metadata = reader.metadata();
 
             self.column_readers = vec![];
 
-            match &self.projection {
-                None => {
-                    for i in 0..reader.num_columns() {
-                        self.column_readers
-                            .push(Some(reader.get_column_reader(i).unwrap()));
-                    }
-                }
-                Some(proj) => {
-                    for i in 0..reader.num_columns() {
-                        if proj.contains(&i) {
-                            self.column_readers
-                                .push(Some(reader.get_column_reader(i).unwrap()));
-                        } else {
-                            //println!("Parquet NOT LOADING COLUMN");
-                            self.column_readers.push(None);
-                        }
-                    }
+            for i in 0..reader.num_columns() {
+                if self.is_projected(&self.leaves[i]) {
+                    self.bytes_read += metadata.column(i).compressed_size() as usize;
+                    let column_reader = reader
+                        .get_column_reader(i)
+                        .map_err(|e| read_error(e, &self.filename, None))?;
+                    self.column_readers.push(Some(column_reader));
+                } else {
+                    //println!("Parquet NOT LOADING COLUMN");
+                    self.column_readers.push(None);
                 }
             }
 
+            self.list_buffers = self
+                .leaves
+                .iter()
+                .zip(&self.column_readers)
+                .map(|(leaf, reader)| match (&leaf.list_levels, reader) {
+                    (Some(levels), Some(_)) => Some(ListBuffer::new(levels.clone())),
+                    _ => None,
+                })
+                .collect();
+
             self.current_row_group = Some(reader);
             self.row_group_index += 1;
-        } else {
-            panic!()
         }
+        Ok(())
     }
 
     fn load_batch(&mut self) -> Option<Result<Rc<RecordBatch>>> {
         match &self.current_row_group {
             Some(reader) => {
-                let mut batch: Vec<Value> = Vec::with_capacity(reader.num_columns());
+                let mut leaf_arrays: Vec<Array> = Vec::with_capacity(reader.num_columns());
                 let mut row_count = 0;
                 for i in 0..self.column_readers.len() {
+                    if let (Some(buffer), Some(column_reader)) =
+                        (&mut self.list_buffers[i], &mut self.column_readers[i])
+                    {
+                        let field = &self.leaves[i].field;
+                        let filename = &self.filename;
+                        match buffer.next_rows(column_reader, self.batch_size, field, filename) {
+                            Ok(array) => {
+                                row_count = array.len();
+                                leaf_arrays.push(array);
+                            }
+                            Err(e) => return Some(Err(e)),
+                        }
+                        continue;
+                    }
+
                     let array = match self.column_readers[i] {
                         Some(ColumnReader::ByteArrayColumnReader(ref mut r)) => {
                             let mut b: Vec<ByteArray> = Vec::with_capacity(self.batch_size);
@@ -123,17 +277,16 @@ impl ParquetFile {
                                 Ok((count, _)) => {
                                     row_count = count;
 
-                                    let mut builder: ListBuilder<u8> =
-                                        ListBuilder::with_capacity(count);
-                                    for j in 0..row_count {
-                                        builder.push(b[j].slice(0, b[j].len()).data());
+                                    let field = &self.leaves[i].field;
+                                    match byte_array_array(&b[0..count], field, &self.filename) {
+                                        Ok(array) => array,
+                                        Err(e) => return Some(Err(e)),
                                     }
-                                    Array::new(
-                                        count,
-                                        ArrayData::Utf8(ListArray::from(builder.finish())),
-                                    )
                                 }
-                                _ => panic!("Error reading parquet batch (column {})", i),
+                                Err(e) => {
+                                    let column = self.leaves[i].field.name();
+                                    return Some(Err(read_error(e, &self.filename, Some(column))));
+                                }
                             }
                         }
                         Some(ColumnReader::BoolColumnReader(ref mut r)) => {
@@ -150,7 +303,10 @@ impl ParquetFile {
                                     builder.set_len(count);
                                     Array::from(builder.finish())
                                 }
-                                _ => panic!("Error reading parquet batch (column {})", i),
+                                Err(e) => {
+                                    let column = self.leaves[i].field.name();
+                                    return Some(Err(read_error(e, &self.filename, Some(column))));
+                                }
                             }
                         }
                         Some(ColumnReader::Int32ColumnReader(ref mut r)) => {
@@ -164,9 +320,13 @@ impl ParquetFile {
                                 Ok((count, _)) => {
                                     row_count = count;
                                     builder.set_len(count);
-                                    Array::from(builder.finish())
+                                    let data_type = self.leaves[i].field.data_type();
+                                    int32_array(builder.finish(), data_type)
+                                }
+                                Err(e) => {
+                                    let column = self.leaves[i].field.name();
+                                    return Some(Err(read_error(e, &self.filename, Some(column))));
                                 }
-                                _ => panic!("Error reading parquet batch (column {})", i),
                             }
                         }
                         Some(ColumnReader::Int64ColumnReader(ref mut r)) => {
@@ -180,9 +340,13 @@ impl ParquetFile {
                                 Ok((count, _)) => {
                                     row_count = count;
                                     builder.set_len(count);
-                                    Array::from(builder.finish())
+                                    let data_type = self.leaves[i].field.data_type();
+                                    int64_array(builder.finish(), data_type)
+                                }
+                                Err(e) => {
+                                    let column = self.leaves[i].field.name();
+                                    return Some(Err(read_error(e, &self.filename, Some(column))));
                                 }
-                                _ => panic!("Error reading parquet batch (column {})", i),
                             }
                         }
                         Some(ColumnReader::Int96ColumnReader(ref mut r)) => {
@@ -200,20 +364,27 @@ impl ParquetFile {
                                     let mut builder: Builder<
                                         i64,
                                     > = Builder::with_capacity(self.batch_size);
-                                    for i in 0..count {
-                                        let v = temp[i].data();
-                                        let value: u128 = (v[0] as u128) << 64
-                                            | (v[1] as u128) << 32
-                                            | (v[2] as u128);
-                                        //println!("value: {}", value);
-
-                                        let ms: i64 = (value / 1000000) as i64;
-                                        builder.push(ms);
+                                    let column = self.leaves[i].field.name();
+                                    for j in 0..count {
+                                        let data = temp[j].data();
+                                        match int96_to_nanos(data, &self.filename, column) {
+                                            Ok(nanos) => builder.push(nanos),
+                                            Err(e) => return Some(Err(e)),
+                                        }
                                     }
 
-                                    Array::from(builder.finish())
+                                    Array::new(
+                                        count,
+                                        ArrayData::Timestamp(
+                                            PrimitiveArray::from(builder.finish()),
+                                            TimeUnit::Nanosecond,
+                                        ),
+                                    )
+                                }
+                                Err(e) => {
+                                    let column = self.leaves[i].field.name();
+                                    return Some(Err(read_error(e, &self.filename, Some(column))));
                                 }
-                                _ => panic!("Error reading parquet batch (column {})", i),
                             }
                         }
                         Some(ColumnReader::FloatColumnReader(ref mut r)) => {
@@ -229,7 +400,10 @@ impl ParquetFile {
                                     builder.set_len(count);
                                     Array::from(builder.finish())
                                 }
-                                _ => panic!("Error reading parquet batch (column {})", i),
+                                Err(e) => {
+                                    let column = self.leaves[i].field.name();
+                                    return Some(Err(read_error(e, &self.filename, Some(column))));
+                                }
                             }
                         }
                         Some(ColumnReader::DoubleColumnReader(ref mut r)) => {
@@ -245,16 +419,38 @@ impl ParquetFile {
                                     builder.set_len(count);
                                     Array::from(builder.finish())
                                 }
-                                _ => panic!("Error reading parquet batch (column {})", i),
+                                Err(e) => {
+                                    let column = self.leaves[i].field.name();
+                                    return Some(Err(read_error(e, &self.filename, Some(column))));
+                                }
+                            }
+                        }
+                        Some(ColumnReader::FixedLenByteArrayColumnReader(ref mut r)) => {
+                            let mut b: Vec<ByteArray> = Vec::with_capacity(self.batch_size);
+                            for _ in 0..self.batch_size {
+                                b.push(ByteArray::default());
+                            }
+                            match r.read_batch(self.batch_size, None, None, &mut b) {
+                                Ok((count, _)) => {
+                                    row_count = count;
+                                    let field = &self.leaves[i].field;
+                                    match byte_array_array(&b[0..count], field, &self.filename) {
+                                        Ok(array) => array,
+                                        Err(e) => return Some(Err(e)),
+                                    }
+                                }
+                                Err(e) => {
+                                    let column = self.leaves[i].field.name();
+                                    return Some(Err(read_error(e, &self.filename, Some(column))));
+                                }
                             }
                         }
-                        Some(ColumnReader::FixedLenByteArrayColumnReader(_)) => unimplemented!(),
                         None => {
                             Array::from(vec![0_i32]) //TODO: really want to return scalar null
                         }
                     };
 
-                    batch.push(Value::Column(Rc::new(array)));
+                    leaf_arrays.push(array);
                 }
 
                 //                println!("Loaded batch of {} rows", row_count);
@@ -262,6 +458,16 @@ impl ParquetFile {
                 if row_count == 0 {
                     None
                 } else {
+                    let mut leaf_arrays = leaf_arrays.into_iter();
+                    let batch = self
+                        .schema
+                        .columns()
+                        .iter()
+                        .map(|f| {
+                            let array = assemble_column(f.data_type(), &mut leaf_arrays, row_count);
+                            Value::Column(Rc::new(array))
+                        })
+                        .collect();
                     Some(Ok(Rc::new(DefaultRecordBatch {
                         schema: self.schema.clone(),
                         data: batch,
@@ -278,13 +484,17 @@ impl DataSource for ParquetFile {
     fn next(&mut self) -> Option<Result<Rc<RecordBatch>>> {
         // advance the row group reader if necessary
         if self.current_row_group.is_none() {
-            self.load_next_row_group();
+            if let Err(e) = self.load_next_row_group() {
+                return Some(Err(e));
+            }
             self.load_batch()
         } else {
             match self.load_batch() {
                 Some(b) => Some(b),
                 None => if self.row_group_index < self.reader.num_row_groups() {
-                    self.load_next_row_group();
+                    if let Err(e) = self.load_next_row_group() {
+                        return Some(Err(e));
+                    }
                     self.load_batch()
                 } else {
                     None
@@ -296,51 +506,742 @@ impl DataSource for ParquetFile {
     fn schema(&self) -> &Rc<Schema> {
         &self.schema
     }
+
+    fn bytes_read(&self) -> usize {
+        self.bytes_read
+    }
+
+    fn statistics(&self) -> Statistics {
+        let metadata = self.reader.metadata();
+        let row_groups = metadata.row_groups();
+
+        let row_count: usize = row_groups.iter().map(|rg| rg.num_rows() as usize).sum();
+        let byte_size: usize = row_groups
+            .iter()
+            .map(|rg| rg.total_byte_size() as usize)
+            .sum();
+
+        // struct columns have a column chunk for each of their fields, so only the statistics of
+        // the other columns are known
+        let column_statistics = (0..self.schema.columns().len())
+            .map(|i| {
+                let data_type = self.schema.column(i).data_type();
+                match self.leaves.iter().position(|leaf| leaf.column == i) {
+                    Some(leaf) if leaf.path.len() == 1 => {
+                        let chunks: Vec<Option<&ParquetStatistics>> =
+                            row_groups.iter().map(|rg| rg.column(leaf).statistics()).collect();
+                        let logical_type =
+                            metadata.file_metadata().schema_descr().column(leaf).logical_type();
+                        merge_column_statistics(&chunks, data_type, logical_type)
+                    }
+                    _ => ColumnStatistics::default(),
+                }
+            })
+            .collect();
+
+        Statistics {
+            row_count: Some(row_count),
+            byte_size: Some(byte_size),
+            column_statistics: Some(column_statistics),
+        }
+    }
+}
+
+/// A column chunk of the file, which is either a top-level column or a field of a struct column
+struct Leaf {
+    /// The index of the top-level column
+    column: usize,
+    /// The names of the fields from the top-level column down to this column
+    path: Vec<String>,
+    /// The type of the values, named after the path
+    field: Field,
+    /// The definition levels of a list column
+    list_levels: Option<ListLevels>,
+}
+
+/// Add the column chunks of a field to `leaves` in schema order, which is the order of the
+/// column chunks in each row group. Lists only have a single column chunk because their elements
+/// are always primitive.
+fn collect_leaves(
+    t: &Type,
+    column: usize,
+    parent_path: &[String],
+    parent_def: i16,
+    leaves: &mut Vec<Leaf>,
+) -> Result<()> {
+    let info = t.get_basic_info();
+    let mut path = parent_path.to_vec();
+    path.push(info.name().to_string());
+    match t {
+        Type::GroupType { fields, .. } if info.logical_type() != basic::LogicalType::LIST => {
+            if info.repetition() == basic::Repetition::REPEATED {
+                return Err(unsupported_type("repeated group", &path.join(".")));
+            }
+            let def = parent_def + (info.repetition() == basic::Repetition::OPTIONAL) as i16;
+            for f in fields {
+                collect_leaves(f, column, &path, def, leaves)?;
+            }
+        }
+        _ => {
+            let field = to_arrow(t)?;
+            leaves.push(Leaf {
+                column,
+                field: Field::new(&path.join("."), field.data_type().clone(), field.is_nullable()),
+                path,
+                list_levels: list_levels(t, parent_def),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Build a column from the arrays read from its column chunks, which are taken from `leaves` in
+/// schema order
+fn assemble_column<I>(data_type: &DataType, leaves: &mut I, row_count: usize) -> Array
+where
+    I: Iterator<Item = Array>,
+{
+    match data_type {
+        DataType::Struct(fields) => {
+            let children = fields
+                .iter()
+                .map(|f| Rc::new(assemble_column(f.data_type(), leaves, row_count)))
+                .collect();
+            Array::new(row_count, ArrayData::Struct(children))
+        }
+        // every column chunk has an array, even if it is only a placeholder
+        _ => leaves.next().unwrap_or_else(|| Array::from(vec![0_i32])),
+    }
 }
 
-fn to_arrow(t: &Type) -> Field {
+/// The definition levels that describe a list column with a single repetition level
+#[derive(Debug, Clone, PartialEq)]
+struct ListLevels {
+    /// Levels below this are null lists
+    null_def: i16,
+    /// Levels at or above this are elements, and levels between `null_def` and this are empty
+    /// lists
+    element_def: i16,
+    /// The level of elements that have a value, where lower element levels are null elements
+    value_def: i16,
+}
+
+/// Get the definition levels of a field if it is a list, which is either a repeated primitive or
+/// a group annotated as a LIST. `parent_def` is the definition level of the struct that contains
+/// the field.
+fn list_levels(t: &Type, parent_def: i16) -> Option<ListLevels> {
+    let info = t.get_basic_info();
+    match t {
+        Type::PrimitiveType { .. } if info.repetition() == basic::Repetition::REPEATED => {
+            Some(ListLevels {
+                null_def: parent_def,
+                element_def: parent_def + 1,
+                value_def: parent_def + 1,
+            })
+        }
+        Type::GroupType { fields, .. } if info.logical_type() == basic::LogicalType::LIST => {
+            let outer_def = parent_def + (info.repetition() == basic::Repetition::OPTIONAL) as i16;
+            let optional_element = match fields.first().map(|f| f.as_ref()) {
+                Some(Type::GroupType { fields, .. }) => fields
+                    .first()
+                    .map(|f| f.get_basic_info().repetition() == basic::Repetition::OPTIONAL)
+                    .unwrap_or(false),
+                _ => false,
+            };
+            Some(ListLevels {
+                null_def: outer_def,
+                element_def: outer_def + 1,
+                value_def: outer_def + 1 + optional_element as i16,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Rows of a list column assembled from its definition and repetition levels. Each row is
+/// `None` for a null list. The last row may still be missing elements until the levels of the
+/// next row, or the end of the column chunk, have been read.
+struct ListBuffer {
+    levels: ListLevels,
+    rows: VecDeque<Option<Vec<ScalarValue>>>,
+    exhausted: bool,
+}
+
+impl ListBuffer {
+    fn new(levels: ListLevels) -> Self {
+        ListBuffer {
+            levels,
+            rows: VecDeque::new(),
+            exhausted: false,
+        }
+    }
+
+    /// Add a batch of levels, with the values of the levels at the value definition level
+    fn push_levels(&mut self, def_levels: &[i16], rep_levels: &[i16], values: Vec<ScalarValue>) {
+        let mut values = values.into_iter();
+        for (def, rep) in def_levels.iter().zip(rep_levels) {
+            if *rep == 0 {
+                self.rows.push_back(if *def < self.levels.null_def {
+                    None
+                } else {
+                    Some(vec![])
+                });
+            }
+            if *def >= self.levels.element_def {
+                let value = if *def == self.levels.value_def {
+                    values.next().unwrap_or(ScalarValue::Null)
+                } else {
+                    ScalarValue::Null
+                };
+                if let Some(Some(elements)) = self.rows.back_mut() {
+                    elements.push(value);
+                }
+            }
+        }
+    }
+
+    fn complete_rows(&self) -> usize {
+        if self.exhausted {
+            self.rows.len()
+        } else {
+            self.rows.len().saturating_sub(1)
+        }
+    }
+
+    /// Read levels and values into the buffer, returning the number of levels read
+    fn read<T, F>(
+        &mut self,
+        reader: &mut ColumnReaderImpl<T>,
+        batch_size: usize,
+        error: &Fn(ParquetError) -> ExecutionError,
+        value: F,
+    ) -> Result<usize>
+    where
+        T: ParquetDataType + 'static,
+        F: Fn(&T::T) -> Result<ScalarValue>,
+    {
+        let mut def_levels = vec![0_i16; batch_size];
+        let mut rep_levels = vec![0_i16; batch_size];
+        let mut values = vec![T::T::default(); batch_size];
+        let (values_read, levels_read) = reader
+            .read_batch(
+                batch_size,
+                Some(&mut def_levels),
+                Some(&mut rep_levels),
+                &mut values,
+            )
+            .map_err(error)?;
+        let values = values[0..values_read]
+            .iter()
+            .map(value)
+            .collect::<Result<Vec<ScalarValue>>>()?;
+        self.push_levels(&def_levels[0..levels_read], &rep_levels[0..levels_read], values);
+        Ok(levels_read)
+    }
+
+    /// Read until a batch of rows is complete, or the column chunk is exhausted, and return
+    /// them as a list array
+    fn next_rows(
+        &mut self,
+        reader: &mut ColumnReader,
+        batch_size: usize,
+        field: &Field,
+        filename: &Option<String>,
+    ) -> Result<Array> {
+        let element_type = match field.data_type() {
+            DataType::List(t) => t.as_ref(),
+            other => {
+                return Err(ExecutionError::type_mismatch(format!(
+                    "Cannot read Parquet repeated values as {:?} (column {})",
+                    other,
+                    field.name()
+                )))
+            }
+        };
+        let error = |e: ParquetError| read_error(e, filename, Some(field.name()));
+        let bytes = |v: &ByteArray| byte_array_value(v, element_type, filename, field.name());
+        while self.complete_rows() < batch_size && !self.exhausted {
+            let levels_read = match reader {
+                ColumnReader::BoolColumnReader(r) => {
+                    self.read(r, batch_size, &error, |v| Ok(ScalarValue::Boolean(*v)))?
+                }
+                ColumnReader::Int32ColumnReader(r) => self.read(r, batch_size, &error, |v| {
+                    Ok(logical_value(ScalarValue::Int32(*v), element_type))
+                })?,
+                ColumnReader::Int64ColumnReader(r) => self.read(r, batch_size, &error, |v| {
+                    Ok(logical_value(ScalarValue::Int64(*v), element_type))
+                })?,
+                ColumnReader::Int96ColumnReader(r) => self.read(r, batch_size, &error, |v| {
+                    let nanos = int96_to_nanos(v.data(), filename, field.name())?;
+                    Ok(ScalarValue::Timestamp(nanos, TimeUnit::Nanosecond))
+                })?,
+                ColumnReader::FloatColumnReader(r) => {
+                    self.read(r, batch_size, &error, |v| Ok(ScalarValue::Float32(*v)))?
+                }
+                ColumnReader::DoubleColumnReader(r) => {
+                    self.read(r, batch_size, &error, |v| Ok(ScalarValue::Float64(*v)))?
+                }
+                ColumnReader::ByteArrayColumnReader(r) => {
+                    self.read(r, batch_size, &error, &bytes)?
+                }
+                ColumnReader::FixedLenByteArrayColumnReader(r) => {
+                    self.read(r, batch_size, &error, &bytes)?
+                }
+            };
+            if levels_read == 0 {
+                self.exhausted = true;
+            }
+        }
+
+        let count = batch_size.min(self.complete_rows());
+        let rows: Vec<ScalarValue> = self
+            .rows
+            .drain(0..count)
+            .map(|row| match row {
+                Some(elements) => ScalarValue::List(elements, element_type.clone()),
+                None => ScalarValue::Null,
+            })
+            .collect();
+        let list_type = field.data_type();
+        build_nullable_array(list_type, &rows, |rows| array_from_scalars(list_type, rows))
+    }
+}
+
+/// Convert a BYTE_ARRAY or FIXED_LEN_BYTE_ARRAY value to a string, binary value or decimal
+fn byte_array_value(
+    value: &ByteArray,
+    data_type: &DataType,
+    filename: &Option<String>,
+    column: &str,
+) -> Result<ScalarValue> {
+    match data_type {
+        DataType::Decimal(p, s) => match decimal::from_be_bytes(value.data()) {
+            Some(v) => Ok(ScalarValue::Decimal(v, *p, *s)),
+            None => Err(ExecutionError::data(
+                "Decimal value does not fit in 128 bits".to_string(),
+                filename.as_ref().map(|f| f.as_str()),
+                None,
+                Some(column),
+            )),
+        },
+        DataType::Binary => Ok(ScalarValue::Binary(Rc::new(value.data().to_vec()))),
+        _ => Ok(ScalarValue::Utf8(Rc::new(
+            String::from_utf8_lossy(value.data()).into_owned(),
+        ))),
+    }
+}
+
+/// Create an array from BYTE_ARRAY or FIXED_LEN_BYTE_ARRAY values, which hold strings, binary
+/// values or decimals
+fn byte_array_array(
+    values: &[ByteArray],
+    field: &Field,
+    filename: &Option<String>,
+) -> Result<Array> {
+    if let DataType::Decimal(_, _) = field.data_type() {
+        return decimal_array(values, field, filename);
+    }
+    let mut builder: ListBuilder<u8> = ListBuilder::with_capacity(values.len());
+    for v in values {
+        builder.push(v.data());
+    }
+    let list = ListArray::from(builder.finish());
+    let data = match field.data_type() {
+        DataType::Binary => ArrayData::Binary(list),
+        _ => ArrayData::Utf8(list),
+    };
+    Ok(Array::new(values.len(), data))
+}
+
+/// Create an array from INT32 values, which may be annotated as dates, times or decimals
+fn int32_array(buffer: Buffer<i32>, data_type: &DataType) -> Array {
+    let len = buffer.len();
+    match data_type {
+        DataType::Date32 => Array::new(len, ArrayData::Date32(PrimitiveArray::from(buffer))),
+        DataType::Time64(unit) => {
+            let values: Vec<i64> = buffer.iter().map(|v| v as i64).collect();
+            Array::new(len, ArrayData::Time64(PrimitiveArray::from(values), *unit))
+        }
+        DataType::Decimal(p, s) => {
+            let values: Vec<i128> = buffer.iter().map(|v| v as i128).collect();
+            Array::new(len, ArrayData::Decimal(values, *p, *s))
+        }
+        _ => Array::from(buffer),
+    }
+}
+
+/// Create an array from INT64 values, which may be annotated as times, timestamps or decimals
+fn int64_array(buffer: Buffer<i64>, data_type: &DataType) -> Array {
+    let len = buffer.len();
+    match data_type {
+        DataType::Time64(unit) => {
+            Array::new(len, ArrayData::Time64(PrimitiveArray::from(buffer), *unit))
+        }
+        DataType::Timestamp(unit) => {
+            Array::new(len, ArrayData::Timestamp(PrimitiveArray::from(buffer), *unit))
+        }
+        DataType::Decimal(p, s) => {
+            let values: Vec<i128> = buffer.iter().map(|v| v as i128).collect();
+            Array::new(len, ArrayData::Decimal(values, *p, *s))
+        }
+        _ => Array::from(buffer),
+    }
+}
+
+/// Create an array from BYTE_ARRAY or FIXED_LEN_BYTE_ARRAY values annotated as decimals, which
+/// hold big-endian two's complement integers
+fn decimal_array(values: &[ByteArray], field: &Field, filename: &Option<String>) -> Result<Array> {
+    let (precision, scale) = match field.data_type() {
+        DataType::Decimal(p, s) => (*p, *s),
+        other => {
+            return Err(ExecutionError::type_mismatch(format!(
+                "Cannot read Parquet byte arrays as {:?} (column {})",
+                other,
+                field.name()
+            )))
+        }
+    };
+    let decimals = values
+        .iter()
+        .map(|v| {
+            decimal::from_be_bytes(v.data()).ok_or_else(|| {
+                ExecutionError::data(
+                    "Decimal value does not fit in 128 bits".to_string(),
+                    filename.as_ref().map(|f| f.as_str()),
+                    None,
+                    Some(field.name()),
+                )
+            })
+        })
+        .collect::<Result<Vec<i128>>>()?;
+    Ok(Array::new(
+        values.len(),
+        ArrayData::Decimal(decimals, precision, scale),
+    ))
+}
+
+/// Convert a legacy INT96 timestamp, which holds the nanoseconds since midnight in its first
+/// eight bytes and the Julian day in its last four, to nanoseconds since the epoch
+fn int96_to_nanos(data: &[u32], filename: &Option<String>, column: &str) -> Result<i64> {
+    let nanos_of_day = (data[1] as i64) << 32 | data[0] as i64;
+    (data[2] as i64 - JULIAN_DAY_OF_EPOCH)
+        .checked_mul(temporal::NANOS_PER_DAY)
+        .and_then(|nanos| nanos.checked_add(nanos_of_day))
+        .ok_or_else(|| {
+            ExecutionError::data(
+                "INT96 timestamp is out of range for nanoseconds".to_string(),
+                filename.as_ref().map(|f| f.as_str()),
+                None,
+                Some(column),
+            )
+        })
+}
+
+/// Convert a statistics value of a column's physical type to its logical type
+fn logical_value(value: ScalarValue, data_type: &DataType) -> ScalarValue {
+    match (value, data_type) {
+        (ScalarValue::Int32(v), DataType::Date32) => ScalarValue::Date32(v),
+        (ScalarValue::Int32(v), DataType::Time64(unit)) => ScalarValue::Time64(v as i64, *unit),
+        (ScalarValue::Int64(v), DataType::Time64(unit)) => ScalarValue::Time64(v, *unit),
+        (ScalarValue::Int64(v), DataType::Timestamp(unit)) => ScalarValue::Timestamp(v, *unit),
+        (ScalarValue::Int32(v), DataType::Decimal(p, s)) => ScalarValue::Decimal(v as i128, *p, *s),
+        (ScalarValue::Int64(v), DataType::Decimal(p, s)) => ScalarValue::Decimal(v as i128, *p, *s),
+        (value, _) => value,
+    }
+}
+
+/// Combine the statistics of the column chunks of one column across all row groups
+fn merge_column_statistics(
+    chunks: &Vec<Option<&ParquetStatistics>>,
+    data_type: &DataType,
+    logical_type: basic::LogicalType,
+) -> ColumnStatistics {
+    let mut stats = ColumnStatistics::default();
+    // the statistics of a list column describe its elements rather than its rows
+    if let DataType::List(_) = data_type {
+        return stats;
+    }
+    if chunks.is_empty() || chunks.iter().any(|c| c.is_none()) {
+        return stats;
+    }
+    let chunks: Vec<&ParquetStatistics> = chunks.iter().map(|c| c.unwrap()).collect();
+
+    stats.null_count = Some(chunks.iter().map(|c| c.null_count() as usize).sum());
+
+    // distinct counts cannot be combined across row groups
+    if chunks.len() == 1 {
+        stats.distinct_count = chunks[0].distinct_count().map(|n| n as usize);
+    }
+
+    for chunk in &chunks {
+        match min_max_values(chunk, data_type, logical_type) {
+            Some((min, max)) => {
+                let (min, max) = (logical_value(min, data_type), logical_value(max, data_type));
+                stats.min_value = match stats.min_value.take() {
+                    Some(ref v) if *v <= min => Some(v.clone()),
+                    _ => Some(min),
+                };
+                stats.max_value = match stats.max_value.take() {
+                    Some(ref v) if *v >= max => Some(v.clone()),
+                    _ => Some(max),
+                };
+            }
+            None => {
+                stats.min_value = None;
+                stats.max_value = None;
+                break;
+            }
+        }
+    }
+    stats
+}
+
+/// Determine whether a column chunk with the given min and max values may contain a value that
+/// satisfies a comparison with `value`. Nulls never satisfy a comparison, and values that are not
+/// ordered relative to each other, such as NaN, may always match.
+fn may_contain_match(
+    min: &ScalarValue,
+    max: &ScalarValue,
+    op: &Operator,
+    value: &ScalarValue,
+) -> bool {
+    if min.partial_cmp(value).is_none() || max.partial_cmp(value).is_none() {
+        return true;
+    }
+    match op {
+        Operator::Eq => min <= value && value <= max,
+        Operator::NotEq => min != value || max != value,
+        Operator::Lt => min < value,
+        Operator::LtEq => min <= value,
+        Operator::Gt => max > value,
+        Operator::GtEq => max >= value,
+        _ => true,
+    }
+}
+
+/// Get the min and max values of a column chunk, if they are set and have a defined order
+fn min_max_values(
+    stats: &ParquetStatistics,
+    data_type: &DataType,
+    logical_type: basic::LogicalType,
+) -> Option<(ScalarValue, ScalarValue)> {
+    if !stats.has_min_max_set() || stats.is_min_max_deprecated() {
+        return None;
+    }
+    // unsigned integers are read as signed integers but their statistics use unsigned order, so
+    // the values only agree when the unsigned max is within the signed range
+    let unsigned = match logical_type {
+        basic::LogicalType::UINT_8
+        | basic::LogicalType::UINT_16
+        | basic::LogicalType::UINT_32
+        | basic::LogicalType::UINT_64 => true,
+        _ => false,
+    };
+    match stats {
+        ParquetStatistics::Int32(s) if unsigned && *s.max() < 0 => None,
+        ParquetStatistics::Int64(s) if unsigned && *s.max() < 0 => None,
+        ParquetStatistics::Boolean(s) => Some((
+            ScalarValue::Boolean(*s.min()),
+            ScalarValue::Boolean(*s.max()),
+        )),
+        ParquetStatistics::Int32(s) => {
+            Some((ScalarValue::Int32(*s.min()), ScalarValue::Int32(*s.max())))
+        }
+        ParquetStatistics::Int64(s) => {
+            Some((ScalarValue::Int64(*s.min()), ScalarValue::Int64(*s.max())))
+        }
+        ParquetStatistics::Float(s) => Some((
+            ScalarValue::Float32(*s.min()),
+            ScalarValue::Float32(*s.max()),
+        )),
+        ParquetStatistics::Double(s) => Some((
+            ScalarValue::Float64(*s.min()),
+            ScalarValue::Float64(*s.max()),
+        )),
+        ParquetStatistics::ByteArray(s) => byte_array_min_max(s.min(), s.max(), data_type),
+        ParquetStatistics::FixedLenByteArray(s) => {
+            byte_array_min_max(s.min(), s.max(), data_type)
+        }
+        _ => None,
+    }
+}
+
+/// Convert the min and max values of a column of strings, binary values or decimals
+fn byte_array_min_max(
+    min: &ByteArray,
+    max: &ByteArray,
+    data_type: &DataType,
+) -> Option<(ScalarValue, ScalarValue)> {
+    match data_type {
+        DataType::Utf8 => match (str::from_utf8(min.data()), str::from_utf8(max.data())) {
+            (Ok(min), Ok(max)) => Some((
+                ScalarValue::Utf8(Rc::new(min.to_string())),
+                ScalarValue::Utf8(Rc::new(max.to_string())),
+            )),
+            _ => None,
+        },
+        DataType::Binary => Some((
+            ScalarValue::Binary(Rc::new(min.data().to_vec())),
+            ScalarValue::Binary(Rc::new(max.data().to_vec())),
+        )),
+        DataType::Decimal(p, s) => {
+            match (
+                decimal::from_be_bytes(min.data()),
+                decimal::from_be_bytes(max.data()),
+            ) {
+                (Some(min), Some(max)) => Some((
+                    ScalarValue::Decimal(min, *p, *s),
+                    ScalarValue::Decimal(max, *p, *s),
+                )),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Create an error for a failure to read a Parquet file, or one of its columns
+fn read_error(e: ParquetError, filename: &Option<String>, column: Option<&str>) -> ExecutionError {
+    ExecutionError::data(
+        format!("Failed to read Parquet data: {}", e),
+        filename.as_ref().map(|f| f.as_str()),
+        None,
+        column,
+    )
+}
+
+fn to_arrow(t: &Type) -> Result<Field> {
     match t {
         Type::PrimitiveType {
             basic_info,
             physical_type,
+            scale,
+            precision,
             ..
-            //type_length,
-            //scale,
-            //precision,
         } => {
 //                println!("basic_info: {:?}", basic_info);
 
             let arrow_type = match physical_type {
                 basic::Type::BOOLEAN => DataType::Boolean,
-                basic::Type::INT32 => DataType::Int32,
-                basic::Type::INT64 => DataType::Int64,
-                basic::Type::INT96 => DataType::Int64, //TODO ???
+                basic::Type::INT32 => match basic_info.logical_type() {
+                    basic::LogicalType::DATE => DataType::Date32,
+                    basic::LogicalType::TIME_MILLIS => DataType::Time64(TimeUnit::Millisecond),
+                    basic::LogicalType::DECIMAL => {
+                        decimal_type(*precision, *scale, basic_info.name())?
+                    }
+                    _ => DataType::Int32,
+                },
+                basic::Type::INT64 => match basic_info.logical_type() {
+                    basic::LogicalType::TIME_MICROS => DataType::Time64(TimeUnit::Microsecond),
+                    basic::LogicalType::TIMESTAMP_MILLIS => {
+                        DataType::Timestamp(TimeUnit::Millisecond)
+                    }
+                    basic::LogicalType::TIMESTAMP_MICROS => {
+                        DataType::Timestamp(TimeUnit::Microsecond)
+                    }
+                    basic::LogicalType::DECIMAL => {
+                        decimal_type(*precision, *scale, basic_info.name())?
+                    }
+                    _ => DataType::Int64,
+                },
+                basic::Type::INT96 => DataType::Timestamp(TimeUnit::Nanosecond),
                 basic::Type::FLOAT => DataType::Float32,
                 basic::Type::DOUBLE => DataType::Float64,
                 basic::Type::BYTE_ARRAY => match basic_info.logical_type() {
-                    basic::LogicalType::UTF8 => DataType::Utf8,
-                    _ => unimplemented!("No support for Parquet BYTE_ARRAY yet"),
+                    basic::LogicalType::UTF8
+                    | basic::LogicalType::ENUM
+                    | basic::LogicalType::JSON => DataType::Utf8,
+                    basic::LogicalType::DECIMAL => {
+                        decimal_type(*precision, *scale, basic_info.name())?
+                    }
+                    // bytes without a string logical type, such as hashes or serialized objects
+                    _ => DataType::Binary,
+                }
+                basic::Type::FIXED_LEN_BYTE_ARRAY => match basic_info.logical_type() {
+                    basic::LogicalType::DECIMAL => {
+                        decimal_type(*precision, *scale, basic_info.name())?
+                    }
+                    _ => DataType::Binary,
                 }
-                basic::Type::FIXED_LEN_BYTE_ARRAY => unimplemented!("No support for Parquet FIXED_LEN_BYTE_ARRAY yet")
             };
 
-            Field::new(basic_info.name(), arrow_type, false)
+            if basic_info.repetition() == basic::Repetition::REPEATED {
+                // a repeated primitive is a list of its values
+                Ok(Field::new(basic_info.name(), DataType::List(Box::new(arrow_type)), false))
+            } else {
+                Ok(Field::new(basic_info.name(), arrow_type, false))
+            }
+        }
+        Type::GroupType { basic_info, fields }
+            if basic_info.logical_type() == basic::LogicalType::LIST =>
+        {
+            let nullable = basic_info.repetition() == basic::Repetition::OPTIONAL;
+            Ok(Field::new(basic_info.name(), list_type(basic_info.name(), fields)?, nullable))
         }
         Type::GroupType { basic_info, fields } => {
-            Field::new(
+            Ok(Field::new(
                 basic_info.name(),
                 DataType::Struct(
-                    fields.iter().map(|f| to_arrow(f)).collect()
+                    fields.iter().map(|f| to_arrow(f)).collect::<Result<Vec<Field>>>()?
                 ),
-                false)
+                false))
         }
     }
 }
 
+/// Get the type of a group annotated as a LIST, which holds a single repeated field that is
+/// either the element itself or a group holding the element
+fn list_type(name: &str, fields: &[TypePtr]) -> Result<DataType> {
+    let repeated = match fields.first() {
+        Some(f)
+            if fields.len() == 1
+                && f.get_basic_info().repetition() == basic::Repetition::REPEATED =>
+        {
+            f
+        }
+        _ => return Err(unsupported_type("LIST without a single repeated field", name)),
+    };
+    match repeated.as_ref() {
+        // in the legacy two-level layout the repeated field is the element
+        Type::PrimitiveType { .. } => Ok(to_arrow(repeated)?.data_type().clone()),
+        Type::GroupType { fields, .. }
+            if fields.len() == 1
+                && fields[0].is_primitive()
+                && fields[0].get_basic_info().repetition() != basic::Repetition::REPEATED =>
+        {
+            Ok(DataType::List(Box::new(to_arrow(&fields[0])?.data_type().clone())))
+        }
+        _ => Err(unsupported_type("nested LIST", name)),
+    }
+}
+
+/// Get the type of a column annotated as a decimal with the given precision and scale
+fn decimal_type(precision: i32, scale: i32, column: &str) -> Result<DataType> {
+    let invalid = |message: String| {
+        ExecutionError::type_mismatch(format!("Invalid DECIMAL column {}: {}", column, message))
+    };
+    if precision < 0 || scale < 0 {
+        return Err(invalid(format!(
+            "negative precision {} or scale {}",
+            precision, scale
+        )));
+    }
+    decimal::validate(precision as usize, scale as usize).map_err(invalid)?;
+    Ok(DataType::Decimal(precision as usize, scale as usize))
+}
+
+fn unsupported_type(physical_type: &str, column: &str) -> ExecutionError {
+    ExecutionError::type_mismatch(format!(
+        "No support for Parquet {} yet (column {})",
+        physical_type, column
+    ))
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use parquet::schema::parser::parse_message_type;
     use std::cell::RefCell;
     use std::rc::Rc;
 
@@ -366,4 +1267,365 @@ mod tests {
             _ => println!("error"),
         });
     }
+
+    #[test]
+    fn test_temporal_types() {
+        let message_type = "
+            message schema {
+                REQUIRED INT32 day (DATE);
+                REQUIRED INT32 time_ms (TIME_MILLIS);
+                REQUIRED INT64 time_us (TIME_MICROS);
+                REQUIRED INT64 ts_ms (TIMESTAMP_MILLIS);
+                REQUIRED INT64 ts_us (TIMESTAMP_MICROS);
+                REQUIRED INT96 ts_legacy;
+                REQUIRED INT64 id;
+            }
+        ";
+        let schema = parse_message_type(message_type).unwrap();
+        let fields = match to_arrow(&schema).unwrap().data_type() {
+            DataType::Struct(fields) => fields.clone(),
+            other => panic!("unexpected type {:?}", other),
+        };
+        let types: Vec<DataType> = fields.iter().map(|f| f.data_type().clone()).collect();
+        assert_eq!(
+            vec![
+                DataType::Date32,
+                DataType::Time64(TimeUnit::Millisecond),
+                DataType::Time64(TimeUnit::Microsecond),
+                DataType::Timestamp(TimeUnit::Millisecond),
+                DataType::Timestamp(TimeUnit::Microsecond),
+                DataType::Timestamp(TimeUnit::Nanosecond),
+                DataType::Int64,
+            ],
+            types
+        );
+
+        // 2018-01-01 12:00:00 is Julian day 2458120 plus 12 hours
+        let nanos_of_day: i64 = 12 * 3600 * 1_000_000_000;
+        let data = [nanos_of_day as u32, (nanos_of_day >> 32) as u32, 2_458_120];
+        assert_eq!(
+            "2018-01-01 12:00:00",
+            temporal::format_timestamp(
+                int96_to_nanos(&data, &None, "ts").unwrap(),
+                TimeUnit::Nanosecond
+            )
+        );
+
+        let filename = Some("legacy.parquet".to_string());
+        match int96_to_nanos(&[0, 0, u32::max_value()], &filename, "ts") {
+            Err(ExecutionError::DataError { file, column, .. }) => {
+                assert_eq!(filename, file);
+                assert_eq!(Some("ts".to_string()), column);
+            }
+            other => panic!("expected a data error but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_decimal_types() {
+        let message_type = "
+            message schema {
+                REQUIRED INT32 small (DECIMAL(9, 2));
+                REQUIRED INT64 medium (DECIMAL(18, 4));
+                REQUIRED BYTE_ARRAY variable (DECIMAL(20, 0));
+                REQUIRED FIXED_LEN_BYTE_ARRAY(16) fixed (DECIMAL(38, 10));
+            }
+        ";
+        let schema = parse_message_type(message_type).unwrap();
+        let fields = match to_arrow(&schema).unwrap().data_type() {
+            DataType::Struct(fields) => fields.clone(),
+            other => panic!("unexpected type {:?}", other),
+        };
+        let types: Vec<DataType> = fields.iter().map(|f| f.data_type().clone()).collect();
+        assert_eq!(
+            vec![
+                DataType::Decimal(9, 2),
+                DataType::Decimal(18, 4),
+                DataType::Decimal(20, 0),
+                DataType::Decimal(38, 10),
+            ],
+            types
+        );
+
+        // -123.45 as a three byte two's complement integer
+        let value = ByteArray::from(vec![0xff, 0xcf, 0xc7]);
+        let array = decimal_array(&[value], &fields[0], &None).unwrap();
+        assert_eq!("-123.45", get_value(&array, 0).to_string());
+    }
+
+    #[test]
+    fn test_binary_types() {
+        let message_type = "
+            message schema {
+                REQUIRED BYTE_ARRAY name (UTF8);
+                REQUIRED BYTE_ARRAY payload;
+                REQUIRED FIXED_LEN_BYTE_ARRAY(16) hash;
+                REQUIRED BYTE_ARRAY status (ENUM);
+            }
+        ";
+        let schema = parse_message_type(message_type).unwrap();
+        let fields = match to_arrow(&schema).unwrap().data_type() {
+            DataType::Struct(fields) => fields.clone(),
+            other => panic!("unexpected type {:?}", other),
+        };
+        let types: Vec<DataType> = fields.iter().map(|f| f.data_type().clone()).collect();
+        assert_eq!(
+            vec![
+                DataType::Utf8,
+                DataType::Binary,
+                DataType::Binary,
+                DataType::Utf8,
+            ],
+            types
+        );
+
+        let values = vec![ByteArray::from(vec![0xde, 0xad]), ByteArray::from(vec![0_u8; 0])];
+        let array = byte_array_array(&values, &fields[1], &None).unwrap();
+        assert_eq!(DataType::Binary, array.data().data_type());
+        assert_eq!("dead", get_value(&array, 0).to_string());
+        assert_eq!("", get_value(&array, 1).to_string());
+        assert_eq!(
+            Some((
+                ScalarValue::Binary(Rc::new(vec![0xde, 0xad])),
+                ScalarValue::Binary(Rc::new(vec![0xff])),
+            )),
+            byte_array_min_max(&values[0], &ByteArray::from(vec![0xff]), &DataType::Binary)
+        );
+    }
+
+    #[test]
+    fn test_unsigned_statistics() {
+        let small = ParquetStatistics::int32(Some(1), Some(100), None, 0, false);
+        let large = ParquetStatistics::int32(Some(1), Some(-1), None, 0, false);
+        let chunks = vec![Some(&small)];
+        let stats = merge_column_statistics(&chunks, &DataType::Int32, basic::LogicalType::UINT_32);
+        assert_eq!(Some(ScalarValue::Int32(1)), stats.min_value);
+        assert_eq!(Some(ScalarValue::Int32(100)), stats.max_value);
+
+        // the unsigned max is 2^32 - 1, which is read as -1
+        let chunks = vec![Some(&small), Some(&large)];
+        let stats = merge_column_statistics(&chunks, &DataType::Int32, basic::LogicalType::UINT_32);
+        assert_eq!(None, stats.min_value);
+        assert_eq!(None, stats.max_value);
+        assert_eq!(Some(0), stats.null_count);
+
+        let stats = merge_column_statistics(&chunks, &DataType::Int32, basic::LogicalType::NONE);
+        assert_eq!(Some(ScalarValue::Int32(-1)), stats.min_value);
+    }
+
+    #[test]
+    fn test_list_types() {
+        let message_type = "
+            message schema {
+                REPEATED INT32 legacy;
+                OPTIONAL group tags (LIST) {
+                    REPEATED group list {
+                        OPTIONAL BYTE_ARRAY element (UTF8);
+                    }
+                }
+                REQUIRED group ids (LIST) {
+                    REPEATED INT64 element;
+                }
+            }
+        ";
+        let schema = parse_message_type(message_type).unwrap();
+        let fields = match to_arrow(&schema).unwrap().data_type() {
+            DataType::Struct(fields) => fields.clone(),
+            other => panic!("unexpected type {:?}", other),
+        };
+        let types: Vec<DataType> = fields.iter().map(|f| f.data_type().clone()).collect();
+        assert_eq!(
+            vec![
+                DataType::List(Box::new(DataType::Int32)),
+                DataType::List(Box::new(DataType::Utf8)),
+                DataType::List(Box::new(DataType::Int64)),
+            ],
+            types
+        );
+
+        let levels = |null_def, element_def, value_def| ListLevels {
+            null_def,
+            element_def,
+            value_def,
+        };
+        assert_eq!(
+            vec![Some(levels(0, 1, 1)), Some(levels(1, 2, 3)), Some(levels(0, 1, 1))],
+            levels_of(&schema)
+        );
+
+        let nested = parse_message_type(
+            "message schema {
+                OPTIONAL group matrix (LIST) {
+                    REPEATED group list {
+                        REPEATED INT32 element;
+                    }
+                }
+            }",
+        ).unwrap();
+        assert!(to_arrow(&nested).is_err());
+    }
+
+    fn levels_of(schema: &Type) -> Vec<Option<ListLevels>> {
+        schema.get_fields().iter().map(|f| list_levels(f, 0)).collect()
+    }
+
+    #[test]
+    fn test_struct_columns() {
+        let message_type = "
+            message schema {
+                REQUIRED INT64 id;
+                OPTIONAL group location {
+                    REQUIRED DOUBLE lat;
+                    REQUIRED DOUBLE lng;
+                    OPTIONAL group names (LIST) {
+                        REPEATED group list {
+                            OPTIONAL BYTE_ARRAY element (UTF8);
+                        }
+                    }
+                }
+            }
+        ";
+        let schema = parse_message_type(message_type).unwrap();
+        let fields = match to_arrow(&schema).unwrap().data_type() {
+            DataType::Struct(fields) => fields.clone(),
+            other => panic!("unexpected type {:?}", other),
+        };
+        assert_eq!(
+            DataType::Struct(vec![
+                Field::new("lat", DataType::Float64, false),
+                Field::new("lng", DataType::Float64, false),
+                Field::new("names", DataType::List(Box::new(DataType::Utf8)), true),
+            ]),
+            *fields[1].data_type()
+        );
+
+        let mut leaves = vec![];
+        for (i, f) in schema.get_fields().iter().enumerate() {
+            collect_leaves(f, i, &[], 0, &mut leaves).unwrap();
+        }
+        let names: Vec<&str> = leaves.iter().map(|l| l.field.name().as_str()).collect();
+        assert_eq!(vec!["id", "location.lat", "location.lng", "location.names"], names);
+        let columns: Vec<usize> = leaves.iter().map(|l| l.column).collect();
+        assert_eq!(vec![0, 1, 1, 1], columns);
+        // the list is inside an optional struct, which adds a definition level
+        assert_eq!(
+            Some(ListLevels {
+                null_def: 2,
+                element_def: 3,
+                value_def: 4,
+            }),
+            leaves[3].list_levels
+        );
+
+        // fields that are not read are placeholders
+        let arrays = vec![Array::from(vec![1.5_f64, 2.5]), Array::from(vec![0_i32])];
+        let location = assemble_column(fields[1].data_type(), &mut arrays.into_iter(), 2);
+        assert_eq!(2, location.len());
+        match location.data() {
+            ArrayData::Struct(children) => {
+                assert_eq!(3, children.len());
+                assert_eq!("2.5", get_value(&children[0], 1).to_string());
+            }
+            other => panic!("unexpected data {:?}", other.data_type()),
+        }
+
+        let repeated = parse_message_type(
+            "message schema {
+                REPEATED group points {
+                    REQUIRED INT32 x;
+                }
+            }",
+        ).unwrap();
+        let mut leaves = vec![];
+        assert!(collect_leaves(&repeated.get_fields()[0], 0, &[], 0, &mut leaves).is_err());
+    }
+
+    #[test]
+    fn test_list_levels_assembly() {
+        // an optional list of optional strings: NULL, [], [NULL], [a, b]
+        let mut buffer = ListBuffer::new(ListLevels {
+            null_def: 1,
+            element_def: 2,
+            value_def: 3,
+        });
+        let values = vec![
+            ScalarValue::Utf8(Rc::new("a".to_string())),
+            ScalarValue::Utf8(Rc::new("b".to_string())),
+        ];
+        buffer.push_levels(&[0, 1, 2, 3], &[0, 0, 0, 0], values[0..1].to_vec());
+        // the last row may continue in the next batch of levels
+        assert_eq!(3, buffer.complete_rows());
+        buffer.push_levels(&[3], &[1], values[1..2].to_vec());
+        buffer.exhausted = true;
+        assert_eq!(4, buffer.complete_rows());
+
+        let rows: Vec<String> = buffer
+            .rows
+            .iter()
+            .map(|row| match row {
+                Some(elements) => ScalarValue::List(elements.clone(), DataType::Utf8).to_string(),
+                None => "NULL".to_string(),
+            })
+            .collect();
+        assert_eq!(vec!["NULL", "[]", "[NULL]", "[a, b]"], rows);
+    }
+
+    #[test]
+    fn test_parquet_statistics() {
+        let file = File::open("test/data/uk_cities.parquet").unwrap();
+        let parquet = ParquetFile::open(file, None).unwrap();
+        let stats = parquet.statistics();
+        assert!(stats.row_count.unwrap() > 0);
+        assert!(stats.byte_size.unwrap() > 0);
+        assert_eq!(3, stats.column_statistics.unwrap().len());
+    }
+
+    #[test]
+    fn test_row_group_filter() {
+        // places.parquet has two row groups, with the ids 1 and 2 and then 3 and 4
+        let read = |predicates: &[RowGroupPredicate]| {
+            let file = File::open("test/data/places.parquet").unwrap();
+            let mut parquet = ParquetFile::open(file, None).unwrap();
+            parquet.set_row_group_filter(predicates).unwrap();
+            let mut ids = vec![];
+            while let Some(batch) = parquet.next() {
+                let batch = batch.unwrap();
+                for i in 0..batch.num_rows() {
+                    ids.push(batch.row_slice(i)[0].to_string());
+                }
+            }
+            (ids, parquet.bytes_read())
+        };
+        let predicate = |path: &[&str], op: Operator, value: f64| RowGroupPredicate {
+            path: path.iter().map(|s| s.to_string()).collect(),
+            op,
+            value: ScalarValue::Float64(value),
+        };
+
+        let (ids, bytes_read) = read(&[]);
+        assert_eq!(vec!["1", "2", "3", "4"], ids);
+
+        // only the row group whose latitudes can be greater than 55 is read
+        let north = predicate(&["location", "lat"], Operator::Gt, 55.0);
+        let (ids, filtered_bytes_read) = read(&[north]);
+        assert_eq!(vec!["3", "4"], ids);
+        assert!(filtered_bytes_read < bytes_read);
+
+        // the value is cast to the type of the column
+        let (ids, _) = read(&[predicate(&["id"], Operator::LtEq, 2.0)]);
+        assert_eq!(vec!["1", "2"], ids);
+
+        let (ids, _) = read(&[
+            predicate(&["location", "lat"], Operator::Gt, 52.0),
+            predicate(&["location", "lng"], Operator::Lt, -3.0),
+        ]);
+        assert_eq!(vec!["3", "4"], ids);
+        let (ids, _) = read(&[predicate(&["location", "lng"], Operator::Eq, 0.0)]);
+        assert!(ids.is_empty());
+
+        let file = File::open("test/data/places.parquet").unwrap();
+        let mut parquet = ParquetFile::open(file, None).unwrap();
+        let unknown = predicate(&["location", "altitude"], Operator::Gt, 0.0);
+        assert!(parquet.set_row_group_filter(&[unknown]).is_err());
+    }
 }
@@ -0,0 +1,162 @@
+// Copyright 2018 Grove Enterprises LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Logical data types, fields and schemas. These follow the Arrow type system, with additional
+//! types for dates, times, timestamps and intervals.
+
+use std::fmt;
+
+/// The resolution of a time or timestamp value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TimeUnit {
+    Second,
+    Millisecond,
+    Microsecond,
+    Nanosecond,
+}
+
+impl TimeUnit {
+    /// The number of units in one second
+    pub fn per_second(&self) -> i64 {
+        match self {
+            TimeUnit::Second => 1,
+            TimeUnit::Millisecond => 1_000,
+            TimeUnit::Microsecond => 1_000_000,
+            TimeUnit::Nanosecond => 1_000_000_000,
+        }
+    }
+}
+
+/// Logical data type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DataType {
+    Boolean,
+    Int8,
+    Int16,
+    Int32,
+    Int64,
+    UInt8,
+    UInt16,
+    UInt32,
+    UInt64,
+    Float16,
+    Float32,
+    Float64,
+    Utf8,
+    /// Variable-length byte strings
+    Binary,
+    /// Fixed-precision decimal with a precision (total number of digits) and a scale (number of
+    /// digits after the decimal point)
+    Decimal(usize, usize),
+    /// Number of days since 1970-01-01
+    Date32,
+    /// Time since midnight
+    Time64(TimeUnit),
+    /// Time since 1970-01-01 00:00:00, without a time zone
+    Timestamp(TimeUnit),
+    /// Calendar interval of months, days and nanoseconds
+    Interval,
+    List(Box<DataType>),
+    Struct(Vec<Field>),
+}
+
+impl DataType {
+    /// Whether this is a date, time or timestamp type
+    pub fn is_temporal(&self) -> bool {
+        match self {
+            DataType::Date32 | DataType::Time64(_) | DataType::Timestamp(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// A named field in a schema or struct
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    name: String,
+    data_type: DataType,
+    nullable: bool,
+}
+
+impl Field {
+    pub fn new(name: &str, data_type: DataType, nullable: bool) -> Self {
+        Field {
+            name: name.to_string(),
+            data_type,
+            nullable,
+        }
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn data_type(&self) -> &DataType {
+        &self.data_type
+    }
+
+    pub fn is_nullable(&self) -> bool {
+        self.nullable
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {:?}", self.name, self.data_type)
+    }
+}
+
+/// The fields of a relation
+#[derive(Debug, Clone)]
+pub struct Schema {
+    columns: Vec<Field>,
+}
+
+impl Schema {
+    /// create an empty schema
+    pub fn empty() -> Self {
+        Schema { columns: vec![] }
+    }
+
+    pub fn new(columns: Vec<Field>) -> Self {
+        Schema { columns }
+    }
+
+    pub fn columns(&self) -> &Vec<Field> {
+        &self.columns
+    }
+
+    pub fn column(&self, i: usize) -> &Field {
+        &self.columns[i]
+    }
+
+    /// look up a column by name and return a reference to the column along with its index
+    pub fn column_with_name(&self, name: &str) -> Option<(usize, &Field)> {
+        self.columns
+            .iter()
+            .enumerate()
+            .find(|&(_, c)| c.name == name)
+    }
+}
+
+impl fmt::Display for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self
+            .columns
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<String>>()
+            .join(", "))
+    }
+}
@@ -0,0 +1,412 @@
+// Copyright 2018 Grove Enterprises LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parsing, formatting and exact arithmetic for fixed-precision decimals.
+//!
+//! A decimal with precision `p` and scale `s` has at most `p` digits, `s` of which are after
+//! the decimal point. It is stored as an `i128` holding the value multiplied by `10^s`.
+
+use std::cmp;
+use std::fmt;
+use std::result;
+
+/// The largest supported precision, which is the most digits that always fit in an i128
+pub const MAX_PRECISION: usize = 38;
+
+/// The scale that the result of an operation keeps, when it has at least this many digits
+/// after the decimal point, if its precision has to be reduced to the maximum
+const MIN_ADJUSTED_SCALE: usize = 6;
+
+/// An arithmetic operation on decimals
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DecimalOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+/// Check that a precision and scale describe a valid decimal type
+pub fn validate(precision: usize, scale: usize) -> result::Result<(), String> {
+    if precision == 0 || precision > MAX_PRECISION {
+        Err(format!(
+            "decimal precision must be between 1 and {}, found {}",
+            MAX_PRECISION, precision
+        ))
+    } else if scale > precision {
+        Err(format!(
+            "decimal scale {} is larger than the precision {}",
+            scale, precision
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// `10^n`, or `None` if it does not fit in an i128
+fn checked_pow10(n: usize) -> Option<i128> {
+    let mut value: i128 = 1;
+    for _ in 0..n {
+        value = value.checked_mul(10)?;
+    }
+    Some(value)
+}
+
+fn out_of_range<T: fmt::Display>(value: T, precision: usize, scale: usize) -> String {
+    format!(
+        "value {} does not fit in DECIMAL({}, {})",
+        value, precision, scale
+    )
+}
+
+/// Check that a value with the given scale has at most `precision` digits
+pub fn check_precision(
+    value: i128,
+    precision: usize,
+    scale: usize,
+) -> result::Result<i128, String> {
+    let limit = checked_pow10(precision).unwrap_or(i128::max_value());
+    if value > -limit && value < limit {
+        Ok(value)
+    } else {
+        Err(out_of_range(format_decimal(value, scale), precision, scale))
+    }
+}
+
+/// Divide, rounding half away from zero
+fn divide_rounded(n: i128, d: i128) -> i128 {
+    let (quotient, remainder) = (n / d, n % d);
+    if remainder.abs() >= d.abs() - remainder.abs() {
+        if (n < 0) == (d < 0) {
+            quotient + 1
+        } else {
+            quotient - 1
+        }
+    } else {
+        quotient
+    }
+}
+
+/// Change the scale of a value, rounding half away from zero when digits are dropped. Returns
+/// `None` if the value overflows.
+pub fn rescale(value: i128, from: usize, to: usize) -> Option<i128> {
+    if to >= from {
+        value.checked_mul(checked_pow10(to - from)?)
+    } else {
+        match checked_pow10(from - to) {
+            Some(divisor) => Some(divide_rounded(value, divisor)),
+            None => Some(0),
+        }
+    }
+}
+
+/// Parse a decimal such as `-12.345`. Digits beyond the scale are rounded half away from zero.
+pub fn parse_decimal(s: &str, precision: usize, scale: usize) -> result::Result<i128, String> {
+    let invalid = || format!("invalid decimal '{}'", s);
+    let trimmed = s.trim();
+    let (negative, digits) = match trimmed.chars().next() {
+        Some('-') => (true, &trimmed[1..]),
+        Some('+') => (false, &trimmed[1..]),
+        _ => (false, trimmed),
+    };
+    let (integer, fraction) = match digits.find('.') {
+        Some(i) => (&digits[..i], &digits[i + 1..]),
+        None => (digits, ""),
+    };
+    if (integer.is_empty() && fraction.is_empty())
+        || !integer.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+
+    let kept = cmp::min(fraction.len(), scale);
+    let mut value: i128 = 0;
+    for c in integer.chars().chain(fraction[..kept].chars()) {
+        value = value
+            .checked_mul(10)
+            .and_then(|v| v.checked_add(c.to_digit(10).unwrap() as i128))
+            .ok_or_else(|| out_of_range(trimmed, precision, scale))?;
+    }
+    value = rescale(value, kept, scale).ok_or_else(|| out_of_range(trimmed, precision, scale))?;
+    if fraction.len() > kept && fraction.as_bytes()[kept] >= b'5' {
+        value += 1;
+    }
+    check_precision(if negative { -value } else { value }, precision, scale)
+}
+
+/// Format a decimal with exactly `scale` digits after the decimal point
+pub fn format_decimal(value: i128, scale: usize) -> String {
+    let s = value.to_string();
+    if scale == 0 {
+        return s;
+    }
+    let (sign, digits) = if value < 0 {
+        ("-", &s[1..])
+    } else {
+        ("", &s[..])
+    };
+    let digits = if digits.len() <= scale {
+        format!("{}{}", "0".repeat(scale + 1 - digits.len()), digits)
+    } else {
+        digits.to_string()
+    };
+    let point = digits.len() - scale;
+    format!("{}{}.{}", sign, &digits[..point], &digits[point..])
+}
+
+/// Convert a decimal to the nearest floating point number
+pub fn to_f64(value: i128, scale: usize) -> f64 {
+    value as f64 / 10_f64.powi(scale as i32)
+}
+
+/// Convert a decimal to an integer, discarding the fractional digits
+pub fn to_i128(value: i128, scale: usize) -> i128 {
+    match checked_pow10(scale) {
+        Some(divisor) => value / divisor,
+        None => 0,
+    }
+}
+
+/// Convert an integer or floating point number to a decimal. Floating point numbers are
+/// rounded half away from zero to the scale.
+pub trait ToDecimal {
+    fn to_decimal(&self, precision: usize, scale: usize) -> result::Result<i128, String>;
+}
+
+macro_rules! integer_to_decimal {
+    ($($TY:ty),*) => {
+        $(
+            impl ToDecimal for $TY {
+                fn to_decimal(
+                    &self,
+                    precision: usize,
+                    scale: usize,
+                ) -> result::Result<i128, String> {
+                    match rescale(*self as i128, 0, scale) {
+                        Some(v) => check_precision(v, precision, scale),
+                        None => Err(out_of_range(self, precision, scale)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+integer_to_decimal!(i8, i16, i32, i64, u8, u16, u32, u64);
+
+impl ToDecimal for f64 {
+    fn to_decimal(&self, precision: usize, scale: usize) -> result::Result<i128, String> {
+        let scaled = (self * 10_f64.powi(scale as i32)).round();
+        // 1e38 is the largest power of ten that is less than the largest i128
+        if scaled.is_finite() && scaled.abs() < 1e38 {
+            check_precision(scaled as i128, precision, scale)
+        } else {
+            Err(out_of_range(self, precision, scale))
+        }
+    }
+}
+
+impl ToDecimal for f32 {
+    fn to_decimal(&self, precision: usize, scale: usize) -> result::Result<i128, String> {
+        (*self as f64).to_decimal(precision, scale)
+    }
+}
+
+/// Get the precision and scale of the result of an operation on decimals with the given
+/// precisions and scales. Results are wide enough to be exact, except that when the precision
+/// would be more than the maximum the scale is reduced to make room for the integer digits.
+pub fn result_type(
+    op: DecimalOperator,
+    (p1, s1): (usize, usize),
+    (p2, s2): (usize, usize),
+) -> (usize, usize) {
+    let (precision, scale) = match op {
+        DecimalOperator::Add | DecimalOperator::Subtract => {
+            let scale = cmp::max(s1, s2);
+            (cmp::max(p1 - s1, p2 - s2) + scale + 1, scale)
+        }
+        DecimalOperator::Multiply => (p1 + p2 + 1, s1 + s2),
+        DecimalOperator::Divide => {
+            let scale = cmp::max(MIN_ADJUSTED_SCALE, s1 + p2 + 1);
+            (p1 - s1 + s2 + scale, scale)
+        }
+    };
+    if precision <= MAX_PRECISION {
+        (precision, scale)
+    } else {
+        let integer_digits = precision - scale;
+        let min_scale = cmp::min(scale, MIN_ADJUSTED_SCALE);
+        (
+            MAX_PRECISION,
+            cmp::max(MAX_PRECISION.saturating_sub(integer_digits), min_scale),
+        )
+    }
+}
+
+/// Apply an operation to the decimals `l` with scale `ls` and `r` with scale `rs`, giving a
+/// decimal with the precision and scale returned by `result_type`
+pub fn apply(
+    op: DecimalOperator,
+    (l, ls): (i128, usize),
+    (r, rs): (i128, usize),
+    (precision, scale): (usize, usize),
+) -> result::Result<i128, String> {
+    let value = match op {
+        DecimalOperator::Add | DecimalOperator::Subtract => {
+            let common = cmp::max(ls, rs);
+            let (l, r) = match (rescale(l, ls, common), rescale(r, rs, common)) {
+                (Some(l), Some(r)) => (l, r),
+                _ => return Err(out_of_range("", precision, scale)),
+            };
+            let sum = if op == DecimalOperator::Add {
+                l.checked_add(r)
+            } else {
+                l.checked_sub(r)
+            };
+            sum.and_then(|v| rescale(v, common, scale))
+        }
+        DecimalOperator::Multiply => l.checked_mul(r).and_then(|v| rescale(v, ls + rs, scale)),
+        DecimalOperator::Divide => {
+            if r == 0 {
+                return Err("division by zero".to_string());
+            }
+            // l / r has scale ls - rs, so l is scaled up first to keep the digits of the result
+            rescale(l, ls, scale + rs).map(|l| divide_rounded(l, r))
+        }
+    };
+    match value {
+        Some(v) => check_precision(v, precision, scale),
+        None => Err(format!(
+            "result does not fit in DECIMAL({}, {})",
+            precision, scale
+        )),
+    }
+}
+
+/// Convert a big-endian two's complement integer, which is how Parquet stores decimals in byte
+/// arrays, to a decimal value. Returns `None` if the value does not fit in an i128.
+pub fn from_be_bytes(bytes: &[u8]) -> Option<i128> {
+    let negative = bytes.first().map_or(false, |b| b & 0x80 != 0);
+    let significant = if bytes.len() > 16 {
+        let (excess, rest) = bytes.split_at(bytes.len() - 16);
+        let sign_byte = if negative { 0xff } else { 0 };
+        if excess.iter().any(|b| *b != sign_byte) || (rest[0] & 0x80 != 0) != negative {
+            return None;
+        }
+        rest
+    } else {
+        bytes
+    };
+    let initial: i128 = if negative { -1 } else { 0 };
+    Some(
+        significant
+            .iter()
+            .fold(initial, |value, b| value << 8 | *b as i128),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_format_decimals() {
+        assert_eq!(Ok(12345), parse_decimal("123.45", 5, 2));
+        assert_eq!(Ok(-5), parse_decimal(" -0.05 ", 5, 2));
+        assert_eq!(Ok(12300), parse_decimal("123", 5, 2));
+        assert_eq!(Ok(1235), parse_decimal("+1.2345", 5, 3));
+        assert_eq!(Ok(-1235), parse_decimal("-1.2345", 5, 3));
+        assert_eq!(Ok(50), parse_decimal(".5", 3, 2));
+        assert_eq!(
+            Err("value 1234.5 does not fit in DECIMAL(5, 2)".to_string()),
+            parse_decimal("1234.5", 5, 2)
+        );
+        assert_eq!(
+            Err("invalid decimal '1.2.3'".to_string()),
+            parse_decimal("1.2.3", 5, 2)
+        );
+        assert!(parse_decimal("", 5, 2).is_err());
+        assert!(parse_decimal("1e5", 5, 2).is_err());
+
+        assert_eq!("123.45", format_decimal(12345, 2));
+        assert_eq!("-0.05", format_decimal(-5, 2));
+        assert_eq!("0.000", format_decimal(0, 3));
+        assert_eq!("-42", format_decimal(-42, 0));
+    }
+
+    #[test]
+    fn convert_decimals() {
+        assert_eq!(Some(1200), rescale(12, 0, 2));
+        assert_eq!(Some(13), rescale(1250, 2, 0));
+        assert_eq!(Some(-13), rescale(-1250, 2, 0));
+        assert_eq!(Some(12), rescale(1249, 2, 0));
+        assert_eq!(None, rescale(i128::max_value(), 0, 1));
+
+        assert_eq!(Ok(4200), 42_i32.to_decimal(4, 2));
+        assert!(420_i32.to_decimal(4, 2).is_err());
+        assert_eq!(Ok(-1235), (-12.345_f64).to_decimal(10, 2));
+        assert!(::std::f64::NAN.to_decimal(10, 2).is_err());
+
+        assert_eq!(-12, to_i128(-1299, 2));
+        assert_eq!(12.99, to_f64(1299, 2));
+    }
+
+    #[test]
+    fn decimal_result_types() {
+        use self::DecimalOperator::*;
+        assert_eq!((11, 3), result_type(Add, (10, 2), (5, 3)));
+        assert_eq!((16, 5), result_type(Multiply, (10, 2), (5, 3)));
+        assert_eq!((19, 11), result_type(Divide, (10, 2), (5, 3)));
+        assert_eq!((38, 6), result_type(Multiply, (38, 10), (38, 10)));
+        assert_eq!((38, 37), result_type(Add, (38, 37), (1, 0)));
+    }
+
+    #[test]
+    fn decimal_arithmetic() {
+        use self::DecimalOperator::*;
+        // 1.50 + 2.125 = 3.625
+        assert_eq!(Ok(3625), apply(Add, (150, 2), (2125, 3), (11, 3)));
+        // 1.50 - 2.125 = -0.625
+        assert_eq!(Ok(-625), apply(Subtract, (150, 2), (2125, 3), (11, 3)));
+        // 1.50 * 2.125 = 3.18750
+        assert_eq!(Ok(318750), apply(Multiply, (150, 2), (2125, 3), (16, 5)));
+        // 10.00 / 3 = 3.333333
+        assert_eq!(Ok(3333333), apply(Divide, (1000, 2), (3, 0), (13, 6)));
+        // 2.00 / 3 = 0.666667
+        assert_eq!(Ok(666667), apply(Divide, (200, 2), (3, 0), (13, 6)));
+        assert_eq!(
+            Err("division by zero".to_string()),
+            apply(Divide, (200, 2), (0, 0), (13, 6))
+        );
+        assert_eq!(
+            Err("value 10.00 does not fit in DECIMAL(3, 2)".to_string()),
+            apply(Add, (999, 2), (1, 2), (3, 2))
+        );
+    }
+
+    #[test]
+    fn decimals_from_bytes() {
+        assert_eq!(Some(0), from_be_bytes(&[]));
+        assert_eq!(Some(12345), from_be_bytes(&[0x30, 0x39]));
+        assert_eq!(Some(-1), from_be_bytes(&[0xff, 0xff, 0xff]));
+        assert_eq!(Some(-12345), from_be_bytes(&[0xcf, 0xc7]));
+
+        let mut bytes = vec![0xff; 4];
+        bytes.extend_from_slice(&[0xcf; 16]);
+        assert_eq!(None, from_be_bytes(&bytes));
+        let mut bytes = vec![0; 4];
+        bytes.extend_from_slice(&[0, 0, 0x30, 0x39]);
+        assert_eq!(Some(12345), from_be_bytes(&bytes));
+    }
+}
@@ -0,0 +1,97 @@
+// Copyright 2018 Grove Enterprises LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Rendering of plans as Graphviz DOT graphs
+
+/// Builds a directed graph in the Graphviz DOT language, where each node is a box with a
+/// multi-line label
+pub struct DotGraph {
+    name: String,
+    nodes: Vec<String>,
+    edges: Vec<(usize, usize)>,
+}
+
+impl DotGraph {
+    pub fn new(name: &str) -> Self {
+        DotGraph {
+            name: name.to_string(),
+            nodes: vec![],
+            edges: vec![],
+        }
+    }
+
+    /// Add a node labelled with one line per entry in `label`, returning the id of the node
+    pub fn add_node(&mut self, label: &[String]) -> usize {
+        let label = label
+            .iter()
+            .map(|line| escape(line))
+            .collect::<Vec<String>>()
+            .join("\\n");
+        self.nodes.push(label);
+        self.nodes.len() - 1
+    }
+
+    /// Add an edge between two nodes
+    pub fn add_edge(&mut self, from: usize, to: usize) {
+        self.edges.push((from, to));
+    }
+
+    /// Render the graph in the DOT language
+    pub fn to_dot(&self) -> String {
+        let mut lines = vec![
+            format!("digraph {} {{", self.name),
+            String::from("  node [shape=box];"),
+        ];
+        for (id, label) in self.nodes.iter().enumerate() {
+            lines.push(format!("  node{} [label=\"{}\"];", id, label));
+        }
+        for &(from, to) in &self.edges {
+            lines.push(format!("  node{} -> node{};", from, to));
+        }
+        lines.push(String::from("}"));
+        lines.join("\n")
+    }
+}
+
+/// Escape a string for use inside a quoted DOT label
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_graph() {
+        let mut graph = DotGraph::new("Plan");
+        let parent = graph.add_node(&vec!["Selection: #name Eq Utf8(\"Andy\")".to_string()]);
+        let child = graph.add_node(&vec![
+            "TableScan: people".to_string(),
+            "Schema: [id: Int32]".to_string(),
+        ]);
+        graph.add_edge(parent, child);
+        assert_eq!(
+            "digraph Plan {\
+             \n  node [shape=box];\
+             \n  node0 [label=\"Selection: #name Eq Utf8(\\\"Andy\\\")\"];\
+             \n  node1 [label=\"TableScan: people\\nSchema: [id: Int32]\"];\
+             \n  node0 -> node1;\
+             \n}",
+            graph.to_dot()
+        );
+    }
+}
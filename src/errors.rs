@@ -15,6 +15,9 @@
 //! Error types
 
 use super::sqlparser::ParserError;
+use std::cmp;
+use std::error;
+use std::fmt;
 use std::io::Error;
 use std::result;
 
@@ -26,15 +29,242 @@ macro_rules! df_error {
 
 pub type Result<T> = result::Result<T, ExecutionError>;
 
+/// Errors that can occur while parsing, planning or executing a query
 #[derive(Debug)]
 pub enum ExecutionError {
+    /// Error reading or writing a file
     IoError(Error),
+    /// The SQL could not be parsed
     ParserError(ParserError),
+    /// A query could not be planned, with the expression that was being planned when known
+    PlanError {
+        message: String,
+        expr: Option<String>,
+    },
+    /// An expression has a type that is not valid where it is used
+    TypeError {
+        message: String,
+        expr: Option<String>,
+    },
+    /// A table name could not be resolved, with the names of similar tables
+    TableNotFound {
+        name: String,
+        suggestions: Vec<String>,
+    },
+    /// A column name could not be resolved, with the names of similar columns
+    ColumnNotFound {
+        name: String,
+        suggestions: Vec<String>,
+    },
+    /// A column name matches more than one column
+    AmbiguousColumn {
+        name: String,
+        candidates: Vec<String>,
+    },
+    /// A value could not be read from a data source, with its location when known
+    DataError {
+        message: String,
+        file: Option<String>,
+        row: Option<usize>,
+        column: Option<String>,
+    },
     General(String),
+    #[deprecated(since = "0.3.3", note = "column errors are reported as ColumnNotFound")]
     InvalidColumn(String),
     NotImplemented,
 }
 
+impl ExecutionError {
+    /// Create a plan error that is not specific to an expression
+    pub fn plan(message: String) -> Self {
+        ExecutionError::PlanError {
+            message,
+            expr: None,
+        }
+    }
+
+    /// Create a plan error for an expression that could not be planned
+    pub fn plan_expr<E: fmt::Debug>(message: String, expr: &E) -> Self {
+        ExecutionError::PlanError {
+            message,
+            expr: Some(format!("{:?}", expr)),
+        }
+    }
+
+    /// Create a type error for an expression
+    pub fn type_error<E: fmt::Debug>(message: String, expr: &E) -> Self {
+        ExecutionError::TypeError {
+            message,
+            expr: Some(format!("{:?}", expr)),
+        }
+    }
+
+    /// Create an error for a value that could not be read from a data source
+    pub fn data(
+        message: String,
+        file: Option<&str>,
+        row: Option<usize>,
+        column: Option<&str>,
+    ) -> Self {
+        ExecutionError::DataError {
+            message,
+            file: file.map(|f| f.to_string()),
+            row,
+            column: column.map(|c| c.to_string()),
+        }
+    }
+
+    /// Create a type error for an operation that does not support the types of its operands
+    pub fn type_mismatch(message: String) -> Self {
+        ExecutionError::TypeError {
+            message,
+            expr: None,
+        }
+    }
+
+    /// Create an error for an unknown table, suggesting similarly named tables
+    pub fn table_not_found(name: &str, tables: &[String]) -> Self {
+        ExecutionError::TableNotFound {
+            name: name.to_string(),
+            suggestions: suggestions(name, tables),
+        }
+    }
+
+    /// Create an error for an unknown column, suggesting similarly named columns
+    pub fn column_not_found(name: &str, columns: &[String]) -> Self {
+        ExecutionError::ColumnNotFound {
+            name: name.to_string(),
+            suggestions: suggestions(name, columns),
+        }
+    }
+}
+
+/// Find the candidates that are similar to a name that could not be resolved, either because
+/// they only differ by case or qualifier, or because they are within a small edit distance
+fn suggestions(name: &str, candidates: &[String]) -> Vec<String> {
+    let name = name.to_lowercase();
+    let max_distance = cmp::max(1, (name.len() + 2) / 3);
+    let is_similar = |c: &str| c == name || edit_distance(c, &name) <= max_distance;
+    candidates
+        .iter()
+        .filter(|c| {
+            let c = c.to_lowercase();
+            is_similar(&c) || is_similar(c.rsplit('.').next().unwrap_or(""))
+        })
+        .cloned()
+        .collect()
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..b.len() + 1).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == *cb { 0 } else { 1 };
+            let value = cmp::min(substitution, cmp::min(previous[j + 1], current[j]) + 1);
+            current.push(value);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+fn quote_list(names: &[String]) -> String {
+    names
+        .iter()
+        .map(|n| format!("'{}'", n))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+#[allow(deprecated)]
+impl fmt::Display for ExecutionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExecutionError::IoError(e) => write!(f, "IO error: {}", e),
+            ExecutionError::ParserError(e) => write!(f, "SQL error: {}", e),
+            ExecutionError::PlanError { message, expr } => match expr {
+                Some(expr) => write!(f, "Plan error: {} in expression {}", message, expr),
+                None => write!(f, "Plan error: {}", message),
+            },
+            ExecutionError::TypeError { message, expr } => match expr {
+                Some(expr) => write!(f, "Type error: {} in expression {}", message, expr),
+                None => write!(f, "Type error: {}", message),
+            },
+            ExecutionError::TableNotFound { name, suggestions } => {
+                write!(f, "Table '{}' not found", name)?;
+                if !suggestions.is_empty() {
+                    write!(f, ", did you mean {}?", quote_list(suggestions))?;
+                }
+                Ok(())
+            }
+            ExecutionError::ColumnNotFound { name, suggestions } => {
+                write!(f, "Column '{}' not found", name)?;
+                if !suggestions.is_empty() {
+                    write!(f, ", did you mean {}?", quote_list(suggestions))?;
+                }
+                Ok(())
+            }
+            ExecutionError::AmbiguousColumn { name, candidates } => write!(
+                f,
+                "Column reference '{}' is ambiguous, it could refer to {}",
+                name,
+                quote_list(candidates)
+            ),
+            ExecutionError::DataError {
+                message,
+                file,
+                row,
+                column,
+            } => {
+                write!(f, "Data error: {}", message)?;
+                if let Some(file) = file {
+                    write!(f, " in file {}", file)?;
+                }
+                if let Some(row) = row {
+                    write!(f, " at row {}", row)?;
+                }
+                if let Some(column) = column {
+                    write!(f, " in column {}", column)?;
+                }
+                Ok(())
+            }
+            ExecutionError::General(message) => write!(f, "{}", message),
+            ExecutionError::InvalidColumn(message) => write!(f, "Invalid column: {}", message),
+            ExecutionError::NotImplemented => write!(f, "Not implemented"),
+        }
+    }
+}
+
+#[allow(deprecated)]
+impl error::Error for ExecutionError {
+    fn description(&self) -> &str {
+        match self {
+            ExecutionError::IoError(_) => "IO error",
+            ExecutionError::ParserError(_) => "SQL error",
+            ExecutionError::PlanError { .. } => "plan error",
+            ExecutionError::TypeError { .. } => "type error",
+            ExecutionError::TableNotFound { .. } => "table not found",
+            ExecutionError::ColumnNotFound { .. } => "column not found",
+            ExecutionError::AmbiguousColumn { .. } => "ambiguous column",
+            ExecutionError::DataError { .. } => "data error",
+            ExecutionError::General(message) => message.as_str(),
+            ExecutionError::InvalidColumn(_) => "invalid column",
+            ExecutionError::NotImplemented => "not implemented",
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        match self {
+            ExecutionError::IoError(e) => Some(e),
+            ExecutionError::ParserError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
 impl From<Error> for ExecutionError {
     fn from(e: Error) -> Self {
         ExecutionError::IoError(e)
@@ -58,3 +288,54 @@ impl From<ParserError> for ExecutionError {
         ExecutionError::ParserError(e)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn column_not_found_suggestions() {
+        let columns = vec![
+            "people.id".to_string(),
+            "people.name".to_string(),
+            "people.city".to_string(),
+        ];
+        let e = ExecutionError::column_not_found("nmae", &columns);
+        assert_eq!(
+            "Column 'nmae' not found, did you mean 'people.name'?",
+            e.to_string()
+        );
+        let e = ExecutionError::column_not_found("ID", &columns);
+        assert_eq!(
+            "Column 'ID' not found, did you mean 'people.id'?",
+            e.to_string()
+        );
+        let e = ExecutionError::column_not_found("banana", &columns);
+        assert_eq!("Column 'banana' not found", e.to_string());
+    }
+
+    #[test]
+    fn data_error_location() {
+        let e = ExecutionError::DataError {
+            message: "Failed to parse 'x' as i32".to_string(),
+            file: Some("test/data/people.csv".to_string()),
+            row: Some(3),
+            column: Some("id".to_string()),
+        };
+        assert_eq!(
+            "Data error: Failed to parse 'x' as i32 in file test/data/people.csv at row 3 \
+             in column id",
+            e.to_string()
+        );
+    }
+
+    #[test]
+    fn parser_error_cause() {
+        use super::super::sqlparser::Parser;
+        use std::error::Error;
+
+        let e = ExecutionError::from(Parser::parse_sql("SELECT (".to_string()).unwrap_err());
+        let cause = e.cause().expect("parser errors have a cause");
+        assert_eq!(e.to_string(), format!("SQL error: {}", cause));
+    }
+}
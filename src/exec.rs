@@ -14,39 +14,57 @@
 
 //! Query execution
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::clone::Clone;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::convert::*;
+use std::fs;
 use std::fs::File;
 use std::io::BufWriter;
 use std::iter::Iterator;
 use std::rc::Rc;
-use std::str;
+use std::result;
 use std::string::String;
 
-use arrow::array::ListArray;
+use arrow::array::{ListArray, PrimitiveArray};
 use arrow::builder::*;
-use arrow::datatypes::*;
 use arrow::list_builder::*;
 
+use super::arithmetic::{Arithmetic, ArithmeticOperator, OverflowMode};
+use super::cast;
+use super::costmodel::CostModel;
 use super::dataframe::*;
 use super::datasources::common::*;
 use super::datasources::csv::*;
 use super::datasources::empty::*;
 use super::datasources::ndjson::*;
 use super::datasources::parquet::*;
+use super::datatypes::*;
+use super::decimal::{self, DecimalOperator};
+use super::dot::DotGraph;
 use super::errors::*;
+use super::functions::array;
+use super::functions::binary;
+use super::functions::datetime;
+use super::functions::structs;
 use super::logical::*;
+use super::metrics::*;
+use super::optimizer::*;
 use super::relations::aggregate::*;
+use super::relations::explain::*;
 use super::relations::filter::*;
+use super::relations::join::*;
 use super::relations::limit::*;
 use super::relations::projection::*;
+use super::relations::union::*;
+use super::relations::unnest::*;
+use super::sqlast::ASTNode;
 use super::sqlast::ASTNode::*;
-use super::sqlast::FileType;
+use super::sqlast::{ExplainFormat, FileType, SQLIdent};
 use super::sqlparser::*;
 use super::sqlplanner::*;
+use super::temporal::{self, Interval};
 use super::types::*;
 //use super::cluster::*;
 
@@ -71,8 +89,21 @@ macro_rules! compare_arrays_inner {
                 Ok(a.iter().zip(b.iter()).map($F).collect::<Vec<bool>>()),
             (&ArrayData::Int64(ref a), &ArrayData::Int64(ref b)) =>
                 Ok(a.iter().zip(b.iter()).map($F).collect::<Vec<bool>>()),
-            //(&ArrayData::Utf8(ref a), &ScalarValue::Utf8(ref b)) => a.iter().map(|n| n > b).collect(),
-            _ => Err(ExecutionError::General("Unsupported types in compare_arrays_inner".to_string()))
+            (&ArrayData::Date32(ref a), &ArrayData::Date32(ref b)) =>
+                Ok(a.iter().zip(b.iter()).map($F).collect::<Vec<bool>>()),
+            (&ArrayData::Time64(ref a, ua), &ArrayData::Time64(ref b, ub)) if ua == ub =>
+                Ok(a.iter().zip(b.iter()).map($F).collect::<Vec<bool>>()),
+            (&ArrayData::Timestamp(ref a, ua), &ArrayData::Timestamp(ref b, ub)) if ua == ub =>
+                Ok(a.iter().zip(b.iter()).map($F).collect::<Vec<bool>>()),
+            (&ArrayData::Decimal(ref a, _, sa), &ArrayData::Decimal(ref b, _, sb)) if sa == sb =>
+                Ok(a.iter().cloned().zip(b.iter().cloned()).map($F).collect::<Vec<bool>>()),
+            (&ArrayData::Utf8(ref a), &ArrayData::Utf8(ref b)) =>
+                Ok((0..a.len()).map(|i| (a.get(i), b.get(i))).map($F).collect::<Vec<bool>>()),
+            (&ArrayData::Binary(ref a), &ArrayData::Binary(ref b)) =>
+                Ok((0..a.len()).map(|i| (a.get(i), b.get(i))).map($F).collect::<Vec<bool>>()),
+            _ => Err(ExecutionError::type_mismatch(
+                "Unsupported types in compare_arrays_inner".to_string()
+            ))
         }
     }
 }
@@ -118,7 +149,27 @@ macro_rules! compare_array_with_scalar_inner {
             (&ArrayData::Float64(ref a), &ScalarValue::Float64(b)) => {
                 Ok(a.iter().map(|aa| (aa, b)).map($F).collect::<Vec<bool>>())
             }
-            _ => Err(ExecutionError::General(
+            (&ArrayData::Date32(ref a), &ScalarValue::Date32(b)) => {
+                Ok(a.iter().map(|aa| (aa, b)).map($F).collect::<Vec<bool>>())
+            }
+            (&ArrayData::Time64(ref a, ua), &ScalarValue::Time64(b, ub)) if ua == ub => {
+                Ok(a.iter().map(|aa| (aa, b)).map($F).collect::<Vec<bool>>())
+            }
+            (&ArrayData::Timestamp(ref a, ua), &ScalarValue::Timestamp(b, ub)) if ua == ub => {
+                Ok(a.iter().map(|aa| (aa, b)).map($F).collect::<Vec<bool>>())
+            }
+            (&ArrayData::Decimal(ref a, _, sa), &ScalarValue::Decimal(b, _, sb)) if sa == sb => {
+                Ok(a.iter().map(|aa| (*aa, b)).map($F).collect::<Vec<bool>>())
+            }
+            (&ArrayData::Utf8(ref a), &ScalarValue::Utf8(ref b)) => Ok((0..a.len())
+                .map(|i| (a.get(i), b.as_bytes()))
+                .map($F)
+                .collect::<Vec<bool>>()),
+            (&ArrayData::Binary(ref a), &ScalarValue::Binary(ref b)) => Ok((0..a.len())
+                .map(|i| (a.get(i), b.as_slice()))
+                .map($F)
+                .collect::<Vec<bool>>()),
+            _ => Err(ExecutionError::type_mismatch(
                 "Unsupported types in compare_array_with_scalar_inner".to_string(),
             )),
         }
@@ -133,122 +184,201 @@ macro_rules! compare_array_with_scalar {
     };
 }
 
-macro_rules! inner_column_operations {
-    ($A:ident, $B:ident, $F:expr, $RT:ident) => {
-        Ok(Value::Column(Rc::new(Array::from(
-            $A.iter().zip($B.iter()).map($F).collect::<Vec<$RT>>(),
-        ))))
-    };
+/// Apply an arithmetic operator to each pair of values. Rows that are null in `validity` hold
+/// placeholder values, which are skipped so that they cannot overflow or divide by zero.
+fn arithmetic_kernel<T, I>(
+    pairs: I,
+    validity: &Option<Vec<bool>>,
+    op: ArithmeticOperator,
+    mode: OverflowMode,
+) -> Result<Value>
+where
+    T: Arithmetic,
+    I: Iterator<Item = (T, T)>,
+    Array: From<Vec<T>>,
+{
+    let mut values: Vec<T> = vec![];
+    let mut valid: Vec<bool> = vec![];
+    for (i, (a, b)) in pairs.enumerate() {
+        let result = match validity {
+            Some(ref v) if !v[i] => Ok(None),
+            _ => T::apply(op, a, b, mode),
+        };
+        match result {
+            Ok(v) => {
+                values.push(v.unwrap_or_default());
+                valid.push(v.is_some());
+            }
+            Err(message) => {
+                return Err(ExecutionError::DataError {
+                    message,
+                    file: None,
+                    row: Some(i),
+                    column: None,
+                })
+            }
+        }
+    }
+    Ok(Value::Column(Rc::new(Array::with_validity(
+        Array::from(values).into_data(),
+        &valid,
+    ))))
+}
+
+/// Apply an arithmetic operator to two scalars, where a null result is `ScalarValue::Null`
+fn arithmetic_scalar<T, F>(
+    a: T,
+    b: T,
+    op: ArithmeticOperator,
+    mode: OverflowMode,
+    to_scalar: F,
+) -> Result<Value>
+where
+    T: Arithmetic,
+    F: Fn(T) -> ScalarValue,
+{
+    match T::apply(op, a, b, mode) {
+        Ok(v) => Ok(Value::Scalar(Rc::new(v.map_or(ScalarValue::Null, to_scalar)))),
+        Err(message) => Err(ExecutionError::DataError {
+            message,
+            file: None,
+            row: None,
+            column: None,
+        }),
+    }
 }
 
+/// Apply an operator to each value of column `$A` and scalar `$B`, where `$SCALAR_LEFT` is true
+/// if the scalar is the left operand
 macro_rules! scalar_operations {
-    ($A:ident, $B:ident, $F:expr, $RT:ident) => {
-        Ok(Value::Column(Rc::new(Array::from(
-            $A.iter().map(|aa| (aa, $B)).map($F).collect::<Vec<$RT>>(),
-        ))))
+    ($A:ident, $B:ident, $VALID:ident, $OP:ident, $MODE:ident, $SCALAR_LEFT:expr) => {
+        if $SCALAR_LEFT {
+            arithmetic_kernel($A.iter().map(|aa| (*$B, aa)), $VALID, $OP, $MODE)
+        } else {
+            arithmetic_kernel($A.iter().map(|aa| (aa, *$B)), $VALID, $OP, $MODE)
+        }
     };
 }
 
 macro_rules! scalar_column_operations {
-    ($X1:ident, $X2:ident, $F:expr) => {
+    ($X1:ident, $X2:ident, $VALID:ident, $OP:ident, $MODE:ident, $SCALAR_LEFT:expr) => {
         match ($X1.as_ref(), $X2.data()) {
-            (ScalarValue::UInt8(a), ArrayData::UInt8(b)) => scalar_operations!(b, a, $F, u8),
-            (ScalarValue::UInt16(a), ArrayData::UInt16(b)) => scalar_operations!(b, a, $F, u16),
-            (ScalarValue::UInt32(a), ArrayData::UInt32(b)) => scalar_operations!(b, a, $F, u32),
-            (ScalarValue::UInt64(a), ArrayData::UInt64(b)) => scalar_operations!(b, a, $F, u64),
-            (ScalarValue::Int8(a), ArrayData::Int8(b)) => scalar_operations!(b, a, $F, i8),
-            (ScalarValue::Int16(a), ArrayData::Int16(b)) => scalar_operations!(b, a, $F, i16),
-            (ScalarValue::Int32(a), ArrayData::Int32(b)) => scalar_operations!(b, a, $F, i32),
-            (ScalarValue::Int64(a), ArrayData::Int64(b)) => scalar_operations!(b, a, $F, i64),
+            (ScalarValue::UInt8(a), ArrayData::UInt8(b)) => {
+                scalar_operations!(b, a, $VALID, $OP, $MODE, $SCALAR_LEFT)
+            }
+            (ScalarValue::UInt16(a), ArrayData::UInt16(b)) => {
+                scalar_operations!(b, a, $VALID, $OP, $MODE, $SCALAR_LEFT)
+            }
+            (ScalarValue::UInt32(a), ArrayData::UInt32(b)) => {
+                scalar_operations!(b, a, $VALID, $OP, $MODE, $SCALAR_LEFT)
+            }
+            (ScalarValue::UInt64(a), ArrayData::UInt64(b)) => {
+                scalar_operations!(b, a, $VALID, $OP, $MODE, $SCALAR_LEFT)
+            }
+            (ScalarValue::Int8(a), ArrayData::Int8(b)) => {
+                scalar_operations!(b, a, $VALID, $OP, $MODE, $SCALAR_LEFT)
+            }
+            (ScalarValue::Int16(a), ArrayData::Int16(b)) => {
+                scalar_operations!(b, a, $VALID, $OP, $MODE, $SCALAR_LEFT)
+            }
+            (ScalarValue::Int32(a), ArrayData::Int32(b)) => {
+                scalar_operations!(b, a, $VALID, $OP, $MODE, $SCALAR_LEFT)
+            }
+            (ScalarValue::Int64(a), ArrayData::Int64(b)) => {
+                scalar_operations!(b, a, $VALID, $OP, $MODE, $SCALAR_LEFT)
+            }
             (ScalarValue::Float32(a), ArrayData::Float32(b)) => {
-                scalar_operations!(b, a, $F, f32)
+                scalar_operations!(b, a, $VALID, $OP, $MODE, $SCALAR_LEFT)
             }
             (ScalarValue::Float64(a), ArrayData::Float64(b)) => {
-                scalar_operations!(b, a, $F, f64)
+                scalar_operations!(b, a, $VALID, $OP, $MODE, $SCALAR_LEFT)
             }
-            ref t => panic!(
+            ref t => Err(ExecutionError::type_mismatch(format!(
                 "Cannot combine results for Scalar Type: {} and Column: {}",
                 t.0, t.1
-            ),
+            ))),
         };
     };
 }
 
 macro_rules! scalar_scalar_operations {
-    ($X1:ident, $X2:ident, $F:expr) => {
+    ($X1:ident, $X2:ident, $OP:ident, $MODE:ident) => {
         match ($X1.as_ref(), $X2.as_ref()) {
             (ScalarValue::UInt8(a), ScalarValue::UInt8(b)) => {
-                Ok(Value::Scalar(Rc::new(ScalarValue::UInt8($F(a, b)))))
+                arithmetic_scalar(*a, *b, $OP, $MODE, ScalarValue::UInt8)
             }
             (ScalarValue::UInt16(a), ScalarValue::UInt16(b)) => {
-                Ok(Value::Scalar(Rc::new(ScalarValue::UInt16($F(a, b)))))
+                arithmetic_scalar(*a, *b, $OP, $MODE, ScalarValue::UInt16)
             }
             (ScalarValue::UInt32(a), ScalarValue::UInt32(b)) => {
-                Ok(Value::Scalar(Rc::new(ScalarValue::UInt32($F(a, b)))))
+                arithmetic_scalar(*a, *b, $OP, $MODE, ScalarValue::UInt32)
             }
             (ScalarValue::UInt64(a), ScalarValue::UInt64(b)) => {
-                Ok(Value::Scalar(Rc::new(ScalarValue::UInt64($F(a, b)))))
+                arithmetic_scalar(*a, *b, $OP, $MODE, ScalarValue::UInt64)
             }
             (ScalarValue::Int8(a), ScalarValue::Int8(b)) => {
-                Ok(Value::Scalar(Rc::new(ScalarValue::Int8($F(a, b)))))
+                arithmetic_scalar(*a, *b, $OP, $MODE, ScalarValue::Int8)
             }
             (ScalarValue::Int16(a), ScalarValue::Int16(b)) => {
-                Ok(Value::Scalar(Rc::new(ScalarValue::Int16($F(a, b)))))
+                arithmetic_scalar(*a, *b, $OP, $MODE, ScalarValue::Int16)
             }
             (ScalarValue::Int32(a), ScalarValue::Int32(b)) => {
-                Ok(Value::Scalar(Rc::new(ScalarValue::Int32($F(a, b)))))
+                arithmetic_scalar(*a, *b, $OP, $MODE, ScalarValue::Int32)
             }
             (ScalarValue::Int64(a), ScalarValue::Int64(b)) => {
-                Ok(Value::Scalar(Rc::new(ScalarValue::Int64($F(a, b)))))
+                arithmetic_scalar(*a, *b, $OP, $MODE, ScalarValue::Int64)
             }
             (ScalarValue::Float32(a), ScalarValue::Float32(b)) => {
-                Ok(Value::Scalar(Rc::new(ScalarValue::Float32($F(a, b)))))
+                arithmetic_scalar(*a, *b, $OP, $MODE, ScalarValue::Float32)
             }
             (ScalarValue::Float64(a), ScalarValue::Float64(b)) => {
-                Ok(Value::Scalar(Rc::new(ScalarValue::Float64($F(a, b)))))
+                arithmetic_scalar(*a, *b, $OP, $MODE, ScalarValue::Float64)
             }
-            ref t => panic!(
+            ref t => Err(ExecutionError::type_mismatch(format!(
                 "Cannot combine results for Scalar Type: {} and Column: {}",
                 t.0, t.1
-            ),
+            ))),
         };
     };
 }
 
 macro_rules! column_operations {
-    ($X:ident, $Y:ident, $F:expr) => {
+    ($X:ident, $Y:ident, $VALID:ident, $OP:ident, $MODE:ident) => {
         match ($X.data(), $Y.data()) {
             (ArrayData::UInt8(ref a), ArrayData::UInt8(ref b)) => {
-                inner_column_operations!(a, b, $F, u8)
+                arithmetic_kernel(a.iter().zip(b.iter()), $VALID, $OP, $MODE)
             }
             (ArrayData::UInt16(ref a), ArrayData::UInt16(ref b)) => {
-                inner_column_operations!(a, b, $F, u16)
+                arithmetic_kernel(a.iter().zip(b.iter()), $VALID, $OP, $MODE)
             }
             (ArrayData::UInt32(ref a), ArrayData::UInt32(ref b)) => {
-                inner_column_operations!(a, b, $F, u32)
+                arithmetic_kernel(a.iter().zip(b.iter()), $VALID, $OP, $MODE)
             }
             (ArrayData::UInt64(ref a), ArrayData::UInt64(ref b)) => {
-                inner_column_operations!(a, b, $F, u64)
+                arithmetic_kernel(a.iter().zip(b.iter()), $VALID, $OP, $MODE)
             }
             (ArrayData::Int8(ref a), ArrayData::Int8(ref b)) => {
-                inner_column_operations!(a, b, $F, i8)
+                arithmetic_kernel(a.iter().zip(b.iter()), $VALID, $OP, $MODE)
             }
             (ArrayData::Int16(ref a), ArrayData::Int16(ref b)) => {
-                inner_column_operations!(a, b, $F, i16)
+                arithmetic_kernel(a.iter().zip(b.iter()), $VALID, $OP, $MODE)
             }
             (ArrayData::Int32(ref a), ArrayData::Int32(ref b)) => {
-                inner_column_operations!(a, b, $F, i32)
+                arithmetic_kernel(a.iter().zip(b.iter()), $VALID, $OP, $MODE)
             }
             (ArrayData::Int64(ref a), ArrayData::Int64(ref b)) => {
-                inner_column_operations!(a, b, $F, i64)
+                arithmetic_kernel(a.iter().zip(b.iter()), $VALID, $OP, $MODE)
             }
             (ArrayData::Float32(ref a), ArrayData::Float32(ref b)) => {
-                inner_column_operations!(a, b, $F, f32)
+                arithmetic_kernel(a.iter().zip(b.iter()), $VALID, $OP, $MODE)
             }
             (ArrayData::Float64(ref a), ArrayData::Float64(ref b)) => {
-                inner_column_operations!(a, b, $F, f64)
+                arithmetic_kernel(a.iter().zip(b.iter()), $VALID, $OP, $MODE)
             }
-            ref t => panic!("Incompatible types for Column: {} and Column: {}", t.0, t.1),
+            ref t => Err(ExecutionError::type_mismatch(format!(
+                "Incompatible types for Column: {} and Column: {}",
+                t.0, t.1
+            ))),
         }
     };
 }
@@ -283,7 +413,9 @@ impl Value {
                     ArrayData::from(bools),
                 ))))
             }
-            Value::Scalar(_) => unimplemented!(),
+            Value::Scalar(ref v) => Ok(Value::Scalar(Rc::new(ScalarValue::Boolean(
+                v.as_ref() == &ScalarValue::Null,
+            )))),
         }
     }
 
@@ -316,12 +448,28 @@ impl Value {
                     ArrayData::from(bools),
                 ))))
             }
-            Value::Scalar(_) => unimplemented!(),
+            Value::Scalar(ref v) => Ok(Value::Scalar(Rc::new(ScalarValue::Boolean(
+                v.as_ref() != &ScalarValue::Null,
+            )))),
+        }
+    }
+
+    /// Apply a kernel to two values, where the result is null wherever either value is null.
+    /// The kernel is given the validity of the result, or `None` if no row is null.
+    fn null_aware<F>(&self, other: &Value, kernel: F) -> Result<Value>
+    where
+        F: Fn(&Value, &Value, &Option<Vec<bool>>) -> Result<Value>,
+    {
+        if is_null_scalar(self) || is_null_scalar(other) {
+            return Ok(Value::Scalar(Rc::new(ScalarValue::Null)));
         }
+        let validity = combined_validity(&[self.clone(), other.clone()]);
+        let result = kernel(self, other, &validity)?;
+        Ok(with_validity(result, validity))
     }
 
     pub fn eq(&self, other: &Value) -> Result<Value> {
-        match (self, other) {
+        self.null_aware(other, |left, right, _| match (left, right) {
             (&Value::Column(ref v1), &Value::Column(ref v2)) => {
                 compare_arrays!(v1, v2, |(aa, bb)| aa == bb)
             }
@@ -333,17 +481,26 @@ impl Value {
                     }
                     Ok(Value::Column(Rc::new(Array::from(v))))
                 }
-                _ => compare_array_with_scalar!(v1, v2, |(aa, bb)| aa != bb),
+                (&ArrayData::Binary(ref list), &ScalarValue::Binary(ref b)) => {
+                    let mut v: Vec<bool> = Vec::with_capacity(list.len() as usize);
+                    for i in 0..list.len() as usize {
+                        v.push(list.get(i) == b.as_slice());
+                    }
+                    Ok(Value::Column(Rc::new(Array::from(v))))
+                }
+                _ => compare_array_with_scalar!(v1, v2, |(aa, bb)| aa == bb),
             },
             (&Value::Scalar(ref v1), &Value::Column(ref v2)) => {
                 compare_array_with_scalar!(v2, v1, |(aa, bb)| aa == bb)
             }
-            (&Value::Scalar(ref _v1), &Value::Scalar(ref _v2)) => unimplemented!(),
-        }
+            (&Value::Scalar(ref v1), &Value::Scalar(ref v2)) => {
+                compare_scalars(v1, v2, |a, b| a == b)
+            }
+        })
     }
 
     pub fn not_eq(&self, other: &Value) -> Result<Value> {
-        match (self, other) {
+        self.null_aware(other, |left, right, _| match (left, right) {
             (&Value::Column(ref v1), &Value::Column(ref v2)) => {
                 compare_arrays!(v1, v2, |(aa, bb)| aa != bb)
             }
@@ -355,286 +512,777 @@ impl Value {
                     }
                     Ok(Value::Column(Rc::new(Array::from(v))))
                 }
+                (&ArrayData::Binary(ref list), &ScalarValue::Binary(ref b)) => {
+                    let mut v: Vec<bool> = Vec::with_capacity(list.len() as usize);
+                    for i in 0..list.len() as usize {
+                        v.push(list.get(i) != b.as_slice());
+                    }
+                    Ok(Value::Column(Rc::new(Array::from(v))))
+                }
                 _ => compare_array_with_scalar!(v1, v2, |(aa, bb)| aa != bb),
             },
             (&Value::Scalar(ref v1), &Value::Column(ref v2)) => {
                 compare_array_with_scalar!(v2, v1, |(aa, bb)| aa != bb)
             }
-            (&Value::Scalar(ref _v1), &Value::Scalar(ref _v2)) => unimplemented!(),
-        }
+            (&Value::Scalar(ref v1), &Value::Scalar(ref v2)) => {
+                compare_scalars(v1, v2, |a, b| a != b)
+            }
+        })
     }
 
     pub fn lt(&self, other: &Value) -> Result<Value> {
-        match (self, other) {
+        self.null_aware(other, |left, right, _| match (left, right) {
             (&Value::Column(ref v1), &Value::Column(ref v2)) => {
                 compare_arrays!(v1, v2, |(aa, bb)| aa < bb)
             }
             (&Value::Column(ref v1), &Value::Scalar(ref v2)) => {
                 compare_array_with_scalar!(v1, v2, |(aa, bb)| aa < bb)
             }
+            // the column is the right operand, so the comparison is mirrored
             (&Value::Scalar(ref v1), &Value::Column(ref v2)) => {
-                compare_array_with_scalar!(v2, v1, |(aa, bb)| aa < bb)
+                compare_array_with_scalar!(v2, v1, |(aa, bb)| aa > bb)
             }
-            (&Value::Scalar(ref _v1), &Value::Scalar(ref _v2)) => unimplemented!(),
-        }
+            (&Value::Scalar(ref v1), &Value::Scalar(ref v2)) => {
+                compare_scalars(v1, v2, |a, b| a < b)
+            }
+        })
     }
 
     pub fn lt_eq(&self, other: &Value) -> Result<Value> {
-        match (self, other) {
+        self.null_aware(other, |left, right, _| match (left, right) {
             (&Value::Column(ref v1), &Value::Column(ref v2)) => {
                 compare_arrays!(v1, v2, |(aa, bb)| aa <= bb)
             }
             (&Value::Column(ref v1), &Value::Scalar(ref v2)) => {
                 compare_array_with_scalar!(v1, v2, |(aa, bb)| aa <= bb)
             }
+            // the column is the right operand, so the comparison is mirrored
             (&Value::Scalar(ref v1), &Value::Column(ref v2)) => {
-                compare_array_with_scalar!(v2, v1, |(aa, bb)| aa <= bb)
+                compare_array_with_scalar!(v2, v1, |(aa, bb)| aa >= bb)
             }
-            (&Value::Scalar(ref _v1), &Value::Scalar(ref _v2)) => unimplemented!(),
-        }
+            (&Value::Scalar(ref v1), &Value::Scalar(ref v2)) => {
+                compare_scalars(v1, v2, |a, b| a <= b)
+            }
+        })
     }
 
     pub fn gt(&self, other: &Value) -> Result<Value> {
-        match (self, other) {
+        self.null_aware(other, |left, right, _| match (left, right) {
             (&Value::Column(ref v1), &Value::Column(ref v2)) => {
-                compare_arrays!(v1, v2, |(aa, bb)| aa >= bb)
+                compare_arrays!(v1, v2, |(aa, bb)| aa > bb)
             }
             (&Value::Column(ref v1), &Value::Scalar(ref v2)) => {
-                compare_array_with_scalar!(v1, v2, |(aa, bb)| aa >= bb)
+                compare_array_with_scalar!(v1, v2, |(aa, bb)| aa > bb)
             }
+            // the column is the right operand, so the comparison is mirrored
             (&Value::Scalar(ref v1), &Value::Column(ref v2)) => {
-                compare_array_with_scalar!(v2, v1, |(aa, bb)| aa >= bb)
+                compare_array_with_scalar!(v2, v1, |(aa, bb)| aa < bb)
             }
-            (&Value::Scalar(ref _v1), &Value::Scalar(ref _v2)) => unimplemented!(),
-        }
+            (&Value::Scalar(ref v1), &Value::Scalar(ref v2)) => {
+                compare_scalars(v1, v2, |a, b| a > b)
+            }
+        })
     }
 
     pub fn gt_eq(&self, other: &Value) -> Result<Value> {
-        match (self, other) {
+        self.null_aware(other, |left, right, _| match (left, right) {
             (&Value::Column(ref v1), &Value::Column(ref v2)) => {
-                compare_arrays!(v1, v2, |(aa, bb)| aa > bb)
+                compare_arrays!(v1, v2, |(aa, bb)| aa >= bb)
             }
             (&Value::Column(ref v1), &Value::Scalar(ref v2)) => {
-                compare_array_with_scalar!(v1, v2, |(aa, bb)| aa > bb)
+                compare_array_with_scalar!(v1, v2, |(aa, bb)| aa >= bb)
             }
+            // the column is the right operand, so the comparison is mirrored
             (&Value::Scalar(ref v1), &Value::Column(ref v2)) => {
-                compare_array_with_scalar!(v2, v1, |(aa, bb)| aa > bb)
+                compare_array_with_scalar!(v2, v1, |(aa, bb)| aa <= bb)
             }
-            (&Value::Scalar(ref _v1), &Value::Scalar(ref _v2)) => unimplemented!(),
-        }
+            (&Value::Scalar(ref v1), &Value::Scalar(ref v2)) => {
+                compare_scalars(v1, v2, |a, b| a >= b)
+            }
+        })
     }
 
-    pub fn add(&self, other: &Value) -> Result<Value> {
-        match (self, other) {
-            (&Value::Column(ref v1), &Value::Column(ref v2)) => {
-                column_operations!(v1, v2, |(x, y)| x + y)
-            }
-            (&Value::Scalar(ref v1), &Value::Column(ref v2)) => {
-                scalar_column_operations!(v1, v2, |(x, y)| x + y)
+    /// Apply an arithmetic operator. Integer overflow and division by zero are handled as
+    /// described by `mode`.
+    pub fn arithmetic(
+        &self,
+        op: ArithmeticOperator,
+        other: &Value,
+        mode: OverflowMode,
+    ) -> Result<Value> {
+        self.null_aware(other, |left, right, validity| {
+            if let Some(interval) = interval_operand(right) {
+                match op {
+                    ArithmeticOperator::Add => return left.add_interval(&interval),
+                    ArithmeticOperator::Subtract => return left.add_interval(&interval.negate()),
+                    _ => {}
+                }
             }
-            (&Value::Column(ref v1), &Value::Scalar(ref v2)) => {
-                scalar_column_operations!(v2, v1, |(x, y)| x + y)
+            if is_decimal(left) || is_decimal(right) {
+                return decimal_arithmetic(left, op, right, validity, mode);
             }
-            (&Value::Scalar(ref x1), &Value::Scalar(ref x2)) => {
-                scalar_scalar_operations!(x1, x2, |x, y| x + y)
+            match (left, right) {
+                (&Value::Column(ref v1), &Value::Column(ref v2)) => {
+                    column_operations!(v1, v2, validity, op, mode)
+                }
+                (&Value::Scalar(ref v1), &Value::Column(ref v2)) => {
+                    scalar_column_operations!(v1, v2, validity, op, mode, true)
+                }
+                (&Value::Column(ref v1), &Value::Scalar(ref v2)) => {
+                    scalar_column_operations!(v2, v1, validity, op, mode, false)
+                }
+                (&Value::Scalar(ref x1), &Value::Scalar(ref x2)) => {
+                    scalar_scalar_operations!(x1, x2, op, mode)
+                }
             }
-        }
+        })
     }
 
-    pub fn subtract(&self, other: &Value) -> Result<Value> {
-        match (self, other) {
-            (&Value::Column(ref v1), &Value::Column(ref v2)) => {
-                column_operations!(v1, v2, |(x, y)| x - y)
-            }
-            (&Value::Scalar(ref v1), &Value::Column(ref v2)) => {
-                scalar_column_operations!(v1, v2, |(x, y)| x - y)
-            }
-            (&Value::Column(ref v1), &Value::Scalar(ref v2)) => {
-                scalar_column_operations!(v2, v1, |(x, y)| x - y)
-            }
-            (&Value::Scalar(ref x1), &Value::Scalar(ref x2)) => {
-                scalar_scalar_operations!(x1, x2, |x, y| x - y)
-            }
-        }
+    pub fn add(&self, other: &Value) -> Result<Value> {
+        self.arithmetic(ArithmeticOperator::Add, other, OverflowMode::default())
     }
 
-    pub fn divide(&self, other: &Value) -> Result<Value> {
-        match (self, other) {
-            (&Value::Column(ref v1), &Value::Column(ref v2)) => {
-                column_operations!(v1, v2, |(x, y)| x / y)
-            }
-            (&Value::Scalar(ref v1), &Value::Column(ref v2)) => {
-                scalar_column_operations!(v1, v2, |(x, y)| x / y)
-            }
-            (&Value::Column(ref v1), &Value::Scalar(ref v2)) => {
-                scalar_column_operations!(v2, v1, |(x, y)| x / y)
-            }
-            (&Value::Scalar(ref x1), &Value::Scalar(ref x2)) => {
-                scalar_scalar_operations!(x1, x2, |x, y| x / y)
-            }
-        }
+    pub fn subtract(&self, other: &Value) -> Result<Value> {
+        self.arithmetic(ArithmeticOperator::Subtract, other, OverflowMode::default())
     }
 
     pub fn multiply(&self, other: &Value) -> Result<Value> {
-        match (self, other) {
-            (&Value::Column(ref v1), &Value::Column(ref v2)) => {
-                column_operations!(v1, v2, |(x, y)| x * y)
-            }
-            (&Value::Scalar(ref v1), &Value::Column(ref v2)) => {
-                scalar_column_operations!(v1, v2, |(x, y)| x * y)
-            }
-            (&Value::Column(ref v1), &Value::Scalar(ref v2)) => {
-                scalar_column_operations!(v2, v1, |(x, y)| x * y)
-            }
-            (&Value::Scalar(ref x1), &Value::Scalar(ref x2)) => {
-                scalar_scalar_operations!(x1, x2, |x, y| x * y)
-            }
-        }
+        self.arithmetic(ArithmeticOperator::Multiply, other, OverflowMode::default())
+    }
+
+    pub fn divide(&self, other: &Value) -> Result<Value> {
+        self.arithmetic(ArithmeticOperator::Divide, other, OverflowMode::default())
     }
 
     pub fn modulo(&self, other: &Value) -> Result<Value> {
-        match (self, other) {
-            (&Value::Column(ref v1), &Value::Column(ref v2)) => {
-                column_operations!(v1, v2, |(x, y)| x % y)
-            }
-            (&Value::Scalar(ref v1), &Value::Column(ref v2)) => {
-                scalar_column_operations!(v1, v2, |(x, y)| x % y)
-            }
-            (&Value::Column(ref v1), &Value::Scalar(ref v2)) => {
-                scalar_column_operations!(v2, v1, |(x, y)| x % y)
-            }
-            (&Value::Scalar(ref x1), &Value::Scalar(ref x2)) => {
-                scalar_scalar_operations!(x1, x2, |x, y| x % y)
-            }
-        }
+        self.arithmetic(ArithmeticOperator::Modulus, other, OverflowMode::default())
     }
 
-    pub fn and(&self, other: &Value) -> Result<Value> {
-        match (self, other) {
-            (&Value::Column(ref v1), &Value::Column(ref v2)) => match (v1.data(), v2.data()) {
-                (ArrayData::Boolean(ref l), ArrayData::Boolean(ref r)) => {
-                    let bools = l
-                        .iter()
-                        .zip(r.iter())
-                        .map(|(ll, rr)| ll && rr)
-                        .collect::<Vec<bool>>();
-                    let bools = Array::from(bools);
-                    Ok(Value::Column(Rc::new(bools)))
+    /// Add an interval to a date or timestamp column or value
+    fn add_interval(&self, interval: &Interval) -> Result<Value> {
+        match self {
+            Value::Column(ref array) => match array.data() {
+                ArrayData::Date32(ref a) => {
+                    let mut b: Builder<i32> = Builder::with_capacity(a.len());
+                    for v in a.iter() {
+                        match temporal::add_interval_to_date(v, interval) {
+                            Some(v) => b.push(v),
+                            None => return Err(interval_out_of_range(&ScalarValue::Date32(v))),
+                        }
+                    }
+                    let data = ArrayData::Date32(PrimitiveArray::from(b.finish()));
+                    Ok(Value::Column(Rc::new(Array::new(a.len(), data))))
                 }
-                _ => panic!("AND expected two boolean inputs"),
-            },
-            (&Value::Column(ref v1), &Value::Scalar(ref v2)) => match (v1.data(), v2.as_ref()) {
-                (ArrayData::Boolean(ref l), ScalarValue::Boolean(r)) => {
-                    let bools = Array::from(l.iter().map(|ll| ll && *r).collect::<Vec<bool>>());
-                    Ok(Value::Column(Rc::new(bools)))
+                ArrayData::Timestamp(ref a, unit) => {
+                    let mut b: Builder<i64> = Builder::with_capacity(a.len());
+                    for v in a.iter() {
+                        match temporal::add_interval_to_timestamp(v, *unit, interval) {
+                            Some(v) => b.push(v),
+                            None => {
+                                return Err(interval_out_of_range(&ScalarValue::Timestamp(v, *unit)))
+                            }
+                        }
+                    }
+                    let data = ArrayData::Timestamp(PrimitiveArray::from(b.finish()), *unit);
+                    Ok(Value::Column(Rc::new(Array::new(a.len(), data))))
                 }
-                _ => panic!("AND expected two boolean inputs"),
+                other => Err(ExecutionError::type_mismatch(format!(
+                    "Cannot add an interval to a column of type {}",
+                    other
+                ))),
             },
-            _ => unimplemented!(),
+            Value::Scalar(ref v) => {
+                let result = match v.as_ref() {
+                    ScalarValue::Date32(d) => {
+                        temporal::add_interval_to_date(*d, interval).map(ScalarValue::Date32)
+                    }
+                    ScalarValue::Timestamp(t, unit) => {
+                        temporal::add_interval_to_timestamp(*t, *unit, interval)
+                            .map(|t| ScalarValue::Timestamp(t, *unit))
+                    }
+                    other => {
+                        return Err(ExecutionError::type_error(
+                            "Cannot add an interval to a value of this type".to_string(),
+                            other,
+                        ))
+                    }
+                };
+                match result {
+                    Some(result) => Ok(Value::Scalar(Rc::new(result))),
+                    None => Err(interval_out_of_range(v)),
+                }
+            }
         }
     }
 
+    /// Concatenate two Utf8 values (the `||` operator)
+    pub fn concat(&self, other: &Value) -> Result<Value> {
+        self.null_aware(other, |left, right, _| concat_utf8(left, right))
+    }
+
+    /// Logical AND, which is false if either value is false, and otherwise null if either
+    /// value is null
+    pub fn and(&self, other: &Value) -> Result<Value> {
+        logical_operation(self, other, "AND", |l, r| match (l, r) {
+            (Some(false), _) | (_, Some(false)) => Some(false),
+            (Some(true), Some(true)) => Some(true),
+            _ => None,
+        })
+    }
+
+    /// Logical OR, which is true if either value is true, and otherwise null if either value
+    /// is null
     pub fn or(&self, other: &Value) -> Result<Value> {
-        match (self, other) {
-            (&Value::Column(ref v1), &Value::Column(ref v2)) => match (v1.data(), v2.data()) {
-                (ArrayData::Boolean(ref l), ArrayData::Boolean(ref r)) => {
-                    let bools = l
-                        .iter()
-                        .zip(r.iter())
-                        .map(|(ll, rr)| ll || rr)
-                        .collect::<Vec<bool>>();
-                    let bools = Array::from(bools);
-                    Ok(Value::Column(Rc::new(bools)))
-                }
-                _ => panic!("OR expected two boolean inputs"),
-            },
-            (&Value::Column(ref v1), &Value::Scalar(ref v2)) => match (v1.data(), v2.as_ref()) {
-                (ArrayData::Boolean(ref l), ScalarValue::Boolean(r)) => {
-                    let bools = Array::from(l.iter().map(|ll| ll || *r).collect::<Vec<bool>>());
-                    Ok(Value::Column(Rc::new(bools)))
-                }
-                _ => panic!("OR expected two boolean inputs"),
-            },
-            _ => unimplemented!(),
-        }
+        logical_operation(self, other, "OR", |l, r| match (l, r) {
+            (Some(true), _) | (_, Some(true)) => Some(true),
+            (Some(false), Some(false)) => Some(false),
+            _ => None,
+        })
+    }
+
+    /// Logical NOT, which is null if the value is null
+    pub fn not(&self) -> Result<Value> {
+        let values = boolean_operand(self, "NOT")?;
+        Ok(match self {
+            Value::Column(ref array) => boolean_column(
+                &(0..array.len())
+                    .map(|i| values.get(i).map(|b| !b))
+                    .collect::<Vec<Option<bool>>>(),
+            ),
+            Value::Scalar(_) => Value::Scalar(Rc::new(boolean_scalar(values.get(0).map(|b| !b)))),
+        })
     }
 }
 
-/// Compiled Expression (basically just a closure to evaluate the expression at runtime)
-pub type CompiledExpr = Rc<Fn(&RecordBatch) -> Result<Value>>;
+fn is_null_scalar(v: &Value) -> bool {
+    match v {
+        Value::Scalar(ref s) => s.as_ref() == &ScalarValue::Null,
+        Value::Column(_) => false,
+    }
+}
 
-pub type CompiledCastFunction = Rc<Fn(&Value) -> Result<Value>>;
+/// Get the validity of each row of a column, or `None` for a scalar or a column without nulls
+fn validity_of(v: &Value) -> Option<Vec<bool>> {
+    match v {
+        Value::Column(ref array) => array.validity(),
+        Value::Scalar(_) => None,
+    }
+}
 
-pub enum AggregateType {
-    Min,
-    Max,
-    Sum,
-    Count,
-    Avg,
-    //CountDistinct()
+/// Get the validity of each row where none of the values are null, or `None` if no row is null
+fn combined_validity(values: &[Value]) -> Option<Vec<bool>> {
+    values.iter().fold(None, |validity, v| match (validity, validity_of(v)) {
+        (Some(l), Some(r)) => Some(l.iter().zip(r.iter()).map(|(l, r)| *l && *r).collect()),
+        (l, r) => l.or(r),
+    })
 }
 
-/// Runtime expression
-pub enum RuntimeExpr {
-    Compiled {
-        f: CompiledExpr,
-        t: DataType,
-    },
-    AggregateFunction {
-        f: AggregateType,
-        args: Vec<CompiledExpr>,
-        t: DataType,
-    },
+/// Execute a scalar function only for the rows where `validity` is true, so that the function
+/// is never called with the placeholder values stored in null slots. The result is null for the
+/// other rows.
+fn execute_non_null(
+    func: &ScalarFunction,
+    args: &[Value],
+    arg_types: &[DataType],
+    validity: &[bool],
+    return_type: &DataType,
+) -> Result<Value> {
+    let rows: Vec<usize> = (0..validity.len()).filter(|i| validity[*i]).collect();
+    let results = if rows.is_empty() {
+        None
+    } else {
+        let args = args
+            .iter()
+            .zip(arg_types.iter())
+            .map(|(arg, t)| match *arg {
+                Value::Column(ref array) => {
+                    let values = rows.iter().map(|i| get_value(array, *i)).collect();
+                    Ok(Value::Column(Rc::new(array_from_scalars(t, &values)?)))
+                }
+                Value::Scalar(_) => Ok(arg.clone()),
+            })
+            .collect::<Result<Vec<Value>>>()?;
+        Some(func.execute(&args)?)
+    };
+    let mut next = 0;
+    let values: Vec<ScalarValue> = validity
+        .iter()
+        .map(|valid| match results {
+            Some(ref results) if *valid => {
+                next += 1;
+                value_at(results, next - 1)
+            }
+            _ => ScalarValue::Null,
+        })
+        .collect();
+    let array = build_nullable_array(return_type, &values, |values| {
+        array_from_scalars(return_type, values)
+    })?;
+    Ok(Value::Column(Rc::new(array)))
 }
 
-impl RuntimeExpr {
-    pub fn get_func(&self) -> CompiledExpr {
-        match self {
-            &RuntimeExpr::Compiled { ref f, .. } => f.clone(),
-            _ => panic!(),
-        }
+/// Mark the rows of the result of a kernel as null wherever `validity` is false
+fn with_validity(result: Value, validity: Option<Vec<bool>>) -> Value {
+    match (result, validity) {
+        (Value::Column(array), Some(validity)) => match Rc::try_unwrap(array) {
+            Ok(array) => {
+                // keep rows that the kernel itself made null, e.g. after division by zero
+                let validity: Vec<bool> = match array.validity() {
+                    Some(own) => own.iter().zip(validity.iter()).map(|(a, b)| *a && *b).collect(),
+                    None => validity,
+                };
+                Value::Column(Rc::new(Array::with_validity(array.into_data(), &validity)))
+            }
+            // the kernel returned its input unchanged, which already has the same validity
+            Err(array) => Value::Column(array),
+        },
+        (result, _) => result,
     }
-    pub fn get_type(&self) -> DataType {
+}
+
+/// The values of a boolean column or scalar, where null values are `None`
+enum Booleans<'a> {
+    Column(&'a Array, &'a PrimitiveArray<bool>),
+    Scalar(Option<bool>),
+}
+
+impl<'a> Booleans<'a> {
+    fn get(&self, i: usize) -> Option<bool> {
         match self {
-            &RuntimeExpr::Compiled { ref t, .. } => t.clone(),
-            &RuntimeExpr::AggregateFunction { ref t, .. } => t.clone(),
+            Booleans::Column(array, values) => if array.is_valid(i) {
+                Some(*values.get(i))
+            } else {
+                None
+            },
+            Booleans::Scalar(value) => *value,
         }
     }
 }
 
-/// Compiles a scalar expression into a closure
-pub fn compile_expr(
-    ctx: &ExecutionContext,
-    expr: &Expr,
-    input_schema: &Schema,
-) -> Result<RuntimeExpr> {
-    match *expr {
-        Expr::AggregateFunction {
-            ref name,
-            ref args,
-            ref return_type,
-        } => {
-            assert_eq!(1, args.len());
+/// Get the values of a boolean operand of a logical operator
+fn boolean_operand<'a>(v: &'a Value, op: &str) -> Result<Booleans<'a>> {
+    let values = match v {
+        Value::Column(ref array) => match array.data() {
+            ArrayData::Boolean(ref values) => Some(Booleans::Column(array.as_ref(), values)),
+            _ => None,
+        },
+        Value::Scalar(ref s) => match s.as_ref() {
+            ScalarValue::Boolean(b) => Some(Booleans::Scalar(Some(*b))),
+            ScalarValue::Null => Some(Booleans::Scalar(None)),
+            _ => None,
+        },
+    };
+    values.ok_or_else(|| ExecutionError::type_mismatch(format!("{} expected boolean inputs", op)))
+}
 
-            let compiled_args: Result<Vec<RuntimeExpr>> = args
-                .iter()
-                .map(|e| compile_scalar_expr(ctx, e, input_schema))
-                .collect();
+/// Create a boolean column from values where null values are `None`
+fn boolean_column(values: &[Option<bool>]) -> Value {
+    let validity: Vec<bool> = values.iter().map(|v| v.is_some()).collect();
+    let data = ArrayData::from(
+        values
+            .iter()
+            .map(|v| v.unwrap_or(false))
+            .collect::<Vec<bool>>(),
+    );
+    Value::Column(Rc::new(Array::with_validity(data, &validity)))
+}
 
-            let func = match name.to_lowercase().as_ref() {
-                "min" => AggregateType::Min,
-                "max" => AggregateType::Max,
-                "count" => AggregateType::Count,
-                "sum" => AggregateType::Sum,
-                _ => unimplemented!("Unsupported aggregate function '{}'", name),
-            };
+fn boolean_scalar(value: Option<bool>) -> ScalarValue {
+    value.map_or(ScalarValue::Null, ScalarValue::Boolean)
+}
 
-            //TODO: this is hacky
-            //            let return_type = match func {
-            //                AggregateType::Count => DataType::UInt64,
-            //                AggregateType::Min | AggregateType::Max => match args[0] {
-            //                    Expr::Column(i) => input_schema.columns()[i].data_type().clone(),
-            //                    _ => {
+/// Apply a logical operator to each row of two boolean columns or scalars, using SQL's
+/// three-valued logic where null values are `None`
+fn logical_operation<F>(left: &Value, right: &Value, op: &str, f: F) -> Result<Value>
+where
+    F: Fn(Option<bool>, Option<bool>) -> Option<bool>,
+{
+    let l = boolean_operand(left, op)?;
+    let r = boolean_operand(right, op)?;
+    let len = match (left, right) {
+        (Value::Column(ref array), _) | (_, Value::Column(ref array)) => array.len(),
+        (Value::Scalar(_), Value::Scalar(_)) => {
+            return Ok(Value::Scalar(Rc::new(boolean_scalar(f(l.get(0), r.get(0))))))
+        }
+    };
+    let values: Vec<Option<bool>> = (0..len).map(|i| f(l.get(i), r.get(i))).collect();
+    Ok(boolean_column(&values))
+}
+
+/// Get the interval that is the right operand of an addition or subtraction, if it is one
+fn interval_operand(v: &Value) -> Option<Interval> {
+    match v {
+        Value::Scalar(ref s) => match s.as_ref() {
+            ScalarValue::Interval(interval) => Some(*interval),
+            _ => None,
+        },
+        Value::Column(_) => None,
+    }
+}
+
+/// The values of a decimal column or scalar
+enum Decimals<'a> {
+    Column(&'a [i128]),
+    Scalar(i128),
+}
+
+impl<'a> Decimals<'a> {
+    fn get(&self, i: usize) -> i128 {
+        match self {
+            Decimals::Column(values) => values[i],
+            Decimals::Scalar(value) => *value,
+        }
+    }
+}
+
+/// Get the values, precision and scale of a decimal column or scalar
+fn decimal_operand(v: &Value) -> Option<(Decimals, usize, usize)> {
+    match v {
+        Value::Column(ref array) => match array.data() {
+            ArrayData::Decimal(ref values, p, s) => Some((Decimals::Column(values), *p, *s)),
+            _ => None,
+        },
+        Value::Scalar(ref s) => match s.as_ref() {
+            ScalarValue::Decimal(value, p, s) => Some((Decimals::Scalar(*value), *p, *s)),
+            _ => None,
+        },
+    }
+}
+
+fn is_decimal(v: &Value) -> bool {
+    decimal_operand(v).is_some()
+}
+
+/// Apply an arithmetic operation to two decimal columns or scalars. The planner casts integer
+/// operands to decimals, and the result has the precision and scale given by
+/// `decimal::result_type`. Decimal overflow is always an error, but division by zero follows
+/// `mode`.
+fn decimal_arithmetic(
+    left: &Value,
+    op: ArithmeticOperator,
+    right: &Value,
+    validity: &Option<Vec<bool>>,
+    mode: OverflowMode,
+) -> Result<Value> {
+    let op = match op {
+        ArithmeticOperator::Add => DecimalOperator::Add,
+        ArithmeticOperator::Subtract => DecimalOperator::Subtract,
+        ArithmeticOperator::Multiply => DecimalOperator::Multiply,
+        ArithmeticOperator::Divide => DecimalOperator::Divide,
+        ArithmeticOperator::Modulus => {
+            return Err(ExecutionError::type_mismatch(
+                "Decimal modulus is not supported".to_string(),
+            ))
+        }
+    };
+    let ((l, lp, ls), (r, rp, rs)) = match (decimal_operand(left), decimal_operand(right)) {
+        (Some(l), Some(r)) => (l, r),
+        _ => {
+            return Err(ExecutionError::type_mismatch(format!(
+                "Decimal {:?} requires two decimal operands",
+                op
+            )))
+        }
+    };
+    let (precision, scale) = decimal::result_type(op, (lp, ls), (rp, rs));
+    // returns `None` for a null result, and reports overflow in the same way as the integer
+    // kernels
+    let apply = |a: i128, b: i128, row: Option<usize>| {
+        if op == DecimalOperator::Divide && b == 0 && mode == OverflowMode::Wrapping {
+            return Ok(None);
+        }
+        decimal::apply(op, (a, ls), (b, rs), (precision, scale))
+            .map(Some)
+            .map_err(|e| {
+                let message = format!("Error in decimal {:?}: {}", op, e);
+                ExecutionError::data(message, None, row, None)
+            })
+    };
+    let len = match (&l, &r) {
+        (Decimals::Column(values), _) | (_, Decimals::Column(values)) => values.len(),
+        (Decimals::Scalar(a), Decimals::Scalar(b)) => {
+            let value = match apply(*a, *b, None)? {
+                Some(v) => ScalarValue::Decimal(v, precision, scale),
+                None => ScalarValue::Null,
+            };
+            return Ok(Value::Scalar(Rc::new(value)));
+        }
+    };
+    let values = (0..len)
+        .map(|i| match validity {
+            Some(ref valid) if !valid[i] => Ok(None),
+            _ => apply(l.get(i), r.get(i), Some(i)),
+        })
+        .collect::<Result<Vec<Option<i128>>>>()?;
+    let valid: Vec<bool> = values.iter().map(|v| v.is_some()).collect();
+    let data = ArrayData::Decimal(
+        values.into_iter().map(|v| v.unwrap_or(0)).collect(),
+        precision,
+        scale,
+    );
+    Ok(Value::Column(Rc::new(Array::with_validity(data, &valid))))
+}
+
+/// The values of a Utf8 column or scalar
+enum Strings<'a> {
+    Column(&'a ListArray<u8>),
+    Scalar(&'a [u8]),
+}
+
+impl<'a> Strings<'a> {
+    fn get(&self, i: usize) -> &'a [u8] {
+        match *self {
+            Strings::Column(list) => list.get(i),
+            Strings::Scalar(value) => value,
+        }
+    }
+}
+
+/// Get the values of a Utf8 column or scalar
+fn utf8_operand(v: &Value) -> Option<Strings> {
+    match v {
+        Value::Column(ref array) => match array.data() {
+            ArrayData::Utf8(ref list) => Some(Strings::Column(list)),
+            _ => None,
+        },
+        Value::Scalar(ref s) => match s.as_ref() {
+            ScalarValue::Utf8(ref value) => Some(Strings::Scalar(value.as_bytes())),
+            _ => None,
+        },
+    }
+}
+
+/// Concatenate two Utf8 columns or scalars row by row
+fn concat_utf8(left: &Value, right: &Value) -> Result<Value> {
+    let (l, r) = match (utf8_operand(left), utf8_operand(right)) {
+        (Some(l), Some(r)) => (l, r),
+        _ => {
+            return Err(ExecutionError::type_mismatch(
+                "|| requires two Utf8 operands".to_string(),
+            ))
+        }
+    };
+    let concat = |i: usize| {
+        let mut bytes = l.get(i).to_vec();
+        bytes.extend_from_slice(r.get(i));
+        bytes
+    };
+    let len = match (&l, &r) {
+        (Strings::Column(list), _) | (_, Strings::Column(list)) => list.len(),
+        (Strings::Scalar(_), Strings::Scalar(_)) => {
+            let s = String::from_utf8_lossy(&concat(0)).to_string();
+            return Ok(Value::Scalar(Rc::new(ScalarValue::Utf8(Rc::new(s)))));
+        }
+    };
+    let mut b: ListBuilder<u8> = ListBuilder::with_capacity(len);
+    for i in 0..len {
+        b.push(&concat(i));
+    }
+    let data = ArrayData::Utf8(ListArray::from(b.finish()));
+    Ok(Value::Column(Rc::new(Array::new(len, data))))
+}
+
+fn interval_out_of_range(v: &ScalarValue) -> ExecutionError {
+    ExecutionError::General(format!(
+        "Adding an interval to {} is out of the range of the type",
+        v
+    ))
+}
+
+fn scalar_comparison_error() -> ExecutionError {
+    ExecutionError::type_mismatch("Comparison of two scalar values is not supported".to_string())
+}
+
+/// Compare two scalar values by their ordering. Type coercion gives both operands of a
+/// comparison the same type, and values of different types are not comparable.
+fn compare_scalars<F>(v1: &ScalarValue, v2: &ScalarValue, f: F) -> Result<Value>
+where
+    F: Fn(&ScalarValue, &ScalarValue) -> bool,
+{
+    let is_nan = |v: &ScalarValue| match *v {
+        ScalarValue::Float32(n) => n.is_nan(),
+        ScalarValue::Float64(n) => n.is_nan(),
+        _ => false,
+    };
+    // NaN is not ordered relative to any value, but can still be compared
+    if v1.partial_cmp(v2).is_none() && !is_nan(v1) && !is_nan(v2) {
+        return Err(scalar_comparison_error());
+    }
+    Ok(Value::Scalar(Rc::new(ScalarValue::Boolean(f(v1, v2)))))
+}
+
+/// Get the comparisons of columns or struct fields with literal values that are conjuncts of a
+/// predicate on a table scan. Each path starts with the name of the column in the scan schema.
+fn row_group_predicates(expr: &Expr, schema: &Schema) -> Vec<RowGroupPredicate> {
+    match expr {
+        Expr::BinaryExpr {
+            left,
+            op: Operator::And,
+            right,
+        } => {
+            let mut predicates = row_group_predicates(left, schema);
+            predicates.extend(row_group_predicates(right, schema));
+            predicates
+        }
+        Expr::BinaryExpr { left, op, right } => {
+            let comparison = match (scan_path(left, schema), literal_value(right)) {
+                (Some(path), Some(value)) => Some((path, op.clone(), value)),
+                _ => match (literal_value(left), scan_path(right, schema)) {
+                    // the column is the right operand, so the comparison is mirrored
+                    (Some(value), Some(path)) => Some((path, mirror_comparison(op), value)),
+                    _ => None,
+                },
+            };
+            match comparison {
+                Some((path, op, value)) => match op {
+                    Operator::Eq
+                    | Operator::NotEq
+                    | Operator::Lt
+                    | Operator::LtEq
+                    | Operator::Gt
+                    | Operator::GtEq => vec![RowGroupPredicate { path, op, value }],
+                    _ => vec![],
+                },
+                None => vec![],
+            }
+        }
+        _ => vec![],
+    }
+}
+
+/// Get the path of the column or struct field that an expression reads, starting with the name
+/// of the column in the schema
+fn scan_path(expr: &Expr, schema: &Schema) -> Option<Vec<String>> {
+    let (base, fields) = field_path(expr);
+    let i = match base {
+        Expr::Column(i) => *i,
+        Expr::ColumnRef(c) => c.index_of(schema).ok()?,
+        _ => return None,
+    };
+    let mut path = vec![schema.column(i).name().clone()];
+    path.extend(fields);
+    Some(path)
+}
+
+/// Get the value of a literal, which may have been cast to the type of the other operand
+fn literal_value(expr: &Expr) -> Option<ScalarValue> {
+    match expr {
+        Expr::Literal(value) => Some(value.clone()),
+        Expr::Cast { expr, data_type } => match expr.as_ref() {
+            Expr::Literal(value) => cast::cast_scalar(value, data_type, false).ok(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Get the comparison that is equivalent to `op` with its operands swapped
+fn mirror_comparison(op: &Operator) -> Operator {
+    match op {
+        Operator::Lt => Operator::Gt,
+        Operator::LtEq => Operator::GtEq,
+        Operator::Gt => Operator::Lt,
+        Operator::GtEq => Operator::LtEq,
+        other => other.clone(),
+    }
+}
+
+/// Convert a path that starts with the name of a column in the schema of a table scan into one
+/// that starts with the name of the same column in the schema of the table
+fn table_path(path: &[String], scan_schema: &Schema, table_schema: &Schema) -> Result<Vec<String>> {
+    let i = Column::from_qualified_name(&path[0]).index_of(scan_schema)?;
+    let mut path = path.to_vec();
+    path[0] = table_schema.column(i).name().clone();
+    Ok(path)
+}
+
+/// Compiled Expression (basically just a closure to evaluate the expression at runtime)
+pub type CompiledExpr = Rc<Fn(&RecordBatch) -> Result<Value>>;
+
+pub enum AggregateType {
+    Min,
+    Max,
+    Sum,
+    Count,
+    Avg,
+    ArrayAgg,
+    //CountDistinct()
+}
+
+/// Runtime expression
+pub enum RuntimeExpr {
+    Compiled {
+        f: CompiledExpr,
+        t: DataType,
+    },
+    AggregateFunction {
+        f: AggregateType,
+        /// How `SUM` handles integer overflow
+        overflow_mode: OverflowMode,
+        args: Vec<CompiledExpr>,
+        t: DataType,
+    },
+}
+
+impl RuntimeExpr {
+    pub fn get_func(&self) -> CompiledExpr {
+        match self {
+            &RuntimeExpr::Compiled { ref f, .. } => f.clone(),
+            _ => panic!(),
+        }
+    }
+    pub fn get_type(&self) -> DataType {
+        match self {
+            &RuntimeExpr::Compiled { ref t, .. } => t.clone(),
+            &RuntimeExpr::AggregateFunction { ref t, .. } => t.clone(),
+        }
+    }
+}
+
+/// Compiles a scalar expression into a closure
+pub fn compile_expr(
+    ctx: &ExecutionContext,
+    expr: &Expr,
+    input_schema: &Schema,
+) -> Result<RuntimeExpr> {
+    match *expr {
+        Expr::AggregateFunction {
+            ref name,
+            ref args,
+            ref return_type,
+        } => {
+            if args.len() != 1 {
+                return Err(ExecutionError::plan_expr(
+                    format!("Aggregate function {} requires exactly one argument", name),
+                    expr,
+                ));
+            }
+
+            let compiled_args: Result<Vec<RuntimeExpr>> = args
+                .iter()
+                .map(|e| compile_scalar_expr(ctx, e, input_schema))
+                .collect();
+
+            let func = match name.to_lowercase().as_ref() {
+                "min" => AggregateType::Min,
+                "max" => AggregateType::Max,
+                "count" => AggregateType::Count,
+                "sum" => AggregateType::Sum,
+                "array_agg" => AggregateType::ArrayAgg,
+                _ => {
+                    return Err(ExecutionError::plan(format!(
+                        "Unsupported aggregate function '{}'",
+                        name
+                    )))
+                }
+            };
+
+            //TODO: this is hacky
+            //            let return_type = match func {
+            //                AggregateType::Count => DataType::UInt64,
+            //                AggregateType::Min | AggregateType::Max => match args[0] {
+            //                    Expr::Column(i) => input_schema.columns()[i].data_type().clone(),
+            //                    _ => {
             //                        //TODO: fix this hack
             //                        DataType::Float64
             //                        //panic!("Aggregate expressions currently only support simple arguments")
@@ -645,6 +1293,7 @@ pub fn compile_expr(
 
             Ok(RuntimeExpr::AggregateFunction {
                 f: func,
+                overflow_mode: ctx.overflow_mode(),
                 args: compiled_args?
                     .iter()
                     .map(|e| e.get_func().clone())
@@ -656,172 +1305,36 @@ pub fn compile_expr(
     }
 }
 
-macro_rules! cast_primitive {
-    {$TO:ty, $LIST:expr} => {{
-        let mut b: Builder<$TO> = Builder::with_capacity($LIST.len() as usize);
-        for i in 0..$LIST.len() as usize {
-            b.push(*$LIST.get(i) as $TO)
-        }
-        Ok(Value::Column(Rc::new(Array::from(b.finish()))))
-    }}
-}
-
-macro_rules! cast_array_from_to {
-    {$FROM:ty, $TO:ident, $LIST:expr} => {{
-        match &$TO {
-            DataType::UInt8 => cast_primitive!(u8, $LIST),
-            DataType::UInt16 => cast_primitive!(u16, $LIST),
-            DataType::UInt32 => cast_primitive!(u32, $LIST),
-            DataType::UInt64 => cast_primitive!(u64, $LIST),
-            DataType::Int8 => cast_primitive!(i8, $LIST),
-            DataType::Int16 => cast_primitive!(i16, $LIST),
-            DataType::Int32 => cast_primitive!(i32, $LIST),
-            DataType::Int64 => cast_primitive!(i64, $LIST),
-            DataType::Float32 => cast_primitive!(f32, $LIST),
-            DataType::Float64 => cast_primitive!(f64, $LIST),
-            DataType::Utf8 => {
-                let mut b: ListBuilder<u8> = ListBuilder::with_capacity($LIST.len() as usize);
-                for i in 0..$LIST.len() as usize {
-                    let s = format!("{:?}", *$LIST.get(i));
-                    b.push(s.as_bytes());
-                }
-                Ok(Value::Column(Rc::new(Array::new($LIST.len() as usize,
-                  ArrayData::Utf8(ListArray::from(b.finish()))))))
-            },
-            _ => unimplemented!("CAST from {:?} to {:?}", stringify!($FROM), stringify!($TO))
+/// Compile a `CAST`, or a `TRY_CAST` if `safe` is true. Casts of literals are evaluated once
+/// when the expression is compiled.
+fn compile_cast_expr(
+    ctx: &ExecutionContext,
+    expr: &Expr,
+    data_type: &DataType,
+    safe: bool,
+    input_schema: &Schema,
+) -> Result<RuntimeExpr> {
+    let to = data_type.clone();
+    match expr {
+        &Expr::Literal(ref lit) => {
+            let value = Value::Scalar(Rc::new(cast::cast_scalar(lit, data_type, safe)?));
+            Ok(RuntimeExpr::Compiled {
+                f: Rc::new(move |_: &RecordBatch| Ok(value.clone())),
+                t: to,
+            })
         }
-    }}
-}
-
-macro_rules! cast_utf8_to {
-    {$TY:ty, $LIST:expr} => {{
-        let mut b: Builder<$TY> = Builder::with_capacity($LIST.len() as usize);
-        for i in 0..$LIST.len() as usize {
-            let x = str::from_utf8($LIST.get(i)).unwrap();
-            match x.parse::<$TY>() {
-                Ok(v) => b.push(v),
-                Err(_) => return Err(ExecutionError::General(format!(
-                    "Cannot cast Utf8 value '{}' to {}", x, stringify!($TY))))
-            }
+        _ => {
+            let compiled_expr = compile_scalar_expr(ctx, expr, input_schema)?.get_func();
+            Ok(RuntimeExpr::Compiled {
+                f: Rc::new(move |batch: &RecordBatch| {
+                    cast::cast(&compiled_expr(batch)?, &to, safe)
+                }),
+                t: data_type.clone(),
+            })
         }
-        Ok(Value::Column(Rc::new(Array::from(b.finish()))))
-    }}
+    }
 }
 
-fn compile_cast_column(data_type: DataType) -> Result<CompiledCastFunction> {
-    Ok(Rc::new(move |v: &Value| match v {
-        Value::Column(ref array) => match array.data() {
-            &ArrayData::Boolean(_) => unimplemented!("CAST from Boolean"),
-            &ArrayData::UInt8(ref list) => cast_array_from_to!(u8, data_type, list),
-            &ArrayData::UInt16(ref list) => cast_array_from_to!(u16, data_type, list),
-            &ArrayData::UInt32(ref list) => cast_array_from_to!(u32, data_type, list),
-            &ArrayData::UInt64(ref list) => cast_array_from_to!(u64, data_type, list),
-            &ArrayData::Int8(ref list) => cast_array_from_to!(i8, data_type, list),
-            &ArrayData::Int16(ref list) => cast_array_from_to!(i16, data_type, list),
-            &ArrayData::Int32(ref list) => cast_array_from_to!(i32, data_type, list),
-            &ArrayData::Int64(ref list) => cast_array_from_to!(i64, data_type, list),
-            &ArrayData::Float32(ref list) => cast_array_from_to!(f32, data_type, list),
-            &ArrayData::Float64(ref list) => cast_array_from_to!(f64, data_type, list),
-            &ArrayData::Struct(_) => unimplemented!("CAST from Struct"),
-            &ArrayData::Utf8(ref list) => match &data_type {
-                DataType::Boolean => cast_utf8_to!(bool, list),
-                DataType::Int8 => cast_utf8_to!(i8, list),
-                DataType::Int16 => cast_utf8_to!(i16, list),
-                DataType::Int32 => cast_utf8_to!(i32, list),
-                DataType::Int64 => cast_utf8_to!(i64, list),
-                DataType::UInt8 => cast_utf8_to!(u8, list),
-                DataType::UInt16 => cast_utf8_to!(u16, list),
-                DataType::UInt32 => cast_utf8_to!(u32, list),
-                DataType::UInt64 => cast_utf8_to!(u64, list),
-                DataType::Float32 => cast_utf8_to!(f32, list),
-                DataType::Float64 => cast_utf8_to!(f64, list),
-                DataType::Utf8 => Ok(v.clone()),
-                _ => unimplemented!("CAST from Utf8 to {:?}", data_type),
-            },
-        },
-        _ => unimplemented!("CAST from ScalarValue"),
-    }))
-}
-
-macro_rules! cast_scalar_from_to {
-    {$SCALAR:expr, $TO:ident} => {{
-        match &$TO {
-            DataType::UInt8 => {
-                let cast_value = *$SCALAR as u8;
-                Ok(Rc::new(move |_: &Value|
-                Ok(Value::Scalar(Rc::new(ScalarValue::UInt8(cast_value)))) ))
-            }
-            DataType::UInt16 => {
-                let cast_value = *$SCALAR as u16;
-                Ok(Rc::new(move |_: &Value|
-                Ok(Value::Scalar(Rc::new(ScalarValue::UInt16(cast_value)))) ))
-            }
-            DataType::UInt32 => {
-                let cast_value = *$SCALAR as u32;
-                Ok(Rc::new(move |_: &Value|
-                Ok(Value::Scalar(Rc::new(ScalarValue::UInt32(cast_value)))) ))
-            }
-            DataType::UInt64 => {
-                let cast_value = *$SCALAR as u64;
-                Ok(Rc::new(move |_: &Value|
-                Ok(Value::Scalar(Rc::new(ScalarValue::UInt64(cast_value)))) ))
-            }
-            DataType::Int8 => {
-                let cast_value = *$SCALAR as i8;
-                Ok(Rc::new(move |_: &Value|
-                Ok(Value::Scalar(Rc::new(ScalarValue::Int8(cast_value)))) ))
-            }
-            DataType::Int16 => {
-                let cast_value = *$SCALAR as i16;
-                Ok(Rc::new(move |_: &Value|
-                Ok(Value::Scalar(Rc::new(ScalarValue::Int16(cast_value)))) ))
-            }
-            DataType::Int32 => {
-                let cast_value = *$SCALAR as i32;
-                Ok(Rc::new(move |_: &Value|
-                Ok(Value::Scalar(Rc::new(ScalarValue::Int32(cast_value)))) ))
-            }
-            DataType::Int64 => {
-                let cast_value = *$SCALAR as i64;
-                Ok(Rc::new(move |_: &Value|
-                 Ok(Value::Scalar(Rc::new(ScalarValue::Int64(cast_value)))) ))
-            }
-            DataType::Float32 => {
-                let cast_value = *$SCALAR as f32;
-                Ok(Rc::new(move |_: &Value|
-                Ok(Value::Scalar(Rc::new(ScalarValue::Float32(cast_value)))) ))
-            }
-            DataType::Float64 => {
-                let cast_value = *$SCALAR as f64;
-                Ok(Rc::new(move |_: &Value|
-                Ok(Value::Scalar(Rc::new(ScalarValue::Float64(cast_value)))) ))
-            }
-            _ => unimplemented!("CAST from {:?} to {:?}", stringify!($SCALAR), stringify!($TO))
-        }
-    }}
-}
-
-fn compile_cast_scalar(scalar: &ScalarValue, data_type: &DataType) -> Result<CompiledCastFunction> {
-    match scalar {
-        ScalarValue::Boolean(_) => unimplemented!("CAST from scalar Boolean"),
-        ScalarValue::UInt8(v) => cast_scalar_from_to!(v, data_type),
-        ScalarValue::UInt16(v) => cast_scalar_from_to!(v, data_type),
-        ScalarValue::UInt32(v) => cast_scalar_from_to!(v, data_type),
-        ScalarValue::UInt64(v) => cast_scalar_from_to!(v, data_type),
-        ScalarValue::Int8(v) => cast_scalar_from_to!(v, data_type),
-        ScalarValue::Int16(v) => cast_scalar_from_to!(v, data_type),
-        ScalarValue::Int32(v) => cast_scalar_from_to!(v, data_type),
-        ScalarValue::Int64(v) => cast_scalar_from_to!(v, data_type),
-        ScalarValue::Float32(v) => cast_scalar_from_to!(v, data_type),
-        ScalarValue::Float64(v) => cast_scalar_from_to!(v, data_type),
-        ScalarValue::Utf8(_) => unimplemented!("CAST from scalar Utf8"),
-        ScalarValue::Struct(_) => unimplemented!("CAST from scalar Struct"),
-        ScalarValue::Null => unimplemented!("CAST from scalar NULL"),
-    }
-}
-
-//Ok(Rc::new(move |_: &Value|
-
 /// Compiles a scalar expression into a closure
 pub fn compile_scalar_expr(
     ctx: &ExecutionContext,
@@ -844,33 +1357,17 @@ pub fn compile_scalar_expr(
             f: Rc::new(move |batch: &RecordBatch| Ok((*batch.column(index)).clone())),
             t: input_schema.column(index).data_type().clone(),
         }),
+        &Expr::ColumnRef(_) => {
+            compile_scalar_expr(ctx, &resolve_columns(expr, input_schema)?, input_schema)
+        }
         &Expr::Cast {
             ref expr,
             ref data_type,
-        } => match expr.as_ref() {
-            &Expr::Column(index) => {
-                let compiled_cast_expr = compile_cast_column(data_type.clone())?;
-                Ok(RuntimeExpr::Compiled {
-                    f: Rc::new(move |batch: &RecordBatch| {
-                        (compiled_cast_expr)(batch.column(index))
-                    }),
-                    t: data_type.clone(),
-                })
-            }
-            &Expr::Literal(ref lit) => {
-                let compiled_cast_expr = compile_cast_scalar(lit, data_type)?;
-                Ok(RuntimeExpr::Compiled {
-                    f: Rc::new(move |_: &RecordBatch| {
-                        (compiled_cast_expr)(&Value::Scalar(Rc::new(ScalarValue::Null))) // pointless arg
-                    }),
-                    t: data_type.clone(),
-                })
-            }
-            other => Err(ExecutionError::General(format!(
-                "CAST not implemented for expression {:?}",
-                other
-            ))),
-        },
+        } => compile_cast_expr(ctx, expr, data_type, false, input_schema),
+        &Expr::TryCast {
+            ref expr,
+            ref data_type,
+        } => compile_cast_expr(ctx, expr, data_type, true, input_schema),
         &Expr::IsNotNull(ref expr) => {
             let compiled_expr = compile_scalar_expr(ctx, expr, input_schema)?;
             Ok(RuntimeExpr::Compiled {
@@ -891,6 +1388,16 @@ pub fn compile_scalar_expr(
                 t: DataType::Boolean,
             })
         }
+        &Expr::Not(ref expr) => {
+            let compiled_expr = compile_scalar_expr(ctx, expr, input_schema)?;
+            Ok(RuntimeExpr::Compiled {
+                f: Rc::new(move |batch: &RecordBatch| {
+                    let values = compiled_expr.get_func()(batch)?;
+                    values.not()
+                }),
+                t: DataType::Boolean,
+            })
+        }
         &Expr::BinaryExpr {
             ref left,
             ref op,
@@ -899,6 +1406,7 @@ pub fn compile_scalar_expr(
             let left_expr = compile_scalar_expr(ctx, left, input_schema)?;
             let right_expr = compile_scalar_expr(ctx, right, input_schema)?;
             let op_type = left_expr.get_type().clone();
+            let mode = ctx.overflow_mode();
             match op {
                 &Operator::Eq => Ok(RuntimeExpr::Compiled {
                     f: Rc::new(move |batch: &RecordBatch| {
@@ -968,7 +1476,7 @@ pub fn compile_scalar_expr(
                     f: Rc::new(move |batch: &RecordBatch| {
                         let left_values = left_expr.get_func()(batch)?;
                         let right_values = right_expr.get_func()(batch)?;
-                        left_values.add(&right_values)
+                        left_values.arithmetic(ArithmeticOperator::Add, &right_values, mode)
                     }),
                     t: op_type,
                 }),
@@ -976,7 +1484,7 @@ pub fn compile_scalar_expr(
                     f: Rc::new(move |batch: &RecordBatch| {
                         let left_values = left_expr.get_func()(batch)?;
                         let right_values = right_expr.get_func()(batch)?;
-                        left_values.subtract(&right_values)
+                        left_values.arithmetic(ArithmeticOperator::Subtract, &right_values, mode)
                     }),
                     t: op_type,
                 }),
@@ -984,7 +1492,7 @@ pub fn compile_scalar_expr(
                     f: Rc::new(move |batch: &RecordBatch| {
                         let left_values = left_expr.get_func()(batch)?;
                         let right_values = right_expr.get_func()(batch)?;
-                        left_values.multiply(&right_values)
+                        left_values.arithmetic(ArithmeticOperator::Multiply, &right_values, mode)
                     }),
                     t: op_type,
                 }),
@@ -992,7 +1500,7 @@ pub fn compile_scalar_expr(
                     f: Rc::new(move |batch: &RecordBatch| {
                         let left_values = left_expr.get_func()(batch)?;
                         let right_values = right_expr.get_func()(batch)?;
-                        left_values.divide(&right_values)
+                        left_values.arithmetic(ArithmeticOperator::Divide, &right_values, mode)
                     }),
                     t: op_type,
                 }),
@@ -1000,10 +1508,18 @@ pub fn compile_scalar_expr(
                     f: Rc::new(move |batch: &RecordBatch| {
                         let left_values = left_expr.get_func()(batch)?;
                         let right_values = right_expr.get_func()(batch)?;
-                        left_values.modulo(&right_values)
+                        left_values.arithmetic(ArithmeticOperator::Modulus, &right_values, mode)
                     }),
                     t: op_type,
                 }),
+                &Operator::StringConcat => Ok(RuntimeExpr::Compiled {
+                    f: Rc::new(move |batch: &RecordBatch| {
+                        let left_values = left_expr.get_func()(batch)?;
+                        let right_values = right_expr.get_func()(batch)?;
+                        left_values.concat(&right_values)
+                    }),
+                    t: DataType::Utf8,
+                }),
             }
         }
         &Expr::Sort { ref expr, .. } => {
@@ -1022,12 +1538,15 @@ pub fn compile_scalar_expr(
             let expected_args = func.args();
 
             if expected_args.len() != args.len() {
-                return Err(ExecutionError::General(format!(
-                    "Function {} requires {} parameters but {} were provided",
-                    name,
-                    expected_args.len(),
-                    args.len()
-                )));
+                return Err(ExecutionError::plan_expr(
+                    format!(
+                        "Function {} requires {} parameters but {} were provided",
+                        name,
+                        expected_args.len(),
+                        args.len()
+                    ),
+                    expr,
+                ));
             }
 
             // evaluate the arguments to the function
@@ -1042,30 +1561,123 @@ pub fn compile_scalar_expr(
             for i in 0..expected_args.len() {
                 let actual_type = compiled_args_ok[i].get_type();
                 if expected_args[i].data_type() != &actual_type {
-                    return Err(ExecutionError::General(format!(
-                        "Scalar function {} requires {:?} for argument {} but got {:?}",
-                        name,
-                        expected_args[i].data_type(),
-                        i,
-                        actual_type
-                    )));
+                    return Err(ExecutionError::type_error(
+                        format!(
+                            "Scalar function {} requires {:?} for argument {} but got {:?}",
+                            name,
+                            expected_args[i].data_type(),
+                            i,
+                            actual_type
+                        ),
+                        expr,
+                    ));
                 }
             }
 
+            let arg_types: Vec<DataType> =
+                expected_args.iter().map(|f| f.data_type().clone()).collect();
+            let t = return_type.clone();
             Ok(RuntimeExpr::Compiled {
                 f: Rc::new(move |batch| {
-                    let arg_values: Result<Vec<Value>> = compiled_args_ok
+                    let arg_values: Vec<Value> = compiled_args_ok
                         .iter()
                         .map(|expr| expr.get_func()(batch))
-                        .collect();
+                        .collect::<Result<Vec<Value>>>()?;
 
-                    func.execute(&arg_values?)
+                    // the result is null wherever an argument is null
+                    if arg_values.iter().any(|v| is_null_scalar(v)) {
+                        return Ok(Value::Scalar(Rc::new(ScalarValue::Null)));
+                    }
+                    match combined_validity(&arg_values) {
+                        Some(validity) => execute_non_null(
+                            func.as_ref(),
+                            &arg_values,
+                            &arg_types,
+                            &validity,
+                            &t,
+                        ),
+                        None => func.execute(&arg_values),
+                    }
                 }),
                 t: return_type.clone(),
             })
         }
+        &Expr::ArrayFunction { ref fun, ref args } => {
+            let compiled_args = args
+                .iter()
+                .map(|e| Ok(compile_scalar_expr(ctx, e, input_schema)?.get_func()))
+                .collect::<Result<Vec<CompiledExpr>>>()?;
+            let arg_types = args
+                .iter()
+                .map(|e| e.get_type(input_schema))
+                .collect::<Result<Vec<DataType>>>()?;
+            let return_type = expr.get_type(input_schema)?;
+            let t = return_type.clone();
+            let fun = fun.clone();
+            Ok(RuntimeExpr::Compiled {
+                f: Rc::new(move |batch: &RecordBatch| {
+                    let arg_values = compiled_args
+                        .iter()
+                        .map(|e| e(batch))
+                        .collect::<Result<Vec<Value>>>()?;
+                    match fun {
+                        ArrayFunction::Length => array::array_length(&arg_values),
+                        ArrayFunction::Contains => match (arg_types.get(0), arg_types.get(1)) {
+                            (Some(DataType::List(element_type)), Some(value_type)) => {
+                                array::array_contains(&arg_values, element_type, value_type)
+                            }
+                            _ => Err(ExecutionError::type_mismatch(
+                                "array_contains() expects a list and a value".to_string(),
+                            )),
+                        },
+                        ArrayFunction::Element => array::array_element(&arg_values, &t),
+                    }
+                }),
+                t: return_type,
+            })
+        }
+        &Expr::GetField {
+            expr: ref struct_expr,
+            ref name,
+        } => {
+            let compiled_struct = compile_scalar_expr(ctx, struct_expr, input_schema)?.get_func();
+            let return_type = expr.get_type(input_schema)?;
+            let (index, _) = struct_field(&struct_expr.get_type(input_schema)?, name)?;
+            let t = return_type.clone();
+            Ok(RuntimeExpr::Compiled {
+                f: Rc::new(move |batch: &RecordBatch| {
+                    structs::get_field(&compiled_struct(batch)?, index, &t)
+                }),
+                t: return_type,
+            })
+        }
+        &Expr::Struct { ref args, .. } => {
+            let compiled_args = args
+                .iter()
+                .map(|e| Ok(compile_scalar_expr(ctx, e, input_schema)?.get_func()))
+                .collect::<Result<Vec<CompiledExpr>>>()?;
+            let return_type = expr.get_type(input_schema)?;
+            let t = return_type.clone();
+            Ok(RuntimeExpr::Compiled {
+                f: Rc::new(move |batch: &RecordBatch| {
+                    let arg_values = compiled_args
+                        .iter()
+                        .map(|e| e(batch))
+                        .collect::<Result<Vec<Value>>>()?;
+                    structs::make_struct(&arg_values, &t)
+                }),
+                t: return_type,
+            })
+        }
+        &Expr::Parameter { ref name, .. } => Err(ExecutionError::plan(format!(
+            "No value has been bound to parameter {}",
+            name
+        ))),
         // aggregate functions don't fit this pattern .. will need to rework this ..
-        &Expr::AggregateFunction { .. } => panic!("Aggregate expressions cannot be compiled yet"),
+        &Expr::AggregateFunction { .. } => Err(ExecutionError::plan_expr(
+            "Aggregate expressions cannot be compiled as scalar expressions".to_string(),
+            expr,
+        )),
         //        &Expr::AggregateFunction { ref name, ref args } => {
         //
         //            // evaluate the arguments to the function
@@ -1101,9 +1713,65 @@ pub trait SimpleRelation {
 
     /// get the schema for this relation
     fn schema<'a>(&'a self) -> &'a Schema;
+
+    /// describe this relation, not including its inputs (used by EXPLAIN)
+    fn describe(&self) -> String;
+
+    /// get the input relations
+    fn inputs(&self) -> Vec<&SimpleRelation> {
+        vec![]
+    }
+
+    /// get the metrics collected while scanning this relation
+    fn metrics(&self) -> RelationMetrics {
+        RelationMetrics::default()
+    }
+}
+
+/// Format a relation and its inputs as an indented tree, one relation per line
+pub fn format_relation(relation: &SimpleRelation) -> String {
+    let mut lines: Vec<String> = vec![];
+    format_relation_lines(relation, 0, &mut lines);
+    lines.join("\n")
+}
+
+fn format_relation_lines(relation: &SimpleRelation, indent: usize, lines: &mut Vec<String>) {
+    lines.push(format!("{}{}", "  ".repeat(indent), relation.describe()));
+    for input in relation.inputs() {
+        format_relation_lines(input, indent + 1, lines);
+    }
+}
+
+/// Render a relation and its inputs as a Graphviz DOT graph, optionally including the metrics
+/// collected while the relations were scanned
+pub fn relation_to_dot(relation: &SimpleRelation, with_metrics: bool) -> String {
+    let mut graph = DotGraph::new("PhysicalPlan");
+    add_relation_dot_node(relation, with_metrics, &mut graph);
+    graph.to_dot()
+}
+
+fn add_relation_dot_node(
+    relation: &SimpleRelation,
+    with_metrics: bool,
+    graph: &mut DotGraph,
+) -> usize {
+    let mut label = vec![
+        relation.describe(),
+        format!("Schema: [{}]", relation.schema()),
+    ];
+    if with_metrics {
+        label.push(format!("Metrics: [{}]", relation.metrics()));
+    }
+    let id = graph.add_node(&label);
+    for input in relation.inputs() {
+        let input_id = add_relation_dot_node(input, with_metrics, graph);
+        graph.add_edge(id, input_id);
+    }
+    id
 }
 
 struct DataSourceRelation {
+    name: String,
     schema: Schema,
     ds: Rc<RefCell<DataSource>>,
 }
@@ -1116,6 +1784,17 @@ impl SimpleRelation for DataSourceRelation {
     fn schema<'a>(&'a self) -> &'a Schema {
         &self.schema
     }
+
+    fn describe(&self) -> String {
+        self.name.clone()
+    }
+
+    fn metrics(&self) -> RelationMetrics {
+        RelationMetrics {
+            bytes_read: self.ds.borrow().bytes_read(),
+            ..RelationMetrics::default()
+        }
+    }
 }
 
 /// Execution plans are sent to worker nodes for execution
@@ -1144,14 +1823,84 @@ pub enum ExecutionResult {
     Str(String),
 }
 
-struct ExecutionContextSchemaProvider {
-    tables: Rc<RefCell<HashMap<String, Rc<DataFrame>>>>,
+/// The outcome of running one statement of a script with `execute_script`
+pub struct StatementResult {
+    /// The statement that was run, or `None` if it could not be parsed
+    pub statement: Option<ASTNode>,
+    /// The batches produced by the statement, or the error that occurred while parsing,
+    /// planning or executing it
+    pub result: Result<Vec<Rc<RecordBatch>>>,
+}
+
+/// A query that has been parsed, planned and optimized once, so that it can be executed many
+/// times with different values bound to its parameters
+pub struct PreparedStatement {
+    ctx: ExecutionContext,
+    plan: Rc<LogicalPlan>,
+    parameters: Vec<Field>,
+}
+
+impl PreparedStatement {
+    /// The parameters of the statement, in the order that `execute` expects their values.
+    /// Positional parameters come first, ordered by number, followed by named parameters in
+    /// the order they first appear in the statement.
+    pub fn parameters(&self) -> &Vec<Field> {
+        &self.parameters
+    }
+
+    /// The optimized logical plan, which references the parameters
+    pub fn plan(&self) -> &Rc<LogicalPlan> {
+        &self.plan
+    }
+
+    /// Bind values to the parameters, returning a DataFrame for the query. Numeric values are
+    /// cast to the type of their parameter.
+    pub fn execute(&self, params: &[ScalarValue]) -> Result<Rc<DataFrame>> {
+        if params.len() != self.parameters.len() {
+            return Err(ExecutionError::plan(format!(
+                "Expected {} parameter values but {} were provided",
+                self.parameters.len(),
+                params.len()
+            )));
+        }
+        let mut values = vec![];
+        for (parameter, value) in self.parameters.iter().zip(params.iter()) {
+            values.push((parameter.name().clone(), bind_value(parameter, value)?));
+        }
+        let plan = bind_parameters(&self.plan, &values)?;
+        Ok(Rc::new(DF::new(self.ctx.clone(), plan)))
+    }
+}
+
+/// Convert a value to the type of the parameter it is bound to. Null can be bound to nullable
+/// parameters.
+fn bind_value(parameter: &Field, value: &ScalarValue) -> Result<ScalarValue> {
+    match value {
+        ScalarValue::Null if parameter.is_nullable() => Ok(ScalarValue::Null),
+        ScalarValue::Null | ScalarValue::Struct(_) | ScalarValue::List(..) => {
+            Err(ExecutionError::type_error(
+                format!(
+                    "Cannot bind value to parameter {} of type {:?}",
+                    parameter.name(),
+                    parameter.data_type()
+                ),
+                value,
+            ))
+        }
+        _ if value.get_datatype()? == *parameter.data_type() => Ok(value.clone()),
+        _ => cast::cast_scalar(value, parameter.data_type(), false),
+    }
+}
+
+struct ExecutionContextSchemaProvider {
+    tables: Rc<RefCell<HashMap<String, Rc<DataFrame>>>>,
     function_meta: Rc<RefCell<HashMap<String, Rc<FunctionMeta>>>>,
+    statistics: Rc<RefCell<HashMap<String, Statistics>>>,
 }
 
 impl SchemaProvider for ExecutionContextSchemaProvider {
     fn get_table_meta(&self, name: &str) -> Option<Rc<Schema>> {
-        match self.tables.borrow().get(&name.to_string().to_lowercase()) {
+        match self.tables.borrow().get(name) {
             Some(table) => Some(table.schema().clone()),
             None => None,
         }
@@ -1167,6 +1916,14 @@ impl SchemaProvider for ExecutionContextSchemaProvider {
             None => None,
         }
     }
+
+    fn get_table_statistics(&self, name: &str) -> Option<Statistics> {
+        self.statistics.borrow().get(name).cloned()
+    }
+
+    fn table_names(&self) -> Vec<String> {
+        self.tables.borrow().keys().cloned().collect()
+    }
 }
 
 #[derive(Clone)]
@@ -1174,7 +1931,13 @@ pub struct ExecutionContext {
     tables: Rc<RefCell<HashMap<String, Rc<DataFrame>>>>,
     function_meta: Rc<RefCell<HashMap<String, Rc<FunctionMeta>>>>,
     functions: Rc<RefCell<HashMap<String, Rc<ScalarFunction>>>>,
+    statistics: Rc<RefCell<HashMap<String, Statistics>>>,
+    last_metrics: Rc<RefCell<Option<PlanMetrics>>>,
     config: Rc<DFConfig>,
+    overflow_mode: OverflowMode,
+    /// When the query being executed started, as a microsecond timestamp. `now()` and
+    /// `current_date()` return this for every row and batch of a query.
+    query_start: Rc<Cell<i64>>,
 }
 
 impl ExecutionContext {
@@ -1182,16 +1945,29 @@ impl ExecutionContext {
         Rc::new(ExecutionContextSchemaProvider {
             tables: self.tables.clone(),
             function_meta: self.function_meta.clone(),
+            statistics: self.statistics.clone(),
         })
     }
 
     pub fn local() -> Self {
-        ExecutionContext {
+        let mut ctx = ExecutionContext {
             tables: Rc::new(RefCell::new(HashMap::new())),
             function_meta: Rc::new(RefCell::new(HashMap::new())),
             functions: Rc::new(RefCell::new(HashMap::new())),
+            statistics: Rc::new(RefCell::new(HashMap::new())),
+            last_metrics: Rc::new(RefCell::new(None)),
             config: Rc::new(DFConfig::Local),
+            overflow_mode: OverflowMode::default(),
+            query_start: Rc::new(Cell::new(temporal::current_timestamp(TimeUnit::Microsecond))),
+        };
+
+        // date, time and binary functions are always available
+        let datetime_functions = datetime::functions(ctx.query_start.clone());
+        for func in datetime_functions.into_iter().chain(binary::functions()) {
+            ctx.register_scalar_function(func);
         }
+
+        ctx
     }
 
     pub fn register_scalar_function(&mut self, func: Rc<ScalarFunction>) {
@@ -1200,6 +1976,7 @@ impl ExecutionContext {
             func.args(),
             func.return_type(),
             FunctionType::Scalar,
+            func.is_deterministic(),
         );
 
         self.function_meta
@@ -1211,6 +1988,21 @@ impl ExecutionContext {
             .insert(func.name().to_lowercase(), func.clone());
     }
 
+    /// Get how integer overflow and division by zero are handled by queries
+    pub fn overflow_mode(&self) -> OverflowMode {
+        self.overflow_mode
+    }
+
+    /// Set how integer overflow and division by zero are handled by queries. By default they
+    /// are errors.
+    ///
+    /// Division by zero is tied to the same mode: it is an error when `Checked` and null when
+    /// `Wrapping`, for integer and decimal operands alike. Decimal overflow is an error in
+    /// either mode, since a decimal result has no width to wrap around.
+    pub fn set_overflow_mode(&mut self, mode: OverflowMode) {
+        self.overflow_mode = mode;
+    }
+
     pub fn create_logical_plan(&self, sql: &str) -> Result<Rc<LogicalPlan>> {
         // parse SQL into AST
         let ast = Parser::parse_sql(String::from(sql))?;
@@ -1222,11 +2014,204 @@ impl ExecutionContext {
         Ok(query_planner.sql_to_rel(&ast)?)
     }
 
+    /// Resolve a table name in SQL to the name of a registered table, with the same rules as
+    /// table names in queries
+    pub fn resolve_table_name(&self, name: &SQLIdent) -> Result<String> {
+        resolve_table_name(self.create_schema_provider().as_ref(), name)
+    }
+
+    fn table_not_found(&self, table_name: &str) -> ExecutionError {
+        let names: Vec<String> = self.tables.borrow().keys().cloned().collect();
+        ExecutionError::table_not_found(table_name, &names)
+    }
+
     pub fn register(&mut self, table_name: &str, df: Rc<DataFrame>) {
         //println!("Registering table {}", table_name);
         self.tables
             .borrow_mut()
             .insert(table_name.to_string(), df.clone());
+
+        // Parquet files have statistics in their metadata so there is no need to ANALYZE them
+        let mut statistics = self.statistics.borrow_mut();
+        statistics.remove(table_name);
+        if let LogicalPlan::ParquetFile { ref filename, .. } = df.plan().as_ref() {
+            if let Ok(file) = File::open(filename) {
+                if let Ok(p) = ParquetFile::open(file, None) {
+                    statistics.insert(table_name.to_string(), p.statistics());
+                }
+            }
+        }
+    }
+
+    /// Get the statistics for a registered table, if they are known
+    pub fn table_statistics(&self, table_name: &str) -> Option<Statistics> {
+        self.statistics.borrow().get(table_name).cloned()
+    }
+
+    /// Set the statistics for a registered table, e.g. when they are known from an external
+    /// catalog
+    pub fn set_table_statistics(&mut self, table_name: &str, statistics: Statistics) {
+        self.statistics
+            .borrow_mut()
+            .insert(table_name.to_string(), statistics);
+    }
+
+    /// Compute statistics for a registered table by scanning it, as for `ANALYZE table`
+    pub fn analyze_table(&mut self, table_name: &str) -> Result<Statistics> {
+        let plan = match self.tables.borrow().get(table_name) {
+            Some(df) => df.plan().clone(),
+            None => return Err(self.table_not_found(table_name)),
+        };
+
+        let mut rel = self.create_execution_plan(&plan)?;
+        let num_columns = rel.schema().columns().len();
+
+        let mut row_count = 0;
+        let mut columns: Vec<ColumnStatistics> = vec![ColumnStatistics::default(); num_columns];
+        // the distinct values of each column, or None for columns whose values cannot be hashed
+        let mut distinct_values: Vec<Option<HashSet<GroupScalar>>> =
+            vec![Some(HashSet::new()); num_columns];
+        for c in columns.iter_mut() {
+            c.null_count = Some(0);
+        }
+
+        for batch in rel.scan() {
+            let batch = batch?;
+            row_count += batch.num_rows();
+            for i in 0..num_columns {
+                let stats = &mut columns[i];
+                for row in 0..batch.num_rows() {
+                    let value = match batch.column(i) {
+                        &Value::Column(ref array) => {
+                            if let &Some(ref bitmap) = array.validity_bitmap() {
+                                if !bitmap.is_set(row) {
+                                    stats.null_count = stats.null_count.map(|n| n + 1);
+                                    continue;
+                                }
+                            }
+                            get_value(array, row)
+                        }
+                        &Value::Scalar(ref v) => v.as_ref().clone(),
+                    };
+                    if stats.min_value.as_ref().map_or(true, |min| value < *min) {
+                        stats.min_value = Some(value.clone());
+                    }
+                    if stats.max_value.as_ref().map_or(true, |max| value > *max) {
+                        stats.max_value = Some(value.clone());
+                    }
+                    match GroupScalar::from_scalar(&value) {
+                        Some(key) => if let Some(ref mut set) = distinct_values[i] {
+                            set.insert(key);
+                        },
+                        None => distinct_values[i] = None,
+                    }
+                }
+            }
+        }
+
+        for (c, d) in columns.iter_mut().zip(distinct_values.iter()) {
+            c.distinct_count = d.as_ref().map(|d| d.len());
+        }
+
+        let byte_size = match plan.as_ref() {
+            LogicalPlan::CsvFile { ref filename, .. }
+            | LogicalPlan::NdJsonFile { ref filename, .. }
+            | LogicalPlan::ParquetFile { ref filename, .. } => {
+                fs::metadata(filename).ok().map(|m| m.len() as usize)
+            }
+            _ => None,
+        };
+
+        let statistics = Statistics {
+            row_count: Some(row_count),
+            byte_size,
+            column_statistics: Some(columns),
+        };
+        self.set_table_statistics(table_name, statistics.clone());
+        Ok(statistics)
+    }
+
+    /// Apply the optimizer rules to a logical plan
+    fn optimizer_rules(&self) -> Vec<Box<OptimizerRule>> {
+        vec![
+            Box::new(JoinReorder::new(CostModel::new(
+                self.create_schema_provider(),
+            ))),
+            Box::new(CommonSubexprEliminate::new(self.create_schema_provider())),
+            Box::new(ProjectionPushDown::new()),
+        ]
+    }
+
+    fn optimize(&self, plan: &Rc<LogicalPlan>) -> Result<Rc<LogicalPlan>> {
+        let mut plan = plan.clone();
+        for rule in self.optimizer_rules().iter_mut() {
+            plan = rule.optimize(&plan)?;
+        }
+        Ok(plan)
+    }
+
+    /// Plan and optimize a query, returning the plans for each stage as they would be reported
+    /// by EXPLAIN. When `verbose` is set the plan is also recorded after each optimizer rule
+    /// that changed it. When `analyze` is set the query is executed and the physical plan is
+    /// annotated with the metrics collected for each relation.
+    fn explain(
+        &self,
+        verbose: bool,
+        analyze: bool,
+        format: &ExplainFormat,
+        ast: &ASTNode,
+    ) -> Result<Vec<StringifiedPlan>> {
+        let stringify = |plan: &LogicalPlan| match *format {
+            ExplainFormat::Text => format!("{:?}", plan),
+            ExplainFormat::Dot => plan.to_dot(),
+        };
+
+        let query_planner = SqlToRel::new(self.create_schema_provider());
+        let mut plan = query_planner.sql_to_rel(ast)?;
+
+        let mut plans = vec![StringifiedPlan::new("logical_plan", stringify(plan.as_ref()))];
+        for rule in self.optimizer_rules().iter_mut() {
+            let new_plan = rule.optimize(&plan)?;
+            if verbose && format!("{:?}", new_plan) != format!("{:?}", plan) {
+                plans.push(StringifiedPlan::new(
+                    &format!("logical_plan after {}", rule.name()),
+                    stringify(new_plan.as_ref()),
+                ));
+            }
+            plan = new_plan;
+        }
+        plans.push(StringifiedPlan::new(
+            "optimized_logical_plan",
+            stringify(plan.as_ref()),
+        ));
+
+        let mut relation = self.create_execution_plan(&plan)?;
+        if analyze {
+            {
+                let it = relation.scan();
+                for batch in it {
+                    batch?;
+                }
+            }
+            let metrics = PlanMetrics::from_relation(relation.as_ref());
+            let physical_plan = match *format {
+                ExplainFormat::Text => metrics.to_string(),
+                ExplainFormat::Dot => relation_to_dot(relation.as_ref(), true),
+            };
+            plans.push(StringifiedPlan::new(
+                "physical_plan_with_metrics",
+                physical_plan,
+            ));
+            *self.last_metrics.borrow_mut() = Some(metrics);
+        } else {
+            let physical_plan = match *format {
+                ExplainFormat::Text => format_relation(relation.as_ref()),
+                ExplainFormat::Dot => relation_to_dot(relation.as_ref(), false),
+            };
+            plans.push(StringifiedPlan::new("physical_plan", physical_plan));
+        }
+
+        Ok(plans)
     }
 
     pub fn sql(&mut self, sql: &str) -> Result<Rc<DataFrame>> {
@@ -1236,6 +2221,68 @@ impl ExecutionContext {
         let ast = Parser::parse_sql(String::from(sql))?;
         //println!("AST: {:?}", ast);
 
+        self.sql_statement(ast)
+    }
+
+    /// Run a script of SQL statements separated by semicolons, in order. Each statement is
+    /// planned and executed before the next one is planned, so that tables created by earlier
+    /// statements can be queried by later ones. When `stop_on_error` is set, no further
+    /// statements are run after a statement fails. A statement with a syntax error fails
+    /// without affecting the statements before it. Only an error in tokenizing the script,
+    /// such as an unterminated string, is returned as an error for the whole script.
+    pub fn execute_script(
+        &mut self,
+        sql: &str,
+        stop_on_error: bool,
+    ) -> Result<Vec<StatementResult>> {
+        let statements = Parser::parse_each_statement(String::from(sql))?;
+        let mut results = vec![];
+        for statement in statements {
+            let (statement, result) = match statement {
+                Ok(ast) => {
+                    let result = self
+                        .sql_statement(ast.clone())
+                        .and_then(|df| self.collect(df.as_ref()));
+                    (Some(ast), result)
+                }
+                Err(e) => (None, Err(e.into())),
+            };
+            let failed = result.is_err();
+            results.push(StatementResult { statement, result });
+            if failed && stop_on_error {
+                break;
+            }
+        }
+        Ok(results)
+    }
+
+    /// Parse, plan and optimize a query containing parameter placeholders (`$1`, `?` or
+    /// `:name`), so that it can be executed repeatedly without being parsed again. The type of
+    /// each parameter is inferred from the expression it is used in.
+    pub fn prepare(&self, sql: &str) -> Result<PreparedStatement> {
+        let ast = Parser::parse_sql(String::from(sql))?;
+        match ast {
+            SQLSelect { .. } => {}
+            _ => {
+                return Err(ExecutionError::plan(String::from(
+                    "Only SELECT statements can be prepared",
+                )))
+            }
+        }
+
+        let query_planner = SqlToRel::new(self.create_schema_provider());
+        let plan = query_planner.sql_to_rel(&ast)?;
+        let parameters = query_planner.parameters()?;
+
+        Ok(PreparedStatement {
+            ctx: self.clone(),
+            plan: self.optimize(&plan)?,
+            parameters,
+        })
+    }
+
+    /// Run a parsed SQL statement, returning a DataFrame for queries
+    fn sql_statement(&mut self, ast: ASTNode) -> Result<Rc<DataFrame>> {
         match ast {
             SQLCreateTable {
                 name,
@@ -1266,6 +2313,38 @@ impl ExecutionContext {
                     }),
                 )))
             }
+            SQLExplain {
+                verbose,
+                analyze,
+                format,
+                statement,
+            } => {
+                let plans = self.explain(verbose, analyze, &format, &statement)?;
+                let schema = Schema::new(vec![
+                    Field::new("plan_type", DataType::Utf8, false),
+                    Field::new("plan", DataType::Utf8, false),
+                ]);
+
+                Ok(Rc::new(DF::new(
+                    self.clone(),
+                    Rc::new(LogicalPlan::Explain {
+                        verbose,
+                        plans,
+                        schema: Rc::new(schema),
+                    }),
+                )))
+            }
+            SQLAnalyze { name } => {
+                let name = self.resolve_table_name(&name)?;
+                self.analyze_table(&name)?;
+
+                Ok(Rc::new(DF::new(
+                    self.clone(),
+                    Rc::new(LogicalPlan::EmptyRelation {
+                        schema: Rc::new(Schema::empty()),
+                    }),
+                )))
+            }
             _ => {
                 // create a query planner
                 let query_planner = SqlToRel::new(self.create_schema_provider());
@@ -1274,7 +2353,7 @@ impl ExecutionContext {
                 let plan = query_planner.sql_to_rel(&ast)?;
                 //println!("Logical plan: {:?}", plan);
 
-                let new_plan = push_down_projection(&plan, &HashSet::new());
+                let new_plan = self.optimize(&plan)?;
                 //println!("Optimized logical plan: {:?}", new_plan);
 
                 // return the DataFrame
@@ -1330,42 +2409,119 @@ impl ExecutionContext {
             filename: filename.to_string(),
             schema: p.schema().clone(),
             projection,
+            fields: vec![],
         };
         Ok(Rc::new(DF::new(self.clone(), Rc::new(plan))))
     }
 
+    /// Create the relation that executes a logical plan. Every relation is wrapped so that
+    /// metrics are collected while it is scanned. This starts a new query, so the current time
+    /// is captured for `now()` and `current_date()`.
     pub fn create_execution_plan(&self, plan: &LogicalPlan) -> Result<Box<SimpleRelation>> {
+        self.query_start.set(temporal::current_timestamp(TimeUnit::Microsecond));
+        self.create_metered_relation(plan)
+    }
+
+    fn create_metered_relation(&self, plan: &LogicalPlan) -> Result<Box<SimpleRelation>> {
+        self.create_filtered_relation(plan, &[])
+    }
+
+    /// Create a metered relation where the scan of a Parquet table skips the row groups in which
+    /// no row satisfies the predicates. The rows that are read are not filtered.
+    fn create_filtered_relation(
+        &self,
+        plan: &LogicalPlan,
+        predicates: &[RowGroupPredicate],
+    ) -> Result<Box<SimpleRelation>> {
+        let relation = self.create_relation(plan, predicates)?;
+        match *plan {
+            // the relation for a table scan is the relation for the registered table, which has
+            // already been wrapped
+            LogicalPlan::TableScan { .. } => Ok(relation),
+            _ => Ok(Box::new(MetricsRelation::new(relation))),
+        }
+    }
+
+    /// Execute the plan for a DataFrame and return all of its batches. The metrics collected
+    /// while executing the plan are available from `last_metrics()` afterwards.
+    pub fn collect(&self, df: &DataFrame) -> Result<Vec<Rc<RecordBatch>>> {
+        let mut relation = self.create_execution_plan(df.plan())?;
+        let batches = {
+            let it = relation.scan();
+            it.collect::<Result<Vec<Rc<RecordBatch>>>>()?
+        };
+        *self.last_metrics.borrow_mut() = Some(PlanMetrics::from_relation(relation.as_ref()));
+        Ok(batches)
+    }
+
+    /// Get the metrics for the relations of the last query executed by `collect()` or
+    /// `EXPLAIN ANALYZE`
+    pub fn last_metrics(&self) -> Option<PlanMetrics> {
+        self.last_metrics.borrow().clone()
+    }
+
+    fn create_relation(
+        &self,
+        plan: &LogicalPlan,
+        predicates: &[RowGroupPredicate],
+    ) -> Result<Box<SimpleRelation>> {
         //println!("Logical plan: {:?}", plan);
 
         match *plan {
             LogicalPlan::EmptyRelation { .. } => Ok(Box::new(DataSourceRelation {
+                name: String::from("EmptyRelation"),
                 schema: Schema::new(vec![]),
                 ds: Rc::new(RefCell::new(EmptyRelation::new())),
             })),
 
-            LogicalPlan::Sort { .. } => unimplemented!(),
+            LogicalPlan::Explain {
+                ref plans,
+                ref schema,
+                ..
+            } => Ok(Box::new(ExplainRelation::new(
+                schema.clone(),
+                plans.clone(),
+            ))),
+
+            LogicalPlan::Sort { .. } => Err(ExecutionError::NotImplemented),
 
             LogicalPlan::TableScan {
                 ref table_name,
+                ref schema,
                 ref projection,
+                ref fields,
                 ..
             } => {
                 //println!("TableScan: {}", table_name);
                 match self.tables.borrow().get(table_name) {
-                    Some(df) => match projection {
-                        Some(p) => {
-                            let mut h: HashSet<usize> = HashSet::new();
-                            p.iter().for_each(|i| {
-                                h.insert(*i);
-                            });
-                            self.create_execution_plan(&push_down_projection(df.plan(), &h))
+                    Some(df) => {
+                        let table_schema = df.plan().schema();
+                        let predicates = predicates
+                            .iter()
+                            .map(|p| {
+                                Ok(RowGroupPredicate {
+                                    path: table_path(&p.path, schema, table_schema)?,
+                                    ..p.clone()
+                                })
+                            })
+                            .collect::<Result<Vec<RowGroupPredicate>>>()?;
+                        match projection {
+                            Some(p) => {
+                                let mut h: HashSet<usize> = HashSet::new();
+                                p.iter().for_each(|i| {
+                                    h.insert(*i);
+                                });
+                                let fields = fields
+                                    .iter()
+                                    .map(|path| table_path(path, schema, table_schema))
+                                    .collect::<Result<Vec<Vec<String>>>>()?;
+                                let plan = push_down_field_paths(df.plan(), &h, &fields)?;
+                                self.create_filtered_relation(&plan, &predicates)
+                            }
+                            None => self.create_filtered_relation(df.plan(), &predicates),
                         }
-                        None => self.create_execution_plan(df.plan()),
-                    },
-                    _ => Err(ExecutionError::General(format!(
-                        "No table registered as '{}'",
-                        table_name
-                    ))),
+                    }
+                    _ => Err(self.table_not_found(table_name)),
                 }
             }
 
@@ -1376,13 +2532,11 @@ impl ExecutionContext {
                 ref projection,
             } => {
                 let file = File::open(filename)?;
-                let ds = Rc::new(RefCell::new(CsvFile::open(
-                    file,
-                    schema.clone(),
-                    *has_header,
-                    projection.clone(),
-                )?)) as Rc<RefCell<DataSource>>;
+                let mut csv = CsvFile::open(file, schema.clone(), *has_header, projection.clone())?;
+                csv.set_filename(filename);
+                let ds = Rc::new(RefCell::new(csv)) as Rc<RefCell<DataSource>>;
                 Ok(Box::new(DataSourceRelation {
+                    name: format!("CsvFile: file={}, projection={:?}", filename, projection),
                     schema: schema.as_ref().clone(),
                     ds,
                 }))
@@ -1394,12 +2548,11 @@ impl ExecutionContext {
                 ref projection,
             } => {
                 let file = File::open(filename)?;
-                let ds = Rc::new(RefCell::new(NdJsonFile::open(
-                    file,
-                    schema.clone(),
-                    projection.clone(),
-                )?)) as Rc<RefCell<DataSource>>;
+                let mut json = NdJsonFile::open(file, schema.clone(), projection.clone())?;
+                json.set_filename(filename);
+                let ds = Rc::new(RefCell::new(json)) as Rc<RefCell<DataSource>>;
                 Ok(Box::new(DataSourceRelation {
+                    name: format!("NdJsonFile: file={}, projection={:?}", filename, projection),
                     schema: schema.as_ref().clone(),
                     ds,
                 }))
@@ -1409,11 +2562,19 @@ impl ExecutionContext {
                 ref filename,
                 ref schema,
                 ref projection,
+                ref fields,
             } => {
                 let file = File::open(filename)?;
-                let ds = Rc::new(RefCell::new(ParquetFile::open(file, projection.clone())?))
-                    as Rc<RefCell<DataSource>>;
+                let mut parquet = ParquetFile::open(file, projection.clone())?;
+                parquet.set_filename(filename);
+                parquet.set_field_projection(fields)?;
+                parquet.set_row_group_filter(predicates)?;
+                let ds = Rc::new(RefCell::new(parquet)) as Rc<RefCell<DataSource>>;
                 Ok(Box::new(DataSourceRelation {
+                    name: format!(
+                        "ParquetFile: file={}, projection={:?}",
+                        filename, projection
+                    ),
                     schema: schema.as_ref().clone(),
                     ds,
                 }))
@@ -1423,8 +2584,15 @@ impl ExecutionContext {
                 ref expr,
                 ref input,
             } => {
-                let input_rel = self.create_execution_plan(input)?;
-                let runtime_expr = compile_scalar_expr(&self, expr, input_rel.schema())?;
+                // comparisons with literals also skip the Parquet row groups without any
+                // matching rows
+                let predicates = match **input {
+                    LogicalPlan::TableScan { ref schema, .. } => row_group_predicates(expr, schema),
+                    _ => vec![],
+                };
+                let input_rel = self.create_filtered_relation(input, &predicates)?;
+                let expr = resolve_columns(expr, input.schema())?;
+                let runtime_expr = compile_scalar_expr(&self, &expr, input_rel.schema())?;
                 let rel = FilterRelation::new(input_rel, runtime_expr.get_func().clone());
                 Ok(Box::new(rel))
             }
@@ -1434,9 +2602,15 @@ impl ExecutionContext {
                 ref input,
                 ..
             } => {
-                let input_rel = self.create_execution_plan(&input)?;
+                let input_rel = self.create_metered_relation(&input)?;
 
-                let project_columns: Vec<Field> = exprlist_to_fields(&expr, input_rel.schema());
+                // resolve column names against the logical schema of the input
+                let expr = expr
+                    .iter()
+                    .map(|e| resolve_columns(e, input.schema()))
+                    .collect::<Result<Vec<Expr>>>()?;
+
+                let project_columns: Vec<Field> = exprlist_to_fields(&expr, input_rel.schema())?;
 
                 let project_schema = Rc::new(Schema::new(project_columns));
 
@@ -1456,7 +2630,17 @@ impl ExecutionContext {
                 ref aggr_expr,
                 ..
             } => {
-                let input_rel = self.create_execution_plan(&input)?;
+                let input_rel = self.create_metered_relation(&input)?;
+
+                // resolve column names against the logical schema of the input
+                let group_expr = group_expr
+                    .iter()
+                    .map(|e| resolve_columns(e, input.schema()))
+                    .collect::<Result<Vec<Expr>>>()?;
+                let aggr_expr = aggr_expr
+                    .iter()
+                    .map(|e| resolve_columns(e, input.schema()))
+                    .collect::<Result<Vec<Expr>>>()?;
 
                 let compiled_group_expr_result: Result<Vec<RuntimeExpr>> = group_expr
                     .iter()
@@ -1509,10 +2693,67 @@ impl ExecutionContext {
                 ref schema,
                 ..
             } => {
-                let input_rel = self.create_execution_plan(input)?;
+                let input_rel = self.create_metered_relation(input)?;
                 let rel = LimitRelation::new(schema.clone(), input_rel, limit);
                 Ok(Box::new(rel))
             }
+
+            LogicalPlan::Join {
+                ref left,
+                ref right,
+                ref on,
+                ref schema,
+                ..
+            } => {
+                let left_keys = on
+                    .iter()
+                    .map(|(l, _)| l.index_of(left.schema()))
+                    .collect::<Result<Vec<usize>>>()?;
+                let right_keys = on
+                    .iter()
+                    .map(|(_, r)| r.index_of(right.schema()))
+                    .collect::<Result<Vec<usize>>>()?;
+                let left_rel = self.create_metered_relation(left)?;
+                let right_rel = self.create_metered_relation(right)?;
+
+                // build the hash table from the smaller input
+                let cost_model = CostModel::new(self.create_schema_provider());
+                let build_left = cost_model.build_left(left, right);
+
+                let rel = HashJoinRelation::new(
+                    schema.clone(),
+                    left_rel,
+                    right_rel,
+                    left_keys,
+                    right_keys,
+                    build_left,
+                );
+                Ok(Box::new(rel))
+            }
+
+            LogicalPlan::Union {
+                ref inputs,
+                ref schema,
+            } => {
+                let input_rels = inputs
+                    .iter()
+                    .map(|input| self.create_metered_relation(input))
+                    .collect::<Result<Vec<Box<SimpleRelation>>>>()?;
+                let rel = UnionRelation::new(schema.clone(), input_rels);
+                Ok(Box::new(rel))
+            }
+
+            LogicalPlan::Unnest {
+                ref input,
+                ref expr,
+                ref schema,
+            } => {
+                let input_rel = self.create_metered_relation(input)?;
+                let expr = resolve_columns(expr, input.schema())?;
+                let compiled_expr = compile_scalar_expr(&self, &expr, input_rel.schema())?;
+                let rel = UnnestRelation::new(schema.clone(), input_rel, compiled_expr);
+                Ok(Box::new(rel))
+            }
         }
     }
 
@@ -1520,7 +2761,7 @@ impl ExecutionContext {
     fn load_scalar_function(&self, function_name: &str) -> Result<Rc<ScalarFunction>> {
         match self.functions.borrow().get(&function_name.to_lowercase()) {
             Some(f) => Ok(f.clone()),
-            _ => Err(ExecutionError::General(format!(
+            _ => Err(ExecutionError::plan(format!(
                 "Unknown scalar function {}",
                 function_name
             ))),
@@ -1597,14 +2838,7 @@ impl ExecutionContext {
         //println!("execute()");
         match &self.config.as_ref() {
             &DFConfig::Local => {
-                //TODO error handling
-                match self.execute_local(physical_plan) {
-                    Ok(r) => Ok(r),
-                    Err(e) => Err(ExecutionError::General(format!(
-                        "execution failed: {:?}",
-                        e
-                    ))),
-                }
+                self.execute_local(physical_plan)
             }
             &DFConfig::Remote { ref etcd } => self.execute_remote(physical_plan, etcd.clone()),
         }
@@ -1618,24 +2852,19 @@ impl ExecutionContext {
                 let mut execution_plan = self.create_execution_plan(plan)?;
 
                 // implement execution here for now but should be a common method for processing a plan
-                let it = execution_plan.scan();
-                it.for_each(|t| {
-                    match t {
-                        Ok(ref batch) => {
-                            ////println!("Processing batch of {} rows", batch.row_count());
-                            for i in 0..batch.num_rows() {
-                                let row = batch.row_slice(i);
-                                let csv = row
-                                    .into_iter()
-                                    .map(|v| v.to_string())
-                                    .collect::<Vec<String>>()
-                                    .join(",");
-                                println!("{}", csv);
-                            }
-                        }
-                        Err(e) => panic!(format!("Error processing row: {:?}", e)), //TODO: error handling
+                for t in execution_plan.scan() {
+                    let batch = t?;
+                    ////println!("Processing batch of {} rows", batch.row_count());
+                    for i in 0..batch.num_rows() {
+                        let row = batch.row_slice(i);
+                        let csv = row
+                            .into_iter()
+                            .map(|v| v.to_string())
+                            .collect::<Vec<String>>()
+                            .join(",");
+                        println!("{}", csv);
                     }
-                });
+                }
 
                 Ok(ExecutionResult::Count(0))
             }
@@ -1656,97 +2885,74 @@ impl ExecutionContext {
                         let mut execution_plan = self.create_execution_plan(plan)?;
 
                         // implement execution here for now but should be a common method for processing a plan
-                        let it = execution_plan.scan();
                         let mut count: usize = 0;
-                        it.for_each(|t| {
-                            match t {
-                                Ok(ref batch) => {
-                                    ////println!("Processing batch of {} rows", batch.row_count());
-                                    for i in 0..batch.num_rows() {
-                                        for j in 0..batch.num_columns() {
-                                            if j > 0 {
-                                                w.write_bytes(b",");
-                                            }
-                                            match *batch.column(j) {
-                                                Value::Scalar(ref v) => w.write_scalar(v),
-                                                Value::Column(ref v) => match v.data() {
-                                                    ArrayData::Boolean(ref v) => {
-                                                        w.write_bool(v.get(i))
-                                                    }
-                                                    ArrayData::Float32(ref v) => {
-                                                        w.write_f32(v.get(i))
-                                                    }
-                                                    ArrayData::Float64(ref v) => {
-                                                        w.write_f64(v.get(i))
-                                                    }
-                                                    ArrayData::Int8(ref v) => w.write_i8(v.get(i)),
-                                                    ArrayData::Int16(ref v) => {
-                                                        w.write_i16(v.get(i))
-                                                    }
-                                                    ArrayData::Int32(ref v) => {
-                                                        w.write_i32(v.get(i))
-                                                    }
-                                                    ArrayData::Int64(ref v) => {
-                                                        w.write_i64(v.get(i))
-                                                    }
-                                                    ArrayData::UInt8(ref v) => w.write_u8(v.get(i)),
-                                                    ArrayData::UInt16(ref v) => {
-                                                        w.write_u16(v.get(i))
-                                                    }
-                                                    ArrayData::UInt32(ref v) => {
-                                                        w.write_u32(v.get(i))
-                                                    }
-                                                    ArrayData::UInt64(ref v) => {
-                                                        w.write_u64(v.get(i))
-                                                    }
-                                                    ArrayData::Utf8(ref data) => {
-                                                        w.write_bytes(data.get(i))
-                                                    }
-                                                    ArrayData::Struct(ref v) => {
-                                                        let fields = v
-                                                            .iter()
-                                                            .map(|arr| get_value(&arr, i))
-                                                            .collect();
-                                                        w.write_bytes(
-                                                            format!("{}", ScalarValue::Struct(fields))
-                                                                .as_bytes(),
-                                                        );
-                                                    }
-                                                },
+                        for t in execution_plan.scan() {
+                            let batch = t?;
+                            ////println!("Processing batch of {} rows", batch.row_count());
+                            for i in 0..batch.num_rows() {
+                                for j in 0..batch.num_columns() {
+                                    if j > 0 {
+                                        w.write_bytes(b",")?;
+                                    }
+                                    match *batch.column(j) {
+                                        Value::Scalar(ref v) => w.write_scalar(v)?,
+                                        // null values are written as empty fields
+                                        Value::Column(ref v) if !v.is_valid(i) => {}
+                                        Value::Column(ref v) => match v.data() {
+                                            ArrayData::Boolean(ref v) => w.write_bool(v.get(i))?,
+                                            ArrayData::Float32(ref v) => w.write_f32(v.get(i))?,
+                                            ArrayData::Float64(ref v) => w.write_f64(v.get(i))?,
+                                            ArrayData::Int8(ref v) => w.write_i8(v.get(i))?,
+                                            ArrayData::Int16(ref v) => w.write_i16(v.get(i))?,
+                                            ArrayData::Int32(ref v) => w.write_i32(v.get(i))?,
+                                            ArrayData::Int64(ref v) => w.write_i64(v.get(i))?,
+                                            ArrayData::UInt8(ref v) => w.write_u8(v.get(i))?,
+                                            ArrayData::UInt16(ref v) => w.write_u16(v.get(i))?,
+                                            ArrayData::UInt32(ref v) => w.write_u32(v.get(i))?,
+                                            ArrayData::UInt64(ref v) => w.write_u64(v.get(i))?,
+                                            ArrayData::Utf8(_)
+                                            | ArrayData::Binary(_)
+                                            | ArrayData::Date32(_)
+                                            | ArrayData::Time64(..)
+                                            | ArrayData::Timestamp(..)
+                                            | ArrayData::Decimal(..)
+                                            | ArrayData::List(..)
+                                            | ArrayData::Struct(_) => {
+                                                w.write_scalar(&get_value(v, i))?
                                             }
-                                        }
-                                        w.write_bytes(b"\n");
-                                        count += 1;
+                                        },
                                     }
                                 }
-                                Err(e) => panic!(format!("Error processing row: {:?}", e)), //TODO: error handling
+                                w.write_bytes(b"\n")?;
+                                count += 1;
                             }
-                        });
+                        }
+                        w.flush()?;
 
                         Ok(ExecutionResult::Count(count))
                     }
                     "string" => {
                         let mut execution_plan = self.create_execution_plan(plan)?;
-                        let it = execution_plan.scan();
                         let mut result = String::new();
-                        it.for_each(|t| match t {
-                            Ok(ref batch) => {
-                                for i in 0..batch.num_rows() {
-                                    let results = batch
-                                        .row_slice(i)
-                                        .into_iter()
-                                        .map(|v| v.to_string())
-                                        .collect::<Vec<String>>()
-                                        .join(",");
-                                    result.push_str(&results);
-                                    result.push_str("\n")
-                                }
+                        for t in execution_plan.scan() {
+                            let batch = t?;
+                            for i in 0..batch.num_rows() {
+                                let results = batch
+                                    .row_slice(i)
+                                    .into_iter()
+                                    .map(|v| format_field(&v))
+                                    .collect::<Vec<String>>()
+                                    .join(",");
+                                result.push_str(&results);
+                                result.push_str("\n")
                             }
-                            Err(e) => panic!(format!("Error processing row: {:?}", e)),
-                        });
+                        }
                         Ok(ExecutionResult::Str(result))
                     }
-                    ref _x => panic!("Unknown physical plan output type."),
+                    ref other => Err(ExecutionError::plan(format!(
+                        "Unknown physical plan output type '{}'",
+                        other
+                    ))),
                 }
             }
             &PhysicalPlan::Show {
@@ -1756,26 +2962,21 @@ impl ExecutionContext {
                 let mut execution_plan = self.create_execution_plan(plan)?;
 
                 // implement execution here for now but should be a common method for processing a plan
-                let it = execution_plan.scan().take(*count);
-                it.for_each(|t| {
-                    match t {
-                        Ok(ref batch) => {
-                            ////println!("Processing batch of {} rows", batch.row_count());
-                            for i in 0..*count {
-                                if i < batch.num_rows() {
-                                    let row = batch.row_slice(i);
-                                    let csv = row
-                                        .into_iter()
-                                        .map(|v| v.to_string())
-                                        .collect::<Vec<String>>()
-                                        .join(",");
-                                    println!("{}", csv);
-                                }
-                            }
+                for t in execution_plan.scan().take(*count) {
+                    let batch = t?;
+                    ////println!("Processing batch of {} rows", batch.row_count());
+                    for i in 0..*count {
+                        if i < batch.num_rows() {
+                            let row = batch.row_slice(i);
+                            let csv = row
+                                .into_iter()
+                                .map(|v| v.to_string())
+                                .collect::<Vec<String>>()
+                                .join(",");
+                            println!("{}", csv);
                         }
-                        Err(e) => panic!(format!("Error processing row: {:?}", e)), //TODO: error handling
                     }
-                });
+                }
 
                 Ok(ExecutionResult::Count(*count))
             }
@@ -1882,7 +3083,10 @@ mod tests {
     fn test_dataframe_col() {
         let mut ctx = create_context();
         let df = ctx.sql(&"SELECT city, lat, lng FROM uk_cities").unwrap();
-        assert_eq!(Expr::Column(2), df.col("lng").unwrap());
+        assert_eq!(
+            Expr::ColumnRef(Column::from_name("lng")),
+            df.col("lng").unwrap()
+        );
     }
 
     #[test]
@@ -1898,7 +3102,7 @@ mod tests {
         let df = ctx.sql(&"SELECT city, lat, lng FROM uk_cities").unwrap();
         let plan = df.plan();
         assert_eq!(
-            "Projection: #0, #1, #2\
+            "Projection: #city, #lat, #lng\
              \n  TableScan: uk_cities projection=None",
             format!("{:?}", plan)
         );
@@ -1928,7 +3132,7 @@ mod tests {
         let plan = ctx
             .create_logical_plan(&"SELECT id, sqrt(id) FROM people")
             .unwrap();
-        let expected_plan = "Projection: #0, sqrt(CAST(#0 AS Float64))\
+        let expected_plan = "Projection: #id, sqrt(CAST(#id AS Float64))\
                              \n  TableScan: people projection=None";
         assert_eq!(expected_plan, format!("{:?}", plan));
     }
@@ -2270,6 +3474,959 @@ fn test_sort() {
         assert_eq!("2\n", &s);
     }
 
+    #[test]
+    fn test_dataframe_join() {
+        let mut ctx = create_context();
+        let left = ctx.sql("SELECT id, name FROM people WHERE id < 4").unwrap();
+        let right = ctx.sql("SELECT id FROM people").unwrap();
+        let df = left
+            .join(right, JoinType::Inner, &["id"], &["id"])
+            .unwrap();
+        let s = ctx.write_string(df).unwrap();
+        assert_eq!("1,Andy,1\n2,Brian,2\n3,Chris,3\n", &s);
+    }
+
+    #[test]
+    fn test_dataframe_union() {
+        let mut ctx = create_context();
+        let df = ctx.sql("SELECT id FROM people WHERE id < 3").unwrap();
+        let df2 = df.union(df.clone()).unwrap();
+        let s = ctx.write_string(df2).unwrap();
+        assert_eq!("1\n2\n1\n2\n", &s);
+    }
+
+    #[test]
+    fn test_analyze_table() {
+        let mut ctx = create_context();
+        assert_eq!(None, ctx.table_statistics("people"));
+        ctx.sql("ANALYZE TABLE people").unwrap();
+
+        let stats = ctx.table_statistics("people").unwrap();
+        assert_eq!(Some(10), stats.row_count);
+        assert!(stats.byte_size.unwrap() > 0);
+
+        let id = stats.column(0).unwrap();
+        assert_eq!(Some(ScalarValue::Int32(1)), id.min_value);
+        assert_eq!(Some(ScalarValue::Int32(10)), id.max_value);
+        assert_eq!(Some(0), id.null_count);
+        assert_eq!(Some(10), id.distinct_count);
+    }
+
+    #[test]
+    fn test_sql_join() {
+        let mut ctx = create_context();
+        let people = ctx.tables.borrow().get("people").unwrap().clone();
+        ctx.register("people2", people);
+        let df = ctx
+            .sql(
+                "SELECT people.name, people2.id FROM people \
+                 JOIN people2 ON people.id = people2.id WHERE people.id < 3",
+            )
+            .unwrap();
+        let s = ctx.write_string(df).unwrap();
+        assert_eq!("Andy,1\nBrian,2\n", &s);
+    }
+
+    #[test]
+    fn test_utf8_expressions() {
+        let mut ctx = create_context();
+        let df = ctx
+            .sql("SELECT id, name || '!' FROM people WHERE name < 'C'")
+            .unwrap();
+        assert_eq!("1,Andy!\n2,Brian!\n", ctx.write_string(df).unwrap());
+
+        let df = ctx
+            .sql("SELECT id FROM people WHERE name = 'Chris' AND 'a' || 'b' = 'ab'")
+            .unwrap();
+        assert_eq!("3\n", ctx.write_string(df).unwrap());
+
+        let df = ctx
+            .sql("SELECT id FROM people WHERE name <= name AND name != 'Andy' AND id < 4")
+            .unwrap();
+        assert_eq!("2\n3\n", ctx.write_string(df).unwrap());
+
+        // join on a Utf8 key
+        let people = ctx.tables.borrow().get("people").unwrap().clone();
+        ctx.register("people2", people);
+        let df = ctx
+            .sql(
+                "SELECT people.id, people2.name FROM people \
+                 JOIN people2 ON people.name = people2.name WHERE people.id < 3",
+            )
+            .unwrap();
+        assert_eq!("1,Andy\n2,Brian\n", ctx.write_string(df).unwrap());
+    }
+
+    #[test]
+    fn test_comparison_operators() {
+        let mut ctx = create_context();
+        let mut ids = |predicate: &str| {
+            let df = ctx
+                .sql(&format!("SELECT id FROM people WHERE {}", predicate))
+                .unwrap();
+            ctx.write_string(df).unwrap()
+        };
+        assert_eq!("9\n10\n", ids("id > 8"));
+        assert_eq!("8\n9\n10\n", ids("id >= 8"));
+        assert_eq!("4\n", ids("id = 4"));
+        assert_eq!("4\n", ids("id = 4.0"));
+
+        // a literal on the left is compared the same way as on the right
+        assert_eq!("9\n10\n", ids("8 < id"));
+        assert_eq!("8\n9\n10\n", ids("8 <= id"));
+        assert_eq!("1\n2\n", ids("3 > id"));
+        assert_eq!("1\n2\n3\n", ids("3 >= id"));
+        assert_eq!("4\n", ids("4 = id"));
+        assert_eq!("1\n2\n", ids("'C' > name"));
+
+        // literals on both sides are compared after type coercion
+        assert_eq!("1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n", ids("1 < 2"));
+        assert_eq!("", ids("2.5 <= 2"));
+        assert_eq!("", ids("3 != 3.0"));
+    }
+
+    #[test]
+    fn test_sql_join_reorder() {
+        let mut ctx = create_context();
+        let people = ctx.tables.borrow().get("people").unwrap().clone();
+        ctx.register("people2", people.clone());
+        ctx.register("people3", people);
+        for (name, rows) in vec![("people", 1000), ("people2", 10), ("people3", 100)] {
+            ctx.set_table_statistics(
+                name,
+                Statistics {
+                    row_count: Some(rows),
+                    ..Statistics::default()
+                },
+            );
+        }
+
+        let sql = "SELECT people.id, people3.name FROM people \
+                   JOIN people2 ON people.id = people2.id \
+                   JOIN people3 ON people2.id = people3.id \
+                   WHERE people.id < 3";
+
+        // the smallest tables are joined first
+        let df = ctx.sql(sql).unwrap();
+        assert_eq!(
+            "Projection: #people.id, #people3.name\
+             \n  Selection: CAST(#people.id AS Int64) Lt Int64(3)\
+             \n    Projection: #4, #5, #0, #1, #2, #3\
+             \n      Join: type=Inner, on=[#people2.id = #people.id]\
+             \n        Join: type=Inner, on=[#people2.id = #people3.id]\
+             \n          TableScan: people2 projection=None\
+             \n          TableScan: people3 projection=None\
+             \n        TableScan: people projection=None",
+            format!("{:?}", df.plan())
+        );
+
+        let s = ctx.write_string(df).unwrap();
+        assert_eq!("1,Andy\n2,Brian\n", &s);
+    }
+
+    #[test]
+    fn test_common_subexpr() {
+        let mut ctx = create_context();
+        let df = ctx
+            .sql("SELECT id * 2, id * 2 + 1 FROM people WHERE id * 2 > 16")
+            .unwrap();
+        assert_eq!(
+            "Projection: #2, #2 Plus Int64(1)\
+             \n  Selection: #2 Gt Int64(16)\
+             \n    Projection: #0, #1, CAST(#id AS Int64) Multiply Int64(2)\
+             \n      TableScan: people projection=None",
+            format!("{:?}", df.plan())
+        );
+        let s = ctx.write_string(df).unwrap();
+        assert_eq!("18,19\n20,21\n", &s);
+    }
+
+    #[test]
+    fn test_common_subexpr_after_selection() {
+        let mut ctx = ExecutionContext::local();
+        let schema = Schema::new(vec![Field::new("c_int", DataType::UInt32, false)]);
+        let df = ctx
+            .load_csv("./test/data/null_test.csv", &schema, true, None)
+            .unwrap();
+        ctx.register("null_test", df);
+
+        // the product overflows for the rows that the selection removes, so it must only be
+        // computed for the rows that are selected
+        let df = ctx
+            .sql(
+                "SELECT c_int * 3074457345618258603, c_int * 3074457345618258603 + 1 \
+                 FROM null_test WHERE c_int < 3",
+            ).unwrap();
+        assert_eq!(
+            "3074457345618258603,3074457345618258604\n\
+             6148914691236517206,6148914691236517207\n",
+            ctx.write_string(df).unwrap()
+        );
+    }
+
+    fn explain_plan_types(s: &str) -> Vec<String> {
+        let mut plan_types: Vec<String> = vec![];
+        for line in s.lines() {
+            let plan_type = line.split(',').next().unwrap().to_string();
+            if plan_types.last() != Some(&plan_type) {
+                plan_types.push(plan_type);
+            }
+        }
+        plan_types
+    }
+
+    #[test]
+    fn test_sql_explain() {
+        let mut ctx = create_context();
+        let df = ctx
+            .sql("EXPLAIN SELECT id FROM people WHERE id < 3")
+            .unwrap();
+        assert_eq!(
+            "plan_type: Utf8, plan: Utf8",
+            df.schema().to_string()
+        );
+
+        let s = ctx.write_string(df).unwrap();
+        assert_eq!(
+            vec!["logical_plan", "optimized_logical_plan", "physical_plan"],
+            explain_plan_types(&s)
+        );
+        assert!(s.starts_with(
+            "logical_plan,Projection: #id\
+             \nlogical_plan,  Selection: CAST(#id AS Int64) Lt Int64(3)\
+             \nlogical_plan,    TableScan: people projection=None\n"
+        ));
+        assert!(s.contains("\nphysical_plan,  FilterRelation\n"));
+        assert!(s.contains("\nphysical_plan,    CsvFile: file=./test/data/people.csv"));
+    }
+
+    #[test]
+    fn test_sql_explain_analyze() {
+        let mut ctx = create_context();
+        let df = ctx
+            .sql("EXPLAIN ANALYZE SELECT id FROM people WHERE id < 3")
+            .unwrap();
+        let s = ctx.write_string(df).unwrap();
+        assert_eq!(
+            vec![
+                "logical_plan",
+                "optimized_logical_plan",
+                "physical_plan_with_metrics",
+            ],
+            explain_plan_types(&s)
+        );
+        assert!(s.contains(
+            "\nphysical_plan_with_metrics,  FilterRelation, \
+             metrics=[output_rows=2, output_batches=1, input_rows=9, input_batches=1,"
+        ));
+    }
+
+    #[test]
+    fn test_sql_explain_dot() {
+        let mut ctx = create_context();
+        let df = ctx
+            .sql("EXPLAIN (FORMAT DOT) SELECT id FROM people WHERE id < 3")
+            .unwrap();
+        let s = ctx.write_string(df).unwrap();
+        assert_eq!(
+            vec!["logical_plan", "optimized_logical_plan", "physical_plan"],
+            explain_plan_types(&s)
+        );
+        assert!(s.starts_with(
+            "logical_plan,digraph LogicalPlan {\
+             \nlogical_plan,  node [shape=box];\
+             \nlogical_plan,  node0 [label=\"Projection: #id\\nSchema: [id: Int32]\"];\n"
+        ));
+        assert!(s.contains("\nphysical_plan,digraph PhysicalPlan {\n"));
+        assert!(s.contains(
+            "\nphysical_plan,  node1 [label=\"FilterRelation\\nSchema: [id: Int32, name: Utf8]\"];"
+        ));
+        assert!(s.ends_with("\nphysical_plan,}\n"));
+
+        let df = ctx
+            .sql("EXPLAIN (FORMAT DOT) ANALYZE SELECT id FROM people WHERE id < 3")
+            .unwrap();
+        let s = ctx.write_string(df).unwrap();
+        assert!(s.contains("\\nMetrics: [output_rows=2, output_batches=1, input_rows=9,"));
+    }
+
+    #[test]
+    fn test_collect_metrics() {
+        let mut ctx = create_context();
+        assert!(ctx.last_metrics().is_none());
+
+        let df = ctx.sql("SELECT id FROM people WHERE id < 3").unwrap();
+        let batches = df.collect().unwrap();
+        assert_eq!(1, batches.len());
+        assert_eq!(2, batches[0].num_rows());
+
+        let projection = ctx.last_metrics().unwrap();
+        assert!(projection.relation.starts_with("ProjectRelation"));
+        assert_eq!(2, projection.metrics.input_rows);
+        assert_eq!(2, projection.metrics.output_rows);
+        assert_eq!(1, projection.metrics.output_batches);
+
+        let filter = &projection.inputs[0];
+        assert_eq!("FilterRelation", filter.relation);
+        assert_eq!(9, filter.metrics.input_rows);
+        assert_eq!(2, filter.metrics.output_rows);
+        assert_eq!(0, filter.metrics.bytes_read);
+
+        let scan = &filter.inputs[0];
+        assert!(scan.relation.starts_with("CsvFile"));
+        assert_eq!(9, scan.metrics.output_rows);
+        assert_eq!(0, scan.metrics.input_rows);
+        assert!(scan.metrics.bytes_read > 0);
+        assert_eq!(0, scan.metrics.spill_count);
+        assert!(scan.inputs.is_empty());
+    }
+
+    #[test]
+    fn test_sql_explain_verbose() {
+        let mut ctx = create_context();
+        let df = ctx
+            .sql("EXPLAIN VERBOSE SELECT id * 2, id * 2 + 1 FROM people WHERE id * 2 > 16")
+            .unwrap();
+        let s = ctx.write_string(df).unwrap();
+        assert_eq!(
+            vec![
+                "logical_plan",
+                "logical_plan after common_subexpr_eliminate",
+                "optimized_logical_plan",
+                "physical_plan",
+            ],
+            explain_plan_types(&s)
+        );
+    }
+
+    #[test]
+    fn test_sql_unknown_column() {
+        let mut ctx = create_context();
+        match ctx.sql("SELECT nmae FROM people") {
+            Err(ExecutionError::ColumnNotFound { name, suggestions }) => {
+                assert_eq!("nmae", name);
+                assert_eq!(1, suggestions.len());
+                assert!(suggestions[0].ends_with("name"));
+            }
+            other => panic!("expected ColumnNotFound, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn test_sql_unknown_table() {
+        let mut ctx = create_context();
+        match ctx.sql("SELECT id FROM peeple") {
+            Err(ExecutionError::TableNotFound { name, suggestions }) => {
+                assert_eq!("peeple", name);
+                assert_eq!(vec!["people".to_string()], suggestions);
+            }
+            other => panic!("expected TableNotFound, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn test_execute_script() {
+        let mut ctx = create_context();
+        let script = "CREATE EXTERNAL TABLE cities (city VARCHAR, lat DOUBLE, lng DOUBLE) \
+                      STORED AS CSV WITHOUT HEADER ROW LOCATION './test/data/uk_cities.csv';\n\
+                      SELECT city FROM cities WHERE lat > 57.0;\n\
+                      SELECT town FROM cities;\n\
+                      SELECT lat FROM cities WHERE lat > 57.0;";
+
+        let results = ctx.execute_script(script, false).unwrap();
+        assert_eq!(4, results.len());
+        assert!(results[0].result.is_ok());
+        let batches = results[1].result.as_ref().unwrap();
+        assert_eq!(3, batches.iter().map(|b| b.num_rows()).sum::<usize>());
+        match results[2].result {
+            Err(ExecutionError::ColumnNotFound { ref name, .. }) => assert_eq!("town", name),
+            _ => panic!("expected the third statement to fail"),
+        }
+        assert!(results[3].result.is_ok());
+
+        let results = ctx.execute_script(script, true).unwrap();
+        assert_eq!(3, results.len());
+
+        // a syntax error fails its own statement, after the earlier statements have run
+        let results = ctx
+            .execute_script("ANALYZE cities; SELECT FROM cities; SELECT city FROM cities", true)
+            .unwrap();
+        assert_eq!(2, results.len());
+        assert!(results[0].result.is_ok());
+        assert!(ctx.table_statistics("cities").is_some());
+        assert!(results[1].statement.is_none());
+        match results[1].result {
+            Err(ExecutionError::ParserError(_)) => {}
+            _ => panic!("expected a syntax error"),
+        }
+
+        // and the statements after it run unless the script stops on errors
+        let results = ctx
+            .execute_script("SELECT FROM cities; SELECT city FROM cities", false)
+            .unwrap();
+        assert_eq!(2, results.len());
+        assert!(results[0].result.is_err());
+        assert!(results[1].result.is_ok());
+    }
+
+    #[test]
+    fn test_prepared_statement() {
+        let mut ctx = create_context();
+        ctx.sql(
+            "CREATE EXTERNAL TABLE cities (city VARCHAR, lat DOUBLE, lng DOUBLE) \
+             STORED AS CSV WITHOUT HEADER ROW LOCATION './test/data/uk_cities.csv'",
+        ).unwrap();
+
+        let stmt = ctx
+            .prepare("SELECT city FROM cities WHERE lat > $1 AND lng < :max_lng")
+            .unwrap();
+        let parameters: Vec<&str> = stmt.parameters().iter().map(|f| f.name().as_str()).collect();
+        assert_eq!(vec!["$1", ":max_lng"], parameters);
+
+        let row_count = |params: &[ScalarValue]| -> usize {
+            let df = stmt.execute(params).unwrap();
+            let batches = ctx.collect(df.as_ref()).unwrap();
+            batches.iter().map(|b| b.num_rows()).sum()
+        };
+        assert_eq!(
+            2,
+            row_count(&[ScalarValue::Float64(57.0), ScalarValue::Float64(-3.0)])
+        );
+        assert_eq!(
+            5,
+            row_count(&[ScalarValue::Float64(53.0), ScalarValue::Float64(-3.0)])
+        );
+        // integer values are cast to the type of the parameter
+        assert_eq!(5, row_count(&[ScalarValue::Int64(53), ScalarValue::Int32(-3)]));
+        // comparisons with a null parameter are null, so no rows match
+        assert_eq!(0, row_count(&[ScalarValue::Null, ScalarValue::Float64(-3.0)]));
+
+        match stmt.execute(&[ScalarValue::Float64(57.0)]) {
+            Err(ExecutionError::PlanError { message, .. }) => assert_eq!(
+                "Expected 2 parameter values but 1 were provided",
+                message
+            ),
+            _ => panic!("expected an error for a missing parameter value"),
+        }
+    }
+
+    #[test]
+    fn test_invalid_csv_value_returns_error() {
+        let mut ctx = create_context();
+        let invalid = ctx
+            .load_csv(
+                "./test/data/people.csv",
+                &Schema::new(vec![
+                    Field::new("id", DataType::Int32, false),
+                    Field::new("name", DataType::Int32, false),
+                ]),
+                true,
+                None,
+            ).unwrap();
+        ctx.register("invalid_people", invalid);
+
+        let df = ctx.sql("SELECT id, name FROM invalid_people").unwrap();
+        match ctx.write_string(df) {
+            Err(ExecutionError::DataError {
+                file, row, column, ..
+            }) => {
+                assert_eq!(Some("./test/data/people.csv".to_string()), file);
+                assert_eq!(Some(2), row);
+                assert_eq!(Some("name".to_string()), column);
+            }
+            other => panic!("expected a data error, got {:?}", other),
+        }
+
+        // the context can still run other queries
+        let df = ctx.sql("SELECT id FROM people WHERE id < 3").unwrap();
+        assert_eq!("1\n2\n", ctx.write_string(df).unwrap());
+    }
+
+    #[test]
+    fn test_temporal_csv() {
+        let mut ctx = ExecutionContext::local();
+        let events = ctx
+            .load_csv(
+                "./test/data/events.csv",
+                &Schema::new(vec![
+                    Field::new("id", DataType::Int32, false),
+                    Field::new("day", DataType::Date32, false),
+                    Field::new("ts", DataType::Timestamp(TimeUnit::Microsecond), false),
+                ]),
+                true,
+                None,
+            ).unwrap();
+        ctx.register("events", events);
+
+        // adding a month clamps the day to the end of the month
+        let df = ctx
+            .sql(
+                "SELECT id, day + INTERVAL '1' MONTH, ts FROM events \
+                 WHERE day < DATE '2018-02-28'",
+            )
+            .unwrap();
+        assert_eq!(
+            "1,2018-02-01,2018-01-01 08:30:00\n2,2018-02-28,2018-01-15 12:00:00\n",
+            ctx.write_string(df).unwrap()
+        );
+
+        // strings are coerced to the type of the column they are compared with
+        let df = ctx
+            .sql(
+                "SELECT id, ts - INTERVAL '12' HOUR FROM events \
+                 WHERE ts <= '2018-01-15 12:00:00'",
+            )
+            .unwrap();
+        assert_eq!(
+            "1,2017-12-31 20:30:00\n2,2018-01-15 00:00:00\n",
+            ctx.write_string(df).unwrap()
+        );
+
+        let df = ctx
+            .sql("SELECT MIN(day), MAX(ts) FROM events")
+            .unwrap();
+        assert_eq!(
+            "2018-01-01,2018-03-01 00:00:00\n",
+            ctx.write_string(df).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_datetime_functions() {
+        let mut ctx = ExecutionContext::local();
+        let events = ctx
+            .load_csv(
+                "./test/data/events.csv",
+                &Schema::new(vec![
+                    Field::new("id", DataType::Int32, false),
+                    Field::new("day", DataType::Date32, false),
+                    Field::new("ts", DataType::Timestamp(TimeUnit::Microsecond), false),
+                ]),
+                true,
+                None,
+            ).unwrap();
+        ctx.register("events", events);
+
+        let df = ctx
+            .sql(
+                "SELECT id, date_trunc('month', ts), EXTRACT(DAY FROM day), \
+                 date_diff('day', day, ts) FROM events WHERE id < 3",
+            )
+            .unwrap();
+        assert_eq!(
+            "1,2018-01-01 00:00:00,1,0\n2,2018-01-01 00:00:00,31,-16\n",
+            ctx.write_string(df).unwrap()
+        );
+
+        let df = ctx
+            .sql(
+                "SELECT date_bin(INTERVAL '1' HOUR, ts, TIMESTAMP '2018-01-01 00:15:00'), \
+                 date_add('week', 1, day) FROM events WHERE id < 3",
+            )
+            .unwrap();
+        assert_eq!(
+            "2018-01-01 08:15:00,2018-01-08 00:00:00\n2018-01-15 11:15:00,2018-02-07 00:00:00\n",
+            ctx.write_string(df).unwrap()
+        );
+
+        // every row sees the same date
+        let df = ctx
+            .sql("SELECT id, CURRENT_DATE FROM events WHERE id < 3")
+            .unwrap();
+        let today = temporal::format_date(temporal::current_date());
+        assert_eq!(
+            format!("1,{}\n2,{}\n", today, today),
+            ctx.write_string(df).unwrap()
+        );
+
+        // every row sees the time that the query started
+        let df = ctx.sql("SELECT now() FROM events").unwrap();
+        let rows = ctx.write_string(df).unwrap();
+        let first = rows.lines().next().unwrap().to_string();
+        assert!(rows.lines().all(|row| row == first));
+    }
+
+    #[test]
+    fn test_decimal_arithmetic() {
+        let mut ctx = ExecutionContext::local();
+        let prices = ctx
+            .load_csv(
+                "./test/data/prices.csv",
+                &Schema::new(vec![
+                    Field::new("id", DataType::Int32, false),
+                    Field::new("price", DataType::Decimal(10, 2), false),
+                    Field::new("qty", DataType::Int32, false),
+                ]),
+                true,
+                None,
+            ).unwrap();
+        ctx.register("prices", prices);
+
+        let df = ctx
+            .sql("SELECT id, price * qty, price / qty, CAST(price AS INT) FROM prices WHERE id < 3")
+            .unwrap();
+        assert_eq!(
+            "1,59.97,6.6633333333333,19\n2,0.70,0.0142857142857,0\n",
+            ctx.write_string(df).unwrap()
+        );
+
+        let df = ctx
+            .sql("SELECT id, price + CAST('0.05' AS DECIMAL(3, 2)) FROM prices WHERE price < 1")
+            .unwrap();
+        assert_eq!("2,0.15\n4,0.25\n", ctx.write_string(df).unwrap());
+
+        // the sum is exact, where adding the values as f64 is not
+        let df = ctx
+            .sql("SELECT SUM(price), MIN(price), MAX(price) FROM prices")
+            .unwrap();
+        assert_eq!("1254.79,0.10,1234.50\n", ctx.write_string(df).unwrap());
+
+        // overflow is a data error for the row, as it is for integers
+        let df = ctx
+            .sql(
+                "SELECT price * CAST('99999999999999999999999999999999999999' AS DECIMAL(38, 0)) \
+                 FROM prices",
+            ).unwrap();
+        match ctx.write_string(df) {
+            Err(ExecutionError::DataError { row, .. }) => assert_eq!(Some(0), row),
+            other => panic!("expected a data error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_null_propagation() {
+        let mut ctx = ExecutionContext::local();
+        let schema = Schema::new(vec![
+            Field::new("c_int", DataType::UInt32, false),
+            Field::new("c_float", DataType::Float64, true),
+            Field::new("c_string", DataType::Utf8, true),
+        ]);
+        let df = ctx
+            .load_csv("./test/data/null_test.csv", &schema, true, None)
+            .unwrap();
+        ctx.register("null_test", df);
+
+        // null values are written as empty fields
+        let df = ctx
+            .sql("SELECT c_int, c_float * 2 FROM null_test WHERE c_float IS NULL OR c_float < 3")
+            .unwrap();
+        assert_eq!("1,2.2\n2,4.4\n3,\n", ctx.write_string(df).unwrap());
+
+        // rows where the predicate is null are not selected
+        let df = ctx
+            .sql("SELECT c_int FROM null_test WHERE c_float < 3 AND c_int < 4")
+            .unwrap();
+        assert_eq!("1\n2\n", ctx.write_string(df).unwrap());
+        let df = ctx
+            .sql("SELECT c_int FROM null_test WHERE NOT c_float < 3")
+            .unwrap();
+        assert_eq!("4\n5\n", ctx.write_string(df).unwrap());
+
+        // scalar functions are null wherever an argument is null
+        ctx.register_scalar_function(Rc::new(SqrtFunction {}));
+        let df = ctx
+            .sql("SELECT c_int, sqrt(c_float) FROM null_test WHERE c_int > 2 AND c_int < 5")
+            .unwrap();
+        assert_eq!("3,\n4,2.0976176963403033\n", ctx.write_string(df).unwrap());
+
+        // null slots hold a placeholder value that must never reach the function
+        ctx.register_scalar_function(Rc::new(ReciprocalFunction {}));
+        let df = ctx
+            .sql("SELECT c_int, reciprocal(c_float) FROM null_test WHERE c_int > 2 AND c_int < 5")
+            .unwrap();
+        assert_eq!("3,\n4,0.22727272727272727\n", ctx.write_string(df).unwrap());
+        let df = ctx
+            .sql("SELECT c_int, reciprocal(c_float) FROM null_test WHERE c_int = 3")
+            .unwrap();
+        assert_eq!("3,\n", ctx.write_string(df).unwrap());
+
+        // aggregates ignore null values
+        let df = ctx
+            .sql("SELECT MIN(c_float), MAX(c_float) FROM null_test")
+            .unwrap();
+        assert_eq!("1.1,6.6\n", ctx.write_string(df).unwrap());
+    }
+
+    /// Fails on zero, which is what the CSV reader stores in null Float64 slots
+    struct ReciprocalFunction {}
+
+    impl ScalarFunction for ReciprocalFunction {
+        fn name(&self) -> String {
+            "reciprocal".to_string()
+        }
+
+        fn execute(&self, args: &[Value]) -> Result<Value> {
+            match args[0] {
+                Value::Column(ref arr) => match arr.data() {
+                    ArrayData::Float64(ref v) => {
+                        let values = v
+                            .iter()
+                            .map(|v| {
+                                if v.abs() < ::std::f64::EPSILON {
+                                    Err(ExecutionError::General("Division by zero".to_string()))
+                                } else {
+                                    Ok(v.recip())
+                                }
+                            })
+                            .collect::<Result<Vec<f64>>>()?;
+                        Ok(Value::Column(Rc::new(Array::from(values))))
+                    }
+                    _ => Err(ExecutionError::General(
+                        "Unsupported arg type for reciprocal".to_string(),
+                    )),
+                },
+                _ => Err(ExecutionError::General(
+                    "Unsupported arg type for reciprocal".to_string(),
+                )),
+            }
+        }
+
+        fn args(&self) -> Vec<Field> {
+            vec![Field::new("n", DataType::Float64, true)]
+        }
+
+        fn return_type(&self) -> DataType {
+            DataType::Float64
+        }
+    }
+
+    #[test]
+    fn test_cast_matrix() {
+        let mut ctx = ExecutionContext::local();
+        let schema = Schema::new(vec![
+            Field::new("c_int", DataType::UInt32, false),
+            Field::new("c_float", DataType::Float64, true),
+            Field::new("c_string", DataType::Utf8, true),
+            Field::new("c_bool", DataType::Boolean, false),
+        ]);
+        let df = ctx
+            .load_csv("./test/data/null_test.csv", &schema, true, None)
+            .unwrap();
+        ctx.register("null_test", df);
+
+        // booleans and numbers can be cast to each other
+        let df = ctx
+            .sql("SELECT CAST(c_bool AS INT), CAST(c_int - 1 AS BOOLEAN) FROM null_test")
+            .unwrap();
+        assert_eq!(
+            "1,false\n1,true\n1,true\n0,true\n0,true\n",
+            ctx.write_string(df).unwrap()
+        );
+
+        // TRY_CAST returns null for values that cannot be converted
+        let df = ctx
+            .sql("SELECT c_int, TRY_CAST(c_string AS DECIMAL(5, 1)), TRY_CAST(c_string AS INT) \
+                  FROM null_test")
+            .unwrap();
+        assert_eq!(
+            "1,1.1,\n2,2.2,\n3,3.3,\n4,,\n5,,\n",
+            ctx.write_string(df).unwrap()
+        );
+        let df = ctx
+            .sql("SELECT TRY_CAST(300 AS INT8), TRY_CAST('2018-02-30' AS DATE), \
+                  CAST('2018-02-28' AS DATE)")
+            .unwrap();
+        assert_eq!(",,2018-02-28\n", ctx.write_string(df).unwrap());
+
+        // CAST fails the query instead
+        let df = ctx
+            .sql("SELECT CAST(c_string AS INT) FROM null_test")
+            .unwrap();
+        assert!(ctx.write_string(df).is_err());
+        let df = ctx.sql("SELECT CAST(c_float * 100 AS UINT8) FROM null_test");
+        assert!(df.and_then(|df| ctx.write_string(df)).is_err());
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        let mut ctx = ExecutionContext::local();
+        let schema = Schema::new(vec![
+            Field::new("c_int", DataType::UInt32, false),
+            Field::new("c_float", DataType::Float64, true),
+        ]);
+        let df = ctx
+            .load_csv("./test/data/null_test.csv", &schema, true, None)
+            .unwrap();
+        ctx.register("null_test", df);
+
+        // integer overflow and division by zero are errors by default
+        let division = "SELECT c_int / (c_int - 3) FROM null_test";
+        let overflow = "SELECT c_int * 9223372036854775807 FROM null_test";
+        for sql in vec![division, overflow] {
+            let df = ctx.sql(sql);
+            assert!(df.and_then(|df| ctx.write_string(df)).is_err());
+        }
+
+        // in wrapping mode division by zero is null
+        ctx.set_overflow_mode(OverflowMode::Wrapping);
+        let df = ctx.sql(division).unwrap();
+        assert_eq!("0\n-2\n\n4\n2\n", ctx.write_string(df).unwrap());
+        let df = ctx.sql(overflow).unwrap();
+        assert!(ctx.write_string(df).is_ok());
+    }
+
+    #[test]
+    fn test_arithmetic_with_literal_on_left() {
+        let mut ctx = ExecutionContext::local();
+        let schema = Schema::new(vec![
+            Field::new("c_int", DataType::UInt32, false),
+            Field::new("c_float", DataType::Float64, true),
+        ]);
+        let df = ctx
+            .load_csv("./test/data/null_test.csv", &schema, true, None)
+            .unwrap();
+        ctx.register("null_test", df);
+
+        let df = ctx.sql("SELECT 10 - c_int, 100 / c_int FROM null_test").unwrap();
+        assert_eq!(
+            "9,100\n8,50\n7,33\n6,25\n5,20\n",
+            ctx.write_string(df).unwrap()
+        );
+
+        // the zero divisor is on the right, in the third row
+        let df = ctx.sql("SELECT 1 / (c_int - 3) FROM null_test").unwrap();
+        match ctx.write_string(df) {
+            Err(ExecutionError::DataError { message, row, .. }) => {
+                assert_eq!("Division by zero", message);
+                assert_eq!(Some(2), row);
+            }
+            other => panic!("expected a division by zero error, got {:?}", other),
+        }
+
+        ctx.set_overflow_mode(OverflowMode::Wrapping);
+        let df = ctx.sql("SELECT 1 / (c_int - 3) FROM null_test").unwrap();
+        assert_eq!("0\n-1\n\n1\n0\n", ctx.write_string(df).unwrap());
+    }
+
+    #[test]
+    fn test_list_functions() {
+        let mut ctx = ExecutionContext::local();
+        ctx.sql(
+            "CREATE EXTERNAL TABLE posts (id INT64, tags VARCHAR[], scores INT64[]) \
+             STORED AS NDJSON LOCATION 'test/data/tags.ndjson'",
+        ).unwrap();
+
+        // each element becomes a row, and null or empty lists produce no rows
+        let df = ctx.sql("SELECT id, UNNEST(tags) FROM posts").unwrap();
+        assert_eq!("1,rust\n1,arrow\n4,sql\n", ctx.write_string(df).unwrap());
+
+        let df = ctx
+            .sql("SELECT id, tags[1], array_length(scores), array_contains(scores, 5) FROM posts")
+            .unwrap();
+        assert_eq!(
+            "1,rust,3,false\n2,,1,false\n3,,2,true\n4,sql,0,false\n",
+            ctx.write_string(df).unwrap()
+        );
+
+        // lists are written as quoted strings
+        let df = ctx
+            .sql("SELECT id, scores FROM posts WHERE id < 2")
+            .unwrap();
+        assert_eq!("1,\"[1, 2, 3]\"\n", ctx.write_string(df).unwrap());
+
+        let df = ctx
+            .sql("SELECT ARRAY_AGG(unnest) FROM posts, UNNEST(scores)")
+            .unwrap();
+        assert_eq!(
+            "\"[1, 2, 3, 4, NULL, 5]\"\n",
+            ctx.write_string(df).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_struct_fields() {
+        let mut ctx = create_context();
+        ctx.register_scalar_function(Rc::new(STPointFunc {}));
+
+        let df = ctx
+            .sql("SELECT ST_Point(lat, lng)['x'], ST_Point(lat, lng)['y'] FROM uk_cities \
+                  WHERE lat > 57.0")
+            .unwrap();
+        assert_eq!(
+            "57.653484,-3.335724\n57.149651,-2.099075\n57.477772,-4.224721\n",
+            ctx.write_string(df).unwrap()
+        );
+
+        let df = ctx
+            .sql("SELECT STRUCT(lat, lng AS longitude)['longitude'] FROM uk_cities \
+                  WHERE lat > 57.6")
+            .unwrap();
+        assert_eq!("-3.335724\n", ctx.write_string(df).unwrap());
+
+        // struct columns of a registered table can be filtered and their fields accessed by name
+        let points = ctx
+            .sql("SELECT city, ST_Point(lat, lng) FROM uk_cities")
+            .unwrap();
+        ctx.register("points", points);
+        let df = ctx
+            .sql("SELECT points.ST_Point.y FROM points WHERE st_point.x > 57.6")
+            .unwrap();
+        assert_eq!("-3.335724\n", ctx.write_string(df).unwrap());
+
+        assert!(ctx.sql("SELECT ST_Point(lat, lng)['z'] FROM uk_cities").is_err());
+
+        // struct values are quoted in the same way as lists
+        let df = ctx
+            .sql("SELECT ST_Point(lat, lng) FROM uk_cities WHERE lat > 57.6")
+            .unwrap();
+        assert_eq!("\"{57.653484, -3.335724}\"\n", ctx.write_string(df).unwrap());
+    }
+
+    #[test]
+    fn test_parquet_struct_predicates() {
+        let mut ctx = ExecutionContext::local();
+        let df = ctx.load_parquet("test/data/places.parquet", None).unwrap();
+        ctx.register("places", df);
+
+        // the row groups that cannot match are skipped, and the rows of the others are filtered
+        let mut ids = |predicate: &str| {
+            let df = ctx
+                .sql(&format!("SELECT id FROM places WHERE {}", predicate))
+                .unwrap();
+            ctx.write_string(df).unwrap()
+        };
+        assert_eq!("3\n4\n", ids("location.lat > 55"));
+        assert_eq!("4\n", ids("location.lat > 57"));
+        assert_eq!("1\n2\n", ids("56 > places.location.lat AND id < 3"));
+        assert_eq!("2\n", ids("location['lng'] = -2.2"));
+        assert_eq!("", ids("location.lng > 0"));
+        assert_eq!("1\n2\n3\n4\n", ids("location.lat > 0 OR id = 5"));
+    }
+
+    #[test]
+    fn test_binary_values() {
+        let mut ctx = create_context();
+
+        // repeating the id gives two hex digits e.g. 0x11 for id 1
+        let hashes = ctx
+            .sql("SELECT id, decode(CAST(id AS VARCHAR) || CAST(id AS VARCHAR), 'hex') \
+                  FROM people WHERE id < 4")
+            .unwrap();
+        ctx.register("hashes", hashes);
+
+        let df = ctx
+            .sql("SELECT id, decode, length(decode), encode(decode, 'base64') FROM hashes \
+                  WHERE decode = X'22'")
+            .unwrap();
+        assert_eq!("2,22,1,Ig==\n", ctx.write_string(df).unwrap());
+
+        let df = ctx
+            .sql("SELECT id FROM hashes WHERE decode <> x'22'")
+            .unwrap();
+        assert_eq!("1\n3\n", ctx.write_string(df).unwrap());
+
+        let df = ctx
+            .sql("SELECT X'ABCD' = decode('q80=', 'base64'), X'abcd', length(X'') \
+                  FROM people WHERE id < 2")
+            .unwrap();
+        assert_eq!("true,abcd,0\n", ctx.write_string(df).unwrap());
+
+        let result = ctx
+            .sql("SELECT decode('abc', 'hex') FROM people")
+            .and_then(|df| ctx.write_string(df));
+        assert!(result.is_err());
+    }
+
     fn read_file(filename: &str) -> String {
         let mut file = File::open(filename).unwrap();
         let mut contents = String::new();
@@ -0,0 +1,190 @@
+// Copyright 2018 Grove Enterprises LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Functions on list values. The first argument of each function is the list, and a null list
+//! produces a null result.
+
+use std::rc::Rc;
+
+use super::super::cast;
+use super::super::datatypes::*;
+use super::super::errors::*;
+use super::super::types::*;
+use super::expect_args;
+
+/// Evaluate a function on the values of each row. The result is a column of the given type if
+/// any argument is a column, and a scalar otherwise.
+fn evaluate<F>(args: &[Value], result_type: &DataType, f: F) -> Result<Value>
+where
+    F: Fn(&[ScalarValue]) -> Result<ScalarValue>,
+{
+    let row = |i: usize| -> Vec<ScalarValue> { args.iter().map(|a| value_at(a, i)).collect() };
+    let len = args
+        .iter()
+        .filter_map(|a| match a {
+            Value::Column(ref array) => Some(array.len()),
+            Value::Scalar(_) => None,
+        })
+        .next();
+    match len {
+        Some(len) => {
+            let results = (0..len)
+                .map(|i| f(&row(i)))
+                .collect::<Result<Vec<ScalarValue>>>()?;
+            let array = build_nullable_array(result_type, &results, |values| {
+                array_from_scalars(result_type, values)
+            })?;
+            Ok(Value::Column(Rc::new(array)))
+        }
+        None => Ok(Value::Scalar(Rc::new(f(&row(0))?))),
+    }
+}
+
+/// Number of elements in each list, including null elements
+pub fn array_length(args: &[Value]) -> Result<Value> {
+    expect_args("array_length", args, 1)?;
+    evaluate(args, &DataType::UInt64, |row| {
+        Ok(match row[0] {
+            ScalarValue::Null => ScalarValue::Null,
+            ref list => ScalarValue::UInt64(list.get_list()?.len() as u64),
+        })
+    })
+}
+
+/// Whether each list contains a value. As with `IN`, the result is null rather than false if
+/// the value is null or is not found in a list that contains nulls. The value has the common
+/// supertype of the elements and the value, and elements of a narrower type are widened to it
+/// before they are compared.
+pub fn array_contains(
+    args: &[Value],
+    element_type: &DataType,
+    value_type: &DataType,
+) -> Result<Value> {
+    expect_args("array_contains", args, 2)?;
+    evaluate(args, &DataType::Boolean, |row| {
+        let list = match row[0] {
+            ScalarValue::Null => return Ok(ScalarValue::Null),
+            ref list => list.get_list()?,
+        };
+        if row[1] == ScalarValue::Null {
+            return Ok(ScalarValue::Null);
+        }
+        let mut has_null = false;
+        for element in list {
+            let element = match element {
+                ScalarValue::Null => {
+                    has_null = true;
+                    continue;
+                }
+                v if element_type == value_type => v.clone(),
+                v => cast::cast_scalar(v, value_type, false)?,
+            };
+            if element == row[1] {
+                return Ok(ScalarValue::Boolean(true));
+            }
+        }
+        Ok(if has_null {
+            ScalarValue::Null
+        } else {
+            ScalarValue::Boolean(false)
+        })
+    })
+}
+
+/// Element of each list at a one-based `Int64` index, or null if the index is out of range
+pub fn array_element(args: &[Value], element_type: &DataType) -> Result<Value> {
+    expect_args("array_element", args, 2)?;
+    evaluate(args, element_type, |row| {
+        let list = match (&row[0], &row[1]) {
+            (ScalarValue::Null, _) | (_, ScalarValue::Null) => return Ok(ScalarValue::Null),
+            (list, _) => list.get_list()?,
+        };
+        let index = row[1].get_i64()?;
+        Ok(if index >= 1 && index as usize <= list.len() {
+            list[index as usize - 1].clone()
+        } else {
+            ScalarValue::Null
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_fixtures::*;
+    use super::*;
+
+    fn lists() -> Value {
+        let values = Array::with_validity(
+            Array::from(vec![1_i32, 2, 0, 3]).into_data(),
+            &vec![true, true, false, true],
+        );
+        // [1, 2], [], NULL, [NULL, 3]
+        let lists = Array::with_validity(
+            ArrayData::List(vec![0, 2, 2, 2, 4], Rc::new(values)),
+            &vec![true, true, false, true],
+        );
+        Value::Column(Rc::new(lists))
+    }
+
+    #[test]
+    fn length_of_lists() {
+        let result = array_length(&vec![lists()]).unwrap();
+        assert_eq!(vec!["2", "0", "NULL", "2"], rows(result));
+    }
+
+    #[test]
+    fn contains_follows_three_valued_logic() {
+        let int32 = DataType::Int32;
+        let args = vec![lists(), scalar(ScalarValue::Int32(3))];
+        let result = array_contains(&args, &int32, &int32).unwrap();
+        assert_eq!(vec!["false", "false", "NULL", "true"], rows(result));
+
+        let args = vec![lists(), scalar(ScalarValue::Int32(5))];
+        let result = array_contains(&args, &int32, &int32).unwrap();
+        assert_eq!(vec!["false", "false", "NULL", "NULL"], rows(result));
+    }
+
+    #[test]
+    fn contains_widens_elements_to_value_type() {
+        let args = vec![lists(), scalar(ScalarValue::Int64(3))];
+        let result = array_contains(&args, &DataType::Int32, &DataType::Int64).unwrap();
+        assert_eq!(vec!["false", "false", "NULL", "true"], rows(result));
+
+        // a value that does not fit in the element type is not found rather than an error
+        let args = vec![lists(), scalar(ScalarValue::Int64(1 << 40))];
+        let result = array_contains(&args, &DataType::Int32, &DataType::Int64).unwrap();
+        assert_eq!(vec!["false", "false", "NULL", "NULL"], rows(result));
+    }
+
+    #[test]
+    fn element_at_one_based_index() {
+        let args = vec![lists(), scalar(ScalarValue::Int64(2))];
+        let result = array_element(&args, &DataType::Int32).unwrap();
+        assert_eq!(vec!["2", "NULL", "NULL", "3"], rows(result));
+
+        let args = vec![lists(), scalar(ScalarValue::Int64(0))];
+        let result = array_element(&args, &DataType::Int32).unwrap();
+        assert_eq!(vec!["NULL", "NULL", "NULL", "NULL"], rows(result));
+    }
+
+    #[test]
+    fn scalar_list_gives_scalar_result() {
+        let list = ScalarValue::List(
+            vec![ScalarValue::Utf8(Rc::new("x".to_string()))],
+            DataType::Utf8,
+        );
+        let result = array_length(&vec![scalar(list)]).unwrap();
+        assert_eq!(vec!["1"], rows(result));
+    }
+}
@@ -0,0 +1,129 @@
+// Copyright 2018 Grove Enterprises LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! ARRAY_AGG() aggregate function
+
+use std::rc::Rc;
+
+use super::super::datasources::common::get_value;
+use super::super::datatypes::*;
+use super::super::errors::*;
+use super::super::types::*;
+
+/// Collects the values of each group into a list, including nulls
+pub struct ArrayAggFunction {
+    data_type: DataType,
+    values: Vec<ScalarValue>,
+    /// Whether any rows have been seen, so that an empty group produces null
+    seen_rows: bool,
+}
+
+impl ArrayAggFunction {
+    /// Create the function for the given return type, which must be a list type
+    pub fn new(data_type: &DataType) -> Self {
+        ArrayAggFunction {
+            data_type: data_type.clone(),
+            values: vec![],
+            seen_rows: false,
+        }
+    }
+
+    fn element_type(&self) -> Result<DataType> {
+        match self.data_type {
+            DataType::List(ref t) => Ok(t.as_ref().clone()),
+            ref other => Err(ExecutionError::type_mismatch(format!(
+                "ARRAY_AGG() must return a list type, not {:?}",
+                other
+            ))),
+        }
+    }
+}
+
+impl AggregateFunction for ArrayAggFunction {
+    fn name(&self) -> String {
+        "ARRAY_AGG".to_string()
+    }
+
+    fn args(&self) -> Vec<Field> {
+        match self.element_type() {
+            Ok(t) => vec![Field::new("arg", t, true)],
+            Err(_) => vec![],
+        }
+    }
+
+    fn return_type(&self) -> DataType {
+        self.data_type.clone()
+    }
+
+    fn execute(&mut self, args: &[Value]) -> Result<()> {
+        if args.len() != 1 {
+            return Err(ExecutionError::plan(
+                "ARRAY_AGG() requires exactly one argument".to_string(),
+            ));
+        }
+        match args[0] {
+            Value::Column(ref array) => {
+                self.values.extend((0..array.len()).map(|i| get_value(array, i)));
+                self.seen_rows |= array.len() > 0;
+            }
+            Value::Scalar(ref v) => {
+                self.values.push(v.as_ref().clone());
+                self.seen_rows = true;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&self) -> Result<Value> {
+        if !self.seen_rows {
+            return Ok(Value::Scalar(Rc::new(ScalarValue::Null)));
+        }
+        Ok(Value::Scalar(Rc::new(ScalarValue::List(
+            self.values.clone(),
+            self.element_type()?,
+        ))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_array_agg_keeps_nulls() {
+        let list_type = DataType::List(Box::new(DataType::Int32));
+        let mut f = ArrayAggFunction::new(&list_type);
+        assert_eq!(list_type, f.return_type());
+        let array = Array::with_validity(
+            Array::from(vec![1_i32, 0, 3]).into_data(),
+            &vec![true, false, true],
+        );
+        f.execute(&vec![Value::Column(Rc::new(array))]).unwrap();
+        f.execute(&vec![Value::Scalar(Rc::new(ScalarValue::Int32(4)))])
+            .unwrap();
+        match f.finish().unwrap() {
+            Value::Scalar(ref v) => assert_eq!("[1, NULL, 3, 4]", v.to_string()),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_array_agg_of_no_rows_is_null() {
+        let f = ArrayAggFunction::new(&DataType::List(Box::new(DataType::Utf8)));
+        match f.finish().unwrap() {
+            Value::Scalar(ref v) => assert_eq!(ScalarValue::Null, *v.as_ref()),
+            _ => panic!(),
+        }
+    }
+}
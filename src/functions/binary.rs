@@ -0,0 +1,321 @@
+// Copyright 2018 Grove Enterprises LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Functions on binary values. Binary values are converted to and from strings with the
+//! `'hex'` or `'base64'` encoding, which is passed as a string.
+
+use std::rc::Rc;
+use std::result;
+
+use super::super::datasources::common::get_value;
+use super::super::datatypes::*;
+use super::super::errors::*;
+use super::super::types::*;
+use super::expect_args;
+
+/// Get all of the binary functions, so that they can be registered with a context
+pub fn functions() -> Vec<Rc<ScalarFunction>> {
+    vec![
+        Rc::new(LengthFunction {}) as Rc<ScalarFunction>,
+        Rc::new(EncodeFunction {}),
+        Rc::new(DecodeFunction {}),
+    ]
+}
+
+const HEX_DIGITS: &[u8] = b"0123456789abcdef";
+
+const BASE64_DIGITS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Format bytes as lowercase hex digits
+pub fn encode_hex(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        s.push(HEX_DIGITS[(b >> 4) as usize] as char);
+        s.push(HEX_DIGITS[(b & 0xf) as usize] as char);
+    }
+    s
+}
+
+/// Parse pairs of hex digits, which may be upper or lower case
+pub fn decode_hex(s: &str) -> result::Result<Vec<u8>, String> {
+    if s.len() % 2 != 0 {
+        return Err(format!("hex string '{}' has an odd number of digits", s));
+    }
+    let digit = |c: u8| match c {
+        b'0'...b'9' => Ok(c - b'0'),
+        b'a'...b'f' => Ok(c - b'a' + 10),
+        b'A'...b'F' => Ok(c - b'A' + 10),
+        _ => Err(format!("invalid hex digit '{}'", c as char)),
+    };
+    s.as_bytes()
+        .chunks(2)
+        .map(|pair| Ok(digit(pair[0])? << 4 | digit(pair[1])?))
+        .collect()
+}
+
+/// Format bytes as base64 with the standard alphabet and `=` padding
+pub fn encode_base64(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0_u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                s.push(BASE64_DIGITS[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                s.push('=');
+            }
+        }
+    }
+    s
+}
+
+/// Parse base64 with the standard alphabet. The `=` padding at the end is optional.
+pub fn decode_base64(s: &str) -> result::Result<Vec<u8>, String> {
+    let digits = s.trim_right_matches('=').as_bytes();
+    if digits.len() % 4 == 1 || s.len() - digits.len() > 2 {
+        return Err(format!("invalid base64 length in '{}'", s));
+    }
+    let sextet = |c: u8| match c {
+        b'A'...b'Z' => Ok(c - b'A'),
+        b'a'...b'z' => Ok(c - b'a' + 26),
+        b'0'...b'9' => Ok(c - b'0' + 52),
+        b'+' => Ok(62),
+        b'/' => Ok(63),
+        _ => Err(format!("invalid base64 character '{}'", c as char)),
+    };
+    let mut bytes = Vec::with_capacity(digits.len() * 3 / 4);
+    for chunk in digits.chunks(4) {
+        let mut n: u32 = 0;
+        for (i, c) in chunk.iter().enumerate() {
+            n |= (sextet(*c)? as u32) << (18 - 6 * i);
+        }
+        // each digit after the first adds one byte
+        for i in 0..chunk.len() - 1 {
+            bytes.push((n >> (16 - 8 * i)) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Encoding {
+    Hex,
+    Base64,
+}
+
+/// Get the encoding, which must be the same for every row
+fn encoding_arg(value: &Value, function: &str) -> Result<Encoding> {
+    let name = match value {
+        Value::Scalar(ref scalar) => scalar.get_string().ok(),
+        Value::Column(_) => None,
+    };
+    match name.map(|s| s.to_lowercase()) {
+        Some(ref s) if s == "hex" => Ok(Encoding::Hex),
+        Some(ref s) if s == "base64" => Ok(Encoding::Base64),
+        _ => Err(ExecutionError::type_mismatch(format!(
+            "{}() expected 'hex' or 'base64' as the encoding",
+            function
+        ))),
+    }
+}
+
+/// Apply a function to each value of a column or scalar. Null values stay null.
+fn map_values<F>(function: &str, value: &Value, return_type: &DataType, f: F) -> Result<Value>
+where
+    F: Fn(&ScalarValue) -> result::Result<ScalarValue, String>,
+{
+    let apply = |v: &ScalarValue| match v {
+        ScalarValue::Null => Ok(ScalarValue::Null),
+        other => f(other)
+            .map_err(|e| ExecutionError::General(format!("Error in {}(): {}", function, e))),
+    };
+    match value {
+        Value::Scalar(ref v) => Ok(Value::Scalar(Rc::new(apply(v)?))),
+        Value::Column(ref array) => {
+            let values = (0..array.len())
+                .map(|i| apply(&get_value(array, i)))
+                .collect::<Result<Vec<ScalarValue>>>()?;
+            let result = build_nullable_array(return_type, &values, |values| {
+                array_from_scalars(return_type, values)
+            })?;
+            Ok(Value::Column(Rc::new(result)))
+        }
+    }
+}
+
+fn binary_value(v: &ScalarValue) -> result::Result<&Vec<u8>, String> {
+    v.get_binary().map_err(|_| "expected a binary argument".to_string())
+}
+
+/// `length(binary)` returns the number of bytes in a binary value
+pub struct LengthFunction {}
+
+impl ScalarFunction for LengthFunction {
+    fn name(&self) -> String {
+        "length".to_string()
+    }
+
+    fn args(&self) -> Vec<Field> {
+        vec![Field::new("binary", DataType::Binary, true)]
+    }
+
+    fn return_type(&self) -> DataType {
+        DataType::Int64
+    }
+
+    fn execute(&self, args: &[Value]) -> Result<Value> {
+        expect_args("length", args, 1)?;
+        map_values("length", &args[0], &DataType::Int64, |v| {
+            Ok(ScalarValue::Int64(binary_value(v)?.len() as i64))
+        })
+    }
+}
+
+/// `encode(binary, encoding)` formats a binary value as a `'hex'` or `'base64'` string
+pub struct EncodeFunction {}
+
+impl ScalarFunction for EncodeFunction {
+    fn name(&self) -> String {
+        "encode".to_string()
+    }
+
+    fn args(&self) -> Vec<Field> {
+        vec![
+            Field::new("binary", DataType::Binary, true),
+            Field::new("encoding", DataType::Utf8, false),
+        ]
+    }
+
+    fn return_type(&self) -> DataType {
+        DataType::Utf8
+    }
+
+    fn execute(&self, args: &[Value]) -> Result<Value> {
+        expect_args("encode", args, 2)?;
+        let encoding = encoding_arg(&args[1], "encode")?;
+        map_values("encode", &args[0], &DataType::Utf8, |v| {
+            let bytes = binary_value(v)?;
+            let s = match encoding {
+                Encoding::Hex => encode_hex(bytes),
+                Encoding::Base64 => encode_base64(bytes),
+            };
+            Ok(ScalarValue::Utf8(Rc::new(s)))
+        })
+    }
+}
+
+/// `decode(string, encoding)` parses a `'hex'` or `'base64'` string as a binary value
+pub struct DecodeFunction {}
+
+impl ScalarFunction for DecodeFunction {
+    fn name(&self) -> String {
+        "decode".to_string()
+    }
+
+    fn args(&self) -> Vec<Field> {
+        vec![
+            Field::new("string", DataType::Utf8, true),
+            Field::new("encoding", DataType::Utf8, false),
+        ]
+    }
+
+    fn return_type(&self) -> DataType {
+        DataType::Binary
+    }
+
+    fn execute(&self, args: &[Value]) -> Result<Value> {
+        expect_args("decode", args, 2)?;
+        let encoding = encoding_arg(&args[1], "decode")?;
+        map_values("decode", &args[0], &DataType::Binary, |v| {
+            let s = v
+                .get_string()
+                .map_err(|_| "expected a string argument".to_string())?;
+            let bytes = match encoding {
+                Encoding::Hex => decode_hex(s)?,
+                Encoding::Base64 => decode_base64(s)?,
+            };
+            Ok(ScalarValue::Binary(Rc::new(bytes)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_fixtures::*;
+    use super::*;
+
+    fn binary(values: Vec<&[u8]>) -> Value {
+        let values = values.iter().map(|v| v.to_vec()).collect::<Vec<Vec<u8>>>();
+        Value::Column(Rc::new(Array::from(values)))
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        assert_eq!("00deadbeef", encode_hex(&[0x00, 0xde, 0xad, 0xbe, 0xef]));
+        assert_eq!(vec![0x00, 0xde, 0xad, 0xbe, 0xef], decode_hex("00DEADbeef").unwrap());
+        assert!(decode_hex("abc").is_err());
+        assert!(decode_hex("zz").is_err());
+    }
+
+    #[test]
+    fn base64_round_trip() {
+        let cases: Vec<(&[u8], &str)> = vec![
+            (&b""[..], ""),
+            (&b"f"[..], "Zg=="),
+            (&b"fo"[..], "Zm8="),
+            (&b"foo"[..], "Zm9v"),
+            (&b"foob"[..], "Zm9vYg=="),
+            (&b"\xff\xfe\xfd"[..], "//79"),
+        ];
+        for (bytes, s) in cases {
+            assert_eq!(s, encode_base64(bytes));
+            assert_eq!(bytes.to_vec(), decode_base64(s).unwrap());
+        }
+        assert_eq!(b"fo".to_vec(), decode_base64("Zm8").unwrap());
+        assert!(decode_base64("Z").is_err());
+        assert!(decode_base64("Zm9v!").is_err());
+    }
+
+    #[test]
+    fn encode_and_decode_columns() {
+        let values = binary(vec![&b"foo"[..], &b""[..], &b"\x01\x02"[..]]);
+        let hex = EncodeFunction {}.execute(&vec![values.clone(), utf8("hex")]).unwrap();
+        assert_eq!(vec!["666f6f", "", "0102"], rows(hex));
+        let base64 = EncodeFunction {}.execute(&vec![values, utf8("BASE64")]).unwrap();
+        assert_eq!(vec!["Zm9v", "", "AQI="], rows(base64.clone()));
+        let decoded = DecodeFunction {}.execute(&vec![base64, utf8("base64")]).unwrap();
+        assert_eq!(vec!["666f6f", "", "0102"], rows(decoded));
+    }
+
+    #[test]
+    fn length_keeps_nulls() {
+        let values = Value::Column(Rc::new(Array::with_validity(
+            Array::from(vec![b"abc".to_vec(), vec![]]).into_data(),
+            &vec![true, false],
+        )));
+        let result = LengthFunction {}.execute(&vec![values]).unwrap();
+        assert_eq!(vec!["3", "NULL"], rows(result));
+    }
+
+    #[test]
+    fn invalid_encoding() {
+        let values = binary(vec![&b"foo"[..]]);
+        assert!(EncodeFunction {}.execute(&vec![values, utf8("utf8")]).is_err());
+        let result = DecodeFunction {}.execute(&vec![utf8("xyz"), utf8("hex")]);
+        assert!(result.is_err());
+    }
+}
@@ -16,11 +16,10 @@
 
 use std::rc::Rc;
 
+use super::super::datatypes::*;
 use super::super::errors::*;
 use super::super::types::*;
 
-use arrow::datatypes::*;
-
 pub struct CountFunction {
     count: usize,
 }
@@ -45,7 +44,11 @@ impl AggregateFunction for CountFunction {
     }
 
     fn execute(&mut self, args: &[Value]) -> Result<()> {
-        assert_eq!(1, args.len());
+        if args.len() != 1 {
+            return Err(ExecutionError::plan(
+                "COUNT() requires exactly one argument".to_string(),
+            ));
+        }
         match args[0] {
             Value::Column(ref array) => {
                 //println!("Counting array elements: {}", array.len());
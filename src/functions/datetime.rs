@@ -0,0 +1,625 @@
+// Copyright 2018 Grove Enterprises LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Date and time functions. Timestamp arguments are converted to microsecond timestamps, which
+//! is the type of SQL `TIMESTAMP`, and date/time fields such as `'day'` are passed as strings.
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::result;
+
+use arrow::array::PrimitiveArray;
+
+use super::super::datatypes::*;
+use super::super::errors::*;
+use super::super::temporal::{self, Interval};
+use super::super::types::*;
+use super::expect_args;
+
+/// Get all of the date and time functions, so that they can be registered with a context.
+/// `now()` and `current_date()` return the query start time, which is a microsecond timestamp
+/// that the context sets when each query starts.
+pub fn functions(query_start: Rc<Cell<i64>>) -> Vec<Rc<ScalarFunction>> {
+    vec![
+        Rc::new(NowFunction {
+            query_start: query_start.clone(),
+        }) as Rc<ScalarFunction>,
+        Rc::new(CurrentDateFunction { query_start }),
+        Rc::new(DateTruncFunction {}),
+        Rc::new(DatePartFunction {}),
+        Rc::new(ToTimestampFunction {}),
+        Rc::new(DateAddFunction {}),
+        Rc::new(DateDiffFunction {}),
+        Rc::new(DateBinFunction {}),
+    ]
+}
+
+fn timestamp_type() -> DataType {
+    DataType::Timestamp(TimeUnit::Microsecond)
+}
+
+/// An argument that is either a column of values or a single value for every row. Null values
+/// are `None`.
+enum Arg<T> {
+    Column(Vec<Option<T>>),
+    Scalar(Option<T>),
+}
+
+impl<T: Clone> Arg<T> {
+    /// Get the value of a row. Functions are only evaluated for rows where no argument is null.
+    fn get(&self, i: usize) -> T {
+        let value = match self {
+            Arg::Column(values) => &values[i],
+            Arg::Scalar(value) => value,
+        };
+        value.clone().expect("functions are not evaluated for null arguments")
+    }
+}
+
+/// The rows of an argument, whatever the type of its values
+trait Rows {
+    /// The number of rows, or `None` for a single value for every row
+    fn len(&self) -> Option<usize>;
+
+    fn is_null(&self, i: usize) -> bool;
+}
+
+impl<T> Rows for Arg<T> {
+    fn len(&self) -> Option<usize> {
+        match self {
+            Arg::Column(values) => Some(values.len()),
+            Arg::Scalar(_) => None,
+        }
+    }
+
+    fn is_null(&self, i: usize) -> bool {
+        match self {
+            Arg::Column(values) => values[i].is_none(),
+            Arg::Scalar(value) => value.is_none(),
+        }
+    }
+}
+
+/// Evaluate a function for every row where none of the arguments are null. The other rows are
+/// null. The result is a column if any argument is a column, and a scalar otherwise.
+fn evaluate<T, F>(function: &str, args: &[&Rows], f: F) -> Result<Arg<T>>
+where
+    F: Fn(usize) -> result::Result<T, String>,
+{
+    let row = |i: usize, index: Option<usize>| -> Result<Option<T>> {
+        if args.iter().any(|arg| arg.is_null(i)) {
+            return Ok(None);
+        }
+        f(i).map(Some).map_err(|e| {
+            ExecutionError::data(format!("Error in {}(): {}", function, e), None, index, None)
+        })
+    };
+    match args.iter().filter_map(|arg| arg.len()).next() {
+        Some(len) => Ok(Arg::Column(
+            (0..len)
+                .map(|i| row(i, Some(i)))
+                .collect::<Result<Vec<Option<T>>>>()?,
+        )),
+        None => Ok(Arg::Scalar(row(0, None)?)),
+    }
+}
+
+fn argument_error(function: &str, expected: &str) -> ExecutionError {
+    ExecutionError::type_mismatch(format!("{}() expected {} argument", function, expected))
+}
+
+/// The values of a column, with `None` for the null rows
+fn column_values<T, I>(array: &Array, values: I) -> Vec<Option<T>>
+where
+    I: Iterator<Item = T>,
+{
+    values
+        .enumerate()
+        .map(|(i, v)| if array.is_valid(i) { Some(v) } else { None })
+        .collect()
+}
+
+fn timestamp_arg(value: &Value, function: &str) -> Result<(Arg<i64>, TimeUnit)> {
+    match value {
+        Value::Column(array) => match array.data() {
+            ArrayData::Timestamp(values, unit) => {
+                Ok((Arg::Column(column_values(array, values.iter())), *unit))
+            }
+            _ => Err(argument_error(function, "a timestamp")),
+        },
+        Value::Scalar(scalar) => match scalar.as_ref() {
+            ScalarValue::Timestamp(value, unit) => Ok((Arg::Scalar(Some(*value)), *unit)),
+            ScalarValue::Null => Ok((Arg::Scalar(None), TimeUnit::Microsecond)),
+            _ => Err(argument_error(function, "a timestamp")),
+        },
+    }
+}
+
+fn int64_arg(value: &Value, function: &str) -> Result<Arg<i64>> {
+    match value {
+        Value::Column(array) => match array.data() {
+            ArrayData::Int64(values) => Ok(Arg::Column(column_values(array, values.iter()))),
+            _ => Err(argument_error(function, "an integer")),
+        },
+        Value::Scalar(scalar) => match scalar.as_ref() {
+            ScalarValue::Int64(value) => Ok(Arg::Scalar(Some(*value))),
+            ScalarValue::Null => Ok(Arg::Scalar(None)),
+            _ => Err(argument_error(function, "an integer")),
+        },
+    }
+}
+
+fn utf8_arg(value: &Value, function: &str) -> Result<Arg<String>> {
+    match value {
+        Value::Column(array) => match array.data() {
+            ArrayData::Utf8(list) => Ok(Arg::Column(column_values(
+                array,
+                (0..list.len()).map(|i| String::from_utf8_lossy(list.get(i)).to_string()),
+            ))),
+            _ => Err(argument_error(function, "a string")),
+        },
+        Value::Scalar(scalar) => match scalar.as_ref() {
+            ScalarValue::Utf8(value) => Ok(Arg::Scalar(Some(value.as_ref().clone()))),
+            ScalarValue::Null => Ok(Arg::Scalar(None)),
+            _ => Err(argument_error(function, "a string")),
+        },
+    }
+}
+
+/// Get a date/time field name, which must be the same for every row
+fn field_arg(value: &Value, function: &str) -> Result<Arg<String>> {
+    match value {
+        Value::Scalar(scalar) => match scalar.as_ref() {
+            ScalarValue::Utf8(value) => Ok(Arg::Scalar(Some(value.as_ref().clone()))),
+            ScalarValue::Null => Ok(Arg::Scalar(None)),
+            _ => Err(argument_error(function, "a literal field name")),
+        },
+        Value::Column(_) => Err(argument_error(function, "a literal field name")),
+    }
+}
+
+/// Get an interval, which must be the same for every row
+fn interval_arg(value: &Value, function: &str) -> Result<Arg<Interval>> {
+    match value {
+        Value::Scalar(scalar) => match scalar.as_ref() {
+            ScalarValue::Interval(interval) => Ok(Arg::Scalar(Some(*interval))),
+            ScalarValue::Null => Ok(Arg::Scalar(None)),
+            _ => Err(argument_error(function, "a literal interval")),
+        },
+        Value::Column(_) => Err(argument_error(function, "a literal interval")),
+    }
+}
+
+/// Create a column from the rows of a result, which are null where the value is `None`
+fn nullable_array<T>(rows: Vec<Option<T>>) -> Array
+where
+    T: Default,
+    ArrayData: From<Vec<T>>,
+{
+    let validity: Vec<bool> = rows.iter().map(|v| v.is_some()).collect();
+    let values: Vec<T> = rows.into_iter().map(|v| v.unwrap_or_default()).collect();
+    Array::with_validity(ArrayData::from(values), &validity)
+}
+
+fn null_value() -> Value {
+    Value::Scalar(Rc::new(ScalarValue::Null))
+}
+
+fn timestamp_value(result: Arg<i64>, unit: TimeUnit) -> Value {
+    match result {
+        Arg::Column(rows) => {
+            let validity: Vec<bool> = rows.iter().map(|v| v.is_some()).collect();
+            let values: Vec<i64> = rows.into_iter().map(|v| v.unwrap_or_default()).collect();
+            let data = ArrayData::Timestamp(PrimitiveArray::from(values), unit);
+            Value::Column(Rc::new(Array::with_validity(data, &validity)))
+        }
+        Arg::Scalar(Some(value)) => Value::Scalar(Rc::new(ScalarValue::Timestamp(value, unit))),
+        Arg::Scalar(None) => null_value(),
+    }
+}
+
+fn float64_value(result: Arg<f64>) -> Value {
+    match result {
+        Arg::Column(rows) => Value::Column(Rc::new(nullable_array(rows))),
+        Arg::Scalar(Some(value)) => Value::Scalar(Rc::new(ScalarValue::Float64(value))),
+        Arg::Scalar(None) => null_value(),
+    }
+}
+
+fn int64_value(result: Arg<i64>) -> Value {
+    match result {
+        Arg::Column(rows) => Value::Column(Rc::new(nullable_array(rows))),
+        Arg::Scalar(Some(value)) => Value::Scalar(Rc::new(ScalarValue::Int64(value))),
+        Arg::Scalar(None) => null_value(),
+    }
+}
+
+/// `now()` returns the time that the query started in UTC, which is the same for every row
+pub struct NowFunction {
+    query_start: Rc<Cell<i64>>,
+}
+
+impl ScalarFunction for NowFunction {
+    fn name(&self) -> String {
+        "now".to_string()
+    }
+
+    fn args(&self) -> Vec<Field> {
+        vec![]
+    }
+
+    fn return_type(&self) -> DataType {
+        timestamp_type()
+    }
+
+    fn execute(&self, args: &[Value]) -> Result<Value> {
+        expect_args("now", args, 0)?;
+        Ok(Value::Scalar(Rc::new(ScalarValue::Timestamp(
+            self.query_start.get(),
+            TimeUnit::Microsecond,
+        ))))
+    }
+
+    fn is_deterministic(&self) -> bool {
+        false
+    }
+}
+
+/// `current_date()`, or `CURRENT_DATE` in SQL, returns the date that the query started in UTC
+pub struct CurrentDateFunction {
+    query_start: Rc<Cell<i64>>,
+}
+
+impl ScalarFunction for CurrentDateFunction {
+    fn name(&self) -> String {
+        "current_date".to_string()
+    }
+
+    fn args(&self) -> Vec<Field> {
+        vec![]
+    }
+
+    fn return_type(&self) -> DataType {
+        DataType::Date32
+    }
+
+    fn execute(&self, args: &[Value]) -> Result<Value> {
+        expect_args("current_date", args, 0)?;
+        Ok(Value::Scalar(Rc::new(ScalarValue::Date32(
+            temporal::timestamp_to_date(self.query_start.get(), TimeUnit::Microsecond)
+                .expect("the query start time is within the range of dates"),
+        ))))
+    }
+
+    fn is_deterministic(&self) -> bool {
+        false
+    }
+}
+
+/// `date_trunc(field, timestamp)` truncates a timestamp to the start of the year, quarter,
+/// month, week, day, hour, minute or second that it falls in
+pub struct DateTruncFunction {}
+
+impl ScalarFunction for DateTruncFunction {
+    fn name(&self) -> String {
+        "date_trunc".to_string()
+    }
+
+    fn args(&self) -> Vec<Field> {
+        vec![
+            Field::new("field", DataType::Utf8, false),
+            Field::new("timestamp", timestamp_type(), false),
+        ]
+    }
+
+    fn return_type(&self) -> DataType {
+        timestamp_type()
+    }
+
+    fn execute(&self, args: &[Value]) -> Result<Value> {
+        expect_args("date_trunc", args, 2)?;
+        let field = field_arg(&args[0], "date_trunc")?;
+        let (values, unit) = timestamp_arg(&args[1], "date_trunc")?;
+        let result = evaluate("date_trunc", &[&field, &values], |i| {
+            temporal::truncate_timestamp(values.get(i), unit, &field.get(i))
+        })?;
+        Ok(timestamp_value(result, unit))
+    }
+}
+
+/// `date_part(field, timestamp)`, or `EXTRACT(field FROM timestamp)` in SQL, gets a field of
+/// a timestamp such as the year or the day of the week
+pub struct DatePartFunction {}
+
+impl ScalarFunction for DatePartFunction {
+    fn name(&self) -> String {
+        "date_part".to_string()
+    }
+
+    fn args(&self) -> Vec<Field> {
+        vec![
+            Field::new("field", DataType::Utf8, false),
+            Field::new("timestamp", timestamp_type(), false),
+        ]
+    }
+
+    fn return_type(&self) -> DataType {
+        DataType::Float64
+    }
+
+    fn execute(&self, args: &[Value]) -> Result<Value> {
+        expect_args("date_part", args, 2)?;
+        let field = field_arg(&args[0], "date_part")?;
+        let (values, unit) = timestamp_arg(&args[1], "date_part")?;
+        let result = evaluate("date_part", &[&field, &values], |i| {
+            temporal::timestamp_part(values.get(i), unit, &field.get(i))
+        })?;
+        Ok(float64_value(result))
+    }
+}
+
+/// `to_timestamp(string, format)` parses a timestamp with a `strftime` style format
+pub struct ToTimestampFunction {}
+
+impl ScalarFunction for ToTimestampFunction {
+    fn name(&self) -> String {
+        "to_timestamp".to_string()
+    }
+
+    fn args(&self) -> Vec<Field> {
+        vec![
+            Field::new("string", DataType::Utf8, false),
+            Field::new("format", DataType::Utf8, false),
+        ]
+    }
+
+    fn return_type(&self) -> DataType {
+        timestamp_type()
+    }
+
+    fn execute(&self, args: &[Value]) -> Result<Value> {
+        expect_args("to_timestamp", args, 2)?;
+        let strings = utf8_arg(&args[0], "to_timestamp")?;
+        let formats = utf8_arg(&args[1], "to_timestamp")?;
+        let result = evaluate("to_timestamp", &[&strings, &formats], |i| {
+            let (s, format) = (strings.get(i), formats.get(i));
+            temporal::parse_timestamp_with_format(&s, &format, TimeUnit::Microsecond)
+                .map_err(|e| format!("cannot parse '{}' with format '{}': {}", s, format, e))
+        })?;
+        Ok(timestamp_value(result, TimeUnit::Microsecond))
+    }
+}
+
+/// `date_add(field, n, timestamp)` adds `n` years, months, days, hours etc. to a timestamp
+pub struct DateAddFunction {}
+
+impl ScalarFunction for DateAddFunction {
+    fn name(&self) -> String {
+        "date_add".to_string()
+    }
+
+    fn args(&self) -> Vec<Field> {
+        vec![
+            Field::new("field", DataType::Utf8, false),
+            Field::new("n", DataType::Int64, false),
+            Field::new("timestamp", timestamp_type(), false),
+        ]
+    }
+
+    fn return_type(&self) -> DataType {
+        timestamp_type()
+    }
+
+    fn execute(&self, args: &[Value]) -> Result<Value> {
+        expect_args("date_add", args, 3)?;
+        let field = field_arg(&args[0], "date_add")?;
+        let n = int64_arg(&args[1], "date_add")?;
+        let (values, unit) = timestamp_arg(&args[2], "date_add")?;
+        let result = evaluate("date_add", &[&field, &n, &values], |i| {
+            let interval = Interval::from_unit(n.get(i), &field.get(i))?;
+            temporal::add_interval_to_timestamp(values.get(i), unit, &interval)
+                .ok_or_else(|| "timestamp out of range".to_string())
+        })?;
+        Ok(timestamp_value(result, unit))
+    }
+}
+
+/// `date_diff(field, start, end)` counts the year, month, day etc. boundaries between two
+/// timestamps
+pub struct DateDiffFunction {}
+
+impl ScalarFunction for DateDiffFunction {
+    fn name(&self) -> String {
+        "date_diff".to_string()
+    }
+
+    fn args(&self) -> Vec<Field> {
+        vec![
+            Field::new("field", DataType::Utf8, false),
+            Field::new("start", timestamp_type(), false),
+            Field::new("end", timestamp_type(), false),
+        ]
+    }
+
+    fn return_type(&self) -> DataType {
+        DataType::Int64
+    }
+
+    fn execute(&self, args: &[Value]) -> Result<Value> {
+        expect_args("date_diff", args, 3)?;
+        let field = field_arg(&args[0], "date_diff")?;
+        let (start, unit) = timestamp_arg(&args[1], "date_diff")?;
+        let (end, end_unit) = timestamp_arg(&args[2], "date_diff")?;
+        let result = evaluate("date_diff", &[&field, &start, &end], |i| {
+            let end = temporal::convert_time_unit(end.get(i), end_unit, unit)
+                .ok_or_else(|| "timestamp out of range".to_string())?;
+            temporal::diff_timestamps(start.get(i), end, unit, &field.get(i))
+        })?;
+        Ok(int64_value(result))
+    }
+}
+
+/// `date_bin(stride, timestamp, origin)` puts timestamps in bins of a fixed width that are
+/// aligned to the origin, returning the start of the bin
+pub struct DateBinFunction {}
+
+impl ScalarFunction for DateBinFunction {
+    fn name(&self) -> String {
+        "date_bin".to_string()
+    }
+
+    fn args(&self) -> Vec<Field> {
+        vec![
+            Field::new("stride", DataType::Interval, false),
+            Field::new("timestamp", timestamp_type(), false),
+            Field::new("origin", timestamp_type(), false),
+        ]
+    }
+
+    fn return_type(&self) -> DataType {
+        timestamp_type()
+    }
+
+    fn execute(&self, args: &[Value]) -> Result<Value> {
+        expect_args("date_bin", args, 3)?;
+        let stride = interval_arg(&args[0], "date_bin")?;
+        let (values, unit) = timestamp_arg(&args[1], "date_bin")?;
+        let (origin, origin_unit) = timestamp_arg(&args[2], "date_bin")?;
+        let result = evaluate("date_bin", &[&stride, &values, &origin], |i| {
+            let origin = temporal::convert_time_unit(origin.get(i), origin_unit, unit)
+                .ok_or_else(|| "timestamp out of range".to_string())?;
+            temporal::bin_timestamp(&stride.get(i), values.get(i), origin, unit)
+        })?;
+        Ok(timestamp_value(result, unit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_fixtures::*;
+    use super::*;
+
+    fn timestamps(values: &[&str]) -> Value {
+        let values: Vec<Option<i64>> = values
+            .iter()
+            .map(|s| Some(temporal::parse_timestamp(s, TimeUnit::Microsecond).unwrap()))
+            .collect();
+        timestamp_value(Arg::Column(values), TimeUnit::Microsecond)
+    }
+
+    fn formatted(value: Value) -> Vec<String> {
+        match value {
+            Value::Column(array) => match array.data() {
+                ArrayData::Timestamp(values, unit) => values
+                    .iter()
+                    .map(|v| temporal::format_timestamp(v, *unit))
+                    .collect(),
+                other => panic!("unexpected array {}", other),
+            },
+            Value::Scalar(v) => vec![v.to_string()],
+        }
+    }
+
+    #[test]
+    fn test_date_trunc() {
+        let ts = timestamps(&["2018-05-17 13:45:30", "2018-12-31 23:59:59"]);
+        let result = DateTruncFunction {}.execute(&[utf8("month"), ts]).unwrap();
+        assert_eq!(
+            vec!["2018-05-01 00:00:00", "2018-12-01 00:00:00"],
+            formatted(result)
+        );
+    }
+
+    #[test]
+    fn test_date_part() {
+        let ts = timestamps(&["2018-05-17 13:45:30"]);
+        match DatePartFunction {}.execute(&[utf8("hour"), ts]).unwrap() {
+            Value::Column(array) => match array.data() {
+                ArrayData::Float64(values) => assert_eq!(13.0, *values.get(0)),
+                other => panic!("unexpected array {}", other),
+            },
+            _ => panic!("expected a column"),
+        }
+    }
+
+    #[test]
+    fn test_date_add_and_diff() {
+        let ts = timestamps(&["2018-01-31 12:00:00"]);
+        let n = Value::Scalar(Rc::new(ScalarValue::Int64(1)));
+        let later = DateAddFunction {}
+            .execute(&[utf8("month"), n, ts.clone()])
+            .unwrap();
+        assert_eq!(vec!["2018-02-28 12:00:00"], formatted(later.clone()));
+
+        match DateDiffFunction {}.execute(&[utf8("day"), ts, later]).unwrap() {
+            Value::Column(array) => match array.data() {
+                ArrayData::Int64(values) => assert_eq!(28, *values.get(0)),
+                other => panic!("unexpected array {}", other),
+            },
+            _ => panic!("expected a column"),
+        }
+    }
+
+    #[test]
+    fn test_date_bin() {
+        let stride = Value::Scalar(Rc::new(ScalarValue::Interval(
+            temporal::parse_interval("10 minutes").unwrap(),
+        )));
+        let ts = timestamps(&["2018-01-01 00:09:59", "2018-01-01 00:10:00"]);
+        let origin = Value::Scalar(Rc::new(ScalarValue::Timestamp(0, TimeUnit::Microsecond)));
+        let result = DateBinFunction {}.execute(&[stride, ts, origin]).unwrap();
+        assert_eq!(
+            vec!["2018-01-01 00:00:00", "2018-01-01 00:10:00"],
+            formatted(result)
+        );
+    }
+
+    #[test]
+    fn test_null_arguments() {
+        let ts = Value::Column(Rc::new(Array::with_validity(
+            ArrayData::Timestamp(PrimitiveArray::from(vec![0, 0]), TimeUnit::Microsecond),
+            &[true, false],
+        )));
+        match DatePartFunction {}.execute(&[utf8("year"), ts]).unwrap() {
+            Value::Column(array) => {
+                assert!(array.is_valid(0));
+                assert!(!array.is_valid(1));
+            }
+            _ => panic!("expected a column"),
+        }
+
+        // a null string is not parsed, so it is null rather than an error
+        let null = Value::Scalar(Rc::new(ScalarValue::Null));
+        match ToTimestampFunction {}.execute(&[null, utf8("%d/%m/%Y")]).unwrap() {
+            Value::Scalar(v) => assert_eq!(ScalarValue::Null, *v),
+            _ => panic!("expected a scalar"),
+        }
+    }
+
+    #[test]
+    fn test_to_timestamp() {
+        let result = ToTimestampFunction {}
+            .execute(&[utf8("17/05/2018"), utf8("%d/%m/%Y")])
+            .unwrap();
+        assert_eq!(vec!["2018-05-17 00:00:00"], formatted(result));
+
+        match ToTimestampFunction {}.execute(&[utf8("2018"), utf8("%d/%m/%Y")]) {
+            Err(ExecutionError::DataError { message, .. }) => assert!(
+                message.starts_with("Error in to_timestamp(): cannot parse '2018' with format")
+            ),
+            Err(e) => panic!("unexpected error {:?}", e),
+            Ok(_) => panic!("expected an error"),
+        }
+    }
+}
@@ -15,8 +15,8 @@
 use std::rc::Rc;
 
 //use arrow::array::*;
-use arrow::datatypes::*;
 
+use super::super::super::datatypes::*;
 use super::super::super::errors::*;
 use super::super::super::types::*;
 
@@ -16,11 +16,11 @@
 
 use std::rc::Rc;
 
+use super::super::datatypes::*;
 use super::super::errors::*;
 use super::super::types::*;
 
 //use arrow::array::*;
-use arrow::datatypes::*;
 
 pub struct SqrtFunction {}
 
@@ -30,7 +30,11 @@ impl ScalarFunction for SqrtFunction {
     }
 
     fn execute(&self, args: &[Value]) -> Result<Value> {
-        assert_eq!(1, args.len());
+        if args.len() != 1 {
+            return Err(ExecutionError::plan(
+                "sqrt() requires exactly one argument".to_string(),
+            ));
+        }
         match args[0] {
             Value::Column(ref arr) => match arr.data() {
                 ArrayData::Float64(ref v) => Ok(Value::Column(Rc::new(Array::from(
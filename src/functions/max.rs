@@ -15,13 +15,12 @@
 //! MAX() aggregate function
 
 use std::rc::Rc;
-use std::str;
 
+use super::super::datatypes::*;
 use super::super::errors::*;
 use super::super::types::*;
 
 //use arrow::array::*;
-use arrow::datatypes::*;
 
 pub struct MaxFunction {
     data_type: DataType,
@@ -38,19 +37,45 @@ impl MaxFunction {
 }
 
 macro_rules! max_in_column {
-    ($SELF:ident, $BUF:ident, $VARIANT:ident) => {{
+    ($SELF:ident, $ARRAY:ident, $BUF:ident, $VARIANT:ident) => {{
         for i in 0..$BUF.len() as usize {
+            if !$ARRAY.is_valid(i) {
+                continue;
+            }
             let value = *$BUF.get(i);
             match $SELF.value {
                 ScalarValue::Null => $SELF.value = ScalarValue::$VARIANT(value),
                 ScalarValue::$VARIANT(x) => if value > x {
                     $SELF.value = ScalarValue::$VARIANT(value)
                 },
-                ref other => panic!(
-                    "Type mismatch in MAX() for datatype {} - {:?}",
-                    stringify!($VARIANT),
-                    other
-                ),
+                ref other => {
+                    return Err(ExecutionError::type_mismatch(format!(
+                        "Type mismatch in MAX() for datatype {} - {:?}",
+                        stringify!($VARIANT),
+                        other
+                    )))
+                }
+            }
+        }
+    }};
+    ($SELF:ident, $ARRAY:ident, $BUF:ident, $VARIANT:ident, $UNIT:expr) => {{
+        for i in 0..$BUF.len() as usize {
+            if !$ARRAY.is_valid(i) {
+                continue;
+            }
+            let value = *$BUF.get(i);
+            match $SELF.value {
+                ScalarValue::Null => $SELF.value = ScalarValue::$VARIANT(value, $UNIT),
+                ScalarValue::$VARIANT(x, unit) if unit == $UNIT => if value > x {
+                    $SELF.value = ScalarValue::$VARIANT(value, $UNIT)
+                },
+                ref other => {
+                    return Err(ExecutionError::type_mismatch(format!(
+                        "Type mismatch in MAX() for datatype {} - {:?}",
+                        stringify!($VARIANT),
+                        other
+                    )))
+                }
             }
         }
     }};
@@ -62,10 +87,51 @@ macro_rules! max_in_scalar {
             ScalarValue::$VARIANT(x) => if *$VALUE > x {
                 $SELF.value = ScalarValue::$VARIANT(*$VALUE)
             },
-            _ => panic!("Type mismatch in MAX()"),
+            _ => {
+                return Err(ExecutionError::type_mismatch(
+                    "Type mismatch in MAX()".to_string(),
+                ))
+            }
         }
         Ok(())
     }};
+    ($SELF:ident, $VALUE:ident, $VARIANT:ident, $UNIT:expr) => {{
+        match $SELF.value {
+            ScalarValue::Null => $SELF.value = ScalarValue::$VARIANT(*$VALUE, $UNIT),
+            ScalarValue::$VARIANT(x, unit) if unit == $UNIT => if *$VALUE > x {
+                $SELF.value = ScalarValue::$VARIANT(*$VALUE, $UNIT)
+            },
+            _ => {
+                return Err(ExecutionError::type_mismatch(
+                    "Type mismatch in MAX()".to_string(),
+                ))
+            }
+        }
+        Ok(())
+    }};
+}
+
+fn utf8_mismatch() -> ExecutionError {
+    ExecutionError::type_mismatch("Type mismatch in MAX() for datatype Utf8".to_string())
+}
+
+impl MaxFunction {
+    /// Replace the current value with a decimal value if it is larger
+    fn update_decimal(&mut self, value: i128, precision: usize, scale: usize) -> Result<()> {
+        match self.value {
+            ScalarValue::Null => self.value = ScalarValue::Decimal(value, precision, scale),
+            ScalarValue::Decimal(x, _, s) if s == scale => if value > x {
+                self.value = ScalarValue::Decimal(value, precision, scale)
+            },
+            ref other => {
+                return Err(ExecutionError::type_mismatch(format!(
+                    "Type mismatch in MAX() for datatype Decimal - {:?}",
+                    other
+                )))
+            }
+        }
+        Ok(())
+    }
 }
 
 impl AggregateFunction for MaxFunction {
@@ -82,30 +148,51 @@ impl AggregateFunction for MaxFunction {
     }
 
     fn execute(&mut self, args: &[Value]) -> Result<()> {
-        assert_eq!(1, args.len());
+        if args.len() != 1 {
+            return Err(ExecutionError::plan(
+                "MAX() requires exactly one argument".to_string(),
+            ));
+        }
         match args[0] {
             Value::Column(ref array) => {
                 match array.data() {
-                    ArrayData::Boolean(ref buf) => max_in_column!(self, buf, Boolean),
-                    ArrayData::UInt8(ref buf) => max_in_column!(self, buf, UInt8),
-                    ArrayData::UInt16(ref buf) => max_in_column!(self, buf, UInt16),
-                    ArrayData::UInt32(ref buf) => max_in_column!(self, buf, UInt32),
-                    ArrayData::UInt64(ref buf) => max_in_column!(self, buf, UInt64),
-                    ArrayData::Int8(ref buf) => max_in_column!(self, buf, Int8),
-                    ArrayData::Int16(ref buf) => max_in_column!(self, buf, Int16),
-                    ArrayData::Int32(ref buf) => max_in_column!(self, buf, Int32),
-                    ArrayData::Int64(ref buf) => max_in_column!(self, buf, Int64),
-                    ArrayData::Float32(ref buf) => max_in_column!(self, buf, Float32),
-                    ArrayData::Float64(ref buf) => max_in_column!(self, buf, Float64),
+                    ArrayData::Boolean(ref buf) => max_in_column!(self, array, buf, Boolean),
+                    ArrayData::UInt8(ref buf) => max_in_column!(self, array, buf, UInt8),
+                    ArrayData::UInt16(ref buf) => max_in_column!(self, array, buf, UInt16),
+                    ArrayData::UInt32(ref buf) => max_in_column!(self, array, buf, UInt32),
+                    ArrayData::UInt64(ref buf) => max_in_column!(self, array, buf, UInt64),
+                    ArrayData::Int8(ref buf) => max_in_column!(self, array, buf, Int8),
+                    ArrayData::Int16(ref buf) => max_in_column!(self, array, buf, Int16),
+                    ArrayData::Int32(ref buf) => max_in_column!(self, array, buf, Int32),
+                    ArrayData::Int64(ref buf) => max_in_column!(self, array, buf, Int64),
+                    ArrayData::Float32(ref buf) => max_in_column!(self, array, buf, Float32),
+                    ArrayData::Float64(ref buf) => max_in_column!(self, array, buf, Float64),
+                    ArrayData::Date32(ref buf) => max_in_column!(self, array, buf, Date32),
+                    ArrayData::Time64(ref buf, unit) => {
+                        max_in_column!(self, array, buf, Time64, *unit)
+                    }
+                    ArrayData::Timestamp(ref buf, unit) => {
+                        max_in_column!(self, array, buf, Timestamp, *unit)
+                    }
+                    ArrayData::Decimal(ref values, precision, scale) => {
+                        for (i, v) in values.iter().enumerate() {
+                            if array.is_valid(i) {
+                                self.update_decimal(*v, *precision, *scale)?;
+                            }
+                        }
+                    }
                     ArrayData::Utf8(ref list) => {
-                        if list.len() > 0 {
-                            let mut s = str::from_utf8(list.get(0)).unwrap().to_string();
-                            for i in 1..list.len() {
-                                let s2 = str::from_utf8(list.get(i)).unwrap().to_string();
-                                if s2 < s {
-                                    s = s2;
-                                }
+                        let mut best: Option<String> = None;
+                        for i in 0..list.len() {
+                            if !array.is_valid(i) {
+                                continue;
                             }
+                            let s2 = String::from_utf8_lossy(list.get(i)).to_string();
+                            if best.as_ref().map_or(true, |s| &s2 < s) {
+                                best = Some(s2);
+                            }
+                        }
+                        if let Some(s) = best {
                             self.value = match &self.value {
                                 ScalarValue::Null => ScalarValue::Utf8(Rc::new(s)),
                                 ScalarValue::Utf8(current) => if &s < current.as_ref() {
@@ -113,11 +200,25 @@ impl AggregateFunction for MaxFunction {
                                 } else {
                                     self.value.clone()
                                 },
-                                _ => panic!(),
+                                _ => return Err(utf8_mismatch()),
                             };
                         }
                     }
-                    ArrayData::Struct(_) => unimplemented!("MAX() does not support struct types"),
+                    ArrayData::Binary(_) => {
+                        return Err(ExecutionError::type_mismatch(
+                            "MAX() does not support binary types".to_string(),
+                        ))
+                    }
+                    ArrayData::Struct(_) => {
+                        return Err(ExecutionError::type_mismatch(
+                            "MAX() does not support struct types".to_string(),
+                        ))
+                    }
+                    ArrayData::List(_, _) => {
+                        return Err(ExecutionError::type_mismatch(
+                            "MAX() does not support list types".to_string(),
+                        ))
+                    }
                 }
                 Ok(())
             }
@@ -132,6 +233,16 @@ impl AggregateFunction for MaxFunction {
                 ScalarValue::Int64(ref value) => max_in_scalar!(self, value, Int64),
                 ScalarValue::Float32(ref value) => max_in_scalar!(self, value, Float32),
                 ScalarValue::Float64(ref value) => max_in_scalar!(self, value, Float64),
+                ScalarValue::Date32(ref value) => max_in_scalar!(self, value, Date32),
+                ScalarValue::Time64(ref value, unit) => {
+                    max_in_scalar!(self, value, Time64, *unit)
+                }
+                ScalarValue::Timestamp(ref value, unit) => {
+                    max_in_scalar!(self, value, Timestamp, *unit)
+                }
+                ScalarValue::Decimal(value, precision, scale) => {
+                    self.update_decimal(*value, *precision, *scale)
+                }
                 ScalarValue::Utf8(ref value) => {
                     self.value = match &self.value {
                         ScalarValue::Null => ScalarValue::Utf8(value.clone()),
@@ -140,11 +251,15 @@ impl AggregateFunction for MaxFunction {
                         } else {
                             self.value.clone()
                         },
-                        _ => panic!(),
+                        _ => return Err(utf8_mismatch()),
                     };
                     Ok(())
                 }
-                _ => unimplemented!("MAX() unsupported scalar datatype"),
+                // nulls are ignored
+                ScalarValue::Null => Ok(()),
+                _ => Err(ExecutionError::type_mismatch(
+                    "MAX() unsupported scalar datatype".to_string(),
+                )),
             },
         }
     }
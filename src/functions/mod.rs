@@ -14,9 +14,55 @@
 
 //! Example SQL functions
 
+use super::errors::*;
+use super::types::*;
+
+pub mod array;
+pub mod array_agg;
+pub mod binary;
 pub mod count;
+pub mod datetime;
 pub mod geospatial;
 pub mod math;
 pub mod max;
 pub mod min;
+pub mod structs;
 pub mod sum;
+
+/// Check that a function was called with the expected number of arguments
+pub fn expect_args(function: &str, args: &[Value], count: usize) -> Result<()> {
+    if args.len() == count {
+        Ok(())
+    } else {
+        Err(ExecutionError::plan(format!(
+            "{}() requires exactly {} arguments",
+            function, count
+        )))
+    }
+}
+
+/// Values shared by the tests of the functions
+#[cfg(test)]
+mod test_fixtures {
+    use std::rc::Rc;
+
+    use super::super::types::*;
+
+    pub fn scalar(v: ScalarValue) -> Value {
+        Value::Scalar(Rc::new(v))
+    }
+
+    pub fn utf8(s: &str) -> Value {
+        scalar(ScalarValue::Utf8(Rc::new(s.to_string())))
+    }
+
+    /// The values of a function result as strings, one per row
+    pub fn rows(value: Value) -> Vec<String> {
+        match value {
+            Value::Column(ref array) => (0..array.len())
+                .map(|i| value_at(&value, i).to_string())
+                .collect(),
+            Value::Scalar(ref v) => vec![v.to_string()],
+        }
+    }
+}
@@ -0,0 +1,167 @@
+// Copyright 2018 Grove Enterprises LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Functions that access and construct struct values
+
+use std::rc::Rc;
+
+use super::super::datatypes::*;
+use super::super::errors::*;
+use super::super::types::*;
+
+/// Get the field at `index` of each struct. The field of a null struct is null.
+pub fn get_field(value: &Value, index: usize, field_type: &DataType) -> Result<Value> {
+    match value {
+        Value::Scalar(ref v) => Ok(Value::Scalar(Rc::new(match v.as_ref() {
+            ScalarValue::Null => ScalarValue::Null,
+            other => match other.get_struct()?.get(index) {
+                Some(field) => field.clone(),
+                None => return Err(missing_field(index)),
+            },
+        }))),
+        Value::Column(ref array) => {
+            let child = match array.data() {
+                ArrayData::Struct(ref fields) => match fields.get(index) {
+                    Some(field) => field.clone(),
+                    None => return Err(missing_field(index)),
+                },
+                other => {
+                    return Err(ExecutionError::type_mismatch(format!(
+                        "Cannot access a field of a value of type {:?}",
+                        other.data_type()
+                    )))
+                }
+            };
+            if array.null_count() == 0 {
+                return Ok(Value::Column(child));
+            }
+            // a null struct masks the value of its field
+            let child = Value::Column(child);
+            let values: Vec<ScalarValue> = (0..array.len())
+                .map(|i| {
+                    if array.is_valid(i) {
+                        value_at(&child, i)
+                    } else {
+                        ScalarValue::Null
+                    }
+                })
+                .collect();
+            let result = build_nullable_array(field_type, &values, |values| {
+                array_from_scalars(field_type, values)
+            })?;
+            Ok(Value::Column(Rc::new(result)))
+        }
+    }
+}
+
+fn missing_field(index: usize) -> ExecutionError {
+    ExecutionError::type_mismatch(format!("Struct has no field at index {}", index))
+}
+
+/// Build a struct of the given type from the values of its fields. Scalar fields are repeated
+/// for every row when any other field is a column.
+pub fn make_struct(args: &[Value], data_type: &DataType) -> Result<Value> {
+    let fields = match data_type {
+        DataType::Struct(fields) if fields.len() == args.len() => fields,
+        other => {
+            return Err(ExecutionError::type_mismatch(format!(
+                "Cannot build a value of type {:?} from {} fields",
+                other,
+                args.len()
+            )))
+        }
+    };
+    let len = args
+        .iter()
+        .filter_map(|a| match a {
+            Value::Column(ref array) => Some(array.len()),
+            Value::Scalar(_) => None,
+        })
+        .next();
+    let len = match len {
+        Some(len) => len,
+        None => {
+            let values = args.iter().map(|a| value_at(a, 0)).collect();
+            return Ok(Value::Scalar(Rc::new(ScalarValue::Struct(values))));
+        }
+    };
+    let children = args
+        .iter()
+        .zip(fields.iter())
+        .map(|(arg, field)| match arg {
+            Value::Column(ref array) if array.len() == len => Ok(array.clone()),
+            Value::Column(ref array) => Err(ExecutionError::General(format!(
+                "Struct field '{}' has {} rows but expected {}",
+                field.name(),
+                array.len(),
+                len
+            ))),
+            Value::Scalar(ref v) => {
+                let field_type = field.data_type();
+                let values = vec![v.as_ref().clone(); len];
+                let array = build_nullable_array(field_type, &values, |values| {
+                    array_from_scalars(field_type, values)
+                })?;
+                Ok(Rc::new(array))
+            }
+        })
+        .collect::<Result<Vec<Rc<Array>>>>()?;
+    Ok(Value::Column(Rc::new(Array::new(len, ArrayData::Struct(children)))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_fixtures::*;
+    use super::*;
+
+    fn points() -> Value {
+        let x = Array::from(vec![1.0_f64, 2.0, 3.0]);
+        let y = Array::from(vec![4.0_f64, 5.0, 6.0]);
+        let points = Array::with_validity(
+            ArrayData::Struct(vec![Rc::new(x), Rc::new(y)]),
+            &vec![true, false, true],
+        );
+        Value::Column(Rc::new(points))
+    }
+
+    #[test]
+    fn field_of_null_struct_is_null() {
+        let result = get_field(&points(), 1, &DataType::Float64).unwrap();
+        assert_eq!(vec!["4", "NULL", "6"], rows(result));
+    }
+
+    #[test]
+    fn field_of_scalar_struct() {
+        let point = ScalarValue::Struct(vec![ScalarValue::Int64(1), ScalarValue::Int64(2)]);
+        let result = get_field(&Value::Scalar(Rc::new(point)), 0, &DataType::Int64).unwrap();
+        assert_eq!(vec!["1"], rows(result));
+    }
+
+    #[test]
+    fn struct_repeats_scalar_fields() {
+        let data_type = DataType::Struct(vec![
+            Field::new("id", DataType::Int64, true),
+            Field::new("name", DataType::Utf8, true),
+        ]);
+        let args = vec![
+            Value::Column(Rc::new(Array::from(vec![1_i64, 2]))),
+            Value::Scalar(Rc::new(ScalarValue::Utf8(Rc::new("a".to_string())))),
+        ];
+        let result = make_struct(&args, &data_type).unwrap();
+        let name = get_field(&result, 1, &DataType::Utf8).unwrap();
+        assert_eq!(vec!["a", "a"], rows(name));
+        let id = get_field(&result, 0, &DataType::Int64).unwrap();
+        assert_eq!(vec!["1", "2"], rows(id));
+    }
+}
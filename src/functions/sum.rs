@@ -1,17 +1,25 @@
 //! Sum() aggregate function
 
+use super::super::arithmetic::{Arithmetic, ArithmeticOperator, OverflowMode};
+use super::super::datatypes::*;
+use super::super::decimal;
 use super::super::errors::*;
 use super::super::types::*;
-use arrow::datatypes::*;
 use std::rc::Rc;
 
 pub struct SumFunction {
     value: ScalarValue,
     data_type: DataType,
+    overflow_mode: OverflowMode,
 }
 
 impl SumFunction {
     pub fn new(dt: &DataType) -> Self {
+        SumFunction::with_overflow_mode(dt, OverflowMode::default())
+    }
+
+    /// Create a SUM where integer overflow is an error, or wraps around in wrapping mode
+    pub fn with_overflow_mode(dt: &DataType, overflow_mode: OverflowMode) -> Self {
         let t;
         match *dt {
             DataType::Int8 => t = ScalarValue::Int8(0),
@@ -24,64 +32,80 @@ impl SumFunction {
             DataType::UInt64 => t = ScalarValue::UInt64(0),
             DataType::Float32 => t = ScalarValue::Float32(0.0),
             DataType::Float64 => t = ScalarValue::Float64(0.0),
-            _ => panic!(
-                "Unsupported Datatype for SUM Aggregate: {:?}",
-                stringify!(dt)
-            ),
+            DataType::Decimal(p, s) => t = ScalarValue::Decimal(0, p, s),
+            // unsupported types are reported as type mismatches when values are aggregated
+            _ => t = ScalarValue::Null,
         };
         SumFunction {
             value: t,
             data_type: dt.clone(),
+            overflow_mode,
         }
     }
-}
 
-macro_rules! sum_of_column {
-    ($SELF:ident, $BUF:ident, $VARIANT:ident) => {{
-        for i in 0..$BUF.len() as usize {
-            let value = *$BUF.get(i);
-            match $SELF.value {
-                ScalarValue::Null => $SELF.value = ScalarValue::$VARIANT(value),
-                ScalarValue::$VARIANT(x) => {
-                    $SELF.value = ScalarValue::$VARIANT(value.saturating_add(x))
+    /// Add decimal values to the sum, which is exact and fails if the total does not fit in
+    /// the precision of the return type
+    fn add_decimals<'a, I>(&mut self, values: I, scale: usize) -> Result<()>
+    where
+        I: Iterator<Item = &'a i128>,
+    {
+        match self.value {
+            ScalarValue::Decimal(mut sum, precision, s) if s == scale => {
+                for v in values {
+                    sum = sum.checked_add(*v).ok_or_else(|| {
+                        ExecutionError::General("Error in SUM(): decimal overflow".to_string())
+                    })?;
                 }
-                ref other => panic!(
-                    "Type mismatch in SUM() for datatype {} - {:?}",
-                    stringify!($VARIANT),
-                    other
-                ),
+                decimal::check_precision(sum, precision, scale)
+                    .map_err(|e| ExecutionError::General(format!("Error in SUM(): {}", e)))?;
+                self.value = ScalarValue::Decimal(sum, precision, scale);
+                Ok(())
             }
+            ref other => Err(ExecutionError::type_mismatch(format!(
+                "Type mismatch in SUM() for datatype Decimal - {:?}",
+                other
+            ))),
         }
-    }};
+    }
 }
 
-macro_rules! sum_of_column_float {
-    ($SELF:ident, $BUF:ident, $VARIANT:ident) => {{
-        for i in 0..$BUF.len() as usize {
-            let value = *$BUF.get(i);
-            match $SELF.value {
-                ScalarValue::Null => $SELF.value = ScalarValue::$VARIANT(value),
-                ScalarValue::$VARIANT(x) => $SELF.value = ScalarValue::$VARIANT(x + value),
-                ref other => panic!(
+/// Add a value to the sum, checking for overflow as configured
+fn add_to_sum<T: Arithmetic>(sum: T, value: T, mode: OverflowMode) -> Result<T> {
+    match T::apply(ArithmeticOperator::Add, sum, value, mode) {
+        Ok(Some(v)) => Ok(v),
+        // addition is never null
+        Ok(None) => Ok(sum),
+        Err(e) => Err(ExecutionError::General(format!("Error in SUM(): {}", e))),
+    }
+}
+
+macro_rules! sum_of_scalar {
+    ($SELF:ident, $VALUE:expr, $VARIANT:ident) => {{
+        match $SELF.value {
+            ScalarValue::Null => $SELF.value = ScalarValue::$VARIANT($VALUE),
+            ScalarValue::$VARIANT(x) => {
+                $SELF.value =
+                    ScalarValue::$VARIANT(add_to_sum(x, $VALUE, $SELF.overflow_mode)?)
+            }
+            ref other => {
+                return Err(ExecutionError::type_mismatch(format!(
                     "Type mismatch in SUM() for datatype {} - {:?}",
                     stringify!($VARIANT),
                     other
-                ),
+                )))
             }
         }
     }};
 }
 
-macro_rules! sum_of_scalar {
-    ($SELF:ident, $VALUE:ident, $VARIANT:ident) => {{
-        match $SELF.value {
-            ScalarValue::Null => $SELF.value = ScalarValue::$VARIANT(*$VALUE),
-            ScalarValue::$VARIANT(x) => $SELF.value = ScalarValue::$VARIANT(x + *$VALUE),
-            ref other => panic!(
-                "Type mismatch in SUM() for datatype {} - {:?}",
-                stringify!($VARIANT),
-                other
-            ),
+macro_rules! sum_of_column {
+    ($SELF:ident, $ARRAY:ident, $BUF:ident, $VARIANT:ident) => {{
+        for i in 0..$BUF.len() as usize {
+            if !$ARRAY.is_valid(i) {
+                continue;
+            }
+            let value = *$BUF.get(i);
+            sum_of_scalar!($SELF, value, $VARIANT);
         }
     }};
 }
@@ -100,33 +124,60 @@ impl AggregateFunction for SumFunction {
     }
 
     fn execute(&mut self, args: &[Value]) -> Result<()> {
-        assert_eq!(1, args.len());
+        if args.len() != 1 {
+            return Err(ExecutionError::plan(
+                "SUM() requires exactly one argument".to_string(),
+            ));
+        }
         match args[0] {
             Value::Column(ref array) => match array.data() {
-                ArrayData::UInt8(ref buf) => sum_of_column!(self, buf, UInt8),
-                ArrayData::UInt16(ref buf) => sum_of_column!(self, buf, UInt16),
-                ArrayData::UInt32(ref buf) => sum_of_column!(self, buf, UInt32),
-                ArrayData::UInt64(ref buf) => sum_of_column!(self, buf, UInt64),
-                ArrayData::Float32(ref buf) => sum_of_column_float!(self, buf, Float32),
-                ArrayData::Float64(ref buf) => sum_of_column_float!(self, buf, Float64),
-                ArrayData::Int8(ref buf) => sum_of_column!(self, buf, Int8),
-                ArrayData::Int16(ref buf) => sum_of_column!(self, buf, Int16),
-                ArrayData::Int32(ref buf) => sum_of_column!(self, buf, Int32),
-                ArrayData::Int64(ref buf) => sum_of_column!(self, buf, Int64),
-                _ => unimplemented!("Not done for this type: Utf8"),
+                ArrayData::UInt8(ref buf) => sum_of_column!(self, array, buf, UInt8),
+                ArrayData::UInt16(ref buf) => sum_of_column!(self, array, buf, UInt16),
+                ArrayData::UInt32(ref buf) => sum_of_column!(self, array, buf, UInt32),
+                ArrayData::UInt64(ref buf) => sum_of_column!(self, array, buf, UInt64),
+                ArrayData::Float32(ref buf) => sum_of_column!(self, array, buf, Float32),
+                ArrayData::Float64(ref buf) => sum_of_column!(self, array, buf, Float64),
+                ArrayData::Int8(ref buf) => sum_of_column!(self, array, buf, Int8),
+                ArrayData::Int16(ref buf) => sum_of_column!(self, array, buf, Int16),
+                ArrayData::Int32(ref buf) => sum_of_column!(self, array, buf, Int32),
+                ArrayData::Int64(ref buf) => sum_of_column!(self, array, buf, Int64),
+                ArrayData::Decimal(ref values, _, scale) => self.add_decimals(
+                    values
+                        .iter()
+                        .enumerate()
+                        .filter(|&(i, _)| array.is_valid(i))
+                        .map(|(_, v)| v),
+                    *scale,
+                )?,
+                other => {
+                    return Err(ExecutionError::type_mismatch(format!(
+                        "SUM() does not support {:?}",
+                        other
+                    )))
+                }
             },
             Value::Scalar(ref v) => match v.as_ref() {
-                ScalarValue::UInt8(ref value) => sum_of_scalar!(self, value, UInt8),
-                ScalarValue::UInt16(ref value) => sum_of_scalar!(self, value, UInt16),
-                ScalarValue::UInt32(ref value) => sum_of_scalar!(self, value, UInt32),
-                ScalarValue::UInt64(ref value) => sum_of_scalar!(self, value, UInt64),
-                ScalarValue::Int8(ref value) => sum_of_scalar!(self, value, Int8),
-                ScalarValue::Int16(ref value) => sum_of_scalar!(self, value, Int16),
-                ScalarValue::Int32(ref value) => sum_of_scalar!(self, value, Int32),
-                ScalarValue::Int64(ref value) => sum_of_scalar!(self, value, Int64),
-                ScalarValue::Float32(ref value) => sum_of_scalar!(self, value, Float32),
-                ScalarValue::Float64(ref value) => sum_of_scalar!(self, value, Float64),
-                _ => unimplemented!("Not done for type"),
+                ScalarValue::UInt8(value) => sum_of_scalar!(self, *value, UInt8),
+                ScalarValue::UInt16(value) => sum_of_scalar!(self, *value, UInt16),
+                ScalarValue::UInt32(value) => sum_of_scalar!(self, *value, UInt32),
+                ScalarValue::UInt64(value) => sum_of_scalar!(self, *value, UInt64),
+                ScalarValue::Int8(value) => sum_of_scalar!(self, *value, Int8),
+                ScalarValue::Int16(value) => sum_of_scalar!(self, *value, Int16),
+                ScalarValue::Int32(value) => sum_of_scalar!(self, *value, Int32),
+                ScalarValue::Int64(value) => sum_of_scalar!(self, *value, Int64),
+                ScalarValue::Float32(value) => sum_of_scalar!(self, *value, Float32),
+                ScalarValue::Float64(value) => sum_of_scalar!(self, *value, Float64),
+                ScalarValue::Decimal(ref value, _, scale) => {
+                    self.add_decimals(::std::iter::once(value), *scale)?
+                }
+                // nulls are ignored
+                ScalarValue::Null => {}
+                other => {
+                    return Err(ExecutionError::type_mismatch(format!(
+                        "SUM() does not support {:?}",
+                        other
+                    )))
+                }
             },
         }
         Ok(())
@@ -158,6 +209,43 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_sum_overflow() {
+        let values: Vec<u8> = vec![200, 100];
+        let mut sum = SumFunction::new(&DataType::UInt8);
+        assert!(sum
+            .execute(&vec![Value::Column(Rc::new(Array::from(values.clone())))])
+            .is_err());
+
+        let mut sum = SumFunction::with_overflow_mode(&DataType::UInt8, OverflowMode::Wrapping);
+        sum.execute(&vec![Value::Column(Rc::new(Array::from(values)))])
+            .unwrap();
+        match sum.finish().unwrap() {
+            Value::Scalar(ref v) => assert_eq!(v.get_u8().unwrap(), 44),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_sum_decimal() {
+        let mut sum = SumFunction::new(&DataType::Decimal(5, 2));
+        let values = ArrayData::Decimal(vec![12345, -99, 1], 4, 2);
+
+        sum.execute(&vec![Value::Column(Rc::new(Array::new(3, values)))])
+            .unwrap();
+        sum.execute(&vec![Value::Scalar(Rc::new(ScalarValue::Decimal(3, 4, 2)))])
+            .unwrap();
+        match sum.finish().unwrap() {
+            Value::Scalar(ref v) => assert_eq!("122.50", v.to_string()),
+            _ => panic!(),
+        }
+
+        let values = ArrayData::Decimal(vec![99999], 5, 2);
+        assert!(sum
+            .execute(&vec![Value::Column(Rc::new(Array::new(1, values)))])
+            .is_err());
+    }
+
     #[test]
     fn test_sum_f64() {
         let mut sum = SumFunction::new(&DataType::Float64);
@@ -21,6 +21,7 @@
 extern crate arrow;
 extern crate byteorder;
 extern crate bytes;
+extern crate chrono;
 extern crate clap;
 extern crate csv;
 extern crate datafusion_rustyline;
@@ -31,16 +32,27 @@ extern crate parquet;
 #[macro_use]
 extern crate lazy_static;
 
+pub mod arithmetic;
+pub mod cast;
+pub mod costmodel;
 pub mod dataframe;
 pub mod datasources;
+pub mod datatypes;
+pub mod decimal;
+pub mod dot;
 #[macro_use]
 pub mod errors;
 pub mod exec;
 pub mod functions;
 pub mod logical;
+pub mod metrics;
+pub mod optimizer;
+pub mod planbuilder;
 pub mod relations;
 pub mod sqlast;
 pub mod sqlparser;
 pub mod sqlplanner;
 pub mod sqltokenizer;
+pub mod temporal;
 pub mod types;
+pub mod visitor;
@@ -15,12 +15,15 @@
 //! Logical plan
 
 use std::fmt;
-use std::fmt::{Error, Formatter};
+use std::fmt::Formatter;
 use std::rc::Rc;
 
+use super::datatypes::*;
+use super::decimal::DecimalOperator;
+use super::dot::DotGraph;
+use super::errors::*;
 use super::types::*;
-
-use arrow::datatypes::*;
+use super::visitor::*;
 
 #[derive(Debug, Clone)]
 pub enum FunctionType {
@@ -34,6 +37,7 @@ pub struct FunctionMeta {
     args: Vec<Field>,
     return_type: DataType,
     function_type: FunctionType,
+    deterministic: bool,
 }
 
 impl FunctionMeta {
@@ -42,12 +46,14 @@ impl FunctionMeta {
         args: Vec<Field>,
         return_type: DataType,
         function_type: FunctionType,
+        deterministic: bool,
     ) -> Self {
         FunctionMeta {
             name,
             args,
             return_type,
             function_type,
+            deterministic,
         }
     }
     pub fn name(&self) -> &String {
@@ -62,6 +68,9 @@ impl FunctionMeta {
     pub fn function_type(&self) -> &FunctionType {
         &self.function_type
     }
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -79,23 +88,184 @@ pub enum Operator {
     Modulus,
     And,
     Or,
+    StringConcat,
 }
 
 impl Operator {
     /// Get the result type of applying this operation to its left and right inputs
-    pub fn get_datatype(&self, l: &Expr, _r: &Expr, schema: &Schema) -> DataType {
+    pub fn get_datatype(&self, l: &Expr, _r: &Expr, schema: &Schema) -> Result<DataType> {
         //TODO: implement correctly, just go with left side for now
-        l.get_type(schema).clone()
+        l.get_type(schema)
+    }
+
+    /// Get the decimal operation for an arithmetic operator that has one
+    pub fn decimal_operator(&self) -> Option<DecimalOperator> {
+        match self {
+            Operator::Plus => Some(DecimalOperator::Add),
+            Operator::Minus => Some(DecimalOperator::Subtract),
+            Operator::Multiply => Some(DecimalOperator::Multiply),
+            Operator::Divide => Some(DecimalOperator::Divide),
+            _ => None,
+        }
+    }
+}
+
+/// A reference to a column by name, optionally qualified with the name of the relation that
+/// the column belongs to (e.g. `t1.id`)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Column {
+    pub relation: Option<String>,
+    pub name: String,
+}
+
+impl Column {
+    /// Create an unqualified column reference
+    pub fn from_name(name: &str) -> Self {
+        Column {
+            relation: None,
+            name: name.to_string(),
+        }
+    }
+
+    /// Create a column reference qualified with a relation name
+    pub fn qualified(relation: &str, name: &str) -> Self {
+        Column {
+            relation: Some(relation.to_string()),
+            name: name.to_string(),
+        }
+    }
+
+    /// Create a column reference from a name that may be qualified e.g. `t1.id`. The name is
+    /// split on the last dot that is not inside double quotes, so `t1."a.b"` is column `a.b` of
+    /// relation `t1`.
+    pub fn from_qualified_name(flat_name: &str) -> Self {
+        let mut in_quotes = false;
+        let mut split = None;
+        for (i, c) in flat_name.char_indices() {
+            match c {
+                '"' => in_quotes = !in_quotes,
+                '.' if !in_quotes => split = Some(i),
+                _ => {}
+            }
+        }
+        match split {
+            Some(i) => Column::qualified(
+                &unquote_name_part(&flat_name[0..i]),
+                &unquote_name_part(&flat_name[i + 1..]),
+            ),
+            None => Column::from_name(flat_name),
+        }
+    }
+
+    /// Get the name of the column, including the relation qualifier if there is one
+    pub fn flat_name(&self) -> String {
+        match self.relation {
+            Some(ref r) => qualified_name(r, &self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    /// Resolve this column reference to the index of a field in the schema. An unqualified
+    /// name will match a qualified field as long as only one field has that name.
+    pub fn index_of(&self, schema: &Schema) -> Result<usize> {
+        let flat_name = self.flat_name();
+        let mut matches: Vec<usize> = schema
+            .columns()
+            .iter()
+            .enumerate()
+            .filter(|&(_, f)| f.name() == &flat_name)
+            .map(|(i, _)| i)
+            .collect();
+        if matches.is_empty() && self.relation.is_none() {
+            let suffix = format!(".{}", quote_name_part(&self.name));
+            matches = schema
+                .columns()
+                .iter()
+                .enumerate()
+                .filter(|&(_, f)| f.name().ends_with(&suffix))
+                .map(|(i, _)| i)
+                .collect();
+        }
+        match matches.len() {
+            1 => Ok(matches[0]),
+            0 => {
+                let names: Vec<String> =
+                    schema.columns().iter().map(|f| f.name().clone()).collect();
+                Err(ExecutionError::column_not_found(&flat_name, &names))
+            }
+            _ => Err(ExecutionError::AmbiguousColumn {
+                name: flat_name,
+                candidates: matches
+                    .iter()
+                    .map(|i| schema.column(*i).name().clone())
+                    .collect(),
+            }),
+        }
+    }
+}
+
+/// Create an expression that references a column by a name that may be qualified e.g. `t1.id`
+pub fn col(name: &str) -> Expr {
+    Expr::ColumnRef(Column::from_qualified_name(name))
+}
+
+/// Create a literal expression
+pub fn lit(value: ScalarValue) -> Expr {
+    Expr::Literal(value)
+}
+
+/// Join a relation name and a column name into a qualified name e.g. `t1.id`. A part that
+/// contains a dot or a double quote is quoted so that the name can be split again.
+pub fn qualified_name(relation: &str, name: &str) -> String {
+    format!("{}.{}", quote_name_part(relation), quote_name_part(name))
+}
+
+fn quote_name_part(part: &str) -> String {
+    if part.contains('.') || part.contains('"') {
+        format!("\"{}\"", part.replace("\"", "\"\""))
+    } else {
+        part.to_string()
+    }
+}
+
+fn unquote_name_part(part: &str) -> String {
+    if part.len() >= 2 && part.starts_with('"') && part.ends_with('"') {
+        part[1..part.len() - 1].replace("\"\"", "\"")
+    } else {
+        part.to_string()
+    }
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.flat_name())
     }
 }
 
+/// Built-in functions on list values
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArrayFunction {
+    /// number of elements in a list, e.g. `array_length(tags)`
+    Length,
+    /// whether a list contains a value, e.g. `array_contains(tags, 'x')`
+    Contains,
+    /// element of a list at a one-based index, e.g. `tags[1]`
+    Element,
+}
+
 /// Relation Expression
 #[derive(Clone, PartialEq)]
 pub enum Expr {
     /// index into a value within the row or complex value
     Column(usize),
+    /// column reference by (optionally qualified) name, which is resolved to a `Column` index
+    /// against the input schema during physical planning
+    ColumnRef(Column),
     /// literal value
     Literal(ScalarValue),
+    /// parameter of a prepared statement, identified by its placeholder e.g. `$1` or `:name`,
+    /// which is replaced with a literal value when the statement is executed
+    Parameter { name: String, data_type: DataType },
     /// binary expression e.g. "age > 21"
     BinaryExpr {
         left: Rc<Expr>,
@@ -106,8 +276,12 @@ pub enum Expr {
     IsNotNull(Rc<Expr>),
     /// unary IS NULL
     IsNull(Rc<Expr>),
+    /// logical NOT, which is null when its operand is null
+    Not(Rc<Expr>),
     /// cast a value to a different type
     Cast { expr: Rc<Expr>, data_type: DataType },
+    /// cast a value to a different type, or to null if it cannot be converted
+    TryCast { expr: Rc<Expr>, data_type: DataType },
     /// sort expression
     Sort { expr: Rc<Expr>, asc: bool },
     /// scalar function
@@ -122,32 +296,84 @@ pub enum Expr {
         args: Vec<Expr>,
         return_type: DataType,
     },
+    /// built-in function on lists, where the first argument is the list
+    ArrayFunction { fun: ArrayFunction, args: Vec<Expr> },
+    /// field of a struct by name e.g. "location.lat"
+    GetField { expr: Rc<Expr>, name: String },
+    /// struct with a named field for each argument e.g. "STRUCT(lat, lng AS longitude)"
+    Struct { names: Vec<String>, args: Vec<Expr> },
 }
 
 impl Expr {
-    pub fn get_type(&self, schema: &Schema) -> DataType {
+    pub fn get_type(&self, schema: &Schema) -> Result<DataType> {
         match self {
-            Expr::Column(n) => schema.column(*n).data_type().clone(),
+            Expr::Column(n) => if *n < schema.columns().len() {
+                Ok(schema.column(*n).data_type().clone())
+            } else {
+                Err(ExecutionError::plan(format!(
+                    "Column index {} is out of bounds for schema {}",
+                    n,
+                    schema.to_string()
+                )))
+            },
+            Expr::ColumnRef(c) => Ok(schema.column(c.index_of(schema)?).data_type().clone()),
             Expr::Literal(l) => l.get_datatype(),
-            Expr::Cast { data_type, .. } => data_type.clone(),
-            Expr::ScalarFunction { return_type, .. } => return_type.clone(),
-            Expr::AggregateFunction { return_type, .. } => return_type.clone(),
-            Expr::IsNull(_) => DataType::Boolean,
-            Expr::IsNotNull(_) => DataType::Boolean,
+            Expr::Parameter { data_type, .. } => Ok(data_type.clone()),
+            Expr::Cast { data_type, .. } | Expr::TryCast { data_type, .. } => {
+                Ok(data_type.clone())
+            }
+            Expr::ScalarFunction { return_type, .. } => Ok(return_type.clone()),
+            Expr::AggregateFunction { return_type, .. } => Ok(return_type.clone()),
+            Expr::ArrayFunction { fun, args } => match fun {
+                ArrayFunction::Length => Ok(DataType::UInt64),
+                ArrayFunction::Contains => Ok(DataType::Boolean),
+                ArrayFunction::Element => match args[0].get_type(schema)? {
+                    DataType::List(t) => Ok(*t),
+                    other => Err(ExecutionError::type_error(
+                        format!("Cannot index into a value of type {:?}", other),
+                        self,
+                    )),
+                },
+            },
+            Expr::GetField { expr, name } => match struct_field(&expr.get_type(schema)?, name) {
+                Ok((_, field)) => Ok(field.data_type().clone()),
+                Err(ExecutionError::TypeError { message, .. }) => {
+                    Err(ExecutionError::type_error(message, self))
+                }
+                Err(e) => Err(e),
+            },
+            Expr::Struct { names, args } => {
+                let fields = names
+                    .iter()
+                    .zip(args)
+                    .map(|(name, arg)| Ok(Field::new(name, arg.get_type(schema)?, true)))
+                    .collect::<Result<Vec<Field>>>()?;
+                Ok(DataType::Struct(fields))
+            }
+            Expr::IsNull(_) => Ok(DataType::Boolean),
+            Expr::IsNotNull(_) => Ok(DataType::Boolean),
+            Expr::Not(_) => Ok(DataType::Boolean),
             Expr::BinaryExpr {
                 ref left,
                 ref right,
                 ref op,
             } => {
                 match op {
-                    Operator::Eq | Operator::NotEq => DataType::Boolean,
-                    Operator::Lt | Operator::LtEq => DataType::Boolean,
-                    Operator::Gt | Operator::GtEq => DataType::Boolean,
-                    Operator::And | Operator::Or => DataType::Boolean,
+                    Operator::Eq | Operator::NotEq => Ok(DataType::Boolean),
+                    Operator::Lt | Operator::LtEq => Ok(DataType::Boolean),
+                    Operator::Gt | Operator::GtEq => Ok(DataType::Boolean),
+                    Operator::And | Operator::Or => Ok(DataType::Boolean),
+                    Operator::StringConcat => Ok(DataType::Utf8),
                     _ => {
-                        let left_type = left.get_type(schema);
-                        let right_type = right.get_type(schema);
-                        get_supertype(&left_type, &right_type).unwrap_or(DataType::Utf8) //TODO ???
+                        let left_type = left.get_type(schema)?;
+                        let right_type = right.get_type(schema)?;
+                        let decimal_type = op.decimal_operator().and_then(|op| {
+                            get_decimal_arithmetic_type(op, &left_type, &right_type)
+                        });
+                        Ok(decimal_type
+                            .or_else(|| get_interval_arithmetic_type(&left_type, &right_type))
+                            .or_else(|| get_supertype(&left_type, &right_type))
+                            .unwrap_or(DataType::Utf8)) //TODO ???
                     }
                 }
             }
@@ -155,8 +381,8 @@ impl Expr {
         }
     }
 
-    pub fn cast_to(&self, cast_to_type: &DataType, schema: &Schema) -> Result<Expr, String> {
-        let this_type = self.get_type(schema);
+    pub fn cast_to(&self, cast_to_type: &DataType, schema: &Schema) -> Result<Expr> {
+        let this_type = self.get_type(schema)?;
         if this_type == *cast_to_type {
             Ok(self.clone())
         } else if can_coerce_from(cast_to_type, &this_type) {
@@ -165,13 +391,106 @@ impl Expr {
                 data_type: cast_to_type.clone(),
             })
         } else {
-            Err(format!(
-                "Cannot automatically convert {:?} to {:?}",
-                this_type, cast_to_type
+            Err(ExecutionError::type_error(
+                format!(
+                    "Cannot automatically convert {:?} to {:?}",
+                    this_type, cast_to_type
+                ),
+                self,
             ))
         }
     }
 
+    /// Get the direct child expressions of this expression
+    pub fn children(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Column(_) | Expr::ColumnRef(_) | Expr::Literal(_) | Expr::Parameter { .. } => {
+                vec![]
+            }
+            Expr::BinaryExpr { left, right, .. } => vec![left.as_ref(), right.as_ref()],
+            Expr::IsNull(e) | Expr::IsNotNull(e) | Expr::Not(e) => vec![e.as_ref()],
+            Expr::Cast { expr, .. }
+            | Expr::TryCast { expr, .. }
+            | Expr::Sort { expr, .. }
+            | Expr::GetField { expr, .. } => vec![expr.as_ref()],
+            Expr::ScalarFunction { args, .. }
+            | Expr::AggregateFunction { args, .. }
+            | Expr::ArrayFunction { args, .. }
+            | Expr::Struct { args, .. } => args.iter().collect(),
+        }
+    }
+
+    /// Create a copy of this expression with its children replaced. The children must be in the
+    /// same order as returned by `children()`.
+    pub fn with_new_children(&self, children: Vec<Expr>) -> Result<Expr> {
+        if children.len() != self.children().len() {
+            return Err(ExecutionError::plan_expr(
+                format!(
+                    "Expected {} children but {} were provided",
+                    self.children().len(),
+                    children.len()
+                ),
+                self,
+            ));
+        }
+        let mut children = children;
+        Ok(match self {
+            Expr::Column(_) | Expr::ColumnRef(_) | Expr::Literal(_) | Expr::Parameter { .. } => {
+                self.clone()
+            }
+            Expr::BinaryExpr { op, .. } => {
+                let right = children.pop().unwrap();
+                let left = children.pop().unwrap();
+                Expr::BinaryExpr {
+                    left: Rc::new(left),
+                    op: op.clone(),
+                    right: Rc::new(right),
+                }
+            }
+            Expr::IsNull(_) => Expr::IsNull(Rc::new(children.remove(0))),
+            Expr::IsNotNull(_) => Expr::IsNotNull(Rc::new(children.remove(0))),
+            Expr::Not(_) => Expr::Not(Rc::new(children.remove(0))),
+            Expr::Cast { data_type, .. } => Expr::Cast {
+                expr: Rc::new(children.remove(0)),
+                data_type: data_type.clone(),
+            },
+            Expr::TryCast { data_type, .. } => Expr::TryCast {
+                expr: Rc::new(children.remove(0)),
+                data_type: data_type.clone(),
+            },
+            Expr::Sort { asc, .. } => Expr::Sort {
+                expr: Rc::new(children.remove(0)),
+                asc: *asc,
+            },
+            Expr::ScalarFunction {
+                name, return_type, ..
+            } => Expr::ScalarFunction {
+                name: name.clone(),
+                args: children,
+                return_type: return_type.clone(),
+            },
+            Expr::AggregateFunction {
+                name, return_type, ..
+            } => Expr::AggregateFunction {
+                name: name.clone(),
+                args: children,
+                return_type: return_type.clone(),
+            },
+            Expr::ArrayFunction { fun, .. } => Expr::ArrayFunction {
+                fun: fun.clone(),
+                args: children,
+            },
+            Expr::GetField { name, .. } => Expr::GetField {
+                expr: Rc::new(children.remove(0)),
+                name: name.clone(),
+            },
+            Expr::Struct { names, .. } => Expr::Struct {
+                names: names.clone(),
+                args: children,
+            },
+        })
+    }
+
     pub fn eq(&self, other: &Expr) -> Expr {
         Expr::BinaryExpr {
             left: Rc::new(self.clone()),
@@ -222,13 +541,19 @@ impl Expr {
 }
 
 impl fmt::Debug for Expr {
-    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
             Expr::Column(i) => write!(f, "#{}", i),
+            Expr::ColumnRef(c) => write!(f, "#{}", c),
             Expr::Literal(v) => write!(f, "{:?}", v),
+            Expr::Parameter { name, .. } => write!(f, "{}", name),
             Expr::Cast { expr, data_type } => write!(f, "CAST({:?} AS {:?})", expr, data_type),
+            Expr::TryCast { expr, data_type } => {
+                write!(f, "TRY_CAST({:?} AS {:?})", expr, data_type)
+            }
             Expr::IsNull(expr) => write!(f, "{:?} IS NULL", expr),
             Expr::IsNotNull(expr) => write!(f, "{:?} IS NOT NULL", expr),
+            Expr::Not(expr) => write!(f, "NOT {:?}", expr),
             Expr::BinaryExpr { left, op, right } => write!(f, "{:?} {:?} {:?}", left, op, right),
             Expr::Sort { expr, asc } => if *asc {
                 write!(f, "{:?} ASC", expr)
@@ -257,10 +582,138 @@ impl fmt::Debug for Expr {
 
                 write!(f, ")")
             }
+            Expr::ArrayFunction { fun, ref args } => match fun {
+                ArrayFunction::Length => write!(f, "array_length({:?})", args[0]),
+                ArrayFunction::Contains => {
+                    write!(f, "array_contains({:?}, {:?})", args[0], args[1])
+                }
+                ArrayFunction::Element => write!(f, "{:?}[{:?}]", args[0], args[1]),
+            },
+            Expr::GetField { expr, name } => write!(f, "{:?}.{}", expr, name),
+            Expr::Struct { names, ref args } => {
+                write!(f, "struct(")?;
+                for i in 0..args.len() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:?} AS {}", args[i], names[i])?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+/// Find a field of a struct type by name, returning its index and definition
+pub fn struct_field<'a>(data_type: &'a DataType, name: &str) -> Result<(usize, &'a Field)> {
+    match data_type {
+        DataType::Struct(fields) => fields
+            .iter()
+            .enumerate()
+            .find(|(_, f)| f.name() == name)
+            .ok_or_else(|| {
+                ExecutionError::type_mismatch(format!("Struct has no field named '{}'", name))
+            }),
+        other => Err(ExecutionError::type_mismatch(format!(
+            "Cannot access field '{}' of a value of type {:?}",
+            name, other
+        ))),
+    }
+}
+
+/// Rewrites column references by name to column references by index into a schema
+struct ColumnResolver<'a> {
+    schema: &'a Schema,
+}
+
+impl<'a> ExprRewriter for ColumnResolver<'a> {
+    fn mutate(&mut self, expr: Expr) -> Result<Expr> {
+        match expr {
+            Expr::ColumnRef(c) => Ok(Expr::Column(c.index_of(self.schema)?)),
+            other => Ok(other),
+        }
+    }
+}
+
+/// Replace any column references by name with column references by index into the schema
+pub fn resolve_columns(expr: &Expr, schema: &Schema) -> Result<Expr> {
+    rewrite_expr(expr, &mut ColumnResolver { schema })
+}
+
+/// Replaces parameters with literal values
+struct ParameterBinder<'a> {
+    values: &'a [(String, ScalarValue)],
+}
+
+impl<'a> ExprRewriter for ParameterBinder<'a> {
+    fn mutate(&mut self, expr: Expr) -> Result<Expr> {
+        match expr {
+            Expr::Parameter {
+                ref name,
+                ref data_type,
+            } => match self.values.iter().find(|v| v.0 == *name) {
+                // a null keeps the type of the parameter, which the rest of the plan expects
+                Some((_, ScalarValue::Null)) => Ok(Expr::Cast {
+                    expr: Rc::new(Expr::Literal(ScalarValue::Null)),
+                    data_type: data_type.clone(),
+                }),
+                Some((_, value)) => Ok(Expr::Literal(value.clone())),
+                None => Err(ExecutionError::plan(format!(
+                    "No value has been bound to parameter {}",
+                    name
+                ))),
+            },
+            other => Ok(other),
+        }
+    }
+}
+
+impl<'a> PlanRewriter for ParameterBinder<'a> {
+    fn mutate(&mut self, plan: Rc<LogicalPlan>) -> Result<Rc<LogicalPlan>> {
+        if plan.expressions().is_empty() {
+            return Ok(plan);
         }
+        let mut expr = vec![];
+        for e in plan.expressions() {
+            expr.push(rewrite_expr(e, self)?);
+        }
+        Ok(Rc::new(plan.with_new_expressions(expr)?))
     }
 }
 
+/// Replace the parameters in a plan with literal values, given as pairs of parameter names
+/// (e.g. `$1` or `:name`) and values. The values must have the same types as the parameters.
+pub fn bind_parameters(
+    plan: &Rc<LogicalPlan>,
+    values: &[(String, ScalarValue)],
+) -> Result<Rc<LogicalPlan>> {
+    rewrite_plan(plan, &mut ParameterBinder { values })
+}
+
+/// Create a copy of a schema where each field name is qualified with the relation name
+pub fn qualify_schema(schema: &Schema, relation: &str) -> Schema {
+    Schema::new(
+        schema
+            .columns()
+            .iter()
+            .map(|f| {
+                Field::new(
+                    &qualified_name(relation, f.name()),
+                    f.data_type().clone(),
+                    f.is_nullable(),
+                )
+            })
+            .collect(),
+    )
+}
+
+/// Join type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinType {
+    /// Inner join, returning only rows where the join keys match on both sides
+    Inner,
+}
+
 /// The LogicalPlan represents different types of relations (such as Projection, Selection, etc) and
 /// can be created by the SQL query planner and the DataFrame API.
 #[derive(Clone)]
@@ -292,12 +745,36 @@ pub enum LogicalPlan {
         input: Rc<LogicalPlan>,
         schema: Rc<Schema>,
     },
+    /// Joins two relations on one or more pairs of equal columns. The output schema contains
+    /// the fields of the left input followed by the fields of the right input.
+    Join {
+        left: Rc<LogicalPlan>,
+        right: Rc<LogicalPlan>,
+        join_type: JoinType,
+        on: Vec<(Column, Column)>,
+        schema: Rc<Schema>,
+    },
+    /// Produces a row for each element of a list expression, with the columns of the input row
+    /// followed by the element. Rows with null or empty lists produce no rows.
+    Unnest {
+        input: Rc<LogicalPlan>,
+        expr: Expr,
+        schema: Rc<Schema>,
+    },
+    /// Concatenates the rows of relations that have the same schema (UNION ALL)
+    Union {
+        inputs: Vec<Rc<LogicalPlan>>,
+        schema: Rc<Schema>,
+    },
     /// A table scan against a table that has been registered on a context
     TableScan {
         schema_name: String,
         table_name: String,
         schema: Rc<Schema>,
         projection: Option<Vec<usize>>,
+        /// Paths of the struct fields that are read, starting with the column name e.g.
+        /// `["location", "lat"]`. Struct columns without any paths are read in full.
+        fields: Vec<Vec<String>>,
     },
     /// Represents a CSV file with a provided schema
     CsvFile {
@@ -317,9 +794,34 @@ pub enum LogicalPlan {
         filename: String,
         schema: Rc<Schema>,
         projection: Option<Vec<usize>>,
+        /// Paths of the struct fields that are read, as for `TableScan`
+        fields: Vec<Vec<String>>,
     },
     /// An empty relation with an empty schema
     EmptyRelation { schema: Rc<Schema> },
+    /// Produces the plans of a query as text, one row per line (EXPLAIN)
+    Explain {
+        verbose: bool,
+        plans: Vec<StringifiedPlan>,
+        schema: Rc<Schema>,
+    },
+}
+
+/// A plan in text form, as shown by EXPLAIN
+#[derive(Debug, Clone)]
+pub struct StringifiedPlan {
+    /// The stage the plan was captured at, e.g. `logical_plan`
+    pub plan_type: String,
+    pub plan: String,
+}
+
+impl StringifiedPlan {
+    pub fn new(plan_type: &str, plan: String) -> Self {
+        StringifiedPlan {
+            plan_type: plan_type.to_string(),
+            plan,
+        }
+    }
 }
 
 impl LogicalPlan {
@@ -336,25 +838,190 @@ impl LogicalPlan {
             LogicalPlan::Aggregate { schema, .. } => &schema,
             LogicalPlan::Sort { schema, .. } => &schema,
             LogicalPlan::Limit { schema, .. } => &schema,
+            LogicalPlan::Join { schema, .. } => &schema,
+            LogicalPlan::Union { schema, .. } => &schema,
+            LogicalPlan::Unnest { schema, .. } => &schema,
+            LogicalPlan::Explain { schema, .. } => &schema,
         }
     }
-}
 
-impl LogicalPlan {
-    fn fmt_with_indent(&self, f: &mut Formatter, indent: usize) -> Result<(), Error> {
-        if indent > 0 {
-            writeln!(f)?;
-            for _ in 0..indent {
-                write!(f, "  ")?;
+    /// Get the inputs of this plan node
+    pub fn inputs(&self) -> Vec<&Rc<LogicalPlan>> {
+        match self {
+            LogicalPlan::Projection { input, .. }
+            | LogicalPlan::Selection { input, .. }
+            | LogicalPlan::Aggregate { input, .. }
+            | LogicalPlan::Sort { input, .. }
+            | LogicalPlan::Limit { input, .. }
+            | LogicalPlan::Unnest { input, .. } => vec![input],
+            LogicalPlan::Join { left, right, .. } => vec![left, right],
+            LogicalPlan::Union { inputs, .. } => inputs.iter().collect(),
+            LogicalPlan::TableScan { .. }
+            | LogicalPlan::CsvFile { .. }
+            | LogicalPlan::NdJsonFile { .. }
+            | LogicalPlan::ParquetFile { .. }
+            | LogicalPlan::EmptyRelation { .. }
+            | LogicalPlan::Explain { .. } => vec![],
+        }
+    }
+
+    /// Get the expressions of this plan node (not including the expressions of its inputs)
+    pub fn expressions(&self) -> Vec<&Expr> {
+        match self {
+            LogicalPlan::Projection { expr, .. } | LogicalPlan::Sort { expr, .. } => {
+                expr.iter().collect()
+            }
+            LogicalPlan::Selection { expr, .. } | LogicalPlan::Unnest { expr, .. } => vec![expr],
+            LogicalPlan::Aggregate {
+                group_expr,
+                aggr_expr,
+                ..
+            } => group_expr.iter().chain(aggr_expr.iter()).collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Create a copy of this plan node with its inputs replaced. The inputs must be in the same
+    /// order as returned by `inputs()`. Schemas are not recomputed, so the new inputs must
+    /// produce the same schema as the inputs they replace.
+    pub fn with_new_inputs(&self, inputs: Vec<Rc<LogicalPlan>>) -> Result<LogicalPlan> {
+        if inputs.len() != self.inputs().len() {
+            return Err(ExecutionError::plan(format!(
+                "Plan expects {} inputs but {} were provided",
+                self.inputs().len(),
+                inputs.len()
+            )));
+        }
+        let mut inputs = inputs;
+        Ok(match self {
+            LogicalPlan::Projection { expr, schema, .. } => LogicalPlan::Projection {
+                expr: expr.clone(),
+                input: inputs.remove(0),
+                schema: schema.clone(),
+            },
+            LogicalPlan::Selection { expr, .. } => LogicalPlan::Selection {
+                expr: expr.clone(),
+                input: inputs.remove(0),
+            },
+            LogicalPlan::Aggregate {
+                group_expr,
+                aggr_expr,
+                schema,
+                ..
+            } => LogicalPlan::Aggregate {
+                input: inputs.remove(0),
+                group_expr: group_expr.clone(),
+                aggr_expr: aggr_expr.clone(),
+                schema: schema.clone(),
+            },
+            LogicalPlan::Sort { expr, schema, .. } => LogicalPlan::Sort {
+                expr: expr.clone(),
+                input: inputs.remove(0),
+                schema: schema.clone(),
+            },
+            LogicalPlan::Limit { limit, schema, .. } => LogicalPlan::Limit {
+                limit: *limit,
+                input: inputs.remove(0),
+                schema: schema.clone(),
+            },
+            LogicalPlan::Join {
+                join_type,
+                on,
+                schema,
+                ..
+            } => {
+                let right = inputs.pop().unwrap();
+                let left = inputs.pop().unwrap();
+                LogicalPlan::Join {
+                    left,
+                    right,
+                    join_type: *join_type,
+                    on: on.clone(),
+                    schema: schema.clone(),
+                }
             }
+            LogicalPlan::Union { schema, .. } => LogicalPlan::Union {
+                inputs,
+                schema: schema.clone(),
+            },
+            LogicalPlan::Unnest { expr, schema, .. } => LogicalPlan::Unnest {
+                input: inputs.remove(0),
+                expr: expr.clone(),
+                schema: schema.clone(),
+            },
+            _ => self.clone(),
+        })
+    }
+
+    /// Create a copy of this plan node with its expressions replaced. The expressions must be in
+    /// the same order as returned by `expressions()`. Schemas are not recomputed, so the new
+    /// expressions must have the same types as the expressions they replace.
+    pub fn with_new_expressions(&self, expr: Vec<Expr>) -> Result<LogicalPlan> {
+        if expr.len() != self.expressions().len() {
+            return Err(ExecutionError::plan(format!(
+                "Plan expects {} expressions but {} were provided",
+                self.expressions().len(),
+                expr.len()
+            )));
         }
+        let mut expr = expr;
+        Ok(match self {
+            LogicalPlan::Projection { input, schema, .. } => LogicalPlan::Projection {
+                expr,
+                input: input.clone(),
+                schema: schema.clone(),
+            },
+            LogicalPlan::Selection { input, .. } => LogicalPlan::Selection {
+                expr: expr.remove(0),
+                input: input.clone(),
+            },
+            LogicalPlan::Aggregate {
+                input,
+                group_expr,
+                schema,
+                ..
+            } => {
+                let aggr_expr = expr.split_off(group_expr.len());
+                LogicalPlan::Aggregate {
+                    input: input.clone(),
+                    group_expr: expr,
+                    aggr_expr,
+                    schema: schema.clone(),
+                }
+            }
+            LogicalPlan::Sort { input, schema, .. } => LogicalPlan::Sort {
+                expr,
+                input: input.clone(),
+                schema: schema.clone(),
+            },
+            LogicalPlan::Unnest { input, schema, .. } => LogicalPlan::Unnest {
+                input: input.clone(),
+                expr: expr.remove(0),
+                schema: schema.clone(),
+            },
+            _ => self.clone(),
+        })
+    }
+}
+
+impl LogicalPlan {
+    /// Format a one line description of this plan node, without its inputs
+    pub fn fmt_node(&self, f: &mut Formatter) -> fmt::Result {
         match *self {
             LogicalPlan::EmptyRelation { .. } => write!(f, "EmptyRelation"),
             LogicalPlan::TableScan {
                 ref table_name,
                 ref projection,
+                ref fields,
                 ..
-            } => write!(f, "TableScan: {} projection={:?}", table_name, projection),
+            } => {
+                write!(f, "TableScan: {} projection={:?}", table_name, projection)?;
+                if !fields.is_empty() {
+                    let paths: Vec<String> = fields.iter().map(|p| p.join(".")).collect();
+                    write!(f, " fields=[{}]", paths.join(", "))?;
+                }
+                Ok(())
+            }
             LogicalPlan::CsvFile {
                 ref filename,
                 ref schema,
@@ -366,11 +1033,7 @@ impl LogicalPlan {
                 ..
             } => write!(f, "NdJsonFile: file={}, schema={:?}", filename, schema),
             LogicalPlan::ParquetFile { .. } => write!(f, "ParquetFile:"),
-            LogicalPlan::Projection {
-                ref expr,
-                ref input,
-                ..
-            } => {
+            LogicalPlan::Projection { ref expr, .. } => {
                 write!(f, "Projection: ")?;
                 for i in 0..expr.len() {
                     if i > 0 {
@@ -378,34 +1041,19 @@ impl LogicalPlan {
                     }
                     write!(f, "{:?}", expr[i])?;
                 }
-                input.fmt_with_indent(f, indent + 1)
-            }
-            LogicalPlan::Selection {
-                ref expr,
-                ref input,
-                ..
-            } => {
-                write!(f, "Selection: {:?}", expr)?;
-                input.fmt_with_indent(f, indent + 1)
+                Ok(())
             }
+            LogicalPlan::Selection { ref expr, .. } => write!(f, "Selection: {:?}", expr),
             LogicalPlan::Aggregate {
-                ref input,
                 ref group_expr,
                 ref aggr_expr,
                 ..
-            } => {
-                write!(
-                    f,
-                    "Aggregate: groupBy=[{:?}], aggr=[{:?}]",
-                    group_expr, aggr_expr
-                )?;
-                input.fmt_with_indent(f, indent + 1)
-            }
-            LogicalPlan::Sort {
-                ref input,
-                ref expr,
-                ..
-            } => {
+            } => write!(
+                f,
+                "Aggregate: groupBy=[{:?}], aggr=[{:?}]",
+                group_expr, aggr_expr
+            ),
+            LogicalPlan::Sort { ref expr, .. } => {
                 write!(f, "Sort: ")?;
                 for i in 0..expr.len() {
                     if i > 0 {
@@ -413,20 +1061,93 @@ impl LogicalPlan {
                     }
                     write!(f, "{:?}", expr[i])?;
                 }
-                input.fmt_with_indent(f, indent + 1)
+                Ok(())
             }
-            LogicalPlan::Limit {
-                ref input, limit, ..
+            LogicalPlan::Limit { limit, .. } => write!(f, "Limit: {}", limit),
+            LogicalPlan::Join {
+                ref join_type,
+                ref on,
+                ..
             } => {
-                write!(f, "Limit: {}", limit)?;
-                input.fmt_with_indent(f, indent + 1)
+                write!(f, "Join: type={:?}, on=[", join_type)?;
+                for i in 0..on.len() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "#{} = #{}", on[i].0, on[i].1)?;
+                }
+                write!(f, "]")
+            }
+            LogicalPlan::Union { .. } => write!(f, "Union"),
+            LogicalPlan::Unnest { ref expr, .. } => write!(f, "Unnest: {:?}", expr),
+            LogicalPlan::Explain { verbose, .. } => if verbose {
+                write!(f, "Explain: verbose")
+            } else {
+                write!(f, "Explain")
+            },
+        }
+    }
+}
+
+impl LogicalPlan {
+    /// Render the plan as a Graphviz DOT graph, with a node for each plan node labelled with
+    /// its expressions and schema
+    pub fn to_dot(&self) -> String {
+        let mut graph = DotGraph::new("LogicalPlan");
+        self.add_dot_node(&mut graph);
+        graph.to_dot()
+    }
+
+    fn add_dot_node(&self, graph: &mut DotGraph) -> usize {
+        let id = graph.add_node(&[
+            format!("{}", PlanNode(self)),
+            format!("Schema: [{}]", self.schema()),
+        ]);
+        for input in self.inputs() {
+            let input_id = input.add_dot_node(graph);
+            graph.add_edge(id, input_id);
+        }
+        id
+    }
+}
+
+/// Displays a single plan node, without its inputs
+struct PlanNode<'a>(&'a LogicalPlan);
+
+impl<'a> fmt::Display for PlanNode<'a> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.0.fmt_node(f)
+    }
+}
+
+/// Formats each node of a plan on its own line, indenting inputs below their parent
+struct IndentVisitor<'a, 'b: 'a> {
+    f: &'a mut Formatter<'b>,
+    indent: usize,
+}
+
+impl<'a, 'b> PlanVisitor for IndentVisitor<'a, 'b> {
+    fn pre_visit(&mut self, plan: &LogicalPlan) -> Result<Recursion> {
+        if self.indent > 0 {
+            writeln!(self.f).map_err(|e| e.to_string())?;
+            for _ in 0..self.indent {
+                write!(self.f, "  ").map_err(|e| e.to_string())?;
             }
         }
+        plan.fmt_node(self.f).map_err(|e| e.to_string())?;
+        self.indent += 1;
+        Ok(Recursion::Continue)
+    }
+
+    fn post_visit(&mut self, _plan: &LogicalPlan) -> Result<Recursion> {
+        self.indent -= 1;
+        Ok(Recursion::Continue)
     }
 }
 
 impl fmt::Debug for LogicalPlan {
-    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        self.fmt_with_indent(f, 0)
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let mut visitor = IndentVisitor { f, indent: 0 };
+        self.accept(&mut visitor).map(|_| ()).map_err(|_| fmt::Error)
     }
 }
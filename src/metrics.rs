@@ -0,0 +1,197 @@
+// Copyright 2018 Grove Enterprises LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Runtime metrics collected while executing relations
+
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use super::datasources::common::*;
+use super::datatypes::Schema;
+use super::errors::*;
+use super::exec::SimpleRelation;
+
+/// Metrics for a single relation
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RelationMetrics {
+    /// Number of rows produced by the inputs of the relation
+    pub input_rows: usize,
+    /// Number of batches produced by the inputs of the relation
+    pub input_batches: usize,
+    /// Number of rows produced by the relation
+    pub output_rows: usize,
+    /// Number of batches produced by the relation
+    pub output_batches: usize,
+    /// Time spent producing batches, including the time spent in the inputs
+    pub elapsed: Duration,
+    /// Time spent producing batches, excluding the time spent in the inputs
+    pub elapsed_compute: Duration,
+    /// Number of bytes read from files (data sources only)
+    pub bytes_read: usize,
+    /// Number of times intermediate state was spilled to disk
+    pub spill_count: usize,
+}
+
+fn format_duration(d: &Duration) -> String {
+    let millis = d.as_secs() as f64 * 1000.0 + d.subsec_nanos() as f64 / 1000000.0;
+    format!("{:.3}ms", millis)
+}
+
+impl fmt::Display for RelationMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "output_rows={}, output_batches={}, input_rows={}, input_batches={}, \
+             elapsed_compute={}, bytes_read={}, spill_count={}",
+            self.output_rows,
+            self.output_batches,
+            self.input_rows,
+            self.input_batches,
+            format_duration(&self.elapsed_compute),
+            self.bytes_read,
+            self.spill_count
+        )
+    }
+}
+
+/// The metrics for a tree of relations, captured after execution
+#[derive(Debug, Clone)]
+pub struct PlanMetrics {
+    /// Description of the relation, as shown by EXPLAIN
+    pub relation: String,
+    pub metrics: RelationMetrics,
+    pub inputs: Vec<PlanMetrics>,
+}
+
+impl PlanMetrics {
+    /// Capture the current metrics for a relation and its inputs
+    pub fn from_relation(relation: &SimpleRelation) -> Self {
+        PlanMetrics {
+            relation: relation.describe(),
+            metrics: relation.metrics(),
+            inputs: relation
+                .inputs()
+                .iter()
+                .map(|input| PlanMetrics::from_relation(*input))
+                .collect(),
+        }
+    }
+
+    fn fmt_indent(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        if indent > 0 {
+            write!(f, "\n")?;
+        }
+        write!(
+            f,
+            "{}{}, metrics=[{}]",
+            "  ".repeat(indent),
+            self.relation,
+            self.metrics
+        )?;
+        for input in &self.inputs {
+            input.fmt_indent(f, indent + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for PlanMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_indent(f, 0)
+    }
+}
+
+/// Wraps a relation and records the rows, batches and time spent producing its output
+pub struct MetricsRelation {
+    input: Box<SimpleRelation>,
+    metrics: Rc<RefCell<RelationMetrics>>,
+}
+
+impl MetricsRelation {
+    pub fn new(input: Box<SimpleRelation>) -> Self {
+        MetricsRelation {
+            input,
+            metrics: Rc::new(RefCell::new(RelationMetrics::default())),
+        }
+    }
+}
+
+struct MetricsIterator<'a> {
+    input: Box<Iterator<Item = Result<Rc<RecordBatch>>> + 'a>,
+    metrics: Rc<RefCell<RelationMetrics>>,
+}
+
+impl<'a> Iterator for MetricsIterator<'a> {
+    type Item = Result<Rc<RecordBatch>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = Instant::now();
+        let batch = self.input.next();
+        let elapsed = start.elapsed();
+
+        let mut metrics = self.metrics.borrow_mut();
+        metrics.elapsed += elapsed;
+        if let Some(Ok(ref b)) = batch {
+            metrics.output_rows += b.num_rows();
+            metrics.output_batches += 1;
+        }
+        batch
+    }
+}
+
+impl SimpleRelation for MetricsRelation {
+    fn scan<'a>(&'a mut self) -> Box<Iterator<Item = Result<Rc<RecordBatch>>> + 'a> {
+        Box::new(MetricsIterator {
+            input: self.input.scan(),
+            metrics: self.metrics.clone(),
+        })
+    }
+
+    fn schema<'a>(&'a self) -> &'a Schema {
+        self.input.schema()
+    }
+
+    fn describe(&self) -> String {
+        self.input.describe()
+    }
+
+    fn inputs(&self) -> Vec<&SimpleRelation> {
+        self.input.inputs()
+    }
+
+    fn metrics(&self) -> RelationMetrics {
+        let mut metrics = self.metrics.borrow().clone();
+
+        // metrics reported by the wrapped relation itself, such as bytes read or spills
+        let own_metrics = self.input.metrics();
+        metrics.bytes_read = own_metrics.bytes_read;
+        metrics.spill_count = own_metrics.spill_count;
+
+        let mut input_elapsed = Duration::new(0, 0);
+        for input in self.inputs() {
+            let input_metrics = input.metrics();
+            metrics.input_rows += input_metrics.output_rows;
+            metrics.input_batches += input_metrics.output_batches;
+            input_elapsed += input_metrics.elapsed;
+        }
+        metrics.elapsed_compute = if metrics.elapsed > input_elapsed {
+            metrics.elapsed - input_elapsed
+        } else {
+            Duration::new(0, 0)
+        };
+        metrics
+    }
+}
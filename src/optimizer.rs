@@ -0,0 +1,741 @@
+// Copyright 2018 Grove Enterprises LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Logical plan optimizer rules
+
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use super::costmodel::CostModel;
+use super::datatypes::{Field, Schema};
+use super::errors::*;
+use super::logical::*;
+use super::planbuilder::LogicalPlanBuilder;
+use super::sqlplanner::{exprlist_to_fields, push_down_projection, SchemaProvider};
+use super::visitor::*;
+
+/// A rule that rewrites a logical plan into an equivalent plan that is cheaper to execute
+pub trait OptimizerRule {
+    /// A short name for the rule
+    fn name(&self) -> &str;
+
+    /// Rewrite the plan, returning the original plan if the rule does not apply
+    fn optimize(&mut self, plan: &Rc<LogicalPlan>) -> Result<Rc<LogicalPlan>>;
+}
+
+/// Reorders chains of inner joins so that the smallest relations are joined first, based on the
+/// row counts estimated by the cost model. Join chains are only reordered when statistics are
+/// available for every relation in the chain, and a projection is added to restore the original
+/// column order.
+pub struct JoinReorder {
+    cost_model: CostModel,
+}
+
+impl JoinReorder {
+    pub fn new(cost_model: CostModel) -> Self {
+        JoinReorder { cost_model }
+    }
+
+    fn optimize_plan(&self, plan: &Rc<LogicalPlan>) -> Result<Rc<LogicalPlan>> {
+        match plan.as_ref() {
+            LogicalPlan::Join { .. } => {
+                let mut relations: Vec<Rc<LogicalPlan>> = vec![];
+                let mut conditions: Vec<(Column, Column)> = vec![];
+                flatten_joins(plan, &mut relations, &mut conditions);
+                let relations = relations
+                    .iter()
+                    .map(|r| self.optimize_plan(r))
+                    .collect::<Result<Vec<Rc<LogicalPlan>>>>()?;
+
+                match self.reorder(&relations, &conditions) {
+                    Some(reordered) => Ok(reordered),
+                    None => self.optimize_inputs(plan),
+                }
+            }
+            _ => self.optimize_inputs(plan),
+        }
+    }
+
+    fn optimize_inputs(&self, plan: &Rc<LogicalPlan>) -> Result<Rc<LogicalPlan>> {
+        let inputs = plan.inputs();
+        if inputs.is_empty() {
+            return Ok(plan.clone());
+        }
+        let new_inputs = inputs
+            .iter()
+            .map(|input| self.optimize_plan(input))
+            .collect::<Result<Vec<Rc<LogicalPlan>>>>()?;
+        Ok(Rc::new(plan.with_new_inputs(new_inputs)?))
+    }
+
+    /// Greedily build a left-deep join tree, starting with the smallest relation and then
+    /// joining whichever connected relation produces the smallest intermediate result. Returns
+    /// `None` if the order does not change or cannot be determined.
+    fn reorder(
+        &self,
+        relations: &Vec<Rc<LogicalPlan>>,
+        conditions: &Vec<(Column, Column)>,
+    ) -> Option<Rc<LogicalPlan>> {
+        if relations.len() < 3 {
+            return None;
+        }
+
+        let mut rows: Vec<usize> = vec![];
+        for r in relations {
+            rows.push(self.cost_model.estimate_rows(r)?);
+        }
+
+        // find the relation that each side of each join condition refers to
+        let mut edges: Vec<(usize, usize)> = vec![];
+        for (l, r) in conditions {
+            edges.push((relation_of(relations, l)?, relation_of(relations, r)?));
+        }
+
+        let mut order: Vec<usize> = vec![(0..relations.len()).min_by_key(|i| rows[*i])?];
+        let mut builder = LogicalPlanBuilder::from(&relations[order[0]]);
+        while order.len() < relations.len() {
+            let mut best: Option<(usize, usize, LogicalPlanBuilder)> = None;
+            for candidate in 0..relations.len() {
+                if order.contains(&candidate) {
+                    continue;
+                }
+                let mut left_keys: Vec<Column> = vec![];
+                let mut right_keys: Vec<Column> = vec![];
+                for (i, &(l, r)) in edges.iter().enumerate() {
+                    if order.contains(&l) && r == candidate {
+                        left_keys.push(conditions[i].0.clone());
+                        right_keys.push(conditions[i].1.clone());
+                    } else if order.contains(&r) && l == candidate {
+                        left_keys.push(conditions[i].1.clone());
+                        right_keys.push(conditions[i].0.clone());
+                    }
+                }
+                if left_keys.is_empty() {
+                    continue;
+                }
+                let joined = builder
+                    .join(&relations[candidate], JoinType::Inner, left_keys, right_keys)
+                    .ok()?;
+                let joined_rows = self.cost_model.estimate_rows(joined.build().ok()?.as_ref())?;
+                let is_better = match best {
+                    Some((_, best_rows, _)) => joined_rows < best_rows,
+                    None => true,
+                };
+                if is_better {
+                    best = Some((candidate, joined_rows, joined));
+                }
+            }
+
+            // give up rather than introduce a cross join
+            let (candidate, _, joined) = best?;
+            order.push(candidate);
+            builder = joined;
+        }
+
+        if order.iter().enumerate().all(|(i, r)| i == *r) {
+            return None;
+        }
+
+        // restore the original column order
+        let mut offsets: Vec<usize> = vec![0; relations.len()];
+        let mut offset = 0;
+        for r in &order {
+            offsets[*r] = offset;
+            offset += relations[*r].schema().columns().len();
+        }
+        let mut expr: Vec<Expr> = vec![];
+        for (r, relation) in relations.iter().enumerate() {
+            for i in 0..relation.schema().columns().len() {
+                expr.push(Expr::Column(offsets[r] + i));
+            }
+        }
+        builder.project(expr).ok()?.build().ok()
+    }
+}
+
+impl OptimizerRule for JoinReorder {
+    fn name(&self) -> &str {
+        "join_reorder"
+    }
+
+    fn optimize(&mut self, plan: &Rc<LogicalPlan>) -> Result<Rc<LogicalPlan>> {
+        self.optimize_plan(plan)
+    }
+}
+
+/// Collect the relations and join conditions of a tree of inner joins, in the order that the
+/// relations' columns appear in the output of the join
+fn flatten_joins(
+    plan: &Rc<LogicalPlan>,
+    relations: &mut Vec<Rc<LogicalPlan>>,
+    conditions: &mut Vec<(Column, Column)>,
+) {
+    match plan.as_ref() {
+        LogicalPlan::Join {
+            ref left,
+            ref right,
+            join_type: JoinType::Inner,
+            ref on,
+            ..
+        } => {
+            flatten_joins(left, relations, conditions);
+            flatten_joins(right, relations, conditions);
+            conditions.extend(on.iter().cloned());
+        }
+        _ => relations.push(plan.clone()),
+    }
+}
+
+/// Find the single relation that a column refers to
+fn relation_of(relations: &Vec<Rc<LogicalPlan>>, column: &Column) -> Option<usize> {
+    let matches: Vec<usize> = (0..relations.len())
+        .filter(|i| column.index_of(relations[*i].schema()).is_ok())
+        .collect();
+    if matches.len() == 1 {
+        Some(matches[0])
+    } else {
+        None
+    }
+}
+
+/// Computes identical deterministic subexpressions once, in an intermediate projection below a
+/// projection or selection. Subexpressions that are shared between a projection and the
+/// selection directly below it are also computed once, below the selection. Those that only the
+/// projection uses are computed above the selection, so that they are not evaluated for rows
+/// that the selection removes.
+pub struct CommonSubexprEliminate {
+    schema_provider: Rc<SchemaProvider>,
+}
+
+impl CommonSubexprEliminate {
+    pub fn new(schema_provider: Rc<SchemaProvider>) -> Self {
+        CommonSubexprEliminate { schema_provider }
+    }
+
+    fn optimize_plan(&self, plan: &Rc<LogicalPlan>) -> Result<Rc<LogicalPlan>> {
+        match plan.as_ref() {
+            LogicalPlan::Projection {
+                ref expr,
+                ref input,
+                ref schema,
+            } => match input.as_ref() {
+                LogicalPlan::Selection {
+                    expr: ref predicate,
+                    input: ref selection_input,
+                } => {
+                    let selection_input = self.optimize_plan(selection_input)?;
+                    let mut all_expr = expr.clone();
+                    all_expr.push(predicate.clone());
+                    // only the subexpressions that the predicate uses are computed below it
+                    let in_predicate = self.count_subexprs(&vec![predicate.clone()])?;
+                    let counts: Vec<(Expr, usize)> = self
+                        .count_subexprs(&all_expr)?
+                        .into_iter()
+                        .filter(|(e, _)| in_predicate.iter().any(|(p, _)| p == e))
+                        .collect();
+                    let (mut all_expr, selection_input) =
+                        match self.eliminate_counted(&all_expr, &counts, &selection_input)? {
+                            Some(result) => result,
+                            None => (all_expr, selection_input),
+                        };
+                    let predicate = all_expr.pop().unwrap();
+                    let selection = Rc::new(LogicalPlan::Selection {
+                        expr: predicate,
+                        input: selection_input,
+                    });
+                    let (expr, input) = match self.eliminate(&all_expr, &selection)? {
+                        Some(result) => result,
+                        None => (all_expr, selection),
+                    };
+                    Ok(Rc::new(LogicalPlan::Projection {
+                        expr,
+                        input,
+                        schema: schema.clone(),
+                    }))
+                }
+                _ => {
+                    let input = self.optimize_plan(input)?;
+                    let (expr, input) = match self.eliminate(expr, &input)? {
+                        Some(result) => result,
+                        None => (expr.clone(), input),
+                    };
+                    Ok(Rc::new(LogicalPlan::Projection {
+                        expr,
+                        input,
+                        schema: schema.clone(),
+                    }))
+                }
+            },
+            LogicalPlan::Selection {
+                ref expr,
+                ref input,
+            } => {
+                let input = self.optimize_plan(input)?;
+                match self.eliminate(&vec![expr.clone()], &input)? {
+                    Some((mut expr, intermediate)) => {
+                        // remove the intermediate columns again
+                        let columns = (0..input.schema().columns().len())
+                            .map(|i| Expr::Column(i))
+                            .collect();
+                        Ok(Rc::new(LogicalPlan::Projection {
+                            expr: columns,
+                            input: Rc::new(LogicalPlan::Selection {
+                                expr: expr.remove(0),
+                                input: intermediate,
+                            }),
+                            schema: input.schema().clone(),
+                        }))
+                    }
+                    None => Ok(Rc::new(LogicalPlan::Selection {
+                        expr: expr.clone(),
+                        input,
+                    })),
+                }
+            }
+            _ => {
+                let inputs = plan.inputs();
+                if inputs.is_empty() {
+                    return Ok(plan.clone());
+                }
+                let new_inputs = inputs
+                    .iter()
+                    .map(|input| self.optimize_plan(input))
+                    .collect::<Result<Vec<Rc<LogicalPlan>>>>()?;
+                Ok(Rc::new(plan.with_new_inputs(new_inputs)?))
+            }
+        }
+    }
+
+    /// Rewrite the expressions to refer to common subexpressions computed by a new projection
+    /// of the input. Returns `None` if there are no common subexpressions.
+    fn eliminate(
+        &self,
+        expr: &Vec<Expr>,
+        input: &Rc<LogicalPlan>,
+    ) -> Result<Option<(Vec<Expr>, Rc<LogicalPlan>)>> {
+        let counts = self.count_subexprs(expr)?;
+        self.eliminate_counted(expr, &counts, input)
+    }
+
+    /// Count the occurrences of each reusable subexpression of the expressions
+    fn count_subexprs(&self, expr: &Vec<Expr>) -> Result<Vec<(Expr, usize)>> {
+        let mut counter = SubexprCounter {
+            schema_provider: self.schema_provider.clone(),
+            counts: vec![],
+        };
+        for e in expr {
+            e.accept(&mut counter)?;
+        }
+        Ok(counter.counts)
+    }
+
+    /// Rewrite the expressions to refer to the subexpressions in `counts` that occur more than
+    /// once, computed by a new projection of the input
+    fn eliminate_counted(
+        &self,
+        expr: &Vec<Expr>,
+        counts: &Vec<(Expr, usize)>,
+        input: &Rc<LogicalPlan>,
+    ) -> Result<Option<(Vec<Expr>, Rc<LogicalPlan>)>> {
+        if counts.iter().all(|(_, n)| *n < 2) {
+            return Ok(None);
+        }
+
+        let input_len = input.schema().columns().len();
+        let mut common: Vec<Expr> = vec![];
+        let rewritten = expr
+            .iter()
+            .map(|e| replace_common_subexpr(e, counts, input_len, &mut common))
+            .collect::<Result<Vec<Expr>>>()?;
+
+        // the intermediate projection passes through the input columns followed by the
+        // common subexpressions
+        let mut projection: Vec<Expr> = (0..input_len).map(|i| Expr::Column(i)).collect();
+        common.iter().for_each(|e| projection.push(e.clone()));
+        let mut fields = exprlist_to_fields(&projection, input.schema())?;
+        for i in 0..common.len() {
+            let field = Field::new(
+                &format!("__common_expr_{}", i),
+                fields[input_len + i].data_type().clone(),
+                fields[input_len + i].is_nullable(),
+            );
+            fields[input_len + i] = field;
+        }
+
+        Ok(Some((
+            rewritten,
+            Rc::new(LogicalPlan::Projection {
+                expr: projection,
+                input: input.clone(),
+                schema: Rc::new(Schema::new(fields)),
+            }),
+        )))
+    }
+}
+
+impl OptimizerRule for CommonSubexprEliminate {
+    fn name(&self) -> &str {
+        "common_subexpr_eliminate"
+    }
+
+    fn optimize(&mut self, plan: &Rc<LogicalPlan>) -> Result<Rc<LogicalPlan>> {
+        self.optimize_plan(plan)
+    }
+}
+
+/// Pushes the columns referenced by the plan down into table scans so that only the required
+/// columns are read
+pub struct ProjectionPushDown {}
+
+impl ProjectionPushDown {
+    pub fn new() -> Self {
+        ProjectionPushDown {}
+    }
+}
+
+impl OptimizerRule for ProjectionPushDown {
+    fn name(&self) -> &str {
+        "projection_push_down"
+    }
+
+    fn optimize(&mut self, plan: &Rc<LogicalPlan>) -> Result<Rc<LogicalPlan>> {
+        push_down_projection(plan, &HashSet::new())
+    }
+}
+
+/// Counts the occurrences of each subexpression that could be computed once
+struct SubexprCounter {
+    schema_provider: Rc<SchemaProvider>,
+    counts: Vec<(Expr, usize)>,
+}
+
+impl SubexprCounter {
+    /// Determine whether the expression can be evaluated once and the result reused
+    fn is_reusable(&self, expr: &Expr) -> bool {
+        let reusable = match expr {
+            Expr::AggregateFunction { .. } | Expr::Sort { .. } => false,
+            Expr::ScalarFunction { ref name, .. } => {
+                match self.schema_provider.get_function_meta(name) {
+                    Some(meta) => meta.is_deterministic(),
+                    None => false,
+                }
+            }
+            _ => true,
+        };
+        reusable && expr.children().iter().all(|e| self.is_reusable(e))
+    }
+}
+
+impl ExprVisitor for SubexprCounter {
+    fn pre_visit(&mut self, expr: &Expr) -> Result<Recursion> {
+        match expr {
+            // there is nothing to gain from computing leaves once
+            Expr::Column(_) | Expr::ColumnRef(_) | Expr::Literal(_) | Expr::Parameter { .. } => {}
+            _ => if self.is_reusable(expr) {
+                match self.counts.iter().position(|(e, _)| e == expr) {
+                    Some(i) => self.counts[i].1 += 1,
+                    None => self.counts.push((expr.clone(), 1)),
+                }
+            },
+        }
+        Ok(Recursion::Continue)
+    }
+}
+
+/// Replace the outermost subexpressions that occur more than once with references to the
+/// columns that will compute them, recording the subexpressions in `common`
+fn replace_common_subexpr(
+    expr: &Expr,
+    counts: &Vec<(Expr, usize)>,
+    input_len: usize,
+    common: &mut Vec<Expr>,
+) -> Result<Expr> {
+    if counts.iter().any(|(e, n)| *n > 1 && e == expr) {
+        let index = match common.iter().position(|e| e == expr) {
+            Some(i) => i,
+            None => {
+                common.push(expr.clone());
+                common.len() - 1
+            }
+        };
+        return Ok(Expr::Column(input_len + index));
+    }
+    let mut children: Vec<Expr> = vec![];
+    for child in expr.children() {
+        children.push(replace_common_subexpr(child, counts, input_len, common)?);
+    }
+    expr.with_new_children(children)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::datasources::common::*;
+    use super::super::types::*;
+    use super::*;
+
+    use super::super::datatypes::*;
+
+    struct TestSchemaProvider {}
+
+    impl SchemaProvider for TestSchemaProvider {
+        fn get_table_meta(&self, _name: &str) -> Option<Rc<Schema>> {
+            None
+        }
+
+        fn get_function_meta(&self, name: &str) -> Option<Rc<FunctionMeta>> {
+            let deterministic = match name {
+                "sqrt" => true,
+                "random" => false,
+                _ => return None,
+            };
+            Some(Rc::new(FunctionMeta::new(
+                name.to_string(),
+                vec![Field::new("n", DataType::Int32, false)],
+                DataType::Int32,
+                FunctionType::Scalar,
+                deterministic,
+            )))
+        }
+
+        fn get_table_statistics(&self, name: &str) -> Option<Statistics> {
+            let rows = match name {
+                "sales" => 1000000,
+                "product" => 1000,
+                "store" => 10,
+                _ => return None,
+            };
+            Some(Statistics {
+                row_count: Some(rows),
+                ..Statistics::default()
+            })
+        }
+    }
+
+    fn scan(name: &str, columns: Vec<&str>) -> Rc<LogicalPlan> {
+        let schema = Schema::new(
+            columns
+                .iter()
+                .map(|c| Field::new(c, DataType::Int32, false))
+                .collect(),
+        );
+        LogicalPlanBuilder::scan("default", name, &schema, None)
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+
+    fn star_join(dimension_first: bool) -> Rc<LogicalPlan> {
+        let sales = scan("sales", vec!["product_id", "store_id"]);
+        let product = scan("product", vec!["id"]);
+        let store = scan("store", vec!["id"]);
+        let builder = if dimension_first {
+            LogicalPlanBuilder::from(&product).join(
+                &sales,
+                JoinType::Inner,
+                vec![Column::qualified("product", "id")],
+                vec![Column::qualified("sales", "product_id")],
+            )
+        } else {
+            LogicalPlanBuilder::from(&sales).join(
+                &product,
+                JoinType::Inner,
+                vec![Column::qualified("sales", "product_id")],
+                vec![Column::qualified("product", "id")],
+            )
+        };
+        builder
+            .unwrap()
+            .join(
+                &store,
+                JoinType::Inner,
+                vec![Column::qualified("sales", "store_id")],
+                vec![Column::qualified("store", "id")],
+            )
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+
+    fn optimize(plan: &Rc<LogicalPlan>) -> Rc<LogicalPlan> {
+        let mut rule = JoinReorder::new(CostModel::new(Rc::new(TestSchemaProvider {})));
+        rule.optimize(plan).unwrap()
+    }
+
+    #[test]
+    fn reorder_star_join() {
+        let plan = optimize(&star_join(false));
+        assert_eq!(
+            "Projection: #1, #2, #3, #0\
+             \n  Join: type=Inner, on=[#sales.product_id = #product.id]\
+             \n    Join: type=Inner, on=[#store.id = #sales.store_id]\
+             \n      TableScan: store projection=None\
+             \n      TableScan: sales projection=None\
+             \n    TableScan: product projection=None",
+            format!("{:?}", plan)
+        );
+
+        // the original column order is preserved
+        let original = star_join(false);
+        let names = |p: &Rc<LogicalPlan>| -> Vec<String> {
+            p.schema()
+                .columns()
+                .iter()
+                .map(|f| f.name().clone())
+                .collect()
+        };
+        assert_eq!(names(&original), names(&plan));
+    }
+
+    #[test]
+    fn reorder_is_independent_of_from_order() {
+        let a = optimize(&star_join(false));
+        let b = optimize(&star_join(true));
+        let joins = |p: &Rc<LogicalPlan>| format!("{:?}", p.inputs()[0]);
+        assert_eq!(joins(&a), joins(&b));
+    }
+
+    #[test]
+    fn no_reorder_without_statistics() {
+        let sales = scan("sales", vec!["product_id", "store_id"]);
+        let plan = LogicalPlanBuilder::from(&sales)
+            .join(
+                &scan("product", vec!["id"]),
+                JoinType::Inner,
+                vec![Column::qualified("sales", "product_id")],
+                vec![Column::qualified("product", "id")],
+            )
+            .unwrap()
+            .join(
+                &scan("unknown", vec!["id"]),
+                JoinType::Inner,
+                vec![Column::qualified("sales", "store_id")],
+                vec![Column::qualified("unknown", "id")],
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(format!("{:?}", plan), format!("{:?}", optimize(&plan)));
+    }
+
+    fn binary(left: Expr, op: Operator, right: Expr) -> Expr {
+        Expr::BinaryExpr {
+            left: Rc::new(left),
+            op,
+            right: Rc::new(right),
+        }
+    }
+
+    fn function(name: &str, arg: Expr) -> Expr {
+        Expr::ScalarFunction {
+            name: name.to_string(),
+            args: vec![arg],
+            return_type: DataType::Int32,
+        }
+    }
+
+    fn eliminate(plan: &Rc<LogicalPlan>) -> Rc<LogicalPlan> {
+        let mut rule = CommonSubexprEliminate::new(Rc::new(TestSchemaProvider {}));
+        rule.optimize(plan).unwrap()
+    }
+
+    #[test]
+    fn eliminate_projection_and_selection() {
+        let total = binary(col("price"), Operator::Multiply, col("qty"));
+        let plan = LogicalPlanBuilder::from(&scan("orders", vec!["price", "qty"]))
+            .filter(total.gt(&lit(ScalarValue::Int32(10))))
+            .unwrap()
+            .project(vec![
+                total.clone(),
+                binary(total.clone(), Operator::Plus, lit(ScalarValue::Int32(1))),
+                binary(
+                    col("price"),
+                    Operator::Multiply,
+                    binary(col("qty"), Operator::Plus, lit(ScalarValue::Int32(1))),
+                ),
+            ])
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let optimized = eliminate(&plan);
+        assert_eq!(
+            "Projection: #2, #2 Plus Int32(1), #price Multiply #qty Plus Int32(1)\
+             \n  Selection: #2 Gt Int32(10)\
+             \n    Projection: #0, #1, #price Multiply #qty\
+             \n      TableScan: orders projection=None",
+            format!("{:?}", optimized)
+        );
+        assert_eq!(plan.schema().to_string(), optimized.schema().to_string());
+    }
+
+    #[test]
+    fn projection_only_subexpr_is_computed_after_selection() {
+        let root = function("sqrt", col("qty"));
+        let plan = LogicalPlanBuilder::from(&scan("orders", vec!["price", "qty"]))
+            .filter(col("price").gt(&lit(ScalarValue::Int32(10))))
+            .unwrap()
+            .project(vec![
+                root.clone(),
+                binary(root.clone(), Operator::Plus, lit(ScalarValue::Int32(1))),
+            ])
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let optimized = eliminate(&plan);
+        assert_eq!(
+            "Projection: #2, #2 Plus Int32(1)\
+             \n  Projection: #0, #1, sqrt(#qty)\
+             \n    Selection: #price Gt Int32(10)\
+             \n      TableScan: orders projection=None",
+            format!("{:?}", optimized)
+        );
+        assert_eq!(plan.schema().to_string(), optimized.schema().to_string());
+    }
+
+    #[test]
+    fn eliminate_selection() {
+        let root = function("sqrt", col("qty"));
+        let plan = LogicalPlanBuilder::from(&scan("orders", vec!["price", "qty"]))
+            .filter(binary(
+                root.gt(&lit(ScalarValue::Int32(1))),
+                Operator::And,
+                root.lt(&lit(ScalarValue::Int32(10))),
+            ))
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let optimized = eliminate(&plan);
+        assert_eq!(
+            "Projection: #0, #1\
+             \n  Selection: #2 Gt Int32(1) And #2 Lt Int32(10)\
+             \n    Projection: #0, #1, sqrt(#qty)\
+             \n      TableScan: orders projection=None",
+            format!("{:?}", optimized)
+        );
+        assert_eq!(plan.schema().to_string(), optimized.schema().to_string());
+    }
+
+    #[test]
+    fn non_deterministic_functions_are_not_eliminated() {
+        let random = function("random", col("qty"));
+        let plan = LogicalPlanBuilder::from(&scan("orders", vec!["price", "qty"]))
+            .project(vec![random.clone(), random.clone()])
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(format!("{:?}", plan), format!("{:?}", eliminate(&plan)));
+    }
+}
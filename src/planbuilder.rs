@@ -0,0 +1,452 @@
+// Copyright 2018 Grove Enterprises LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Builder for logical plans that derives the schema of each step and validates column
+//! references and types as the plan is built
+
+use std::rc::Rc;
+
+use super::datatypes::*;
+use super::errors::*;
+use super::logical::*;
+use super::sqlplanner::exprlist_to_fields;
+
+/// Builds a logical plan one step at a time
+pub struct LogicalPlanBuilder {
+    plan: Rc<LogicalPlan>,
+}
+
+impl LogicalPlanBuilder {
+    /// Create a builder starting from an existing plan
+    pub fn from(plan: &Rc<LogicalPlan>) -> Self {
+        LogicalPlanBuilder { plan: plan.clone() }
+    }
+
+    /// Create a builder for a relation with no columns (e.g. `SELECT 1`)
+    pub fn empty() -> Self {
+        LogicalPlanBuilder::from(&Rc::new(LogicalPlan::EmptyRelation {
+            schema: Rc::new(Schema::empty()),
+        }))
+    }
+
+    /// Scan a registered table. Fields in the resulting schema are qualified with the table name.
+    pub fn scan(
+        schema_name: &str,
+        table_name: &str,
+        table_schema: &Schema,
+        projection: Option<Vec<usize>>,
+    ) -> Result<Self> {
+        if let Some(ref p) = projection {
+            if let Some(i) = p.iter().find(|i| **i >= table_schema.columns().len()) {
+                return Err(ExecutionError::plan(format!(
+                    "Projection index {} is out of bounds for table {}",
+                    i, table_name
+                )));
+            }
+        }
+        Ok(LogicalPlanBuilder::from(&Rc::new(LogicalPlan::TableScan {
+            schema_name: schema_name.to_string(),
+            table_name: table_name.to_string(),
+            schema: Rc::new(qualify_schema(table_schema, table_name)),
+            projection,
+            fields: vec![],
+        })))
+    }
+
+    /// Get the schema of the plan built so far
+    pub fn schema(&self) -> &Rc<Schema> {
+        self.plan.schema()
+    }
+
+    /// Apply a projection
+    pub fn project(&self, expr: Vec<Expr>) -> Result<Self> {
+        let schema = Schema::new(exprlist_to_fields(&expr, self.schema())?);
+        Ok(self.with_plan(LogicalPlan::Projection {
+            expr,
+            input: self.plan.clone(),
+            schema: Rc::new(schema),
+        }))
+    }
+
+    /// Apply a filter, which must be a boolean expression
+    pub fn filter(&self, expr: Expr) -> Result<Self> {
+        match expr.get_type(self.schema())? {
+            DataType::Boolean => Ok(self.with_plan(LogicalPlan::Selection {
+                expr,
+                input: self.plan.clone(),
+            })),
+            other => Err(ExecutionError::type_error(
+                format!("Filter expression must be Boolean but is {:?}", other),
+                &expr,
+            )),
+        }
+    }
+
+    /// Apply an aggregate with optional grouping expressions
+    pub fn aggregate(&self, group_expr: Vec<Expr>, aggr_expr: Vec<Expr>) -> Result<Self> {
+        if let Some(e) = aggr_expr.iter().find(|e| match e {
+            Expr::AggregateFunction { .. } => false,
+            _ => true,
+        }) {
+            return Err(ExecutionError::plan_expr(
+                "Expression is not an aggregate function".to_string(),
+                e,
+            ));
+        }
+
+        let mut all_expr: Vec<Expr> = group_expr.clone();
+        aggr_expr.iter().for_each(|e| all_expr.push(e.clone()));
+        let schema = Schema::new(exprlist_to_fields(&all_expr, self.schema())?);
+
+        Ok(self.with_plan(LogicalPlan::Aggregate {
+            input: self.plan.clone(),
+            group_expr,
+            aggr_expr,
+            schema: Rc::new(schema),
+        }))
+    }
+
+    /// Apply a sort, where each expression is a sort expression
+    pub fn sort(&self, expr: Vec<Expr>) -> Result<Self> {
+        for e in &expr {
+            match e {
+                Expr::Sort { ref expr, .. } => {
+                    expr.get_type(self.schema())?;
+                }
+                _ => {
+                    return Err(ExecutionError::plan_expr(
+                        "Expression is not a sort expression".to_string(),
+                        e,
+                    ))
+                }
+            }
+        }
+        Ok(self.with_plan(LogicalPlan::Sort {
+            expr,
+            input: self.plan.clone(),
+            schema: self.schema().clone(),
+        }))
+    }
+
+    /// Apply a limit
+    pub fn limit(&self, limit: usize) -> Result<Self> {
+        Ok(self.with_plan(LogicalPlan::Limit {
+            limit,
+            input: self.plan.clone(),
+            schema: self.schema().clone(),
+        }))
+    }
+
+    /// Join with another plan on pairs of columns that must have the same type
+    pub fn join(
+        &self,
+        right: &Rc<LogicalPlan>,
+        join_type: JoinType,
+        left_keys: Vec<Column>,
+        right_keys: Vec<Column>,
+    ) -> Result<Self> {
+        if left_keys.len() != right_keys.len() {
+            return Err(ExecutionError::plan(String::from(
+                "Join requires the same number of left and right keys",
+            )));
+        }
+        if left_keys.is_empty() {
+            return Err(ExecutionError::plan(String::from(
+                "Join requires at least one pair of keys",
+            )));
+        }
+
+        let left_schema = self.schema();
+        let right_schema = right.schema();
+        for (l, r) in left_keys.iter().zip(right_keys.iter()) {
+            let left_type = left_schema.column(l.index_of(left_schema)?).data_type();
+            let right_type = right_schema.column(r.index_of(right_schema)?).data_type();
+            if left_type != right_type {
+                return Err(ExecutionError::type_mismatch(format!(
+                    "Join key #{} has type {:?} but #{} has type {:?}",
+                    l, left_type, r, right_type
+                )));
+            }
+        }
+
+        let mut fields: Vec<Field> = left_schema.columns().clone();
+        fields.extend(right_schema.columns().iter().cloned());
+
+        Ok(self.with_plan(LogicalPlan::Join {
+            left: self.plan.clone(),
+            right: right.clone(),
+            join_type,
+            on: left_keys.into_iter().zip(right_keys.into_iter()).collect(),
+            schema: Rc::new(Schema::new(fields)),
+        }))
+    }
+
+    /// Produce a row for each element of a list expression. The element is appended to the
+    /// input fields as a field named `unnest`.
+    pub fn unnest(&self, expr: Expr) -> Result<Self> {
+        let element_type = match expr.get_type(self.schema())? {
+            DataType::List(t) => *t,
+            other => {
+                return Err(ExecutionError::type_error(
+                    format!("UNNEST requires a list but got {:?}", other),
+                    &expr,
+                ))
+            }
+        };
+        let mut fields: Vec<Field> = self.schema().columns().clone();
+        fields.push(Field::new("unnest", element_type, true));
+        Ok(self.with_plan(LogicalPlan::Unnest {
+            input: self.plan.clone(),
+            expr,
+            schema: Rc::new(Schema::new(fields)),
+        }))
+    }
+
+    /// Append the rows of another plan, which must have the same field types
+    pub fn union(&self, other: &Rc<LogicalPlan>) -> Result<Self> {
+        let schema = self.schema();
+        let other_schema = other.schema();
+        if schema.columns().len() != other_schema.columns().len() {
+            return Err(ExecutionError::plan(format!(
+                "Union inputs must have the same number of fields but have {} and {}",
+                schema.columns().len(),
+                other_schema.columns().len()
+            )));
+        }
+        for (l, r) in schema.columns().iter().zip(other_schema.columns().iter()) {
+            if l.data_type() != r.data_type() {
+                return Err(ExecutionError::type_mismatch(format!(
+                    "Union field {} has type {:?} but {} has type {:?}",
+                    l.name(),
+                    l.data_type(),
+                    r.name(),
+                    r.data_type()
+                )));
+            }
+        }
+
+        // flatten nested unions
+        let mut inputs = match self.plan.as_ref() {
+            LogicalPlan::Union { ref inputs, .. } => inputs.clone(),
+            _ => vec![self.plan.clone()],
+        };
+        inputs.push(other.clone());
+
+        Ok(self.with_plan(LogicalPlan::Union {
+            inputs,
+            schema: schema.clone(),
+        }))
+    }
+
+    /// Return the logical plan
+    pub fn build(&self) -> Result<Rc<LogicalPlan>> {
+        Ok(self.plan.clone())
+    }
+
+    fn with_plan(&self, plan: LogicalPlan) -> Self {
+        LogicalPlanBuilder {
+            plan: Rc::new(plan),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::types::*;
+    use super::*;
+
+    #[test]
+    fn plan_builder_simple() {
+        let plan = LogicalPlanBuilder::scan("default", "employee", &employee_schema(), None)
+            .unwrap()
+            .filter(col("state").eq(&lit(ScalarValue::Utf8(Rc::new("CO".to_string())))))
+            .unwrap()
+            .project(vec![col("id")])
+            .unwrap()
+            .limit(10)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            "Limit: 10\
+             \n  Projection: #id\
+             \n    Selection: #state Eq Utf8(\"CO\")\
+             \n      TableScan: employee projection=None",
+            format!("{:?}", plan)
+        );
+        assert_eq!("id", plan.schema().column(0).name());
+    }
+
+    #[test]
+    fn plan_to_dot() {
+        let plan = LogicalPlanBuilder::scan("default", "employee", &employee_schema(), None)
+            .unwrap()
+            .filter(col("state").eq(&lit(ScalarValue::Utf8(Rc::new("CO".to_string())))))
+            .unwrap()
+            .project(vec![col("id")])
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let employee = "Schema: [employee.id: Int32, employee.first_name: Utf8, \
+                        employee.state: Utf8, employee.dept_id: Int32]";
+        assert_eq!(
+            format!(
+                "digraph LogicalPlan {{\
+                 \n  node [shape=box];\
+                 \n  node0 [label=\"Projection: #id\\nSchema: [id: Int32]\"];\
+                 \n  node1 [label=\"Selection: #state Eq Utf8(\\\"CO\\\")\\n{}\"];\
+                 \n  node2 [label=\"TableScan: employee projection=None\\n{}\"];\
+                 \n  node1 -> node2;\
+                 \n  node0 -> node1;\
+                 \n}}",
+                employee, employee
+            ),
+            plan.to_dot()
+        );
+    }
+
+    #[test]
+    fn plan_builder_invalid_column() {
+        let builder =
+            LogicalPlanBuilder::scan("default", "employee", &employee_schema(), None).unwrap();
+        assert!(builder.project(vec![col("banana")]).is_err());
+    }
+
+    #[test]
+    fn plan_builder_filter_must_be_boolean() {
+        let builder =
+            LogicalPlanBuilder::scan("default", "employee", &employee_schema(), None).unwrap();
+        assert!(builder.filter(col("state")).is_err());
+    }
+
+    #[test]
+    fn plan_builder_unnest_requires_list() {
+        let builder =
+            LogicalPlanBuilder::scan("default", "employee", &employee_schema(), None).unwrap();
+        assert!(builder.unnest(col("state")).is_err());
+    }
+
+    #[test]
+    fn plan_builder_join() {
+        let right = LogicalPlanBuilder::scan("default", "dept", &dept_schema(), None)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let plan = LogicalPlanBuilder::scan("default", "employee", &employee_schema(), None)
+            .unwrap()
+            .join(
+                &right,
+                JoinType::Inner,
+                vec![Column::from_name("dept_id")],
+                vec![Column::qualified("dept", "id")],
+            )
+            .unwrap()
+            .project(vec![col("employee.id"), col("dept.name")])
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            "Projection: #employee.id, #dept.name\
+             \n  Join: type=Inner, on=[#dept_id = #dept.id]\
+             \n    TableScan: employee projection=None\
+             \n    TableScan: dept projection=None",
+            format!("{:?}", plan)
+        );
+    }
+
+    #[test]
+    fn plan_builder_join_key_type_mismatch() {
+        let right = LogicalPlanBuilder::scan("default", "dept", &dept_schema(), None)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let result = LogicalPlanBuilder::scan("default", "employee", &employee_schema(), None)
+            .unwrap()
+            .join(
+                &right,
+                JoinType::Inner,
+                vec![Column::from_name("state")],
+                vec![Column::qualified("dept", "id")],
+            );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn plan_builder_union() {
+        let scan = LogicalPlanBuilder::scan("default", "employee", &employee_schema(), None)
+            .unwrap()
+            .project(vec![col("id")])
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let plan = LogicalPlanBuilder::from(&scan)
+            .union(&scan)
+            .unwrap()
+            .union(&scan)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        match plan.as_ref() {
+            LogicalPlan::Union { ref inputs, .. } => assert_eq!(3, inputs.len()),
+            _ => panic!("expected union"),
+        }
+
+        let other = LogicalPlanBuilder::scan("default", "employee", &employee_schema(), None)
+            .unwrap()
+            .project(vec![col("state")])
+            .unwrap()
+            .build()
+            .unwrap();
+        assert!(LogicalPlanBuilder::from(&scan).union(&other).is_err());
+    }
+
+    #[test]
+    fn qualified_names_with_dots() {
+        let column = Column::from_qualified_name("t1.\"a.b\"");
+        assert_eq!(Column::qualified("t1", "a.b"), column);
+        assert_eq!("t1.\"a.b\"", column.flat_name());
+        assert_eq!(column, Column::from_qualified_name(&column.flat_name()));
+        assert_eq!(Column::qualified("t1", "x"), Column::from_qualified_name("t1.x"));
+
+        let schema = qualify_schema(
+            &Schema::new(vec![Field::new("a.b", DataType::Int32, false)]),
+            "t1",
+        );
+        assert_eq!(0, Column::from_name("a.b").index_of(&schema).unwrap());
+        assert_eq!(0, column.index_of(&schema).unwrap());
+    }
+
+    fn employee_schema() -> Schema {
+        Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("first_name", DataType::Utf8, false),
+            Field::new("state", DataType::Utf8, false),
+            Field::new("dept_id", DataType::Int32, false),
+        ])
+    }
+
+    fn dept_schema() -> Schema {
+        Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("name", DataType::Utf8, false),
+        ])
+    }
+}
@@ -15,12 +15,14 @@
 //! Aggregate / Grouping Relation
 
 use std::cell::RefCell;
+use std::iter;
 use std::rc::Rc;
-use std::str;
 
 use super::super::datasources::common::*;
+use super::super::datatypes::*;
 use super::super::errors::*;
 use super::super::exec::*;
+use super::super::functions::array_agg::ArrayAggFunction;
 use super::super::functions::count::CountFunction;
 use super::super::functions::max::MaxFunction;
 use super::super::functions::min::MinFunction;
@@ -29,7 +31,6 @@ use super::super::types::*;
 
 use arrow::array::ListArray;
 use arrow::builder::*;
-use arrow::datatypes::*;
 use arrow::list_builder::*;
 
 use fnv::FnvHashMap;
@@ -61,9 +62,9 @@ impl AggregateRelation {
     }
 }
 
-/// Enumeration of types that can be used in a GROUP BY expression
+/// Enumeration of types that can be used in a GROUP BY expression or as a join key
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
-enum GroupScalar {
+pub enum GroupScalar {
     Boolean(bool),
     UInt8(u8),
     UInt16(u16),
@@ -73,7 +74,17 @@ enum GroupScalar {
     Int16(i16),
     Int32(i32),
     Int64(i64),
+    /// The bits of a float, so that it can be hashed
+    Float32(u32),
+    /// The bits of a double, so that it can be hashed
+    Float64(u64),
     Utf8(Rc<String>),
+    Binary(Rc<Vec<u8>>),
+    Date32(i32),
+    Time64(i64, TimeUnit),
+    Timestamp(i64, TimeUnit),
+    Decimal(i128, usize, usize),
+    Null,
 }
 
 impl GroupScalar {
@@ -88,19 +99,70 @@ impl GroupScalar {
             GroupScalar::Int16(v) => ScalarValue::Int16(v),
             GroupScalar::Int32(v) => ScalarValue::Int32(v),
             GroupScalar::Int64(v) => ScalarValue::Int64(v),
+            GroupScalar::Float32(v) => ScalarValue::Float32(f32::from_bits(v)),
+            GroupScalar::Float64(v) => ScalarValue::Float64(f64::from_bits(v)),
             GroupScalar::Utf8(ref v) => ScalarValue::Utf8(v.clone()),
+            GroupScalar::Binary(ref v) => ScalarValue::Binary(v.clone()),
+            GroupScalar::Date32(v) => ScalarValue::Date32(v),
+            GroupScalar::Time64(v, unit) => ScalarValue::Time64(v, unit),
+            GroupScalar::Timestamp(v, unit) => ScalarValue::Timestamp(v, unit),
+            GroupScalar::Decimal(v, p, s) => ScalarValue::Decimal(v, p, s),
+            GroupScalar::Null => ScalarValue::Null,
         }
     }
+
+    /// Convert a scalar value to a hashable value, or `None` for values of nested types and
+    /// intervals. Zero and NaN floats are normalized so that equal values hash the same.
+    pub fn from_scalar(value: &ScalarValue) -> Option<GroupScalar> {
+        Some(match *value {
+            ScalarValue::Null => GroupScalar::Null,
+            ScalarValue::Boolean(v) => GroupScalar::Boolean(v),
+            ScalarValue::UInt8(v) => GroupScalar::UInt8(v),
+            ScalarValue::UInt16(v) => GroupScalar::UInt16(v),
+            ScalarValue::UInt32(v) => GroupScalar::UInt32(v),
+            ScalarValue::UInt64(v) => GroupScalar::UInt64(v),
+            ScalarValue::Int8(v) => GroupScalar::Int8(v),
+            ScalarValue::Int16(v) => GroupScalar::Int16(v),
+            ScalarValue::Int32(v) => GroupScalar::Int32(v),
+            ScalarValue::Int64(v) => GroupScalar::Int64(v),
+            ScalarValue::Float32(v) if v == 0.0 => GroupScalar::Float32(0),
+            ScalarValue::Float32(v) if v.is_nan() => {
+                GroupScalar::Float32(::std::f32::NAN.to_bits())
+            }
+            ScalarValue::Float32(v) => GroupScalar::Float32(v.to_bits()),
+            ScalarValue::Float64(v) if v == 0.0 => GroupScalar::Float64(0),
+            ScalarValue::Float64(v) if v.is_nan() => {
+                GroupScalar::Float64(::std::f64::NAN.to_bits())
+            }
+            ScalarValue::Float64(v) => GroupScalar::Float64(v.to_bits()),
+            ScalarValue::Utf8(ref v) => GroupScalar::Utf8(v.clone()),
+            ScalarValue::Binary(ref v) => GroupScalar::Binary(v.clone()),
+            ScalarValue::Date32(v) => GroupScalar::Date32(v),
+            ScalarValue::Time64(v, unit) => GroupScalar::Time64(v, unit),
+            ScalarValue::Timestamp(v, unit) => GroupScalar::Timestamp(v, unit),
+            ScalarValue::Decimal(v, p, s) => GroupScalar::Decimal(v, p, s),
+            ScalarValue::Interval(_) | ScalarValue::Struct(_) | ScalarValue::List(..) => {
+                return None
+            }
+        })
+    }
 }
 
 /// Make a hash map key from a list of values
-fn write_key(key: &mut Vec<GroupScalar>, group_values: &Vec<Value>, i: usize) {
+pub fn write_key(key: &mut Vec<GroupScalar>, group_values: &Vec<Value>, i: usize) -> Result<()> {
     for j in 0..group_values.len() {
         key[j] = match group_values[j] {
             Value::Scalar(ref vv) => match vv.as_ref() {
                 ScalarValue::Boolean(x) => GroupScalar::Boolean(*x),
-                _ => unimplemented!(),
+                ScalarValue::Null => GroupScalar::Null,
+                other => {
+                    return Err(ExecutionError::type_mismatch(format!(
+                        "Unsupported scalar value for grouping expression: {:?}",
+                        other
+                    )))
+                }
             },
+            Value::Column(ref array) if !array.is_valid(i) => GroupScalar::Null,
             Value::Column(ref array) => match array.data() {
                 ArrayData::Boolean(ref buf) => GroupScalar::Boolean(*buf.get(i)),
                 ArrayData::Int8(ref buf) => GroupScalar::Int8(*buf.get(i)),
@@ -112,49 +174,120 @@ fn write_key(key: &mut Vec<GroupScalar>, group_values: &Vec<Value>, i: usize) {
                 ArrayData::UInt32(ref buf) => GroupScalar::UInt32(*buf.get(i)),
                 ArrayData::UInt64(ref buf) => GroupScalar::UInt64(*buf.get(i)),
                 ArrayData::Utf8(ref list) => {
-                    GroupScalar::Utf8(Rc::new(str::from_utf8(list.get(i)).unwrap().to_string()))
+                    GroupScalar::Utf8(Rc::new(String::from_utf8_lossy(list.get(i)).to_string()))
+                }
+                ArrayData::Binary(ref list) => GroupScalar::Binary(Rc::new(list.get(i).to_vec())),
+                ArrayData::Date32(ref buf) => GroupScalar::Date32(*buf.get(i)),
+                ArrayData::Time64(ref buf, unit) => GroupScalar::Time64(*buf.get(i), *unit),
+                ArrayData::Timestamp(ref buf, unit) => GroupScalar::Timestamp(*buf.get(i), *unit),
+                ArrayData::Decimal(ref values, p, s) => GroupScalar::Decimal(values[i], *p, *s),
+                _ => {
+                    return Err(ExecutionError::type_mismatch(
+                        "Unsupported datatype for aggregate grouping expression".to_string(),
+                    ))
                 }
-                _ => unimplemented!("Unsupported datatype for aggregate grouping expression"),
             },
         };
     }
+    Ok(())
 }
 
 /// Create an initial aggregate entry
-fn create_aggregate_entry(aggr_expr: &Vec<RuntimeExpr>) -> Rc<RefCell<AggregateEntry>> {
+fn create_aggregate_entry(aggr_expr: &Vec<RuntimeExpr>) -> Result<Rc<RefCell<AggregateEntry>>> {
     //println!("Creating new aggregate entry");
 
     let functions = aggr_expr
         .iter()
         .map(|e| match e {
-            RuntimeExpr::AggregateFunction { ref f, ref t, .. } => match f {
-                AggregateType::Min => Box::new(MinFunction::new(t)) as Box<AggregateFunction>,
-                AggregateType::Max => Box::new(MaxFunction::new(t)) as Box<AggregateFunction>,
-                AggregateType::Count => Box::new(CountFunction::new()) as Box<AggregateFunction>,
-                AggregateType::Sum => Box::new(SumFunction::new(t)) as Box<AggregateFunction>,
-                _ => panic!(),
+            RuntimeExpr::AggregateFunction {
+                ref f,
+                ref t,
+                overflow_mode,
+                ..
+            } => match f {
+                AggregateType::Min => Ok(Box::new(MinFunction::new(t)) as Box<AggregateFunction>),
+                AggregateType::Max => Ok(Box::new(MaxFunction::new(t)) as Box<AggregateFunction>),
+                AggregateType::Count => {
+                    Ok(Box::new(CountFunction::new()) as Box<AggregateFunction>)
+                }
+                AggregateType::Sum => {
+                    let sum = SumFunction::with_overflow_mode(t, *overflow_mode);
+                    Ok(Box::new(sum) as Box<AggregateFunction>)
+                }
+                AggregateType::ArrayAgg => {
+                    Ok(Box::new(ArrayAggFunction::new(t)) as Box<AggregateFunction>)
+                }
+                AggregateType::Avg => Err(ExecutionError::NotImplemented),
             },
-            _ => panic!(),
+            _ => Err(not_an_aggregate()),
         })
-        .collect();
+        .collect::<Result<Vec<Box<AggregateFunction>>>>()?;
 
-    Rc::new(RefCell::new(AggregateEntry {
+    Ok(Rc::new(RefCell::new(AggregateEntry {
         aggr_values: functions,
-    }))
+    })))
+}
+
+fn not_an_aggregate() -> ExecutionError {
+    ExecutionError::plan("Aggregate relation expected an aggregate expression".to_string())
 }
 
 macro_rules! build_aggregate_array {
     ($TY:ty, $NAME:ident, $DATA:expr) => {{
         let mut b: Builder<$TY> = Builder::new();
         for v in $DATA {
-            b.push(v.$NAME().unwrap());
+            b.push(v.$NAME()?);
         }
         Array::from(b.finish())
     }};
 }
 
-impl SimpleRelation for AggregateRelation {
-    fn scan<'a>(&'a mut self) -> Box<Iterator<Item = Result<Rc<RecordBatch>>> + 'a> {
+/// Build an array of the return type of an aggregate function from its results
+fn build_aggregate_result(t: &DataType, aggr_values: &Vec<ScalarValue>) -> Result<Array> {
+    Ok(match t {
+        DataType::Boolean => build_aggregate_array!(bool, get_bool, aggr_values),
+        DataType::UInt8 => build_aggregate_array!(u8, get_u8, aggr_values),
+        DataType::UInt16 => build_aggregate_array!(u16, get_u16, aggr_values),
+        DataType::UInt32 => build_aggregate_array!(u32, get_u32, aggr_values),
+        DataType::UInt64 => build_aggregate_array!(u64, get_u64, aggr_values),
+        DataType::Int8 => build_aggregate_array!(i8, get_i8, aggr_values),
+        DataType::Int16 => build_aggregate_array!(i16, get_i16, aggr_values),
+        DataType::Int32 => build_aggregate_array!(i32, get_i32, aggr_values),
+        DataType::Int64 => build_aggregate_array!(i64, get_i64, aggr_values),
+        DataType::Float32 => build_aggregate_array!(f32, get_f32, aggr_values),
+        DataType::Float64 => build_aggregate_array!(f64, get_f64, aggr_values),
+        DataType::Utf8 => {
+            let mut b: ListBuilder<u8> = ListBuilder::with_capacity(aggr_values.len());
+            for v in aggr_values {
+                b.push(v.get_string()?.as_bytes());
+            }
+            Array::new(aggr_values.len(), ArrayData::Utf8(ListArray::from(b.finish())))
+        }
+        DataType::Binary => {
+            let mut b: ListBuilder<u8> = ListBuilder::with_capacity(aggr_values.len());
+            for v in aggr_values {
+                b.push(v.get_binary()?);
+            }
+            Array::new(aggr_values.len(), ArrayData::Binary(ListArray::from(b.finish())))
+        }
+        DataType::Date32 | DataType::Time64(_) | DataType::Timestamp(_) => {
+            build_temporal_array(t, aggr_values)?
+        }
+        DataType::Decimal(_, _) => build_decimal_array(t, aggr_values)?,
+        DataType::List(_) => build_list_array(t, aggr_values)?,
+        DataType::Struct(_) => build_struct_array(t, aggr_values)?,
+        _ => {
+            return Err(ExecutionError::type_mismatch(format!(
+                "No support for aggregate with return type {:?}",
+                t
+            )))
+        }
+    })
+}
+
+impl AggregateRelation {
+    /// Aggregate all of the input batches, producing a single batch with one row per group
+    fn aggregate(&mut self) -> Result<Rc<RecordBatch>> {
         let aggr_expr = &self.aggr_expr;
         let group_expr = &self.group_expr;
         //        let mut map: HashMap<Vec<GroupScalar>, Rc<RefCell<AggregateEntry>>> = HashMap::new();
@@ -163,107 +296,78 @@ impl SimpleRelation for AggregateRelation {
 
         //println!("There are {} aggregate expressions", aggr_expr.len());
 
-        self.input.scan().for_each(|batch| {
-            match batch {
-                Ok(ref b) => {
-                    //println!("Processing aggregates for batch with {} rows", b.num_rows());
-
-                    // evaluate the single argument to each aggregate function
-                    let mut aggr_col_args: Vec<Vec<Value>> = Vec::with_capacity(aggr_expr.len());
-                    for i in 0..aggr_expr.len() {
-                        match aggr_expr[i] {
-                            RuntimeExpr::AggregateFunction { ref args, .. } => {
-                                // arguments to the aggregate function
-                                let aggr_func_args: Result<
-                                    Vec<Value>,
-                                > = args.iter().map(|e| (*e)(b.as_ref())).collect();
-
-                                // push the column onto the vector
-                                aggr_col_args.push(aggr_func_args.unwrap());
-                            }
-                            _ => panic!(),
-                        }
+        for batch in self.input.scan() {
+            let b = batch?;
+            //println!("Processing aggregates for batch with {} rows", b.num_rows());
+
+            // evaluate the single argument to each aggregate function
+            let mut aggr_col_args: Vec<Vec<Value>> = Vec::with_capacity(aggr_expr.len());
+            for i in 0..aggr_expr.len() {
+                match aggr_expr[i] {
+                    RuntimeExpr::AggregateFunction { ref args, .. } => {
+                        // arguments to the aggregate function
+                        let aggr_func_args: Result<Vec<Value>> =
+                            args.iter().map(|e| (*e)(b.as_ref())).collect();
+
+                        // push the column onto the vector
+                        aggr_col_args.push(aggr_func_args?);
                     }
+                    _ => return Err(not_an_aggregate()),
+                }
+            }
 
-                    // evaluate the grouping expressions
-                    let group_values_result: Result<Vec<Value>> = group_expr
-                        .iter()
-                        .map(|e| e.get_func()(b.as_ref()))
-                        .collect();
-
-                    let group_values: Vec<Value> = group_values_result.unwrap(); //TODO
-
-                    if group_values.len() == 0 {
-                        // aggregate columns directly
-                        let key: Vec<GroupScalar> = Vec::with_capacity(0);
-
-                        let entry = map
-                            .entry(key)
-                            .or_insert_with(|| create_aggregate_entry(aggr_expr));
-                        let mut entry_mut = entry.borrow_mut();
-
-                        for i in 0..aggr_expr.len() {
-                            (*entry_mut).aggr_values[i]
-                                .execute(&aggr_col_args[i])
-                                .unwrap();
-                        }
-                    } else {
-                        let mut key: Vec<GroupScalar> = Vec::with_capacity(group_values.len());
-                        for _ in 0..group_values.len() {
-                            key.push(GroupScalar::Int32(0));
-                        }
-
-                        // expensive row-based aggregation by group
-                        for i in 0..b.num_rows() {
-                            write_key(&mut key, &group_values, i);
-                            //let key = make_key(&group_values, i);
-                            //println!("key = {:?}", key);
-
-                            let x = match map.get(&key) {
-                                Some(entry) => {
-                                    let mut entry_mut = entry.borrow_mut();
-
-                                    for j in 0..aggr_expr.len() {
-                                        let row_aggr_values: Vec<Value> = aggr_col_args[j].iter()
-                                            .map(|col| match col {
-                                                Value::Column(ref col) => Value::Scalar(Rc::new(get_value(col, i))),
-                                                Value::Scalar(ref v) => Value::Scalar(v.clone())
-                                            }).collect();
-                                        (*entry_mut).aggr_values[j]
-                                            .execute(&row_aggr_values)
-                                            .unwrap();
-                                    }
-
-                                    true
-                                }
-                                None => false,
-                            };
-
-                            if !x {
-                                let entry = create_aggregate_entry(aggr_expr);
-                                {
-                                    let mut entry_mut = entry.borrow_mut();
-
-                                    for j in 0..aggr_expr.len() {
-                                        let row_aggr_values: Vec<Value> = aggr_col_args[j].iter()
-                                            .map(|col| match col {
-                                                Value::Column(ref col) =>
-                                                    Value::Scalar(Rc::new(get_value(col, i))),
-                                                Value::Scalar(ref v) => Value::Scalar(v.clone())
-                                            }).collect();
-                                        (*entry_mut).aggr_values[j]
-                                            .execute(&row_aggr_values)
-                                            .unwrap();
-                                    }
+            // evaluate the grouping expressions
+            let group_values_result: Result<Vec<Value>> = group_expr
+                .iter()
+                .map(|e| e.get_func()(b.as_ref()))
+                .collect();
+
+            let group_values: Vec<Value> = group_values_result?;
+
+            if group_values.len() == 0 {
+                // aggregate columns directly
+                let key: Vec<GroupScalar> = Vec::with_capacity(0);
+
+                if !map.contains_key(&key) {
+                    map.insert(key.clone(), create_aggregate_entry(aggr_expr)?);
+                }
+                let mut entry_mut = map[&key].borrow_mut();
+
+                for i in 0..aggr_expr.len() {
+                    (*entry_mut).aggr_values[i].execute(&aggr_col_args[i])?;
+                }
+            } else {
+                let mut key: Vec<GroupScalar> = Vec::with_capacity(group_values.len());
+                for _ in 0..group_values.len() {
+                    key.push(GroupScalar::Int32(0));
+                }
+
+                // expensive row-based aggregation by group
+                for i in 0..b.num_rows() {
+                    write_key(&mut key, &group_values, i)?;
+                    //let key = make_key(&group_values, i);
+                    //println!("key = {:?}", key);
+
+                    if !map.contains_key(&key) {
+                        map.insert(key.clone(), create_aggregate_entry(aggr_expr)?);
+                    }
+                    let mut entry_mut = map[&key].borrow_mut();
+
+                    for j in 0..aggr_expr.len() {
+                        let row_aggr_values: Vec<Value> = aggr_col_args[j]
+                            .iter()
+                            .map(|col| match col {
+                                Value::Column(ref col) => {
+                                    Value::Scalar(Rc::new(get_value(col, i)))
                                 }
-                                map.insert(key.clone(), entry);
-                            }
-                        }
+                                Value::Scalar(ref v) => Value::Scalar(v.clone()),
+                            })
+                            .collect();
+                        (*entry_mut).aggr_values[j].execute(&row_aggr_values)?;
                     }
                 }
-                Err(e) => panic!("Error aggregating batch: {:?}", e),
             }
-        });
+        }
 
         //        println!("Preparing results");
 
@@ -285,15 +389,19 @@ impl SimpleRelation for AggregateRelation {
                 .borrow()
                 .aggr_values
                 .iter()
-                .map(|v| v.finish().unwrap())
-                .collect();
+                .map(|v| v.finish())
+                .collect::<Result<Vec<Value>>>()?;
 
             //            println!("aggregate entry: {:?}", g);
 
             for col_index in 0..g.len() {
                 result_columns[col_index + group_expr.len()].push(match g[col_index] {
                     Value::Scalar(ref v) => v.as_ref().clone(),
-                    _ => panic!(),
+                    _ => {
+                        return Err(ExecutionError::General(
+                            "Aggregate function did not produce a scalar value".to_string(),
+                        ))
+                    }
                 });
             }
         }
@@ -307,13 +415,11 @@ impl SimpleRelation for AggregateRelation {
         // create Arrow arrays from grouping scalar values
         for i in 0..group_expr.len() {
             //TODO: should not use string version of group keys
-            let mut tmp: Vec<String> = vec![];
-            for v in &result_columns[i] {
-                tmp.push(format!("{}", v));
-            }
-            aggr_batch
-                .data
-                .push(Value::Column(Rc::new(Array::from(tmp))));
+            let array = build_nullable_array(&DataType::Utf8, &result_columns[i], |values| {
+                let tmp: Vec<String> = values.iter().map(|v| format!("{}", v)).collect();
+                Ok(Array::from(tmp))
+            })?;
+            aggr_batch.data.push(Value::Column(Rc::new(array)));
         }
 
         // create Arrow arrays from aggregate scalar values
@@ -322,46 +428,39 @@ impl SimpleRelation for AggregateRelation {
                 RuntimeExpr::AggregateFunction { ref t, .. } => {
                     let aggr_values = &result_columns[i + group_expr.len()];
 
-                    let array: Array = match t {
-                        DataType::Boolean => build_aggregate_array!(bool, get_bool, aggr_values),
-                        DataType::UInt8 => build_aggregate_array!(u8, get_u8, aggr_values),
-                        DataType::UInt16 => build_aggregate_array!(u16, get_u16, aggr_values),
-                        DataType::UInt32 => build_aggregate_array!(u32, get_u32, aggr_values),
-                        DataType::UInt64 => build_aggregate_array!(u64, get_u64, aggr_values),
-                        DataType::Int8 => build_aggregate_array!(i8, get_i8, aggr_values),
-                        DataType::Int16 => build_aggregate_array!(i16, get_i16, aggr_values),
-                        DataType::Int32 => build_aggregate_array!(i32, get_i32, aggr_values),
-                        DataType::Int64 => build_aggregate_array!(i64, get_i64, aggr_values),
-                        DataType::Float32 => build_aggregate_array!(f32, get_f32, aggr_values),
-                        DataType::Float64 => build_aggregate_array!(f64, get_f64, aggr_values),
-                        DataType::Utf8 => {
-                            let mut b: ListBuilder<u8> =
-                                ListBuilder::with_capacity(aggr_values.len());
-                            for v in aggr_values {
-                                b.push(v.get_string().unwrap().as_bytes());
-                            }
-                            Array::new(
-                                aggr_values.len(),
-                                ArrayData::Utf8(ListArray::from(b.finish())),
-                            )
-                        }
-                        _ => unimplemented!("No support for aggregate with return type {:?}", t),
-                    };
+                    let array = build_nullable_array(t, aggr_values, |values| {
+                        build_aggregate_result(t, values)
+                    })?;
 
                     aggr_batch.data.push(Value::Column(Rc::new(array)))
                 }
-                _ => panic!(),
+                _ => return Err(not_an_aggregate()),
             }
         }
 
-        let tb: Rc<RecordBatch> = Rc::new(aggr_batch);
+        Ok(Rc::new(aggr_batch))
+    }
+}
 
+impl SimpleRelation for AggregateRelation {
+    fn scan<'a>(&'a mut self) -> Box<Iterator<Item = Result<Rc<RecordBatch>>> + 'a> {
         // create iterator over the single batch
-        let v = vec![Ok(tb)];
-        Box::new(v.into_iter())
+        Box::new(iter::once(self.aggregate()))
     }
 
     fn schema<'a>(&'a self) -> &'a Schema {
         self.schema.as_ref()
     }
+
+    fn describe(&self) -> String {
+        format!(
+            "AggregateRelation: group_expr={}, aggr_expr={}",
+            self.group_expr.len(),
+            self.aggr_expr.len()
+        )
+    }
+
+    fn inputs(&self) -> Vec<&SimpleRelation> {
+        vec![self.input.as_ref()]
+    }
 }
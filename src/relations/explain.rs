@@ -0,0 +1,84 @@
+// Copyright 2018 Grove Enterprises LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Explain Relation
+
+use std::iter;
+use std::rc::Rc;
+
+use super::super::datasources::common::*;
+use super::super::datatypes::Schema;
+use super::super::errors::*;
+use super::super::exec::*;
+use super::super::logical::StringifiedPlan;
+use super::super::types::*;
+
+use arrow::array::ListArray;
+use arrow::list_builder::*;
+
+/// Produces the plans collected for an EXPLAIN statement as a single batch with one row per
+/// plan line
+pub struct ExplainRelation {
+    schema: Rc<Schema>,
+    plans: Vec<StringifiedPlan>,
+}
+
+impl ExplainRelation {
+    pub fn new(schema: Rc<Schema>, plans: Vec<StringifiedPlan>) -> Self {
+        ExplainRelation { schema, plans }
+    }
+
+    fn create_batch(&self) -> Rc<RecordBatch> {
+        let mut plan_types: Vec<&str> = vec![];
+        let mut lines: Vec<&str> = vec![];
+        for p in &self.plans {
+            for line in p.plan.lines() {
+                plan_types.push(&p.plan_type);
+                lines.push(line);
+            }
+        }
+
+        let row_count = lines.len();
+        Rc::new(DefaultRecordBatch {
+            schema: self.schema.clone(),
+            data: vec![utf8_column(&plan_types), utf8_column(&lines)],
+            row_count,
+        })
+    }
+}
+
+fn utf8_column(values: &Vec<&str>) -> Value {
+    let mut b: ListBuilder<u8> = ListBuilder::with_capacity(values.len());
+    for v in values {
+        b.push(v.as_bytes());
+    }
+    Value::Column(Rc::new(Array::new(
+        values.len(),
+        ArrayData::Utf8(ListArray::from(b.finish())),
+    )))
+}
+
+impl SimpleRelation for ExplainRelation {
+    fn scan<'a>(&'a mut self) -> Box<Iterator<Item = Result<Rc<RecordBatch>>> + 'a> {
+        Box::new(iter::once(Ok(self.create_batch())))
+    }
+
+    fn describe(&self) -> String {
+        String::from("ExplainRelation")
+    }
+
+    fn schema<'a>(&'a self) -> &'a Schema {
+        self.schema.as_ref()
+    }
+}
@@ -14,13 +14,15 @@
 
 use std::rc::Rc;
 
+use arrow::array::PrimitiveArray;
+
 use super::super::datasources::common::*;
+use super::super::datatypes::*;
 use super::super::errors::*;
 use super::super::exec::*;
 use super::super::types::*;
 
 //use arrow::array::*;
-use arrow::datatypes::*;
 
 pub struct FilterRelation {
     input: Box<SimpleRelation>,
@@ -43,161 +45,292 @@ impl SimpleRelation for FilterRelation {
                 Ok(ref batch) => {
                     //println!("FilterRelation batch {} rows with {} columns", batch.num_rows(), batch.num_columns());
 
-                    assert!(batch.num_rows() > 0);
                     // evaluate the filter expression for every row in the batch
                     let x = (*filter_expr)(batch.as_ref())?;
 
-                    match x {
-                        Value::Column(ref filter_eval) => {
-                            assert_eq!(batch.num_rows(), filter_eval.len());
-
-                            let filtered_columns: Vec<Value> = (0..batch.num_columns())
-                                .map(move |column_index| {
-                                    //println!("Filtering column {}", column_index);
-                                    let column = batch.column(column_index);
-                                    Value::Column(Rc::new(filter(column, &filter_eval)))
-                                })
-                                .collect();
-
-                            let row_count_opt: Option<usize> = filtered_columns
-                                .iter()
-                                .map(|c| match c {
-                                    Value::Scalar(_) => 1,
-                                    Value::Column(ref v) => v.len(),
-                                })
-                                .max();
-
-                            //TODO: should ge able to something like `row_count_opt.or_else(0)` ?
-                            let row_count = match row_count_opt {
-                                None => 0,
-                                Some(n) => n,
+                    // a constant predicate, such as a comparison with a null parameter,
+                    // selects every row or none of them
+                    let filter_eval: Rc<Array> = match x {
+                        Value::Column(array) => array,
+                        Value::Scalar(ref v) => {
+                            let selected = match v.as_ref() {
+                                ScalarValue::Boolean(b) => *b,
+                                ScalarValue::Null => false,
+                                _ => {
+                                    return Err(ExecutionError::type_mismatch(
+                                        "Filter expression expected to be boolean".to_string(),
+                                    ))
+                                }
                             };
+                            Rc::new(Array::from(vec![selected; batch.num_rows()]))
+                        }
+                    };
 
-                            //println!("Filtered batch has {} rows out of original {}", row_count, batch.num_rows());
+                    if batch.num_rows() != filter_eval.len() {
+                        return Err(ExecutionError::General(format!(
+                            "Filter expression produced {} values for {} rows",
+                            filter_eval.len(),
+                            batch.num_rows()
+                        )));
+                    }
 
-                            let filtered_batch: Rc<RecordBatch> = Rc::new(DefaultRecordBatch {
-                                row_count,
-                                data: filtered_columns,
-                                schema: schema.clone(),
-                            });
+                    let filtered_columns: Vec<Value> = (0..batch.num_columns())
+                        .map(move |column_index| {
+                            //println!("Filtering column {}", column_index);
+                            let column = batch.column(column_index);
+                            Ok(Value::Column(Rc::new(filter(column, &filter_eval)?)))
+                        })
+                        .collect::<Result<Vec<Value>>>()?;
 
-                            Ok(filtered_batch)
-                        }
-                        Value::Scalar(_) => unimplemented!("Cannot filter on a scalar value yet"), //TODO: implement
-                    }
+                    let row_count_opt: Option<usize> = filtered_columns
+                        .iter()
+                        .map(|c| match c {
+                            Value::Scalar(_) => 1,
+                            Value::Column(ref v) => v.len(),
+                        })
+                        .max();
+
+                    //TODO: should ge able to something like `row_count_opt.or_else(0)` ?
+                    let row_count = match row_count_opt {
+                        None => 0,
+                        Some(n) => n,
+                    };
+
+                    //println!("Filtered batch has {} rows out of original {}", row_count, batch.num_rows());
+
+                    let filtered_batch: Rc<RecordBatch> = Rc::new(DefaultRecordBatch {
+                        row_count,
+                        data: filtered_columns,
+                        schema: schema.clone(),
+                    });
+
+                    Ok(filtered_batch)
                 }
                 Err(e) => Err(e),
             }
         }))
     }
 
+    fn describe(&self) -> String {
+        String::from("FilterRelation")
+    }
+
+    fn inputs(&self) -> Vec<&SimpleRelation> {
+        vec![self.input.as_ref()]
+    }
+
     fn schema<'a>(&'a self) -> &'a Schema {
         &self.input.schema()
     }
 }
 
-pub fn filter(column: &Value, bools: &Array) -> Array {
-    match column {
+pub fn filter(column: &Value, bools: &Array) -> Result<Array> {
+    // rows where the filter expression is null are not selected
+    let b: Vec<bool> = match bools.data() {
+        &ArrayData::Boolean(ref b) => (0..bools.len())
+            .map(|i| bools.is_valid(i) && *b.get(i))
+            .collect(),
+        _ => {
+            return Err(ExecutionError::type_mismatch(
+                "Filter array expected to be boolean".to_string(),
+            ))
+        }
+    };
+    let filtered = match column {
         &Value::Scalar(ref v) => match v.as_ref() {
             ScalarValue::Null => {
                 let b: Vec<i32> = vec![];
                 Array::from(b)
             }
-            _ => unimplemented!("unsupported scalar type for filter '{:?}'", v),
+            _ => {
+                return Err(ExecutionError::type_mismatch(format!(
+                    "unsupported scalar type for filter '{:?}'",
+                    v
+                )))
+            }
         },
-        &Value::Column(ref arr) => match bools.data() {
-            &ArrayData::Boolean(ref b) => match arr.as_ref().data() {
-                &ArrayData::Boolean(ref v) => Array::from(
-                    v.iter()
-                        .zip(b.iter())
-                        .filter(|&(_, f)| f)
-                        .map(|(v, _)| v)
-                        .collect::<Vec<bool>>(),
-                ),
-                &ArrayData::Float32(ref v) => Array::from(
-                    v.iter()
-                        .zip(b.iter())
-                        .filter(|&(_, f)| f)
-                        .map(|(v, _)| v)
-                        .collect::<Vec<f32>>(),
-                ),
-                &ArrayData::Float64(ref v) => Array::from(
-                    v.iter()
-                        .zip(b.iter())
-                        .filter(|&(_, f)| f)
-                        .map(|(v, _)| v)
-                        .collect::<Vec<f64>>(),
-                ),
-                &ArrayData::UInt8(ref v) => Array::from(
-                    v.iter()
-                        .zip(b.iter())
-                        .filter(|&(_, f)| f)
-                        .map(|(v, _)| v)
-                        .collect::<Vec<u8>>(),
-                ),
-                &ArrayData::UInt16(ref v) => Array::from(
-                    v.iter()
-                        .zip(b.iter())
-                        .filter(|&(_, f)| f)
-                        .map(|(v, _)| v)
-                        .collect::<Vec<u16>>(),
-                ),
-                &ArrayData::UInt32(ref v) => Array::from(
-                    v.iter()
-                        .zip(b.iter())
-                        .filter(|&(_, f)| f)
-                        .map(|(v, _)| v)
-                        .collect::<Vec<u32>>(),
-                ),
-                &ArrayData::UInt64(ref v) => Array::from(
-                    v.iter()
-                        .zip(b.iter())
-                        .filter(|&(_, f)| f)
-                        .map(|(v, _)| v)
-                        .collect::<Vec<u64>>(),
-                ),
-                &ArrayData::Int8(ref v) => Array::from(
-                    v.iter()
-                        .zip(b.iter())
-                        .filter(|&(_, f)| f)
-                        .map(|(v, _)| v)
-                        .collect::<Vec<i8>>(),
-                ),
-                &ArrayData::Int16(ref v) => Array::from(
-                    v.iter()
-                        .zip(b.iter())
-                        .filter(|&(_, f)| f)
-                        .map(|(v, _)| v)
-                        .collect::<Vec<i16>>(),
-                ),
-                &ArrayData::Int32(ref v) => Array::from(
-                    v.iter()
-                        .zip(b.iter())
-                        .filter(|&(_, f)| f)
-                        .map(|(v, _)| v)
-                        .collect::<Vec<i32>>(),
-                ),
-                &ArrayData::Int64(ref v) => Array::from(
-                    v.iter()
-                        .zip(b.iter())
-                        .filter(|&(_, f)| f)
-                        .map(|(v, _)| v)
-                        .collect::<Vec<i64>>(),
-                ),
-                &ArrayData::Utf8(ref v) => {
-                    //println!("utf8 len = {}, bools len = {}", v.len(), b.len());
-                    let mut x: Vec<String> = Vec::with_capacity(b.len() as usize);
-                    for i in 0..b.len() as usize {
-                        if *b.get(i) {
-                            //println!("i = {}", i);
-                            x.push(String::from_utf8(v.get(i as usize).to_vec()).unwrap());
-                        }
+        &Value::Column(ref arr) => match arr.as_ref().data() {
+            &ArrayData::Boolean(ref v) => Array::from(
+                v.iter()
+                    .zip(b.iter())
+                    .filter(|&(_, f)| *f)
+                    .map(|(v, _)| v)
+                    .collect::<Vec<bool>>(),
+            ),
+            &ArrayData::Float32(ref v) => Array::from(
+                v.iter()
+                    .zip(b.iter())
+                    .filter(|&(_, f)| *f)
+                    .map(|(v, _)| v)
+                    .collect::<Vec<f32>>(),
+            ),
+            &ArrayData::Float64(ref v) => Array::from(
+                v.iter()
+                    .zip(b.iter())
+                    .filter(|&(_, f)| *f)
+                    .map(|(v, _)| v)
+                    .collect::<Vec<f64>>(),
+            ),
+            &ArrayData::UInt8(ref v) => Array::from(
+                v.iter()
+                    .zip(b.iter())
+                    .filter(|&(_, f)| *f)
+                    .map(|(v, _)| v)
+                    .collect::<Vec<u8>>(),
+            ),
+            &ArrayData::UInt16(ref v) => Array::from(
+                v.iter()
+                    .zip(b.iter())
+                    .filter(|&(_, f)| *f)
+                    .map(|(v, _)| v)
+                    .collect::<Vec<u16>>(),
+            ),
+            &ArrayData::UInt32(ref v) => Array::from(
+                v.iter()
+                    .zip(b.iter())
+                    .filter(|&(_, f)| *f)
+                    .map(|(v, _)| v)
+                    .collect::<Vec<u32>>(),
+            ),
+            &ArrayData::UInt64(ref v) => Array::from(
+                v.iter()
+                    .zip(b.iter())
+                    .filter(|&(_, f)| *f)
+                    .map(|(v, _)| v)
+                    .collect::<Vec<u64>>(),
+            ),
+            &ArrayData::Int8(ref v) => Array::from(
+                v.iter()
+                    .zip(b.iter())
+                    .filter(|&(_, f)| *f)
+                    .map(|(v, _)| v)
+                    .collect::<Vec<i8>>(),
+            ),
+            &ArrayData::Int16(ref v) => Array::from(
+                v.iter()
+                    .zip(b.iter())
+                    .filter(|&(_, f)| *f)
+                    .map(|(v, _)| v)
+                    .collect::<Vec<i16>>(),
+            ),
+            &ArrayData::Int32(ref v) => Array::from(
+                v.iter()
+                    .zip(b.iter())
+                    .filter(|&(_, f)| *f)
+                    .map(|(v, _)| v)
+                    .collect::<Vec<i32>>(),
+            ),
+            &ArrayData::Int64(ref v) => Array::from(
+                v.iter()
+                    .zip(b.iter())
+                    .filter(|&(_, f)| *f)
+                    .map(|(v, _)| v)
+                    .collect::<Vec<i64>>(),
+            ),
+            &ArrayData::Utf8(ref v) => {
+                //println!("utf8 len = {}, bools len = {}", v.len(), b.len());
+                let mut x: Vec<String> = Vec::with_capacity(b.len() as usize);
+                for i in 0..b.len() as usize {
+                    if b[i] {
+                        //println!("i = {}", i);
+                        x.push(String::from_utf8_lossy(v.get(i as usize)).to_string());
                     }
-                    Array::from(x)
                 }
-                &ArrayData::Struct(ref _v) => unimplemented!("Cannot filter on structs yet"),
-            },
-            _ => panic!("Filter array expected to be boolean"),
+                Array::from(x)
+            }
+            &ArrayData::Binary(ref v) => {
+                let mut x: Vec<Vec<u8>> = Vec::with_capacity(b.len() as usize);
+                for i in 0..b.len() as usize {
+                    if b[i] {
+                        x.push(v.get(i).to_vec());
+                    }
+                }
+                Array::from(x)
+            }
+            &ArrayData::Date32(ref v) => {
+                let values = v
+                    .iter()
+                    .zip(b.iter())
+                    .filter(|&(_, f)| *f)
+                    .map(|(v, _)| v)
+                    .collect::<Vec<i32>>();
+                let len = values.len();
+                Array::new(len, ArrayData::Date32(PrimitiveArray::from(values)))
+            }
+            &ArrayData::Time64(ref v, unit) => {
+                let values = v
+                    .iter()
+                    .zip(b.iter())
+                    .filter(|&(_, f)| *f)
+                    .map(|(v, _)| v)
+                    .collect::<Vec<i64>>();
+                let len = values.len();
+                Array::new(len, ArrayData::Time64(PrimitiveArray::from(values), unit))
+            }
+            &ArrayData::Timestamp(ref v, unit) => {
+                let values = v
+                    .iter()
+                    .zip(b.iter())
+                    .filter(|&(_, f)| *f)
+                    .map(|(v, _)| v)
+                    .collect::<Vec<i64>>();
+                let len = values.len();
+                Array::new(len, ArrayData::Timestamp(PrimitiveArray::from(values), unit))
+            }
+            &ArrayData::Decimal(ref v, precision, scale) => {
+                let values = v
+                    .iter()
+                    .zip(b.iter())
+                    .filter(|&(_, f)| *f)
+                    .map(|(v, _)| *v)
+                    .collect::<Vec<i128>>();
+                let len = values.len();
+                Array::new(len, ArrayData::Decimal(values, precision, scale))
+            }
+            &ArrayData::List(ref offsets, ref values) => {
+                // keep the elements of the selected lists and close up the offsets
+                let mut element_mask: Vec<bool> = Vec::with_capacity(values.len());
+                let mut new_offsets: Vec<usize> = vec![0];
+                for i in 0..b.len() {
+                    let len = offsets[i + 1] - offsets[i];
+                    element_mask.extend(::std::iter::repeat(b[i]).take(len));
+                    if b[i] {
+                        let end = new_offsets[new_offsets.len() - 1] + len;
+                        new_offsets.push(end);
+                    }
+                }
+                let elements = filter(&Value::Column(values.clone()), &Array::from(element_mask))?;
+                Array::new(
+                    new_offsets.len() - 1,
+                    ArrayData::List(new_offsets, Rc::new(elements)),
+                )
+            }
+            &ArrayData::Struct(ref fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|f| Ok(Rc::new(filter(&Value::Column(f.clone()), bools)?)))
+                    .collect::<Result<Vec<Rc<Array>>>>()?;
+                Array::new(
+                    b.iter().filter(|f| **f).count(),
+                    ArrayData::Struct(fields),
+                )
+            }
         },
-    }
+    };
+    // keep the nulls of the selected rows
+    Ok(match column {
+        &Value::Column(ref arr) => match arr.validity() {
+            Some(validity) => {
+                let validity: Vec<bool> = validity
+                    .iter()
+                    .zip(b.iter())
+                    .filter(|&(_, f)| *f)
+                    .map(|(v, _)| *v)
+                    .collect();
+                Array::with_validity(filtered.into_data(), &validity)
+            }
+            None => filtered,
+        },
+        _ => filtered,
+    })
 }
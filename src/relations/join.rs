@@ -0,0 +1,171 @@
+// Copyright 2018 Grove Enterprises LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Hash Join Relation
+
+use std::rc::Rc;
+
+use super::super::datasources::common::*;
+use super::super::datatypes::*;
+use super::super::errors::*;
+use super::super::exec::*;
+use super::super::types::*;
+use super::aggregate::{write_key, GroupScalar};
+
+use fnv::FnvHashMap;
+
+/// Inner equi-join that loads one input into a hash table (the build side) and then streams
+/// the other input (the probe side) through it. The right input is the build side unless
+/// `build_left` is set, and output columns are always the left columns followed by the right.
+pub struct HashJoinRelation {
+    schema: Rc<Schema>,
+    left: Box<SimpleRelation>,
+    right: Box<SimpleRelation>,
+    left_keys: Vec<usize>,
+    right_keys: Vec<usize>,
+    build_left: bool,
+}
+
+impl HashJoinRelation {
+    pub fn new(
+        schema: Rc<Schema>,
+        left: Box<SimpleRelation>,
+        right: Box<SimpleRelation>,
+        left_keys: Vec<usize>,
+        right_keys: Vec<usize>,
+        build_left: bool,
+    ) -> Self {
+        HashJoinRelation {
+            schema,
+            left,
+            right,
+            left_keys,
+            right_keys,
+            build_left,
+        }
+    }
+}
+
+/// Get the values of the key columns of a batch
+fn key_values(batch: &RecordBatch, keys: &Vec<usize>) -> Vec<Value> {
+    keys.iter().map(|k| batch.column(*k).clone()).collect()
+}
+
+impl SimpleRelation for HashJoinRelation {
+    fn scan<'a>(&'a mut self) -> Box<Iterator<Item = Result<Rc<RecordBatch>>> + 'a> {
+        let schema = self.schema.clone();
+        let build_left = self.build_left;
+        let num_left_columns = self.left.schema().columns().len();
+        let (build, probe, build_keys, probe_keys) = if build_left {
+            (&mut self.left, &mut self.right, &self.left_keys, self.right_keys.clone())
+        } else {
+            (&mut self.right, &mut self.left, &self.right_keys, self.left_keys.clone())
+        };
+
+        // build a hash table mapping keys to (batch, row) for the build side
+        let mut build_batches: Vec<Rc<RecordBatch>> = vec![];
+        let mut map: FnvHashMap<Vec<GroupScalar>, Vec<(usize, usize)>> = FnvHashMap::default();
+        for batch in build.scan() {
+            match batch {
+                Ok(b) => {
+                    let key_columns = key_values(b.as_ref(), build_keys);
+                    let mut key: Vec<GroupScalar> = vec![GroupScalar::Int32(0); build_keys.len()];
+                    for row in 0..b.num_rows() {
+                        if let Err(e) = write_key(&mut key, &key_columns, row) {
+                            return Box::new(vec![Err(e)].into_iter());
+                        }
+                        // null keys never match
+                        if key.contains(&GroupScalar::Null) {
+                            continue;
+                        }
+                        map.entry(key.clone())
+                            .or_insert_with(Vec::new)
+                            .push((build_batches.len(), row));
+                    }
+                    build_batches.push(b);
+                }
+                Err(e) => return Box::new(vec![Err(e)].into_iter()),
+            }
+        }
+
+        Box::new(probe.scan().map(move |batch| -> Result<Rc<RecordBatch>> {
+            let b = batch?;
+
+            // find the matching rows for each row in the probe batch
+            let key_columns = key_values(b.as_ref(), &probe_keys);
+            let mut key: Vec<GroupScalar> = vec![GroupScalar::Int32(0); probe_keys.len()];
+            let mut matches: Vec<(usize, usize, usize)> = vec![];
+            for row in 0..b.num_rows() {
+                write_key(&mut key, &key_columns, row)?;
+                if key.contains(&GroupScalar::Null) {
+                    continue;
+                }
+                if let Some(build_rows) = map.get(&key) {
+                    build_rows
+                        .iter()
+                        .for_each(|&(build_batch, build_row)| {
+                            matches.push((row, build_batch, build_row))
+                        });
+                }
+            }
+
+            // build the output columns from the left columns followed by the right columns
+            let mut columns: Vec<Value> = Vec::with_capacity(schema.columns().len());
+            for i in 0..schema.columns().len() {
+                let is_left = i < num_left_columns;
+                let from_probe = is_left != build_left;
+                let column = if is_left { i } else { i - num_left_columns };
+                let values: Vec<ScalarValue> = matches
+                    .iter()
+                    .map(|&(row, build_batch, build_row)| {
+                        if from_probe {
+                            value_at(b.column(column), row)
+                        } else {
+                            value_at(build_batches[build_batch].column(column), build_row)
+                        }
+                    })
+                    .collect();
+                let data_type = schema.column(i).data_type();
+                let array = build_nullable_array(data_type, &values, |values| {
+                    array_from_scalars(data_type, values)
+                })?;
+                columns.push(Value::Column(Rc::new(array)));
+            }
+
+            let joined_batch: Rc<RecordBatch> = Rc::new(DefaultRecordBatch {
+                schema: schema.clone(),
+                data: columns,
+                row_count: matches.len(),
+            });
+            Ok(joined_batch)
+        }))
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "HashJoinRelation: left_keys={:?}, right_keys={:?}, build_side={}",
+            self.left_keys,
+            self.right_keys,
+            if self.build_left { "left" } else { "right" }
+        )
+    }
+
+    fn inputs(&self) -> Vec<&SimpleRelation> {
+        vec![self.left.as_ref(), self.right.as_ref()]
+    }
+
+    fn schema<'a>(&'a self) -> &'a Schema {
+        self.schema.as_ref()
+    }
+}
@@ -17,9 +17,9 @@
 use std::rc::Rc;
 
 use super::super::datasources::common::*;
+use super::super::datatypes::Schema;
 use super::super::errors::*;
 use super::super::exec::*;
-use arrow::datatypes::Schema;
 
 pub struct LimitRelation {
     schema: Rc<Schema>,
@@ -63,6 +63,14 @@ impl SimpleRelation for LimitRelation {
         }))
     }
 
+    fn describe(&self) -> String {
+        format!("LimitRelation: limit={}", self.limit)
+    }
+
+    fn inputs(&self) -> Vec<&SimpleRelation> {
+        vec![self.input.as_ref()]
+    }
+
     fn schema<'a>(&'a self) -> &'a Schema {
         self.schema.as_ref()
     }
@@ -13,6 +13,10 @@
 // limitations under the License.
 
 pub mod aggregate;
+pub mod explain;
 pub mod filter;
+pub mod join;
 pub mod limit;
 pub mod projection;
+pub mod union;
+pub mod unnest;
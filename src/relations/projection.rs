@@ -17,12 +17,11 @@
 use std::rc::Rc;
 
 use super::super::datasources::common::*;
+use super::super::datatypes::*;
 use super::super::errors::*;
 use super::super::exec::*;
 use super::super::types::*;
 
-use arrow::datatypes::*;
-
 pub struct ProjectRelation {
     schema: Rc<Schema>,
     input: Box<SimpleRelation>,
@@ -64,6 +63,20 @@ impl SimpleRelation for ProjectRelation {
         Box::new(projection_iter)
     }
 
+    fn describe(&self) -> String {
+        let names: Vec<String> = self
+            .schema
+            .columns()
+            .iter()
+            .map(|f| f.name().clone())
+            .collect();
+        format!("ProjectRelation: [{}]", names.join(", "))
+    }
+
+    fn inputs(&self) -> Vec<&SimpleRelation> {
+        vec![self.input.as_ref()]
+    }
+
     fn schema<'a>(&'a self) -> &'a Schema {
         self.schema.as_ref()
     }
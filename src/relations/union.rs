@@ -0,0 +1,52 @@
+// Copyright 2018 Grove Enterprises LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Union Relation
+
+use std::rc::Rc;
+
+use super::super::datasources::common::*;
+use super::super::datatypes::Schema;
+use super::super::errors::*;
+use super::super::exec::*;
+
+pub struct UnionRelation {
+    schema: Rc<Schema>,
+    inputs: Vec<Box<SimpleRelation>>,
+}
+
+impl UnionRelation {
+    pub fn new(schema: Rc<Schema>, inputs: Vec<Box<SimpleRelation>>) -> Self {
+        UnionRelation { schema, inputs }
+    }
+}
+
+impl SimpleRelation for UnionRelation {
+    fn scan<'a>(&'a mut self) -> Box<Iterator<Item = Result<Rc<RecordBatch>>> + 'a> {
+        // batches from each input are returned in turn
+        Box::new(self.inputs.iter_mut().flat_map(|input| input.scan()))
+    }
+
+    fn describe(&self) -> String {
+        String::from("UnionRelation")
+    }
+
+    fn inputs(&self) -> Vec<&SimpleRelation> {
+        self.inputs.iter().map(|i| i.as_ref()).collect()
+    }
+
+    fn schema<'a>(&'a self) -> &'a Schema {
+        self.schema.as_ref()
+    }
+}
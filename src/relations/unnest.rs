@@ -0,0 +1,110 @@
+// Copyright 2018 Grove Enterprises LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Unnest Relation
+
+use std::rc::Rc;
+
+use super::super::datasources::common::*;
+use super::super::datatypes::*;
+use super::super::errors::*;
+use super::super::exec::*;
+use super::super::types::*;
+
+/// Produces a row for each element of a list expression, made up of the columns of the input
+/// row followed by the element
+pub struct UnnestRelation {
+    schema: Rc<Schema>,
+    input: Box<SimpleRelation>,
+    expr: RuntimeExpr,
+}
+
+impl UnnestRelation {
+    pub fn new(schema: Rc<Schema>, input: Box<SimpleRelation>, expr: RuntimeExpr) -> Self {
+        UnnestRelation {
+            schema,
+            input,
+            expr,
+        }
+    }
+}
+
+/// Build a column of the given type from the value of each output row
+fn build_column(data_type: &DataType, values: &Vec<ScalarValue>) -> Result<Value> {
+    let array = build_nullable_array(data_type, values, |values| {
+        array_from_scalars(data_type, values)
+    })?;
+    Ok(Value::Column(Rc::new(array)))
+}
+
+impl SimpleRelation for UnnestRelation {
+    fn scan<'a>(&'a mut self) -> Box<Iterator<Item = Result<Rc<RecordBatch>>> + 'a> {
+        let schema = self.schema.clone();
+        let list_expr = self.expr.get_func();
+
+        Box::new(self.input.scan().map(move |batch| -> Result<Rc<RecordBatch>> {
+            let b = batch?;
+            let lists = (*list_expr)(b.as_ref())?;
+
+            // the input row of each output row, and the element for that row
+            let mut rows: Vec<usize> = vec![];
+            let mut elements: Vec<ScalarValue> = vec![];
+            for row in 0..b.num_rows() {
+                match value_at(&lists, row) {
+                    ScalarValue::Null => {}
+                    list => for element in list.get_list()? {
+                        rows.push(row);
+                        elements.push(element.clone());
+                    },
+                }
+            }
+
+            let num_input_columns = b.num_columns();
+            let mut columns: Vec<Value> = Vec::with_capacity(num_input_columns + 1);
+            for i in 0..num_input_columns {
+                columns.push(match b.column(i) {
+                    &Value::Scalar(ref v) => Value::Scalar(v.clone()),
+                    column => {
+                        let values: Vec<ScalarValue> =
+                            rows.iter().map(|row| value_at(column, *row)).collect();
+                        build_column(schema.column(i).data_type(), &values)?
+                    }
+                });
+            }
+            columns.push(build_column(
+                schema.column(num_input_columns).data_type(),
+                &elements,
+            )?);
+
+            let unnested_batch: Rc<RecordBatch> = Rc::new(DefaultRecordBatch {
+                schema: schema.clone(),
+                data: columns,
+                row_count: rows.len(),
+            });
+            Ok(unnested_batch)
+        }))
+    }
+
+    fn describe(&self) -> String {
+        String::from("UnnestRelation")
+    }
+
+    fn inputs(&self) -> Vec<&SimpleRelation> {
+        vec![self.input.as_ref()]
+    }
+
+    fn schema<'a>(&'a self) -> &'a Schema {
+        self.schema.as_ref()
+    }
+}
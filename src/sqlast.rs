@@ -14,6 +14,10 @@
 
 //! SQL Abstract Syntax Tree (AST) types
 
+use std::fmt;
+
+use super::sqltokenizer::is_keyword;
+
 /// Supported file types for `CREATE EXTERNAL TABLE`
 #[derive(Debug, Clone, PartialEq)]
 pub enum FileType {
@@ -22,13 +26,60 @@ pub enum FileType {
     Parquet,
 }
 
+/// Output formats for `EXPLAIN`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExplainFormat {
+    /// Indented text, one line per plan node
+    Text,
+    /// Graphviz DOT graph
+    Dot,
+}
+
+/// An identifier such as a table or column name. Quoted identifiers are matched
+/// case-sensitively, while unquoted identifiers also match names that only differ by case.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SQLIdent {
+    pub value: String,
+    pub quoted: bool,
+}
+
+impl SQLIdent {
+    /// Create an unquoted identifier
+    pub fn new(value: &str) -> Self {
+        SQLIdent {
+            value: value.to_string(),
+            quoted: false,
+        }
+    }
+
+    /// Create a quoted identifier e.g. `"First Name"`
+    pub fn quoted(value: &str) -> Self {
+        SQLIdent {
+            value: value.to_string(),
+            quoted: true,
+        }
+    }
+}
+
+impl fmt::Display for SQLIdent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.quoted {
+            write!(f, "\"{}\"", self.value.replace("\"", "\"\""))
+        } else {
+            write!(f, "{}", self.value)
+        }
+    }
+}
+
 /// SQL Abstract Syntax Tree (AST)
 #[derive(Debug, Clone, PartialEq)]
 pub enum ASTNode {
-    SQLIdentifier(String),
+    SQLIdentifier(SQLIdent),
     SQLWildcard,
-    SQLCompoundIdentifier(Vec<String>),
+    SQLCompoundIdentifier(Vec<SQLIdent>),
     SQLIsNull(Box<ASTNode>),
+    /// Logical negation e.g. `NOT a`
+    SQLNot(Box<ASTNode>),
     SQLIsNotNull(Box<ASTNode>),
     SQLBinaryExpr {
         left: Box<ASTNode>,
@@ -39,6 +90,11 @@ pub enum ASTNode {
         expr: Box<ASTNode>,
         data_type: SQLType,
     },
+    /// `TRY_CAST(expr AS type)`, which is null where the value cannot be converted
+    SQLTryCast {
+        expr: Box<ASTNode>,
+        data_type: SQLType,
+    },
     SQLNested(Box<ASTNode>),
     SQLUnary {
         operator: SQLOperator,
@@ -47,14 +103,54 @@ pub enum ASTNode {
     SQLLiteralLong(i64),
     SQLLiteralDouble(f64),
     SQLLiteralString(String),
+    /// A binary literal e.g. `X'DEADBEEF'`
+    SQLLiteralBinary(Vec<u8>),
+    SQLLiteralNull,
+    /// A string literal of a date, time or timestamp type e.g. `DATE '2018-01-01'`
+    SQLTypedString {
+        data_type: SQLType,
+        value: String,
+    },
+    /// An interval literal e.g. `INTERVAL '1' DAY` or `INTERVAL '1 day 2 hours'`
+    SQLInterval {
+        value: String,
+        field: Option<SQLDateTimeField>,
+    },
+    /// `EXTRACT(field FROM expr)`, which gets a field such as the year from a date or timestamp
+    SQLExtract {
+        field: String,
+        expr: Box<ASTNode>,
+    },
+    /// Parameter placeholder whose value is bound when a prepared statement is executed
+    SQLParameter(SQLParameter),
     SQLFunction {
         id: String,
         args: Vec<ASTNode>,
     },
+    /// Struct with the given fields, which are named by `AS` or after the expression e.g.
+    /// `STRUCT(lat, lng AS longitude)`
+    SQLStruct(Vec<(ASTNode, Option<String>)>),
+    /// Element of a list at a one-based index e.g. `tags[1]`, or field of a struct e.g.
+    /// `location['lat']`
+    SQLArrayIndex {
+        expr: Box<ASTNode>,
+        index: Box<ASTNode>,
+    },
     SQLOrderBy {
         expr: Box<ASTNode>,
         asc: bool,
     },
+    /// Inner join of two relations on a join condition
+    SQLJoin {
+        left: Box<ASTNode>,
+        right: Box<ASTNode>,
+        on: Box<ASTNode>,
+    },
+    /// A relation followed by `, UNNEST(expr)`, which produces a row for each element of a list
+    SQLUnnest {
+        relation: Box<ASTNode>,
+        expr: Box<ASTNode>,
+    },
     SQLSelect {
         projection: Vec<ASTNode>,
         relation: Option<Box<ASTNode>>,
@@ -76,6 +172,252 @@ pub enum ASTNode {
         /// Path to file or directory contianing files
         location: String,
     },
+    /// Show the plans for a query
+    SQLExplain {
+        /// Whether to show the plan after each optimizer rule
+        verbose: bool,
+        /// Whether to execute the query and show the metrics for each relation
+        analyze: bool,
+        /// The format to show the plans in
+        format: ExplainFormat,
+        /// The query to explain
+        statement: Box<ASTNode>,
+    },
+    /// Collect statistics for a table
+    SQLAnalyze {
+        /// Table name
+        name: SQLIdent,
+    },
+}
+
+/// Quote a name for use as an identifier in SQL, unless it can be used as an unquoted
+/// identifier
+pub fn quote_identifier(name: &str) -> String {
+    let is_plain = name.chars().enumerate().all(|(i, c)| match c {
+        'a'...'z' | 'A'...'Z' | '_' | '@' => true,
+        '0'...'9' => i > 0,
+        _ => false,
+    });
+    if is_plain && !name.is_empty() && !is_keyword(name) {
+        name.to_string()
+    } else {
+        format!("\"{}\"", name.replace("\"", "\"\""))
+    }
+}
+
+/// Quote a string literal for use in SQL
+fn quote_string(s: &str) -> String {
+    format!("'{}'", s.replace("'", "''"))
+}
+
+/// Join items into a comma-separated list
+fn comma_separated<T: fmt::Display>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Precedence of `IS NULL` and `IS NOT NULL`, matching the parser
+const IS_PRECEDENCE: u8 = 15;
+
+/// Precedence of `NOT`, matching the parser, which binds more loosely than comparisons and more
+/// tightly than `AND`
+const NOT_PRECEDENCE: u8 = 12;
+
+/// The precedence of an expression, used to decide whether it needs to be parenthesized when
+/// it is the operand of another expression
+fn precedence(node: &ASTNode) -> u8 {
+    match node {
+        ASTNode::SQLBinaryExpr { op, .. } => op.precedence(),
+        ASTNode::SQLIsNull(_) | ASTNode::SQLIsNotNull(_) => IS_PRECEDENCE,
+        ASTNode::SQLNot(_) => NOT_PRECEDENCE,
+        _ => u8::max_value(),
+    }
+}
+
+/// Write an operand of an expression, in parentheses when `nested` is set
+fn fmt_operand(f: &mut fmt::Formatter, node: &ASTNode, nested: bool) -> fmt::Result {
+    if nested {
+        write!(f, "({})", node)
+    } else {
+        write!(f, "{}", node)
+    }
+}
+
+/// Renders the AST as canonical SQL that parses back to the same AST. Operands are
+/// parenthesized where the precedence of the operators requires it, and identifiers and
+/// string literals are quoted where needed.
+impl fmt::Display for ASTNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ASTNode::SQLIdentifier(id) => write!(f, "{}", id),
+            ASTNode::SQLWildcard => write!(f, "*"),
+            ASTNode::SQLCompoundIdentifier(parts) => {
+                let parts: Vec<String> = parts.iter().map(|p| p.to_string()).collect();
+                write!(f, "{}", parts.join("."))
+            }
+            ASTNode::SQLIsNull(expr) => {
+                fmt_operand(f, expr, precedence(expr) < IS_PRECEDENCE)?;
+                write!(f, " IS NULL")
+            }
+            ASTNode::SQLIsNotNull(expr) => {
+                fmt_operand(f, expr, precedence(expr) < IS_PRECEDENCE)?;
+                write!(f, " IS NOT NULL")
+            }
+            ASTNode::SQLNot(expr) => {
+                write!(f, "NOT ")?;
+                fmt_operand(f, expr, precedence(expr) < NOT_PRECEDENCE)
+            }
+            ASTNode::SQLBinaryExpr { left, op, right } => {
+                // operators are left associative, so a right operand with the same precedence
+                // must be parenthesized
+                fmt_operand(f, left, precedence(left) < op.precedence())?;
+                write!(f, " {} ", op)?;
+                fmt_operand(f, right, precedence(right) <= op.precedence())
+            }
+            ASTNode::SQLCast { expr, data_type } => write!(f, "CAST({} AS {})", expr, data_type),
+            ASTNode::SQLTryCast { expr, data_type } => {
+                write!(f, "TRY_CAST({} AS {})", expr, data_type)
+            }
+            ASTNode::SQLNested(expr) => write!(f, "({})", expr),
+            ASTNode::SQLUnary { operator, rex } => {
+                write!(f, "{}", operator)?;
+                // an operand that starts with a sign, such as a nested unary minus or a negative
+                // number, is parenthesized so that `- -x` is not written as the comment `--x`
+                let operand = rex.to_string();
+                let signed = operand.starts_with('-') || operand.starts_with('+');
+                if signed || precedence(rex) < u8::max_value() {
+                    write!(f, "({})", operand)
+                } else {
+                    write!(f, "{}", operand)
+                }
+            }
+            ASTNode::SQLLiteralLong(n) => write!(f, "{}", n),
+            ASTNode::SQLLiteralDouble(n) => {
+                // the display format never uses an exponent, which SQL numbers cannot have, but
+                // whole numbers need a decimal point to be parsed as doubles again
+                let s = n.to_string();
+                if s.contains('.') || !n.is_finite() {
+                    write!(f, "{}", s)
+                } else {
+                    write!(f, "{}.0", s)
+                }
+            }
+            ASTNode::SQLLiteralString(s) => write!(f, "{}", quote_string(s)),
+            ASTNode::SQLLiteralNull => write!(f, "NULL"),
+            ASTNode::SQLLiteralBinary(bytes) => {
+                write!(f, "X'")?;
+                for b in bytes {
+                    write!(f, "{:02X}", b)?;
+                }
+                write!(f, "'")
+            }
+            ASTNode::SQLTypedString { data_type, value } => {
+                write!(f, "{} {}", data_type, quote_string(value))
+            }
+            ASTNode::SQLInterval { value, field } => {
+                write!(f, "INTERVAL {}", quote_string(value))?;
+                if let Some(field) = field {
+                    write!(f, " {}", field)?;
+                }
+                Ok(())
+            }
+            ASTNode::SQLExtract { field, expr } => write!(f, "EXTRACT({} FROM {})", field, expr),
+            ASTNode::SQLParameter(p) => write!(f, "{}", p),
+            ASTNode::SQLFunction { id, args } => write!(f, "{}({})", id, comma_separated(args)),
+            ASTNode::SQLStruct(fields) => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(expr, name)| match name {
+                        Some(name) => format!("{} AS {}", expr, quote_identifier(name)),
+                        None => expr.to_string(),
+                    })
+                    .collect();
+                write!(f, "STRUCT({})", fields.join(", "))
+            }
+            ASTNode::SQLArrayIndex { expr, index } => {
+                fmt_operand(f, expr, precedence(expr) < u8::max_value())?;
+                write!(f, "[{}]", index)
+            }
+            ASTNode::SQLOrderBy { expr, asc } => {
+                write!(f, "{} {}", expr, if *asc { "ASC" } else { "DESC" })
+            }
+            ASTNode::SQLJoin { left, right, on } => {
+                write!(f, "{} JOIN {} ON {}", left, right, on)
+            }
+            ASTNode::SQLUnnest { relation, expr } => write!(f, "{}, UNNEST({})", relation, expr),
+            ASTNode::SQLSelect {
+                projection,
+                relation,
+                selection,
+                order_by,
+                group_by,
+                having,
+                limit,
+            } => {
+                write!(f, "SELECT {}", comma_separated(projection))?;
+                if let Some(relation) = relation {
+                    write!(f, " FROM {}", relation)?;
+                }
+                if let Some(selection) = selection {
+                    write!(f, " WHERE {}", selection)?;
+                }
+                if let Some(group_by) = group_by {
+                    write!(f, " GROUP BY {}", comma_separated(group_by))?;
+                }
+                if let Some(having) = having {
+                    write!(f, " HAVING {}", having)?;
+                }
+                if let Some(order_by) = order_by {
+                    write!(f, " ORDER BY {}", comma_separated(order_by))?;
+                }
+                if let Some(limit) = limit {
+                    write!(f, " LIMIT {}", limit)?;
+                }
+                Ok(())
+            }
+            ASTNode::SQLCreateTable {
+                name,
+                columns,
+                file_type,
+                header_row,
+                location,
+            } => {
+                write!(f, "CREATE EXTERNAL TABLE {}", quote_identifier(name))?;
+                if !columns.is_empty() {
+                    write!(f, " ({})", comma_separated(columns))?;
+                }
+                match file_type {
+                    FileType::CSV if *header_row => write!(f, " STORED AS CSV WITH HEADER ROW")?,
+                    FileType::CSV => write!(f, " STORED AS CSV WITHOUT HEADER ROW")?,
+                    FileType::NdJson => write!(f, " STORED AS NDJSON")?,
+                    FileType::Parquet => write!(f, " STORED AS PARQUET")?,
+                }
+                write!(f, " LOCATION {}", quote_string(location))
+            }
+            ASTNode::SQLExplain {
+                verbose,
+                analyze,
+                format,
+                statement,
+            } => {
+                write!(f, "EXPLAIN")?;
+                if *format == ExplainFormat::Dot {
+                    write!(f, " (FORMAT DOT)")?;
+                }
+                if *analyze {
+                    write!(f, " ANALYZE")?;
+                }
+                if *verbose {
+                    write!(f, " VERBOSE")?;
+                }
+                write!(f, " {}", statement)
+            }
+            ASTNode::SQLAnalyze { name } => write!(f, "ANALYZE TABLE {}", name),
+        }
+    }
 }
 
 /// SQL column definition
@@ -86,6 +428,24 @@ pub struct SQLColumnDef {
     pub allow_null: bool,
 }
 
+/// A parameter placeholder in a SQL statement
+#[derive(Debug, Clone, PartialEq)]
+pub enum SQLParameter {
+    /// `$1`, `$2` etc. Anonymous `?` placeholders are numbered in the order they appear.
+    Positional(usize),
+    /// `:name`
+    Named(String),
+}
+
+impl fmt::Display for SQLParameter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SQLParameter::Positional(n) => write!(f, "${}", n),
+            SQLParameter::Named(name) => write!(f, ":{}", name),
+        }
+    }
+}
+
 /// SQL datatypes for literals in SQL statements
 #[derive(Debug, Clone, PartialEq)]
 pub enum SQLType {
@@ -101,6 +461,87 @@ pub enum SQLType {
     Float32,
     Double64,
     Utf8(usize),
+    Date,
+    Time,
+    Timestamp,
+    /// Precision and scale
+    Decimal(usize, usize),
+    /// List of values of the element type e.g. `VARCHAR[]`
+    List(Box<SQLType>),
+}
+
+impl fmt::Display for SQLColumnDef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", quote_identifier(&self.name), self.data_type)?;
+        if !self.allow_null {
+            write!(f, " NOT NULL")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SQLType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SQLType::Boolean => write!(f, "BOOLEAN"),
+            SQLType::UInt8 => write!(f, "UINT8"),
+            SQLType::UInt16 => write!(f, "UINT16"),
+            SQLType::UInt32 => write!(f, "UINT32"),
+            SQLType::UInt64 => write!(f, "UINT64"),
+            SQLType::Int8 => write!(f, "INT8"),
+            SQLType::Int16 => write!(f, "INT16"),
+            SQLType::Int32 => write!(f, "INT32"),
+            SQLType::Int64 => write!(f, "INT64"),
+            SQLType::Float32 => write!(f, "FLOAT32"),
+            SQLType::Double64 => write!(f, "FLOAT64"),
+            SQLType::Utf8(n) => write!(f, "VARCHAR({})", n),
+            SQLType::Date => write!(f, "DATE"),
+            SQLType::Time => write!(f, "TIME"),
+            SQLType::Timestamp => write!(f, "TIMESTAMP"),
+            SQLType::Decimal(p, s) => write!(f, "DECIMAL({},{})", p, s),
+            SQLType::List(t) => write!(f, "{}[]", t),
+        }
+    }
+}
+
+/// A field of a date, time or timestamp, which is also the unit of an interval
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SQLDateTimeField {
+    Year,
+    Month,
+    Day,
+    Hour,
+    Minute,
+    Second,
+}
+
+impl SQLDateTimeField {
+    /// Get the field with the given name, ignoring case
+    pub fn from_name(name: &str) -> Option<SQLDateTimeField> {
+        match name.to_uppercase().as_ref() {
+            "YEAR" => Some(SQLDateTimeField::Year),
+            "MONTH" => Some(SQLDateTimeField::Month),
+            "DAY" => Some(SQLDateTimeField::Day),
+            "HOUR" => Some(SQLDateTimeField::Hour),
+            "MINUTE" => Some(SQLDateTimeField::Minute),
+            "SECOND" => Some(SQLDateTimeField::Second),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SQLDateTimeField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            SQLDateTimeField::Year => "YEAR",
+            SQLDateTimeField::Month => "MONTH",
+            SQLDateTimeField::Day => "DAY",
+            SQLDateTimeField::Hour => "HOUR",
+            SQLDateTimeField::Minute => "MINUTE",
+            SQLDateTimeField::Second => "SECOND",
+        };
+        write!(f, "{}", name)
+    }
 }
 
 /// SQL Operator
@@ -119,4 +560,47 @@ pub enum SQLOperator {
     NotEq,
     And,
     Or,
+    StringConcat,
+}
+
+impl SQLOperator {
+    /// The precedence of the operator, matching the parser, where operators with a higher
+    /// precedence bind more tightly
+    pub fn precedence(&self) -> u8 {
+        match self {
+            SQLOperator::Or => 5,
+            SQLOperator::And => 10,
+            SQLOperator::Eq
+            | SQLOperator::NotEq
+            | SQLOperator::Lt
+            | SQLOperator::LtEq
+            | SQLOperator::Gt
+            | SQLOperator::GtEq => 20,
+            SQLOperator::StringConcat => 25,
+            SQLOperator::Plus | SQLOperator::Minus => 30,
+            SQLOperator::Multiply | SQLOperator::Divide | SQLOperator::Modulus => 40,
+        }
+    }
+}
+
+impl fmt::Display for SQLOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let op = match self {
+            SQLOperator::Plus => "+",
+            SQLOperator::Minus => "-",
+            SQLOperator::Multiply => "*",
+            SQLOperator::Divide => "/",
+            SQLOperator::Modulus => "%",
+            SQLOperator::Gt => ">",
+            SQLOperator::Lt => "<",
+            SQLOperator::GtEq => ">=",
+            SQLOperator::LtEq => "<=",
+            SQLOperator::Eq => "=",
+            SQLOperator::NotEq => "!=",
+            SQLOperator::And => "AND",
+            SQLOperator::Or => "OR",
+            SQLOperator::StringConcat => "||",
+        };
+        write!(f, "{}", op)
+    }
 }
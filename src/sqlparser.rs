@@ -14,50 +14,164 @@
 
 //! SQL Parser
 
+use std::error;
+use std::fmt;
+
+use super::decimal;
+use super::functions::binary;
 use super::sqlast::*;
 use super::sqltokenizer::*;
 
+/// A syntax error, recording what the parser expected and the token it found instead
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxError {
+    /// Description of what was expected e.g. `')'` or `an expression`
+    pub expected: String,
+    /// The token that was found, or `None` at the end of the statement
+    pub found: Option<Token>,
+    /// The region of the SQL covered by the token that was found
+    pub span: Span,
+}
+
 #[derive(Debug, Clone)]
 pub enum ParserError {
-    TokenizerError(String),
-    ParserError(String),
+    TokenizerError(TokenizerError),
+    ParserError(SyntaxError),
+}
+
+impl ParserError {
+    /// The region of the SQL where the error occurred
+    pub fn span(&self) -> Span {
+        match self {
+            ParserError::TokenizerError(e) => Span::empty(e.location),
+            ParserError::ParserError(e) => e.span,
+        }
+    }
+
+    /// Render the error followed by the line of SQL where it occurred, with a caret pointing
+    /// at the location of the error
+    pub fn render(&self, sql: &str) -> String {
+        format!("{}\n{}", self, self.span().snippet(sql))
+    }
 }
 
-macro_rules! parser_err {
-    ($MSG:expr) => {
-        Err(ParserError::ParserError($MSG.to_string()))
-    };
+impl fmt::Display for ParserError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParserError::TokenizerError(e) => write!(f, "{}", e),
+            ParserError::ParserError(e) => match e.found {
+                Some(ref token) => write!(
+                    f,
+                    "Expected {}, found {} at {}",
+                    e.expected, token, e.span.start
+                ),
+                None => write!(
+                    f,
+                    "Expected {}, found end of input at {}",
+                    e.expected, e.span.start
+                ),
+            },
+        }
+    }
+}
+
+impl error::Error for ParserError {
+    fn description(&self) -> &str {
+        match self {
+            ParserError::TokenizerError(_) => "SQL tokenizer error",
+            ParserError::ParserError(_) => "SQL syntax error",
+        }
+    }
 }
 
 impl From<TokenizerError> for ParserError {
     fn from(e: TokenizerError) -> Self {
-        ParserError::TokenizerError(format!("{:?}", e))
+        ParserError::TokenizerError(e)
     }
 }
 
 /// SQL Parser
 pub struct Parser {
-    tokens: Vec<Token>,
+    tokens: Vec<TokenWithSpan>,
+    /// Index of the next token
     index: usize,
+    /// Index of the token last requested by `next_token`, which is the number of tokens if the
+    /// end of the statement was reached
+    last: usize,
+    /// Location of the end of the statement
+    end: Location,
+    /// Number of anonymous `?` placeholders parsed so far, used to number the next one
+    anonymous_parameters: usize,
+    /// Whether a numbered `$n` or named `:name` placeholder has been parsed, which cannot be
+    /// mixed with anonymous placeholders
+    explicit_parameters: bool,
 }
 
 impl Parser {
-    /// Parse the specified tokens
+    /// Parse the specified tokens. Errors do not have a location because the tokens do not
+    /// record where they were in the SQL.
     pub fn new(tokens: Vec<Token>) -> Self {
+        let start = Span::empty(Location { line: 1, column: 1 });
+        Parser::with_spans(
+            tokens
+                .into_iter()
+                .map(|token| TokenWithSpan { token, span: start })
+                .collect(),
+        )
+    }
+
+    /// Parse the specified tokens, reporting errors at the locations of the tokens
+    pub fn with_spans(tokens: Vec<TokenWithSpan>) -> Self {
+        let end = match tokens.last() {
+            Some(t) => t.span.end,
+            None => Location { line: 1, column: 1 },
+        };
         Parser {
             tokens: tokens,
             index: 0,
+            last: 0,
+            end,
+            anonymous_parameters: 0,
+            explicit_parameters: false,
         }
     }
 
-    /// Parse a SQL statement and produce an Abstract Syntax Tree (AST)
+    /// Parse a SQL statement, which may be terminated with a semicolon, and produce an
+    /// Abstract Syntax Tree (AST)
     pub fn parse_sql(sql: String) -> Result<ASTNode, ParserError> {
         let mut tokenizer = Tokenizer::new(&sql);
-        let tokens = tokenizer.tokenize()?;
-        let mut parser = Parser::new(tokens);
+        let mut tokens = tokenizer.tokenize_with_spans()?;
+        if tokens.last().map(|t| t.token == Token::SemiColon) == Some(true) {
+            tokens.pop();
+        }
+        let mut parser = Parser::with_spans(tokens);
         parser.parse()
     }
 
+    /// Parse a script containing SQL statements separated by semicolons and produce an AST for
+    /// each statement. Semicolons inside strings, quoted identifiers and comments do not end a
+    /// statement, and empty statements are ignored. The locations in any error are relative
+    /// to the start of the script.
+    pub fn parse_statements(sql: String) -> Result<Vec<ASTNode>, ParserError> {
+        Parser::parse_each_statement(sql)?.into_iter().collect()
+    }
+
+    /// Split a script into statements in the same way as `parse_statements`, then parse each
+    /// statement on its own, so that a syntax error in one statement does not prevent the
+    /// others from being parsed. Only an error in tokenizing the script, such as an
+    /// unterminated string, fails the whole script.
+    pub fn parse_each_statement(
+        sql: String,
+    ) -> Result<Vec<Result<ASTNode, ParserError>>, ParserError> {
+        let mut tokenizer = Tokenizer::new(&sql);
+        let tokens = tokenizer.tokenize_with_spans()?;
+        Ok(tokens
+            .split(|t| t.token == Token::SemiColon)
+            .filter(|statement| !statement.is_empty())
+            .map(|statement| Parser::with_spans(statement.to_vec()).parse())
+            .collect())
+    }
+
     /// Parse a new expression
     pub fn parse(&mut self) -> Result<ASTNode, ParserError> {
         self.parse_expr(0)
@@ -88,6 +202,31 @@ impl Parser {
         Ok(expr)
     }
 
+    /// Parse a parameter placeholder that has just been consumed
+    fn parse_parameter(&mut self, placeholder: &str) -> Result<ASTNode, ParserError> {
+        // anonymous placeholders are numbered in order, so they would be ambiguous alongside
+        // numbered or named placeholders
+        if placeholder == "?" && self.explicit_parameters {
+            return self.expected_last("a $n or :name placeholder, not mixed with '?'");
+        } else if placeholder != "?" && self.anonymous_parameters > 0 {
+            return self.expected_last("a '?' placeholder, not mixed with $n or :name");
+        }
+        let parameter = if placeholder == "?" {
+            self.anonymous_parameters += 1;
+            SQLParameter::Positional(self.anonymous_parameters)
+        } else if placeholder.starts_with('$') {
+            self.explicit_parameters = true;
+            match placeholder[1..].parse::<usize>() {
+                Ok(n) if n > 0 => SQLParameter::Positional(n),
+                _ => return self.expected_last("a parameter number from $1"),
+            }
+        } else {
+            self.explicit_parameters = true;
+            SQLParameter::Named(placeholder[1..].to_string())
+        };
+        Ok(ASTNode::SQLParameter(parameter))
+    }
+
     /// Parse an expression prefix
     fn parse_prefix(&mut self) -> Result<ASTNode, ParserError> {
         match self.next_token() {
@@ -96,71 +235,171 @@ impl Parser {
                     Token::Keyword(k) => match k.to_uppercase().as_ref() {
                         "SELECT" => Ok(self.parse_select()?),
                         "CREATE" => Ok(self.parse_create()?),
-                        _ => return parser_err!(format!("No prefix parser for keyword {}", k)),
+                        "ANALYZE" => Ok(self.parse_analyze()?),
+                        "EXPLAIN" => Ok(self.parse_explain()?),
+                        "DATE" => self.parse_typed_string(SQLType::Date),
+                        "TIME" => self.parse_typed_string(SQLType::Time),
+                        "TIMESTAMP" => self.parse_typed_string(SQLType::Timestamp),
+                        "INTERVAL" => self.parse_interval(),
+                        "NULL" => Ok(ASTNode::SQLLiteralNull),
+                        // NOT binds more loosely than comparisons and more tightly than AND
+                        "NOT" => Ok(ASTNode::SQLNot(Box::new(self.parse_expr(12)?))),
+                        _ => self.expected_last("an expression"),
                     },
                     Token::Mult => Ok(ASTNode::SQLWildcard),
+                    Token::LParen => {
+                        let expr = self.parse_expr(0)?;
+                        self.expect_token(&Token::RParen)?;
+                        Ok(expr)
+                    }
                     Token::Identifier(id) => {
                         match self.peek_token() {
                             Some(Token::LParen) => {
                                 self.next_token(); // skip lparen
                                 match id.to_uppercase().as_ref() {
-                                    "CAST" => self.parse_cast_expression(),
+                                    "CAST" => self.parse_cast_expression(false),
+                                    "TRY_CAST" => self.parse_cast_expression(true),
+                                    "EXTRACT" => self.parse_extract(),
+                                    "STRUCT" => self.parse_struct(),
                                     _ => {
-                                        let args = self.parse_expr_list()?;
-                                        self.next_token(); // skip rparen
+                                        let args = if self.consume_token(&Token::RParen) {
+                                            vec![]
+                                        } else {
+                                            let args = self.parse_expr_list()?;
+                                            self.expect_token(&Token::RParen)?;
+                                            args
+                                        };
                                         Ok(ASTNode::SQLFunction { id, args })
                                     }
                                 }
                             }
                             Some(Token::Period) => {
-                                let mut id_parts: Vec<String> = vec![id];
-                                while self.peek_token() == Some(Token::Period) {
-                                    self.consume_token(&Token::Period)?;
-                                    match self.next_token() {
-                                        Some(Token::Identifier(id)) => id_parts.push(id),
-                                        _ => {
-                                            return parser_err!(format!(
-                                                "Error parsing compound identifier"
-                                            ))
-                                        }
-                                    }
-                                }
-                                Ok(ASTNode::SQLCompoundIdentifier(id_parts))
+                                self.parse_compound_identifier(SQLIdent::new(&id))
                             }
-                            _ => Ok(ASTNode::SQLIdentifier(id)),
+                            _ => match id.to_uppercase().as_ref() {
+                                // these functions are called without parentheses
+                                "CURRENT_DATE" => Ok(ASTNode::SQLFunction {
+                                    id: "current_date".to_string(),
+                                    args: vec![],
+                                }),
+                                "CURRENT_TIMESTAMP" => Ok(ASTNode::SQLFunction {
+                                    id: "now".to_string(),
+                                    args: vec![],
+                                }),
+                                _ => Ok(ASTNode::SQLIdentifier(SQLIdent::new(&id))),
+                            },
                         }
                     }
+                    Token::QuotedIdentifier(id) => match self.peek_token() {
+                        Some(Token::Period) => {
+                            self.parse_compound_identifier(SQLIdent::quoted(&id))
+                        }
+                        _ => Ok(ASTNode::SQLIdentifier(SQLIdent::quoted(&id))),
+                    },
                     Token::Number(ref n) if n.contains(".") => match n.parse::<f64>() {
                         Ok(n) => Ok(ASTNode::SQLLiteralDouble(n)),
-                        Err(e) => parser_err!(format!("Could not parse '{}' as i64: {}", n, e)),
+                        Err(_) => self.expected_last("a 64-bit floating point number"),
                     },
                     Token::Number(ref n) => match n.parse::<i64>() {
                         Ok(n) => Ok(ASTNode::SQLLiteralLong(n)),
-                        Err(e) => parser_err!(format!("Could not parse '{}' as i64: {}", n, e)),
+                        Err(_) => self.expected_last("a 64-bit integer"),
                     },
                     Token::String(ref s) => Ok(ASTNode::SQLLiteralString(s.to_string())),
-                    _ => parser_err!(format!(
-                        "Prefix parser expected a keyword but found {:?}",
-                        t
-                    )),
+                    Token::HexString(ref s) => match binary::decode_hex(s) {
+                        Ok(bytes) => Ok(ASTNode::SQLLiteralBinary(bytes)),
+                        Err(_) => self.expected_last("a hex string"),
+                    },
+                    Token::Placeholder(ref p) => self.parse_parameter(p),
+                    _ => self.expected_last("an expression"),
                 }
             }
-            None => parser_err!(format!("Prefix parser expected a keyword but hit EOF")),
+            None => self.expected_last("an expression"),
         }
     }
 
-    /// Parse a SQL CAST function e.g. `CAST(expr AS FLOAT)`
-    fn parse_cast_expression(&mut self) -> Result<ASTNode, ParserError> {
-        let expr = self.parse_expr(0)?;
-        self.consume_token(&Token::Keyword("AS".to_string()))?;
+    /// Parse the remaining parts of a compound identifier such as `t.a` after the first part
+    fn parse_compound_identifier(&mut self, first: SQLIdent) -> Result<ASTNode, ParserError> {
+        let mut id_parts: Vec<SQLIdent> = vec![first];
+        while self.consume_token(&Token::Period) {
+            match self.next_identifier() {
+                Some(id) => id_parts.push(id),
+                None => return self.expected_last("an identifier after '.'"),
+            }
+        }
+        Ok(ASTNode::SQLCompoundIdentifier(id_parts))
+    }
+
+    /// Parse a SQL CAST function e.g. `CAST(expr AS FLOAT)`, or a TRY_CAST if `try_cast` is
+    /// true
+    fn parse_cast_expression(&mut self, try_cast: bool) -> Result<ASTNode, ParserError> {
+        let expr = Box::new(self.parse_expr(0)?);
+        self.expect_token(&Token::Keyword("AS".to_string()))?;
         let data_type = self.parse_data_type()?;
-        self.consume_token(&Token::RParen)?;
-        Ok(ASTNode::SQLCast {
+        self.expect_token(&Token::RParen)?;
+        if try_cast {
+            Ok(ASTNode::SQLTryCast { expr, data_type })
+        } else {
+            Ok(ASTNode::SQLCast { expr, data_type })
+        }
+    }
+
+    /// Parse `EXTRACT(field FROM expr)`, after the opening parenthesis
+    fn parse_extract(&mut self) -> Result<ASTNode, ParserError> {
+        let field = match self.next_token() {
+            Some(Token::Identifier(id)) => id.to_uppercase(),
+            _ => return self.expected_last("a date/time field such as YEAR"),
+        };
+        self.expect_token(&Token::Keyword("FROM".to_string()))?;
+        let expr = self.parse_expr(0)?;
+        self.expect_token(&Token::RParen)?;
+        Ok(ASTNode::SQLExtract {
+            field,
             expr: Box::new(expr),
-            data_type,
         })
     }
 
+    /// Parse `STRUCT(expr [AS name], ...)`, after the opening parenthesis
+    fn parse_struct(&mut self) -> Result<ASTNode, ParserError> {
+        let mut fields: Vec<(ASTNode, Option<String>)> = vec![];
+        loop {
+            let expr = self.parse_expr(0)?;
+            let name = if self.parse_keyword("AS") {
+                match self.next_identifier() {
+                    Some(id) => Some(id.value),
+                    None => return self.expected_last("a field name after AS"),
+                }
+            } else {
+                None
+            };
+            fields.push((expr, name));
+            if !self.consume_token(&Token::Comma) {
+                break;
+            }
+        }
+        self.expect_token(&Token::RParen)?;
+        Ok(ASTNode::SQLStruct(fields))
+    }
+
+    /// Parse a date, time or timestamp literal e.g. `DATE '2018-01-01'`, after the type keyword
+    fn parse_typed_string(&mut self, data_type: SQLType) -> Result<ASTNode, ParserError> {
+        let value = self.parse_literal_string()?;
+        Ok(ASTNode::SQLTypedString { data_type, value })
+    }
+
+    /// Parse an interval literal e.g. `INTERVAL '1' DAY`, after the INTERVAL keyword. The unit
+    /// is optional because the string can contain units itself e.g. `INTERVAL '1 day 2 hours'`.
+    fn parse_interval(&mut self) -> Result<ASTNode, ParserError> {
+        let value = self.parse_literal_string()?;
+        let field = match self.peek_token() {
+            Some(Token::Identifier(ref id)) => SQLDateTimeField::from_name(id),
+            _ => None,
+        };
+        if field.is_some() {
+            self.next_token();
+        }
+        Ok(ASTNode::SQLInterval { value, field })
+    }
+
     /// Parse an expression infix (typically an operator)
     fn parse_infix(
         &mut self,
@@ -175,7 +414,7 @@ impl Parser {
                     } else if self.parse_keywords(vec!["NOT", "NULL"]) {
                         Ok(Some(ASTNode::SQLIsNotNull(Box::new(expr))))
                     } else {
-                        parser_err!("Invalid tokens after IS")
+                        self.expected("NULL or NOT NULL")
                     }
                 } else {
                     Ok(Some(ASTNode::SQLBinaryExpr {
@@ -194,12 +433,21 @@ impl Parser {
                 | Token::Minus
                 | Token::Mult
                 | Token::Mod
-                | Token::Div => Ok(Some(ASTNode::SQLBinaryExpr {
+                | Token::Div
+                | Token::StringConcat => Ok(Some(ASTNode::SQLBinaryExpr {
                     left: Box::new(expr),
                     op: self.to_sql_operator(&tok)?,
                     right: Box::new(self.parse_expr(precedence)?),
                 })),
-                _ => parser_err!(format!("No infix parser for token {:?}", tok)),
+                Token::LBracket => {
+                    let index = self.parse_expr(0)?;
+                    self.expect_token(&Token::RBracket)?;
+                    Ok(Some(ASTNode::SQLArrayIndex {
+                        expr: Box::new(expr),
+                        index: Box::new(index),
+                    }))
+                }
+                _ => self.expected_last("an operator"),
             },
             None => Ok(None),
         }
@@ -219,16 +467,17 @@ impl Parser {
             &Token::Mult => Ok(SQLOperator::Multiply),
             &Token::Div => Ok(SQLOperator::Divide),
             &Token::Mod => Ok(SQLOperator::Modulus),
+            &Token::StringConcat => Ok(SQLOperator::StringConcat),
             &Token::Keyword(ref k) if k == "AND" => Ok(SQLOperator::And),
             &Token::Keyword(ref k) if k == "OR" => Ok(SQLOperator::Or),
-            _ => parser_err!(format!("Unsupported SQL operator {:?}", tok)),
+            _ => self.expected_last("an operator"),
         }
     }
 
     /// Get the precedence of the next token
     fn get_next_precedence(&self) -> Result<u8, ParserError> {
         if self.index < self.tokens.len() {
-            self.get_precedence(&self.tokens[self.index])
+            self.get_precedence(&self.tokens[self.index].token)
         } else {
             Ok(0)
         }
@@ -245,38 +494,54 @@ impl Parser {
             &Token::Eq | &Token::Lt | &Token::LtEq | &Token::Neq | &Token::Gt | &Token::GtEq => {
                 Ok(20)
             }
+            &Token::StringConcat => Ok(25),
             &Token::Plus | &Token::Minus => Ok(30),
             &Token::Mult | &Token::Div | &Token::Mod => Ok(40),
+            &Token::LBracket => Ok(50),
             _ => Ok(0),
         }
     }
 
     /// Peek at the next token
     fn peek_token(&mut self) -> Option<Token> {
-        if self.index < self.tokens.len() {
-            Some(self.tokens[self.index].clone())
-        } else {
-            None
-        }
+        self.tokens.get(self.index).map(|t| t.token.clone())
     }
 
-    /// Get the next token and increment the token index
+    /// Get the next token and increment the token index, unless the end of the statement has
+    /// been reached
     fn next_token(&mut self) -> Option<Token> {
-        if self.index < self.tokens.len() {
-            self.index = self.index + 1;
-            Some(self.tokens[self.index - 1].clone())
-        } else {
-            None
+        self.last = self.index;
+        match self.tokens.get(self.index) {
+            Some(t) => {
+                self.index = self.index + 1;
+                Some(t.token.clone())
+            }
+            None => None,
         }
     }
 
-    /// Get the previous token and decrement the token index
-    fn prev_token(&mut self) -> Option<Token> {
-        if self.index > 0 {
-            Some(self.tokens[self.index - 1].clone())
-        } else {
-            None
-        }
+    /// Create an error reporting that the token at `index` is not what was expected
+    fn syntax_error<T>(&self, expected: &str, index: usize) -> Result<T, ParserError> {
+        let (found, span) = match self.tokens.get(index) {
+            Some(t) => (Some(t.token.clone()), t.span),
+            None => (None, Span::empty(self.end)),
+        };
+        Err(ParserError::ParserError(SyntaxError {
+            expected: expected.to_string(),
+            found,
+            span,
+        }))
+    }
+
+    /// Create an error reporting that the next token is not what was expected
+    fn expected<T>(&self, expected: &str) -> Result<T, ParserError> {
+        self.syntax_error(expected, self.index)
+    }
+
+    /// Create an error reporting that the token last returned by `next_token` is not what was
+    /// expected
+    fn expected_last<T>(&self, expected: &str) -> Result<T, ParserError> {
+        self.syntax_error(expected, self.last)
     }
 
     /// Look for an expected keyword and consume it if it exists
@@ -309,81 +574,77 @@ impl Parser {
         true
     }
 
-    //    fn parse_identifier(&mut self) -> Result<ASTNode::SQLIdentifier, Err> {
-    //        let expr = self.parse_expr()?;
-    //        match expr {
-    //            Some(ASTNode::SQLIdentifier { .. }) => Ok(expr),
-    //            _ => parser_err!(format!("Expected identifier but found {:?}", expr)))
-    //        }
-    //    }
+    /// Get the next token as an identifier, or `None` if it is not an identifier
+    fn next_identifier(&mut self) -> Option<SQLIdent> {
+        match self.next_token() {
+            Some(Token::Identifier(id)) => Some(SQLIdent::new(&id)),
+            Some(Token::QuotedIdentifier(id)) => Some(SQLIdent::quoted(&id)),
+            _ => None,
+        }
+    }
+
+    /// Consume the next token if it matches the expected token
+    fn consume_token(&mut self, expected: &Token) -> bool {
+        if self.peek_token().as_ref() == Some(expected) {
+            self.next_token();
+            true
+        } else {
+            false
+        }
+    }
 
     /// Consume the next token if it matches the expected token, otherwise return an error
-    fn consume_token(&mut self, expected: &Token) -> Result<bool, ParserError> {
-        match self.peek_token() {
-            Some(ref t) => if *t == *expected {
-                self.next_token();
-                Ok(true)
-            } else {
-                Ok(false)
-            },
-            _ => parser_err!(format!(
-                "expected token {:?} but was {:?}",
-                expected,
-                self.prev_token()
-            )),
+    fn expect_token(&mut self, expected: &Token) -> Result<(), ParserError> {
+        if self.consume_token(expected) {
+            Ok(())
+        } else {
+            self.expected(&format!("'{}'", expected))
         }
     }
 
     /// Parse a SQL CREATE statement
     fn parse_create(&mut self) -> Result<ASTNode, ParserError> {
         if self.parse_keywords(vec!["EXTERNAL", "TABLE"]) {
-            match self.next_token() {
-                Some(Token::Identifier(id)) => {
+            match self.next_identifier() {
+                Some(id) => {
                     // parse optional column list (schema)
                     let mut columns = vec![];
-                    if self.consume_token(&Token::LParen)? {
+                    if self.consume_token(&Token::LParen) {
                         loop {
-                            if let Some(Token::Identifier(column_name)) = self.next_token() {
-                                if let Ok(data_type) = self.parse_data_type() {
-                                    let allow_null = if self.parse_keywords(vec!["NOT", "NULL"]) {
-                                        false
-                                    } else if self.parse_keyword("NULL") {
-                                        true
-                                    } else {
-                                        true
-                                    };
-
-                                    match self.peek_token() {
-                                        Some(Token::Comma) => {
-                                            self.next_token();
-                                            columns.push(SQLColumnDef {
-                                                name: column_name,
-                                                data_type: data_type,
-                                                allow_null,
-                                            });
-                                        }
-                                        Some(Token::RParen) => {
-                                            self.next_token();
-                                            columns.push(SQLColumnDef {
-                                                name: column_name,
-                                                data_type: data_type,
-                                                allow_null,
-                                            });
-                                            break;
-                                        }
-                                        _ => {
-                                            return parser_err!(
-                                                "Expected ',' or ')' after column definition"
-                                            );
-                                        }
-                                    }
+                            if let Some(column_name) = self.next_identifier() {
+                                let data_type = self.parse_data_type()?;
+                                let allow_null = if self.parse_keywords(vec!["NOT", "NULL"]) {
+                                    false
+                                } else if self.parse_keyword("NULL") {
+                                    true
                                 } else {
-                                    return parser_err!(
-                                        "Error parsing data type in column definition"
-                                    );
+                                    true
+                                };
+
+                                match self.peek_token() {
+                                    Some(Token::Comma) => {
+                                        self.next_token();
+                                        columns.push(SQLColumnDef {
+                                            name: column_name.value,
+                                            data_type: data_type,
+                                            allow_null,
+                                        });
+                                    }
+                                    Some(Token::RParen) => {
+                                        self.next_token();
+                                        columns.push(SQLColumnDef {
+                                            name: column_name.value,
+                                            data_type: data_type,
+                                            allow_null,
+                                        });
+                                        break;
+                                    }
+                                    _ => {
+                                        return self.expected("',' or ')' after column definition");
+                                    }
                                 }
                             } else {
-                                return parser_err!("Error parsing column name");
+                                return self.expected_last("a column name");
                             }
                         }
                     }
@@ -403,59 +664,61 @@ impl Parser {
                     } else if self.parse_keywords(vec!["STORED", "AS", "PARQUET"]) {
                         FileType::Parquet
                     } else {
-                        return parser_err!(format!(
-                            "Expected 'STORED AS' clause, found {:?}",
-                            self.peek_token()
-                        ));
+                        return self.expected("STORED AS CSV, NDJSON or PARQUET");
                     };
 
                     let location: String = if self.parse_keywords(vec!["LOCATION"]) {
                         self.parse_literal_string()?
                     } else {
-                        return parser_err!("Missing 'LOCATION' clause");
+                        return self.expected("LOCATION");
                     };
 
                     Ok(ASTNode::SQLCreateTable {
-                        name: id,
+                        name: id.value,
                         columns,
                         file_type,
                         header_row: headers,
                         location,
                     })
                 }
-                _ => parser_err!(format!(
-                    "Unexpected token after CREATE EXTERNAL TABLE: {:?}",
-                    self.peek_token()
-                )),
+                None => self.expected_last("a table name"),
             }
         } else {
-            parser_err!(format!(
-                "Unexpected token after CREATE: {:?}",
-                self.peek_token()
-            ))
+            self.expected("EXTERNAL TABLE")
         }
     }
 
     /// Parse a literal integer/long
     fn parse_literal_int(&mut self) -> Result<i64, ParserError> {
-        match self.next_token() {
-            Some(Token::Number(s)) => s.parse::<i64>().map_err(|e| {
-                ParserError::ParserError(format!("Could not parse '{}' as i64: {}", s, e))
-            }),
-            other => parser_err!(format!("Expected literal int, found {:?}", other)),
+        if let Some(Token::Number(s)) = self.next_token() {
+            if let Ok(n) = s.parse::<i64>() {
+                return Ok(n);
+            }
         }
+        self.expected_last("a literal integer")
     }
 
     /// Parse a literal string
     fn parse_literal_string(&mut self) -> Result<String, ParserError> {
         match self.next_token() {
             Some(Token::String(ref s)) => Ok(s.clone()),
-            other => parser_err!(format!("Expected literal string, found {:?}", other)),
+            _ => self.expected_last("a literal string"),
         }
     }
 
-    /// Parse a SQL datatype (in the context of a CREATE TABLE statement for example)
+    /// Parse a SQL datatype (in the context of a CREATE TABLE statement for example), where
+    /// each `[]` suffix makes a list of the preceding type e.g. `INT[]`
     fn parse_data_type(&mut self) -> Result<SQLType, ParserError> {
+        let mut data_type = self.parse_element_data_type()?;
+        while self.consume_token(&Token::LBracket) {
+            self.expect_token(&Token::RBracket)?;
+            data_type = SQLType::List(Box::new(data_type));
+        }
+        Ok(data_type)
+    }
+
+    /// Parse a SQL datatype that is not a list
+    fn parse_element_data_type(&mut self) -> Result<SQLType, ParserError> {
         match self.next_token() {
             Some(Token::Keyword(k)) => match k.to_uppercase().as_ref() {
                 "BOOLEAN" => Ok(SQLType::Boolean),
@@ -469,20 +732,137 @@ impl Parser {
                 "INT64" | "LONG" => Ok(SQLType::Int64),
                 "FLOAT32" | "FLOAT" => Ok(SQLType::Float32),
                 "FLOAT64" | "DOUBLE" => Ok(SQLType::Double64),
+                "DATE" => Ok(SQLType::Date),
+                "TIME" => Ok(SQLType::Time),
+                "TIMESTAMP" => Ok(SQLType::Timestamp),
+                "DECIMAL" | "NUMERIC" => {
+                    // optional precision and scale
+                    let (precision, scale) = if self.consume_token(&Token::LParen) {
+                        let precision = self.parse_literal_int()?;
+                        if precision < 1 || precision > decimal::MAX_PRECISION as i64 {
+                            return self.expected_last("a precision between 1 and 38");
+                        }
+                        let scale = if self.consume_token(&Token::Comma) {
+                            let scale = self.parse_literal_int()?;
+                            if scale < 0 || scale > precision {
+                                return self.expected_last("a scale no larger than the precision");
+                            }
+                            scale
+                        } else {
+                            0
+                        };
+                        self.expect_token(&Token::RParen)?;
+                        (precision as usize, scale as usize)
+                    } else {
+                        (10, 0)
+                    };
+                    Ok(SQLType::Decimal(precision, scale))
+                }
                 "UTF8" | "VARCHAR" | "STRING" => {
                     // optional length
-                    if self.consume_token(&Token::LParen)? {
+                    if self.consume_token(&Token::LParen) {
                         let n = self.parse_literal_int()?;
-                        self.consume_token(&Token::RParen)?;
+                        self.expect_token(&Token::RParen)?;
                         Ok(SQLType::Utf8(n as usize))
                     } else {
                         Ok(SQLType::Utf8(100 as usize))
                     }
                 }
-                _ => parser_err!(format!("Invalid data type '{:?}'", k)),
+                _ => self.expected_last("a data type"),
             },
-            other => parser_err!(format!("Invalid data type: '{:?}'", other)),
+            _ => self.expected_last("a data type"),
+        }
+    }
+
+    /// Parse a SQL ANALYZE statement
+    fn parse_analyze(&mut self) -> Result<ASTNode, ParserError> {
+        // the TABLE keyword is optional
+        self.parse_keyword("TABLE");
+        match self.next_identifier() {
+            Some(name) => {
+                if self.peek_token().is_some() {
+                    self.expected("end of statement")
+                } else {
+                    Ok(ASTNode::SQLAnalyze { name })
+                }
+            }
+            None => self.expected_last("a table name"),
+        }
+    }
+
+    /// Parse a SQL EXPLAIN statement
+    fn parse_explain(&mut self) -> Result<ASTNode, ParserError> {
+        let format = if self.consume_token(&Token::LParen) {
+            let format = self.parse_explain_format()?;
+            self.expect_token(&Token::RParen)?;
+            format
+        } else {
+            ExplainFormat::Text
+        };
+        let analyze = self.parse_keyword("ANALYZE");
+        let verbose = self.parse_keyword("VERBOSE");
+        match self.peek_token() {
+            Some(Token::Keyword(ref k)) if k == "SELECT" => Ok(ASTNode::SQLExplain {
+                verbose,
+                analyze,
+                format,
+                statement: Box::new(self.parse_expr(0)?),
+            }),
+            _ => self.expected("a SELECT statement"),
+        }
+    }
+
+    /// Parse the `FORMAT <name>` option of EXPLAIN, where the name is TEXT or DOT
+    fn parse_explain_format(&mut self) -> Result<ExplainFormat, ParserError> {
+        match self.next_token() {
+            Some(Token::Identifier(ref id)) if id.eq_ignore_ascii_case("FORMAT") => {}
+            _ => return self.expected_last("FORMAT"),
+        }
+        match self.next_token() {
+            Some(Token::Identifier(ref id)) if id.eq_ignore_ascii_case("TEXT") => {
+                Ok(ExplainFormat::Text)
+            }
+            Some(Token::Identifier(ref id)) if id.eq_ignore_ascii_case("DOT") => {
+                Ok(ExplainFormat::Dot)
+            }
+            _ => self.expected_last("TEXT or DOT"),
+        }
+    }
+
+    /// Parse a relation with optional inner joins e.g. `a JOIN b ON a.id = b.id`, and
+    /// optional lists to unnest e.g. `a, UNNEST(a.tags)`
+    fn parse_relation(&mut self) -> Result<ASTNode, ParserError> {
+        let mut relation = self.parse_expr(0)?;
+        loop {
+            if self.parse_keyword("JOIN") || self.parse_keywords(vec!["INNER", "JOIN"]) {
+                let right = self.parse_expr(0)?;
+                if !self.parse_keyword("ON") {
+                    return self.expected("ON");
+                }
+                let on = self.parse_expr(0)?;
+                relation = ASTNode::SQLJoin {
+                    left: Box::new(relation),
+                    right: Box::new(right),
+                    on: Box::new(on),
+                };
+            } else if self.consume_token(&Token::Comma) {
+                // tables are joined with JOIN .. ON, so only UNNEST can follow a comma
+                match self.parse_expr(0)? {
+                    ASTNode::SQLFunction { ref id, ref args }
+                        if id.eq_ignore_ascii_case("UNNEST") && args.len() == 1 =>
+                    {
+                        relation = ASTNode::SQLUnnest {
+                            relation: Box::new(relation),
+                            expr: Box::new(args[0].clone()),
+                        };
+                    }
+                    _ => return self.expected_last("UNNEST(expr), or JOIN .. ON to join tables"),
+                }
+            } else {
+                break;
+            }
         }
+        Ok(relation)
     }
 
     /// Parse a SELECT statement
@@ -490,8 +870,7 @@ impl Parser {
         let projection = self.parse_expr_list()?;
 
         let relation: Option<Box<ASTNode>> = if self.parse_keyword("FROM") {
-            //TODO: add support for JOIN
-            Some(Box::new(self.parse_expr(0)?))
+            Some(Box::new(self.parse_relation()?))
         } else {
             None
         };
@@ -526,11 +905,8 @@ impl Parser {
             None
         };
 
-        if let Some(next_token) = self.peek_token() {
-            parser_err!(format!(
-                "Unexpected token at end of SELECT: {:?}",
-                next_token
-            ))
+        if self.peek_token().is_some() {
+            self.expected("end of statement")
         } else {
             Ok(ASTNode::SQLSelect {
                 projection,
@@ -576,18 +952,11 @@ impl Parser {
                     match k.to_uppercase().as_ref() {
                         "ASC" => true,
                         "DESC" => false,
-                        _ => {
-                            return parser_err!(format!(
-                                "Invalid modifier for ORDER BY expression: {:?}",
-                                k
-                            ))
-                        }
+                        _ => return self.expected_last("ASC or DESC"),
                     }
                 }
                 Some(Token::Comma) => true,
-                Some(other) => {
-                    return parser_err!(format!("Unexpected token after ORDER BY expr: {:?}", other))
-                }
+                Some(_) => return self.expected("ASC, DESC or ','"),
                 None => true,
             };
 
@@ -698,12 +1067,12 @@ mod tests {
         let ast = parse_sql(&sql);
         assert_eq!(
             SQLBinaryExpr {
-                left: Box::new(SQLIdentifier("a".to_string())),
+                left: Box::new(SQLIdentifier(SQLIdent::new("a"))),
                 op: Plus,
                 right: Box::new(SQLBinaryExpr {
-                    left: Box::new(SQLIdentifier("b".to_string())),
+                    left: Box::new(SQLIdentifier(SQLIdent::new("b"))),
                     op: Multiply,
-                    right: Box::new(SQLIdentifier("c".to_string()))
+                    right: Box::new(SQLIdentifier(SQLIdent::new("c")))
                 })
             },
             ast
@@ -719,12 +1088,12 @@ mod tests {
         assert_eq!(
             SQLBinaryExpr {
                 left: Box::new(SQLBinaryExpr {
-                    left: Box::new(SQLIdentifier("a".to_string())),
+                    left: Box::new(SQLIdentifier(SQLIdent::new("a"))),
                     op: Multiply,
-                    right: Box::new(SQLIdentifier("b".to_string()))
+                    right: Box::new(SQLIdentifier(SQLIdent::new("b")))
                 }),
                 op: Plus,
-                right: Box::new(SQLIdentifier("c".to_string()))
+                right: Box::new(SQLIdentifier(SQLIdent::new("c")))
             },
             ast
         );
@@ -735,7 +1104,7 @@ mod tests {
         use self::ASTNode::*;
         let sql = String::from("a IS NULL");
         let ast = parse_sql(&sql);
-        assert_eq!(SQLIsNull(Box::new(SQLIdentifier("a".to_string()))), ast);
+        assert_eq!(SQLIsNull(Box::new(SQLIdentifier(SQLIdent::new("a")))), ast);
     }
 
     #[test]
@@ -743,7 +1112,48 @@ mod tests {
         use self::ASTNode::*;
         let sql = String::from("a IS NOT NULL");
         let ast = parse_sql(&sql);
-        assert_eq!(SQLIsNotNull(Box::new(SQLIdentifier("a".to_string()))), ast);
+        assert_eq!(SQLIsNotNull(Box::new(SQLIdentifier(SQLIdent::new("a")))), ast);
+    }
+
+    #[test]
+    fn parse_not() {
+        use self::ASTNode::*;
+        let sql = String::from("NOT a = 1 AND b");
+        let ast = parse_sql(&sql);
+        assert_eq!(
+            SQLBinaryExpr {
+                left: Box::new(SQLNot(Box::new(SQLBinaryExpr {
+                    left: Box::new(SQLIdentifier(SQLIdent::new("a"))),
+                    op: SQLOperator::Eq,
+                    right: Box::new(SQLLiteralLong(1)),
+                }))),
+                op: SQLOperator::And,
+                right: Box::new(SQLIdentifier(SQLIdent::new("b"))),
+            },
+            ast
+        );
+        assert_eq!("NOT a = 1 AND b", ast.to_string());
+        assert_eq!("NOT (a OR b)", parse_sql("NOT (a OR b)").to_string());
+    }
+
+    #[test]
+    fn parse_string_concat() {
+        use self::ASTNode::*;
+        let sql = String::from("a || 'b' = c");
+        let ast = parse_sql(&sql);
+        assert_eq!(
+            SQLBinaryExpr {
+                left: Box::new(SQLBinaryExpr {
+                    left: Box::new(SQLIdentifier(SQLIdent::new("a"))),
+                    op: SQLOperator::StringConcat,
+                    right: Box::new(SQLLiteralString("b".to_string())),
+                }),
+                op: SQLOperator::Eq,
+                right: Box::new(SQLIdentifier(SQLIdent::new("c"))),
+            },
+            ast
+        );
+        assert_eq!("a || 'b' = c", ast.to_string());
     }
 
     #[test]
@@ -757,11 +1167,11 @@ mod tests {
                 assert_eq!(
                     Some(vec![
                         ASTNode::SQLOrderBy {
-                            expr: Box::new(ASTNode::SQLIdentifier("lname".to_string())),
+                            expr: Box::new(ASTNode::SQLIdentifier(SQLIdent::new("lname"))),
                             asc: true,
                         },
                         ASTNode::SQLOrderBy {
-                            expr: Box::new(ASTNode::SQLIdentifier("fname".to_string())),
+                            expr: Box::new(ASTNode::SQLIdentifier(SQLIdent::new("fname"))),
                             asc: false,
                         },
                     ]),
@@ -780,8 +1190,8 @@ mod tests {
             ASTNode::SQLSelect { group_by, .. } => {
                 assert_eq!(
                     Some(vec![
-                        ASTNode::SQLIdentifier("lname".to_string()),
-                        ASTNode::SQLIdentifier("fname".to_string()),
+                        ASTNode::SQLIdentifier(SQLIdent::new("lname")),
+                        ASTNode::SQLIdentifier(SQLIdent::new("fname")),
                     ]),
                     group_by
                 );
@@ -814,7 +1224,7 @@ mod tests {
                 assert_eq!(1, projection.len());
                 assert_eq!(
                     ASTNode::SQLCast {
-                        expr: Box::new(ASTNode::SQLIdentifier("id".to_string())),
+                        expr: Box::new(ASTNode::SQLIdentifier(SQLIdent::new("id"))),
                         data_type: SQLType::Double64
                     },
                     projection[0]
@@ -824,6 +1234,66 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_try_cast() {
+        let sql = "SELECT TRY_CAST(id AS INT) FROM customer";
+        match parse_sql(sql) {
+            ASTNode::SQLSelect { projection, .. } => {
+                assert_eq!(
+                    vec![ASTNode::SQLTryCast {
+                        expr: Box::new(ASTNode::SQLIdentifier(SQLIdent::new("id"))),
+                        data_type: SQLType::Int32,
+                    }],
+                    projection
+                );
+                assert_eq!("TRY_CAST(id AS INT32)", projection[0].to_string());
+            }
+            _ => assert!(false),
+        }
+    }
+
+    #[test]
+    fn parse_decimal_type() {
+        let sql = "SELECT CAST(price AS DECIMAL(10, 2)), CAST(qty AS NUMERIC) FROM t";
+        match parse_sql(sql) {
+            ASTNode::SQLSelect { projection, .. } => {
+                assert_eq!(
+                    vec![
+                        ASTNode::SQLCast {
+                            expr: Box::new(ASTNode::SQLIdentifier(SQLIdent::new("price"))),
+                            data_type: SQLType::Decimal(10, 2),
+                        },
+                        ASTNode::SQLCast {
+                            expr: Box::new(ASTNode::SQLIdentifier(SQLIdent::new("qty"))),
+                            data_type: SQLType::Decimal(10, 0),
+                        },
+                    ],
+                    projection
+                );
+                assert_eq!("CAST(price AS DECIMAL(10,2))", projection[0].to_string());
+            }
+            _ => assert!(false),
+        }
+
+        let sql = String::from("SELECT CAST(a AS DECIMAL(40, 2)) FROM t");
+        match Parser::parse_sql(sql) {
+            Err(e) => assert_eq!(
+                "Expected a precision between 1 and 38, found 40 at line 1, column 26",
+                e.to_string()
+            ),
+            _ => assert!(false),
+        }
+
+        let sql = String::from("SELECT CAST(a AS DECIMAL(4, 5)) FROM t");
+        match Parser::parse_sql(sql) {
+            Err(e) => assert_eq!(
+                "Expected a scale no larger than the precision, found 5 at line 1, column 29",
+                e.to_string()
+            ),
+            _ => assert!(false),
+        }
+    }
+
     #[test]
     fn parse_create_external_table_csv_with_header_row() {
         let sql = String::from(
@@ -930,7 +1400,7 @@ mod tests {
             assert_eq!(
                 vec![ASTNode::SQLFunction {
                     id: String::from("sqrt"),
-                    args: vec![ASTNode::SQLIdentifier(String::from("id"))],
+                    args: vec![ASTNode::SQLIdentifier(SQLIdent::new("id"))],
                 }],
                 projection
             );
@@ -953,17 +1423,607 @@ mod tests {
             ASTNode::SQLSelect { ref projection, .. } => {
                 assert_eq!(
                     projection[0],
-                    ASTNode::SQLIdentifier("@@version".to_string())
+                    ASTNode::SQLIdentifier(SQLIdent::new("@@version"))
+                );
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn parse_select_join() {
+        use self::ASTNode::*;
+        let sql = "SELECT a.x FROM a JOIN b ON a.id = b.id \
+                   INNER JOIN c ON b.c_id = c.id WHERE a.x > 1";
+        match parse_sql(&sql) {
+            SQLSelect {
+                ref relation,
+                ref selection,
+                ..
+            } => {
+                let compound = |a: &str, b: &str| {
+                    Box::new(SQLCompoundIdentifier(vec![SQLIdent::new(a), SQLIdent::new(b)]))
+                };
+                let expected = SQLJoin {
+                    left: Box::new(SQLJoin {
+                        left: Box::new(SQLIdentifier(SQLIdent::new("a"))),
+                        right: Box::new(SQLIdentifier(SQLIdent::new("b"))),
+                        on: Box::new(SQLBinaryExpr {
+                            left: compound("a", "id"),
+                            op: SQLOperator::Eq,
+                            right: compound("b", "id"),
+                        }),
+                    }),
+                    right: Box::new(SQLIdentifier(SQLIdent::new("c"))),
+                    on: Box::new(SQLBinaryExpr {
+                        left: compound("b", "c_id"),
+                        op: SQLOperator::Eq,
+                        right: compound("c", "id"),
+                    }),
+                };
+                assert_eq!(&Some(Box::new(expected)), relation);
+                assert!(selection.is_some());
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn parse_list_types() {
+        let sql = "CREATE EXTERNAL TABLE t (tags VARCHAR[], matrix INT[][]) \
+                   STORED AS NDJSON LOCATION 't.json'";
+        match parse_sql(sql) {
+            ASTNode::SQLCreateTable { columns, .. } => {
+                assert_eq!(
+                    SQLType::List(Box::new(SQLType::Utf8(100))),
+                    columns[0].data_type
+                );
+                assert_eq!(
+                    SQLType::List(Box::new(SQLType::List(Box::new(SQLType::Int32)))),
+                    columns[1].data_type
+                );
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn parse_array_index() {
+        use self::ASTNode::*;
+        match parse_sql("SELECT tags[1 + 1] FROM t") {
+            SQLSelect { ref projection, .. } => assert_eq!(
+                SQLArrayIndex {
+                    expr: Box::new(SQLIdentifier(SQLIdent::new("tags"))),
+                    index: Box::new(SQLBinaryExpr {
+                        left: Box::new(SQLLiteralLong(1)),
+                        op: SQLOperator::Plus,
+                        right: Box::new(SQLLiteralLong(1)),
+                    }),
+                },
+                projection[0]
+            ),
+            _ => panic!(),
+        }
+        // indexing binds more tightly than arithmetic
+        assert_eq!(
+            "SELECT a + tags[1] FROM t",
+            parse_sql("SELECT a + tags[1] FROM t").to_string()
+        );
+        assert_eq!(
+            "SELECT (a + b)[1] FROM t",
+            parse_sql("SELECT (a + b)[1] FROM t").to_string()
+        );
+    }
+
+    #[test]
+    fn parse_struct_fields() {
+        use self::ASTNode::*;
+        match parse_sql("SELECT STRUCT(lat, lng AS longitude), location.lat FROM t") {
+            SQLSelect { ref projection, .. } => {
+                assert_eq!(
+                    SQLStruct(vec![
+                        (SQLIdentifier(SQLIdent::new("lat")), None),
+                        (
+                            SQLIdentifier(SQLIdent::new("lng")),
+                            Some("longitude".to_string())
+                        ),
+                    ]),
+                    projection[0]
+                );
+                assert_eq!(
+                    SQLCompoundIdentifier(vec![SQLIdent::new("location"), SQLIdent::new("lat")]),
+                    projection[1]
+                );
+            }
+            _ => panic!(),
+        }
+        assert_eq!(
+            "SELECT STRUCT(a + 1 AS b, c)['b'] FROM t",
+            parse_sql("SELECT struct(a + 1 AS b, c)['b'] FROM t").to_string()
+        );
+    }
+
+    #[test]
+    fn parse_from_unnest() {
+        use self::ASTNode::*;
+        match parse_sql("SELECT id, unnest FROM t, UNNEST(t.tags) WHERE id > 1") {
+            SQLSelect { ref relation, .. } => assert_eq!(
+                &Some(Box::new(SQLUnnest {
+                    relation: Box::new(SQLIdentifier(SQLIdent::new("t"))),
+                    expr: Box::new(SQLCompoundIdentifier(vec![
+                        SQLIdent::new("t"),
+                        SQLIdent::new("tags"),
+                    ])),
+                })),
+                relation
+            ),
+            _ => panic!(),
+        }
+        // tables can only be joined with JOIN .. ON
+        assert!(Parser::parse_sql("SELECT id FROM a, b".to_string()).is_err());
+    }
+
+    #[test]
+    fn parse_explain() {
+        match parse_sql("EXPLAIN VERBOSE SELECT id FROM customer") {
+            ASTNode::SQLExplain {
+                verbose,
+                analyze,
+                statement,
+            } => {
+                assert!(verbose);
+                assert!(!analyze);
+                match *statement {
+                    ASTNode::SQLSelect { .. } => {}
+                    _ => panic!(),
+                }
+            }
+            _ => panic!(),
+        }
+        match parse_sql("EXPLAIN SELECT id FROM customer") {
+            ASTNode::SQLExplain { verbose, .. } => assert!(!verbose),
+            _ => panic!(),
+        }
+        match parse_sql("EXPLAIN ANALYZE SELECT id FROM customer") {
+            ASTNode::SQLExplain {
+                verbose,
+                analyze,
+                format,
+                ..
+            } => {
+                assert!(!verbose);
+                assert!(analyze);
+                assert_eq!(ExplainFormat::Text, format);
+            }
+            _ => panic!(),
+        }
+        match parse_sql("EXPLAIN (FORMAT DOT) ANALYZE SELECT id FROM customer") {
+            ASTNode::SQLExplain {
+                analyze, format, ..
+            } => {
+                assert!(analyze);
+                assert_eq!(ExplainFormat::Dot, format);
+            }
+            _ => panic!(),
+        }
+        assert!(
+            Parser::parse_sql(String::from("EXPLAIN (FORMAT XML) SELECT id FROM customer"))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn parse_analyze() {
+        assert_eq!(
+            ASTNode::SQLAnalyze {
+                name: SQLIdent::new("uk_cities")
+            },
+            parse_sql("ANALYZE TABLE uk_cities")
+        );
+        assert_eq!(
+            ASTNode::SQLAnalyze {
+                name: SQLIdent::new("uk_cities")
+            },
+            parse_sql("ANALYZE uk_cities")
+        );
+        assert_eq!(
+            ASTNode::SQLAnalyze {
+                name: SQLIdent::quoted("UK Cities")
+            },
+            parse_sql("ANALYZE \"UK Cities\"")
+        );
+    }
+
+    #[test]
+    fn parse_quoted_identifiers() {
+        let sql = "CREATE EXTERNAL TABLE \"People\" (\"First Name\" VARCHAR) \
+                   STORED AS CSV LOCATION 'people.csv'";
+        match parse_sql(sql) {
+            ASTNode::SQLCreateTable { name, columns, .. } => {
+                assert_eq!("People", name);
+                assert_eq!("First Name", columns[0].name);
+            }
+            _ => panic!(),
+        }
+        match parse_sql("SELECT \"First Name\" FROM people") {
+            ASTNode::SQLSelect { projection, .. } => assert_eq!(
+                vec![ASTNode::SQLIdentifier(SQLIdent::quoted("First Name"))],
+                projection
+            ),
+            _ => panic!(),
+        }
+        match parse_sql("SELECT \"p\".\"a\"\"b\", p.c FROM \"p\"") {
+            ASTNode::SQLSelect {
+                projection,
+                relation,
+                ..
+            } => {
+                assert_eq!(
+                    vec![
+                        ASTNode::SQLCompoundIdentifier(vec![
+                            SQLIdent::quoted("p"),
+                            SQLIdent::quoted("a\"b"),
+                        ]),
+                        ASTNode::SQLCompoundIdentifier(vec![
+                            SQLIdent::new("p"),
+                            SQLIdent::new("c"),
+                        ]),
+                    ],
+                    projection
+                );
+                assert_eq!(
+                    Some(Box::new(ASTNode::SQLIdentifier(SQLIdent::quoted("p")))),
+                    relation
                 );
             }
             _ => panic!(),
         }
     }
 
+    #[test]
+    fn parse_statements() {
+        let sql = "CREATE EXTERNAL TABLE t STORED AS CSV LOCATION 'a;b.csv';\n\
+                   -- a comment; with a semicolon\n\
+                   SELECT \"a;b\" FROM t /* ; */;;\n\
+                   ANALYZE t";
+        let statements = Parser::parse_statements(sql.to_string()).unwrap();
+        assert_eq!(3, statements.len());
+        match statements[0] {
+            ASTNode::SQLCreateTable { ref location, .. } => assert_eq!("a;b.csv", location),
+            _ => panic!(),
+        }
+        match statements[1] {
+            ASTNode::SQLSelect { ref projection, .. } => assert_eq!(
+                vec![ASTNode::SQLIdentifier(SQLIdent::quoted("a;b"))],
+                *projection
+            ),
+            _ => panic!(),
+        }
+        assert_eq!(
+            ASTNode::SQLAnalyze {
+                name: SQLIdent::new("t")
+            },
+            statements[2]
+        );
+
+        // errors are reported relative to the start of the script
+        let sql = "SELECT a FROM t;\nSELECT a FROM t WHERE;";
+        match Parser::parse_statements(sql.to_string()) {
+            Err(ParserError::ParserError(e)) => {
+                assert_eq!(Location { line: 2, column: 22 }, e.span.start)
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        // the other statements are still parsed when one has a syntax error
+        let statements = Parser::parse_each_statement(sql.to_string()).unwrap();
+        assert_eq!(2, statements.len());
+        assert!(statements[0].is_ok());
+        assert!(statements[1].is_err());
+
+        // a single statement may be terminated with a semicolon, but not followed by another
+        assert!(Parser::parse_sql("SELECT a FROM t;".to_string()).is_ok());
+        assert!(Parser::parse_sql("SELECT a FROM t; SELECT b FROM t".to_string()).is_err());
+    }
+
+    #[test]
+    fn parse_error_location() {
+        let sql = "SELECT id, name\nFROM customer\nWHERE id = 1 id";
+        match Parser::parse_sql(sql.to_string()) {
+            Err(ParserError::ParserError(e)) => {
+                assert_eq!("end of statement", e.expected);
+                assert_eq!(Some(Token::Identifier("id".to_string())), e.found);
+                assert_eq!(Location { line: 3, column: 14 }, e.span.start);
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_error_snippet() {
+        let sql = "SELECT CAST(a AS INT FROM t";
+        let e = Parser::parse_sql(sql.to_string()).unwrap_err();
+        assert_eq!(
+            "Expected ')', found FROM at line 1, column 22\n\
+             1 | SELECT CAST(a AS INT FROM t\n\
+             \x20 |                      ^^^^",
+            e.render(sql)
+        );
+
+        let sql = "SELECT a FROM";
+        let e = Parser::parse_sql(sql.to_string()).unwrap_err();
+        assert_eq!(
+            "Expected an expression, found end of input at line 1, column 14\n\
+             1 | SELECT a FROM\n\
+             \x20 |              ^",
+            e.render(sql)
+        );
+    }
+
+    #[test]
+    fn parse_tokens_without_spans() {
+        let tokens = Tokenizer::new("SELECT a FROM t").tokenize().unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+        assert_eq!("SELECT a FROM t", ast.to_string());
+
+        let tokens = Tokenizer::new("SELECT a FROM").tokenize().unwrap();
+        match Parser::new(tokens).parse() {
+            Err(ParserError::ParserError(e)) => {
+                assert_eq!("an expression", e.expected);
+                assert_eq!(None, e.found);
+            }
+            other => panic!("expected a syntax error, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_tokenizer_error() {
+        let sql = "SELECT a FROM t WHERE a ! 1";
+        let e = Parser::parse_sql(sql.to_string()).unwrap_err();
+        assert_eq!(
+            "Expected '=' after '!', found ' ' at line 1, column 25\n\
+             1 | SELECT a FROM t WHERE a ! 1\n\
+             \x20 |                         ^",
+            e.render(sql)
+        );
+    }
+
+    #[test]
+    fn parse_nested_expr() {
+        use self::ASTNode::*;
+        let sql = "SELECT (a + b) * c FROM t";
+        match parse_sql(&sql) {
+            SQLSelect { projection, .. } => assert_eq!(
+                vec![SQLBinaryExpr {
+                    left: Box::new(SQLBinaryExpr {
+                        left: Box::new(SQLIdentifier(SQLIdent::new("a"))),
+                        op: SQLOperator::Plus,
+                        right: Box::new(SQLIdentifier(SQLIdent::new("b"))),
+                    }),
+                    op: SQLOperator::Multiply,
+                    right: Box::new(SQLIdentifier(SQLIdent::new("c"))),
+                }],
+                projection
+            ),
+            _ => panic!(),
+        }
+        assert!(Parser::parse_sql("SELECT (a + b FROM t".to_string()).is_err());
+    }
+
+    #[test]
+    fn parse_parenthesized_expr() {
+        let sql = "SELECT (a + b) * c, a + b * c, a - (b - c), (a - b) - c FROM t";
+        assert_eq!(
+            "SELECT (a + b) * c, a + b * c, a - (b - c), a - b - c FROM t",
+            parse_sql(sql).to_string()
+        );
+    }
+
+    #[test]
+    fn parse_parameters() {
+        let sql = "SELECT a FROM t WHERE b = ? AND d < ?";
+        match parse_sql(sql) {
+            ASTNode::SQLSelect {
+                selection: Some(selection),
+                ..
+            } => assert_eq!("b = $1 AND d < $2", selection.to_string()),
+            _ => assert!(false),
+        }
+
+        let sql = "SELECT a FROM t WHERE c > $3 AND e = :name";
+        match parse_sql(sql) {
+            ASTNode::SQLSelect {
+                selection: Some(selection),
+                ..
+            } => assert_eq!("c > $3 AND e = :name", selection.to_string()),
+            _ => assert!(false),
+        }
+
+        let sql = String::from("SELECT a FROM t WHERE b = ? AND c > $1");
+        match Parser::parse_sql(sql) {
+            Err(e) => assert_eq!(
+                "Expected a '?' placeholder, not mixed with $n or :name, found $1 at line 1, \
+                 column 37",
+                e.to_string()
+            ),
+            _ => assert!(false),
+        }
+
+        let sql = String::from("SELECT a FROM t WHERE e = :name AND b = ?");
+        match Parser::parse_sql(sql) {
+            Err(e) => assert_eq!(
+                "Expected a $n or :name placeholder, not mixed with '?', found ? at line 1, \
+                 column 41",
+                e.to_string()
+            ),
+            _ => assert!(false),
+        }
+
+        let sql = String::from("SELECT a FROM t WHERE b = $0");
+        match Parser::parse_sql(sql) {
+            Err(e) => assert_eq!(
+                "Expected a parameter number from $1, found $0 at line 1, column 27",
+                e.to_string()
+            ),
+            _ => assert!(false),
+        }
+    }
+
+    #[test]
+    fn parse_temporal_literals() {
+        let sql = "SELECT CAST(a AS TIMESTAMP) FROM t \
+                   WHERE d >= DATE '2018-01-01' AND ts < TIMESTAMP '2018-01-01 12:00:00' \
+                   AND d + INTERVAL '1' day > DATE '2018-02-01' AND t = TIME '12:00:00' \
+                   AND ts - INTERVAL '1 hour 30 minutes' > TIMESTAMP '2018-01-01'";
+        match parse_sql(sql) {
+            ASTNode::SQLSelect {
+                projection,
+                selection: Some(selection),
+                ..
+            } => {
+                assert_eq!("CAST(a AS TIMESTAMP)", projection[0].to_string());
+                assert_eq!(
+                    "d >= DATE '2018-01-01' AND ts < TIMESTAMP '2018-01-01 12:00:00' \
+                     AND d + INTERVAL '1' DAY > DATE '2018-02-01' AND t = TIME '12:00:00' \
+                     AND ts - INTERVAL '1 hour 30 minutes' > TIMESTAMP '2018-01-01'",
+                    selection.to_string()
+                );
+            }
+            _ => assert!(false),
+        }
+
+        let sql = String::from("SELECT DATE 1");
+        match Parser::parse_sql(sql) {
+            Err(e) => assert_eq!(
+                "Expected a literal string, found 1 at line 1, column 13",
+                e.to_string()
+            ),
+            _ => assert!(false),
+        }
+    }
+
+    #[test]
+    fn parse_binary_literals() {
+        let sql = "SELECT x'deadBEEF', X'' FROM t WHERE hash = X'00ff'";
+        match parse_sql(sql) {
+            ASTNode::SQLSelect {
+                projection,
+                selection: Some(selection),
+                ..
+            } => {
+                assert_eq!(
+                    ASTNode::SQLLiteralBinary(vec![0xde, 0xad, 0xbe, 0xef]),
+                    projection[0]
+                );
+                assert_eq!("X''", projection[1].to_string());
+                assert_eq!("hash = X'00FF'", selection.to_string());
+            }
+            _ => assert!(false),
+        }
+    }
+
+    #[test]
+    fn parse_datetime_functions() {
+        let sql = "SELECT now(), CURRENT_DATE, CURRENT_TIMESTAMP, EXTRACT(year FROM ts + 1) FROM t";
+        match parse_sql(sql) {
+            ASTNode::SQLSelect { projection, .. } => {
+                assert_eq!(
+                    vec![
+                        ASTNode::SQLFunction {
+                            id: "now".to_string(),
+                            args: vec![],
+                        },
+                        ASTNode::SQLFunction {
+                            id: "current_date".to_string(),
+                            args: vec![],
+                        },
+                        ASTNode::SQLFunction {
+                            id: "now".to_string(),
+                            args: vec![],
+                        },
+                        ASTNode::SQLExtract {
+                            field: "YEAR".to_string(),
+                            expr: Box::new(ASTNode::SQLBinaryExpr {
+                                left: Box::new(ASTNode::SQLIdentifier(SQLIdent::new("ts"))),
+                                op: SQLOperator::Plus,
+                                right: Box::new(ASTNode::SQLLiteralLong(1)),
+                            }),
+                        },
+                    ],
+                    projection
+                );
+                assert_eq!("EXTRACT(YEAR FROM ts + 1)", projection[3].to_string());
+            }
+            _ => assert!(false),
+        }
+
+        let sql = String::from("SELECT EXTRACT(year ts) FROM t");
+        match Parser::parse_sql(sql) {
+            Err(e) => assert_eq!("Expected 'FROM', found ts at line 1, column 21", e.to_string()),
+            _ => assert!(false),
+        }
+    }
+
+    #[test]
+    fn display_sql() {
+        let sql = "SELECT \"First Name\", CAST(id AS VARCHAR), 'O''Brien', 1.0 FROM t \
+                   WHERE (a IS NULL OR b = 1) AND c IS NOT NULL AND d != NULL \
+                   ORDER BY a DESC, b ASC LIMIT 5";
+        assert_eq!(
+            "SELECT \"First Name\", CAST(id AS VARCHAR(100)), 'O''Brien', 1.0 FROM t \
+             WHERE (a IS NULL OR b = 1) AND c IS NOT NULL AND d != NULL \
+             ORDER BY a DESC, b ASC LIMIT 5",
+            parse_sql(sql).to_string()
+        );
+
+        let ast = ASTNode::SQLCreateTable {
+            name: "my table".to_string(),
+            columns: vec![SQLColumnDef {
+                name: "select".to_string(),
+                data_type: SQLType::Int32,
+                allow_null: false,
+            }],
+            file_type: FileType::CSV,
+            header_row: false,
+            location: "it's.csv".to_string(),
+        };
+        assert_eq!(
+            "CREATE EXTERNAL TABLE \"my table\" (\"select\" INT32 NOT NULL) \
+             STORED AS CSV WITHOUT HEADER ROW LOCATION 'it''s.csv'",
+            ast.to_string()
+        );
+        assert_eq!(ast, parse_sql(&ast.to_string()));
+    }
+
+    #[test]
+    fn display_numbers() {
+        let sql = "SELECT 10000000000000000.0, 0.0000001, 1.5 FROM t";
+        assert_eq!(sql, parse_sql(sql).to_string());
+
+        let negate = |rex: ASTNode| ASTNode::SQLUnary {
+            operator: SQLOperator::Minus,
+            rex: Box::new(rex),
+        };
+        let x = ASTNode::SQLIdentifier(SQLIdent::new("x"));
+        assert_eq!("-x", negate(x.clone()).to_string());
+        assert_eq!("-(-x)", negate(negate(x)).to_string());
+        assert_eq!("-(-1)", negate(ASTNode::SQLLiteralLong(-1)).to_string());
+        assert_eq!("-(-0.5)", negate(ASTNode::SQLLiteralDouble(-0.5)).to_string());
+    }
+
+    /// Parse a SQL statement, checking that printing the AST as SQL and parsing it again
+    /// produces the same AST
     fn parse_sql(sql: &str) -> ASTNode {
+        let ast = parse_tokens(sql);
+        let printed = ast.to_string();
+        assert_eq!(ast, parse_tokens(&printed), "round trip of {}", printed);
+        ast
+    }
+
+    fn parse_tokens(sql: &str) -> ASTNode {
         let mut tokenizer = Tokenizer::new(&sql);
-        let tokens = tokenizer.tokenize().unwrap();
-        let mut parser = Parser::new(tokens);
+        let tokens = tokenizer.tokenize_with_spans().unwrap();
+        let mut parser = Parser::with_spans(tokens);
         let ast = parser.parse().unwrap();
         ast
     }
@@ -14,34 +14,117 @@
 
 //! SQL Query Planner (produces logical plan from SQL AST)
 
-use std::collections::HashSet;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 use std::string::String;
 
+use super::datasources::common::Statistics;
+use super::datatypes::*;
+use super::decimal;
+use super::errors::*;
 use super::logical::*;
+use super::planbuilder::LogicalPlanBuilder;
 use super::sqlast::*;
+use super::temporal;
 use super::types::*;
-
-use arrow::datatypes::*;
+use super::visitor::*;
 
 pub trait SchemaProvider {
     fn get_table_meta(&self, name: &str) -> Option<Rc<Schema>>;
     fn get_function_meta(&self, name: &str) -> Option<Rc<FunctionMeta>>;
+
+    /// Get statistics for a table, if they are known
+    fn get_table_statistics(&self, _name: &str) -> Option<Statistics> {
+        None
+    }
+
+    /// Get the names of all tables, which are used to suggest alternatives for unknown tables
+    fn table_names(&self) -> Vec<String> {
+        vec![]
+    }
 }
 
 /// SQL query planner
 pub struct SqlToRel {
     schema_provider: Rc<SchemaProvider>,
+    /// Parameters referenced by the planned statements, in the order they first appear
+    parameters: RefCell<Vec<(SQLParameter, Field)>>,
 }
 
 impl SqlToRel {
     /// Create a new query planner
     pub fn new(schema_provider: Rc<SchemaProvider>) -> Self {
-        SqlToRel { schema_provider }
+        SqlToRel {
+            schema_provider,
+            parameters: RefCell::new(vec![]),
+        }
+    }
+
+    /// Get the parameters referenced by the statements planned so far, with the types inferred
+    /// from the expressions they are used in. Positional parameters come first, ordered by
+    /// number, followed by named parameters in the order they first appear.
+    pub fn parameters(&self) -> Result<Vec<Field>> {
+        let mut positional: Vec<(usize, Field)> = vec![];
+        let mut named: Vec<Field> = vec![];
+        for (parameter, field) in self.parameters.borrow().iter() {
+            match parameter {
+                SQLParameter::Positional(n) => positional.push((*n, field.clone())),
+                SQLParameter::Named(_) => named.push(field.clone()),
+            }
+        }
+        positional.sort_by_key(|&(n, _)| n);
+        for (i, &(n, _)) in positional.iter().enumerate() {
+            if n != i + 1 {
+                return Err(ExecutionError::plan(format!(
+                    "Parameter ${} is not used, but parameters must be numbered consecutively \
+                     from $1",
+                    i + 1
+                )));
+            }
+        }
+        Ok(positional
+            .into_iter()
+            .map(|(_, field)| field)
+            .chain(named.into_iter())
+            .collect())
+    }
+
+    /// Create an expression for a parameter with the type inferred from where it is used. A
+    /// parameter that is used more than once must have the same type everywhere.
+    fn parameter_to_rex(
+        &self,
+        parameter: &SQLParameter,
+        data_type: DataType,
+        sql: &ASTNode,
+    ) -> Result<Expr> {
+        let name = parameter.to_string();
+        let mut parameters = self.parameters.borrow_mut();
+        let existing_type = parameters
+            .iter()
+            .find(|&&(ref p, _)| p == parameter)
+            .map(|&(_, ref field)| field.data_type().clone());
+        match existing_type {
+            Some(ref t) if *t != data_type => {
+                return Err(ExecutionError::type_error(
+                    format!(
+                        "Parameter {} is used as both {:?} and {:?}",
+                        name, t, data_type
+                    ),
+                    sql,
+                ))
+            }
+            Some(_) => {}
+            None => {
+                let field = Field::new(&name, data_type.clone(), true);
+                parameters.push((parameter.clone(), field))
+            }
+        }
+        Ok(Expr::Parameter { name, data_type })
     }
 
     /// Generate a logic plan from a SQL AST node
-    pub fn sql_to_rel(&self, sql: &ASTNode) -> Result<Rc<LogicalPlan>, String> {
+    pub fn sql_to_rel(&self, sql: &ASTNode) -> Result<Rc<LogicalPlan>> {
         match sql {
             &ASTNode::SQLSelect {
                 ref projection,
@@ -56,26 +139,50 @@ impl SqlToRel {
                 // parse the input relation so we have access to the row type
                 let input = match relation {
                     &Some(ref r) => self.sql_to_rel(r)?,
-                    &None => Rc::new(LogicalPlan::EmptyRelation {
-                        schema: Rc::new(Schema::empty()),
-                    }),
+                    &None => LogicalPlanBuilder::empty().build()?,
                 };
 
                 let input_schema = input.schema();
 
                 // selection first
-                let selection_plan = match selection {
-                    &Some(ref filter_expr) => Some(LogicalPlan::Selection {
-                        expr: self.sql_to_rex(&filter_expr, &input_schema.clone())?,
-                        input: input.clone(),
-                    }),
-                    _ => None,
+                let mut builder = LogicalPlanBuilder::from(&input);
+                if let &Some(ref filter_expr) = selection {
+                    builder = builder.filter(self.sql_to_rex(&filter_expr, &input_schema)?)?;
+                }
+
+                // an UNNEST in the SELECT list produces a row for each element, which is
+                // the last column of the unnested relation
+                let unnest_items: Vec<(usize, &ASTNode)> = projection
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, e)| unnest_arg(e).map(|arg| (i, arg)))
+                    .collect();
+                if unnest_items.len() > 1 {
+                    return Err(ExecutionError::plan_expr(
+                        String::from("Only one UNNEST is supported in a SELECT list"),
+                        unnest_items[1].1,
+                    ));
+                }
+                let unnest_item = match unnest_items.first() {
+                    Some(&(i, arg)) => {
+                        builder = builder.unnest(self.sql_to_rex(arg, &input_schema)?)?;
+                        Some(i)
+                    }
+                    None => None,
                 };
+                let input_schema = builder.schema().clone();
 
                 let expr: Vec<Expr> = projection
                     .iter()
-                    .map(|e| self.sql_to_rex(&e, &input_schema))
-                    .collect::<Result<Vec<Expr>, String>>()?;
+                    .enumerate()
+                    .map(|(i, e)| {
+                        if unnest_item == Some(i) {
+                            Ok(Expr::Column(input_schema.columns().len() - 1))
+                        } else {
+                            self.sql_to_rex(&e, &input_schema)
+                        }
+                    })
+                    .collect::<Result<Vec<Expr>>>()?;
 
                 // collect aggregate expressions
                 let aggr_expr: Vec<Expr> = expr
@@ -88,48 +195,19 @@ impl SqlToRel {
                     .collect();
 
                 if aggr_expr.len() > 0 {
-                    let aggregate_input: Rc<LogicalPlan> = match selection_plan {
-                        Some(s) => Rc::new(s),
-                        _ => input.clone(),
-                    };
-
                     let group_expr: Vec<Expr> = match group_by {
                         Some(gbe) => gbe
                             .iter()
                             .map(|e| self.sql_to_rex(&e, &input_schema))
-                            .collect::<Result<Vec<Expr>, String>>()?,
+                            .collect::<Result<Vec<Expr>>>()?,
                         None => vec![],
                     };
                     //println!("GROUP BY: {:?}", group_expr);
 
-                    let mut all_fields: Vec<Expr> = group_expr.clone();
-                    aggr_expr.iter().for_each(|x| all_fields.push(x.clone()));
-
-                    let aggr_schema = Schema::new(exprlist_to_fields(&all_fields, input_schema));
-
                     //TODO: selection, projection, everything else
-                    Ok(Rc::new(LogicalPlan::Aggregate {
-                        input: aggregate_input,
-                        group_expr,
-                        aggr_expr,
-                        schema: Rc::new(aggr_schema),
-                    }))
+                    builder.aggregate(group_expr, aggr_expr)?.build()
                 } else {
-                    let projection_input: Rc<LogicalPlan> = match selection_plan {
-                        Some(s) => Rc::new(s),
-                        _ => input.clone(),
-                    };
-
-                    let projection_schema = Rc::new(Schema::new(exprlist_to_fields(
-                        &expr,
-                        input_schema.as_ref(),
-                    )));
-
-                    let projection = LogicalPlan::Projection {
-                        expr: expr,
-                        input: projection_input,
-                        schema: projection_schema.clone(),
-                    };
+                    builder = builder.project(expr)?;
 
                     // aggregate queries
                     //                    match group_by {
@@ -138,82 +216,235 @@ impl SqlToRel {
                     //                    }
 
                     if let &Some(_) = having {
-                        return Err(String::from("HAVING is not implemented yet"));
+                        return Err(ExecutionError::plan(String::from(
+                            "HAVING is not implemented yet",
+                        )));
                     }
 
-                    let order_by_plan = match order_by {
-                        &Some(ref order_by_expr) => {
-                            let input_schema = projection.schema();
-                            let order_by_rex: Result<Vec<Expr>, String> = order_by_expr
-                                .iter()
-                                .map(|e| self.sql_to_rex(e, &input_schema))
-                                .collect();
-
-                            LogicalPlan::Sort {
-                                expr: order_by_rex?,
-                                input: Rc::new(projection.clone()),
-                                schema: input_schema.clone(),
-                            }
-                        }
-                        _ => projection,
-                    };
+                    if let &Some(ref order_by_expr) = order_by {
+                        let order_by_rex = order_by_expr
+                            .iter()
+                            .map(|e| self.sql_to_rex(e, builder.schema()))
+                            .collect::<Result<Vec<Expr>>>()?;
+                        builder = builder.sort(order_by_rex)?;
+                    }
 
-                    let limit_plan = match limit {
-                        &Some(ref limit_ast_node) => {
-                            let limit_count = match **limit_ast_node {
-                                ASTNode::SQLLiteralLong(n) => n,
-                                _ => return Err(String::from("LIMIT parameter is not a number")),
-                            };
-                            LogicalPlan::Limit {
-                                limit: limit_count as usize,
-                                schema: order_by_plan.schema().clone(),
-                                input: Rc::new(order_by_plan),
+                    if let &Some(ref limit_ast_node) = limit {
+                        let limit_count = match **limit_ast_node {
+                            ASTNode::SQLLiteralLong(n) => n,
+                            _ => {
+                                return Err(ExecutionError::plan_expr(
+                                    String::from("LIMIT parameter is not a number"),
+                                    limit_ast_node,
+                                ))
                             }
-                        }
-                        _ => order_by_plan,
-                    };
+                        };
+                        builder = builder.limit(limit_count as usize)?;
+                    }
 
-                    Ok(Rc::new(limit_plan))
+                    builder.build()
                 }
             }
 
             &ASTNode::SQLIdentifier(ref id) => {
-                match self.schema_provider.get_table_meta(id.as_ref()) {
-                    Some(schema) => Ok(Rc::new(LogicalPlan::TableScan {
-                        schema_name: String::from("default"),
-                        table_name: id.clone(),
-                        schema: schema.clone(),
-                        projection: None,
-                    })),
-                    None => Err(format!("no schema found for table {}", id)),
+                let name = resolve_table_name(self.schema_provider.as_ref(), id)?;
+                match self.schema_provider.get_table_meta(&name) {
+                    Some(schema) => {
+                        LogicalPlanBuilder::scan("default", &name, &schema, None)?.build()
+                    }
+                    None => Err(ExecutionError::table_not_found(
+                        &id.value,
+                        &self.schema_provider.table_names(),
+                    )),
                 }
             }
 
-            _ => Err(format!(
-                "sql_to_rel does not support this relation: {:?}",
-                sql
+            &ASTNode::SQLUnnest {
+                ref relation,
+                ref expr,
+            } => {
+                let input = self.sql_to_rel(relation)?;
+                let list = self.sql_to_rex(expr, input.schema())?;
+                LogicalPlanBuilder::from(&input).unnest(list)?.build()
+            }
+
+            &ASTNode::SQLJoin {
+                ref left,
+                ref right,
+                ref on,
+            } => {
+                let left = self.sql_to_rel(left)?;
+                let right = self.sql_to_rel(right)?;
+                let mut left_keys: Vec<Column> = vec![];
+                let mut right_keys: Vec<Column> = vec![];
+                self.sql_to_join_keys(
+                    on,
+                    left.schema(),
+                    right.schema(),
+                    &mut left_keys,
+                    &mut right_keys,
+                )?;
+                LogicalPlanBuilder::from(&left)
+                    .join(&right, JoinType::Inner, left_keys, right_keys)?
+                    .build()
+            }
+
+            _ => Err(ExecutionError::plan_expr(
+                String::from("Unsupported relation"),
+                sql,
+            )),
+        }
+    }
+
+    /// Extract pairs of join keys from a join condition, which must be a conjunction of
+    /// equality predicates that each compare a column from the left and right relations
+    fn sql_to_join_keys(
+        &self,
+        sql: &ASTNode,
+        left_schema: &Schema,
+        right_schema: &Schema,
+        left_keys: &mut Vec<Column>,
+        right_keys: &mut Vec<Column>,
+    ) -> Result<()> {
+        match sql {
+            &ASTNode::SQLBinaryExpr {
+                ref left,
+                op: SQLOperator::And,
+                ref right,
+            } => {
+                self.sql_to_join_keys(left, left_schema, right_schema, left_keys, right_keys)?;
+                self.sql_to_join_keys(right, left_schema, right_schema, left_keys, right_keys)
+            }
+            &ASTNode::SQLBinaryExpr {
+                ref left,
+                op: SQLOperator::Eq,
+                ref right,
+            } => {
+                let a = sql_to_column_name(left)?;
+                let b = sql_to_column_name(right)?;
+                let a_left = resolve_column(&a, left_schema);
+                let b_right = resolve_column(&b, right_schema);
+                let b_left = resolve_column(&b, left_schema);
+                let a_right = resolve_column(&a, right_schema);
+                if let (Ok(a), Ok(b)) = (a_left, b_right) {
+                    left_keys.push(a);
+                    right_keys.push(b);
+                    Ok(())
+                } else if let (Ok(b), Ok(a)) = (b_left, a_right) {
+                    left_keys.push(b);
+                    right_keys.push(a);
+                    Ok(())
+                } else {
+                    Err(ExecutionError::plan_expr(
+                        String::from(
+                            "Join condition must compare a column from each side of the join",
+                        ),
+                        sql,
+                    ))
+                }
+            }
+            _ => Err(ExecutionError::plan_expr(
+                String::from(
+                    "Unsupported join condition; only equality predicates combined with AND are \
+                     supported",
+                ),
+                sql,
             )),
         }
     }
 
     /// Generate a relational expression from a SQL expression
-    pub fn sql_to_rex(&self, sql: &ASTNode, schema: &Schema) -> Result<Expr, String> {
+    pub fn sql_to_rex(&self, sql: &ASTNode, schema: &Schema) -> Result<Expr> {
         match sql {
             &ASTNode::SQLLiteralLong(n) => Ok(Expr::Literal(ScalarValue::Int64(n))),
             &ASTNode::SQLLiteralDouble(n) => Ok(Expr::Literal(ScalarValue::Float64(n))),
             &ASTNode::SQLLiteralString(ref s) => {
                 Ok(Expr::Literal(ScalarValue::Utf8(Rc::new(s.clone()))))
             }
+            &ASTNode::SQLLiteralBinary(ref bytes) => {
+                Ok(Expr::Literal(ScalarValue::Binary(Rc::new(bytes.clone()))))
+            }
+            &ASTNode::SQLLiteralNull => Ok(Expr::Literal(ScalarValue::Null)),
+
+            &ASTNode::SQLTypedString {
+                ref data_type,
+                ref value,
+            } => {
+                let literal = match convert_data_type(data_type) {
+                    DataType::Date32 => temporal::parse_date(value).map(ScalarValue::Date32),
+                    DataType::Time64(unit) => {
+                        temporal::parse_time(value, unit).map(|t| ScalarValue::Time64(t, unit))
+                    }
+                    DataType::Timestamp(unit) => temporal::parse_timestamp(value, unit)
+                        .map(|t| ScalarValue::Timestamp(t, unit)),
+                    _ => Ok(ScalarValue::Utf8(Rc::new(value.clone()))),
+                };
+                literal.map(Expr::Literal).map_err(|e| {
+                    ExecutionError::plan_expr(
+                        format!("Invalid {} literal '{}': {}", data_type, value, e),
+                        sql,
+                    )
+                })
+            }
+
+            &ASTNode::SQLInterval {
+                ref value,
+                ref field,
+            } => {
+                let text = match field {
+                    Some(field) => format!("{} {}", value, field),
+                    None => value.clone(),
+                };
+                temporal::parse_interval(&text)
+                    .map(|interval| Expr::Literal(ScalarValue::Interval(interval)))
+                    .map_err(|e| {
+                        let message = format!("Invalid interval '{}': {}", text, e);
+                        ExecutionError::plan_expr(message, sql)
+                    })
+            }
 
             &ASTNode::SQLIdentifier(ref id) => {
-                match schema.columns().iter().position(|c| c.name().eq(id)) {
-                    Some(index) => Ok(Expr::Column(index)),
-                    None => Err(format!(
-                        "Invalid identifier '{}' for schema {}",
-                        id,
-                        schema.to_string()
-                    )),
+                Ok(Expr::ColumnRef(resolve_column(&[id.clone()], schema)?))
+            }
+
+            &ASTNode::SQLCompoundIdentifier(ref parts) => {
+                // `a.b.c` is either field `c` of the qualified column `a.b` or field `b.c` of
+                // column `a`, preferring the qualified column
+                let (column, fields) = match resolve_column(&parts[..2], schema) {
+                    Ok(column) => (column, &parts[2..]),
+                    Err(e) => match resolve_column(&parts[..1], schema) {
+                        Ok(column) => (column, &parts[1..]),
+                        Err(_) => return Err(e),
+                    },
+                };
+                let mut expr = Expr::ColumnRef(column);
+                for part in fields {
+                    expr = field_access(expr, &part.value, part.quoted, schema)?;
                 }
+                Ok(expr)
+            }
+
+            &ASTNode::SQLStruct(ref fields) => {
+                let mut names: Vec<String> = Vec::with_capacity(fields.len());
+                let mut args = Vec::with_capacity(fields.len());
+                for (i, &(ref field, ref alias)) in fields.iter().enumerate() {
+                    let arg = self.sql_to_rex(field, schema)?;
+                    let name = match (alias, &arg) {
+                        (&Some(ref alias), _) => alias.clone(),
+                        (&None, &Expr::ColumnRef(ref c)) => c.name.clone(),
+                        (&None, &Expr::GetField { ref name, .. }) => name.clone(),
+                        (&None, _) => format!("f{}", i + 1),
+                    };
+                    if names.contains(&name) {
+                        return Err(ExecutionError::plan_expr(
+                            format!("Duplicate field name '{}' in struct", name),
+                            sql,
+                        ));
+                    }
+                    names.push(name);
+                    args.push(arg);
+                }
+                Ok(Expr::Struct { names, args })
             }
 
             &ASTNode::SQLWildcard => {
@@ -222,10 +453,31 @@ impl SqlToRel {
                 unimplemented!("SQL wildcard operator is not supported in projection - please use explicit column names")
             }
 
+            &ASTNode::SQLParameter(ref p) => Err(ExecutionError::plan_expr(
+                format!(
+                    "Cannot infer the type of parameter {}; use CAST({} AS <type>) to specify it",
+                    p, p
+                ),
+                sql,
+            )),
+
             &ASTNode::SQLCast {
                 ref expr,
                 ref data_type,
-            } => Ok(Expr::Cast {
+            } => match expr.as_ref() {
+                &ASTNode::SQLParameter(ref p) => {
+                    self.parameter_to_rex(p, convert_data_type(data_type), sql)
+                }
+                _ => Ok(Expr::Cast {
+                    expr: Rc::new(self.sql_to_rex(&expr, schema)?),
+                    data_type: convert_data_type(data_type),
+                }),
+            },
+
+            &ASTNode::SQLTryCast {
+                ref expr,
+                ref data_type,
+            } => Ok(Expr::TryCast {
                 expr: Rc::new(self.sql_to_rex(&expr, schema)?),
                 data_type: convert_data_type(data_type),
             }),
@@ -234,10 +486,52 @@ impl SqlToRel {
                 Ok(Expr::IsNull(Rc::new(self.sql_to_rex(expr, schema)?)))
             }
 
+            &ASTNode::SQLArrayIndex {
+                ref expr,
+                ref index,
+            } => {
+                let list = self.sql_to_rex(expr, schema)?;
+                if let DataType::Struct(_) = list.get_type(schema)? {
+                    return match index.as_ref() {
+                        &ASTNode::SQLLiteralString(ref name) => {
+                            field_access(list, name, true, schema)
+                        }
+                        _ => Err(ExecutionError::plan_expr(
+                            String::from("Struct fields must be accessed by a string literal"),
+                            index,
+                        )),
+                    };
+                }
+                list_element_type(&list, schema)?;
+                let index = match index.as_ref() {
+                    &ASTNode::SQLParameter(ref p) => {
+                        self.parameter_to_rex(p, DataType::Int64, index)?
+                    }
+                    _ => self
+                        .sql_to_rex(index, schema)?
+                        .cast_to(&DataType::Int64, schema)?,
+                };
+                Ok(Expr::ArrayFunction {
+                    fun: ArrayFunction::Element,
+                    args: vec![list, index],
+                })
+            }
+
             &ASTNode::SQLIsNotNull(ref expr) => {
                 Ok(Expr::IsNotNull(Rc::new(self.sql_to_rex(expr, schema)?)))
             }
 
+            &ASTNode::SQLNot(ref expr) => {
+                let expr = self.sql_to_rex(expr, schema)?;
+                match expr.get_type(schema)? {
+                    DataType::Boolean => Ok(Expr::Not(Rc::new(expr))),
+                    other => Err(ExecutionError::type_error(
+                        format!("NOT requires a boolean operand but got {:?}", other),
+                        &expr,
+                    )),
+                }
+            }
+
             &ASTNode::SQLBinaryExpr {
                 ref left,
                 ref op,
@@ -257,12 +551,99 @@ impl SqlToRel {
                     &SQLOperator::Modulus => Operator::Modulus,
                     &SQLOperator::And => Operator::And,
                     &SQLOperator::Or => Operator::Or,
+                    &SQLOperator::StringConcat => Operator::StringConcat,
                 };
 
-                let left_expr = self.sql_to_rex(&left, &schema)?;
-                let right_expr = self.sql_to_rex(&right, &schema)?;
-                let left_type = left_expr.get_type(schema);
-                let right_type = right_expr.get_type(schema);
+                // a parameter takes the type of the other operand
+                let (left_expr, right_expr) = match (left.as_ref(), right.as_ref()) {
+                    (&ASTNode::SQLParameter(ref p), r) if !is_parameter(r) => {
+                        let right_expr = self.sql_to_rex(r, &schema)?;
+                        let left_type = right_expr.get_type(schema)?;
+                        (self.parameter_to_rex(p, left_type, left)?, right_expr)
+                    }
+                    (l, &ASTNode::SQLParameter(ref p)) if !is_parameter(l) => {
+                        let left_expr = self.sql_to_rex(l, &schema)?;
+                        let right_type = left_expr.get_type(schema)?;
+                        (left_expr, self.parameter_to_rex(p, right_type, right)?)
+                    }
+                    // and so does a null
+                    (&ASTNode::SQLLiteralNull, r) if !is_null(r) => {
+                        let right_expr = self.sql_to_rex(r, &schema)?;
+                        let left_type = right_expr.get_type(schema)?;
+                        (typed_null(left_type), right_expr)
+                    }
+                    (l, &ASTNode::SQLLiteralNull) if !is_null(l) => {
+                        let left_expr = self.sql_to_rex(l, &schema)?;
+                        let right_type = left_expr.get_type(schema)?;
+                        (left_expr, typed_null(right_type))
+                    }
+                    _ => (
+                        self.sql_to_rex(&left, &schema)?,
+                        self.sql_to_rex(&right, &schema)?,
+                    ),
+                };
+                let left_type = left_expr.get_type(schema)?;
+                let right_type = right_expr.get_type(schema)?;
+
+                if operator == Operator::StringConcat {
+                    if left_type != DataType::Utf8 || right_type != DataType::Utf8 {
+                        return Err(ExecutionError::type_error(
+                            format!(
+                                "|| requires Utf8 operands but got {:?} and {:?}",
+                                left_type, right_type
+                            ),
+                            sql,
+                        ));
+                    }
+                    return Ok(Expr::BinaryExpr {
+                        left: Rc::new(left_expr),
+                        op: operator,
+                        right: Rc::new(right_expr),
+                    });
+                }
+
+                // intervals shift dates and timestamps without any coercion, and are always
+                // evaluated as the right operand
+                let shifts_by_interval = match operator {
+                    Operator::Plus | Operator::Minus => {
+                        get_interval_arithmetic_type(&left_type, &right_type).is_some()
+                    }
+                    _ => false,
+                };
+                if shifts_by_interval {
+                    return Ok(Expr::BinaryExpr {
+                        left: Rc::new(left_expr),
+                        op: operator,
+                        right: Rc::new(right_expr),
+                    });
+                }
+                if operator == Operator::Plus
+                    && get_interval_arithmetic_type(&right_type, &left_type).is_some()
+                {
+                    return Ok(Expr::BinaryExpr {
+                        left: Rc::new(right_expr),
+                        op: operator,
+                        right: Rc::new(left_expr),
+                    });
+                }
+
+                // decimal arithmetic keeps the precision and scale of each operand, and the
+                // result type is derived from both
+                let decimal_type = operator.decimal_operator().and_then(|op| {
+                    get_decimal_arithmetic_type(op, &left_type, &right_type)
+                });
+                if decimal_type.is_some() {
+                    if let (Some(left_type), Some(right_type)) = (
+                        get_decimal_operand_type(&left_type),
+                        get_decimal_operand_type(&right_type),
+                    ) {
+                        return Ok(Expr::BinaryExpr {
+                            left: Rc::new(left_expr.cast_to(&left_type, schema)?),
+                            op: operator,
+                            right: Rc::new(right_expr.cast_to(&right_type, schema)?),
+                        });
+                    }
+                }
 
                 match get_supertype(&left_type, &right_type) {
                     Some(supertype) => Ok(Expr::BinaryExpr {
@@ -271,10 +652,13 @@ impl SqlToRel {
                         right: Rc::new(right_expr.cast_to(&supertype, schema)?),
                     }),
                     None => {
-                        return Err(format!(
-                            "No common supertype found for binary operator {:?} \
-                             with input types {:?} and {:?}",
-                            operator, left_type, right_type
+                        return Err(ExecutionError::type_error(
+                            format!(
+                                "No common supertype found for binary operator {:?} \
+                                 with input types {:?} and {:?}",
+                                operator, left_type, right_type
+                            ),
+                            sql,
                         ))
                     }
                 }
@@ -285,6 +669,20 @@ impl SqlToRel {
                 asc,
             }),
 
+            &ASTNode::SQLExtract {
+                ref field,
+                ref expr,
+            } => {
+                let date_part = ASTNode::SQLFunction {
+                    id: "date_part".to_string(),
+                    args: vec![
+                        ASTNode::SQLLiteralString(field.to_lowercase()),
+                        expr.as_ref().clone(),
+                    ],
+                };
+                self.sql_to_rex(&date_part, schema)
+            }
+
             &ASTNode::SQLFunction { ref id, ref args } => {
                 //TODO: fix this hack
                 match id.to_lowercase().as_ref() {
@@ -292,10 +690,16 @@ impl SqlToRel {
                         let rex_args = args
                             .iter()
                             .map(|a| self.sql_to_rex(a, schema))
-                            .collect::<Result<Vec<Expr>, String>>()?;
+                            .collect::<Result<Vec<Expr>>>()?;
 
-                        // return type is same as the argument type for these aggregate functions
-                        let return_type = rex_args[0].get_type(schema).clone();
+                        // return type is same as the argument type for these aggregate functions,
+                        // except that the sum of decimals has room for more integer digits
+                        let return_type = match rex_args[0].get_type(schema)? {
+                            DataType::Decimal(p, s) if id.to_lowercase() == "sum" => {
+                                DataType::Decimal((p + 10).min(decimal::MAX_PRECISION), s)
+                            }
+                            t => t,
+                        };
 
                         Ok(Expr::AggregateFunction {
                             name: id.clone(),
@@ -312,7 +716,7 @@ impl SqlToRel {
                                 ASTNode::SQLWildcard => Ok(Expr::Column(0)),
                                 _ => self.sql_to_rex(a, schema),
                             })
-                            .collect::<Result<Vec<Expr>, String>>()?;
+                            .collect::<Result<Vec<Expr>>>()?;
 
                         Ok(Expr::AggregateFunction {
                             name: id.clone(),
@@ -320,12 +724,109 @@ impl SqlToRel {
                             return_type: DataType::UInt64,
                         })
                     }
+                    "array_agg" => {
+                        let rex_args = args
+                            .iter()
+                            .map(|a| self.sql_to_rex(a, schema))
+                            .collect::<Result<Vec<Expr>>>()?;
+                        if rex_args.len() != 1 {
+                            return Err(ExecutionError::plan_expr(
+                                String::from("ARRAY_AGG() requires exactly one argument"),
+                                sql,
+                            ));
+                        }
+                        let element_type = rex_args[0].get_type(schema)?;
+                        Ok(Expr::AggregateFunction {
+                            name: id.clone(),
+                            args: rex_args,
+                            return_type: DataType::List(Box::new(element_type)),
+                        })
+                    }
+                    "array_length" | "array_contains" => {
+                        let (fun, arg_count) = if id.eq_ignore_ascii_case("array_length") {
+                            (ArrayFunction::Length, 1)
+                        } else {
+                            (ArrayFunction::Contains, 2)
+                        };
+                        if args.len() != arg_count {
+                            return Err(ExecutionError::plan_expr(
+                                format!(
+                                    "Function '{}' expects {} arguments but {} were given",
+                                    id,
+                                    arg_count,
+                                    args.len()
+                                ),
+                                sql,
+                            ));
+                        }
+                        let list = self.sql_to_rex(&args[0], schema)?;
+                        let element_type = list_element_type(&list, schema)?;
+                        let mut rex_args = vec![list];
+                        // as with binary operators, the value and the elements are compared as
+                        // their common supertype. The value is widened here and the elements
+                        // are widened when the function is evaluated.
+                        if let Some(value) = args.get(1) {
+                            rex_args.push(match value {
+                                ASTNode::SQLParameter(ref p) => {
+                                    self.parameter_to_rex(p, element_type, value)?
+                                }
+                                _ => {
+                                    let rex = self.sql_to_rex(value, schema)?;
+                                    let value_type = rex.get_type(schema)?;
+                                    match get_supertype(&element_type, &value_type) {
+                                        Some(supertype) => rex.cast_to(&supertype, schema)?,
+                                        None => {
+                                            return Err(ExecutionError::type_error(
+                                                format!(
+                                                    "No common supertype found for \
+                                                     array_contains with element type {:?} \
+                                                     and value type {:?}",
+                                                    element_type, value_type
+                                                ),
+                                                sql,
+                                            ))
+                                        }
+                                    }
+                                }
+                            });
+                        }
+                        Ok(Expr::ArrayFunction {
+                            fun,
+                            args: rex_args,
+                        })
+                    }
+                    "unnest" => Err(ExecutionError::plan_expr(
+                        String::from(
+                            "UNNEST can only be used in the FROM clause or as an item in a \
+                             SELECT list",
+                        ),
+                        sql,
+                    )),
                     _ => match self.schema_provider.get_function_meta(id) {
                         Some(fm) => {
+                            if args.len() != fm.args().len() {
+                                return Err(ExecutionError::plan_expr(
+                                    format!(
+                                        "Function '{}' expects {} arguments but {} were given",
+                                        id,
+                                        fm.args().len(),
+                                        args.len()
+                                    ),
+                                    sql,
+                                ));
+                            }
+
+                            // parameters take the type of the function argument
                             let rex_args = args
                                 .iter()
-                                .map(|a| self.sql_to_rex(a, schema))
-                                .collect::<Result<Vec<Expr>, String>>()?;
+                                .enumerate()
+                                .map(|(i, a)| match (a, fm.args().get(i)) {
+                                    (&ASTNode::SQLParameter(ref p), Some(arg)) => {
+                                        self.parameter_to_rex(p, arg.data_type().clone(), a)
+                                    }
+                                    _ => self.sql_to_rex(a, schema),
+                                })
+                                .collect::<Result<Vec<Expr>>>()?;
 
                             let mut safe_args: Vec<Expr> = vec![];
                             for i in 0..rex_args.len() {
@@ -339,16 +840,181 @@ impl SqlToRel {
                                 return_type: fm.return_type().clone(),
                             })
                         }
-                        _ => Err(format!("Invalid function '{}'", id)),
+                        _ => Err(ExecutionError::plan_expr(
+                            format!("Invalid function '{}'", id),
+                            sql,
+                        )),
                     },
                 }
             }
 
-            _ => Err(String::from(format!(
-                "Unsupported ast node {:?} in sqltorel",
-                sql
-            ))),
+            _ => Err(ExecutionError::plan_expr(
+                String::from("Unsupported expression"),
+                sql,
+            )),
+        }
+    }
+}
+
+/// Get the element type of an expression that must be a list
+fn list_element_type(expr: &Expr, schema: &Schema) -> Result<DataType> {
+    match expr.get_type(schema)? {
+        DataType::List(t) => Ok(*t),
+        other => Err(ExecutionError::type_error(
+            format!("Expected a list but got {:?}", other),
+            expr,
+        )),
+    }
+}
+
+/// Access a field of a struct expression. Unquoted names also match a field whose name only
+/// differs by case, as long as there is exactly one such field.
+fn field_access(expr: Expr, name: &str, case_sensitive: bool, schema: &Schema) -> Result<Expr> {
+    let name = match expr.get_type(schema)? {
+        DataType::Struct(ref fields)
+            if !case_sensitive && !fields.iter().any(|f| f.name() == name) =>
+        {
+            let candidates: Vec<&Field> = fields
+                .iter()
+                .filter(|f| f.name().to_lowercase() == name.to_lowercase())
+                .collect();
+            if candidates.len() == 1 {
+                candidates[0].name().clone()
+            } else {
+                name.to_string()
+            }
+        }
+        _ => name.to_string(),
+    };
+    let expr = Expr::GetField {
+        expr: Rc::new(expr),
+        name,
+    };
+    expr.get_type(schema)?;
+    Ok(expr)
+}
+
+/// Get the argument of an `UNNEST(expr)` call
+fn unnest_arg(sql: &ASTNode) -> Option<&ASTNode> {
+    match sql {
+        &ASTNode::SQLFunction { ref id, ref args }
+            if id.eq_ignore_ascii_case("unnest") && args.len() == 1 =>
+        {
+            Some(&args[0])
+        }
+        _ => None,
+    }
+}
+
+fn is_parameter(sql: &ASTNode) -> bool {
+    match sql {
+        &ASTNode::SQLParameter(_) => true,
+        _ => false,
+    }
+}
+
+fn is_null(sql: &ASTNode) -> bool {
+    match sql {
+        &ASTNode::SQLLiteralNull => true,
+        _ => false,
+    }
+}
+
+/// A null literal of the given type
+fn typed_null(data_type: DataType) -> Expr {
+    Expr::Cast {
+        expr: Rc::new(Expr::Literal(ScalarValue::Null)),
+        data_type,
+    }
+}
+
+/// Get the identifiers that make up a possibly qualified column name in a join condition
+fn sql_to_column_name(sql: &ASTNode) -> Result<Vec<SQLIdent>> {
+    match sql {
+        &ASTNode::SQLIdentifier(ref id) => Ok(vec![id.clone()]),
+        &ASTNode::SQLCompoundIdentifier(ref parts) if parts.len() == 2 => Ok(parts.clone()),
+        _ => Err(ExecutionError::plan_expr(
+            String::from("Expected a column in join condition"),
+            sql,
+        )),
+    }
+}
+
+/// Resolve a table name to the name of a table known to the schema provider. Quoted names must
+/// match exactly, while unquoted names fall back to matching a table whose name only differs by
+/// case, the same as column names.
+pub fn resolve_table_name(schema_provider: &SchemaProvider, name: &SQLIdent) -> Result<String> {
+    if name.quoted || schema_provider.get_table_meta(&name.value).is_some() {
+        return Ok(name.value.clone());
+    }
+    let names = schema_provider.table_names();
+    let candidates: Vec<&String> = names
+        .iter()
+        .filter(|n| n.to_lowercase() == name.value.to_lowercase())
+        .collect();
+    match candidates.len() {
+        0 => Ok(name.value.clone()),
+        1 => Ok(candidates[0].clone()),
+        _ => Err(ExecutionError::plan(format!(
+            "Table name '{}' is ambiguous, it could refer to {}",
+            name.value,
+            candidates
+                .iter()
+                .map(|n| format!("'{}'", n))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ))),
+    }
+}
+
+/// Resolve a column name, optionally qualified with a relation name, to a column in the
+/// schema. Quoted identifiers must match exactly, while unquoted identifiers fall back to
+/// matching a column whose name only differs by case.
+fn resolve_column(parts: &[SQLIdent], schema: &Schema) -> Result<Column> {
+    let column = if parts.len() == 1 {
+        Column::from_name(&parts[0].value)
+    } else {
+        Column::qualified(&parts[0].value, &parts[1].value)
+    };
+    let e = match column.index_of(schema) {
+        Ok(_) => return Ok(column),
+        Err(e) => e,
+    };
+    let not_found = match e {
+        ExecutionError::ColumnNotFound { .. } => true,
+        _ => false,
+    };
+    if !not_found || parts.iter().all(|p| p.quoted) {
+        return Err(e);
+    }
+
+    let matches = |name: &str, part: &SQLIdent| {
+        if part.quoted {
+            name == part.value
+        } else {
+            name.to_lowercase() == part.value.to_lowercase()
         }
+    };
+    let candidates: Vec<Column> = schema
+        .columns()
+        .iter()
+        .map(|f| Column::from_qualified_name(f.name()))
+        .filter(|c| {
+            let relation_matches = match (&column.relation, &c.relation) {
+                (&None, _) => true,
+                (&Some(_), &Some(ref r)) => matches(r, &parts[0]),
+                (&Some(_), &None) => false,
+            };
+            relation_matches && matches(&c.name, &parts[parts.len() - 1])
+        })
+        .collect();
+    match candidates.len() {
+        0 => Err(e),
+        1 => Ok(candidates[0].clone()),
+        _ => Err(ExecutionError::AmbiguousColumn {
+            name: column.flat_name(),
+            candidates: candidates.iter().map(|c| c.flat_name()).collect(),
+        }),
     }
 }
 
@@ -367,152 +1033,355 @@ pub fn convert_data_type(sql: &SQLType) -> DataType {
         SQLType::Float32 => DataType::Float64,
         SQLType::Double64 => DataType::Float64,
         SQLType::Utf8(_) => DataType::Utf8,
+        SQLType::Date => DataType::Date32,
+        SQLType::Time => DataType::Time64(TimeUnit::Microsecond),
+        SQLType::Timestamp => DataType::Timestamp(TimeUnit::Microsecond),
+        SQLType::Decimal(p, s) => DataType::Decimal(*p, *s),
+        SQLType::List(t) => DataType::List(Box::new(convert_data_type(t))),
     }
 }
 
-pub fn expr_to_field(e: &Expr, input_schema: &Schema) -> Field {
+pub fn expr_to_field(e: &Expr, input_schema: &Schema) -> Result<Field> {
     match e {
-        Expr::Column(i) => input_schema.columns()[*i].clone(),
-        Expr::Literal(ref lit) => Field::new("lit", lit.get_datatype(), true),
+        Expr::Column(i) => {
+            if *i < input_schema.columns().len() {
+                Ok(input_schema.columns()[*i].clone())
+            } else {
+                Err(ExecutionError::plan(format!(
+                    "Column index {} is out of bounds for schema {}",
+                    i,
+                    input_schema.to_string()
+                )))
+            }
+        }
+        Expr::ColumnRef(c) => {
+            let field = input_schema.column(c.index_of(input_schema)?);
+            Ok(Field::new(
+                &c.name,
+                field.data_type().clone(),
+                field.is_nullable(),
+            ))
+        }
+        Expr::Literal(ref lit) => Ok(Field::new("lit", lit.get_datatype()?, true)),
+        Expr::Parameter {
+            ref name,
+            ref data_type,
+        } => Ok(Field::new(name, data_type.clone(), true)),
         Expr::ScalarFunction {
             ref name,
             ref return_type,
             ..
-        } => Field::new(name, return_type.clone(), true),
+        } => Ok(Field::new(name, return_type.clone(), true)),
         Expr::AggregateFunction {
             ref name,
             ref return_type,
             ..
-        } => Field::new(name, return_type.clone(), true),
-        Expr::Cast { ref data_type, .. } => Field::new("cast", data_type.clone(), true),
+        } => Ok(Field::new(name, return_type.clone(), true)),
+        Expr::ArrayFunction { ref fun, .. } => {
+            let name = match fun {
+                ArrayFunction::Length => "array_length",
+                ArrayFunction::Contains => "array_contains",
+                ArrayFunction::Element => "array_element",
+            };
+            Ok(Field::new(name, e.get_type(input_schema)?, true))
+        }
+        Expr::GetField { ref name, .. } => Ok(Field::new(name, e.get_type(input_schema)?, true)),
+        Expr::Struct { .. } => Ok(Field::new("struct", e.get_type(input_schema)?, true)),
+        Expr::Cast { ref data_type, .. } => Ok(Field::new("cast", data_type.clone(), true)),
+        Expr::TryCast { ref data_type, .. } => {
+            Ok(Field::new("try_cast", data_type.clone(), true))
+        }
+        Expr::IsNull(_) | Expr::IsNotNull(_) | Expr::Not(_) => {
+            Ok(Field::new("unary_expr", DataType::Boolean, true))
+        }
         Expr::BinaryExpr {
             ref left,
             ref right,
-            ..
+            ref op,
         } => {
-            let left_type = left.get_type(input_schema);
-            let right_type = right.get_type(input_schema);
-            Field::new(
-                "binary_expr",
-                get_supertype(&left_type, &right_type).unwrap(),
-                true,
-            )
+            let left_type = left.get_type(input_schema)?;
+            let right_type = right.get_type(input_schema)?;
+            let arithmetic_type = op
+                .decimal_operator()
+                .and_then(|op| get_decimal_arithmetic_type(op, &left_type, &right_type))
+                .or_else(|| get_interval_arithmetic_type(&left_type, &right_type));
+            match arithmetic_type.or_else(|| get_supertype(&left_type, &right_type)) {
+                Some(t) => Ok(Field::new("binary_expr", t, true)),
+                None => Err(ExecutionError::type_error(
+                    format!(
+                        "No common supertype found for {:?} and {:?}",
+                        left_type, right_type
+                    ),
+                    e,
+                )),
+            }
         }
-        _ => unimplemented!("Cannot determine schema type for expression {:?}", e),
+        _ => Err(ExecutionError::plan_expr(
+            String::from("Cannot determine schema type for expression"),
+            e,
+        )),
     }
 }
 
-pub fn exprlist_to_fields(expr: &Vec<Expr>, input_schema: &Schema) -> Vec<Field> {
+pub fn exprlist_to_fields(expr: &Vec<Expr>, input_schema: &Schema) -> Result<Vec<Field>> {
     expr.iter()
         .map(|e| expr_to_field(e, input_schema))
         .collect()
 }
 
-fn collect_expr(e: &Expr, accum: &mut HashSet<usize>) {
-    match e {
-        Expr::Column(i) => {
-            accum.insert(*i);
+/// The columns required from a plan by index. Each column has the paths of the struct fields
+/// that are required, where an empty path means that the whole column is required.
+type RequiredColumns = HashMap<usize, Vec<Vec<String>>>;
+
+/// Collects the columns referenced by an expression. A struct column that is only referenced
+/// through field accesses is collected with the paths of those fields.
+struct ColumnIndexCollector<'a> {
+    schema: &'a Schema,
+    accum: &'a mut RequiredColumns,
+}
+
+impl<'a> ExprVisitor for ColumnIndexCollector<'a> {
+    fn pre_visit(&mut self, expr: &Expr) -> Result<Recursion> {
+        let (base, path) = field_path(expr);
+        let index = match base {
+            Expr::Column(i) => *i,
+            Expr::ColumnRef(c) => c.index_of(self.schema)?,
+            _ => return Ok(Recursion::Continue),
+        };
+        let paths = self.accum.entry(index).or_insert_with(Vec::new);
+        if !paths.contains(&path) {
+            paths.push(path);
         }
-        Expr::Cast { ref expr, .. } => collect_expr(expr, accum),
-        Expr::Literal(_) => {}
-        Expr::IsNotNull(ref expr) => collect_expr(expr, accum),
-        Expr::IsNull(ref expr) => collect_expr(expr, accum),
-        Expr::BinaryExpr {
-            ref left,
-            ref right,
-            ..
-        } => {
-            collect_expr(left, accum);
-            collect_expr(right, accum);
+        Ok(Recursion::SkipChildren)
+    }
+}
+
+/// Split a chain of field accesses such as `location.lat` into the expression that the fields
+/// are accessed on and the names of the fields
+pub fn field_path(expr: &Expr) -> (&Expr, Vec<String>) {
+    match expr {
+        Expr::GetField { expr, name } => {
+            let (base, mut path) = field_path(expr.as_ref());
+            path.push(name.clone());
+            (base, path)
         }
-        Expr::AggregateFunction { ref args, .. } => {
-            args.iter().for_each(|e| collect_expr(e, accum));
+        other => (other, vec![]),
+    }
+}
+
+/// Collect the columns referenced by an expression, resolving column references by name
+/// against the schema
+fn collect_expr(e: &Expr, schema: &Schema, accum: &mut RequiredColumns) -> Result<()> {
+    e.accept(&mut ColumnIndexCollector { schema, accum })?;
+    Ok(())
+}
+
+/// Get the sorted indices of the required columns
+fn required_indices(required: &RequiredColumns) -> Vec<usize> {
+    let mut indices: Vec<usize> = required.keys().cloned().collect();
+    indices.sort();
+    indices
+}
+
+/// Get the paths of the struct fields that are required, for the columns that are not required
+/// in full. Each path starts with the name of the column in the schema.
+fn required_fields(required: &RequiredColumns, schema: &Schema) -> Vec<Vec<String>> {
+    let mut fields = vec![];
+    for i in required_indices(required) {
+        let paths = &required[&i];
+        if paths.iter().any(|p| p.is_empty()) {
+            continue;
         }
-        Expr::ScalarFunction { ref args, .. } => {
-            args.iter().for_each(|e| collect_expr(e, accum));
+        for path in paths {
+            let mut field_path = vec![schema.column(i).name().clone()];
+            field_path.extend(path.iter().cloned());
+            fields.push(field_path);
         }
-        Expr::Sort { ref expr, .. } => collect_expr(expr, accum),
     }
+    fields
 }
 
+/// Push the columns that are required from a plan down into its table scans, so that only
+/// those columns are read
 pub fn push_down_projection(
     plan: &Rc<LogicalPlan>,
     projection: &HashSet<usize>,
-) -> Rc<LogicalPlan> {
-    //println!("push_down_projection() projection={:?}", projection);
-    match plan.as_ref() {
-        LogicalPlan::Aggregate {
-            ref input,
-            ref group_expr,
-            ref aggr_expr,
-            ref schema,
-        } => {
-            //TODO: apply projection first
-            let mut accum: HashSet<usize> = HashSet::new();
-            group_expr.iter().for_each(|e| collect_expr(e, &mut accum));
-            aggr_expr.iter().for_each(|e| collect_expr(e, &mut accum));
-            Rc::new(LogicalPlan::Aggregate {
-                input: push_down_projection(&input, &accum),
-                group_expr: group_expr.clone(),
-                aggr_expr: aggr_expr.clone(),
+) -> Result<Rc<LogicalPlan>> {
+    let required: RequiredColumns = projection.iter().map(|i| (*i, vec![vec![]])).collect();
+    push_down_columns(plan, &required)
+}
+
+/// Push the required columns down into table scans, also pushing down the struct fields that are
+/// required, as paths that start with the column name. It is an error for a path to name a
+/// column or field that does not exist.
+pub fn push_down_field_paths(
+    plan: &Rc<LogicalPlan>,
+    projection: &HashSet<usize>,
+    fields: &[Vec<String>],
+) -> Result<Rc<LogicalPlan>> {
+    let schema = plan.schema();
+    let mut required: RequiredColumns = projection.iter().map(|i| (*i, vec![])).collect();
+    for path in fields {
+        let (name, nested) = match path.split_first() {
+            Some(parts) => parts,
+            None => return Err(ExecutionError::plan("Empty struct field path".to_string())),
+        };
+        let i = Column::from_name(name).index_of(&schema)?;
+        let mut data_type = schema.column(i).data_type();
+        for field in nested {
+            let nested_field = match *data_type {
+                DataType::Struct(ref fields) => fields.iter().find(|f| f.name() == field),
+                _ => None,
+            };
+            data_type = match nested_field {
+                Some(f) => f.data_type(),
+                None => {
+                    return Err(ExecutionError::plan(format!(
+                        "Struct field path {} does not exist",
+                        path.join(".")
+                    )))
+                }
+            };
+        }
+        required.entry(i).or_insert_with(Vec::new).push(nested.to_vec());
+    }
+    for paths in required.values_mut() {
+        if paths.is_empty() {
+            paths.push(vec![]);
+        }
+    }
+    push_down_columns(plan, &required)
+}
+
+fn push_down_columns(
+    plan: &Rc<LogicalPlan>,
+    required: &RequiredColumns,
+) -> Result<Rc<LogicalPlan>> {
+    rewrite_plan(
+        plan,
+        &mut ColumnPushDown {
+            required: vec![required.clone()],
+        },
+    )
+}
+
+/// Pushes the required columns down into table scans. The columns required by each node are
+/// kept on a stack: `pre_visit` pushes the columns required from the inputs of a node and
+/// `mutate` pops them again once the inputs have been rewritten.
+struct ColumnPushDown {
+    required: Vec<RequiredColumns>,
+}
+
+impl ColumnPushDown {
+    /// The columns required from the node currently being visited
+    fn current(&self) -> &RequiredColumns {
+        &self.required[self.required.len() - 1]
+    }
+}
+
+impl PlanRewriter for ColumnPushDown {
+    fn pre_visit(&mut self, plan: &LogicalPlan) -> Result<Recursion> {
+        let (accum, recursion) = match plan {
+            LogicalPlan::Projection {
+                ref expr,
+                ref input,
+                ..
+            } => {
+                // every expression is still evaluated, so the required columns are only those
+                // referenced by the expressions. Columns are only pushed through a projection
+                // when it accesses struct fields, so that only those fields are read.
+                let mut accum = RequiredColumns::new();
+                for e in expr {
+                    collect_expr(e, input.schema(), &mut accum)?;
+                }
+                let accesses_fields = accum
+                    .values()
+                    .any(|paths| paths.iter().any(|path| !path.is_empty()));
+                if accesses_fields {
+                    (accum, Recursion::Continue)
+                } else {
+                    (accum, Recursion::SkipChildren)
+                }
+            }
+            LogicalPlan::Aggregate {
+                ref input,
+                ref group_expr,
+                ref aggr_expr,
+                ..
+            } => {
+                //TODO: apply projection first
+                let mut accum = RequiredColumns::new();
+                for e in group_expr.iter().chain(aggr_expr.iter()) {
+                    collect_expr(e, input.schema(), &mut accum)?;
+                }
+                (accum, Recursion::Continue)
+            }
+            LogicalPlan::Selection {
+                ref expr,
+                ref input,
+            } => {
+                let mut accum = self.current().clone();
+                collect_expr(expr, input.schema(), &mut accum)?;
+                (accum, Recursion::Continue)
+            }
+            LogicalPlan::TableScan { .. }
+            | LogicalPlan::CsvFile { .. }
+            | LogicalPlan::NdJsonFile { .. }
+            | LogicalPlan::ParquetFile { .. } => (RequiredColumns::new(), Recursion::Continue),
+            _ => (RequiredColumns::new(), Recursion::SkipChildren),
+        };
+        self.required.push(accum);
+        Ok(recursion)
+    }
+
+    fn mutate(&mut self, plan: Rc<LogicalPlan>) -> Result<Rc<LogicalPlan>> {
+        self.required.pop();
+        let required = self.current();
+        match plan.as_ref() {
+            LogicalPlan::TableScan {
+                ref schema_name,
+                ref table_name,
+                ref schema,
+                ..
+            } => Ok(Rc::new(LogicalPlan::TableScan {
+                schema_name: schema_name.to_string(),
+                table_name: table_name.to_string(),
+                schema: schema.clone(),
+                projection: Some(required_indices(required)),
+                fields: required_fields(required, schema),
+            })),
+            LogicalPlan::CsvFile {
+                ref filename,
+                ref schema,
+                ref has_header,
+                ..
+            } => Ok(Rc::new(LogicalPlan::CsvFile {
+                filename: filename.to_string(),
                 schema: schema.clone(),
-            })
+                has_header: *has_header,
+                projection: Some(required_indices(required)),
+            })),
+            LogicalPlan::NdJsonFile {
+                ref filename,
+                ref schema,
+                ..
+            } => Ok(Rc::new(LogicalPlan::NdJsonFile {
+                filename: filename.to_string(),
+                schema: schema.clone(),
+                projection: Some(required_indices(required)),
+            })),
+            LogicalPlan::ParquetFile {
+                ref filename,
+                ref schema,
+                ..
+            } => Ok(Rc::new(LogicalPlan::ParquetFile {
+                filename: filename.to_string(),
+                schema: schema.clone(),
+                projection: Some(required_indices(required)),
+                fields: required_fields(required, schema),
+            })),
+            _ => Ok(plan.clone()),
         }
-        LogicalPlan::Selection {
-            ref expr,
-            ref input,
-        } => {
-            let mut accum: HashSet<usize> = projection.clone();
-            collect_expr(expr, &mut accum);
-            Rc::new(LogicalPlan::Selection {
-                expr: expr.clone(),
-                input: push_down_projection(&input, &accum),
-            })
-        }
-        LogicalPlan::TableScan {
-            ref schema_name,
-            ref table_name,
-            ref schema,
-            ..
-        } => Rc::new(LogicalPlan::TableScan {
-            schema_name: schema_name.to_string(),
-            table_name: table_name.to_string(),
-            schema: schema.clone(),
-            projection: Some(projection.iter().cloned().collect()),
-        }),
-        LogicalPlan::CsvFile {
-            ref filename,
-            ref schema,
-            ref has_header,
-            ..
-        } => Rc::new(LogicalPlan::CsvFile {
-            filename: filename.to_string(),
-            schema: schema.clone(),
-            has_header: *has_header,
-            projection: Some(projection.iter().cloned().collect()),
-        }),
-        LogicalPlan::NdJsonFile {
-            ref filename,
-            ref schema,
-            ..
-        } => Rc::new(LogicalPlan::NdJsonFile {
-            filename: filename.to_string(),
-            schema: schema.clone(),
-            projection: Some(projection.iter().cloned().collect()),
-        }),
-        LogicalPlan::ParquetFile {
-            ref filename,
-            ref schema,
-            ..
-        } => Rc::new(LogicalPlan::ParquetFile {
-            filename: filename.to_string(),
-            schema: schema.clone(),
-            projection: Some(projection.iter().cloned().collect()),
-        }),
-        LogicalPlan::Projection { .. } => plan.clone(),
-        LogicalPlan::Limit { .. } => plan.clone(),
-        LogicalPlan::Sort { .. } => plan.clone(),
-        LogicalPlan::EmptyRelation { .. } => plan.clone(),
     }
 }
 
@@ -544,8 +1413,8 @@ mod tests {
     fn select_simple_selection() {
         let sql = "SELECT id, first_name, last_name \
                    FROM person WHERE state = 'CO'";
-        let expected = "Projection: #0, #1, #2\
-                        \n  Selection: #4 Eq Utf8(\"CO\")\
+        let expected = "Projection: #id, #first_name, #last_name\
+                        \n  Selection: #state Eq Utf8(\"CO\")\
                         \n    TableScan: person projection=None";
         quick_test(sql, expected);
     }
@@ -555,12 +1424,48 @@ mod tests {
         let sql = "SELECT id, first_name, last_name \
                    FROM person WHERE state = 'CO' AND age >= 21 AND age <= 65";
         let expected =
-            "Projection: #0, #1, #2\
-            \n  Selection: #4 Eq Utf8(\"CO\") And CAST(#3 AS Int64) GtEq Int64(21) And CAST(#3 AS Int64) LtEq Int64(65)\
+            "Projection: #id, #first_name, #last_name\
+            \n  Selection: #state Eq Utf8(\"CO\") And CAST(#age AS Int64) GtEq Int64(21) And CAST(#age AS Int64) LtEq Int64(65)\
             \n    TableScan: person projection=None";
         quick_test(sql, expected);
     }
 
+    #[test]
+    fn select_not_selection() {
+        let sql = "SELECT id FROM person WHERE NOT state = 'CO' AND age IS NOT NULL";
+        let expected = "Projection: #id\
+                        \n  Selection: NOT #state Eq Utf8(\"CO\") And #age IS NOT NULL\
+                        \n    TableScan: person projection=None";
+        quick_test(sql, expected);
+
+        let sql = "SELECT id FROM person WHERE NOT age";
+        let planner = SqlToRel::new(Rc::new(MockSchemaProvider {}));
+        let ast = Parser::parse_sql(sql.to_string()).unwrap();
+        assert!(planner.sql_to_rel(&ast).is_err());
+    }
+
+    #[test]
+    fn select_string_concat() {
+        let sql = "SELECT first_name || ' ' || last_name FROM person WHERE state = 'CO'";
+        let expected = "Projection: #first_name StringConcat Utf8(\" \") StringConcat #last_name\
+                        \n  Selection: #state Eq Utf8(\"CO\")\
+                        \n    TableScan: person projection=None";
+        quick_test(sql, expected);
+
+        let sql = "SELECT first_name || age FROM person";
+        let planner = SqlToRel::new(Rc::new(MockSchemaProvider {}));
+        let ast = Parser::parse_sql(sql.to_string()).unwrap();
+        assert!(planner.sql_to_rel(&ast).is_err());
+    }
+
+    #[test]
+    fn select_try_cast() {
+        let sql = "SELECT TRY_CAST(first_name AS INT) FROM person";
+        let expected = "Projection: TRY_CAST(#first_name AS Int32)\
+                        \n  TableScan: person projection=None";
+        quick_test(sql, expected);
+    }
+
     #[test]
     fn select_all_boolean_operators() {
         let sql = "SELECT age, first_name, last_name \
@@ -571,13 +1476,13 @@ mod tests {
                    AND age >= 21 \
                    AND age < 65 \
                    AND age <= 65";
-        let expected = "Projection: #3, #1, #2\
-                        \n  Selection: CAST(#3 AS Int64) Eq Int64(21) \
-                        And CAST(#3 AS Int64) NotEq Int64(21) \
-                        And CAST(#3 AS Int64) Gt Int64(21) \
-                        And CAST(#3 AS Int64) GtEq Int64(21) \
-                        And CAST(#3 AS Int64) Lt Int64(65) \
-                        And CAST(#3 AS Int64) LtEq Int64(65)\
+        let expected = "Projection: #age, #first_name, #last_name\
+                        \n  Selection: CAST(#age AS Int64) Eq Int64(21) \
+                        And CAST(#age AS Int64) NotEq Int64(21) \
+                        And CAST(#age AS Int64) Gt Int64(21) \
+                        And CAST(#age AS Int64) GtEq Int64(21) \
+                        And CAST(#age AS Int64) Lt Int64(65) \
+                        And CAST(#age AS Int64) LtEq Int64(65)\
                         \n    TableScan: person projection=None";
         quick_test(sql, expected);
     }
@@ -586,7 +1491,7 @@ mod tests {
     fn select_simple_aggregate() {
         quick_test(
             "SELECT MIN(age) FROM person",
-            "Aggregate: groupBy=[[]], aggr=[[MIN(#3)]]\
+            "Aggregate: groupBy=[[]], aggr=[[MIN(#age)]]\
              \n  TableScan: person projection=None",
         );
     }
@@ -595,7 +1500,7 @@ mod tests {
     fn test_sum_aggregate() {
         quick_test(
             "SELECT SUM(age) from person",
-            "Aggregate: groupBy=[[]], aggr=[[SUM(#3)]]\
+            "Aggregate: groupBy=[[]], aggr=[[SUM(#age)]]\
              \n  TableScan: person projection=None",
         );
     }
@@ -604,7 +1509,7 @@ mod tests {
     fn select_simple_aggregate_with_groupby() {
         quick_test(
             "SELECT state, MIN(age), MAX(age) FROM person GROUP BY state",
-            "Aggregate: groupBy=[[#4]], aggr=[[MIN(#3), MAX(#3)]]\
+            "Aggregate: groupBy=[[#state]], aggr=[[MIN(#age), MAX(#age)]]\
              \n  TableScan: person projection=None",
         );
     }
@@ -620,7 +1525,7 @@ mod tests {
     #[test]
     fn select_scalar_func() {
         let sql = "SELECT sqrt(age) FROM person";
-        let expected = "Projection: sqrt(CAST(#3 AS Float64))\
+        let expected = "Projection: sqrt(CAST(#age AS Float64))\
                         \n  TableScan: person projection=None";
         quick_test(sql, expected);
     }
@@ -628,8 +1533,8 @@ mod tests {
     #[test]
     fn select_order_by() {
         let sql = "SELECT id FROM person ORDER BY id";
-        let expected = "Sort: #0 ASC\
-                        \n  Projection: #0\
+        let expected = "Sort: #id ASC\
+                        \n  Projection: #id\
                         \n    TableScan: person projection=None";
         quick_test(sql, expected);
     }
@@ -637,8 +1542,8 @@ mod tests {
     #[test]
     fn select_order_by_desc() {
         let sql = "SELECT id FROM person ORDER BY id DESC";
-        let expected = "Sort: #0 DESC\
-                        \n  Projection: #0\
+        let expected = "Sort: #id DESC\
+                        \n  Projection: #id\
                         \n    TableScan: person projection=None";
         quick_test(sql, expected);
     }
@@ -647,8 +1552,8 @@ mod tests {
     fn select_order_limit() {
         let sql = "SELECT id FROM person ORDER BY id DESC LIMIT 10";
         let expected = "Limit: 10\
-                        \n  Sort: #0 DESC\
-                        \n    Projection: #0\
+                        \n  Sort: #id DESC\
+                        \n    Projection: #id\
                         \n      TableScan: person projection=None";
         quick_test(sql, expected);
     }
@@ -657,31 +1562,432 @@ mod tests {
     fn select_limit() {
         let sql = "SELECT id FROM person LIMIT 10";
         let expected = "Limit: 10\
-                        \n  Projection: #0\
+                        \n  Projection: #id\
                         \n    TableScan: person projection=None";
         quick_test(sql, expected);
     }
 
+    #[test]
+    fn select_qualified_column() {
+        let sql = "SELECT person.id, first_name FROM person WHERE person.state = 'CO'";
+        let expected = "Projection: #person.id, #first_name\
+                        \n  Selection: #person.state Eq Utf8(\"CO\")\
+                        \n    TableScan: person projection=None";
+        quick_test(sql, expected);
+    }
+
+    #[test]
+    fn select_null() {
+        // a null takes the type of the other operand, or of a cast
+        let sql = "SELECT age + NULL, CAST(NULL AS DOUBLE) FROM person WHERE NULL = id";
+        let expected = "Projection: #age Plus CAST(Null AS Int32), CAST(Null AS Float64)\
+                        \n  Selection: CAST(Null AS UInt32) Eq #id\
+                        \n    TableScan: person projection=None";
+        quick_test(sql, expected);
+
+        // a bare null has no type
+        let planner = SqlToRel::new(Rc::new(MockSchemaProvider {}));
+        let ast = Parser::parse_sql("SELECT NULL FROM person".to_string()).unwrap();
+        match planner.sql_to_rel(&ast) {
+            Err(ExecutionError::TypeError { ref message, .. }) => {
+                assert!(message.starts_with("Cannot determine the type of NULL"))
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn select_invalid_qualifier() {
+        let planner = SqlToRel::new(Rc::new(MockSchemaProvider {}));
+        let ast = Parser::parse_sql("SELECT customer.id FROM person".to_string()).unwrap();
+        assert!(planner.sql_to_rel(&ast).is_err());
+    }
+
+    #[test]
+    fn select_join() {
+        let sql = "SELECT person.first_name, orders.id \
+                   FROM person JOIN orders ON orders.customer_id = person.id";
+        let expected = "Projection: #person.first_name, #orders.id\
+                        \n  Join: type=Inner, on=[#person.id = #orders.customer_id]\
+                        \n    TableScan: person projection=None\
+                        \n    TableScan: orders projection=None";
+        quick_test(sql, expected);
+    }
+
+    #[test]
+    fn select_join_non_equi_condition() {
+        let planner = SqlToRel::new(Rc::new(MockSchemaProvider {}));
+        let ast = Parser::parse_sql(
+            "SELECT orders.id FROM person JOIN orders ON person.id > orders.customer_id"
+                .to_string(),
+        ).unwrap();
+        assert!(planner.sql_to_rel(&ast).is_err());
+    }
+
+    #[test]
+    fn select_quoted_identifiers() {
+        let sql = "SELECT \"Full Name\", email FROM \"contacts\" WHERE \"phone\" = '555'";
+        let expected = "Projection: #Full Name, #contacts.Email\
+                        \n  Selection: #phone Eq Utf8(\"555\")\
+                        \n    TableScan: contacts projection=None";
+        quick_test(sql, expected);
+    }
+
+    #[test]
+    fn select_identifier_case_sensitivity() {
+        let planner = SqlToRel::new(Rc::new(MockSchemaProvider {}));
+
+        // quoted identifiers must match exactly
+        let ast = Parser::parse_sql("SELECT \"email\" FROM contacts".to_string()).unwrap();
+        match planner.sql_to_rel(&ast) {
+            Err(ExecutionError::ColumnNotFound { name, .. }) => assert_eq!("email", name),
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        // unquoted identifiers that match more than one column by case are ambiguous
+        let ast = Parser::parse_sql("SELECT PHONE FROM contacts".to_string()).unwrap();
+        match planner.sql_to_rel(&ast) {
+            Err(ExecutionError::AmbiguousColumn { candidates, .. }) => {
+                assert_eq!(2, candidates.len())
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        // table names follow the same rules as column names
+        let ast = Parser::parse_sql("SELECT Email FROM Contacts".to_string()).unwrap();
+        assert_eq!(
+            "Projection: #contacts.Email             \n  TableScan: contacts projection=None",
+            format!("{:?}", planner.sql_to_rel(&ast).unwrap())
+        );
+        let ast = Parser::parse_sql("SELECT Email FROM \"Contacts\"".to_string()).unwrap();
+        match planner.sql_to_rel(&ast) {
+            Err(ExecutionError::TableNotFound { name, suggestions }) => {
+                assert_eq!("Contacts", name);
+                assert_eq!(vec!["contacts".to_string()], suggestions);
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn select_parameters() {
+        let planner = SqlToRel::new(Rc::new(MockSchemaProvider {}));
+        let sql = "SELECT id, sqrt($1) FROM person \
+                   WHERE state = :state AND age >= $2 AND :state = last_name";
+        let ast = Parser::parse_sql(sql.to_string()).unwrap();
+        let plan = planner.sql_to_rel(&ast).unwrap();
+        assert_eq!(
+            "Projection: #id, sqrt($1)\
+             \n  Selection: #state Eq :state And #age GtEq $2 And :state Eq #last_name\
+             \n    TableScan: person projection=None",
+            format!("{:?}", plan)
+        );
+
+        let parameters: Vec<(String, DataType)> = planner
+            .parameters()
+            .unwrap()
+            .iter()
+            .map(|f| (f.name().clone(), f.data_type().clone()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("$1".to_string(), DataType::Float64),
+                ("$2".to_string(), DataType::Int32),
+                (":state".to_string(), DataType::Utf8),
+            ],
+            parameters
+        );
+    }
+
+    #[test]
+    fn select_temporal_literals() {
+        let sql = "SELECT id, INTERVAL '1' DAY + day FROM events \
+                   WHERE day < DATE '2018-01-02' AND ts >= '2018-01-01'";
+        let expected = "Projection: #id, \
+                        #day Plus Interval(Interval { months: 0, days: 1, nanos: 0 })\
+                        \n  Selection: #day Lt Date32(17533) \
+                        And #ts GtEq CAST(Utf8(\"2018-01-01\") AS Timestamp(Microsecond))\
+                        \n    TableScan: events projection=None";
+        quick_test(sql, expected);
+
+        let planner = SqlToRel::new(Rc::new(MockSchemaProvider {}));
+        let ast = Parser::parse_sql("SELECT DATE '2018-13-01'".to_string()).unwrap();
+        match planner.sql_to_rel(&ast) {
+            Err(ExecutionError::PlanError { message, .. }) => {
+                assert!(message.starts_with("Invalid DATE literal '2018-13-01': "))
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn select_extract() {
+        quick_test(
+            "SELECT EXTRACT(MONTH FROM day) FROM events",
+            "Projection: date_part(Utf8(\"month\"), CAST(#day AS Timestamp(Microsecond)))\
+             \n  TableScan: events projection=None",
+        );
+
+        let planner = SqlToRel::new(Rc::new(MockSchemaProvider {}));
+        let ast = Parser::parse_sql("SELECT date_part('year') FROM events".to_string()).unwrap();
+        match planner.sql_to_rel(&ast) {
+            Err(ExecutionError::PlanError { message, .. }) => assert_eq!(
+                "Function 'date_part' expects 2 arguments but 1 were given",
+                message
+            ),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn select_decimal_arithmetic() {
+        quick_test(
+            "SELECT price * qty FROM prices WHERE price < 1.5",
+            "Projection: #price Multiply CAST(#qty AS Decimal(10, 0))\
+             \n  Selection: CAST(#price AS Float64) Lt Float64(1.5)\
+             \n    TableScan: prices projection=None",
+        );
+
+        let planner = SqlToRel::new(Rc::new(MockSchemaProvider {}));
+        let types = |sql: &str| {
+            let ast = Parser::parse_sql(sql.to_string()).unwrap();
+            let plan = planner.sql_to_rel(&ast).unwrap();
+            plan.schema()
+                .columns()
+                .iter()
+                .map(|f| f.data_type().clone())
+                .collect::<Vec<DataType>>()
+        };
+        assert_eq!(
+            vec![
+                DataType::Decimal(21, 2),
+                DataType::Decimal(11, 2),
+                DataType::Decimal(21, 13),
+            ],
+            types("SELECT price * qty, price - price, price / qty FROM prices")
+        );
+        assert_eq!(
+            vec![DataType::Decimal(20, 2)],
+            types("SELECT SUM(price) FROM prices")
+        );
+    }
+
+    #[test]
+    fn select_list_functions() {
+        quick_test(
+            "SELECT tags[1], array_length(tags), array_contains(scores, 2) FROM posts",
+            "Projection: #tags[Int64(1)], array_length(#tags), \
+             array_contains(#scores, Int64(2))\
+             \n  TableScan: posts projection=None",
+        );
+
+        let planner = SqlToRel::new(Rc::new(MockSchemaProvider {}));
+        let ast = Parser::parse_sql("SELECT id[1] FROM posts".to_string()).unwrap();
+        match planner.sql_to_rel(&ast) {
+            Err(ExecutionError::TypeError { message, .. }) => {
+                assert_eq!("Expected a list but got UInt32", message)
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn select_struct_fields() {
+        quick_test(
+            "SELECT location.lat, places.location.address.city, location['lng'] FROM places",
+            "Projection: #location.lat, #places.location.address.city, #location.lng\
+             \n  TableScan: places projection=None",
+        );
+        // unquoted field names also match fields that only differ by case
+        quick_test(
+            "SELECT location.LAT FROM places",
+            "Projection: #location.lat\
+             \n  TableScan: places projection=None",
+        );
+        quick_test(
+            "SELECT STRUCT(id, location.lat, 1 AS one) FROM places",
+            "Projection: struct(#id AS id, #location.lat AS lat, Int64(1) AS one)\
+             \n  TableScan: places projection=None",
+        );
+
+        let planner = SqlToRel::new(Rc::new(MockSchemaProvider {}));
+        let plan_error = |sql: &str| {
+            let ast = Parser::parse_sql(sql.to_string()).unwrap();
+            match planner.sql_to_rel(&ast) {
+                Err(ExecutionError::TypeError { message, .. })
+                | Err(ExecutionError::PlanError { message, .. }) => message,
+                other => panic!("unexpected result {:?}", other),
+            }
+        };
+        assert_eq!(
+            "Struct has no field named 'altitude'",
+            plan_error("SELECT location.altitude FROM places")
+        );
+        assert_eq!(
+            "Struct fields must be accessed by a string literal",
+            plan_error("SELECT location[1] FROM places")
+        );
+        assert_eq!(
+            "Duplicate field name 'id' in struct",
+            plan_error("SELECT STRUCT(id, places.id) FROM places")
+        );
+    }
+
+    #[test]
+    fn push_down_struct_fields() {
+        let planner = SqlToRel::new(Rc::new(MockSchemaProvider {}));
+        let sql = "SELECT location.lat FROM places WHERE location.address.city = 'Paris'";
+        let ast = Parser::parse_sql(sql.to_string()).unwrap();
+        let plan = planner.sql_to_rel(&ast).unwrap();
+        let plan = push_down_projection(&plan, &HashSet::new()).unwrap();
+        assert_eq!(
+            "Projection: #location.lat\
+             \n  Selection: #location.address.city Eq Utf8(\"Paris\")\
+             \n    TableScan: places projection=Some([1]) \
+             fields=[places.location.lat, places.location.address.city]",
+            format!("{:?}", plan)
+        );
+
+        // a column that is referenced directly is read in full
+        let sql = "SELECT location.lat, location FROM places";
+        let ast = Parser::parse_sql(sql.to_string()).unwrap();
+        let plan = planner.sql_to_rel(&ast).unwrap();
+        let plan = push_down_projection(&plan, &HashSet::new()).unwrap();
+        assert_eq!(
+            "Projection: #location.lat, #location\
+             \n  TableScan: places projection=Some([1])",
+            format!("{:?}", plan)
+        );
+
+        // projections that do not access struct fields are left as they are
+        let sql = "SELECT id FROM places WHERE id > 1";
+        let ast = Parser::parse_sql(sql.to_string()).unwrap();
+        let plan = planner.sql_to_rel(&ast).unwrap();
+        let plan = push_down_projection(&plan, &HashSet::new()).unwrap();
+        assert_eq!(
+            "Projection: #id\
+             \n  Selection: CAST(#id AS Int64) Gt Int64(1)\
+             \n    TableScan: places projection=None",
+            format!("{:?}", plan)
+        );
+    }
+
+    #[test]
+    fn push_down_unknown_field_paths() {
+        let planner = SqlToRel::new(Rc::new(MockSchemaProvider {}));
+        let ast = Parser::parse_sql("SELECT id, location FROM places".to_string()).unwrap();
+        let plan = planner.sql_to_rel(&ast).unwrap();
+        let push_down = |path: &str| {
+            let path: Vec<String> = path.split('.').map(|s| s.to_string()).collect();
+            push_down_field_paths(&plan, &HashSet::new(), &[path])
+        };
+        assert!(push_down("location.address.city").is_ok());
+        assert!(push_down("location.altitude").is_err());
+        assert!(push_down("location.lat.degrees").is_err());
+        assert!(push_down("position.lat").is_err());
+    }
+
+    #[test]
+    fn select_unnest() {
+        quick_test(
+            "SELECT id, UNNEST(tags) FROM posts",
+            "Projection: #id, #3\
+             \n  Unnest: #tags\
+             \n    TableScan: posts projection=None",
+        );
+        quick_test(
+            "SELECT id, unnest FROM posts, UNNEST(tags) ORDER BY unnest",
+            "Sort: #unnest ASC\
+             \n  Projection: #id, #unnest\
+             \n    Unnest: #tags\
+             \n      TableScan: posts projection=None",
+        );
+        quick_test(
+            "SELECT id, ARRAY_AGG(unnest) FROM posts, UNNEST(tags) GROUP BY id",
+            "Aggregate: groupBy=[[#id]], aggr=[[ARRAY_AGG(#unnest)]]\
+             \n  Unnest: #tags\
+             \n    TableScan: posts projection=None",
+        );
+
+        let plan = |sql: &str| {
+            let planner = SqlToRel::new(Rc::new(MockSchemaProvider {}));
+            let ast = Parser::parse_sql(sql.to_string()).unwrap();
+            planner.sql_to_rel(&ast)
+        };
+        let schema = plan("SELECT id, UNNEST(tags) FROM posts").unwrap().schema().clone();
+        assert_eq!("unnest", schema.column(1).name());
+        assert_eq!(&DataType::Utf8, schema.column(1).data_type());
+
+        match plan("SELECT UNNEST(tags), UNNEST(scores) FROM posts") {
+            Err(ExecutionError::PlanError { message, .. }) => {
+                assert_eq!("Only one UNNEST is supported in a SELECT list", message)
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        match plan("SELECT id FROM posts WHERE array_length(UNNEST(tags)) > 0") {
+            Err(ExecutionError::PlanError { message, .. }) => assert_eq!(
+                "UNNEST can only be used in the FROM clause or as an item in a SELECT list",
+                message
+            ),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn select_invalid_parameters() {
+        let plan = |sql: &str| {
+            let planner = SqlToRel::new(Rc::new(MockSchemaProvider {}));
+            let ast = Parser::parse_sql(sql.to_string()).unwrap();
+            planner.sql_to_rel(&ast).and_then(|_| planner.parameters())
+        };
+
+        match plan("SELECT $1 FROM person") {
+            Err(ExecutionError::PlanError { message, .. }) => assert_eq!(
+                "Cannot infer the type of parameter $1; use CAST($1 AS <type>) to specify it",
+                message
+            ),
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        match plan("SELECT CAST($1 AS INT) FROM person WHERE state = $1") {
+            Err(ExecutionError::TypeError { message, .. }) => {
+                assert_eq!("Parameter $1 is used as both Utf8 and Int32", message)
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        match plan("SELECT id FROM person WHERE age = $2") {
+            Err(ExecutionError::PlanError { message, .. }) => assert_eq!(
+                "Parameter $1 is not used, but parameters must be numbered consecutively from $1",
+                message
+            ),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
     #[test]
     fn test_collect_expr() {
-        let mut accum: HashSet<usize> = HashSet::new();
+        let schema = MockSchemaProvider {}.get_table_meta("person").unwrap();
+        let mut accum = RequiredColumns::new();
         collect_expr(
             &Expr::Cast {
                 expr: Rc::new(Expr::Column(3)),
                 data_type: DataType::Float64,
             },
+            &schema,
             &mut accum,
-        );
+        ).unwrap();
         collect_expr(
             &Expr::Cast {
-                expr: Rc::new(Expr::Column(3)),
+                expr: Rc::new(Expr::ColumnRef(Column::from_name("age"))),
                 data_type: DataType::Float64,
             },
+            &schema,
             &mut accum,
-        );
+        ).unwrap();
         println!("accum: {:?}", accum);
         assert_eq!(1, accum.len());
-        assert!(accum.contains(&3));
+        assert!(accum.contains_key(&3));
     }
 
     //TODO fix
@@ -747,6 +2053,49 @@ mod tests {
                     Field::new("state", DataType::Utf8, false),
                     Field::new("salary", DataType::Float64, false),
                 ]))),
+                "orders" => Some(Rc::new(Schema::new(vec![
+                    Field::new("id", DataType::UInt32, false),
+                    Field::new("customer_id", DataType::UInt32, false),
+                ]))),
+                "events" => Some(Rc::new(Schema::new(vec![
+                    Field::new("id", DataType::UInt32, false),
+                    Field::new("day", DataType::Date32, false),
+                    Field::new("ts", DataType::Timestamp(TimeUnit::Microsecond), false),
+                ]))),
+                "prices" => Some(Rc::new(Schema::new(vec![
+                    Field::new("price", DataType::Decimal(10, 2), false),
+                    Field::new("qty", DataType::Int32, false),
+                ]))),
+                "posts" => Some(Rc::new(Schema::new(vec![
+                    Field::new("id", DataType::UInt32, false),
+                    Field::new("tags", DataType::List(Box::new(DataType::Utf8)), true),
+                    Field::new("scores", DataType::List(Box::new(DataType::Int32)), true),
+                ]))),
+                "places" => Some(Rc::new(Schema::new(vec![
+                    Field::new("id", DataType::UInt32, false),
+                    Field::new(
+                        "location",
+                        DataType::Struct(vec![
+                            Field::new("lat", DataType::Float64, false),
+                            Field::new("lng", DataType::Float64, false),
+                            Field::new(
+                                "address",
+                                DataType::Struct(vec![
+                                    Field::new("city", DataType::Utf8, true),
+                                    Field::new("zip", DataType::Utf8, true),
+                                ]),
+                                true,
+                            ),
+                        ]),
+                        true,
+                    ),
+                ]))),
+                "contacts" => Some(Rc::new(Schema::new(vec![
+                    Field::new("Full Name", DataType::Utf8, false),
+                    Field::new("Email", DataType::Utf8, false),
+                    Field::new("phone", DataType::Utf8, false),
+                    Field::new("Phone", DataType::Utf8, false),
+                ]))),
                 _ => None,
             }
         }
@@ -758,10 +2107,28 @@ mod tests {
                     vec![Field::new("n", DataType::Float64, false)],
                     DataType::Float64,
                     FunctionType::Scalar,
+                    true,
+                ))),
+                "date_part" => Some(Rc::new(FunctionMeta::new(
+                    "date_part".to_string(),
+                    vec![
+                        Field::new("field", DataType::Utf8, false),
+                        Field::new("timestamp", DataType::Timestamp(TimeUnit::Microsecond), false),
+                    ],
+                    DataType::Float64,
+                    FunctionType::Scalar,
+                    true,
                 ))),
                 _ => None,
             }
         }
+
+        fn table_names(&self) -> Vec<String> {
+            ["person", "orders", "events", "prices", "posts", "places", "contacts"]
+                .iter()
+                .map(|name| name.to_string())
+                .collect()
+        }
     }
 
 }
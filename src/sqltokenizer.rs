@@ -14,6 +14,7 @@
 
 //! SQL Tokenizer
 
+use std::fmt;
 use std::iter::Peekable;
 use std::str::Chars;
 
@@ -24,15 +25,19 @@ use fnv::FnvHashSet;
 pub enum Token {
     /// SQL identifier e.g. table or column name
     Identifier(String),
+    /// Quoted SQL identifier e.g. `"First Name"`, without the quotes
+    QuotedIdentifier(String),
     /// SQL keyword  e.g. Keyword("SELECT")
     Keyword(String),
     /// Numeric literal
     Number(String),
     /// String literal
     String(String),
+    /// Binary literal written as hex digits e.g. `X'DEADBEEF'`, without the quotes
+    HexString(String),
     /// Comma
     Comma,
-    /// Whitespace (space, tab, etc)
+    /// Whitespace (space, tab, etc) or a comment
     Whitespace,
     /// Equality operator `=`
     Eq,
@@ -56,17 +61,138 @@ pub enum Token {
     Div,
     /// Modulo Operator `%`
     Mod,
+    /// String concatenation operator `||`
+    StringConcat,
     /// Left parenthesis `(`
     LParen,
     /// Right parenthesis `)`
     RParen,
+    /// Left bracket `[` (used for list types and indexing into lists)
+    LBracket,
+    /// Right bracket `]`
+    RBracket,
     /// Period (used for compound identifiers or projections into nested types)
     Period,
+    /// Semicolon (used to separate the statements in a script)
+    SemiColon,
+    /// Parameter placeholder in a prepared statement, as written e.g. `$1`, `?` or `:name`
+    Placeholder(String),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Identifier(s) | Token::Keyword(s) | Token::Number(s) | Token::Placeholder(s) => {
+                write!(f, "{}", s)
+            }
+            Token::QuotedIdentifier(s) => write!(f, "\"{}\"", s.replace("\"", "\"\"")),
+            Token::String(s) => write!(f, "'{}'", s.replace("'", "''")),
+            Token::HexString(s) => write!(f, "X'{}'", s),
+            Token::Comma => write!(f, ","),
+            Token::Whitespace => write!(f, " "),
+            Token::Eq => write!(f, "="),
+            Token::Neq => write!(f, "!="),
+            Token::Lt => write!(f, "<"),
+            Token::Gt => write!(f, ">"),
+            Token::LtEq => write!(f, "<="),
+            Token::GtEq => write!(f, ">="),
+            Token::Plus => write!(f, "+"),
+            Token::Minus => write!(f, "-"),
+            Token::Mult => write!(f, "*"),
+            Token::Div => write!(f, "/"),
+            Token::Mod => write!(f, "%"),
+            Token::StringConcat => write!(f, "||"),
+            Token::LParen => write!(f, "("),
+            Token::RParen => write!(f, ")"),
+            Token::LBracket => write!(f, "["),
+            Token::RBracket => write!(f, "]"),
+            Token::Period => write!(f, "."),
+            Token::SemiColon => write!(f, ";"),
+        }
+    }
+}
+
+/// A position in the SQL text, where lines and columns are numbered from 1 and columns
+/// count characters rather than bytes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// The region of the SQL text covered by a token, from `start` up to but excluding `end`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub start: Location,
+    pub end: Location,
+}
+
+impl Span {
+    /// Create an empty span at a location, such as the end of the SQL text
+    pub fn empty(location: Location) -> Self {
+        Span {
+            start: location,
+            end: location,
+        }
+    }
+
+    /// Render the line of SQL containing the start of the span, with carets underneath the
+    /// characters covered by the span e.g.
+    ///
+    /// ```text
+    /// 1 | SELECT a FROM FROM t
+    ///   |               ^^^^
+    /// ```
+    pub fn snippet(&self, sql: &str) -> String {
+        let line = sql.lines().nth(self.start.line - 1).unwrap_or("");
+        let width = if self.end.line == self.start.line && self.end.column > self.start.column {
+            self.end.column - self.start.column
+        } else {
+            1
+        };
+        // keep tabs in the indentation so that the carets line up with the SQL
+        let indent: String = line
+            .chars()
+            .take(self.start.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let gutter = self.start.line.to_string();
+        format!(
+            "{} | {}\n{} | {}{}",
+            gutter,
+            line,
+            " ".repeat(gutter.len()),
+            indent,
+            "^".repeat(width)
+        )
+    }
+}
+
+/// A token and the region of the SQL text that it was read from
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenWithSpan {
+    pub token: Token,
+    pub span: Span,
 }
 
 /// Tokenizer error
-#[derive(Debug)]
-pub struct TokenizerError(String);
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenizerError {
+    pub message: String,
+    pub location: Location,
+}
+
+impl fmt::Display for TokenizerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.location)
+    }
+}
 
 lazy_static! {
     static ref KEYWORDS: FnvHashSet<&'static str> = {
@@ -106,6 +232,12 @@ lazy_static! {
         m.insert("WITHOUT");
         m.insert("HEADER");
         m.insert("ROW");
+        m.insert("JOIN");
+        m.insert("INNER");
+        m.insert("ON");
+        m.insert("ANALYZE");
+        m.insert("EXPLAIN");
+        m.insert("VERBOSE");
 
         // SQL types
         m.insert("STRING");
@@ -115,6 +247,12 @@ lazy_static! {
         m.insert("INT");
         m.insert("INTEGER");
         m.insert("LONG");
+        m.insert("DATE");
+        m.insert("TIME");
+        m.insert("TIMESTAMP");
+        m.insert("INTERVAL");
+        m.insert("DECIMAL");
+        m.insert("NUMERIC");
 
         // Arrow native types
         m.insert("BOOLEAN");
@@ -134,6 +272,12 @@ lazy_static! {
     };
 }
 
+/// Determine whether a word is a SQL keyword, in which case it must be quoted to be used as an
+/// identifier
+pub fn is_keyword(word: &str) -> bool {
+    KEYWORDS.contains(word.to_uppercase().as_str())
+}
+
 /// SQL Tokenizer
 pub struct Tokenizer {
     pub query: String,
@@ -149,35 +293,51 @@ impl Tokenizer {
 
     /// Tokenize the statement and produce a vector of tokens
     pub fn tokenize(&mut self) -> Result<Vec<Token>, TokenizerError> {
-        let mut peekable = self.query.chars().peekable();
-
-        let mut tokens: Vec<Token> = vec![];
+        Ok(self
+            .tokenize_with_spans()?
+            .into_iter()
+            .map(|t| t.token)
+            .collect())
+    }
 
-        while let Some(token) = self.next_token(&mut peekable)? {
-            tokens.push(token);
+    /// Tokenize the statement and produce a vector of tokens along with the region of the
+    /// statement that each token was read from
+    pub fn tokenize_with_spans(&mut self) -> Result<Vec<TokenWithSpan>, TokenizerError> {
+        let mut chars = CharStream::new(&self.query);
+
+        let mut tokens: Vec<TokenWithSpan> = vec![];
+
+        loop {
+            let start = chars.location();
+            match self.next_token(&mut chars)? {
+                Some(Token::Whitespace) => {}
+                Some(token) => tokens.push(TokenWithSpan {
+                    token,
+                    span: Span {
+                        start,
+                        end: chars.location(),
+                    },
+                }),
+                None => break,
+            }
         }
 
-        Ok(tokens
-            .into_iter()
-            .filter(|t| match t {
-                Token::Whitespace => false,
-                _ => true,
-            })
-            .collect())
+        Ok(tokens)
     }
 
     /// Get the next token or return None
-    fn next_token(&self, chars: &mut Peekable<Chars>) -> Result<Option<Token>, TokenizerError> {
+    fn next_token(&self, chars: &mut CharStream) -> Result<Option<Token>, TokenizerError> {
         //println!("next_token: {:?}", chars.peek());
         match chars.peek() {
             Some(&ch) => match ch {
                 // whitespace
-                ' ' | '\t' | '\n' => {
+                ' ' | '\t' | '\r' | '\n' => {
                     chars.next(); // consume
                     Ok(Some(Token::Whitespace))
                 }
                 // identifier or keyword
                 'a'...'z' | 'A'...'Z' | '_' | '@' => {
+                    let location = chars.location();
                     let mut s = String::new();
                     while let Some(&ch) = chars.peek() {
                         match ch {
@@ -189,6 +349,16 @@ impl Tokenizer {
                         }
                     }
                     let upper_str = s.to_uppercase();
+                    if upper_str == "X" && chars.peek() == Some(&'\'') {
+                        let digits = self.quoted(chars, '\'')?;
+                        if digits.len() % 2 != 0 || !digits.chars().all(|c| c.is_digit(16)) {
+                            return Err(TokenizerError {
+                                message: format!("Invalid hex string X'{}'", digits),
+                                location,
+                            });
+                        }
+                        return Ok(Some(Token::HexString(digits)));
+                    }
                     if KEYWORDS.contains(upper_str.as_str()) {
                         Ok(Some(Token::Keyword(upper_str)))
                     } else {
@@ -196,25 +366,9 @@ impl Tokenizer {
                     }
                 }
                 // string
-                '\'' => {
-                    //TODO: handle escaped quotes in string
-                    //TODO: handle EOF before terminating quote
-                    let mut s = String::new();
-                    chars.next(); // consume
-                    while let Some(&ch) = chars.peek() {
-                        match ch {
-                            '\'' => {
-                                chars.next(); // consume
-                                break;
-                            }
-                            _ => {
-                                chars.next(); // consume
-                                s.push(ch);
-                            }
-                        }
-                    }
-                    Ok(Some(Token::String(s)))
-                }
+                '\'' => Ok(Some(Token::String(self.quoted(chars, '\'')?))),
+                // quoted identifier
+                '"' => Ok(Some(Token::QuotedIdentifier(self.quoted(chars, '"')?))),
                 // numbers
                 '0'...'9' => {
                     let mut s = String::new();
@@ -242,6 +396,14 @@ impl Tokenizer {
                     chars.next();
                     Ok(Some(Token::RParen))
                 }
+                '[' => {
+                    chars.next();
+                    Ok(Some(Token::LBracket))
+                }
+                ']' => {
+                    chars.next();
+                    Ok(Some(Token::RBracket))
+                }
                 // operators
                 '+' => {
                     chars.next();
@@ -249,15 +411,48 @@ impl Tokenizer {
                 }
                 '-' => {
                     chars.next();
-                    Ok(Some(Token::Minus))
+                    match chars.peek() {
+                        Some(&'-') => {
+                            // line comment
+                            while let Some(&ch) = chars.peek() {
+                                if ch == '\n' {
+                                    break;
+                                }
+                                chars.next();
+                            }
+                            Ok(Some(Token::Whitespace))
+                        }
+                        _ => Ok(Some(Token::Minus)),
+                    }
                 }
                 '*' => {
                     chars.next();
                     Ok(Some(Token::Mult))
                 }
                 '/' => {
+                    let location = chars.location();
                     chars.next();
-                    Ok(Some(Token::Div))
+                    match chars.peek() {
+                        Some(&'*') => {
+                            // block comment
+                            chars.next();
+                            let mut last = ' ';
+                            loop {
+                                match chars.next() {
+                                    Some('/') if last == '*' => break,
+                                    Some(ch) => last = ch,
+                                    None => {
+                                        return Err(TokenizerError {
+                                            message: format!("Unterminated block comment"),
+                                            location,
+                                        })
+                                    }
+                                }
+                            }
+                            Ok(Some(Token::Whitespace))
+                        }
+                        _ => Ok(Some(Token::Div)),
+                    }
                 }
                 '%' => {
                     chars.next();
@@ -271,17 +466,74 @@ impl Tokenizer {
                     chars.next();
                     Ok(Some(Token::Period))
                 }
+                ';' => {
+                    chars.next();
+                    Ok(Some(Token::SemiColon))
+                }
+                // parameter placeholders
+                '?' => {
+                    chars.next();
+                    Ok(Some(Token::Placeholder(String::from("?"))))
+                }
+                '$' | ':' => {
+                    let location = chars.location();
+                    chars.next(); // consume
+                    let mut s = ch.to_string();
+                    while let Some(&next) = chars.peek() {
+                        match next {
+                            '0'...'9' => {}
+                            'a'...'z' | 'A'...'Z' | '_' if ch == ':' => {}
+                            _ => break,
+                        }
+                        chars.next();
+                        s.push(next);
+                    }
+                    match s.chars().nth(1) {
+                        Some('0'...'9') if ch == ':' => Err(TokenizerError {
+                            message: format!("Parameter name must not start with a digit"),
+                            location,
+                        }),
+                        Some(_) => Ok(Some(Token::Placeholder(s))),
+                        None if ch == '$' => Err(TokenizerError {
+                            message: format!("Expected a parameter number after '$'"),
+                            location,
+                        }),
+                        None => Err(TokenizerError {
+                            message: format!("Expected a parameter name after ':'"),
+                            location,
+                        }),
+                    }
+                }
                 '!' => {
+                    let location = chars.location();
                     chars.next(); // consume
                     match chars.peek() {
-                        Some(&ch) => match ch {
-                            '=' => {
-                                chars.next();
-                                Ok(Some(Token::Neq))
-                            }
-                            _ => Err(TokenizerError(format!("TBD"))),
-                        },
-                        None => Err(TokenizerError(format!("TBD"))),
+                        Some(&'=') => {
+                            chars.next();
+                            Ok(Some(Token::Neq))
+                        }
+                        Some(&ch) => Err(TokenizerError {
+                            message: format!("Expected '=' after '!', found '{}'", ch),
+                            location,
+                        }),
+                        None => Err(TokenizerError {
+                            message: format!("Expected '=' after '!', found end of input"),
+                            location,
+                        }),
+                    }
+                }
+                '|' => {
+                    let location = chars.location();
+                    chars.next(); // consume
+                    match chars.peek() {
+                        Some(&'|') => {
+                            chars.next();
+                            Ok(Some(Token::StringConcat))
+                        }
+                        _ => Err(TokenizerError {
+                            message: format!("Expected '|' after '|'"),
+                            location,
+                        }),
                     }
                 }
                 '<' => {
@@ -314,14 +566,78 @@ impl Tokenizer {
                         None => Ok(Some(Token::Gt)),
                     }
                 }
-                _ => Err(TokenizerError(format!(
-                    "unhandled char '{}' in tokenizer",
-                    ch
-                ))),
+                _ => Err(TokenizerError {
+                    message: format!("Unexpected character '{}'", ch),
+                    location: chars.location(),
+                }),
             },
             None => Ok(None),
         }
     }
+
+    /// Read a string or identifier that is enclosed in `quote` characters, where two quote
+    /// characters in a row stand for one quote character in the value e.g. `'O''Brien'`
+    fn quoted(&self, chars: &mut CharStream, quote: char) -> Result<String, TokenizerError> {
+        let location = chars.location();
+        chars.next(); // consume the opening quote
+        let mut s = String::new();
+        loop {
+            match chars.next() {
+                Some(ch) if ch == quote => {
+                    if chars.peek() == Some(&quote) {
+                        chars.next();
+                        s.push(quote);
+                    } else {
+                        return Ok(s);
+                    }
+                }
+                Some(ch) => s.push(ch),
+                None => {
+                    return Err(TokenizerError {
+                        message: format!("Missing closing {} for quoted value", quote),
+                        location,
+                    })
+                }
+            }
+        }
+    }
+}
+
+/// Iterates over the characters of the SQL text, keeping track of the current location
+struct CharStream<'a> {
+    chars: Peekable<Chars<'a>>,
+    location: Location,
+}
+
+impl<'a> CharStream<'a> {
+    fn new(sql: &'a str) -> Self {
+        CharStream {
+            chars: sql.chars().peekable(),
+            location: Location { line: 1, column: 1 },
+        }
+    }
+
+    fn peek(&mut self) -> Option<&char> {
+        self.chars.peek()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let ch = self.chars.next();
+        match ch {
+            Some('\n') => {
+                self.location.line += 1;
+                self.location.column = 1;
+            }
+            Some(_) => self.location.column += 1,
+            None => {}
+        }
+        ch
+    }
+
+    /// The location of the next character
+    fn location(&self) -> Location {
+        self.location
+    }
 }
 
 #[cfg(test)]
@@ -359,6 +675,23 @@ mod tests {
         compare(expected, tokens);
     }
 
+    #[test]
+    fn tokenize_list_index() {
+        let sql = String::from("SELECT tags[1]");
+        let mut tokenizer = Tokenizer::new(&sql);
+        let tokens = tokenizer.tokenize().unwrap();
+
+        let expected = vec![
+            Token::Keyword(String::from("SELECT")),
+            Token::Identifier(String::from("tags")),
+            Token::LBracket,
+            Token::Number(String::from("1")),
+            Token::RBracket,
+        ];
+
+        compare(expected, tokens);
+    }
+
     #[test]
     fn tokenize_simple_select() {
         let sql = String::from("SELECT * FROM customer WHERE id = 1 LIMIT 5");
@@ -416,6 +749,171 @@ mod tests {
         compare(expected, tokens);
     }
 
+    #[test]
+    fn tokenize_string_concat() {
+        let sql = String::from("a || 'b'");
+        let mut tokenizer = Tokenizer::new(&sql);
+        let tokens = tokenizer.tokenize().unwrap();
+
+        let expected = vec![
+            Token::Identifier(String::from("a")),
+            Token::StringConcat,
+            Token::String(String::from("b")),
+        ];
+
+        compare(expected, tokens);
+
+        let err = Tokenizer::new("a | b").tokenize().unwrap_err();
+        assert_eq!("Expected '|' after '|' at line 1, column 3", err.to_string());
+    }
+
+    #[test]
+    fn tokenize_quoted() {
+        let sql = String::from(r#"SELECT "First Name", "a""b" FROM t WHERE c = 'O''Brien'"#);
+        let mut tokenizer = Tokenizer::new(&sql);
+        let tokens = tokenizer.tokenize().unwrap();
+
+        let expected = vec![
+            Token::Keyword(String::from("SELECT")),
+            Token::QuotedIdentifier(String::from("First Name")),
+            Token::Comma,
+            Token::QuotedIdentifier(String::from("a\"b")),
+            Token::Keyword(String::from("FROM")),
+            Token::Identifier(String::from("t")),
+            Token::Keyword(String::from("WHERE")),
+            Token::Identifier(String::from("c")),
+            Token::Eq,
+            Token::String(String::from("O'Brien")),
+        ];
+
+        compare(expected, tokens);
+    }
+
+    #[test]
+    fn tokenize_hex_string() {
+        let sql = String::from("SELECT X'DEADbeef', x'', X, x");
+        let mut tokenizer = Tokenizer::new(&sql);
+        let tokens = tokenizer.tokenize().unwrap();
+
+        let expected = vec![
+            Token::Keyword(String::from("SELECT")),
+            Token::HexString(String::from("DEADbeef")),
+            Token::Comma,
+            Token::HexString(String::from("")),
+            Token::Comma,
+            Token::Identifier(String::from("X")),
+            Token::Comma,
+            Token::Identifier(String::from("x")),
+        ];
+
+        compare(expected, tokens);
+
+        let err = Tokenizer::new("SELECT X'ABC'").tokenize().unwrap_err();
+        assert_eq!("Invalid hex string X'ABC' at line 1, column 8", err.to_string());
+        assert!(Tokenizer::new("SELECT X'GG'").tokenize().is_err());
+    }
+
+    #[test]
+    fn tokenize_unterminated_string() {
+        let sql = String::from("SELECT 'abc");
+        let mut tokenizer = Tokenizer::new(&sql);
+        let e = tokenizer.tokenize().unwrap_err();
+        assert_eq!(Location { line: 1, column: 8 }, e.location);
+    }
+
+    #[test]
+    fn tokenize_comments() {
+        let sql = String::from("SELECT a -- the a column\n, b /* the\n b column */ - 1 / 2");
+        let mut tokenizer = Tokenizer::new(&sql);
+        let tokens = tokenizer.tokenize().unwrap();
+
+        let expected = vec![
+            Token::Keyword(String::from("SELECT")),
+            Token::Identifier(String::from("a")),
+            Token::Comma,
+            Token::Identifier(String::from("b")),
+            Token::Minus,
+            Token::Number(String::from("1")),
+            Token::Div,
+            Token::Number(String::from("2")),
+        ];
+
+        compare(expected, tokens);
+    }
+
+    #[test]
+    fn tokenize_placeholders() {
+        let sql = String::from("SELECT a FROM t WHERE b = $12 AND c > ? AND d = :max_d");
+        let mut tokenizer = Tokenizer::new(&sql);
+        let tokens = tokenizer.tokenize().unwrap();
+
+        let placeholders: Vec<Token> = tokens
+            .into_iter()
+            .filter(|t| match t {
+                Token::Placeholder(_) => true,
+                _ => false,
+            })
+            .collect();
+        let expected = vec![
+            Token::Placeholder(String::from("$12")),
+            Token::Placeholder(String::from("?")),
+            Token::Placeholder(String::from(":max_d")),
+        ];
+
+        compare(expected, placeholders);
+    }
+
+    #[test]
+    fn tokenize_invalid_placeholders() {
+        let err = Tokenizer::new("SELECT $a").tokenize().unwrap_err();
+        assert_eq!("Expected a parameter number after '$' at line 1, column 8", err.to_string());
+
+        let err = Tokenizer::new("SELECT :1").tokenize().unwrap_err();
+        assert_eq!(
+            "Parameter name must not start with a digit at line 1, column 8",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn tokenize_with_spans() {
+        let sql = String::from("SELECT a,\n  bc FROM t");
+        let mut tokenizer = Tokenizer::new(&sql);
+        let tokens = tokenizer.tokenize_with_spans().unwrap();
+
+        let spans: Vec<(usize, usize, usize, usize)> = tokens
+            .iter()
+            .map(|t| (t.span.start.line, t.span.start.column, t.span.end.line, t.span.end.column))
+            .collect();
+        assert_eq!(
+            vec![
+                (1, 1, 1, 7),
+                (1, 8, 1, 9),
+                (1, 9, 1, 10),
+                (2, 3, 2, 5),
+                (2, 6, 2, 10),
+                (2, 11, 2, 12),
+            ],
+            spans
+        );
+        assert_eq!(
+            "2 | \t bc FROM t\n  | \t ^^",
+            tokens[3].span.snippet("SELECT a,\n\t bc FROM t")
+        );
+    }
+
+    #[test]
+    fn tokenize_error_location() {
+        let sql = String::from("SELECT a\nFROM t WHERE a ! 1");
+        let mut tokenizer = Tokenizer::new(&sql);
+        let e = tokenizer.tokenize().unwrap_err();
+        assert_eq!(Location { line: 2, column: 16 }, e.location);
+        assert_eq!(
+            "Expected '=' after '!', found ' ' at line 2, column 16",
+            e.to_string()
+        );
+    }
+
     fn compare(expected: Vec<Token>, actual: Vec<Token>) {
         //println!("------------------------------");
         //println!("tokens   = {:?}", actual);
@@ -0,0 +1,649 @@
+// Copyright 2018 Grove Enterprises LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parsing, formatting and arithmetic for dates, times, timestamps and intervals.
+//!
+//! Dates are stored as the number of days since 1970-01-01, times as the number of units since
+//! midnight and timestamps as the number of units since 1970-01-01 00:00:00.
+
+use std::fmt;
+use std::result;
+
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc};
+
+use super::datatypes::TimeUnit;
+
+pub const SECONDS_PER_DAY: i64 = 86_400;
+pub const NANOS_PER_SECOND: i64 = 1_000_000_000;
+pub const NANOS_PER_DAY: i64 = SECONDS_PER_DAY * NANOS_PER_SECOND;
+
+/// The number of days from 0001-01-01 to 1970-01-01
+const EPOCH_DAYS_FROM_CE: i32 = 719_163;
+
+/// A calendar interval. Months and days are kept separate from the time part because their
+/// length depends on the date they are added to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    pub months: i32,
+    pub days: i32,
+    pub nanos: i64,
+}
+
+impl Interval {
+    pub fn new(months: i32, days: i32, nanos: i64) -> Self {
+        Interval {
+            months,
+            days,
+            nanos,
+        }
+    }
+
+    /// An interval of `n` of a unit such as `day`, `hours` or `mon`
+    pub fn from_unit(n: i64, unit: &str) -> result::Result<Interval, String> {
+        let (months, days, nanos) = match unit.to_lowercase().trim_right_matches('s') {
+            "year" => (n.checked_mul(12), Some(0), Some(0)),
+            "month" | "mon" => (Some(n), Some(0), Some(0)),
+            "week" => (Some(0), n.checked_mul(7), Some(0)),
+            "day" => (Some(0), Some(n), Some(0)),
+            "hour" => (Some(0), Some(0), n.checked_mul(3600 * NANOS_PER_SECOND)),
+            "minute" | "min" => (Some(0), Some(0), n.checked_mul(60 * NANOS_PER_SECOND)),
+            "second" | "sec" => (Some(0), Some(0), n.checked_mul(NANOS_PER_SECOND)),
+            "millisecond" => (Some(0), Some(0), n.checked_mul(1_000_000)),
+            "microsecond" => (Some(0), Some(0), n.checked_mul(1_000)),
+            _ => return Err(format!("invalid interval unit '{}'", unit)),
+        };
+        let out_of_range = || format!("interval of {} {} is out of range", n, unit);
+        Ok(Interval::new(
+            to_i32(months).ok_or_else(out_of_range)?,
+            to_i32(days).ok_or_else(out_of_range)?,
+            nanos.ok_or_else(out_of_range)?,
+        ))
+    }
+
+    /// The interval with every part negated, which is used to subtract an interval
+    pub fn negate(&self) -> Interval {
+        Interval::new(-self.months, -self.days, -self.nanos)
+    }
+
+    /// Add two intervals part by part, returning `None` if any part overflows
+    pub fn checked_add(&self, other: &Interval) -> Option<Interval> {
+        Some(Interval::new(
+            self.months.checked_add(other.months)?,
+            self.days.checked_add(other.days)?,
+            self.nanos.checked_add(other.nanos)?,
+        ))
+    }
+}
+
+impl fmt::Display for Interval {
+    /// Format an interval like PostgreSQL does e.g. `1 year 2 mons 3 days 04:05:06.5`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts: Vec<String> = vec![];
+        let (years, months) = (self.months / 12, self.months % 12);
+        if years != 0 {
+            parts.push(plural(years as i64, "year", "years"));
+        }
+        if months != 0 {
+            parts.push(plural(months as i64, "mon", "mons"));
+        }
+        if self.days != 0 {
+            parts.push(plural(self.days as i64, "day", "days"));
+        }
+        if self.nanos != 0 || parts.is_empty() {
+            let sign = if self.nanos < 0 { "-" } else { "" };
+            let nanos = self.nanos.abs();
+            let seconds = nanos / NANOS_PER_SECOND;
+            let mut time = format!(
+                "{}{:02}:{:02}:{:02}",
+                sign,
+                seconds / 3600,
+                seconds / 60 % 60,
+                seconds % 60
+            );
+            let fraction = nanos % NANOS_PER_SECOND;
+            if fraction != 0 {
+                time.push_str(format!(".{:09}", fraction).trim_right_matches('0'));
+            }
+            parts.push(time);
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+fn plural(n: i64, singular: &str, plural: &str) -> String {
+    format!("{} {}", n, if n == 1 { singular } else { plural })
+}
+
+/// Integer division that rounds towards negative infinity, so that values before the epoch
+/// belong to the previous day or second
+fn div_floor(a: i64, b: i64) -> i64 {
+    let d = a / b;
+    if a % b != 0 && (a < 0) != (b < 0) {
+        d - 1
+    } else {
+        d
+    }
+}
+
+fn mod_floor(a: i64, b: i64) -> i64 {
+    a - div_floor(a, b) * b
+}
+
+fn date_to_naive(days: i32) -> Option<NaiveDate> {
+    days.checked_add(EPOCH_DAYS_FROM_CE)
+        .and_then(NaiveDate::from_num_days_from_ce_opt)
+}
+
+fn naive_to_date(date: &NaiveDate) -> i32 {
+    date.num_days_from_ce() - EPOCH_DAYS_FROM_CE
+}
+
+fn time_to_naive(value: i64, unit: TimeUnit) -> Option<NaiveTime> {
+    let per_second = unit.per_second();
+    let seconds = div_floor(value, per_second);
+    let nanos = mod_floor(value, per_second) * (NANOS_PER_SECOND / per_second);
+    if seconds < 0 || seconds >= SECONDS_PER_DAY {
+        return None;
+    }
+    NaiveTime::from_num_seconds_from_midnight_opt(seconds as u32, nanos as u32)
+}
+
+fn naive_to_time(time: &NaiveTime, unit: TimeUnit) -> i64 {
+    let per_second = unit.per_second();
+    time.num_seconds_from_midnight() as i64 * per_second
+        + time.nanosecond() as i64 / (NANOS_PER_SECOND / per_second)
+}
+
+fn timestamp_to_naive(value: i64, unit: TimeUnit) -> Option<NaiveDateTime> {
+    let per_day = SECONDS_PER_DAY * unit.per_second();
+    let days = div_floor(value, per_day);
+    if days < i32::min_value() as i64 || days > i32::max_value() as i64 {
+        return None;
+    }
+    let date = date_to_naive(days as i32)?;
+    let time = time_to_naive(mod_floor(value, per_day), unit)?;
+    Some(NaiveDateTime::new(date, time))
+}
+
+fn naive_to_timestamp(datetime: &NaiveDateTime, unit: TimeUnit) -> Option<i64> {
+    date_to_timestamp(naive_to_date(&datetime.date()), unit)?
+        .checked_add(naive_to_time(&datetime.time(), unit))
+}
+
+/// Parse a date in the format `YYYY-MM-DD`
+pub fn parse_date(s: &str) -> result::Result<i32, String> {
+    match NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d") {
+        Ok(date) => Ok(naive_to_date(&date)),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Parse a time in the format `HH:MM:SS[.fraction]`
+pub fn parse_time(s: &str, unit: TimeUnit) -> result::Result<i64, String> {
+    match NaiveTime::parse_from_str(s.trim(), "%H:%M:%S%.f") {
+        Ok(time) => Ok(naive_to_time(&time, unit)),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Parse an ISO-8601 timestamp such as `2018-01-01 12:30:00`, `2018-01-01T12:30:00.123` or
+/// `2018-01-01T12:30:00+02:00`. Timestamps with a time zone offset are converted to UTC and a
+/// date without a time is midnight at the start of that day.
+pub fn parse_timestamp(s: &str, unit: TimeUnit) -> result::Result<i64, String> {
+    let s = s.trim();
+    let datetime = match DateTime::parse_from_rfc3339(s) {
+        Ok(datetime) => datetime.naive_utc(),
+        Err(_) => match NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f")
+            .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f"))
+        {
+            Ok(datetime) => datetime,
+            Err(e) => match parse_date(s) {
+                Ok(days) => {
+                    return date_to_timestamp(days, unit)
+                        .ok_or_else(|| "timestamp out of range".to_string())
+                }
+                Err(_) => return Err(e.to_string()),
+            },
+        },
+    };
+    naive_to_timestamp(&datetime, unit).ok_or_else(|| "timestamp out of range".to_string())
+}
+
+/// Parse an interval such as `1 day`, `2 hours 30 minutes` or `1 year 6 months`
+pub fn parse_interval(s: &str) -> result::Result<Interval, String> {
+    let words: Vec<&str> = s.split_whitespace().collect();
+    if words.is_empty() || words.len() % 2 != 0 {
+        return Err(format!(
+            "expected a list of quantities and units but found '{}'",
+            s
+        ));
+    }
+    let mut interval = Interval::new(0, 0, 0);
+    for pair in words.chunks(2) {
+        let n = pair[0]
+            .parse::<i64>()
+            .map_err(|_| format!("invalid interval quantity '{}'", pair[0]))?;
+        interval = interval
+            .checked_add(&Interval::from_unit(n, pair[1])?)
+            .ok_or_else(|| format!("interval '{}' is out of range", s))?;
+    }
+    Ok(interval)
+}
+
+/// Narrow a sum of interval parts to the `i32` that months and days are stored in
+fn to_i32(n: Option<i64>) -> Option<i32> {
+    match n {
+        Some(n) if n >= i32::min_value() as i64 && n <= i32::max_value() as i64 => Some(n as i32),
+        _ => None,
+    }
+}
+
+/// Parse a timestamp with a `strftime` style format such as `%d/%m/%Y %H:%M`. A format without
+/// a time parses a date, which is midnight at the start of that day.
+pub fn parse_timestamp_with_format(
+    s: &str,
+    format: &str,
+    unit: TimeUnit,
+) -> result::Result<i64, String> {
+    match NaiveDateTime::parse_from_str(s, format) {
+        Ok(datetime) => {
+            naive_to_timestamp(&datetime, unit).ok_or_else(|| "timestamp out of range".to_string())
+        }
+        Err(e) => match NaiveDate::parse_from_str(s, format) {
+            Ok(date) => date_to_timestamp(naive_to_date(&date), unit)
+                .ok_or_else(|| "timestamp out of range".to_string()),
+            Err(_) => Err(e.to_string()),
+        },
+    }
+}
+
+/// Format a date as `YYYY-MM-DD`
+pub fn format_date(days: i32) -> String {
+    match date_to_naive(days) {
+        Some(date) => date.format("%Y-%m-%d").to_string(),
+        None => days.to_string(),
+    }
+}
+
+/// Format a time as `HH:MM:SS`, with as many fractional digits as needed
+pub fn format_time(value: i64, unit: TimeUnit) -> String {
+    match time_to_naive(value, unit) {
+        Some(time) => time.format("%H:%M:%S%.f").to_string(),
+        None => value.to_string(),
+    }
+}
+
+/// Format a timestamp as `YYYY-MM-DD HH:MM:SS`, with as many fractional digits as needed
+pub fn format_timestamp(value: i64, unit: TimeUnit) -> String {
+    match timestamp_to_naive(value, unit) {
+        Some(datetime) => datetime.format("%Y-%m-%d %H:%M:%S%.f").to_string(),
+        None => value.to_string(),
+    }
+}
+
+/// Convert a time or timestamp to a different unit, truncating when the new unit is coarser.
+/// Returns `None` if the value does not fit in the finer unit.
+pub fn convert_time_unit(value: i64, from: TimeUnit, to: TimeUnit) -> Option<i64> {
+    if from < to {
+        value.checked_mul(to.per_second() / from.per_second())
+    } else {
+        Some(div_floor(value, from.per_second() / to.per_second()))
+    }
+}
+
+/// Convert a date to a timestamp at midnight at the start of that day. Returns `None` if the
+/// timestamp does not fit in the unit.
+pub fn date_to_timestamp(days: i32, unit: TimeUnit) -> Option<i64> {
+    (days as i64).checked_mul(SECONDS_PER_DAY * unit.per_second())
+}
+
+/// Get the date that a timestamp falls on. Returns `None` if the date is out of range.
+pub fn timestamp_to_date(value: i64, unit: TimeUnit) -> Option<i32> {
+    to_i32(Some(div_floor(value, SECONDS_PER_DAY * unit.per_second())))
+}
+
+/// Add a number of months to a date. When the day does not exist in the resulting month the
+/// date is clamped to the last day of the month, so 2018-01-31 plus one month is 2018-02-28.
+fn add_months(date: &NaiveDate, months: i32) -> Option<NaiveDate> {
+    let total = date.year() as i64 * 12 + date.month0() as i64 + months as i64;
+    let year = div_floor(total, 12) as i32;
+    let month = mod_floor(total, 12) as u32 + 1;
+    let mut day = date.day();
+    loop {
+        match NaiveDate::from_ymd_opt(year, month, day) {
+            Some(date) => return Some(date),
+            None if day > 28 => day -= 1,
+            None => return None,
+        }
+    }
+}
+
+/// Add an interval to a date. Only whole days of the time part of the interval are added.
+pub fn add_interval_to_date(days: i32, interval: &Interval) -> Option<i32> {
+    let date = add_months(&date_to_naive(days)?, interval.months)?;
+    naive_to_date(&date)
+        .checked_add(interval.days)?
+        .checked_add(div_floor(interval.nanos, NANOS_PER_DAY) as i32)
+}
+
+/// Add an interval to a timestamp. The time part of the interval is truncated to the unit of
+/// the timestamp.
+pub fn add_interval_to_timestamp(value: i64, unit: TimeUnit, interval: &Interval) -> Option<i64> {
+    let datetime = timestamp_to_naive(value, unit)?;
+    let date = add_months(&datetime.date(), interval.months)?;
+    let per_second = unit.per_second();
+    naive_to_timestamp(&NaiveDateTime::new(date, datetime.time()), unit)?
+        .checked_add((interval.days as i64).checked_mul(SECONDS_PER_DAY * per_second)?)?
+        .checked_add(interval.nanos / (NANOS_PER_SECOND / per_second))
+}
+
+/// The current date in UTC
+pub fn current_date() -> i32 {
+    naive_to_date(&Utc::now().naive_utc().date())
+}
+
+/// The current time in UTC as a timestamp
+pub fn current_timestamp(unit: TimeUnit) -> i64 {
+    naive_to_timestamp(&Utc::now().naive_utc(), unit)
+        .expect("the current time is within the range of every timestamp unit")
+}
+
+/// How a date/time field such as `month` or `hour` divides the time line
+enum FieldWidth {
+    /// A number of calendar months
+    Months(i32),
+    /// A fixed number of units, and the offset of the first boundary from the epoch
+    Fixed(i64, i64),
+}
+
+fn field_width(field: &str, unit: TimeUnit) -> result::Result<FieldWidth, String> {
+    let per_second = unit.per_second();
+    let per_day = SECONDS_PER_DAY * per_second;
+    Ok(match field.to_lowercase().as_ref() {
+        "year" => FieldWidth::Months(12),
+        "quarter" => FieldWidth::Months(3),
+        "month" => FieldWidth::Months(1),
+        // weeks start on Monday, and 1970-01-01 was a Thursday
+        "week" => FieldWidth::Fixed(7 * per_day, 3 * per_day),
+        "day" => FieldWidth::Fixed(per_day, 0),
+        "hour" => FieldWidth::Fixed(3600 * per_second, 0),
+        "minute" => FieldWidth::Fixed(60 * per_second, 0),
+        "second" => FieldWidth::Fixed(per_second, 0),
+        "millisecond" => FieldWidth::Fixed((per_second / 1_000).max(1), 0),
+        "microsecond" => FieldWidth::Fixed((per_second / 1_000_000).max(1), 0),
+        _ => return Err(format!("unsupported date/time field '{}'", field)),
+    })
+}
+
+/// The number of months from 0000-01 to the month that a timestamp falls in
+fn month_index(value: i64, unit: TimeUnit) -> result::Result<i64, String> {
+    let date = timestamp_to_naive(value, unit)
+        .ok_or_else(|| "timestamp out of range".to_string())?
+        .date();
+    Ok(date.year() as i64 * 12 + date.month0() as i64)
+}
+
+/// Truncate a timestamp to the start of the year, quarter, month, week, day, hour, minute,
+/// second, millisecond or microsecond that it falls in
+pub fn truncate_timestamp(value: i64, unit: TimeUnit, field: &str) -> result::Result<i64, String> {
+    match field_width(field, unit)? {
+        FieldWidth::Months(months) => {
+            let index = month_index(value, unit)?;
+            let start = index - mod_floor(index, months as i64);
+            NaiveDate::from_ymd_opt(div_floor(start, 12) as i32, mod_floor(start, 12) as u32 + 1, 1)
+                .and_then(|date| date_to_timestamp(naive_to_date(&date), unit))
+                .ok_or_else(|| "timestamp out of range".to_string())
+        }
+        FieldWidth::Fixed(width, offset) => Ok(value - mod_floor(value + offset, width)),
+    }
+}
+
+/// The number of field boundaries between two timestamps, so the number of days between
+/// 2018-01-01 23:00 and 2018-01-02 01:00 is one
+pub fn diff_timestamps(
+    start: i64,
+    end: i64,
+    unit: TimeUnit,
+    field: &str,
+) -> result::Result<i64, String> {
+    match field_width(field, unit)? {
+        FieldWidth::Months(months) => Ok(div_floor(month_index(end, unit)?, months as i64)
+            - div_floor(month_index(start, unit)?, months as i64)),
+        FieldWidth::Fixed(width, offset) => {
+            Ok(div_floor(end + offset, width) - div_floor(start + offset, width))
+        }
+    }
+}
+
+/// Get a field of a timestamp. Fields are numbered like PostgreSQL does, so `dow` is 0 for
+/// Sunday, `week` is the ISO week and `second` includes fractional seconds.
+pub fn timestamp_part(value: i64, unit: TimeUnit, field: &str) -> result::Result<f64, String> {
+    let datetime =
+        timestamp_to_naive(value, unit).ok_or_else(|| "timestamp out of range".to_string())?;
+    Ok(match field.to_lowercase().as_ref() {
+        "year" => datetime.year() as f64,
+        "quarter" => (datetime.month0() / 3 + 1) as f64,
+        "month" => datetime.month() as f64,
+        "week" => datetime.iso_week().week() as f64,
+        "day" => datetime.day() as f64,
+        "dow" => datetime.weekday().num_days_from_sunday() as f64,
+        "doy" => datetime.ordinal() as f64,
+        "hour" => datetime.hour() as f64,
+        "minute" => datetime.minute() as f64,
+        "second" => datetime.second() as f64 + datetime.nanosecond() as f64 / 1e9,
+        "epoch" => value as f64 / unit.per_second() as f64,
+        _ => return Err(format!("unsupported date/time field '{}'", field)),
+    })
+}
+
+/// Put a timestamp in a bin of a fixed width, returning the start of the bin. Bins are aligned
+/// to `origin`, and the width cannot contain months because their length varies.
+pub fn bin_timestamp(
+    stride: &Interval,
+    value: i64,
+    origin: i64,
+    unit: TimeUnit,
+) -> result::Result<i64, String> {
+    if stride.months != 0 {
+        return Err("the stride cannot contain months or years".to_string());
+    }
+    let per_second = unit.per_second();
+    let width = (stride.days as i64)
+        .checked_mul(SECONDS_PER_DAY * per_second)
+        .and_then(|days| days.checked_add(stride.nanos / (NANOS_PER_SECOND / per_second)))
+        .ok_or_else(|| "the stride is out of range".to_string())?;
+    if width <= 0 {
+        return Err("the stride must be positive".to_string());
+    }
+    let offset = value
+        .checked_sub(origin)
+        .ok_or_else(|| "timestamp out of range".to_string())?;
+    Ok(value - mod_floor(offset, width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_format_dates() {
+        assert_eq!(Ok(0), parse_date("1970-01-01"));
+        assert_eq!(Ok(17532), parse_date("2018-01-01"));
+        assert_eq!(Ok(-1), parse_date("1969-12-31"));
+        assert!(parse_date("2018-02-30").is_err());
+        assert_eq!("2018-01-01", format_date(17532));
+        assert_eq!("1969-12-31", format_date(-1));
+    }
+
+    #[test]
+    fn parse_and_format_timestamps() {
+        let ts = parse_timestamp("2018-01-01 12:30:45", TimeUnit::Second).unwrap();
+        assert_eq!(1514809845, ts);
+        assert_eq!(
+            Ok(ts * 1000 + 250),
+            parse_timestamp("2018-01-01T12:30:45.25", TimeUnit::Millisecond)
+        );
+        assert_eq!(
+            Ok(ts),
+            parse_timestamp("2018-01-01T14:30:45+02:00", TimeUnit::Second)
+        );
+        assert_eq!(
+            Ok(1514764800000000),
+            parse_timestamp("2018-01-01", TimeUnit::Microsecond)
+        );
+        assert!(parse_timestamp("2018-01-01 25:00:00", TimeUnit::Second).is_err());
+
+        assert_eq!("2018-01-01 12:30:45", format_timestamp(ts, TimeUnit::Second));
+        assert_eq!(
+            "2018-01-01 12:30:45.250",
+            format_timestamp(ts * 1000 + 250, TimeUnit::Millisecond)
+        );
+        assert_eq!("1969-12-31 23:59:59", format_timestamp(-1, TimeUnit::Second));
+        assert_eq!(
+            "12:30:00.500",
+            format_time(45_000_500, TimeUnit::Millisecond)
+        );
+    }
+
+    #[test]
+    fn convert_units() {
+        assert_eq!(Some(1_000), convert_time_unit(1, TimeUnit::Second, TimeUnit::Millisecond));
+        assert_eq!(Some(-1), convert_time_unit(-1, TimeUnit::Millisecond, TimeUnit::Second));
+        assert_eq!(Some(-1), timestamp_to_date(-1, TimeUnit::Nanosecond));
+        assert_eq!(None, timestamp_to_date(i64::max_value(), TimeUnit::Second));
+        assert_eq!(Some(86_400_000), date_to_timestamp(1, TimeUnit::Millisecond));
+
+        let max = i64::max_value();
+        assert_eq!(None, convert_time_unit(max, TimeUnit::Second, TimeUnit::Nanosecond));
+        assert_eq!(None, date_to_timestamp(i32::max_value(), TimeUnit::Nanosecond));
+        assert!(parse_timestamp("2300-01-01", TimeUnit::Nanosecond).is_err());
+    }
+
+    #[test]
+    fn parse_and_format_intervals() {
+        assert_eq!(Ok(Interval::new(0, 1, 0)), parse_interval("1 day"));
+        assert_eq!(
+            Ok(Interval::new(14, 3, 7_200_000_000_000)),
+            parse_interval("1 year 2 MONTHS 3 days 2 hours")
+        );
+        assert!(parse_interval("1 fortnight").is_err());
+        assert!(parse_interval("day").is_err());
+        assert_eq!(
+            "1 year 2 mons 3 days 02:00:00",
+            format!("{}", Interval::new(14, 3, 7_200_000_000_000))
+        );
+        assert_eq!("-00:00:01.5", format!("{}", Interval::new(0, 0, -1_500_000_000)));
+        assert_eq!("00:00:00", format!("{}", Interval::new(0, 0, 0)));
+    }
+
+    #[test]
+    fn add_intervals() {
+        let jan31 = parse_date("2018-01-31").unwrap();
+        assert_eq!(
+            "2018-02-28",
+            format_date(add_interval_to_date(jan31, &Interval::new(1, 0, 0)).unwrap())
+        );
+        assert_eq!(
+            "2018-01-30",
+            format_date(add_interval_to_date(jan31, &Interval::new(0, -1, 0)).unwrap())
+        );
+        let ts = parse_timestamp("2018-12-31 23:00:00", TimeUnit::Microsecond).unwrap();
+        let later = Interval::new(2, 1, 3_600_000_000_000);
+        assert_eq!(
+            "2019-03-02 00:00:00",
+            format_timestamp(
+                add_interval_to_timestamp(ts, TimeUnit::Microsecond, &later).unwrap(),
+                TimeUnit::Microsecond
+            )
+        );
+    }
+
+    #[test]
+    fn truncate_and_diff_timestamps() {
+        let ts = parse_timestamp("2018-05-17 13:45:30.25", TimeUnit::Microsecond).unwrap();
+        let truncate = |field: &str| {
+            format_timestamp(
+                truncate_timestamp(ts, TimeUnit::Microsecond, field).unwrap(),
+                TimeUnit::Microsecond,
+            )
+        };
+        assert_eq!("2018-01-01 00:00:00", truncate("year"));
+        assert_eq!("2018-04-01 00:00:00", truncate("QUARTER"));
+        assert_eq!("2018-05-14 00:00:00", truncate("week"));
+        assert_eq!("2018-05-17 13:00:00", truncate("hour"));
+        assert_eq!("2018-05-17 13:45:30", truncate("second"));
+        assert!(truncate_timestamp(ts, TimeUnit::Microsecond, "fortnight").is_err());
+
+        let before_epoch = parse_timestamp("1969-12-31 23:30:00", TimeUnit::Second).unwrap();
+        assert_eq!(
+            "1969-12-31 00:00:00",
+            format_timestamp(
+                truncate_timestamp(before_epoch, TimeUnit::Second, "day").unwrap(),
+                TimeUnit::Second
+            )
+        );
+
+        let start = parse_timestamp("2018-01-01 23:00:00", TimeUnit::Second).unwrap();
+        let end = parse_timestamp("2018-03-02 01:00:00", TimeUnit::Second).unwrap();
+        assert_eq!(Ok(2), diff_timestamps(start, end, TimeUnit::Second, "month"));
+        assert_eq!(Ok(60), diff_timestamps(start, end, TimeUnit::Second, "day"));
+        assert_eq!(Ok(-60), diff_timestamps(end, start, TimeUnit::Second, "day"));
+        assert_eq!(Ok(0), diff_timestamps(start, end, TimeUnit::Second, "year"));
+    }
+
+    #[test]
+    fn timestamp_parts() {
+        let ts = parse_timestamp("2018-05-17 13:45:30.25", TimeUnit::Millisecond).unwrap();
+        let part = |field: &str| timestamp_part(ts, TimeUnit::Millisecond, field).unwrap();
+        assert_eq!(2018.0, part("year"));
+        assert_eq!(2.0, part("quarter"));
+        assert_eq!(20.0, part("week"));
+        assert_eq!(4.0, part("dow"));
+        assert_eq!(137.0, part("doy"));
+        assert_eq!(30.25, part("second"));
+        assert_eq!(1526564730.25, part("epoch"));
+    }
+
+    #[test]
+    fn bin_timestamps() {
+        let unit = TimeUnit::Second;
+        let origin = parse_timestamp("2018-01-01 00:00:00", unit).unwrap();
+        let ts = parse_timestamp("2018-01-01 00:22:10", unit).unwrap();
+        let stride = parse_interval("15 minutes").unwrap();
+        assert_eq!(
+            "2018-01-01 00:15:00",
+            format_timestamp(bin_timestamp(&stride, ts, origin, unit).unwrap(), unit)
+        );
+        let earlier = parse_timestamp("2017-12-31 23:50:00", unit).unwrap();
+        assert_eq!(
+            "2017-12-31 23:45:00",
+            format_timestamp(bin_timestamp(&stride, earlier, origin, unit).unwrap(), unit)
+        );
+        assert!(bin_timestamp(&Interval::new(1, 0, 0), ts, origin, unit).is_err());
+        assert!(bin_timestamp(&Interval::new(0, 0, 0), ts, origin, unit).is_err());
+    }
+
+    #[test]
+    fn parse_with_format() {
+        assert_eq!(
+            parse_timestamp("2018-05-17 13:45:00", TimeUnit::Second),
+            parse_timestamp_with_format("17/05/2018 13:45", "%d/%m/%Y %H:%M", TimeUnit::Second)
+        );
+        assert_eq!(
+            Ok(date_to_timestamp(17532, TimeUnit::Second).unwrap()),
+            parse_timestamp_with_format("01.01.2018", "%d.%m.%Y", TimeUnit::Second)
+        );
+        assert!(parse_timestamp_with_format("2018", "%d.%m.%Y", TimeUnit::Second).is_err());
+    }
+}
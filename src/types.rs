@@ -14,19 +14,25 @@
 
 //! Datatype definitions
 
+use std::cmp::Ordering;
 use std::fmt;
 use std::fmt::Formatter;
-use std::ops::Add;
 use std::rc::Rc;
 use std::result;
 
 use arrow::array::{ListArray, PrimitiveArray};
 use arrow::bitmap::*;
 use arrow::buffer::*;
-use arrow::datatypes::{DataType, Field};
+use arrow::builder::Builder;
 use arrow::list::*;
+use arrow::list_builder::ListBuilder;
 
+use super::arithmetic::{Arithmetic, ArithmeticOperator, OverflowMode};
+use super::datasources::common::get_value;
+use super::datatypes::{DataType, Field, TimeUnit};
+use super::decimal::{self, DecimalOperator};
 use super::errors::*;
+use super::temporal::{self, Interval};
 
 pub struct Array {
     /// number of elements in the array
@@ -50,7 +56,7 @@ impl Array {
         }
     }
 
-    /// Create a new array where there are no null values
+    /// Create a new array with null values where the validity bitmap is not set
     pub fn with_nulls(len: usize, data: ArrayData, null_count: usize, bitmap: Bitmap) -> Self {
         Array {
             len: len as i32,
@@ -60,11 +66,31 @@ impl Array {
         }
     }
 
+    /// Create a new array with null values wherever `validity` is false
+    pub fn with_validity(data: ArrayData, validity: &[bool]) -> Self {
+        let null_count = validity.iter().filter(|valid| !**valid).count();
+        if null_count == 0 {
+            return Array::new(validity.len(), data);
+        }
+        let mut bitmap = Bitmap::new(validity.len());
+        for (i, valid) in validity.iter().enumerate() {
+            if !valid {
+                bitmap.clear(i);
+            }
+        }
+        Array::with_nulls(validity.len(), data, null_count, bitmap)
+    }
+
     /// Get a reference to the array data
     pub fn data(&self) -> &ArrayData {
         &self.data
     }
 
+    /// Take the array data, discarding the validity bitmap
+    pub fn into_data(self) -> ArrayData {
+        self.data
+    }
+
     /// number of elements in the array
     pub fn len(&self) -> usize {
         self.len as usize
@@ -79,6 +105,23 @@ impl Array {
     pub fn validity_bitmap(&self) -> &Option<Bitmap> {
         &self.validity_bitmap
     }
+
+    /// Whether the element at the given index is not null
+    pub fn is_valid(&self, i: usize) -> bool {
+        match self.validity_bitmap {
+            Some(ref bitmap) => bitmap.is_set(i),
+            None => true,
+        }
+    }
+
+    /// Get the validity of every element, or `None` if there are no null values
+    pub fn validity(&self) -> Option<Vec<bool>> {
+        if self.null_count == 0 {
+            None
+        } else {
+            Some((0..self.len()).map(|i| self.is_valid(i)).collect())
+        }
+    }
 }
 
 macro_rules! arraydata_from_primitive {
@@ -121,7 +164,47 @@ pub enum ArrayData {
     UInt32(PrimitiveArray<u32>),
     UInt64(PrimitiveArray<u64>),
     Utf8(ListArray<u8>),
+    /// Variable-length byte strings
+    Binary(ListArray<u8>),
+    /// Days since 1970-01-01
+    Date32(PrimitiveArray<i32>),
+    /// Time since midnight in the given unit
+    Time64(PrimitiveArray<i64>, TimeUnit),
+    /// Time since 1970-01-01 00:00:00 in the given unit
+    Timestamp(PrimitiveArray<i64>, TimeUnit),
+    /// Decimal values multiplied by 10^scale, with the precision and scale
+    Decimal(Vec<i128>, usize, usize),
     Struct(Vec<Rc<Array>>),
+    /// Lists of values, where list `i` holds the elements from `offsets[i]` up to
+    /// `offsets[i + 1]` of the element array. There is one more offset than there are lists.
+    List(Vec<usize>, Rc<Array>),
+}
+
+impl ArrayData {
+    /// Get the type of the values. The fields of structs are not known.
+    pub fn data_type(&self) -> DataType {
+        match self {
+            ArrayData::Boolean(_) => DataType::Boolean,
+            ArrayData::Float32(_) => DataType::Float32,
+            ArrayData::Float64(_) => DataType::Float64,
+            ArrayData::Int8(_) => DataType::Int8,
+            ArrayData::Int16(_) => DataType::Int16,
+            ArrayData::Int32(_) => DataType::Int32,
+            ArrayData::Int64(_) => DataType::Int64,
+            ArrayData::UInt8(_) => DataType::UInt8,
+            ArrayData::UInt16(_) => DataType::UInt16,
+            ArrayData::UInt32(_) => DataType::UInt32,
+            ArrayData::UInt64(_) => DataType::UInt64,
+            ArrayData::Utf8(_) => DataType::Utf8,
+            ArrayData::Binary(_) => DataType::Binary,
+            ArrayData::Date32(_) => DataType::Date32,
+            ArrayData::Time64(_, unit) => DataType::Time64(*unit),
+            ArrayData::Timestamp(_, unit) => DataType::Timestamp(*unit),
+            ArrayData::Decimal(_, p, s) => DataType::Decimal(*p, *s),
+            ArrayData::Struct(_) => DataType::Struct(vec![]),
+            ArrayData::List(_, ref values) => DataType::List(Box::new(values.data().data_type())),
+        }
+    }
 }
 
 impl fmt::Display for ArrayData {
@@ -139,7 +222,13 @@ impl fmt::Display for ArrayData {
             ArrayData::UInt32(_) => "u32",
             ArrayData::UInt64(_) => "u64",
             ArrayData::Utf8(_) => "String",
+            ArrayData::Binary(_) => "binary",
+            ArrayData::Date32(_) => "date",
+            ArrayData::Time64(_, _) => "time",
+            ArrayData::Timestamp(_, _) => "timestamp",
+            ArrayData::Decimal(_, _, _) => "decimal",
             ArrayData::Struct(_) => "Struct",
+            ArrayData::List(_, _) => "List",
         };
         write!(f, "{}", printable)
     }
@@ -200,6 +289,21 @@ impl From<Vec<String>> for Array {
     }
 }
 
+impl From<Vec<Vec<u8>>> for Array {
+    fn from(v: Vec<Vec<u8>>) -> Self {
+        let mut b: ListBuilder<u8> = ListBuilder::with_capacity(v.len());
+        for bytes in &v {
+            b.push(bytes);
+        }
+        Array {
+            len: v.len() as i32,
+            null_count: 0,
+            validity_bitmap: None,
+            data: ArrayData::Binary(ListArray::from(b.finish())),
+        }
+    }
+}
+
 impl From<Vec<Rc<Array>>> for Array {
     fn from(v: Vec<Rc<Array>>) -> Self {
         Array {
@@ -227,12 +331,26 @@ pub enum ScalarValue {
     UInt32(u32),
     UInt64(u64),
     Utf8(Rc<String>),
+    Binary(Rc<Vec<u8>>),
+    /// Days since 1970-01-01
+    Date32(i32),
+    /// Time since midnight in the given unit
+    Time64(i64, TimeUnit),
+    /// Time since 1970-01-01 00:00:00 in the given unit
+    Timestamp(i64, TimeUnit),
+    Interval(Interval),
+    /// Decimal value multiplied by 10^scale, with the precision and scale
+    Decimal(i128, usize, usize),
     Struct(Vec<ScalarValue>),
+    /// A list of values of the given element type, which may include nulls
+    List(Vec<ScalarValue>, DataType),
 }
 
 impl ScalarValue {
-    pub fn get_datatype(&self) -> DataType {
-        match *self {
+    /// Get the type of the value. A null has no type of its own, so it is an error to ask for
+    /// one; a null that needs a type is cast to it.
+    pub fn get_datatype(&self) -> Result<DataType> {
+        Ok(match *self {
             ScalarValue::Boolean(_) => DataType::Boolean,
             ScalarValue::UInt8(_) => DataType::UInt8,
             ScalarValue::UInt16(_) => DataType::UInt16,
@@ -245,8 +363,59 @@ impl ScalarValue {
             ScalarValue::Float32(_) => DataType::Float32,
             ScalarValue::Float64(_) => DataType::Float64,
             ScalarValue::Utf8(_) => DataType::Utf8,
-            ScalarValue::Struct(_) => unimplemented!(),
-            ScalarValue::Null => unimplemented!(),
+            ScalarValue::Binary(_) => DataType::Binary,
+            ScalarValue::Date32(_) => DataType::Date32,
+            ScalarValue::Time64(_, unit) => DataType::Time64(unit),
+            ScalarValue::Timestamp(_, unit) => DataType::Timestamp(unit),
+            ScalarValue::Interval(_) => DataType::Interval,
+            ScalarValue::Decimal(_, p, s) => DataType::Decimal(p, s),
+            ScalarValue::List(_, ref t) => DataType::List(Box::new(t.clone())),
+            ScalarValue::Struct(ref values) => DataType::Struct(
+                values
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| Ok(Field::new(&format!("f{}", i + 1), v.get_datatype()?, true)))
+                    .collect::<Result<Vec<Field>>>()?,
+            ),
+            ScalarValue::Null => {
+                return Err(ExecutionError::type_mismatch(
+                    "Cannot determine the type of NULL; use CAST(NULL AS <type>)".to_string(),
+                ))
+            }
+        })
+    }
+}
+
+impl PartialOrd for ScalarValue {
+    /// Values of the same type are ordered by value. Values of different types, nulls,
+    /// intervals and structs are not comparable.
+    fn partial_cmp(&self, other: &ScalarValue) -> Option<Ordering> {
+        use self::ScalarValue::*;
+        match (self, other) {
+            (Boolean(l), Boolean(r)) => l.partial_cmp(r),
+            (Float32(l), Float32(r)) => l.partial_cmp(r),
+            (Float64(l), Float64(r)) => l.partial_cmp(r),
+            (Int8(l), Int8(r)) => l.partial_cmp(r),
+            (Int16(l), Int16(r)) => l.partial_cmp(r),
+            (Int32(l), Int32(r)) => l.partial_cmp(r),
+            (Int64(l), Int64(r)) => l.partial_cmp(r),
+            (UInt8(l), UInt8(r)) => l.partial_cmp(r),
+            (UInt16(l), UInt16(r)) => l.partial_cmp(r),
+            (UInt32(l), UInt32(r)) => l.partial_cmp(r),
+            (UInt64(l), UInt64(r)) => l.partial_cmp(r),
+            (Utf8(l), Utf8(r)) => l.partial_cmp(r),
+            (Binary(l), Binary(r)) => l.partial_cmp(r),
+            (Date32(l), Date32(r)) => l.partial_cmp(r),
+            (Time64(l, lu), Time64(r, ru)) if lu == ru => l.partial_cmp(r),
+            (Timestamp(l, lu), Timestamp(r, ru)) if lu == ru => l.partial_cmp(r),
+            (Decimal(l, _, ls), Decimal(r, _, rs)) => {
+                let scale = *ls.max(rs);
+                match (decimal::rescale(*l, *ls, scale), decimal::rescale(*r, *rs, scale)) {
+                    (Some(l), Some(r)) => l.partial_cmp(&r),
+                    _ => None,
+                }
+            }
+            _ => None,
         }
     }
 }
@@ -295,9 +464,32 @@ pub fn can_coerce_from(left: &DataType, other: &DataType) -> bool {
         Float64 => match other {
             Int8 | Int16 | Int32 | Int64 => true,
             UInt8 | UInt16 | UInt32 | UInt64 => true,
-            Float32 | Float64 => true,
+            Float32 | Float64 | Decimal(_, _) => true,
             _ => false,
         },
+        Date32 => match other {
+            Date32 | Utf8 => true,
+            _ => false,
+        },
+        Time64(unit) => match other {
+            Time64(other_unit) => other_unit <= unit,
+            Utf8 => true,
+            _ => false,
+        },
+        Timestamp(unit) => match other {
+            Date32 | Utf8 => true,
+            Timestamp(other_unit) => other_unit <= unit,
+            _ => false,
+        },
+        Decimal(precision, scale) => match other {
+            Utf8 => true,
+            _ => match get_decimal_operand_type(other) {
+                Some(Decimal(other_precision, other_scale)) => {
+                    other_scale <= *scale && other_precision - other_scale <= precision - scale
+                }
+                _ => false,
+            },
+        },
         _ => false,
     }
 }
@@ -307,7 +499,10 @@ macro_rules! primitive_accessor {
         pub fn $NAME(&self) -> Result<$TY> {
             match self {
                 ScalarValue::$VARIANT(v) => Ok(*v),
-                other => Err(ExecutionError::General(format!("Cannot access scalar value {:?} as {}", other, stringify!($VARIANT))))
+                other => Err(ExecutionError::type_error(
+                    format!("Cannot access scalar value as {}", stringify!($VARIANT)),
+                    other,
+                )),
             }
         }
     }
@@ -326,6 +521,17 @@ impl ScalarValue {
     primitive_accessor!(get_f32, Float32, f32);
     primitive_accessor!(get_f64, Float64, f64);
 
+    /// Get the value, precision and scale of a decimal
+    pub fn get_decimal(&self) -> Result<(i128, usize, usize)> {
+        match *self {
+            ScalarValue::Decimal(v, p, s) => Ok((v, p, s)),
+            ref other => Err(ExecutionError::type_error(
+                "Cannot access scalar value as Decimal".to_string(),
+                other,
+            )),
+        }
+    }
+
     pub fn get_string(&self) -> Result<&String> {
         match *self {
             ScalarValue::Utf8(ref v) => Ok(v),
@@ -333,31 +539,92 @@ impl ScalarValue {
         }
     }
 
+    /// Get the bytes of a binary value
+    pub fn get_binary(&self) -> Result<&Vec<u8>> {
+        match *self {
+            ScalarValue::Binary(ref v) => Ok(v),
+            ref other => Err(ExecutionError::type_error(
+                "Cannot access scalar value as Binary".to_string(),
+                other,
+            )),
+        }
+    }
+
     pub fn get_struct(&self) -> Result<&Vec<ScalarValue>> {
         match *self {
             ScalarValue::Struct(ref v) => Ok(v),
             _ => Err(df_error!("TBD")),
         }
     }
+
+    /// Get the elements of a list
+    pub fn get_list(&self) -> Result<&Vec<ScalarValue>> {
+        match *self {
+            ScalarValue::List(ref v, _) => Ok(v),
+            ref other => Err(ExecutionError::type_error(
+                "Cannot access scalar value as List".to_string(),
+                other,
+            )),
+        }
+    }
 }
 
-impl Add for ScalarValue {
-    type Output = ScalarValue;
+/// Add two numbers of the same type, where the result is never null
+fn add_scalars<T, F>(a: T, b: T, mode: OverflowMode, to_scalar: F) -> Result<ScalarValue>
+where
+    T: Arithmetic,
+    F: Fn(T) -> ScalarValue,
+{
+    match T::apply(ArithmeticOperator::Add, a, b, mode) {
+        Ok(v) => Ok(v.map_or(ScalarValue::Null, to_scalar)),
+        Err(message) => Err(ExecutionError::DataError {
+            message,
+            file: None,
+            row: None,
+            column: None,
+        }),
+    }
+}
 
-    fn add(self, rhs: ScalarValue) -> ScalarValue {
-        assert_eq!(self.get_datatype(), rhs.get_datatype());
-        match self {
-            ScalarValue::UInt8(x) => ScalarValue::UInt8(x + rhs.get_u8().unwrap()),
-            ScalarValue::UInt16(x) => ScalarValue::UInt16(x + rhs.get_u16().unwrap()),
-            ScalarValue::UInt32(x) => ScalarValue::UInt32(x + rhs.get_u32().unwrap()),
-            ScalarValue::UInt64(x) => ScalarValue::UInt64(x + rhs.get_u64().unwrap()),
-            ScalarValue::Float32(x) => ScalarValue::Float32(x + rhs.get_f32().unwrap()),
-            ScalarValue::Float64(x) => ScalarValue::Float64(x + rhs.get_f64().unwrap()),
-            ScalarValue::Int8(x) => ScalarValue::Int8(x.saturating_add(rhs.get_i8().unwrap())),
-            ScalarValue::Int16(x) => ScalarValue::Int16(x.saturating_add(rhs.get_i16().unwrap())),
-            ScalarValue::Int32(x) => ScalarValue::Int32(x.saturating_add(rhs.get_i32().unwrap())),
-            ScalarValue::Int64(x) => ScalarValue::Int64(x.saturating_add(rhs.get_i64().unwrap())),
-            _ => panic!("Unsupported type for addition"),
+impl ScalarValue {
+    /// Add a value of the same numeric type. Integer overflow is an error unless `mode` is
+    /// `OverflowMode::Wrapping`.
+    pub fn checked_add(&self, rhs: &ScalarValue, mode: OverflowMode) -> Result<ScalarValue> {
+        match (self, rhs) {
+            (ScalarValue::UInt8(a), ScalarValue::UInt8(b)) => {
+                add_scalars(*a, *b, mode, ScalarValue::UInt8)
+            }
+            (ScalarValue::UInt16(a), ScalarValue::UInt16(b)) => {
+                add_scalars(*a, *b, mode, ScalarValue::UInt16)
+            }
+            (ScalarValue::UInt32(a), ScalarValue::UInt32(b)) => {
+                add_scalars(*a, *b, mode, ScalarValue::UInt32)
+            }
+            (ScalarValue::UInt64(a), ScalarValue::UInt64(b)) => {
+                add_scalars(*a, *b, mode, ScalarValue::UInt64)
+            }
+            (ScalarValue::Int8(a), ScalarValue::Int8(b)) => {
+                add_scalars(*a, *b, mode, ScalarValue::Int8)
+            }
+            (ScalarValue::Int16(a), ScalarValue::Int16(b)) => {
+                add_scalars(*a, *b, mode, ScalarValue::Int16)
+            }
+            (ScalarValue::Int32(a), ScalarValue::Int32(b)) => {
+                add_scalars(*a, *b, mode, ScalarValue::Int32)
+            }
+            (ScalarValue::Int64(a), ScalarValue::Int64(b)) => {
+                add_scalars(*a, *b, mode, ScalarValue::Int64)
+            }
+            (ScalarValue::Float32(a), ScalarValue::Float32(b)) => {
+                add_scalars(*a, *b, mode, ScalarValue::Float32)
+            }
+            (ScalarValue::Float64(a), ScalarValue::Float64(b)) => {
+                add_scalars(*a, *b, mode, ScalarValue::Float64)
+            }
+            _ => Err(ExecutionError::type_mismatch(format!(
+                "Cannot add {:?} and {:?}",
+                self, rhs
+            ))),
         }
     }
 }
@@ -378,14 +645,40 @@ impl fmt::Display for ScalarValue {
             ScalarValue::Float32(v) => write!(f, "{}", v),
             ScalarValue::Float64(v) => write!(f, "{}", v),
             ScalarValue::Utf8(ref v) => write!(f, "{}", v),
+            ScalarValue::Binary(ref v) => {
+                for b in v.iter() {
+                    write!(f, "{:02x}", b)?;
+                }
+                Ok(())
+            }
+            ScalarValue::Date32(v) => write!(f, "{}", temporal::format_date(*v)),
+            ScalarValue::Time64(v, unit) => write!(f, "{}", temporal::format_time(*v, *unit)),
+            ScalarValue::Timestamp(v, unit) => {
+                write!(f, "{}", temporal::format_timestamp(*v, *unit))
+            }
+            ScalarValue::Interval(ref v) => write!(f, "{}", v),
+            ScalarValue::Decimal(v, _, scale) => {
+                write!(f, "{}", decimal::format_decimal(*v, *scale))
+            }
             ScalarValue::Struct(ref v) => {
+                write!(f, "{{")?;
                 for i in 0..v.len() {
                     if i > 0 {
                         write!(f, ", ")?;
                     }
                     write!(f, "{}", v[i])?;
                 }
-                Ok(())
+                write!(f, "}}")
+            }
+            ScalarValue::List(ref v, _) => {
+                write!(f, "[")?;
+                for i in 0..v.len() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", v[i])?;
+                }
+                write!(f, "]")
             }
         }
     }
@@ -418,6 +711,53 @@ pub fn get_supertype(l: &DataType, r: &DataType) -> Option<DataType> {
     }
 }
 
+/// Get the type of the result of adding an interval to, or subtracting an interval from, a
+/// date or timestamp, which is the type of the date or timestamp
+pub fn get_interval_arithmetic_type(l: &DataType, r: &DataType) -> Option<DataType> {
+    match (l, r) {
+        (DataType::Date32, DataType::Interval) | (DataType::Timestamp(_), DataType::Interval) => {
+            Some(l.clone())
+        }
+        _ => None,
+    }
+}
+
+/// Get the decimal type that can hold every value of a decimal or integer type
+pub fn get_decimal_operand_type(t: &DataType) -> Option<DataType> {
+    use self::DataType::*;
+    let digits = match t {
+        Decimal(_, _) => return Some(t.clone()),
+        Int8 | UInt8 => 3,
+        Int16 | UInt16 => 5,
+        Int32 | UInt32 => 10,
+        Int64 => 19,
+        UInt64 => 20,
+        _ => return None,
+    };
+    Some(Decimal(digits, 0))
+}
+
+/// Get the type of the result of an arithmetic operation where at least one side is a decimal
+/// and the other is a decimal or an integer
+pub fn get_decimal_arithmetic_type(
+    op: DecimalOperator,
+    l: &DataType,
+    r: &DataType,
+) -> Option<DataType> {
+    match (l, r) {
+        (DataType::Decimal(_, _), _) | (_, DataType::Decimal(_, _)) => {
+            match (get_decimal_operand_type(l), get_decimal_operand_type(r)) {
+                (Some(DataType::Decimal(lp, ls)), Some(DataType::Decimal(rp, rs))) => {
+                    let (precision, scale) = decimal::result_type(op, (lp, ls), (rp, rs));
+                    Some(DataType::Decimal(precision, scale))
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
 fn _get_supertype(l: &DataType, r: &DataType) -> Option<DataType> {
     use self::DataType::*;
     match (l, r) {
@@ -511,9 +851,29 @@ fn _get_supertype(l: &DataType, r: &DataType) -> Option<DataType> {
         (Float64, Float64) => Some(Float64),
 
         (Utf8, Utf8) => Some(Utf8),
+        (Binary, Binary) => Some(Binary),
+
+        (Date32, Date32) => Some(Date32),
+        (Date32, Timestamp(unit)) => Some(Timestamp(*unit)),
+        (Time64(l), Time64(r)) => Some(Time64(*l.max(r))),
+        (Timestamp(l), Timestamp(r)) => Some(Timestamp(*l.max(r))),
+        (Date32, Utf8) => Some(Date32),
+        (Time64(unit), Utf8) => Some(Time64(*unit)),
+        (Timestamp(unit), Utf8) => Some(Timestamp(*unit)),
 
         (Boolean, Boolean) => Some(Boolean),
 
+        (Decimal(_, _), Float32) | (Decimal(_, _), Float64) => Some(Float64),
+        (Decimal(_, _), Utf8) => Some(l.clone()),
+        (Decimal(lp, ls), _) => match get_decimal_operand_type(r) {
+            Some(Decimal(rp, rs)) => {
+                let scale = *ls.max(&rs);
+                let integer_digits = (lp - ls).max(rp - rs);
+                Some(Decimal((integer_digits + scale).min(decimal::MAX_PRECISION), scale))
+            }
+            _ => None,
+        },
+
         _ => None,
     }
 }
@@ -524,6 +884,12 @@ pub trait ScalarFunction {
     fn args(&self) -> Vec<Field>;
     fn return_type(&self) -> DataType;
     fn execute(&self, args: &[Value]) -> Result<Value>;
+
+    /// Whether the function always returns the same result for the same arguments. Calls to
+    /// deterministic functions may be evaluated once and the result reused.
+    fn is_deterministic(&self) -> bool {
+        true
+    }
 }
 
 /// Aggregate function
@@ -535,6 +901,257 @@ pub trait AggregateFunction {
     fn finish(&self) -> Result<Value>;
 }
 
+/// Build a date, time or timestamp array from scalar values of that type
+pub fn build_temporal_array(t: &DataType, values: &Vec<ScalarValue>) -> Result<Array> {
+    let mismatch = |v: &ScalarValue| {
+        ExecutionError::type_mismatch(format!(
+            "Expected value of type {:?}, found {:?}",
+            t, v
+        ))
+    };
+    let data = match t {
+        DataType::Date32 => ArrayData::Date32(PrimitiveArray::from(values
+            .iter()
+            .map(|v| match v {
+                ScalarValue::Date32(d) => Ok(*d),
+                other => Err(mismatch(other)),
+            })
+            .collect::<Result<Vec<i32>>>()?)),
+        DataType::Time64(unit) => ArrayData::Time64(
+            PrimitiveArray::from(values
+                .iter()
+                .map(|v| match v {
+                    ScalarValue::Time64(x, u) if u == unit => Ok(*x),
+                    other => Err(mismatch(other)),
+                })
+                .collect::<Result<Vec<i64>>>()?),
+            *unit,
+        ),
+        DataType::Timestamp(unit) => ArrayData::Timestamp(
+            PrimitiveArray::from(values
+                .iter()
+                .map(|v| match v {
+                    ScalarValue::Timestamp(x, u) if u == unit => Ok(*x),
+                    other => Err(mismatch(other)),
+                })
+                .collect::<Result<Vec<i64>>>()?),
+            *unit,
+        ),
+        other => {
+            return Err(ExecutionError::type_mismatch(format!(
+                "{:?} is not a temporal type",
+                other
+            )))
+        }
+    };
+    Ok(Array::new(values.len(), data))
+}
+
+/// Build a decimal array from decimal values with the scale of the type
+pub fn build_decimal_array(t: &DataType, values: &Vec<ScalarValue>) -> Result<Array> {
+    let (precision, scale) = match t {
+        DataType::Decimal(p, s) => (*p, *s),
+        other => {
+            return Err(ExecutionError::type_mismatch(format!(
+                "{:?} is not a decimal type",
+                other
+            )))
+        }
+    };
+    let decimals = values
+        .iter()
+        .map(|v| match v {
+            ScalarValue::Decimal(x, _, s) if *s == scale => Ok(*x),
+            other => Err(ExecutionError::type_mismatch(format!(
+                "Expected value of type {:?}, found {:?}",
+                t, other
+            ))),
+        })
+        .collect::<Result<Vec<i128>>>()?;
+    let data = ArrayData::Decimal(decimals, precision, scale);
+    Ok(Array::new(values.len(), data))
+}
+
+/// Value stored in the slots of an array of the given type where the values are null
+fn null_placeholder(t: &DataType) -> Result<ScalarValue> {
+    Ok(match t {
+        DataType::Boolean => ScalarValue::Boolean(false),
+        DataType::UInt8 => ScalarValue::UInt8(0),
+        DataType::UInt16 => ScalarValue::UInt16(0),
+        DataType::UInt32 => ScalarValue::UInt32(0),
+        DataType::UInt64 => ScalarValue::UInt64(0),
+        DataType::Int8 => ScalarValue::Int8(0),
+        DataType::Int16 => ScalarValue::Int16(0),
+        DataType::Int32 => ScalarValue::Int32(0),
+        DataType::Int64 => ScalarValue::Int64(0),
+        DataType::Float32 => ScalarValue::Float32(0.0),
+        DataType::Float64 => ScalarValue::Float64(0.0),
+        DataType::Utf8 => ScalarValue::Utf8(Rc::new(String::new())),
+        DataType::Binary => ScalarValue::Binary(Rc::new(vec![])),
+        DataType::Date32 => ScalarValue::Date32(0),
+        DataType::Time64(unit) => ScalarValue::Time64(0, *unit),
+        DataType::Timestamp(unit) => ScalarValue::Timestamp(0, *unit),
+        DataType::Decimal(p, s) => ScalarValue::Decimal(0, *p, *s),
+        DataType::List(element_type) => ScalarValue::List(vec![], element_type.as_ref().clone()),
+        DataType::Struct(fields) => ScalarValue::Struct(
+            fields
+                .iter()
+                .map(|f| null_placeholder(f.data_type()))
+                .collect::<Result<Vec<ScalarValue>>>()?,
+        ),
+        other => {
+            return Err(ExecutionError::type_mismatch(format!(
+                "No support for null values of type {:?}",
+                other
+            )))
+        }
+    })
+}
+
+/// Build an array from scalar values that may be null. The nulls are replaced with
+/// placeholder values of the given type before the array is built with `build`.
+pub fn build_nullable_array<F>(t: &DataType, values: &Vec<ScalarValue>, build: F) -> Result<Array>
+where
+    F: Fn(&Vec<ScalarValue>) -> Result<Array>,
+{
+    let validity: Vec<bool> = values
+        .iter()
+        .map(|v| match v {
+            ScalarValue::Null => false,
+            _ => true,
+        })
+        .collect();
+    if validity.iter().all(|v| *v) {
+        return build(values);
+    }
+    let placeholder = null_placeholder(t)?;
+    let values: Vec<ScalarValue> = values
+        .iter()
+        .map(|v| match v {
+            ScalarValue::Null => placeholder.clone(),
+            other => other.clone(),
+        })
+        .collect();
+    Ok(Array::with_validity(build(&values)?.into_data(), &validity))
+}
+
+/// Get the value of a column at the given row
+pub fn value_at(column: &Value, row: usize) -> ScalarValue {
+    match column {
+        Value::Column(ref array) => get_value(array, row),
+        Value::Scalar(ref v) => v.as_ref().clone(),
+    }
+}
+
+macro_rules! build_array_from_scalars {
+    ($TY:ty, $NAME:ident, $VALUES:expr) => {{
+        let mut b: Builder<$TY> = Builder::with_capacity($VALUES.len());
+        for v in $VALUES {
+            b.push(v.$NAME()?);
+        }
+        Array::from(b.finish())
+    }};
+}
+
+/// Create an array of the given type from a list of scalar values
+pub fn array_from_scalars(data_type: &DataType, values: &Vec<ScalarValue>) -> Result<Array> {
+    Ok(match data_type {
+        DataType::Boolean => build_array_from_scalars!(bool, get_bool, values),
+        DataType::UInt8 => build_array_from_scalars!(u8, get_u8, values),
+        DataType::UInt16 => build_array_from_scalars!(u16, get_u16, values),
+        DataType::UInt32 => build_array_from_scalars!(u32, get_u32, values),
+        DataType::UInt64 => build_array_from_scalars!(u64, get_u64, values),
+        DataType::Int8 => build_array_from_scalars!(i8, get_i8, values),
+        DataType::Int16 => build_array_from_scalars!(i16, get_i16, values),
+        DataType::Int32 => build_array_from_scalars!(i32, get_i32, values),
+        DataType::Int64 => build_array_from_scalars!(i64, get_i64, values),
+        DataType::Float32 => build_array_from_scalars!(f32, get_f32, values),
+        DataType::Float64 => build_array_from_scalars!(f64, get_f64, values),
+        DataType::Utf8 => {
+            let mut strings: Vec<String> = Vec::with_capacity(values.len());
+            for v in values {
+                strings.push(v.get_string()?.clone());
+            }
+            Array::from(strings)
+        }
+        DataType::Binary => {
+            let mut bytes: Vec<Vec<u8>> = Vec::with_capacity(values.len());
+            for v in values {
+                bytes.push(v.get_binary()?.clone());
+            }
+            Array::from(bytes)
+        }
+        DataType::Date32 | DataType::Time64(_) | DataType::Timestamp(_) => {
+            build_temporal_array(data_type, values)?
+        }
+        DataType::Decimal(_, _) => build_decimal_array(data_type, values)?,
+        DataType::List(_) => build_list_array(data_type, values)?,
+        DataType::Struct(_) => build_struct_array(data_type, values)?,
+        other => {
+            return Err(ExecutionError::type_mismatch(format!(
+                "Cannot build an array of type {:?} from scalar values",
+                other
+            )))
+        }
+    })
+}
+
+/// Build a list array from list values, where the elements of the lists may be null
+pub fn build_list_array(t: &DataType, values: &Vec<ScalarValue>) -> Result<Array> {
+    let element_type = match t {
+        DataType::List(element_type) => element_type.as_ref(),
+        other => {
+            return Err(ExecutionError::type_mismatch(format!(
+                "{:?} is not a list type",
+                other
+            )))
+        }
+    };
+    let mut offsets: Vec<usize> = Vec::with_capacity(values.len() + 1);
+    let mut elements: Vec<ScalarValue> = vec![];
+    offsets.push(0);
+    for v in values {
+        elements.extend(v.get_list()?.iter().cloned());
+        offsets.push(elements.len());
+    }
+    let child = build_nullable_array(element_type, &elements, |values| {
+        array_from_scalars(element_type, values)
+    })?;
+    Ok(Array::new(values.len(), ArrayData::List(offsets, Rc::new(child))))
+}
+
+/// Build a struct array from struct values, where the fields of the structs may be null
+pub fn build_struct_array(t: &DataType, values: &Vec<ScalarValue>) -> Result<Array> {
+    let fields = match t {
+        DataType::Struct(fields) => fields,
+        other => {
+            return Err(ExecutionError::type_mismatch(format!(
+                "{:?} is not a struct type",
+                other
+            )))
+        }
+    };
+    let mut children: Vec<Rc<Array>> = Vec::with_capacity(fields.len());
+    for (i, field) in fields.iter().enumerate() {
+        let field_values = values
+            .iter()
+            .map(|v| match v.get_struct()?.get(i) {
+                Some(field_value) => Ok(field_value.clone()),
+                None => Err(ExecutionError::type_mismatch(format!(
+                    "Expected value of type {:?}, found {:?}",
+                    t, v
+                ))),
+            })
+            .collect::<Result<Vec<ScalarValue>>>()?;
+        let field_type = field.data_type();
+        let child = build_nullable_array(field_type, &field_values, |values| {
+            array_from_scalars(field_type, values)
+        })?;
+        children.push(Rc::new(child));
+    }
+    Ok(Array::new(values.len(), ArrayData::Struct(children)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -553,6 +1170,26 @@ mod tests {
         assert_eq!("[array with length 3]", str);
     }
 
+    #[test]
+    fn test_scalar_checked_add() {
+        let a = ScalarValue::Int8(100);
+        let b = ScalarValue::Int8(28);
+        assert!(a.checked_add(&b, OverflowMode::Checked).is_err());
+        assert_eq!(
+            ScalarValue::Int8(-128),
+            a.checked_add(&b, OverflowMode::Wrapping).unwrap()
+        );
+        assert_eq!(
+            ScalarValue::UInt64(3),
+            ScalarValue::UInt64(1)
+                .checked_add(&ScalarValue::UInt64(2), OverflowMode::Checked)
+                .unwrap()
+        );
+        assert!(a
+            .checked_add(&ScalarValue::Int16(1), OverflowMode::Checked)
+            .is_err());
+    }
+
     #[test]
     fn test_scalar_fmt_display() {
         let values: Vec<ScalarValue> = vec![
@@ -594,8 +1231,73 @@ mod tests {
 
         assert_eq!(
             "NULL\ntrue\n123\n123\n123\n123\n-123\n-123\n-123\n-123\n1.23\n1.23\
-             \nHello\nNULL, false, 55, 55, 55, 55, -55, -55, -55, -55, 5.5, 5.5, Hello",
+             \nHello\n{NULL, false, 55, 55, 55, 55, -55, -55, -55, -55, 5.5, 5.5, Hello}",
+            str
+        );
+    }
+    #[test]
+    fn test_temporal_scalar_fmt_display() {
+        let values: Vec<ScalarValue> = vec![
+            ScalarValue::Date32(17532),
+            ScalarValue::Time64(45_296_000_000, TimeUnit::Microsecond),
+            ScalarValue::Timestamp(1514809845, TimeUnit::Second),
+            ScalarValue::Timestamp(1514809845123, TimeUnit::Millisecond),
+            ScalarValue::Interval(Interval::new(1, 2, 3_600_000_000_000)),
+        ];
+
+        let str = values
+            .iter()
+            .map(|v| format!("{}", v))
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        assert_eq!(
+            "2018-01-01\n12:34:56\n2018-01-01 12:30:45\n2018-01-01 12:30:45.123\
+             \n1 mon 2 days 01:00:00",
             str
         );
     }
+
+    #[test]
+    fn test_temporal_supertypes() {
+        use self::DataType::*;
+        assert_eq!(
+            Some(Timestamp(TimeUnit::Nanosecond)),
+            get_supertype(
+                &Timestamp(TimeUnit::Millisecond),
+                &Timestamp(TimeUnit::Nanosecond)
+            )
+        );
+        assert_eq!(
+            Some(Timestamp(TimeUnit::Second)),
+            get_supertype(&Timestamp(TimeUnit::Second), &Date32)
+        );
+        assert_eq!(Some(Date32), get_supertype(&Utf8, &Date32));
+        assert_eq!(None, get_supertype(&Date32, &Interval));
+        assert_eq!(
+            Some(Date32),
+            get_interval_arithmetic_type(&Date32, &Interval)
+        );
+        assert!(can_coerce_from(&Timestamp(TimeUnit::Microsecond), &Date32));
+        assert!(!can_coerce_from(
+            &Timestamp(TimeUnit::Second),
+            &Timestamp(TimeUnit::Millisecond)
+        ));
+    }
+
+    #[test]
+    fn test_decimal_supertypes() {
+        use self::DataType::*;
+        assert_eq!(
+            Some(Decimal(12, 4)),
+            get_supertype(&Decimal(10, 2), &Decimal(8, 4))
+        );
+        assert_eq!(Some(Float64), get_supertype(&Decimal(10, 2), &Float32));
+        assert!(can_coerce_from(&Decimal(12, 2), &Int32));
+        assert!(!can_coerce_from(&Decimal(5, 2), &Int32));
+        assert_eq!(
+            "12.34",
+            format!("{}", ScalarValue::Decimal(1234, 10, 2))
+        );
+    }
 }
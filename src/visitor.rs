@@ -0,0 +1,309 @@
+// Copyright 2018 Grove Enterprises LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Visitors and rewriters for logical plans and expressions, so that passes over a plan do not
+//! need to match every variant to recurse into children
+
+use std::rc::Rc;
+
+use super::errors::*;
+use super::logical::*;
+
+/// Returned by visitor hooks to control how the traversal continues
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recursion {
+    /// Continue the traversal
+    Continue,
+    /// Do not visit the children of the current node (only meaningful from `pre_visit`)
+    SkipChildren,
+    /// Stop the traversal immediately
+    Stop,
+}
+
+/// Visits the nodes of an expression tree
+pub trait ExprVisitor {
+    /// Invoked before the children of `expr` are visited
+    fn pre_visit(&mut self, _expr: &Expr) -> Result<Recursion> {
+        Ok(Recursion::Continue)
+    }
+
+    /// Invoked after the children of `expr` have been visited
+    fn post_visit(&mut self, _expr: &Expr) -> Result<Recursion> {
+        Ok(Recursion::Continue)
+    }
+}
+
+/// Visits the nodes of a logical plan tree
+pub trait PlanVisitor {
+    /// Invoked before the inputs of `plan` are visited
+    fn pre_visit(&mut self, _plan: &LogicalPlan) -> Result<Recursion> {
+        Ok(Recursion::Continue)
+    }
+
+    /// Invoked after the inputs of `plan` have been visited
+    fn post_visit(&mut self, _plan: &LogicalPlan) -> Result<Recursion> {
+        Ok(Recursion::Continue)
+    }
+}
+
+/// Rewrites an expression tree from the bottom up
+pub trait ExprRewriter {
+    /// Invoked before the children of `expr` are rewritten. Returning `SkipChildren` leaves the
+    /// children unchanged and `Stop` leaves the rest of the tree unchanged.
+    fn pre_visit(&mut self, _expr: &Expr) -> Result<Recursion> {
+        Ok(Recursion::Continue)
+    }
+
+    /// Invoked after the children of `expr` have been rewritten, returning the replacement
+    fn mutate(&mut self, expr: Expr) -> Result<Expr>;
+}
+
+/// Rewrites a logical plan tree from the bottom up
+pub trait PlanRewriter {
+    /// Invoked before the inputs of `plan` are rewritten. Returning `SkipChildren` leaves the
+    /// inputs unchanged and `Stop` leaves the rest of the tree unchanged.
+    fn pre_visit(&mut self, _plan: &LogicalPlan) -> Result<Recursion> {
+        Ok(Recursion::Continue)
+    }
+
+    /// Invoked after the inputs of `plan` have been rewritten, returning the replacement
+    fn mutate(&mut self, plan: Rc<LogicalPlan>) -> Result<Rc<LogicalPlan>>;
+}
+
+impl Expr {
+    /// Walk the expression tree depth first, calling the visitor's hooks on each node. Returns
+    /// `Recursion::Stop` if the traversal was stopped early.
+    pub fn accept<V: ExprVisitor>(&self, visitor: &mut V) -> Result<Recursion> {
+        match visitor.pre_visit(self)? {
+            Recursion::Stop => return Ok(Recursion::Stop),
+            Recursion::SkipChildren => {}
+            Recursion::Continue => for child in self.children() {
+                if child.accept(visitor)? == Recursion::Stop {
+                    return Ok(Recursion::Stop);
+                }
+            },
+        }
+        match visitor.post_visit(self)? {
+            Recursion::Stop => Ok(Recursion::Stop),
+            _ => Ok(Recursion::Continue),
+        }
+    }
+}
+
+impl LogicalPlan {
+    /// Walk the plan tree depth first, calling the visitor's hooks on each node. Returns
+    /// `Recursion::Stop` if the traversal was stopped early.
+    pub fn accept<V: PlanVisitor>(&self, visitor: &mut V) -> Result<Recursion> {
+        match visitor.pre_visit(self)? {
+            Recursion::Stop => return Ok(Recursion::Stop),
+            Recursion::SkipChildren => {}
+            Recursion::Continue => for input in self.inputs() {
+                if input.accept(visitor)? == Recursion::Stop {
+                    return Ok(Recursion::Stop);
+                }
+            },
+        }
+        match visitor.post_visit(self)? {
+            Recursion::Stop => Ok(Recursion::Stop),
+            _ => Ok(Recursion::Continue),
+        }
+    }
+}
+
+/// Rewrite an expression with the given rewriter
+pub fn rewrite_expr<R: ExprRewriter>(expr: &Expr, rewriter: &mut R) -> Result<Expr> {
+    let mut stopped = false;
+    rewrite_expr_internal(expr, rewriter, &mut stopped)
+}
+
+fn rewrite_expr_internal<R: ExprRewriter>(
+    expr: &Expr,
+    rewriter: &mut R,
+    stopped: &mut bool,
+) -> Result<Expr> {
+    if *stopped {
+        return Ok(expr.clone());
+    }
+    let expr = match rewriter.pre_visit(expr)? {
+        Recursion::Stop => {
+            *stopped = true;
+            return Ok(expr.clone());
+        }
+        Recursion::SkipChildren => expr.clone(),
+        Recursion::Continue => {
+            let mut children: Vec<Expr> = vec![];
+            for child in expr.children() {
+                children.push(rewrite_expr_internal(child, rewriter, stopped)?);
+            }
+            expr.with_new_children(children)?
+        }
+    };
+    if *stopped {
+        Ok(expr)
+    } else {
+        rewriter.mutate(expr)
+    }
+}
+
+/// Rewrite a logical plan with the given rewriter
+pub fn rewrite_plan<R: PlanRewriter>(
+    plan: &Rc<LogicalPlan>,
+    rewriter: &mut R,
+) -> Result<Rc<LogicalPlan>> {
+    let mut stopped = false;
+    rewrite_plan_internal(plan, rewriter, &mut stopped)
+}
+
+fn rewrite_plan_internal<R: PlanRewriter>(
+    plan: &Rc<LogicalPlan>,
+    rewriter: &mut R,
+    stopped: &mut bool,
+) -> Result<Rc<LogicalPlan>> {
+    if *stopped {
+        return Ok(plan.clone());
+    }
+    let plan = match rewriter.pre_visit(plan)? {
+        Recursion::Stop => {
+            *stopped = true;
+            return Ok(plan.clone());
+        }
+        Recursion::SkipChildren => plan.clone(),
+        Recursion::Continue => {
+            let mut inputs: Vec<Rc<LogicalPlan>> = vec![];
+            for input in plan.inputs() {
+                inputs.push(rewrite_plan_internal(input, rewriter, stopped)?);
+            }
+            if inputs.is_empty() {
+                plan.clone()
+            } else {
+                Rc::new(plan.with_new_inputs(inputs)?)
+            }
+        }
+    };
+    if *stopped {
+        Ok(plan)
+    } else {
+        rewriter.mutate(plan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::types::*;
+    use super::*;
+
+    use super::super::datatypes::*;
+
+    /// Collects the names of all referenced columns, stopping after `limit` columns
+    struct ColumnNames {
+        names: Vec<String>,
+        limit: usize,
+    }
+
+    impl ExprVisitor for ColumnNames {
+        fn pre_visit(&mut self, expr: &Expr) -> Result<Recursion> {
+            if let Expr::ColumnRef(c) = expr {
+                self.names.push(c.flat_name());
+            }
+            if self.names.len() == self.limit {
+                Ok(Recursion::Stop)
+            } else {
+                Ok(Recursion::Continue)
+            }
+        }
+    }
+
+    #[test]
+    fn visit_expr() {
+        let expr = col("a").eq(&col("b")).eq(&col("c"));
+
+        let mut visitor = ColumnNames {
+            names: vec![],
+            limit: 10,
+        };
+        assert_eq!(Recursion::Continue, expr.accept(&mut visitor).unwrap());
+        assert_eq!(vec!["a", "b", "c"], visitor.names);
+
+        let mut visitor = ColumnNames {
+            names: vec![],
+            limit: 2,
+        };
+        assert_eq!(Recursion::Stop, expr.accept(&mut visitor).unwrap());
+        assert_eq!(vec!["a", "b"], visitor.names);
+    }
+
+    /// Renames every column reference
+    struct Rename {}
+
+    impl ExprRewriter for Rename {
+        fn mutate(&mut self, expr: Expr) -> Result<Expr> {
+            match expr {
+                Expr::ColumnRef(c) => Ok(col(&format!("{}_renamed", c.name))),
+                other => Ok(other),
+            }
+        }
+    }
+
+    #[test]
+    fn rewrite_expression() {
+        let expr = Expr::Cast {
+            expr: Rc::new(col("a").lt(&col("b"))),
+            data_type: DataType::Boolean,
+        };
+        let rewritten = rewrite_expr(&expr, &mut Rename {}).unwrap();
+        assert_eq!("CAST(#a_renamed Lt #b_renamed AS Boolean)", format!("{:?}", rewritten));
+    }
+
+    /// Adds a filter above every scan of a table, e.g. for row-level security
+    struct SecurityFilter {}
+
+    impl PlanRewriter for SecurityFilter {
+        fn mutate(&mut self, plan: Rc<LogicalPlan>) -> Result<Rc<LogicalPlan>> {
+            match plan.as_ref() {
+                LogicalPlan::TableScan { .. } => Ok(Rc::new(LogicalPlan::Selection {
+                    expr: col("tenant").eq(&lit(ScalarValue::Int32(1))),
+                    input: plan.clone(),
+                })),
+                _ => Ok(plan.clone()),
+            }
+        }
+    }
+
+    #[test]
+    fn rewrite_plan_inject_filter() {
+        let scan = Rc::new(LogicalPlan::TableScan {
+            schema_name: "default".to_string(),
+            table_name: "t".to_string(),
+            schema: Rc::new(Schema::new(vec![
+                Field::new("id", DataType::Int32, false),
+                Field::new("tenant", DataType::Int32, false),
+            ])),
+            projection: None,
+            fields: vec![],
+        });
+        let plan = Rc::new(LogicalPlan::Limit {
+            limit: 5,
+            schema: scan.schema().clone(),
+            input: scan,
+        });
+
+        let rewritten = rewrite_plan(&plan, &mut SecurityFilter {}).unwrap();
+        assert_eq!(
+            "Limit: 5\
+             \n  Selection: #tenant Eq Int32(1)\
+             \n    TableScan: t projection=None",
+            format!("{:?}", rewritten)
+        );
+    }
+}
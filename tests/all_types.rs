@@ -1,10 +1,9 @@
-extern crate arrow;
 extern crate datafusion;
 
 use std::fs::File;
 use std::io::prelude::*;
 
-use arrow::datatypes::*;
+use datafusion::datatypes::*;
 use datafusion::exec::*;
 
 #[test]